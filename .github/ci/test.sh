@@ -13,16 +13,20 @@ export CARGO_TARGET_DIR=/ci/cache/target
 export CARGO_NET_GIT_FETCH_WITH_CLI=true
 
 cargo test --manifest-path ./embassy-executor/Cargo.toml
+cargo test --manifest-path ./embassy-executor/Cargo.toml --features spawn-wait
 cargo test --manifest-path ./embassy-futures/Cargo.toml
 cargo test --manifest-path ./embassy-sync/Cargo.toml
 cargo test --manifest-path ./embassy-embedded-hal/Cargo.toml
 cargo test --manifest-path ./embassy-hal-internal/Cargo.toml
 cargo test --manifest-path ./embassy-time/Cargo.toml --features mock-driver,embassy-time-queue-utils/generic-queue-8
 cargo test --manifest-path ./embassy-time-driver/Cargo.toml
+cargo test --manifest-path ./embassy-time-queue-utils/Cargo.toml --features generic-queue-8
+cargo test --manifest-path ./embassy-net/Cargo.toml --features dhcpv4-server,mdns-responder,slaac,raw-ethernet,rx-error-statistics,sntp
 
 cargo test --manifest-path ./embassy-boot/Cargo.toml
 cargo test --manifest-path ./embassy-boot/Cargo.toml --features ed25519-dalek
 cargo test --manifest-path ./embassy-boot/Cargo.toml --features ed25519-salty
+cargo test --manifest-path ./embassy-boot/Cargo.toml --features ecdsa-p256
 
 cargo test --manifest-path ./embassy-nrf/Cargo.toml --no-default-features --features nrf52840,time-driver-rtc1,gpiote
 
@@ -5,8 +5,10 @@ mod fmt;
 
 pub use embassy_boot::{
     AlignedBuffer, BlockingFirmwareState, BlockingFirmwareUpdater, BootError, BootLoaderConfig, FirmwareState,
-    FirmwareUpdater, FirmwareUpdaterConfig, State,
+    FirmwareUpdater, FirmwareUpdaterConfig, FirmwareUpdaterError, State,
 };
+use embassy_stm32::wdg::{self, IndependentWatchdog};
+use embassy_stm32::Peri;
 use embedded_storage::nor_flash::NorFlash;
 
 /// A bootloader for STM32 devices.
@@ -39,6 +41,25 @@ impl BootLoader {
         Ok(Self { state })
     }
 
+    /// Start the independent watchdog if a new application is about to be booted for the first time.
+    ///
+    /// Call this right before [`load`](Self::load). If the new application hangs before calling
+    /// [`BootWatchdog::mark_booted`], the watchdog resets the device and the bootloader reverts to the
+    /// previous application. The watchdog can't be stopped once started, so the new application must
+    /// keep petting it with [`BootWatchdog`], even after marking the boot successful.
+    ///
+    /// The watchdog isn't started on regular boots. Returns whether the watchdog was started.
+    pub fn arm_watchdog(&self, wdg: Peri<'static, impl wdg::Instance>, timeout_us: u32) -> bool {
+        if self.state != State::Swap {
+            return false;
+        }
+
+        trace!("Arming watchdog with {}us timeout", timeout_us);
+        let mut wdg = IndependentWatchdog::new(wdg, timeout_us);
+        wdg.unleash();
+        true
+    }
+
     /// Boots the application.
     ///
     /// # Safety
@@ -55,3 +76,66 @@ impl BootLoader {
         cortex_m::asm::bootload(start as *const u32)
     }
 }
+
+/// Boot the update written to the inactive flash bank, by swapping the flash banks.
+///
+/// On dual-bank chips, this is a zero-copy alternative to [`FirmwareUpdater::mark_updated`]: the
+/// update is written with a [`FirmwareUpdater`] or [`BlockingFirmwareUpdater`] whose DFU partition
+/// is the inactive bank, and is mapped at the start of the flash instead of being copied to the
+/// active partition. Each bank must hold a complete firmware, bootloader included, as the
+/// bootloader is swapped too. There is no trial boot: to roll back, swap the banks again.
+///
+/// See [`embassy_stm32::flash::perform_bank_swap`] for when the swap takes effect.
+#[cfg(feature = "bank-swap")]
+pub fn mark_updated_bank_swap() {
+    embassy_stm32::flash::perform_bank_swap();
+}
+
+/// Get whether the flash banks are swapped, that is whether the running firmware is the one
+/// written to bank 2.
+#[cfg(feature = "bank-swap")]
+pub fn banks_swapped() -> bool {
+    embassy_stm32::flash::banks_swapped()
+}
+
+/// Application side of a trial boot with the watchdog armed by [`BootLoader::arm_watchdog`].
+///
+/// Creating the `BootWatchdog` reconfigures the running watchdog with the application's own
+/// timeout, and starts it if the bootloader didn't. The application must then pet it periodically
+/// for as long as it runs, and mark the boot successful once its self-tests passed.
+pub struct BootWatchdog<'d, T: wdg::Instance> {
+    wdg: IndependentWatchdog<'d, T>,
+}
+
+impl<'d, T: wdg::Instance> BootWatchdog<'d, T> {
+    /// Take over the watchdog with the given timeout.
+    pub fn new(wdg: Peri<'d, T>, timeout_us: u32) -> Self {
+        let mut wdg = IndependentWatchdog::new(wdg, timeout_us);
+        wdg.unleash();
+        wdg.pet();
+        Self { wdg }
+    }
+
+    /// Pet the watchdog.
+    pub fn pet(&mut self) {
+        self.wdg.pet();
+    }
+
+    /// Mark the boot successful, so that the next reset doesn't revert to the previous application.
+    pub fn mark_booted<STATE: NorFlash>(
+        &mut self,
+        state: &mut BlockingFirmwareState<'_, STATE>,
+    ) -> Result<(), FirmwareUpdaterError> {
+        self.wdg.pet();
+        state.mark_booted()
+    }
+
+    /// Mark the boot successful, so that the next reset doesn't revert to the previous application.
+    pub async fn mark_booted_async<STATE: embedded_storage_async::nor_flash::NorFlash>(
+        &mut self,
+        state: &mut FirmwareState<'_, STATE>,
+    ) -> Result<(), FirmwareUpdaterError> {
+        self.wdg.pet();
+        state.mark_booted().await
+    }
+}
@@ -5,7 +5,10 @@ use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embassy_sync::blocking_mutex::Mutex;
 use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
 
-use crate::{State, DFU_DETACH_MAGIC, REVERT_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC};
+use crate::delta::{Action, Adler32, DeltaPatcher};
+use crate::{
+    ImageHeader, State, BOOT_MAGIC, DFU_DETACH_MAGIC, PATCH_MAGIC, REVERT_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC,
+};
 
 /// Errors returned by bootloader
 #[derive(PartialEq, Eq, Debug)]
@@ -14,6 +17,8 @@ pub enum BootError {
     Flash(NorFlashErrorKind),
     /// Invalid bootloader magic
     BadMagic,
+    /// No slot holds a bootable image.
+    NoValidSlot,
 }
 
 #[cfg(feature = "defmt")]
@@ -22,6 +27,7 @@ impl defmt::Format for BootError {
         match self {
             BootError::Flash(_) => defmt::write!(fmt, "BootError::Flash(_)"),
             BootError::BadMagic => defmt::write!(fmt, "BootError::BadMagic"),
+            BootError::NoValidSlot => defmt::write!(fmt, "BootError::NoValidSlot"),
         }
     }
 }
@@ -234,6 +240,20 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
     /// |    Active |            3 |      1 |      2 |      3 |      - |
     /// |       DFU |            3 |      4 |      5 |      6 |      3 |
     ///
+    /// ## PATCHING
+    ///
+    /// When the application stored a delta patch at the start of the DFU partition and marked it
+    /// with `mark_patched`, the new image is first reconstructed from the patch and the active
+    /// image, before being swapped in as above. The image is reconstructed at the end of the DFU
+    /// partition, in the last N pages where N is the number of pages of the active partition, so
+    /// the patch must fit in the pages before them. Neither the patch nor the active image are
+    /// modified by this step, so it is restarted from scratch on power failure. Once the image is
+    /// complete and its checksum verified, it is moved page by page to the start of the DFU
+    /// partition, using the progress index.
+    ///
+    /// If the patch is malformed or does not apply to the active image, it is dropped and the
+    /// active image is booted.
+    ///
     pub fn prepare_boot(&mut self, aligned_buf: &mut [u8]) -> Result<State, BootError> {
         const {
             core::assert!(Self::PAGE_SIZE % ACTIVE::WRITE_SIZE as u32 == 0);
@@ -251,6 +271,13 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
         // Ensure our partitions are able to handle boot operations
         assert_partitions(&self.active, &self.dfu, &self.state, Self::PAGE_SIZE);
 
+        // Turn a pending patch into a swap
+        if self.is_patch_pending(aligned_buf)? {
+            trace!("Patching");
+            self.apply_patch(aligned_buf)?;
+            trace!("Patching done");
+        }
+
         // Copy contents from partition N to active
         let state = self.read_state(aligned_buf)?;
         if state == State::Swap {
@@ -265,24 +292,27 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
             } else {
                 trace!("Reverting");
                 self.revert(aligned_buf)?;
-
-                let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
-
-                // Invalidate progress
-                state_word.fill(!STATE_ERASE_VALUE);
-                self.state.write(STATE::WRITE_SIZE as u32, state_word)?;
-
-                // Clear magic and progress
-                self.state.erase(0, self.state.capacity() as u32)?;
-
-                // Set magic
-                state_word.fill(REVERT_MAGIC);
-                self.state.write(0, state_word)?;
+                self.set_magic(REVERT_MAGIC, aligned_buf)?;
             }
         }
         Ok(state)
     }
 
+    /// Read the [`ImageHeader`] of the image in the active partition, if any.
+    pub fn current_header(&mut self) -> Result<Option<ImageHeader>, BootError> {
+        let active_size = self.active.capacity();
+        Ok(ImageHeader::read_blocking(&mut self.active, active_size)?)
+    }
+
+    /// Read the [`ImageHeader`] of the update in the DFU partition, if any.
+    ///
+    /// This is only meaningful until the update has been swapped in, after which the DFU partition
+    /// holds the previous image, shifted by one page.
+    pub fn pending_header(&mut self) -> Result<Option<ImageHeader>, BootError> {
+        let active_size = self.active.capacity();
+        Ok(ImageHeader::read_blocking(&mut self.dfu, active_size)?)
+    }
+
     fn is_swapped(&mut self, aligned_buf: &mut [u8]) -> Result<bool, BootError> {
         let page_count = self.active.capacity() / Self::PAGE_SIZE as usize;
         let progress = self.current_progress(aligned_buf)?;
@@ -403,6 +433,161 @@ impl<ACTIVE: NorFlash, DFU: NorFlash, STATE: NorFlash> BootLoader<ACTIVE, DFU, S
         Ok(())
     }
 
+    fn is_patch_pending(&mut self, aligned_buf: &mut [u8]) -> Result<bool, BootError> {
+        let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
+        self.state.read(0, state_word)?;
+        Ok(!state_word.iter().any(|&b| b != PATCH_MAGIC))
+    }
+
+    fn apply_patch(&mut self, aligned_buf: &mut [u8]) -> Result<(), BootError> {
+        // Progress index 0 marks the image as reconstructed, the next ones track the move.
+        if self.current_progress(aligned_buf)? == 0 {
+            if !self.reconstruct_patched_image(aligned_buf)? {
+                warn!("Invalid patch, booting the active image");
+                return self.set_magic(BOOT_MAGIC, aligned_buf);
+            }
+            self.update_progress(0, aligned_buf)?;
+        }
+
+        let page_count = self.active.capacity() as u32 / Self::PAGE_SIZE;
+        let image_offset = self.dfu.capacity() as u32 - self.active.capacity() as u32;
+        for page_num in 0..page_count {
+            let offset = page_num * Self::PAGE_SIZE;
+            self.copy_page_once_within_dfu(1 + page_num as usize, image_offset + offset, offset, aligned_buf)?;
+        }
+
+        self.set_magic(SWAP_MAGIC, aligned_buf)
+    }
+
+    /// Apply the patch at the start of the DFU partition to the active image, writing the new image
+    /// to the last pages of the DFU partition. Returns `false` if the patch is invalid.
+    fn reconstruct_patched_image(&mut self, aligned_buf: &mut [u8]) -> Result<bool, BootError> {
+        assert_eq!(ACTIVE::READ_SIZE, 1);
+
+        let image_offset = self.dfu.capacity() as u32 - self.active.capacity() as u32;
+        self.dfu.erase(image_offset, self.dfu.capacity() as u32)?;
+
+        let mut patcher = DeltaPatcher::new(aligned_buf);
+        let mut patch_buf = [0; 32];
+        let mut source_buf = [0; 32];
+        let mut patch_offset = 0;
+        while patcher.target_len() != Some(patcher.written()) {
+            // The patch must not run into the new image.
+            if patch_offset >= image_offset {
+                return Ok(false);
+            }
+            let n = core::cmp::min(patch_buf.len() as u32, image_offset - patch_offset) as usize;
+            self.dfu.read(patch_offset, &mut patch_buf[..n])?;
+            patch_offset += n as u32;
+
+            let mut patch = &patch_buf[..n];
+            while !patch.is_empty() && patcher.target_len() != Some(patcher.written()) {
+                let Ok((consumed, action)) = patcher.parse(patch) else {
+                    return Ok(false);
+                };
+                patch = &patch[consumed..];
+
+                match action {
+                    Some(Action::VerifySource { len, checksum }) => {
+                        let capacity = self.active.capacity() as u32;
+                        if len > capacity || patcher.target_len().is_some_and(|target_len| target_len > capacity) {
+                            return Ok(false);
+                        }
+                        let mut adler = Adler32::new();
+                        for offset in (0..len).step_by(source_buf.len()) {
+                            let n = core::cmp::min((len - offset) as usize, source_buf.len());
+                            self.active.read(offset, &mut source_buf[..n])?;
+                            adler.update(&source_buf[..n]);
+                        }
+                        if adler.finish() != checksum {
+                            return Ok(false);
+                        }
+                    }
+                    Some(Action::Copy { offset, len }) => {
+                        let end = offset + len;
+                        for offset in (offset..end).step_by(source_buf.len()) {
+                            let n = core::cmp::min((end - offset) as usize, source_buf.len());
+                            self.active.read(offset, &mut source_buf[..n])?;
+                            if !Self::write_patched_image(&mut self.dfu, image_offset, &mut patcher, &source_buf[..n])?
+                            {
+                                return Ok(false);
+                            }
+                        }
+                    }
+                    Some(Action::Insert(data)) => {
+                        let written = Self::write_patched_image(&mut self.dfu, image_offset, &mut patcher, data)?;
+                        if !written {
+                            return Ok(false);
+                        }
+                    }
+                    None => {}
+                }
+            }
+        }
+
+        let Ok((offset, output)) = patcher.finish(DFU::WRITE_SIZE) else {
+            return Ok(false);
+        };
+        self.dfu.write(image_offset + offset as u32, output)?;
+        Ok(true)
+    }
+
+    fn write_patched_image(
+        dfu: &mut DFU,
+        image_offset: u32,
+        patcher: &mut DeltaPatcher<'_>,
+        mut data: &[u8],
+    ) -> Result<bool, BootError> {
+        while !data.is_empty() {
+            let Ok(n) = patcher.buffer(data) else {
+                return Ok(false);
+            };
+            data = &data[n..];
+            if let Some((offset, output)) = patcher.take_full() {
+                dfu.write(image_offset + offset as u32, output)?;
+            }
+        }
+        Ok(true)
+    }
+
+    fn copy_page_once_within_dfu(
+        &mut self,
+        progress_index: usize,
+        from_offset: u32,
+        to_offset: u32,
+        aligned_buf: &mut [u8],
+    ) -> Result<(), BootError> {
+        if self.current_progress(aligned_buf)? <= progress_index {
+            let page_size = Self::PAGE_SIZE;
+
+            self.dfu.erase(to_offset, to_offset + page_size)?;
+
+            for offset_in_page in (0..page_size).step_by(aligned_buf.len()) {
+                self.dfu.read(from_offset + offset_in_page, aligned_buf)?;
+                self.dfu.write(to_offset + offset_in_page, aligned_buf)?;
+            }
+
+            self.update_progress(progress_index, aligned_buf)?;
+        }
+        Ok(())
+    }
+
+    fn set_magic(&mut self, magic: u8, aligned_buf: &mut [u8]) -> Result<(), BootError> {
+        let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
+
+        // Invalidate progress
+        state_word.fill(!STATE_ERASE_VALUE);
+        self.state.write(STATE::WRITE_SIZE as u32, state_word)?;
+
+        // Clear magic and progress
+        self.state.erase(0, self.state.capacity() as u32)?;
+
+        // Set magic
+        state_word.fill(magic);
+        self.state.write(0, state_word)?;
+        Ok(())
+    }
+
     fn read_state(&mut self, aligned_buf: &mut [u8]) -> Result<State, BootError> {
         let state_word = &mut aligned_buf[..STATE::WRITE_SIZE];
         self.state.read(0, state_word)?;
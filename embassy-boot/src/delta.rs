@@ -0,0 +1,233 @@
+use crate::STATE_ERASE_VALUE;
+
+const PATCH_MAGIC: u32 = 0x4544_4C54;
+const HEADER_SIZE: usize = 20;
+const OP_COPY: u8 = 0;
+const OP_INSERT: u8 = 1;
+
+/// Adler-32 checksum, used to check the source and target images of a patch.
+pub(crate) struct Adler32 {
+    a: u32,
+    b: u32,
+}
+
+impl Adler32 {
+    const MOD: u32 = 65521;
+
+    pub(crate) fn new() -> Self {
+        Self { a: 1, b: 0 }
+    }
+
+    pub(crate) fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            self.a = (self.a + byte as u32) % Self::MOD;
+            self.b = (self.b + self.a) % Self::MOD;
+        }
+    }
+
+    pub(crate) fn finish(&self) -> u32 {
+        (self.b << 16) | self.a
+    }
+}
+
+/// Step of a patch, returned by [`DeltaPatcher::parse`].
+pub(crate) enum Action<'p> {
+    /// Check that the first `len` bytes of the source image have the given Adler-32 checksum.
+    VerifySource { len: u32, checksum: u32 },
+    /// Copy `len` bytes of the source image at `offset` to the output.
+    Copy { offset: u32, len: u32 },
+    /// Copy the bytes from the patch to the output.
+    Insert(&'p [u8]),
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ParseState {
+    Header,
+    Op,
+    CopyArgs,
+    InsertLen,
+    Insert(u32),
+}
+
+/// Malformed delta patch, or patch not matching the source image.
+pub(crate) struct BadPatch;
+
+/// Streaming decoder for delta patches.
+///
+/// A delta patch describes the new firmware as a sequence of ranges copied from the currently
+/// running firmware, and of bytes inserted from the patch. Since most of the firmware is usually
+/// unchanged between versions, the patch is typically much smaller than the new image, which cuts
+/// transfer sizes for devices with slow or expensive links.
+///
+/// The application stores the patch in the DFU partition, and the bootloader applies it against
+/// the active image before swapping the result in.
+///
+/// Patches have the following format, with all integers in little endian:
+/// | Field              | Description                                                        |
+/// | magic: u32         | `0x45444C54`.                                                      |
+/// | source_len: u32    | Length of the image the patch applies to.                          |
+/// | source_adler: u32  | Adler-32 checksum of the image the patch applies to.               |
+/// | target_len: u32    | Length of the image produced by the patch.                         |
+/// | target_adler: u32  | Adler-32 checksum of the image produced by the patch.              |
+/// | ops...             | `0x00, offset: u32, len: u32` copies `len` bytes of the source     |
+/// |                    | at `offset`, `0x01, len: u32, bytes` inserts `len` bytes.          |
+pub(crate) struct DeltaPatcher<'b> {
+    buf: &'b mut [u8],
+    buffered: usize,
+    flushed: u32,
+    state: ParseState,
+    args: [u8; HEADER_SIZE],
+    args_len: usize,
+    source_len: u32,
+    target_len: u32,
+    target_adler: u32,
+    adler: Adler32,
+}
+
+impl<'b> DeltaPatcher<'b> {
+    /// Create a patcher buffering the output in `buf` before writing it to the DFU partition.
+    ///
+    /// The length of `buf` must be a multiple of the DFU write size.
+    pub(crate) fn new(buf: &'b mut [u8]) -> Self {
+        assert!(!buf.is_empty());
+        Self {
+            buf,
+            buffered: 0,
+            flushed: 0,
+            state: ParseState::Header,
+            args: [0; HEADER_SIZE],
+            args_len: 0,
+            source_len: 0,
+            target_len: 0,
+            target_adler: 0,
+            adler: Adler32::new(),
+        }
+    }
+
+    /// Length of the image produced by the patch, once the patch header has been received.
+    pub(crate) fn target_len(&self) -> Option<u32> {
+        (self.state != ParseState::Header).then_some(self.target_len)
+    }
+
+    /// Number of bytes of the new image produced so far.
+    pub(crate) fn written(&self) -> u32 {
+        self.flushed + self.buffered as u32
+    }
+
+    fn arg(&self, index: usize) -> u32 {
+        u32::from_le_bytes(self.args[index * 4..index * 4 + 4].try_into().unwrap())
+    }
+
+    /// Accumulate `n` bytes of arguments from `patch`, returning the number of bytes consumed and
+    /// whether all the arguments were received.
+    fn take_args(&mut self, patch: &[u8], n: usize) -> (usize, bool) {
+        let consumed = core::cmp::min(n - self.args_len, patch.len());
+        self.args[self.args_len..self.args_len + consumed].copy_from_slice(&patch[..consumed]);
+        self.args_len += consumed;
+        let complete = self.args_len == n;
+        if complete {
+            self.args_len = 0;
+        }
+        (consumed, complete)
+    }
+
+    /// Parse the next step of the patch, returning the number of bytes of `patch` consumed.
+    pub(crate) fn parse<'p>(&mut self, patch: &'p [u8]) -> Result<(usize, Option<Action<'p>>), BadPatch> {
+        match self.state {
+            ParseState::Header => {
+                let (consumed, complete) = self.take_args(patch, HEADER_SIZE);
+                if !complete {
+                    return Ok((consumed, None));
+                }
+                if self.arg(0) != PATCH_MAGIC {
+                    return Err(BadPatch);
+                }
+                self.source_len = self.arg(1);
+                self.target_len = self.arg(3);
+                self.target_adler = self.arg(4);
+                self.state = ParseState::Op;
+                Ok((
+                    consumed,
+                    Some(Action::VerifySource {
+                        len: self.source_len,
+                        checksum: self.arg(2),
+                    }),
+                ))
+            }
+            ParseState::Op => {
+                self.state = match patch[0] {
+                    OP_COPY => ParseState::CopyArgs,
+                    OP_INSERT => ParseState::InsertLen,
+                    _ => return Err(BadPatch),
+                };
+                Ok((1, None))
+            }
+            ParseState::CopyArgs => {
+                let (consumed, complete) = self.take_args(patch, 8);
+                if !complete {
+                    return Ok((consumed, None));
+                }
+                let (offset, len) = (self.arg(0), self.arg(1));
+                if offset.checked_add(len).is_none_or(|end| end > self.source_len) {
+                    return Err(BadPatch);
+                }
+                self.state = ParseState::Op;
+                Ok((consumed, Some(Action::Copy { offset, len })))
+            }
+            ParseState::InsertLen => {
+                let (consumed, complete) = self.take_args(patch, 4);
+                if complete {
+                    self.state = match self.arg(0) {
+                        0 => ParseState::Op,
+                        len => ParseState::Insert(len),
+                    };
+                }
+                Ok((consumed, None))
+            }
+            ParseState::Insert(remaining) => {
+                let consumed = core::cmp::min(remaining as usize, patch.len());
+                self.state = match remaining - consumed as u32 {
+                    0 => ParseState::Op,
+                    remaining => ParseState::Insert(remaining),
+                };
+                Ok((consumed, Some(Action::Insert(&patch[..consumed]))))
+            }
+        }
+    }
+
+    /// Buffer output bytes, returning how many were buffered.
+    pub(crate) fn buffer(&mut self, data: &[u8]) -> Result<usize, BadPatch> {
+        if self.written() as usize + data.len() > self.target_len as usize {
+            return Err(BadPatch);
+        }
+        let n = core::cmp::min(self.buf.len() - self.buffered, data.len());
+        self.buf[self.buffered..self.buffered + n].copy_from_slice(&data[..n]);
+        self.adler.update(&data[..n]);
+        self.buffered += n;
+        Ok(n)
+    }
+
+    /// Take the buffered output if the buffer is full, returning its offset in the image.
+    pub(crate) fn take_full(&mut self) -> Option<(usize, &[u8])> {
+        (self.buffered == self.buf.len()).then(|| self.take(self.buf.len()))
+    }
+
+    /// Check that the patch is complete and take the remaining output, padded to a multiple of
+    /// `write_size`.
+    pub(crate) fn finish(&mut self, write_size: usize) -> Result<(usize, &[u8]), BadPatch> {
+        if self.state != ParseState::Op || self.written() != self.target_len || self.adler.finish() != self.target_adler
+        {
+            return Err(BadPatch);
+        }
+        let len = self.buffered.div_ceil(write_size) * write_size;
+        self.buf[self.buffered..len].fill(STATE_ERASE_VALUE);
+        Ok(self.take(len))
+    }
+
+    fn take(&mut self, len: usize) -> (usize, &[u8]) {
+        let offset = self.flushed as usize;
+        self.flushed += self.buffered as u32;
+        self.buffered = 0;
+        (offset, &self.buf[..len])
+    }
+}
@@ -6,7 +6,9 @@ use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embedded_storage_async::nor_flash::NorFlash;
 
 use super::FirmwareUpdaterConfig;
-use crate::{FirmwareUpdaterError, State, BOOT_MAGIC, DFU_DETACH_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC};
+use crate::{
+    FirmwareUpdaterError, ImageHeader, State, BOOT_MAGIC, DFU_DETACH_MAGIC, PATCH_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC,
+};
 
 /// FirmwareUpdater is an application API for interacting with the BootLoader without the ability to
 /// 'mess up' the internal bootloader state
@@ -144,6 +146,36 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
         }
     }
 
+    /// Verify the update in DFU against a [`SignedHeader`](crate::SignedHeader). If there is an
+    /// error then DO NOT proceed with updating the firmware.
+    ///
+    /// The SHA-256 digest of the first `header.len` bytes of DFU must match the header, and the
+    /// header signature must be accepted by `verifier`. Mark to trigger firmware swap on next boot
+    /// if both checks succeed.
+    #[cfg(feature = "signed-header")]
+    pub async fn verify_header_and_mark_updated<V: crate::HeaderVerifier>(
+        &mut self,
+        verifier: &mut V,
+        header: &crate::SignedHeader,
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(header.len <= self.dfu.capacity() as u32);
+
+        self.state.verify_booted().await?;
+
+        let mut chunk_buf = [0; 32];
+        let mut digest = [0; 32];
+        self.hash::<sha2::Sha256>(header.len, &mut chunk_buf, &mut digest)
+            .await?;
+        if digest != header.hash {
+            return Err(FirmwareUpdaterError::Signature(signature::Error::new()));
+        }
+
+        verifier
+            .verify_prehash(&header.signed_digest(), &header.signature)
+            .map_err(FirmwareUpdaterError::Signature)?;
+        self.state.mark_updated().await
+    }
+
     /// Verify the update in DFU with any digest.
     pub async fn hash<D: Digest>(
         &mut self,
@@ -178,6 +210,14 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
         self.state.mark_updated().await
     }
 
+    /// Mark to apply the delta patch written to the DFU partition on next boot.
+    ///
+    /// See [`FirmwareState::mark_patched`] for details.
+    #[cfg(not(feature = "_verify"))]
+    pub async fn mark_patched(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.state.mark_patched().await
+    }
+
     /// Mark to trigger USB DFU on next boot.
     pub async fn mark_dfu(&mut self) -> Result<(), FirmwareUpdaterError> {
         self.state.verify_booted().await?;
@@ -255,6 +295,27 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> FirmwareUpdater<'d, DFU, STATE> {
         Ok(())
     }
 
+    /// Write the [`ImageHeader`] of the update to the DFU partition.
+    ///
+    /// The header is written at the end of the image region, whose size is `active_size`, the size
+    /// of the active partition. Write it after the firmware, as the sector it's written to is
+    /// erased unless it was the last one written by [`write_firmware`](Self::write_firmware).
+    pub async fn write_image_header(
+        &mut self,
+        active_size: usize,
+        header: &ImageHeader,
+    ) -> Result<(), FirmwareUpdaterError> {
+        self.write_firmware(ImageHeader::offset(active_size) as usize, &header.to_bytes())
+            .await
+    }
+
+    /// Read the [`ImageHeader`] of the update in the DFU partition, if any.
+    ///
+    /// `active_size` is the size of the active partition.
+    pub async fn pending_header(&mut self, active_size: usize) -> Result<Option<ImageHeader>, FirmwareUpdaterError> {
+        Ok(ImageHeader::read(&mut self.dfu, active_size).await?)
+    }
+
     /// Prepare for an incoming DFU update by erasing the entire DFU area and
     /// returning its `Partition`.
     ///
@@ -323,6 +384,18 @@ impl<'d, STATE: NorFlash> FirmwareState<'d, STATE> {
         self.set_magic(SWAP_MAGIC).await
     }
 
+    /// Mark to apply the delta patch written to the DFU partition on next boot.
+    ///
+    /// The patch must be written at the start of the DFU partition, like a full image. The
+    /// bootloader checks that it applies to the active image, reconstructs the new image at the
+    /// end of the DFU partition and moves it to the start, then swaps it in as for
+    /// [`mark_updated`](Self::mark_updated). The patch and the new image must fit in the DFU
+    /// partition together. If the patch is invalid, the bootloader drops it and boots the active
+    /// image.
+    pub async fn mark_patched(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.set_magic(PATCH_MAGIC).await
+    }
+
     /// Mark to trigger USB DFU on next boot.
     pub async fn mark_dfu(&mut self) -> Result<(), FirmwareUpdaterError> {
         self.set_magic(DFU_DETACH_MAGIC).await
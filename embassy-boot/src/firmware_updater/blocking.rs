@@ -6,7 +6,9 @@ use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embedded_storage::nor_flash::NorFlash;
 
 use super::FirmwareUpdaterConfig;
-use crate::{FirmwareUpdaterError, State, BOOT_MAGIC, DFU_DETACH_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC};
+use crate::{
+    FirmwareUpdaterError, ImageHeader, State, BOOT_MAGIC, DFU_DETACH_MAGIC, PATCH_MAGIC, STATE_ERASE_VALUE, SWAP_MAGIC,
+};
 
 /// Blocking FirmwareUpdater is an application API for interacting with the BootLoader without the ability to
 /// 'mess up' the internal bootloader state
@@ -179,6 +181,35 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
         }
     }
 
+    /// Verify the update in DFU against a [`SignedHeader`](crate::SignedHeader). If there is an
+    /// error then DO NOT proceed with updating the firmware.
+    ///
+    /// The SHA-256 digest of the first `header.len` bytes of DFU must match the header, and the
+    /// header signature must be accepted by `verifier`. Mark to trigger firmware swap on next boot
+    /// if both checks succeed.
+    #[cfg(feature = "signed-header")]
+    pub fn verify_header_and_mark_updated<V: crate::HeaderVerifier>(
+        &mut self,
+        verifier: &mut V,
+        header: &crate::SignedHeader,
+    ) -> Result<(), FirmwareUpdaterError> {
+        assert!(header.len <= self.dfu.capacity() as u32);
+
+        self.state.verify_booted()?;
+
+        let mut chunk_buf = [0; 32];
+        let mut digest = [0; 32];
+        self.hash::<sha2::Sha256>(header.len, &mut chunk_buf, &mut digest)?;
+        if digest != header.hash {
+            return Err(FirmwareUpdaterError::Signature(signature::Error::new()));
+        }
+
+        verifier
+            .verify_prehash(&header.signed_digest(), &header.signature)
+            .map_err(FirmwareUpdaterError::Signature)?;
+        self.state.mark_updated()
+    }
+
     /// Verify the update in DFU with any digest.
     pub fn hash<D: Digest>(
         &mut self,
@@ -213,6 +244,14 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
         self.state.mark_updated()
     }
 
+    /// Mark to apply the delta patch written to the DFU partition on next boot.
+    ///
+    /// See [`BlockingFirmwareState::mark_patched`] for details.
+    #[cfg(not(feature = "_verify"))]
+    pub fn mark_patched(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.state.mark_patched()
+    }
+
     /// Mark to trigger USB DFU device on next boot.
     pub fn mark_dfu(&mut self) -> Result<(), FirmwareUpdaterError> {
         self.state.verify_booted()?;
@@ -290,6 +329,22 @@ impl<'d, DFU: NorFlash, STATE: NorFlash> BlockingFirmwareUpdater<'d, DFU, STATE>
         Ok(())
     }
 
+    /// Write the [`ImageHeader`] of the update to the DFU partition.
+    ///
+    /// The header is written at the end of the image region, whose size is `active_size`, the size
+    /// of the active partition. Write it after the firmware, as the sector it's written to is
+    /// erased unless it was the last one written by [`write_firmware`](Self::write_firmware).
+    pub fn write_image_header(&mut self, active_size: usize, header: &ImageHeader) -> Result<(), FirmwareUpdaterError> {
+        self.write_firmware(ImageHeader::offset(active_size) as usize, &header.to_bytes())
+    }
+
+    /// Read the [`ImageHeader`] of the update in the DFU partition, if any.
+    ///
+    /// `active_size` is the size of the active partition.
+    pub fn pending_header(&mut self, active_size: usize) -> Result<Option<ImageHeader>, FirmwareUpdaterError> {
+        Ok(ImageHeader::read_blocking(&mut self.dfu, active_size)?)
+    }
+
     /// Prepare for an incoming DFU update by erasing the entire DFU area and
     /// returning its `Partition`.
     ///
@@ -358,6 +413,18 @@ impl<'d, STATE: NorFlash> BlockingFirmwareState<'d, STATE> {
         self.set_magic(SWAP_MAGIC)
     }
 
+    /// Mark to apply the delta patch written to the DFU partition on next boot.
+    ///
+    /// The patch must be written at the start of the DFU partition, like a full image. The
+    /// bootloader checks that it applies to the active image, reconstructs the new image at the
+    /// end of the DFU partition and moves it to the start, then swaps it in as for
+    /// [`mark_updated`](Self::mark_updated). The patch and the new image must fit in the DFU
+    /// partition together. If the patch is invalid, the bootloader drops it and boots the active
+    /// image.
+    pub fn mark_patched(&mut self) -> Result<(), FirmwareUpdaterError> {
+        self.set_magic(PATCH_MAGIC)
+    }
+
     /// Mark to trigger USB DFU on next boot.
     pub fn mark_dfu(&mut self) -> Result<(), FirmwareUpdaterError> {
         self.set_magic(DFU_DETACH_MAGIC)
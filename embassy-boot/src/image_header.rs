@@ -0,0 +1,181 @@
+use embedded_storage::nor_flash::ReadNorFlash;
+use embedded_storage_async::nor_flash::ReadNorFlash as AsyncReadNorFlash;
+
+const IMAGE_HEADER_MAGIC: u32 = 0x494D_4748;
+
+/// Metadata describing a firmware image.
+///
+/// The header is stored as a trailer in the last [`SIZE`](Self::SIZE) bytes of the image region,
+/// which has the size of the active partition: the application must leave these bytes unused,
+/// for example by making the FLASH region of its linker script that much smaller than the active
+/// partition. The updater writes the header of a new image to the DFU partition with
+/// `write_image_header`, and the bootloader swaps it in along with the image.
+///
+/// The header of the running image can then be read from the end of the active partition, and
+/// the header of a pending update from the DFU partition, by both the bootloader and the
+/// application. This allows devices to report their firmware version, and to refuse updates
+/// built for another target or older than the running image.
+///
+/// The serialized header is 32 bytes long, with all integers in little endian: the magic
+/// `0x494D4748`, followed by `version`, `build_id`, `target_id` and `flags`, padded with zeroes.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ImageHeader {
+    /// Version of the image. Higher versions are newer.
+    pub version: u32,
+    /// Identifier of the build, for example part of a VCS commit hash.
+    pub build_id: u32,
+    /// Identifier of the hardware the image is built for.
+    pub target_id: u32,
+    /// Application defined flags.
+    pub flags: u32,
+}
+
+impl ImageHeader {
+    /// Size of the serialized header in bytes.
+    pub const SIZE: usize = 32;
+
+    /// Parse a serialized header, returning `None` if the magic doesn't match.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        let word = |i: usize| u32::from_le_bytes(bytes[i * 4..i * 4 + 4].try_into().unwrap());
+        if word(0) != IMAGE_HEADER_MAGIC {
+            return None;
+        }
+        Some(Self {
+            version: word(1),
+            build_id: word(2),
+            target_id: word(3),
+            flags: word(4),
+        })
+    }
+
+    /// Serialize the header.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        for (i, word) in [
+            IMAGE_HEADER_MAGIC,
+            self.version,
+            self.build_id,
+            self.target_id,
+            self.flags,
+        ]
+        .iter()
+        .enumerate()
+        {
+            bytes[i * 4..i * 4 + 4].copy_from_slice(&word.to_le_bytes());
+        }
+        bytes
+    }
+
+    /// Whether an update with this header may replace the image described by `current`: it must be
+    /// built for the same target and have a higher version.
+    pub fn is_upgrade_of(&self, current: &ImageHeader) -> bool {
+        self.target_id == current.target_id && self.version > current.version
+    }
+
+    /// Offset of the header in the active or DFU partition, given the size of the active partition.
+    pub(crate) fn offset(active_size: usize) -> u32 {
+        assert!(active_size >= Self::SIZE);
+        (active_size - Self::SIZE) as u32
+    }
+
+    /// Read the header of the image in `flash`, which is either the active or the DFU partition.
+    ///
+    /// `active_size` is the size of the active partition. Returns `None` if there is no header.
+    pub fn read_blocking<F: ReadNorFlash>(flash: &mut F, active_size: usize) -> Result<Option<Self>, F::Error> {
+        let mut bytes = [0; Self::SIZE];
+        flash.read(Self::offset(active_size), &mut bytes)?;
+        Ok(Self::from_bytes(&bytes))
+    }
+
+    /// Read the header of the image in `flash`, which is either the active or the DFU partition.
+    ///
+    /// `active_size` is the size of the active partition. Returns `None` if there is no header.
+    pub async fn read<F: AsyncReadNorFlash>(flash: &mut F, active_size: usize) -> Result<Option<Self>, F::Error> {
+        let mut bytes = [0; Self::SIZE];
+        flash.read(Self::offset(active_size), &mut bytes).await?;
+        Ok(Self::from_bytes(&bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(not(feature = "_verify"))]
+    use embedded_storage::nor_flash::NorFlash;
+
+    use super::*;
+    #[cfg(not(feature = "_verify"))]
+    use crate::mem_flash::MemFlash;
+    #[cfg(not(feature = "_verify"))]
+    use crate::test_flash::BlockingTestFlash;
+    #[cfg(not(feature = "_verify"))]
+    use crate::{BlockingFirmwareUpdater, BootLoader, BootLoaderConfig, FirmwareUpdaterConfig, State};
+
+    const HEADER: ImageHeader = ImageHeader {
+        version: 2,
+        build_id: 0xC0FFEE,
+        target_id: 7,
+        flags: 1,
+    };
+
+    #[test]
+    fn roundtrip() {
+        assert_eq!(ImageHeader::from_bytes(&HEADER.to_bytes()), Some(HEADER));
+        assert_eq!(ImageHeader::from_bytes(&[0xFF; ImageHeader::SIZE]), None);
+
+        let current = ImageHeader { version: 1, ..HEADER };
+        assert!(HEADER.is_upgrade_of(&current));
+        assert!(!current.is_upgrade_of(&HEADER));
+        assert!(!HEADER.is_upgrade_of(&ImageHeader {
+            target_id: 8,
+            ..current
+        }));
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn header_is_swapped_with_image() {
+        const ACTIVE_SIZE: usize = 8192;
+        let flash = BlockingTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<ACTIVE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<12288, 4096, 4>::default(),
+            state: MemFlash::<4096, 4096, 4>::default(),
+        });
+
+        let current = ImageHeader { version: 1, ..HEADER };
+        flash
+            .active()
+            .write(ImageHeader::offset(ACTIVE_SIZE), &current.to_bytes())
+            .unwrap();
+
+        let mut aligned = [0; 4];
+        let mut updater = BlockingFirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        assert_eq!(updater.pending_header(ACTIVE_SIZE).unwrap(), None);
+        updater.write_firmware(0, &[0xAA; 1024]).unwrap();
+        updater.write_image_header(ACTIVE_SIZE, &HEADER).unwrap();
+        assert_eq!(updater.pending_header(ACTIVE_SIZE).unwrap(), Some(HEADER));
+        updater.mark_updated().unwrap();
+
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+        let mut page = [0; 4096];
+        assert_eq!(bootloader.current_header().unwrap(), Some(current));
+        assert_eq!(bootloader.pending_header().unwrap(), Some(HEADER));
+
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+        assert_eq!(bootloader.current_header().unwrap(), Some(HEADER));
+        assert_eq!(
+            ImageHeader::read_blocking(&mut flash.active(), ACTIVE_SIZE).unwrap(),
+            Some(HEADER)
+        );
+    }
+}
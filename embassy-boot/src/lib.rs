@@ -5,10 +5,15 @@
 mod fmt;
 
 mod boot_loader;
+mod delta;
 mod digest_adapters;
 mod firmware_updater;
+mod image_header;
 #[cfg(test)]
 mod mem_flash;
+mod multi_slot;
+#[cfg(feature = "signed-header")]
+mod signed_header;
 #[cfg(test)]
 mod test_flash;
 
@@ -24,11 +29,18 @@ pub use firmware_updater::{
     BlockingFirmwareState, BlockingFirmwareUpdater, FirmwareState, FirmwareUpdater, FirmwareUpdaterConfig,
     FirmwareUpdaterError,
 };
+pub use image_header::ImageHeader;
+pub use multi_slot::{BlockingMultiSlotUpdater, MultiSlotBootLoader, MultiSlotConfig, SlotInfo, SlotState};
+#[cfg(feature = "ecdsa-p256")]
+pub use signed_header::P256Verifier;
+#[cfg(feature = "signed-header")]
+pub use signed_header::{HeaderVerifier, SignedHeader};
 
 pub(crate) const REVERT_MAGIC: u8 = 0xC0;
 pub(crate) const BOOT_MAGIC: u8 = 0xD0;
 pub(crate) const SWAP_MAGIC: u8 = 0xF0;
 pub(crate) const DFU_DETACH_MAGIC: u8 = 0xE0;
+pub(crate) const PATCH_MAGIC: u8 = 0xB0;
 
 /// The state of the bootloader after running prepare.
 #[derive(PartialEq, Eq, Debug)]
@@ -50,7 +62,8 @@ where
 {
     fn from(magic: T) -> State {
         let magic = magic.as_ref();
-        if !magic.iter().any(|&b| b != SWAP_MAGIC) {
+        // A pending delta patch is turned into a swap by the bootloader.
+        if !magic.iter().any(|&b| b != SWAP_MAGIC) || !magic.iter().any(|&b| b != PATCH_MAGIC) {
             State::Swap
         } else if !magic.iter().any(|&b| b != REVERT_MAGIC) {
             State::Revert
@@ -82,8 +95,9 @@ impl<const N: usize> AsMut<[u8]> for AlignedBuffer<N> {
 mod tests {
     #![allow(unused_imports)]
 
+    use embassy_embedded_hal::flash::BlockOnFlash;
     use embedded_storage::nor_flash::{NorFlash, ReadNorFlash};
-    use embedded_storage_async::nor_flash::NorFlash as AsyncNorFlash;
+    use embedded_storage_async::nor_flash::{NorFlash as AsyncNorFlash, ReadNorFlash as AsyncReadNorFlash};
     use futures::executor::block_on;
 
     use super::*;
@@ -297,8 +311,171 @@ mod tests {
         assert_eq!(ORIGINAL, read_buf);
     }
 
+    /// Build a delta patch turning `source` into `target` with `ops`, padded to a multiple of 4.
+    #[cfg(not(feature = "_verify"))]
+    fn make_patch(source: &[u8], target: &[u8], ops: &[u8], patch: &mut [u8]) -> usize {
+        let checksum = |data: &[u8]| {
+            let mut adler = crate::delta::Adler32::new();
+            adler.update(data);
+            adler.finish()
+        };
+        patch[0..4].copy_from_slice(&0x4544_4C54u32.to_le_bytes());
+        patch[4..8].copy_from_slice(&(source.len() as u32).to_le_bytes());
+        patch[8..12].copy_from_slice(&checksum(source).to_le_bytes());
+        patch[12..16].copy_from_slice(&(target.len() as u32).to_le_bytes());
+        patch[16..20].copy_from_slice(&checksum(target).to_le_bytes());
+        patch[20..20 + ops.len()].copy_from_slice(ops);
+        (20 + ops.len()).next_multiple_of(4)
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_patch_state() {
+        const FIRMWARE_SIZE: usize = 16384;
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<24576, 4096, 4>::default(),
+            state: MemFlash::<4096, 4096, 4>::default(),
+        });
+
+        let original: [u8; FIRMWARE_SIZE] = core::array::from_fn(|i| (i % 251) as u8);
+        block_on(flash.active().write(0, &original)).unwrap();
+
+        // Insert 3 bytes, copy most of the original image, and append a tail.
+        let mut update = [0; 3 + 15900 + 4];
+        update[..3].copy_from_slice(&[0xAA, 0xBB, 0xCC]);
+        update[3..15903].copy_from_slice(&original[100..16000]);
+        update[15903..].copy_from_slice(b"tail");
+        let ops = [
+            1, 3, 0, 0, 0, 0xAA, 0xBB, 0xCC, // insert
+            0, 100, 0, 0, 0, 0x1C, 0x3E, 0, 0, // copy 15900 bytes at 100
+            1, 4, 0, 0, 0, b't', b'a', b'i', b'l', // insert
+        ];
+        let mut patch = [0xFF; 64];
+        let patch_len = make_patch(&original, &update, &ops, &mut patch);
+
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &patch[..patch_len])).unwrap();
+        block_on(updater.mark_patched()).unwrap();
+        assert_eq!(State::Swap, block_on(updater.get_state()).unwrap());
+
+        let flash = flash.into_blocking();
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+
+        let mut page = [0; 1024];
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+
+        let mut read_buf = [0; FIRMWARE_SIZE];
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(update, read_buf[..update.len()]);
+
+        // The patched image can be reverted like any other update
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+        assert_eq!(State::Revert, bootloader.prepare_boot(&mut page).unwrap());
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(original, read_buf);
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_patch_for_other_source() {
+        const FIRMWARE_SIZE: usize = 16384;
+        let flash = BlockingTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 4096, 4>::default(),
+            dfu: MemFlash::<24576, 4096, 4>::default(),
+            state: MemFlash::<4096, 4096, 4>::default(),
+        });
+
+        const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
+        flash.active().write(0, &ORIGINAL).unwrap();
+
+        let ops = [0, 0, 0, 0, 0, 0, 0x40, 0, 0]; // copy 16384 bytes at 0
+        let mut patch = [0xFF; 32];
+        let patch_len = make_patch(&[0xAA; FIRMWARE_SIZE], &[0xAA; FIRMWARE_SIZE], &ops, &mut patch);
+
+        let mut aligned = [0; 4];
+        let mut updater = BlockingFirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        updater.write_firmware(0, &patch[..patch_len]).unwrap();
+        updater.mark_patched().unwrap();
+
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: flash.active(),
+            dfu: flash.dfu(),
+            state: flash.state(),
+        });
+
+        // The patch is dropped and the original image is booted
+        let mut page = [0; 1024];
+        assert_eq!(State::Boot, bootloader.prepare_boot(&mut page).unwrap());
+
+        let mut read_buf = [0; FIRMWARE_SIZE];
+        flash.active().read(0, &mut read_buf).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+    }
+
+    #[test]
+    #[cfg(not(feature = "_verify"))]
+    fn test_swap_state_external_dfu() {
+        const FIRMWARE_SIZE: usize = 16384;
+        // DFU on an async flash with bigger sectors than the internal flash
+        let flash = AsyncTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<FIRMWARE_SIZE, 2048, 4>::random(),
+            dfu: MemFlash::<24576, 8192, 4>::random(),
+            state: MemFlash::<4096, 4096, 4>::default(),
+        });
+
+        const ORIGINAL: [u8; FIRMWARE_SIZE] = [0x55; FIRMWARE_SIZE];
+        const UPDATE: [u8; FIRMWARE_SIZE] = [0xAA; FIRMWARE_SIZE];
+        let mut aligned = [0; 4];
+
+        block_on(flash.active().erase(0, ORIGINAL.len() as u32)).unwrap();
+        block_on(flash.active().write(0, &ORIGINAL)).unwrap();
+
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+        block_on(updater.write_firmware(0, &UPDATE)).unwrap();
+        block_on(updater.mark_updated()).unwrap();
+
+        let mut bootloader = BootLoader::new(BootLoaderConfig {
+            active: BlockOnFlash::new(flash.active()),
+            dfu: BlockOnFlash::new(flash.dfu()),
+            state: BlockOnFlash::new(flash.state()),
+        });
+
+        let mut page = [0; 2048];
+        assert_eq!(State::Swap, bootloader.prepare_boot(&mut page).unwrap());
+
+        let mut read_buf = [0; FIRMWARE_SIZE];
+        block_on(flash.active().read(0, &mut read_buf)).unwrap();
+        assert_eq!(UPDATE, read_buf);
+        block_on(flash.dfu().read(8192, &mut read_buf)).unwrap();
+        assert_eq!(ORIGINAL, read_buf);
+    }
+
     #[test]
-    #[cfg(feature = "_verify")]
+    #[cfg(any(feature = "ed25519-dalek", feature = "ed25519-salty"))]
     fn test_verify() {
         // The following key setup is based on:
         // https://docs.rs/ed25519-dalek/latest/ed25519_dalek/#example
@@ -348,4 +525,56 @@ mod tests {
         ))
         .is_ok());
     }
+
+    #[test]
+    #[cfg(feature = "ecdsa-p256")]
+    fn test_verify_signed_header() {
+        use p256::ecdsa::signature::hazmat::PrehashSigner;
+        use p256::ecdsa::{Signature, SigningKey};
+        use sha2::{Digest, Sha256};
+
+        let signing_key = SigningKey::from_slice(&[0x42; 32]).unwrap();
+        let public_key = signing_key.verifying_key().to_encoded_point(false);
+        let mut verifier = P256Verifier::from_sec1_bytes(public_key.as_bytes()).unwrap();
+
+        let firmware: &[u8] = b"This are bytes that would otherwise be firmware bytes for DFU.";
+        let mut header = SignedHeader {
+            len: firmware.len() as u32,
+            hash: Sha256::digest(firmware).into(),
+            signature: [0; 64],
+        };
+        let signature: Signature = signing_key.sign_prehash(&header.signed_digest()).unwrap();
+        header.signature = signature.to_bytes().into();
+        assert_eq!(SignedHeader::from_bytes(&header.to_bytes()), Some(header.clone()));
+
+        // Setup flash
+        let flash = BlockingTestFlash::new(BootLoaderConfig {
+            active: MemFlash::<0, 0, 0>::default(),
+            dfu: MemFlash::<4096, 4096, 4>::default(),
+            state: MemFlash::<4096, 4096, 4>::default(),
+        });
+
+        let mut write_buf = [0; 4096];
+        write_buf[0..firmware.len()].copy_from_slice(firmware);
+        flash.dfu().write(0, &write_buf).unwrap();
+
+        let flash = flash.into_async();
+        let mut aligned = [0; 4];
+        let mut updater = FirmwareUpdater::new(
+            FirmwareUpdaterConfig {
+                dfu: flash.dfu(),
+                state: flash.state(),
+            },
+            &mut aligned,
+        );
+
+        // Tampered header is refused
+        let mut tampered = header.clone();
+        tampered.len -= 1;
+        assert!(block_on(updater.verify_header_and_mark_updated(&mut verifier, &tampered)).is_err());
+        assert_eq!(block_on(updater.get_state()).unwrap(), State::Boot);
+
+        assert!(block_on(updater.verify_header_and_mark_updated(&mut verifier, &header)).is_ok());
+        assert_eq!(block_on(updater.get_state()).unwrap(), State::Swap);
+    }
 }
@@ -0,0 +1,444 @@
+use embedded_storage::nor_flash::NorFlash;
+
+use crate::{BootError, FirmwareUpdaterError, STATE_ERASE_VALUE};
+
+const SLOT_MAGIC: u32 = 0x534C_4F54;
+const HEADER_LEN: usize = 12;
+
+/// State of a firmware slot, as recorded in the metadata partition.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SlotState {
+    /// The slot holds no image, or an image that's being written.
+    Empty,
+    /// The slot holds a new image which hasn't been booted yet.
+    Pending,
+    /// The image in the slot has been booted once, but hasn't been marked good yet.
+    Trial,
+    /// The image in the slot has been marked good.
+    Good,
+    /// The image in the slot has been marked bad, or failed its trial boot.
+    Bad,
+}
+
+impl SlotState {
+    /// Whether the bootloader may select a slot in this state.
+    pub fn is_bootable(self) -> bool {
+        matches!(self, SlotState::Pending | SlotState::Good)
+    }
+}
+
+/// Metadata of a firmware slot.
+#[derive(PartialEq, Eq, Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SlotInfo {
+    /// State of the slot.
+    pub state: SlotState,
+    /// Version of the image in the slot. Higher versions are preferred by the bootloader.
+    pub version: u32,
+    /// Length of the image in the slot, in bytes.
+    pub len: u32,
+}
+
+impl SlotInfo {
+    const EMPTY: Self = Self {
+        state: SlotState::Empty,
+        version: 0,
+        len: 0,
+    };
+}
+
+#[derive(Clone, Copy)]
+enum Flag {
+    Attempted = 0,
+    Confirmed = 1,
+    Bad = 2,
+}
+
+/// Multi-slot flash configuration, holding the firmware slots and the metadata partition.
+///
+/// Each slot is a partition the application can be executed from. The metadata partition holds one
+/// record per slot, each in its own erase sector, so it must be at least `N * META::ERASE_SIZE` bytes.
+pub struct MultiSlotConfig<SLOT, META, const N: usize> {
+    /// The firmware slot partitions.
+    pub slots: [SLOT; N],
+    /// The metadata partition.
+    pub meta: META,
+}
+
+/// The metadata records of the slots.
+///
+/// Each record has the following format, where all ranges are in multiples of WRITE_SIZE bytes:
+/// | Range    | Description                                                                 |
+/// | 0..H     | Header: magic, version and image length as little endian u32 values.       |
+/// | H..H + 1 | Attempted flag, set by the bootloader when starting a trial boot.           |
+/// | H + 1..  | Confirmed flag, set when the image is marked good.                          |
+/// | H + 2..  | Bad flag, set when the image is marked bad or fails its trial boot.         |
+///
+/// State changes only program words which are still erased, so they are power-fail safe.
+struct SlotTable<META> {
+    meta: META,
+}
+
+impl<META: NorFlash> SlotTable<META> {
+    const HEADER_SIZE: usize = HEADER_LEN.div_ceil(META::WRITE_SIZE) * META::WRITE_SIZE;
+
+    fn new<const N: usize>(meta: META) -> Self {
+        assert!(META::ERASE_SIZE >= Self::HEADER_SIZE + 3 * META::WRITE_SIZE);
+        assert!(meta.capacity() >= N * META::ERASE_SIZE);
+        Self { meta }
+    }
+
+    fn record_offset(index: usize) -> u32 {
+        (index * META::ERASE_SIZE) as u32
+    }
+
+    fn flag_offset(index: usize, flag: Flag) -> u32 {
+        Self::record_offset(index) + (Self::HEADER_SIZE + flag as usize * META::WRITE_SIZE) as u32
+    }
+
+    fn is_flag_set(&mut self, index: usize, flag: Flag, aligned_buf: &mut [u8]) -> Result<bool, META::Error> {
+        let word = &mut aligned_buf[..META::WRITE_SIZE];
+        self.meta.read(Self::flag_offset(index, flag), word)?;
+        Ok(word.iter().any(|&b| b != STATE_ERASE_VALUE))
+    }
+
+    fn set_flag(&mut self, index: usize, flag: Flag, aligned_buf: &mut [u8]) -> Result<(), META::Error> {
+        if !self.is_flag_set(index, flag, aligned_buf)? {
+            let word = &mut aligned_buf[..META::WRITE_SIZE];
+            word.fill(!STATE_ERASE_VALUE);
+            self.meta.write(Self::flag_offset(index, flag), word)?;
+        }
+        Ok(())
+    }
+
+    fn read(&mut self, index: usize, aligned_buf: &mut [u8]) -> Result<SlotInfo, META::Error> {
+        let header = &mut aligned_buf[..Self::HEADER_SIZE];
+        self.meta.read(Self::record_offset(index), header)?;
+
+        let word = |i: usize| u32::from_le_bytes(header[i * 4..i * 4 + 4].try_into().unwrap());
+        if word(0) != SLOT_MAGIC {
+            return Ok(SlotInfo::EMPTY);
+        }
+        let version = word(1);
+        let len = word(2);
+
+        let state = if self.is_flag_set(index, Flag::Bad, aligned_buf)? {
+            SlotState::Bad
+        } else if self.is_flag_set(index, Flag::Confirmed, aligned_buf)? {
+            SlotState::Good
+        } else if self.is_flag_set(index, Flag::Attempted, aligned_buf)? {
+            SlotState::Trial
+        } else {
+            SlotState::Pending
+        };
+        Ok(SlotInfo { state, version, len })
+    }
+
+    fn write_header(
+        &mut self,
+        index: usize,
+        version: u32,
+        len: u32,
+        aligned_buf: &mut [u8],
+    ) -> Result<(), META::Error> {
+        let header = &mut aligned_buf[..Self::HEADER_SIZE];
+        header.fill(STATE_ERASE_VALUE);
+        header[0..4].copy_from_slice(&SLOT_MAGIC.to_le_bytes());
+        header[4..8].copy_from_slice(&version.to_le_bytes());
+        header[8..12].copy_from_slice(&len.to_le_bytes());
+        self.meta.write(Self::record_offset(index), header)
+    }
+
+    fn erase(&mut self, index: usize) -> Result<(), META::Error> {
+        let from = Self::record_offset(index);
+        self.meta.erase(from, from + META::ERASE_SIZE as u32)
+    }
+}
+
+/// Bootloader selecting the newest valid image among several firmware slots.
+///
+/// Unlike [`BootLoader`](crate::BootLoader), which swaps the update into a single active partition,
+/// the multi-slot bootloader never copies images: the application is built to run from any slot
+/// (or one build per slot is used), and [`prepare_boot`](Self::prepare_boot) returns which slot to
+/// jump to. This allows A/B/C layouts where a known good "golden" image is kept in one of the
+/// slots as a last resort fallback.
+///
+/// A new image is booted once in a trial state. If the application doesn't mark it good with
+/// [`BlockingMultiSlotUpdater::mark_booted`] before the next reset, the image is marked bad and
+/// the bootloader falls back to the newest remaining valid image.
+pub struct MultiSlotBootLoader<META: NorFlash, const N: usize> {
+    table: SlotTable<META>,
+}
+
+impl<META: NorFlash, const N: usize> MultiSlotBootLoader<META, N> {
+    /// Create a new multi-slot bootloader with the metadata partition.
+    ///
+    /// The bootloader only needs the metadata partition, the slots themselves are never accessed.
+    pub fn new(meta: META) -> Self {
+        Self {
+            table: SlotTable::new::<N>(meta),
+        }
+    }
+
+    /// Read the metadata of the slot at `index`.
+    ///
+    /// The provided aligned_buf argument must satisfy the alignment requirements of the metadata
+    /// flash, and be large enough to hold a record header.
+    pub fn slot_info(&mut self, index: usize, aligned_buf: &mut [u8]) -> Result<SlotInfo, BootError> {
+        assert!(index < N);
+        Ok(self.table.read(index, aligned_buf)?)
+    }
+
+    /// Select the slot to boot.
+    ///
+    /// Slots whose trial boot wasn't confirmed are marked bad. Among the remaining pending and
+    /// good slots, the one with the highest version is selected, lowest index first on a tie. If
+    /// the selected image is pending, it's marked as attempted before returning, so it must be
+    /// marked good by the application before the next reset.
+    ///
+    /// Returns the index of the slot to boot, or [`BootError::NoValidSlot`] if there is none.
+    pub fn prepare_boot(&mut self, aligned_buf: &mut [u8]) -> Result<usize, BootError> {
+        let mut selected: Option<(usize, SlotInfo)> = None;
+        for index in 0..N {
+            let mut info = self.table.read(index, aligned_buf)?;
+            if info.state == SlotState::Trial {
+                trace!("Slot {} failed its trial boot", index);
+                self.table.set_flag(index, Flag::Bad, aligned_buf)?;
+                info.state = SlotState::Bad;
+            }
+
+            if info.state.is_bootable() && selected.is_none_or(|(_, s)| info.version > s.version) {
+                selected = Some((index, info));
+            }
+        }
+
+        let (index, info) = selected.ok_or(BootError::NoValidSlot)?;
+        if info.state == SlotState::Pending {
+            trace!("Trial boot of slot {}", index);
+            self.table.set_flag(index, Flag::Attempted, aligned_buf)?;
+        }
+        Ok(index)
+    }
+}
+
+/// Application API for updating firmware slots and marking them good or bad.
+pub struct BlockingMultiSlotUpdater<'d, SLOT: NorFlash, META: NorFlash, const N: usize> {
+    slots: [SLOT; N],
+    table: SlotTable<META>,
+    aligned: &'d mut [u8],
+}
+
+impl<'d, SLOT: NorFlash, META: NorFlash, const N: usize> BlockingMultiSlotUpdater<'d, SLOT, META, N> {
+    /// Create a multi-slot updater.
+    ///
+    /// The `aligned` buffer must follow the alignment rules for the metadata flash.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `aligned` can't hold a record header (12 bytes rounded up to META::WRITE_SIZE).
+    pub fn new(config: MultiSlotConfig<SLOT, META, N>, aligned: &'d mut [u8]) -> Self {
+        assert!(aligned.len() >= SlotTable::<META>::HEADER_SIZE);
+        Self {
+            slots: config.slots,
+            table: SlotTable::new::<N>(config.meta),
+            aligned,
+        }
+    }
+
+    /// Read the metadata of the slot at `index`.
+    pub fn slot_info(&mut self, index: usize) -> Result<SlotInfo, FirmwareUpdaterError> {
+        assert!(index < N);
+        Ok(self.table.read(index, self.aligned)?)
+    }
+
+    /// Returns the index of the slot in its trial boot, if any.
+    ///
+    /// Right after booting a new image, this is the slot the application is running from.
+    pub fn trial_slot(&mut self) -> Result<Option<usize>, FirmwareUpdaterError> {
+        for index in 0..N {
+            if self.slot_info(index)?.state == SlotState::Trial {
+                return Ok(Some(index));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Find a slot to write an update to, skipping the slots in `exclude`.
+    ///
+    /// Empty and bad slots are used first, then the slot holding the oldest image. Exclude at
+    /// least the slot the application is running from, as well as any golden image slot.
+    pub fn find_update_slot(&mut self, exclude: &[usize]) -> Result<Option<usize>, FirmwareUpdaterError> {
+        let mut oldest: Option<(usize, u32)> = None;
+        for index in (0..N).filter(|i| !exclude.contains(i)) {
+            let info = self.slot_info(index)?;
+            match info.state {
+                SlotState::Empty | SlotState::Bad => return Ok(Some(index)),
+                SlotState::Trial => {}
+                SlotState::Pending | SlotState::Good => {
+                    if oldest.is_none_or(|(_, version)| info.version < version) {
+                        oldest = Some((index, info.version));
+                    }
+                }
+            }
+        }
+        Ok(oldest.map(|(index, _)| index))
+    }
+
+    /// Prepare the slot at `index` for an update by invalidating its metadata and erasing it.
+    ///
+    /// The slot is returned so the update can be written to it, after which it must be marked
+    /// updated with [`mark_updated`](Self::mark_updated). A slot in its trial boot can't be
+    /// prepared, since the application is running from it.
+    pub fn prepare_update(&mut self, index: usize) -> Result<&mut SLOT, FirmwareUpdaterError> {
+        if self.slot_info(index)?.state == SlotState::Trial {
+            return Err(FirmwareUpdaterError::BadState);
+        }
+
+        self.table.erase(index)?;
+        let slot = &mut self.slots[index];
+        slot.erase(0, slot.capacity() as u32)?;
+        Ok(slot)
+    }
+
+    /// Write firmware data to the slot at `index`, which must have been prepared with
+    /// [`prepare_update`](Self::prepare_update).
+    ///
+    /// The data must be a multiple of SLOT::WRITE_SIZE.
+    pub fn write_firmware(&mut self, index: usize, offset: usize, data: &[u8]) -> Result<(), FirmwareUpdaterError> {
+        if self.slot_info(index)?.state != SlotState::Empty {
+            return Err(FirmwareUpdaterError::BadState);
+        }
+        self.slots[index].write(offset as u32, data)?;
+        Ok(())
+    }
+
+    /// Read a slice of data from the slot at `index`.
+    pub fn read_slot(&mut self, index: usize, offset: u32, buf: &mut [u8]) -> Result<(), FirmwareUpdaterError> {
+        self.slots[index].read(offset, buf)?;
+        Ok(())
+    }
+
+    /// Mark the image written to the slot at `index` as pending, so the bootloader tries it on
+    /// the next boot if its version is the highest.
+    pub fn mark_updated(&mut self, index: usize, version: u32, len: u32) -> Result<(), FirmwareUpdaterError> {
+        assert!(len as usize <= self.slots[index].capacity());
+        if self.slot_info(index)?.state != SlotState::Empty {
+            return Err(FirmwareUpdaterError::BadState);
+        }
+        self.table.write_header(index, version, len, self.aligned)?;
+        Ok(())
+    }
+
+    /// Mark the image in the slot at `index` good, so the bootloader keeps booting it.
+    pub fn mark_good(&mut self, index: usize) -> Result<(), FirmwareUpdaterError> {
+        match self.slot_info(index)?.state {
+            SlotState::Pending | SlotState::Trial | SlotState::Good => {
+                Ok(self.table.set_flag(index, Flag::Confirmed, self.aligned)?)
+            }
+            SlotState::Empty | SlotState::Bad => Err(FirmwareUpdaterError::BadState),
+        }
+    }
+
+    /// Mark the image in the slot at `index` bad, so the bootloader never boots it again.
+    pub fn mark_bad(&mut self, index: usize) -> Result<(), FirmwareUpdaterError> {
+        if self.slot_info(index)?.state == SlotState::Empty {
+            return Err(FirmwareUpdaterError::BadState);
+        }
+        Ok(self.table.set_flag(index, Flag::Bad, self.aligned)?)
+    }
+
+    /// Mark the image in its trial boot good and stop the fallback on reset.
+    ///
+    /// Does nothing if no slot is in its trial boot.
+    pub fn mark_booted(&mut self) -> Result<(), FirmwareUpdaterError> {
+        if let Some(index) = self.trial_slot()? {
+            self.mark_good(index)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::cell::RefCell;
+
+    use embassy_embedded_hal::flash::partition::BlockingPartition;
+    use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+    use embassy_sync::blocking_mutex::Mutex;
+
+    use super::*;
+    use crate::mem_flash::MemFlash;
+
+    type Flash = Mutex<NoopRawMutex, RefCell<MemFlash<40960, 4096, 4>>>;
+
+    fn config(
+        flash: &Flash,
+    ) -> MultiSlotConfig<
+        BlockingPartition<'_, NoopRawMutex, MemFlash<40960, 4096, 4>>,
+        BlockingPartition<'_, NoopRawMutex, MemFlash<40960, 4096, 4>>,
+        3,
+    > {
+        MultiSlotConfig {
+            slots: [
+                BlockingPartition::new(flash, 16384, 8192),
+                BlockingPartition::new(flash, 24576, 8192),
+                BlockingPartition::new(flash, 32768, 8192),
+            ],
+            meta: BlockingPartition::new(flash, 0, 12288),
+        }
+    }
+
+    fn install(flash: &Flash, index: usize, version: u32) {
+        let mut aligned = [0; 12];
+        let mut updater = BlockingMultiSlotUpdater::new(config(flash), &mut aligned);
+        updater.prepare_update(index).unwrap();
+        updater.write_firmware(index, 0, &[index as u8; 16]).unwrap();
+        updater.mark_updated(index, version, 16).unwrap();
+    }
+
+    #[test]
+    fn no_valid_slot() {
+        let flash = Flash::new(RefCell::new(MemFlash::default()));
+        let mut bootloader = MultiSlotBootLoader::<_, 3>::new(config(&flash).meta);
+
+        let mut aligned = [0; 12];
+        assert_eq!(bootloader.prepare_boot(&mut aligned), Err(BootError::NoValidSlot));
+    }
+
+    #[test]
+    fn boots_newest_and_falls_back() {
+        let flash = Flash::new(RefCell::new(MemFlash::default()));
+        let mut aligned = [0; 12];
+
+        // Golden image in slot 0.
+        install(&flash, 0, 1);
+        BlockingMultiSlotUpdater::new(config(&flash), &mut aligned)
+            .mark_good(0)
+            .unwrap();
+
+        // Update in slot 2, trial boot confirmed by the application.
+        install(&flash, 2, 2);
+        let mut bootloader = MultiSlotBootLoader::<_, 3>::new(config(&flash).meta);
+        assert_eq!(bootloader.prepare_boot(&mut aligned), Ok(2));
+        let mut updater = BlockingMultiSlotUpdater::new(config(&flash), &mut aligned);
+        assert_eq!(updater.trial_slot().unwrap(), Some(2));
+        updater.mark_booted().unwrap();
+        assert_eq!(updater.slot_info(2).unwrap().state, SlotState::Good);
+        assert_eq!(updater.find_update_slot(&[0, 2]).unwrap(), Some(1));
+
+        // Update in slot 1, which fails its trial boot.
+        install(&flash, 1, 3);
+        let mut bootloader = MultiSlotBootLoader::<_, 3>::new(config(&flash).meta);
+        assert_eq!(bootloader.prepare_boot(&mut aligned), Ok(1));
+        assert_eq!(bootloader.prepare_boot(&mut aligned), Ok(2));
+        assert_eq!(bootloader.slot_info(1, &mut aligned).unwrap().state, SlotState::Bad);
+
+        // Marking the current image bad falls back to the golden image.
+        let mut updater = BlockingMultiSlotUpdater::new(config(&flash), &mut aligned);
+        updater.mark_bad(2).unwrap();
+        assert_eq!(updater.find_update_slot(&[0]).unwrap(), Some(1));
+        let mut bootloader = MultiSlotBootLoader::<_, 3>::new(config(&flash).meta);
+        assert_eq!(bootloader.prepare_boot(&mut aligned), Ok(0));
+    }
+}
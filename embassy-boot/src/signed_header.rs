@@ -0,0 +1,105 @@
+use sha2::{Digest, Sha256};
+
+const HEADER_MAGIC: u32 = 0x5349_474E;
+
+/// Header describing a signed firmware image.
+///
+/// The header is produced when signing the firmware and delivered to the application along with
+/// the image, which writes the image to the DFU partition and passes the header to
+/// `verify_header_and_mark_updated` on the updater. The update is only activated if the SHA-256
+/// digest of the image matches [`hash`](Self::hash), and [`signature`](Self::signature) is a
+/// valid signature of the header.
+///
+/// The serialized format is, with all integers in little endian:
+/// | Range    | Description                                                |
+/// | 0..4     | Magic, `0x5349474E`.                                       |
+/// | 4..8     | Image length in bytes.                                     |
+/// | 8..40    | SHA-256 digest of the image.                               |
+/// | 40..104  | Signature of bytes 0..40, as the `r` and `s` scalars.      |
+#[derive(PartialEq, Eq, Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SignedHeader {
+    /// Length of the image in bytes.
+    pub len: u32,
+    /// SHA-256 digest of the image.
+    pub hash: [u8; 32],
+    /// Signature of the SHA-256 digest of the signed part of the header.
+    pub signature: [u8; 64],
+}
+
+impl SignedHeader {
+    /// Size of the serialized header in bytes.
+    pub const SIZE: usize = 104;
+    const SIGNED_SIZE: usize = 40;
+
+    /// Parse a serialized header, returning `None` if the magic doesn't match.
+    pub fn from_bytes(bytes: &[u8; Self::SIZE]) -> Option<Self> {
+        if u32::from_le_bytes(bytes[0..4].try_into().unwrap()) != HEADER_MAGIC {
+            return None;
+        }
+        Some(Self {
+            len: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            hash: bytes[8..40].try_into().unwrap(),
+            signature: bytes[40..104].try_into().unwrap(),
+        })
+    }
+
+    /// Serialize the header.
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[..Self::SIGNED_SIZE].copy_from_slice(&self.signed_bytes());
+        bytes[40..104].copy_from_slice(&self.signature);
+        bytes
+    }
+
+    /// The part of the header covered by the signature.
+    pub fn signed_bytes(&self) -> [u8; Self::SIGNED_SIZE] {
+        let mut bytes = [0; Self::SIGNED_SIZE];
+        bytes[0..4].copy_from_slice(&HEADER_MAGIC.to_le_bytes());
+        bytes[4..8].copy_from_slice(&self.len.to_le_bytes());
+        bytes[8..40].copy_from_slice(&self.hash);
+        bytes
+    }
+
+    /// SHA-256 digest of the part of the header covered by the signature.
+    pub fn signed_digest(&self) -> [u8; 32] {
+        Sha256::digest(self.signed_bytes()).into()
+    }
+}
+
+/// Verifies the signature of a [`SignedHeader`].
+///
+/// Implement this trait to verify signatures with a hardware accelerator, like the PKA peripheral
+/// of some STM32 chips. A software implementation for ECDSA P-256 is provided as
+/// [`P256Verifier`] with the `ecdsa-p256` feature.
+pub trait HeaderVerifier {
+    /// Verify `signature` of the SHA-256 `digest`.
+    fn verify_prehash(&mut self, digest: &[u8; 32], signature: &[u8; 64]) -> Result<(), signature::Error>;
+}
+
+/// Software ECDSA P-256 verifier, holding the public key baked into the firmware.
+#[cfg(feature = "ecdsa-p256")]
+pub struct P256Verifier {
+    key: p256::ecdsa::VerifyingKey,
+}
+
+#[cfg(feature = "ecdsa-p256")]
+impl P256Verifier {
+    /// Create a verifier from a SEC1 encoded public key, either compressed (33 bytes) or
+    /// uncompressed (65 bytes).
+    pub fn from_sec1_bytes(public_key: &[u8]) -> Result<Self, signature::Error> {
+        Ok(Self {
+            key: p256::ecdsa::VerifyingKey::from_sec1_bytes(public_key)?,
+        })
+    }
+}
+
+#[cfg(feature = "ecdsa-p256")]
+impl HeaderVerifier for P256Verifier {
+    fn verify_prehash(&mut self, digest: &[u8; 32], signature: &[u8; 64]) -> Result<(), signature::Error> {
+        use p256::ecdsa::signature::hazmat::PrehashVerifier;
+
+        let signature = p256::ecdsa::Signature::from_slice(signature)?;
+        self.key.verify_prehash(digest, &signature)
+    }
+}
@@ -0,0 +1,57 @@
+use embassy_futures::block_on;
+use embedded_storage::nor_flash::{ErrorType, NorFlash, ReadNorFlash};
+use embedded_storage_async::nor_flash::{NorFlash as AsyncNorFlash, ReadNorFlash as AsyncReadNorFlash};
+
+/// Adapter running the operations of an async flash to completion, so it can be used where a
+/// blocking flash is expected, like the `embassy-boot` bootloader.
+///
+/// This allows placing the DFU and/or state partitions of the bootloader on an external flash
+/// which only has an async driver, like a QSPI or OSPI NOR flash, while the active partition is
+/// in internal flash. For the reverse direction, see [`BlockingAsync`](crate::adapter::BlockingAsync).
+///
+/// The driver must make progress without an executor running other tasks, for example by
+/// polling the peripheral or relying on interrupts, as the futures are polled in a busy loop.
+pub struct BlockOnFlash<F> {
+    wrapped: F,
+}
+
+impl<F> BlockOnFlash<F> {
+    /// Create a new adapter for the async flash.
+    pub fn new(wrapped: F) -> Self {
+        Self { wrapped }
+    }
+
+    /// Returns the wrapped flash.
+    pub fn into_inner(self) -> F {
+        self.wrapped
+    }
+}
+
+impl<F: ErrorType> ErrorType for BlockOnFlash<F> {
+    type Error = F::Error;
+}
+
+impl<F: AsyncReadNorFlash> ReadNorFlash for BlockOnFlash<F> {
+    const READ_SIZE: usize = F::READ_SIZE;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        block_on(self.wrapped.read(offset, bytes))
+    }
+
+    fn capacity(&self) -> usize {
+        self.wrapped.capacity()
+    }
+}
+
+impl<F: AsyncNorFlash> NorFlash for BlockOnFlash<F> {
+    const WRITE_SIZE: usize = F::WRITE_SIZE;
+    const ERASE_SIZE: usize = F::ERASE_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        block_on(self.wrapped.erase(from, to))
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        block_on(self.wrapped.write(offset, bytes))
+    }
+}
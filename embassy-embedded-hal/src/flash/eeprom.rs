@@ -0,0 +1,593 @@
+//! EEPROM emulation on top of NOR flash.
+//!
+//! Stores small values, like configuration or calibration data, identified by a `u16` id. The flash
+//! is split in two pages, each made of one or more erase sectors. Writing a value appends a new
+//! record to the active page instead of erasing flash, so a sector is only erased once a whole page
+//! has been filled. When the active page is full, the latest record of each id is copied to the
+//! other page, which then becomes the active one.
+//!
+//! All updates are power-loss safe: a record which was being written is detected by its checksum
+//! and ignored, so a read returns the previous value. A torn record header stops further writes to
+//! the page until it's garbage collected. The copied page is only activated by writing its header
+//! once all records are copied, so a power loss during garbage collection leaves the old page
+//! active.
+//!
+//! Page layout, with all integers in little endian:
+//! | Field                 | Description                                                   |
+//! | generation: u32       | Incremented on each garbage collection.                       |
+//! | magic: u32            | `0x45455052`, written after the generation.                   |
+//! | records...            | Padded to a multiple of the write size.                       |
+//!
+//! Record layout:
+//! | Field                 | Description                                                   |
+//! | id: u16               | Id of the value. `0xFFFF` is reserved.                        |
+//! | len: u16              | Length of the data.                                           |
+//! | crc: u32              | CRC-32 of the id, len and data.                               |
+//! | data...               | Padded with `0xFF` to a multiple of the write size.           |
+
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+
+const PAGE_MAGIC: u32 = 0x4545_5052;
+const HEADER_SIZE: usize = 8;
+const ERASED_ID: u16 = 0xFFFF;
+/// Number of ids whose latest record is looked up in each pass over the page during garbage
+/// collection.
+const COLLECT_BATCH: usize = 32;
+
+/// EEPROM emulation error
+#[derive(Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error<T> {
+    /// The working buffer is too small for the record
+    BufferTooSmall,
+    /// The value doesn't fit in a page, even after garbage collection
+    Full,
+    /// The stored value couldn't be decoded as the requested type
+    Decode,
+    /// Underlying flash error
+    Flash(T),
+}
+
+impl<T: NorFlashError> NorFlashError for Error<T> {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            Error::Full => NorFlashErrorKind::OutOfBounds,
+            Error::Flash(f) => f.kind(),
+            _ => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// A value which can be stored in an [`EmulatedEeprom`].
+pub trait EepromValue: Sized {
+    /// Serialize the value into `buf`, returning the number of bytes used, or `None` if `buf` is
+    /// too small.
+    fn to_bytes(&self, buf: &mut [u8]) -> Option<usize>;
+
+    /// Deserialize a value, returning `None` if `bytes` isn't a valid encoding.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+}
+
+macro_rules! impl_eeprom_value {
+    ($($t:ty),*) => {
+        $(
+            impl EepromValue for $t {
+                fn to_bytes(&self, buf: &mut [u8]) -> Option<usize> {
+                    let bytes = self.to_le_bytes();
+                    buf.get_mut(..bytes.len())?.copy_from_slice(&bytes);
+                    Some(bytes.len())
+                }
+
+                fn from_bytes(bytes: &[u8]) -> Option<Self> {
+                    Some(Self::from_le_bytes(bytes.try_into().ok()?))
+                }
+            }
+        )*
+    };
+}
+
+impl_eeprom_value!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+impl EepromValue for bool {
+    fn to_bytes(&self, buf: &mut [u8]) -> Option<usize> {
+        (*self as u8).to_bytes(buf)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        match u8::from_bytes(bytes)? {
+            0 => Some(false),
+            1 => Some(true),
+            _ => None,
+        }
+    }
+}
+
+impl<const N: usize> EepromValue for [u8; N] {
+    fn to_bytes(&self, buf: &mut [u8]) -> Option<usize> {
+        buf.get_mut(..N)?.copy_from_slice(self);
+        Some(N)
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        bytes.try_into().ok()
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Record {
+    offset: u32,
+    id: u16,
+    len: u16,
+    crc: u32,
+}
+
+impl Record {
+    fn size<F: NorFlash>(&self) -> usize {
+        align_up::<F>(HEADER_SIZE + self.len as usize)
+    }
+}
+
+/// What was found at an offset of a page.
+enum Slot {
+    /// A record header.
+    Record(Record),
+    /// Erased flash, where the next record can be written.
+    Free,
+    /// A header torn by a power loss, or otherwise corrupted.
+    Corrupt,
+}
+
+#[derive(Clone, Copy)]
+struct Mount {
+    page: u32,
+    generation: u32,
+    free: u32,
+}
+
+/// EEPROM emulation over a NOR flash, typically a partition of the internal flash.
+///
+/// The flash is split in two pages of half its capacity, which must be a multiple of the erase
+/// size. The working buffer must be able to hold the largest record, that is 8 bytes plus the
+/// largest value, rounded up to the write size.
+///
+/// Writing a value which is equal to the stored one doesn't write to flash.
+pub struct EmulatedEeprom<'b, F: NorFlash> {
+    flash: F,
+    buf: &'b mut [u8],
+    page_size: u32,
+    mount: Option<Mount>,
+}
+
+impl<'b, F: NorFlash> EmulatedEeprom<'b, F> {
+    /// Create a new EEPROM emulation on `flash`, using `buf` as working buffer.
+    ///
+    /// The flash isn't accessed until the first read or write.
+    pub fn new(flash: F, buf: &'b mut [u8]) -> Self {
+        let page_size = flash.capacity() / 2;
+        assert!(page_size > 0 && page_size % F::ERASE_SIZE == 0);
+        assert!(F::WRITE_SIZE % F::READ_SIZE == 0);
+        assert!(buf.len() >= align_up::<F>(HEADER_SIZE) && buf.len() % F::WRITE_SIZE == 0);
+        Self {
+            flash,
+            buf,
+            page_size: page_size as u32,
+            mount: None,
+        }
+    }
+
+    /// Returns the underlying flash.
+    pub fn into_inner(self) -> F {
+        self.flash
+    }
+
+    /// Read the value stored for `id`, or `None` if there is none.
+    pub fn read<V: EepromValue>(&mut self, id: u16) -> Result<Option<V>, Error<F::Error>> {
+        let mount = self.mount()?;
+        let Some(record) = self.find(mount.page, mount.free, id)? else {
+            return Ok(None);
+        };
+        self.read_record(mount.page, &record)?;
+        let data = &self.buf[HEADER_SIZE..HEADER_SIZE + record.len as usize];
+        V::from_bytes(data).map(Some).ok_or(Error::Decode)
+    }
+
+    /// Store `value` for `id`, replacing the previous value.
+    pub fn write<V: EepromValue + PartialEq>(&mut self, id: u16, value: &V) -> Result<(), Error<F::Error>> {
+        assert_ne!(id, ERASED_ID);
+
+        // Skip the write if the stored value is the same.
+        match self.read::<V>(id) {
+            Ok(Some(stored)) if stored == *value => return Ok(()),
+            Err(Error::Flash(e)) => return Err(Error::Flash(e)),
+            _ => {}
+        }
+
+        let mount = self.mount()?;
+        let len = value
+            .to_bytes(&mut self.buf[HEADER_SIZE..])
+            .ok_or(Error::BufferTooSmall)?;
+        let crc = record_crc(id, &self.buf[HEADER_SIZE..HEADER_SIZE + len]);
+
+        let record = Record {
+            offset: mount.free,
+            id,
+            len: len as u16,
+            crc,
+        };
+        let size = record.size::<F>();
+        if size > self.buf.len() {
+            return Err(Error::BufferTooSmall);
+        }
+        if size > self.page_size as usize - align_up::<F>(HEADER_SIZE) {
+            return Err(Error::Full);
+        }
+
+        let mut mount = mount;
+        if mount.free as usize + size > self.page_size as usize {
+            mount = self.collect(mount)?;
+            if mount.free as usize + size > self.page_size as usize {
+                return Err(Error::Full);
+            }
+        }
+
+        // Collecting uses the working buffer.
+        value.to_bytes(&mut self.buf[HEADER_SIZE..]);
+
+        self.buf[0..2].copy_from_slice(&id.to_le_bytes());
+        self.buf[2..4].copy_from_slice(&(len as u16).to_le_bytes());
+        self.buf[4..8].copy_from_slice(&crc.to_le_bytes());
+        self.buf[HEADER_SIZE + len..size].fill(0xFF);
+        self.flash
+            .write(mount.page * self.page_size + mount.free, &self.buf[..size])
+            .map_err(Error::Flash)?;
+        mount.free += size as u32;
+        self.mount = Some(mount);
+        Ok(())
+    }
+
+    /// Erase all stored values.
+    pub fn format(&mut self) -> Result<(), Error<F::Error>> {
+        self.mount = None;
+        self.flash.erase(0, 2 * self.page_size).map_err(Error::Flash)?;
+        self.mount = Some(self.activate(0, 0)?);
+        Ok(())
+    }
+
+    fn mount(&mut self) -> Result<Mount, Error<F::Error>> {
+        if let Some(mount) = self.mount {
+            return Ok(mount);
+        }
+
+        let mount = match (self.read_page_header(0)?, self.read_page_header(1)?) {
+            (None, None) => {
+                self.format()?;
+                return Ok(self.mount.unwrap());
+            }
+            (Some(generation), None) => Mount {
+                page: 0,
+                generation,
+                free: 0,
+            },
+            (None, Some(generation)) => Mount {
+                page: 1,
+                generation,
+                free: 0,
+            },
+            (Some(first), Some(second)) => {
+                if (second.wrapping_sub(first) as i32) > 0 {
+                    Mount {
+                        page: 1,
+                        generation: second,
+                        free: 0,
+                    }
+                } else {
+                    Mount {
+                        page: 0,
+                        generation: first,
+                        free: 0,
+                    }
+                }
+            }
+        };
+
+        // Nothing can be written after a corrupted header until the page is garbage collected.
+        let mut offset = align_up::<F>(HEADER_SIZE) as u32;
+        let free = loop {
+            match self.record_at(mount.page, offset)? {
+                Slot::Record(record) => offset += record.size::<F>() as u32,
+                Slot::Free => break offset,
+                Slot::Corrupt => break self.page_size,
+            }
+        };
+        let mount = Mount { free, ..mount };
+        self.mount = Some(mount);
+        Ok(mount)
+    }
+
+    fn read_page_header(&mut self, page: u32) -> Result<Option<u32>, Error<F::Error>> {
+        let len = align_up::<F>(HEADER_SIZE);
+        self.flash
+            .read(page * self.page_size, &mut self.buf[..len])
+            .map_err(Error::Flash)?;
+        let generation = u32::from_le_bytes(self.buf[0..4].try_into().unwrap());
+        let magic = u32::from_le_bytes(self.buf[4..8].try_into().unwrap());
+        Ok((magic == PAGE_MAGIC).then_some(generation))
+    }
+
+    /// Write the header of an erased page, making it the active page.
+    fn activate(&mut self, page: u32, generation: u32) -> Result<Mount, Error<F::Error>> {
+        let len = align_up::<F>(HEADER_SIZE);
+        self.buf[0..4].copy_from_slice(&generation.to_le_bytes());
+        self.buf[4..8].copy_from_slice(&PAGE_MAGIC.to_le_bytes());
+        self.buf[HEADER_SIZE..len].fill(0xFF);
+        self.flash
+            .write(page * self.page_size, &self.buf[..len])
+            .map_err(Error::Flash)?;
+        Ok(Mount {
+            page,
+            generation,
+            free: len as u32,
+        })
+    }
+
+    /// Parse the record header at `offset`.
+    ///
+    /// Both erased flash and a corrupted header end the records.
+    fn record_at(&mut self, page: u32, offset: u32) -> Result<Slot, Error<F::Error>> {
+        let len = align_up::<F>(HEADER_SIZE);
+        if offset as usize + len > self.page_size as usize {
+            return Ok(Slot::Free);
+        }
+        self.flash
+            .read(page * self.page_size + offset, &mut self.buf[..len])
+            .map_err(Error::Flash)?;
+        let record = Record {
+            offset,
+            id: u16::from_le_bytes(self.buf[0..2].try_into().unwrap()),
+            len: u16::from_le_bytes(self.buf[2..4].try_into().unwrap()),
+            crc: u32::from_le_bytes(self.buf[4..8].try_into().unwrap()),
+        };
+        if self.buf[..len].iter().all(|&b| b == 0xFF) {
+            return Ok(Slot::Free);
+        }
+        if record.id == ERASED_ID || offset as usize + record.size::<F>() > self.page_size as usize {
+            return Ok(Slot::Corrupt);
+        }
+        Ok(Slot::Record(record))
+    }
+
+    /// Read a record into the working buffer, returning whether its checksum is valid.
+    fn read_record(&mut self, page: u32, record: &Record) -> Result<bool, Error<F::Error>> {
+        let size = record.size::<F>();
+        if size > self.buf.len() {
+            return Err(Error::BufferTooSmall);
+        }
+        self.flash
+            .read(page * self.page_size + record.offset, &mut self.buf[..size])
+            .map_err(Error::Flash)?;
+        let data = &self.buf[HEADER_SIZE..HEADER_SIZE + record.len as usize];
+        Ok(record_crc(record.id, data) == record.crc)
+    }
+
+    /// Find the latest valid record for `id` in the records of `page` before `end`.
+    fn find(&mut self, page: u32, end: u32, id: u16) -> Result<Option<Record>, Error<F::Error>> {
+        let mut found = None;
+        let mut offset = align_up::<F>(HEADER_SIZE) as u32;
+        while offset < end {
+            let Slot::Record(record) = self.record_at(page, offset)? else {
+                break;
+            };
+            if record.id == id && self.read_record(page, &record)? {
+                found = Some(record);
+            }
+            offset += record.size::<F>() as u32;
+        }
+        Ok(found)
+    }
+
+    /// Copy the latest value of each id to the other page, and make it the active page.
+    ///
+    /// The ids are copied in increasing order, in batches of [`COLLECT_BATCH`] ids found in a single
+    /// pass over the page, so that usually only one pass is needed.
+    fn collect(&mut self, mount: Mount) -> Result<Mount, Error<F::Error>> {
+        let page = 1 - mount.page;
+        self.flash
+            .erase(page * self.page_size, (page + 1) * self.page_size)
+            .map_err(Error::Flash)?;
+
+        let start = align_up::<F>(HEADER_SIZE) as u32;
+        let mut free = start;
+        let mut min_id = 0u32;
+        loop {
+            // Latest valid records of the smallest ids from `min_id`, sorted by id.
+            let mut batch = [Record {
+                offset: 0,
+                id: 0,
+                len: 0,
+                crc: 0,
+            }; COLLECT_BATCH];
+            let mut len = 0;
+
+            let mut offset = start;
+            while offset < mount.free {
+                let Slot::Record(record) = self.record_at(mount.page, offset)? else {
+                    break;
+                };
+                offset += record.size::<F>() as u32;
+                if (record.id as u32) < min_id || !self.read_record(mount.page, &record)? {
+                    continue;
+                }
+                match batch[..len].binary_search_by_key(&record.id, |r| r.id) {
+                    Ok(i) => batch[i] = record,
+                    Err(i) if i < COLLECT_BATCH => {
+                        // Drop the largest id if the batch is full, it's handled by the next pass.
+                        len = core::cmp::min(len + 1, COLLECT_BATCH);
+                        batch.copy_within(i..len - 1, i + 1);
+                        batch[i] = record;
+                    }
+                    Err(_) => {}
+                }
+            }
+
+            for record in &batch[..len] {
+                self.read_record(mount.page, record)?;
+                self.flash
+                    .write(page * self.page_size + free, &self.buf[..record.size::<F>()])
+                    .map_err(Error::Flash)?;
+                free += record.size::<F>() as u32;
+            }
+
+            if len < COLLECT_BATCH {
+                break;
+            }
+            min_id = batch[len - 1].id as u32 + 1;
+        }
+
+        let mount = Mount {
+            free,
+            ..self.activate(page, mount.generation.wrapping_add(1))?
+        };
+        self.mount = Some(mount);
+        Ok(mount)
+    }
+}
+
+const fn align_up<F: NorFlash>(len: usize) -> usize {
+    len.div_ceil(F::WRITE_SIZE) * F::WRITE_SIZE
+}
+
+fn record_crc(id: u16, data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in id
+        .to_le_bytes()
+        .iter()
+        .chain(&(data.len() as u16).to_le_bytes())
+        .chain(data)
+    {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flash::mem_flash::MemFlash;
+
+    #[test]
+    fn can_read_and_write() {
+        let mut buf = [0; 32];
+        let mut eeprom = EmulatedEeprom::new(MemFlash::<1024, 256, 8>::default(), &mut buf);
+
+        assert_eq!(eeprom.read::<u32>(1), Ok(None));
+        eeprom.write(1, &0xDEAD_BEEFu32).unwrap();
+        eeprom.write(2, &[1u8, 2, 3]).unwrap();
+        eeprom.write(1, &42u32).unwrap();
+        assert_eq!(eeprom.read::<u32>(1), Ok(Some(42)));
+        assert_eq!(eeprom.read::<[u8; 3]>(2), Ok(Some([1, 2, 3])));
+        assert_eq!(eeprom.read::<u16>(1), Err(Error::Decode));
+
+        // Writing the same value doesn't write to flash.
+        let flash = eeprom.into_inner();
+        let writes = flash.writes.len();
+        let mut eeprom = EmulatedEeprom::new(flash, &mut buf);
+        eeprom.write(1, &42u32).unwrap();
+        assert_eq!(eeprom.read::<u32>(1), Ok(Some(42)));
+        assert_eq!(eeprom.into_inner().writes.len(), writes);
+    }
+
+    #[test]
+    fn collects_full_page() {
+        let mut buf = [0; 32];
+        let mut eeprom = EmulatedEeprom::new(MemFlash::<1024, 256, 8>::default(), &mut buf);
+
+        eeprom.write(7, &true).unwrap();
+        for i in 0..100u64 {
+            eeprom.write(1, &i).unwrap();
+            eeprom.write(2, &(i as u16)).unwrap();
+        }
+        assert_eq!(eeprom.read::<u64>(1), Ok(Some(99)));
+        assert_eq!(eeprom.read::<u16>(2), Ok(Some(99)));
+        assert_eq!(eeprom.read::<bool>(7), Ok(Some(true)));
+
+        // Values survive a remount.
+        let flash = eeprom.into_inner();
+        assert!(!flash.erases.is_empty());
+        let mut eeprom = EmulatedEeprom::new(flash, &mut buf);
+        assert_eq!(eeprom.read::<u64>(1), Ok(Some(99)));
+        assert_eq!(eeprom.read::<bool>(7), Ok(Some(true)));
+    }
+
+    #[test]
+    fn ignores_torn_writes() {
+        let mut buf = [0; 32];
+        let mut eeprom = EmulatedEeprom::new(MemFlash::<1024, 256, 8>::default(), &mut buf);
+        eeprom.write(1, &1u32).unwrap();
+        eeprom.write(1, &2u32).unwrap();
+
+        // Simulate a power loss while writing the data of the last record.
+        let mut flash = eeprom.into_inner();
+        flash.mem[24 + 8..24 + 16].fill(0xFF);
+        let mut eeprom = EmulatedEeprom::new(flash, &mut buf);
+        assert_eq!(eeprom.read::<u32>(1), Ok(Some(1)));
+
+        // Simulate a power loss during garbage collection, before the new page is activated.
+        let mut flash = eeprom.into_inner();
+        flash.mem[512 + 8..512 + 24].fill(0);
+        let mut eeprom = EmulatedEeprom::new(flash, &mut buf);
+        assert_eq!(eeprom.read::<u32>(1), Ok(Some(1)));
+        eeprom.write(2, &3u32).unwrap();
+        assert_eq!(eeprom.read::<u32>(2), Ok(Some(3)));
+    }
+    #[test]
+    fn collects_more_ids_than_a_batch() {
+        let mut buf = [0; 32];
+        let mut eeprom = EmulatedEeprom::new(MemFlash::<8192, 1024, 4>::default(), &mut buf);
+
+        let ids = COLLECT_BATCH as u16 + 8;
+        for round in 0..10u32 {
+            for id in 0..ids {
+                eeprom.write(id, &(round * 100 + id as u32)).unwrap();
+            }
+        }
+
+        let flash = eeprom.into_inner();
+        assert!(!flash.erases.is_empty());
+        let mut eeprom = EmulatedEeprom::new(flash, &mut buf);
+        for id in 0..ids {
+            assert_eq!(eeprom.read::<u32>(id), Ok(Some(900 + id as u32)));
+        }
+    }
+
+    #[test]
+    fn stops_writing_after_torn_header() {
+        let mut buf = [0; 32];
+        let mut eeprom = EmulatedEeprom::new(MemFlash::<1024, 256, 8>::default(), &mut buf);
+        eeprom.write(1, &1u32).unwrap();
+
+        // Simulate a power loss while writing the header of the next record, with the id still
+        // erased.
+        let mut flash = eeprom.into_inner();
+        flash.mem[24 + 2..24 + 4].copy_from_slice(&4u16.to_le_bytes());
+        let writes = flash.writes.len();
+        let mut eeprom = EmulatedEeprom::new(flash, &mut buf);
+        assert_eq!(eeprom.read::<u32>(1), Ok(Some(1)));
+
+        // The torn header is not written over, the page is garbage collected instead.
+        eeprom.write(1, &2u32).unwrap();
+        assert_eq!(eeprom.read::<u32>(1), Ok(Some(2)));
+        let flash = eeprom.into_inner();
+        assert!(!flash.erases.is_empty());
+        assert!(!flash.writes[writes..].iter().any(|&(offset, _)| offset == 24));
+
+        let mut eeprom = EmulatedEeprom::new(flash, &mut buf);
+        assert_eq!(eeprom.read::<u32>(1), Ok(Some(2)));
+    }
+}
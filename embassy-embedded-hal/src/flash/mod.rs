@@ -1,8 +1,11 @@
 //! Utilities related to flash.
 
+mod block_on_flash;
 mod concat_flash;
+pub mod eeprom;
 #[cfg(test)]
 pub(crate) mod mem_flash;
 pub mod partition;
 
+pub use block_on_flash::BlockOnFlash;
 pub use concat_flash::ConcatFlash;
@@ -98,11 +98,23 @@ mod thread {
         ///
         /// This function never returns.
         pub fn run(&'static mut self, init: impl FnOnce(Spawner)) -> ! {
+            self.run_with_idle(init, || {})
+        }
+
+        /// Run the executor, calling `idle` each time it goes to sleep.
+        ///
+        /// `idle` is called when all ready tasks have been polled, right before the `WFE`. It can
+        /// for example select a deeper sleep mode. Tasks woken while `idle` runs are not missed,
+        /// the `WFE` then returns immediately.
+        ///
+        /// See [`Executor::run`] for details.
+        pub fn run_with_idle(&'static mut self, init: impl FnOnce(Spawner), mut idle: impl FnMut()) -> ! {
             init(self.inner.spawner());
 
             loop {
                 unsafe {
                     self.inner.poll();
+                    idle();
                     asm!("wfe");
                 };
             }
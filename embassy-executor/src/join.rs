@@ -0,0 +1,197 @@
+use core::cell::RefCell;
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll, Waker};
+
+use critical_section::Mutex;
+
+/// Error returned when awaiting a [`JoinHandle`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum JoinError {
+    /// The task was aborted with [`JoinHandle::abort()`] before it finished.
+    Aborted,
+}
+
+impl core::fmt::Display for JoinError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            JoinError::Aborted => write!(f, "Aborted - The task was aborted before it finished."),
+        }
+    }
+}
+
+impl core::error::Error for JoinError {}
+
+struct SlotInner<T> {
+    output: Option<Result<T, JoinError>>,
+    joined: bool,
+    abort: bool,
+    handle_alive: bool,
+    task_waker: Option<Waker>,
+    join_waker: Option<Waker>,
+}
+
+/// Shared state between a task spawned with a handle and its [`JoinHandle`].
+pub(crate) struct JoinSlot<T> {
+    inner: Mutex<RefCell<SlotInner<T>>>,
+}
+
+impl<T> JoinSlot<T> {
+    pub(crate) const fn new() -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(SlotInner {
+                output: None,
+                joined: false,
+                abort: false,
+                handle_alive: false,
+                task_waker: None,
+                join_waker: None,
+            })),
+        }
+    }
+
+    /// Returns whether the slot can be used for a new task, i.e. the previous handle was dropped.
+    pub(crate) fn available(&self) -> bool {
+        critical_section::with(|cs| !self.inner.borrow_ref(cs).handle_alive)
+    }
+
+    /// Reset the slot for a newly spawned task, and create its handle.
+    pub(crate) fn handle(&'static self) -> JoinHandle<T> {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.output = None;
+            inner.joined = false;
+            inner.abort = false;
+            inner.handle_alive = true;
+            inner.task_waker = None;
+            inner.join_waker = None;
+        });
+        JoinHandle { slot: self }
+    }
+
+    fn finish(&self, output: Result<T, JoinError>) {
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+            inner.task_waker = None;
+            if inner.handle_alive {
+                inner.output = Some(output);
+            }
+            if let Some(waker) = inner.join_waker.take() {
+                waker.wake();
+            }
+        })
+    }
+}
+
+/// Future of a task spawned with a handle, storing the output of `F` in the slot.
+pub(crate) struct Joinable<F: Future + 'static> {
+    future: F,
+    slot: &'static JoinSlot<F::Output>,
+}
+
+impl<F: Future + 'static> Joinable<F> {
+    pub(crate) fn new(future: F, slot: &'static JoinSlot<F::Output>) -> Self {
+        Self { future, slot }
+    }
+}
+
+impl<F: Future + 'static> Future for Joinable<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = unsafe { self.get_unchecked_mut() };
+        let slot = this.slot;
+
+        let abort = critical_section::with(|cs| {
+            let mut inner = slot.inner.borrow_ref_mut(cs);
+            if !inner.abort {
+                match &inner.task_waker {
+                    Some(w) if w.will_wake(cx.waker()) => {}
+                    _ => inner.task_waker = Some(cx.waker().clone()),
+                }
+            }
+            inner.abort
+        });
+        if abort {
+            // The future is dropped by the task storage once this returns.
+            slot.finish(Err(JoinError::Aborted));
+            return Poll::Ready(());
+        }
+
+        match unsafe { Pin::new_unchecked(&mut this.future) }.poll(cx) {
+            Poll::Ready(output) => {
+                slot.finish(Ok(output));
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+/// Handle to a task spawned with [`Spawner::spawn_with_handle()`](crate::Spawner::spawn_with_handle).
+///
+/// Awaiting the handle waits for the task to finish and returns its output. Dropping the handle
+/// detaches the task, which keeps running and whose output is dropped.
+pub struct JoinHandle<T: 'static> {
+    slot: &'static JoinSlot<T>,
+}
+
+impl<T> JoinHandle<T> {
+    /// Request the task to stop.
+    ///
+    /// The task is dropped the next time it would be polled, that is at its current await point
+    /// once it's woken, and awaiting the handle returns [`JoinError::Aborted`]. If the task has
+    /// already finished, this does nothing.
+    pub fn abort(&self) {
+        let waker = critical_section::with(|cs| {
+            let mut inner = self.slot.inner.borrow_ref_mut(cs);
+            inner.abort = true;
+            inner.task_waker.take()
+        });
+        if let Some(waker) = waker {
+            waker.wake();
+        }
+    }
+
+    /// Returns whether the task has finished, or has been dropped after an abort.
+    pub fn is_finished(&self) -> bool {
+        critical_section::with(|cs| {
+            let inner = self.slot.inner.borrow_ref(cs);
+            inner.joined || inner.output.is_some()
+        })
+    }
+}
+
+impl<T> Future for JoinHandle<T> {
+    type Output = Result<T, JoinError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        critical_section::with(|cs| {
+            let mut inner = self.slot.inner.borrow_ref_mut(cs);
+            match inner.output.take() {
+                Some(output) => {
+                    inner.joined = true;
+                    Poll::Ready(output)
+                }
+                None => {
+                    assert!(!inner.joined, "JoinHandle polled after completion");
+                    inner.join_waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+}
+
+impl<T> Drop for JoinHandle<T> {
+    fn drop(&mut self) {
+        let output = critical_section::with(|cs| {
+            let mut inner = self.slot.inner.borrow_ref_mut(cs);
+            inner.handle_alive = false;
+            inner.join_waker = None;
+            inner.output.take()
+        });
+        drop(output);
+    }
+}
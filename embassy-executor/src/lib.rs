@@ -51,6 +51,8 @@ pub use embassy_executor_macros::main_unspecified as main;
 
 pub mod raw;
 
+mod join;
+pub use join::{JoinError, JoinHandle};
 mod spawner;
 pub use spawner::*;
 
@@ -0,0 +1,174 @@
+use core::any::TypeId;
+use core::cell::RefCell;
+use core::future::Future;
+use core::mem::{self, MaybeUninit};
+
+use critical_section::Mutex;
+
+use super::{AvailableTask, TaskStorage};
+use crate::join::{JoinHandle, JoinSlot, Joinable};
+use crate::SpawnToken;
+
+/// Header of each task storage allocated in a [`TaskArena`].
+struct BlockHeader {
+    type_id: TypeId,
+    next: *const BlockHeader,
+}
+
+// repr(C) is needed to guarantee that the header is located at offset 0
+// This makes it safe to cast between BlockHeader and Block pointers.
+#[repr(C)]
+struct Block<F: Future + 'static, E> {
+    header: BlockHeader,
+    /// Data kept alongside the task, like the output of a task spawned with a handle.
+    extra: E,
+    task: TaskStorage<F>,
+}
+
+struct Inner {
+    buffer: *mut u8,
+    len: usize,
+    used: usize,
+    blocks: *const BlockHeader,
+}
+
+impl Inner {
+    fn alloc<T>(&mut self) -> Option<*mut T> {
+        let addr = (self.buffer as usize).wrapping_add(self.used);
+        let padding = addr.wrapping_neg() & (mem::align_of::<T>() - 1);
+        let offset = self.used.checked_add(padding)?;
+        let end = offset.checked_add(mem::size_of::<T>())?;
+        if end > self.len {
+            return None;
+        }
+        self.used = end;
+        Some(unsafe { self.buffer.add(offset) }.cast())
+    }
+}
+
+/// Task storage allocated on demand from a user-provided buffer.
+///
+/// A [`TaskPool`](super::TaskPool) reserves storage for a fixed number of tasks of a single type at
+/// compile time. A `TaskArena` instead carves the storage of each task out of a byte buffer when
+/// the task is spawned, so the number of tasks that can run concurrently is only limited by the
+/// size of the buffer. This is useful for example for connection handlers, where the buffer can
+/// take whatever RAM is left after the static allocations.
+///
+/// Memory is never returned to the buffer, because a task storage must live forever. Instead,
+/// when a task finishes running, its storage is reused by the next task spawned with the same
+/// future type. The arena therefore grows up to the largest number of tasks of each type that
+/// were running at the same time.
+///
+/// ```rust,ignore
+/// static ARENA_BUF: StaticCell<[MaybeUninit<u8>; 16384]> = StaticCell::new();
+///
+/// let arena = TaskArena::new(ARENA_BUF.init([MaybeUninit::uninit(); 16384]));
+/// loop {
+///     let socket = accept().await;
+///     if spawner.spawn(arena.spawn(move || handle_connection(socket))).is_err() {
+///         warn!("out of task memory, dropping connection");
+///     }
+/// }
+/// ```
+pub struct TaskArena {
+    inner: Mutex<RefCell<Inner>>,
+}
+
+// The buffer is only accessed while holding the critical section, and the task storages
+// allocated from it are `Sync`.
+unsafe impl Send for TaskArena {}
+unsafe impl Sync for TaskArena {}
+
+impl TaskArena {
+    /// Create a new arena allocating task storage from `buffer`.
+    pub fn new(buffer: &'static mut [MaybeUninit<u8>]) -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(Inner {
+                buffer: buffer.as_mut_ptr().cast(),
+                len: buffer.len(),
+                used: 0,
+                blocks: core::ptr::null(),
+            })),
+        }
+    }
+
+    /// Number of bytes of the buffer that haven't been allocated yet.
+    pub fn free_bytes(&self) -> usize {
+        critical_section::with(|cs| {
+            let inner = self.inner.borrow_ref(cs);
+            inner.len - inner.used
+        })
+    }
+
+    fn claim<F: Future + 'static, E: 'static>(
+        &self,
+        extra: impl FnOnce() -> E,
+        available: impl Fn(&E) -> bool,
+    ) -> Option<(&'static E, AvailableTask<F>)> {
+        let type_id = TypeId::of::<Block<F, E>>();
+        critical_section::with(|cs| {
+            let mut inner = self.inner.borrow_ref_mut(cs);
+
+            // Reuse a finished task of the same type if possible.
+            let mut current = inner.blocks;
+            while let Some(header) = unsafe { current.as_ref() } {
+                if header.type_id == type_id {
+                    let block: &'static Block<F, E> = unsafe { &*(current as *const Block<F, E>) };
+                    if available(&block.extra) {
+                        if let Some(task) = AvailableTask::claim(&block.task) {
+                            return Some((&block.extra, task));
+                        }
+                    }
+                }
+                current = header.next;
+            }
+
+            let block = inner.alloc::<Block<F, E>>()?;
+            unsafe {
+                block.write(Block {
+                    header: BlockHeader {
+                        type_id,
+                        next: inner.blocks,
+                    },
+                    extra: extra(),
+                    task: TaskStorage::new(),
+                });
+            }
+            inner.blocks = block as *const BlockHeader;
+
+            // The buffer is `'static` and blocks are never freed.
+            let block: &'static Block<F, E> = unsafe { &*block };
+            AvailableTask::claim(&block.task).map(|task| (&block.extra, task))
+        })
+    }
+
+    /// Try to spawn a task with storage from the arena.
+    ///
+    /// See [`TaskStorage::spawn()`] for details.
+    ///
+    /// This reuses the storage of a finished task with the same future type, or allocates new
+    /// storage from the buffer. If the buffer is exhausted, a "poisoned" SpawnToken is returned,
+    /// which will cause [`Spawner::spawn()`](super::super::Spawner::spawn) to return the error.
+    pub fn spawn<F: Future + 'static>(&self, future: impl FnOnce() -> F) -> SpawnToken<impl Sized> {
+        match self.claim::<F, ()>(|| (), |_| true) {
+            Some((_, task)) => task.initialize_impl::<F>(future),
+            None => SpawnToken::new_failed(),
+        }
+    }
+
+    /// Claim storage for a task whose output is kept for a [`JoinHandle`].
+    ///
+    /// Storage is reused once the task has finished and its handle has been dropped.
+    pub(crate) fn spawn_with_handle<S, F>(
+        &self,
+        future: impl FnOnce() -> F,
+    ) -> Option<(SpawnToken<S>, JoinHandle<F::Output>)>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let (slot, task) = self.claim::<Joinable<F>, JoinSlot<F::Output>>(JoinSlot::new, JoinSlot::available)?;
+        let handle = slot.handle();
+        Some((task.initialize_impl::<S>(|| Joinable::new(future(), slot)), handle))
+    }
+}
@@ -0,0 +1,222 @@
+//! # Metrics
+//!
+//! The `metrics` feature records how much time the executor spends polling each task, to find the
+//! tasks that hog the executor and delay the others, and how much time each executor spends
+//! polling in total, to measure the CPU load.
+//!
+//! Times are measured with the `embassy-time-driver` clock and reported in its ticks, see
+//! [`embassy_time_driver::TICK_HZ`]. A time driver must therefore be available when the feature is
+//! enabled.
+//!
+//! ```rust,ignore
+//! use embassy_executor::raw::metrics::{for_each_task, TaskRefMetrics};
+//!
+//! for_each_task(|task| {
+//!     let stats = task.stats();
+//!     info!("{} polls, max {} ticks", stats.polls, stats.max_poll_ticks);
+//! });
+//!
+//! // CPU load of the current executor over the last second, in per mille.
+//! let before = spawner.executor_stats();
+//! Timer::after_secs(1).await;
+//! let load = spawner.executor_stats().load_since(&before);
+//! ```
+
+use core::cell::Cell;
+use core::sync::atomic::Ordering;
+
+use critical_section::Mutex;
+
+use super::{AtomicPtr, SyncExecutor, TaskHeader, TaskRef};
+
+/// List of all tasks that were spawned at least once.
+static TASKS: AtomicPtr<TaskHeader> = AtomicPtr::new(core::ptr::null_mut());
+
+/// Poll statistics of a task.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TaskStats {
+    /// Number of times the task was polled.
+    pub polls: u32,
+    /// Cumulative time spent polling the task, in ticks.
+    pub total_poll_ticks: u64,
+    /// Longest single poll of the task, in ticks.
+    pub max_poll_ticks: u64,
+}
+
+impl TaskStats {
+    /// Average time spent in a single poll of the task, in ticks.
+    pub fn mean_poll_ticks(&self) -> u64 {
+        if self.polls == 0 {
+            0
+        } else {
+            self.total_poll_ticks / self.polls as u64
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Entry {
+    stats: TaskStats,
+    registered: bool,
+}
+
+pub(crate) struct TaskMetrics {
+    entry: Mutex<Cell<Entry>>,
+    next: AtomicPtr<TaskHeader>,
+}
+
+impl TaskMetrics {
+    pub(crate) const fn new() -> Self {
+        Self {
+            entry: Mutex::new(Cell::new(Entry {
+                stats: TaskStats {
+                    polls: 0,
+                    total_poll_ticks: 0,
+                    max_poll_ticks: 0,
+                },
+                registered: false,
+            })),
+            next: AtomicPtr::new(core::ptr::null_mut()),
+        }
+    }
+}
+
+/// Extension trait for `TaskRef` to access the task's poll statistics.
+///
+/// This trait is only available when the `metrics` feature is enabled.
+pub trait TaskRefMetrics {
+    /// Get the poll statistics of the task since it was last spawned or reset.
+    fn stats(&self) -> TaskStats;
+
+    /// Reset the poll statistics of the task.
+    fn reset_stats(&self);
+}
+
+impl TaskRefMetrics for TaskRef {
+    fn stats(&self) -> TaskStats {
+        critical_section::with(|cs| self.header().metrics.entry.borrow(cs).get().stats)
+    }
+
+    fn reset_stats(&self) {
+        critical_section::with(|cs| {
+            let entry = self.header().metrics.entry.borrow(cs);
+            entry.set(Entry {
+                stats: TaskStats::default(),
+                ..entry.get()
+            });
+        })
+    }
+}
+
+/// Call `f` for each task that was spawned at least once, including tasks that have finished.
+pub fn for_each_task(mut f: impl FnMut(TaskRef)) {
+    let mut current = TASKS.load(Ordering::Acquire);
+    while !current.is_null() {
+        let task = unsafe { TaskRef::from_ptr(current) };
+        f(task);
+        current = task.header().metrics.next.load(Ordering::Acquire);
+    }
+}
+
+pub(crate) fn task_new(task: &TaskRef) {
+    let metrics = &task.header().metrics;
+    critical_section::with(|cs| {
+        let entry = metrics.entry.borrow(cs);
+        if !entry.get().registered {
+            metrics.next.store(TASKS.load(Ordering::Relaxed), Ordering::Relaxed);
+            TASKS.store(task.as_ptr() as *mut TaskHeader, Ordering::Release);
+        }
+        entry.set(Entry {
+            stats: TaskStats::default(),
+            registered: true,
+        });
+    })
+}
+
+pub(crate) fn task_polled(task: &TaskRef, ticks: u64) {
+    critical_section::with(|cs| {
+        let entry = task.header().metrics.entry.borrow(cs);
+        let mut e = entry.get();
+        e.stats.polls = e.stats.polls.wrapping_add(1);
+        e.stats.total_poll_ticks += ticks;
+        e.stats.max_poll_ticks = e.stats.max_poll_ticks.max(ticks);
+        entry.set(e);
+    })
+}
+
+/// Busy-time statistics of an executor.
+///
+/// The executor is busy while it polls tasks, and idle otherwise. For a thread-mode executor the
+/// idle time is spent sleeping, for an interrupt executor it is left to lower priority code.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ExecutorStats {
+    /// Time at which the statistics were read, in ticks.
+    pub timestamp: u64,
+    /// Cumulative time spent polling tasks, in ticks.
+    pub busy_ticks: u64,
+    /// Number of times the executor was polled.
+    pub polls: u32,
+}
+
+impl ExecutorStats {
+    /// Time the executor was idle between `earlier` and these statistics, in ticks.
+    pub fn idle_ticks_since(&self, earlier: &ExecutorStats) -> u64 {
+        let elapsed = self.timestamp.saturating_sub(earlier.timestamp);
+        elapsed.saturating_sub(self.busy_ticks.saturating_sub(earlier.busy_ticks))
+    }
+
+    /// Fraction of the time the executor was busy between `earlier` and these statistics,
+    /// in per mille (0 to 1000).
+    pub fn load_since(&self, earlier: &ExecutorStats) -> u32 {
+        let elapsed = self.timestamp.saturating_sub(earlier.timestamp);
+        if elapsed == 0 {
+            return 0;
+        }
+        let busy = self.busy_ticks.saturating_sub(earlier.busy_ticks).min(elapsed);
+        (busy as u128 * 1000 / elapsed as u128) as u32
+    }
+}
+
+#[derive(Clone, Copy)]
+struct ExecutorEntry {
+    busy_ticks: u64,
+    polls: u32,
+}
+
+pub(crate) struct ExecutorMetrics {
+    entry: Mutex<Cell<ExecutorEntry>>,
+}
+
+impl ExecutorMetrics {
+    pub(crate) const fn new() -> Self {
+        Self {
+            entry: Mutex::new(Cell::new(ExecutorEntry {
+                busy_ticks: 0,
+                polls: 0,
+            })),
+        }
+    }
+}
+
+pub(crate) fn executor_polled(executor: &SyncExecutor, ticks: u64) {
+    critical_section::with(|cs| {
+        let entry = executor.metrics.entry.borrow(cs);
+        let mut e = entry.get();
+        e.busy_ticks += ticks;
+        e.polls = e.polls.wrapping_add(1);
+        entry.set(e);
+    })
+}
+
+pub(crate) fn executor_stats(executor: &SyncExecutor) -> ExecutorStats {
+    critical_section::with(|cs| {
+        let e = executor.metrics.entry.borrow(cs).get();
+        ExecutorStats {
+            timestamp: embassy_time_driver::now(),
+            busy_ticks: e.busy_ticks,
+            polls: e.polls,
+        }
+    })
+}
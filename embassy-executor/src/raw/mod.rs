@@ -7,6 +7,7 @@
 //! Using this module requires respecting subtle safety contracts. If you can, prefer using the safe
 //! [executor wrappers](crate::Executor) and the [`embassy_executor::task`](embassy_executor_macros::task) macro, which are fully safe.
 
+mod arena;
 #[cfg_attr(target_has_atomic = "ptr", path = "run_queue_atomics.rs")]
 #[cfg_attr(not(target_has_atomic = "ptr"), path = "run_queue_critical_section.rs")]
 mod run_queue;
@@ -16,6 +17,10 @@ mod run_queue;
 #[cfg_attr(not(target_has_atomic = "8"), path = "state_critical_section.rs")]
 mod state;
 
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "spawn-wait")]
+mod spawn_waiters;
 pub mod timer_queue;
 #[cfg(feature = "trace")]
 pub mod trace;
@@ -36,7 +41,10 @@ use core::task::{Context, Poll};
 #[cfg(feature = "arch-avr")]
 use portable_atomic::AtomicPtr;
 
+pub use self::arena::TaskArena;
 use self::run_queue::{RunQueue, RunQueueItem};
+#[cfg(feature = "spawn-wait")]
+pub(crate) use self::spawn_waiters::SpawnWaiter;
 use self::state::State;
 use self::util::{SyncUnsafeCell, UninitCell};
 pub use self::waker::task_from_waker;
@@ -95,6 +103,8 @@ pub(crate) struct TaskHeader {
     pub(crate) id: u32,
     #[cfg(feature = "trace")]
     all_tasks_next: AtomicPtr<TaskHeader>,
+    #[cfg(feature = "metrics")]
+    pub(crate) metrics: metrics::TaskMetrics,
 }
 
 /// This is essentially a `&'static TaskStorage<F>` where the type of the future has been erased.
@@ -196,6 +206,8 @@ impl<F: Future + 'static> TaskStorage<F> {
                 id: 0,
                 #[cfg(feature = "trace")]
                 all_tasks_next: AtomicPtr::new(core::ptr::null_mut()),
+                #[cfg(feature = "metrics")]
+                metrics: metrics::TaskMetrics::new(),
             },
             future: UninitCell::uninit(),
         }
@@ -245,6 +257,9 @@ impl<F: Future + 'static> TaskStorage<F> {
                 // after we're done with it.
                 this.raw.state.despawn();
 
+                #[cfg(feature = "spawn-wait")]
+                spawn_waiters::wake();
+
                 #[cfg(feature = "trace")]
                 trace::task_end(exec_ptr, &p);
             }
@@ -400,6 +415,8 @@ impl Pender {
 pub(crate) struct SyncExecutor {
     run_queue: RunQueue,
     pender: Pender,
+    #[cfg(feature = "metrics")]
+    metrics: metrics::ExecutorMetrics,
 }
 
 impl SyncExecutor {
@@ -407,6 +424,8 @@ impl SyncExecutor {
         Self {
             run_queue: RunQueue::new(),
             pender,
+            #[cfg(feature = "metrics")]
+            metrics: metrics::ExecutorMetrics::new(),
         }
     }
 
@@ -434,6 +453,9 @@ impl SyncExecutor {
         #[cfg(feature = "trace")]
         trace::task_new(self, &task);
 
+        #[cfg(feature = "metrics")]
+        metrics::task_new(&task);
+
         state::locked(|l| {
             self.enqueue(task, l);
         })
@@ -446,22 +468,39 @@ impl SyncExecutor {
         #[cfg(feature = "trace")]
         trace::poll_start(self);
 
+        #[cfg(feature = "metrics")]
+        let poll_start = embassy_time_driver::now();
+
         self.run_queue.dequeue_all(|p| {
             let task = p.header();
 
             #[cfg(feature = "trace")]
             trace::task_exec_begin(self, &p);
 
+            #[cfg(feature = "metrics")]
+            let start = embassy_time_driver::now();
+
             // Run the task
             task.poll_fn.get().unwrap_unchecked()(p);
 
+            #[cfg(feature = "metrics")]
+            metrics::task_polled(&p, embassy_time_driver::now().saturating_sub(start));
+
             #[cfg(feature = "trace")]
             trace::task_exec_end(self, &p);
         });
 
+        #[cfg(feature = "metrics")]
+        metrics::executor_polled(self, embassy_time_driver::now().saturating_sub(poll_start));
+
         #[cfg(feature = "trace")]
         trace::executor_idle(self)
     }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn stats(&self) -> metrics::ExecutorStats {
+        metrics::executor_stats(self)
+    }
 }
 
 /// Raw executor.
@@ -571,6 +610,12 @@ impl Executor {
     pub fn id(&'static self) -> usize {
         &self.inner as *const SyncExecutor as usize
     }
+
+    /// Get the busy-time statistics of this executor.
+    #[cfg(feature = "metrics")]
+    pub fn stats(&self) -> metrics::ExecutorStats {
+        self.inner.stats()
+    }
 }
 
 /// Wake a task by `TaskRef`.
@@ -0,0 +1,111 @@
+use core::cell::{Cell, RefCell};
+use core::marker::PhantomPinned;
+use core::pin::Pin;
+use core::ptr::NonNull;
+use core::task::Waker;
+
+use critical_section::{CriticalSection, Mutex};
+
+/// Pointer to a [`SpawnWaiter`] linked in the waiter list.
+#[derive(Clone, Copy)]
+struct WaiterRef(NonNull<SpawnWaiter>);
+
+// Safety: the pointed waiter is only accessed in a critical section.
+unsafe impl Send for WaiterRef {}
+
+impl WaiterRef {
+    fn links<'a>(self, cs: CriticalSection<'a>) -> &'a RefCell<Links> {
+        // Safety: a waiter unlinks itself before it's dropped, and it can't move while it's
+        // linked because it's pinned.
+        unsafe { self.0.as_ref() }.links.borrow(cs)
+    }
+}
+
+struct Links {
+    waker: Option<Waker>,
+    prev: Option<WaiterRef>,
+    next: Option<WaiterRef>,
+    linked: bool,
+}
+
+/// Head of the list of tasks waiting for a task to finish, to spawn a task in its storage.
+static WAITERS: Mutex<Cell<Option<WaiterRef>>> = Mutex::new(Cell::new(None));
+
+/// A task waiting to spawn, linked in the waiter list while it's alive.
+///
+/// The waiter lives in the waiting future, so any number of tasks can wait.
+pub(crate) struct SpawnWaiter {
+    links: Mutex<RefCell<Links>>,
+    _pin: PhantomPinned,
+}
+
+impl SpawnWaiter {
+    pub const fn new() -> Self {
+        Self {
+            links: Mutex::new(RefCell::new(Links {
+                waker: None,
+                prev: None,
+                next: None,
+                linked: false,
+            })),
+            _pin: PhantomPinned,
+        }
+    }
+
+    /// Register `waker` to be woken when a task finishes.
+    pub fn register(self: Pin<&Self>, waker: &Waker) {
+        critical_section::with(|cs| {
+            let this = WaiterRef(NonNull::from(self.get_ref()));
+            let mut links = this.links(cs).borrow_mut();
+            match &links.waker {
+                Some(w) if w.will_wake(waker) => {}
+                _ => links.waker = Some(waker.clone()),
+            }
+
+            if !links.linked {
+                let head = WAITERS.borrow(cs);
+                links.prev = None;
+                links.next = head.get();
+                if let Some(next) = links.next {
+                    next.links(cs).borrow_mut().prev = Some(this);
+                }
+                head.set(Some(this));
+                links.linked = true;
+            }
+        })
+    }
+}
+
+impl Drop for SpawnWaiter {
+    fn drop(&mut self) {
+        critical_section::with(|cs| {
+            let links = self.links.borrow(cs).borrow();
+            if !links.linked {
+                return;
+            }
+            match links.prev {
+                Some(prev) => prev.links(cs).borrow_mut().next = links.next,
+                None => WAITERS.borrow(cs).set(links.next),
+            }
+            if let Some(next) = links.next {
+                next.links(cs).borrow_mut().prev = links.prev;
+            }
+        })
+    }
+}
+
+/// Wake all the tasks waiting to spawn, called when a task has finished and its storage is free.
+pub(crate) fn wake() {
+    critical_section::with(|cs| {
+        let mut cur = WAITERS.borrow(cs).get();
+        while let Some(waiter) = cur {
+            let mut links = waiter.links(cs).borrow_mut();
+            cur = links.next;
+            let waker = links.waker.take();
+            drop(links);
+            if let Some(waker) = waker {
+                waker.wake();
+            }
+        }
+    })
+}
@@ -22,6 +22,29 @@
 //! Callbacks can be used by enabling the `trace` feature, and providing implementations of the
 //! `extern "Rust"` functions below. All callbacks must be implemented.
 //!
+//! Instead of implementing the functions directly, you can implement the [`Tracer`] trait, which
+//! has empty default implementations for the events you don't need, and generate the callbacks
+//! with the [`tracer!`](crate::tracer) macro:
+//!
+//! ```rust,ignore
+//! struct Timeline;
+//!
+//! impl embassy_executor::raw::trace::Tracer for Timeline {
+//!     fn task_exec_begin(executor_id: u32, task_id: u32) {
+//!         defmt::info!("{=u32:x} poll start {=u32:x}", executor_id, task_id);
+//!     }
+//!
+//!     fn task_exec_end(executor_id: u32, task_id: u32) {
+//!         defmt::info!("{=u32:x} poll end {=u32:x}", executor_id, task_id);
+//!     }
+//! }
+//!
+//! embassy_executor::tracer!(Timeline);
+//! ```
+//!
+//! With the `rtos-trace` feature, the events are sent to the `rtos-trace` framework instead, for
+//! example to visualize them with SEGGER SystemView.
+//!
 //! ## Task Tracing lifecycle
 //!
 //! ```text
@@ -84,6 +107,7 @@
 use core::cell::UnsafeCell;
 use core::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
 
+#[cfg(feature = "rtos-trace")]
 use rtos_trace::TaskInfo;
 
 use crate::raw::{SyncExecutor, TaskHeader, TaskRef};
@@ -207,6 +231,93 @@ impl TaskRefTrace for TaskRef {
     }
 }
 
+/// Receiver of the executor trace events.
+///
+/// Each method corresponds to one of the `_embassy_trace_*` callbacks, see the
+/// [module documentation](self) for when they are called. Register the implementation with
+/// [`tracer!`](crate::tracer).
+pub trait Tracer {
+    /// A task was spawned, see `_embassy_trace_task_new`.
+    fn task_new(executor_id: u32, task_id: u32) {
+        let _ = (executor_id, task_id);
+    }
+
+    /// A task finished, see `_embassy_trace_task_end`.
+    fn task_end(executor_id: u32, task_id: u32) {
+        let _ = (executor_id, task_id);
+    }
+
+    /// A task was woken and is ready to be polled, see `_embassy_trace_task_ready_begin`.
+    ///
+    /// This may be called from an interrupt.
+    fn task_ready_begin(executor_id: u32, task_id: u32) {
+        let _ = (executor_id, task_id);
+    }
+
+    /// The executor starts polling a task, see `_embassy_trace_task_exec_begin`.
+    fn task_exec_begin(executor_id: u32, task_id: u32) {
+        let _ = (executor_id, task_id);
+    }
+
+    /// The executor finished polling a task, see `_embassy_trace_task_exec_end`.
+    fn task_exec_end(executor_id: u32, task_id: u32) {
+        let _ = (executor_id, task_id);
+    }
+
+    /// The executor leaves the idle state to poll the ready tasks, see `_embassy_trace_poll_start`.
+    fn poll_start(executor_id: u32) {
+        let _ = executor_id;
+    }
+
+    /// The executor has no more ready tasks and becomes idle, see `_embassy_trace_executor_idle`.
+    fn executor_idle(executor_id: u32) {
+        let _ = executor_id;
+    }
+}
+
+/// Implement the trace callbacks by forwarding them to a [`Tracer`](crate::raw::trace::Tracer).
+///
+/// This must be used only once in the whole program, and not together with the `rtos-trace` feature.
+#[macro_export]
+macro_rules! tracer {
+    ($t:ty) => {
+        #[no_mangle]
+        fn _embassy_trace_poll_start(executor_id: u32) {
+            <$t as $crate::raw::trace::Tracer>::poll_start(executor_id)
+        }
+
+        #[no_mangle]
+        fn _embassy_trace_task_new(executor_id: u32, task_id: u32) {
+            <$t as $crate::raw::trace::Tracer>::task_new(executor_id, task_id)
+        }
+
+        #[no_mangle]
+        fn _embassy_trace_task_end(executor_id: u32, task_id: u32) {
+            <$t as $crate::raw::trace::Tracer>::task_end(executor_id, task_id)
+        }
+
+        #[no_mangle]
+        fn _embassy_trace_task_exec_begin(executor_id: u32, task_id: u32) {
+            <$t as $crate::raw::trace::Tracer>::task_exec_begin(executor_id, task_id)
+        }
+
+        #[no_mangle]
+        fn _embassy_trace_task_exec_end(executor_id: u32, task_id: u32) {
+            <$t as $crate::raw::trace::Tracer>::task_exec_end(executor_id, task_id)
+        }
+
+        #[no_mangle]
+        fn _embassy_trace_task_ready_begin(executor_id: u32, task_id: u32) {
+            <$t as $crate::raw::trace::Tracer>::task_ready_begin(executor_id, task_id)
+        }
+
+        #[no_mangle]
+        fn _embassy_trace_executor_idle(executor_id: u32) {
+            <$t as $crate::raw::trace::Tracer>::executor_idle(executor_id)
+        }
+    };
+}
+
 #[cfg(not(feature = "rtos-trace"))]
 extern "Rust" {
     /// This callback is called when the executor begins polling. This will always
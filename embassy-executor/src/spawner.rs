@@ -7,6 +7,7 @@ use core::task::Poll;
 use super::raw;
 #[cfg(feature = "trace")]
 use crate::raw::trace::TaskRefTrace;
+use crate::JoinHandle;
 
 /// Token to spawn a newly-created task in an executor.
 ///
@@ -69,6 +70,9 @@ pub enum SpawnError {
     /// By default, a task marked with `#[embassy_executor::task]` can only have one instance
     /// running at a time. You may allow multiple instances to run in parallel with
     /// `#[embassy_executor::task(pool_size = 4)]`, at the cost of higher RAM usage.
+    ///
+    /// With the `spawn-wait` feature, you can wait for a running instance to finish instead with
+    /// `Spawner::spawn_when_available()` or `Spawner::spawn_or_evict()`.
     Busy,
 }
 
@@ -169,6 +173,80 @@ impl Spawner {
         unwrap!(self.spawn(token));
     }
 
+    /// Spawn a task, waiting until there is room for it if its task pool is exhausted.
+    ///
+    /// Requires the `spawn-wait` feature.
+    ///
+    /// `token_fn` is called to get the task's `SpawnToken`, typically by calling the task function.
+    /// It's called again each time a task finishes, until spawning succeeds.
+    ///
+    /// ```rust,ignore
+    /// loop {
+    ///     let socket = accept().await;
+    ///     spawner.spawn_when_available(|| handle_connection(socket.clone())).await;
+    /// }
+    /// ```
+    #[cfg(feature = "spawn-wait")]
+    pub async fn spawn_when_available<S>(&self, mut token_fn: impl FnMut() -> SpawnToken<S>) {
+        let waiter = core::pin::pin!(raw::SpawnWaiter::new());
+        poll_fn(|cx| {
+            // Register before trying, so a task finishing in between isn't missed.
+            waiter.as_ref().register(cx.waker());
+            match self.spawn(token_fn()) {
+                Ok(()) => Poll::Ready(()),
+                Err(SpawnError::Busy) => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Spawn a task, calling `evict` to make room for it if its task pool is exhausted.
+    ///
+    /// `evict` should ask a running task of the pool to finish, for example the oldest connection
+    /// handler, and return `true`. The task is then spawned once a task has finished, as with
+    /// [`Spawner::spawn_when_available()`]. If `evict` returns `false`, because no task can be
+    /// evicted, this returns the spawn error.
+    ///
+    /// `evict` is called at most once.
+    ///
+    /// Requires the `spawn-wait` feature.
+    #[cfg(feature = "spawn-wait")]
+    pub async fn spawn_or_evict<S>(
+        &self,
+        mut token_fn: impl FnMut() -> SpawnToken<S>,
+        evict: impl FnOnce() -> bool,
+    ) -> Result<(), SpawnError> {
+        let mut evict = Some(evict);
+        let waiter = core::pin::pin!(raw::SpawnWaiter::new());
+        poll_fn(|cx| {
+            waiter.as_ref().register(cx.waker());
+            match self.spawn(token_fn()) {
+                Ok(()) => Poll::Ready(Ok(())),
+                Err(e) if evict.take().is_some_and(|evict| !evict()) => Poll::Ready(Err(e)),
+                Err(_) => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Spawn a future as a task with storage from `arena`, returning a handle to it.
+    ///
+    /// The [`JoinHandle`] can be awaited for the output of the future, or used to abort the task.
+    /// See [`raw::TaskArena::spawn()`] for how the storage is allocated.
+    pub fn spawn_with_handle<F>(
+        &self,
+        arena: &raw::TaskArena,
+        future: impl FnOnce() -> F,
+    ) -> Result<JoinHandle<F::Output>, SpawnError>
+    where
+        F: Future + 'static,
+        F::Output: 'static,
+    {
+        let (token, handle) = arena.spawn_with_handle::<F, F>(future).ok_or(SpawnError::Busy)?;
+        self.spawn(token)?;
+        Ok(handle)
+    }
+
     /// Convert this Spawner to a SendSpawner. This allows you to send the
     /// spawner to other threads, but the spawner loses the ability to spawn
     /// non-Send tasks.
@@ -180,6 +258,12 @@ impl Spawner {
     pub fn executor_id(&self) -> usize {
         self.executor.id()
     }
+
+    /// Get the busy-time statistics of this Spawner's Executor, to measure its CPU load.
+    #[cfg(feature = "metrics")]
+    pub fn executor_stats(&self) -> raw::metrics::ExecutorStats {
+        self.executor.stats()
+    }
 }
 
 /// Extension trait adding tracing capabilities to the Spawner
@@ -292,4 +376,27 @@ impl SendSpawner {
     pub fn must_spawn<S: Send>(&self, token: SpawnToken<S>) {
         unwrap!(self.spawn(token));
     }
+
+    /// Spawn a future as a task with storage from `arena`, returning a handle to it.
+    ///
+    /// See [`Spawner::spawn_with_handle()`] for details.
+    pub fn spawn_with_handle<F>(
+        &self,
+        arena: &raw::TaskArena,
+        future: impl FnOnce() -> F,
+    ) -> Result<JoinHandle<F::Output>, SpawnError>
+    where
+        F: Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let (token, handle) = arena.spawn_with_handle::<F, F>(future).ok_or(SpawnError::Busy)?;
+        self.spawn(token)?;
+        Ok(handle)
+    }
+
+    /// Get the busy-time statistics of this SendSpawner's Executor, to measure its CPU load.
+    #[cfg(feature = "metrics")]
+    pub fn executor_stats(&self) -> raw::metrics::ExecutorStats {
+        self.executor.stats()
+    }
 }
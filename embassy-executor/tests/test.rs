@@ -2,11 +2,14 @@
 
 use std::boxed::Box;
 use std::future::poll_fn;
+use std::mem::MaybeUninit;
 use std::sync::{Arc, Mutex};
 use std::task::Poll;
 
-use embassy_executor::raw::Executor;
-use embassy_executor::task;
+use embassy_executor::raw::{Executor, TaskArena};
+#[cfg(feature = "spawn-wait")]
+use embassy_executor::Spawner;
+use embassy_executor::{task, JoinError, SpawnError};
 
 #[export_name = "__pender"]
 fn __pender(context: *mut ()) {
@@ -283,3 +286,271 @@ fn executor_task_cfg_args() {
         let (_, _, _) = (a, b, c);
     }
 }
+
+#[test]
+fn arena_spawn_and_reuse() {
+    async fn task1(trace: Trace) {
+        trace.push("poll task1")
+    }
+
+    let (executor, trace) = setup();
+    let buffer = Box::leak(Box::new([MaybeUninit::uninit(); 1024]));
+    let arena = TaskArena::new(buffer);
+
+    executor.spawner().spawn(arena.spawn(|| task1(trace.clone()))).unwrap();
+    executor.spawner().spawn(arena.spawn(|| task1(trace.clone()))).unwrap();
+    let free = arena.free_bytes();
+    assert!(free < 1024);
+
+    unsafe { executor.poll() };
+
+    // Finished tasks are reused instead of allocating new storage.
+    executor.spawner().spawn(arena.spawn(|| task1(trace.clone()))).unwrap();
+    assert_eq!(arena.free_bytes(), free);
+
+    unsafe { executor.poll() };
+
+    assert_eq!(
+        trace.get(),
+        &[
+            "pend",       // spawning a task pends the executor
+            "poll task1", //
+            "poll task1", //
+            "pend",       // the storage of a finished task is reused
+            "poll task1", //
+        ]
+    )
+}
+
+#[test]
+fn arena_exhausted() {
+    async fn task1(trace: Trace) {
+        poll_fn(|_| {
+            trace.push("poll task1");
+            Poll::<()>::Pending
+        })
+        .await
+    }
+
+    let (executor, trace) = setup();
+    let buffer = Box::leak(Box::new([MaybeUninit::uninit(); 256]));
+    let arena = TaskArena::new(buffer);
+
+    let mut spawned = 0;
+    while executor.spawner().spawn(arena.spawn(|| task1(trace.clone()))).is_ok() {
+        spawned += 1;
+    }
+    assert!(spawned > 0);
+    assert!(matches!(
+        executor.spawner().spawn(arena.spawn(|| task1(trace.clone()))),
+        Err(SpawnError::Busy)
+    ));
+}
+
+#[test]
+fn spawn_with_handle() {
+    async fn add(trace: Trace, a: u32, b: u32) -> u32 {
+        trace.push("poll add");
+        a + b
+    }
+
+    let (executor, trace) = setup();
+    let buffer = Box::leak(Box::new([MaybeUninit::uninit(); 1024]));
+    let arena = TaskArena::new(buffer);
+
+    let t = trace.clone();
+    let handle = executor
+        .spawner()
+        .spawn_with_handle(&arena, move || add(t, 1, 2))
+        .unwrap();
+    assert!(!handle.is_finished());
+
+    let t = trace.clone();
+    executor
+        .spawner()
+        .spawn(arena.spawn(move || async move {
+            let sum = handle.await.unwrap();
+            assert_eq!(sum, 3);
+            t.push("joined");
+        }))
+        .unwrap();
+
+    unsafe { executor.poll() };
+    unsafe { executor.poll() };
+
+    assert_eq!(
+        trace.get(),
+        &[
+            "pend",     // spawning a task pends the executor
+            "poll add", //
+            "pend",     // finishing the task wakes the joining task
+            "joined",   //
+        ]
+    )
+}
+
+#[test]
+fn spawn_with_handle_abort() {
+    async fn forever(trace: Trace) -> u32 {
+        poll_fn(|_| {
+            trace.push("poll forever");
+            Poll::Pending
+        })
+        .await
+    }
+
+    let (executor, trace) = setup();
+    let buffer = Box::leak(Box::new([MaybeUninit::uninit(); 1024]));
+    let arena = TaskArena::new(buffer);
+
+    let t = trace.clone();
+    let handle = executor
+        .spawner()
+        .spawn_with_handle(&arena, move || forever(t))
+        .unwrap();
+
+    unsafe { executor.poll() };
+    handle.abort();
+    unsafe { executor.poll() };
+    assert!(handle.is_finished());
+
+    let t = trace.clone();
+    executor
+        .spawner()
+        .spawn(arena.spawn(move || async move {
+            assert_eq!(handle.await, Err(JoinError::Aborted));
+            t.push("aborted");
+        }))
+        .unwrap();
+    unsafe { executor.poll() };
+
+    assert_eq!(
+        trace.get(),
+        &[
+            "pend",         // spawning a task pends the executor
+            "poll forever", //
+            "pend",         // abort wakes the task, which is dropped without being polled
+            "pend",         //
+            "aborted",      //
+        ]
+    )
+}
+
+#[test]
+#[cfg(feature = "spawn-wait")]
+fn spawn_when_available() {
+    #[task(pool_size = 1)]
+    async fn worker(trace: Trace, name: &'static str) {
+        trace.push(name);
+        // Yield once, so the pool stays exhausted for one poll.
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[task]
+    async fn spawn_workers(spawner: Spawner, trace: Trace) {
+        spawner.spawn(worker(trace.clone(), "worker 1")).unwrap();
+        spawner.spawn_when_available(|| worker(trace.clone(), "worker 2")).await;
+        trace.push("spawned worker 2");
+    }
+
+    let (executor, trace) = setup();
+    executor
+        .spawner()
+        .spawn(spawn_workers(executor.spawner(), trace.clone()))
+        .unwrap();
+
+    for _ in 0..5 {
+        unsafe { executor.poll() };
+    }
+
+    let events: Vec<_> = trace.get().into_iter().filter(|e| *e != "pend").collect();
+    assert_eq!(events, &["worker 1", "spawned worker 2", "worker 2"]);
+}
+
+#[test]
+#[cfg(feature = "spawn-wait")]
+fn spawn_when_available_many_waiters() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+
+    #[task(pool_size = 1)]
+    async fn worker(trace: Trace) {
+        trace.push("worker");
+        // Yield once, so the pool stays exhausted for one poll.
+        let mut yielded = false;
+        poll_fn(|cx| {
+            if yielded {
+                Poll::Ready(())
+            } else {
+                yielded = true;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    #[task(pool_size = 6)]
+    async fn waiter(spawner: Spawner, trace: Trace) {
+        spawner
+            .spawn_when_available(|| {
+                ATTEMPTS.fetch_add(1, Ordering::Relaxed);
+                worker(trace.clone())
+            })
+            .await;
+    }
+
+    let (executor, trace) = setup();
+    for _ in 0..6 {
+        executor
+            .spawner()
+            .spawn(waiter(executor.spawner(), trace.clone()))
+            .unwrap();
+    }
+
+    for _ in 0..20 {
+        unsafe { executor.poll() };
+    }
+
+    let workers = trace.get().into_iter().filter(|e| *e == "worker").count();
+    assert_eq!(workers, 6);
+    // Waiters are only polled again when a task finishes, they don't wake each other.
+    assert!(ATTEMPTS.load(Ordering::Relaxed) <= 6 + 6 * 5);
+}
+
+#[test]
+#[cfg(feature = "spawn-wait")]
+fn spawn_or_evict() {
+    #[task(pool_size = 1)]
+    async fn worker() {
+        poll_fn(|_| Poll::<()>::Pending).await
+    }
+
+    #[task]
+    async fn spawn_workers(spawner: Spawner, trace: Trace) {
+        spawner.spawn(worker()).unwrap();
+        let res = spawner.spawn_or_evict(worker, || false).await;
+        assert!(matches!(res, Err(SpawnError::Busy)));
+        trace.push("nothing to evict");
+    }
+
+    let (executor, trace) = setup();
+    executor
+        .spawner()
+        .spawn(spawn_workers(executor.spawner(), trace.clone()))
+        .unwrap();
+    unsafe { executor.poll() };
+
+    assert!(trace.get().contains(&"nothing to evict"));
+}
@@ -10,7 +10,7 @@ use core::mem::MaybeUninit;
 use core::task::{Context, Poll};
 
 pub use embassy_net_driver as driver;
-use embassy_net_driver::{Capabilities, LinkState};
+use embassy_net_driver::{Capabilities, LinkState, Timestamp};
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
 use embassy_sync::blocking_mutex::Mutex;
 use embassy_sync::waitqueue::WakerRegistration;
@@ -46,6 +46,8 @@ struct Shared {
     link_state: LinkState,
     waker: WakerRegistration,
     hardware_address: driver::HardwareAddress,
+    tx_timestamp: Option<Timestamp>,
+    tx_timestamp_waker: WakerRegistration,
 }
 
 /// Channel runner.
@@ -125,6 +127,15 @@ impl<'d, const MTU: usize> Runner<'d, MTU> {
         });
     }
 
+    /// Report the hardware timestamp of the last packet sent with a timestamp request.
+    pub fn set_tx_timestamp(&mut self, timestamp: Timestamp) {
+        self.shared.lock(|s| {
+            let s = &mut *s.borrow_mut();
+            s.tx_timestamp = Some(timestamp);
+            s.tx_timestamp_waker.wake();
+        });
+    }
+
     /// Wait until there is space for more inbound packets and return a slice they can be copied into.
     pub async fn rx_buf(&mut self) -> &mut [u8] {
         let p = self.rx_chan.send().await;
@@ -147,8 +158,14 @@ impl<'d, const MTU: usize> Runner<'d, MTU> {
 
     /// Mark packet of len bytes as pushed to the inbound channel.
     pub fn rx_done(&mut self, len: usize) {
+        self.rx_done_with_meta(len, RxMeta::default());
+    }
+
+    /// Mark packet of len bytes, with its timestamp and VLAN tag, as pushed to the inbound channel.
+    pub fn rx_done_with_meta(&mut self, len: usize, meta: RxMeta) {
         let p = self.rx_chan.try_send().unwrap();
         p.len = len;
+        p.rx_meta = meta;
         self.rx_chan.send_done();
     }
 
@@ -172,6 +189,13 @@ impl<'d, const MTU: usize> Runner<'d, MTU> {
         }
     }
 
+    /// Get the timestamp request and VLAN tag of the current outbound packet.
+    ///
+    /// Once a packet with a timestamp request is sent, report the timestamp with `set_tx_timestamp()`.
+    pub fn tx_meta(&mut self) -> TxMeta {
+        self.tx_chan.try_receive().map(|p| p.tx_meta).unwrap_or_default()
+    }
+
     /// Mark outbound packet as copied.
     pub fn tx_done(&mut self) {
         self.tx_chan.receive_done();
@@ -196,6 +220,15 @@ impl<'d> StateRunner<'d> {
             s.waker.wake();
         });
     }
+
+    /// Report the hardware timestamp of the last packet sent with a timestamp request.
+    pub fn set_tx_timestamp(&self, timestamp: Timestamp) {
+        self.shared.lock(|s| {
+            let s = &mut *s.borrow_mut();
+            s.tx_timestamp = Some(timestamp);
+            s.tx_timestamp_waker.wake();
+        });
+    }
 }
 
 impl<'d, const MTU: usize> RxRunner<'d, MTU> {
@@ -221,8 +254,14 @@ impl<'d, const MTU: usize> RxRunner<'d, MTU> {
 
     /// Mark packet of len bytes as pushed to the inbound channel.
     pub fn rx_done(&mut self, len: usize) {
+        self.rx_done_with_meta(len, RxMeta::default());
+    }
+
+    /// Mark packet of len bytes, with its timestamp and VLAN tag, as pushed to the inbound channel.
+    pub fn rx_done_with_meta(&mut self, len: usize, meta: RxMeta) {
         let p = self.rx_chan.try_send().unwrap();
         p.len = len;
+        p.rx_meta = meta;
         self.rx_chan.send_done();
     }
 }
@@ -248,6 +287,13 @@ impl<'d, const MTU: usize> TxRunner<'d, MTU> {
         }
     }
 
+    /// Get the timestamp request and VLAN tag of the current outbound packet.
+    ///
+    /// Once a packet with a timestamp request is sent, report the timestamp with `set_tx_timestamp()`.
+    pub fn tx_meta(&mut self) -> TxMeta {
+        self.tx_chan.try_receive().map(|p| p.tx_meta).unwrap_or_default()
+    }
+
     /// Mark outbound packet as copied.
     pub fn tx_done(&mut self) {
         self.tx_chan.receive_done();
@@ -279,6 +325,8 @@ pub fn new<'d, const MTU: usize, const N_RX: usize, const N_TX: usize>(
             link_state: LinkState::Down,
             hardware_address,
             waker: WakerRegistration::new(),
+            tx_timestamp: None,
+            tx_timestamp_waker: WakerRegistration::new(),
         })),
     });
 
@@ -304,15 +352,46 @@ pub fn new<'d, const MTU: usize, const N_RX: usize, const N_TX: usize>(
 pub struct PacketBuf<const MTU: usize> {
     len: usize,
     buf: [u8; MTU],
+    rx_meta: RxMeta,
+    tx_meta: TxMeta,
 }
 
 impl<const MTU: usize> PacketBuf<MTU> {
     /// Create a new packet buffer.
     pub const fn new() -> Self {
-        Self { len: 0, buf: [0; MTU] }
+        Self {
+            len: 0,
+            buf: [0; MTU],
+            rx_meta: RxMeta {
+                timestamp: None,
+                vlan_tag: None,
+            },
+            tx_meta: TxMeta {
+                request_timestamp: false,
+                vlan_tag: None,
+            },
+        }
     }
 }
 
+/// Metadata of an inbound packet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RxMeta {
+    /// Time the packet was received at, from the device's PTP clock.
+    pub timestamp: Option<Timestamp>,
+    /// Tag control information of the VLAN tag the device stripped from the packet.
+    pub vlan_tag: Option<u16>,
+}
+
+/// Metadata of an outbound packet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxMeta {
+    /// The stack requested a hardware timestamp of the packet.
+    pub request_timestamp: bool,
+    /// Tag control information of a VLAN tag to insert in the packet.
+    pub vlan_tag: Option<u16>,
+}
+
 /// Channel device.
 ///
 /// Holds the shared state and upper end of channels for inbound and outbound packets.
@@ -323,6 +402,15 @@ pub struct Device<'d, const MTU: usize> {
     caps: Capabilities,
 }
 
+impl<'d, const MTU: usize> Device<'d, MTU> {
+    /// Get the capabilities reported to the stack, to advertise what the runner supports.
+    ///
+    /// Only the maximum transmission unit is set by default.
+    pub fn capabilities_mut(&mut self) -> &mut Capabilities {
+        &mut self.caps
+    }
+}
+
 impl<'d, const MTU: usize> embassy_net_driver::Driver for Device<'d, MTU> {
     type RxToken<'a>
         = RxToken<'a, MTU>
@@ -335,7 +423,16 @@ impl<'d, const MTU: usize> embassy_net_driver::Driver for Device<'d, MTU> {
 
     fn receive(&mut self, cx: &mut Context) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
         if self.rx.poll_receive(cx).is_ready() && self.tx.poll_send(cx).is_ready() {
-            Some((RxToken { rx: self.rx.borrow() }, TxToken { tx: self.tx.borrow() }))
+            let meta = self.rx.try_receive().map(|p| p.rx_meta).unwrap_or_default();
+            let rx = RxToken {
+                rx: self.rx.borrow(),
+                meta,
+            };
+            let tx = TxToken {
+                tx: self.tx.borrow(),
+                meta: TxMeta::default(),
+            };
+            Some((rx, tx))
         } else {
             None
         }
@@ -344,7 +441,10 @@ impl<'d, const MTU: usize> embassy_net_driver::Driver for Device<'d, MTU> {
     /// Construct a transmit token.
     fn transmit(&mut self, cx: &mut Context) -> Option<Self::TxToken<'_>> {
         if self.tx.poll_send(cx).is_ready() {
-            Some(TxToken { tx: self.tx.borrow() })
+            Some(TxToken {
+                tx: self.tx.borrow(),
+                meta: TxMeta::default(),
+            })
         } else {
             None
         }
@@ -366,6 +466,14 @@ impl<'d, const MTU: usize> embassy_net_driver::Driver for Device<'d, MTU> {
             s.link_state
         })
     }
+
+    fn tx_timestamp(&mut self, cx: &mut Context) -> Option<Timestamp> {
+        self.shared.lock(|s| {
+            let s = &mut *s.borrow_mut();
+            s.tx_timestamp_waker.register(cx.waker());
+            s.tx_timestamp.take()
+        })
+    }
 }
 
 /// A rx token.
@@ -373,6 +481,7 @@ impl<'d, const MTU: usize> embassy_net_driver::Driver for Device<'d, MTU> {
 /// Holds inbound receive channel and interfaces with embassy-net-driver.
 pub struct RxToken<'a, const MTU: usize> {
     rx: zerocopy_channel::Receiver<'a, NoopRawMutex, PacketBuf<MTU>>,
+    meta: RxMeta,
 }
 
 impl<'a, const MTU: usize> embassy_net_driver::RxToken for RxToken<'a, MTU> {
@@ -386,6 +495,14 @@ impl<'a, const MTU: usize> embassy_net_driver::RxToken for RxToken<'a, MTU> {
         self.rx.receive_done();
         r
     }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.meta.timestamp
+    }
+
+    fn vlan_tag(&self) -> Option<u16> {
+        self.meta.vlan_tag
+    }
 }
 
 /// A tx token.
@@ -393,6 +510,7 @@ impl<'a, const MTU: usize> embassy_net_driver::RxToken for RxToken<'a, MTU> {
 /// Holds outbound transmit channel and interfaces with embassy-net-driver.
 pub struct TxToken<'a, const MTU: usize> {
     tx: zerocopy_channel::Sender<'a, NoopRawMutex, PacketBuf<MTU>>,
+    meta: TxMeta,
 }
 
 impl<'a, const MTU: usize> embassy_net_driver::TxToken for TxToken<'a, MTU> {
@@ -404,7 +522,16 @@ impl<'a, const MTU: usize> embassy_net_driver::TxToken for TxToken<'a, MTU> {
         let pkt = unwrap!(self.tx.try_send());
         let r = f(&mut pkt.buf[..len]);
         pkt.len = len;
+        pkt.tx_meta = self.meta;
         self.tx.send_done();
         r
     }
+
+    fn request_timestamp(&mut self) {
+        self.meta.request_timestamp = true;
+    }
+
+    fn set_vlan_tag(&mut self, tci: u16) {
+        self.meta.vlan_tag = Some(tci);
+    }
 }
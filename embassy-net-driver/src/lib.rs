@@ -80,6 +80,32 @@ pub trait Driver {
     /// what kind of packet the sent/received bytes are, and determines some behaviors of
     /// the interface. For example, ARP/NDISC address resolution is only done for Ethernet mediums.
     fn hardware_address(&self) -> HardwareAddress;
+
+    /// Get the hardware timestamp of the last packet sent with [`TxToken::request_timestamp`].
+    ///
+    /// If the packet has not been sent yet, this function must return `None`, and wake
+    /// `cx.waker()` when the timestamp becomes available. Each timestamp is returned only once.
+    ///
+    /// Devices that don't timestamp packets always return `None`, see [`Capabilities::timestamping`].
+    fn tx_timestamp(&mut self, cx: &mut Context) -> Option<Timestamp> {
+        let _ = cx;
+        None
+    }
+
+    /// Start receiving packets sent to the Ethernet multicast address `addr`.
+    ///
+    /// Devices without multicast filtering receive all multicast packets and ignore this.
+    /// The network stack calls this for every multicast group it joins, devices can stop
+    /// receiving other multicast packets once the first address was added.
+    fn add_multicast_address(&mut self, addr: [u8; 6]) {
+        let _ = addr;
+    }
+
+    /// Stop receiving packets sent to the Ethernet multicast address `addr`, previously added with
+    /// [`Driver::add_multicast_address`].
+    fn remove_multicast_address(&mut self, addr: [u8; 6]) {
+        let _ = addr;
+    }
 }
 
 impl<T: ?Sized + Driver> Driver for &mut T {
@@ -107,6 +133,15 @@ impl<T: ?Sized + Driver> Driver for &mut T {
     fn hardware_address(&self) -> HardwareAddress {
         T::hardware_address(self)
     }
+    fn tx_timestamp(&mut self, cx: &mut Context) -> Option<Timestamp> {
+        T::tx_timestamp(self, cx)
+    }
+    fn add_multicast_address(&mut self, addr: [u8; 6]) {
+        T::add_multicast_address(self, addr)
+    }
+    fn remove_multicast_address(&mut self, addr: [u8; 6]) {
+        T::remove_multicast_address(self, addr)
+    }
 }
 
 /// A token to receive a single network packet.
@@ -118,6 +153,22 @@ pub trait RxToken {
     fn consume<R, F>(self, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R;
+
+    /// Get the time the packet was received at, from the device's PTP clock.
+    ///
+    /// Returns `None` if the device doesn't timestamp received packets.
+    fn timestamp(&self) -> Option<Timestamp> {
+        None
+    }
+
+    /// Get the IEEE 802.1Q tag control information (priority, DEI and VLAN ID) of the VLAN tag
+    /// the device stripped from the packet.
+    ///
+    /// Returns `None` if the packet was untagged, or if the device doesn't strip VLAN tags. In
+    /// the latter case, the tag is still in the packet.
+    fn vlan_tag(&self) -> Option<u16> {
+        None
+    }
 }
 
 /// A token to transmit a single network packet.
@@ -131,6 +182,20 @@ pub trait TxToken {
     fn consume<R, F>(self, len: usize, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R;
+
+    /// Request a hardware timestamp of the packet sent with this token.
+    ///
+    /// Once the packet is sent, the timestamp can be read with [`Driver::tx_timestamp`].
+    /// Devices that don't timestamp packets ignore the request.
+    fn request_timestamp(&mut self) {}
+
+    /// Insert an IEEE 802.1Q VLAN tag with the tag control information `tci` (priority, DEI and
+    /// VLAN ID) in the packet sent with this token.
+    ///
+    /// Only supported if [`Capabilities::vlan_tag_insertion`] is set, other devices ignore this.
+    fn set_vlan_tag(&mut self, tci: u16) {
+        let _ = tci;
+    }
 }
 
 /// A description of device capabilities.
@@ -169,6 +234,16 @@ pub struct Capabilities {
     /// If the network device is capable of verifying or computing checksums for some protocols,
     /// it can request that the stack not do so in software to improve performance.
     pub checksum: ChecksumCapabilities,
+
+    /// Whether the device timestamps sent and received packets with an IEEE 1588 (PTP) clock.
+    ///
+    /// See [`RxToken::timestamp`] and [`Driver::tx_timestamp`].
+    pub timestamping: bool,
+
+    /// Whether the device inserts IEEE 802.1Q VLAN tags in sent packets.
+    ///
+    /// See [`TxToken::set_vlan_tag`].
+    pub vlan_tag_insertion: bool,
 }
 
 /// A description of checksum behavior for every supported protocol.
@@ -208,6 +283,16 @@ impl Default for Checksum {
     }
 }
 
+/// A hardware timestamp, read from the device's IEEE 1588 (PTP) clock.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Timestamp {
+    /// Seconds.
+    pub seconds: u64,
+    /// Nanoseconds, always less than 1_000_000_000.
+    pub nanoseconds: u32,
+}
+
 /// The link state of a network device.
 #[derive(PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
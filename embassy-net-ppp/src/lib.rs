@@ -66,6 +66,8 @@ impl<'d> Runner<'d> {
     ///
     /// After this function returns or is canceled, you can call it again to establish
     /// a new PPP connection.
+    ///
+    /// Only PAP authentication is supported, with the credentials in `config`.
     pub async fn run<RW: BufRead + Write>(
         &mut self,
         mut rw: RW,
@@ -0,0 +1,556 @@
+//! DHCPv4 server.
+//!
+//! A small DHCP server for devices that create their own network segment, such as a USB NCM
+//! gadget, a Wi-Fi access point or a point-to-point Ethernet link, and need to hand out addresses
+//! to the hosts connected to it.
+//!
+//! The server hands out addresses from a single contiguous pool of `N` addresses, one per client
+//! hardware address, and keeps the lease table in memory. The stack itself must be configured
+//! with a static IPv4 address, which is announced as the server identifier.
+//!
+//! DHCP relay agents are not supported, requests forwarded by a relay are ignored.
+
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+use smoltcp::wire::{
+    DhcpMessageType, DhcpPacket, DhcpRepr, EthernetAddress, IpEndpoint, Ipv4Address, Ipv4Cidr, DHCP_CLIENT_PORT,
+    DHCP_SERVER_PORT,
+};
+
+use crate::udp::{PacketMetadata, UdpSocket};
+use crate::Stack;
+
+/// How long an offered address is reserved for the client while waiting for its request.
+const OFFER_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// DHCP server configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Address of the server and the subnet it serves.
+    ///
+    /// This must be the IPv4 address the stack is configured with. The prefix length is sent to
+    /// clients as their subnet mask.
+    pub server_address: Ipv4Cidr,
+    /// First address of the pool. The pool contains `N` consecutive addresses.
+    pub pool_start: Ipv4Address,
+    /// Default gateway sent to clients, if any.
+    pub router: Option<Ipv4Address>,
+    /// DNS servers sent to clients.
+    pub dns_servers: Vec<Ipv4Address, 3>,
+    /// Lease duration.
+    pub lease_duration: Duration,
+}
+
+/// State of a [`Lease`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LeaseState {
+    /// The address was offered to the client, which has not requested it yet.
+    Offered,
+    /// The address is assigned to the client.
+    Bound,
+    /// The client reported the address is already in use on the network.
+    Declined,
+}
+
+/// An entry of the lease table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lease {
+    /// Hardware address of the client.
+    pub hardware_address: EthernetAddress,
+    /// Address leased to the client.
+    pub address: Ipv4Address,
+    /// State of the lease.
+    pub state: LeaseState,
+    /// When the lease expires and the address returns to the pool.
+    pub expires_at: Instant,
+}
+
+/// The fields of a received DHCP message the server acts upon.
+struct Request {
+    message_type: DhcpMessageType,
+    transaction_id: u32,
+    client_hardware_address: EthernetAddress,
+    client_ip: Ipv4Address,
+    requested_ip: Option<Ipv4Address>,
+    server_identifier: Option<Ipv4Address>,
+    broadcast: bool,
+}
+
+/// The reply to send to a [`Request`].
+struct Reply {
+    message_type: DhcpMessageType,
+    your_ip: Ipv4Address,
+    lease_duration: Option<Duration>,
+}
+
+/// DHCPv4 server with a pool of `N` addresses.
+pub struct DhcpServer<'a, const N: usize> {
+    socket: UdpSocket<'a>,
+    pool: Pool<N>,
+}
+
+/// The lease table, and the protocol logic deciding what to reply to requests.
+struct Pool<const N: usize> {
+    config: Config,
+    leases: [Option<Lease>; N],
+}
+
+impl<'a, const N: usize> DhcpServer<'a, N> {
+    /// Create a new DHCP server using the provided stack and socket buffers.
+    ///
+    /// The buffers are used for the server's UDP socket, each receive and transmit buffer should
+    /// fit at least one 576-byte DHCP message.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the address pool is empty, not contained in the server's subnet, or contains the
+    /// server's own address.
+    pub fn new(
+        stack: Stack<'a>,
+        rx_meta: &'a mut [PacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [PacketMetadata],
+        tx_buffer: &'a mut [u8],
+        config: Config,
+    ) -> Self {
+        let pool = Pool::new(config);
+
+        let mut socket = UdpSocket::new(stack, rx_meta, rx_buffer, tx_meta, tx_buffer);
+        unwrap!(socket.bind(DHCP_SERVER_PORT));
+
+        Self { socket, pool }
+    }
+
+    /// Get the lease table.
+    ///
+    /// Expired leases are kept in the table until their address is handed out again.
+    pub fn leases(&self) -> impl Iterator<Item = &Lease> {
+        self.pool.leases.iter().flatten()
+    }
+
+    /// Run the server, answering requests forever.
+    pub async fn run(&mut self) -> ! {
+        loop {
+            let request = self.socket.recv_from_with(|buf, _meta| parse(buf)).await;
+            let Some(request) = request else {
+                continue;
+            };
+
+            let Some(reply) = self.pool.handle(&request, Instant::now()) else {
+                continue;
+            };
+
+            // Clients in the INIT state don't have an address yet and can't answer ARP, so reply
+            // with a broadcast unless the client is renewing an address it already uses.
+            let destination = if request.broadcast
+                || request.client_ip.is_unspecified()
+                || reply.message_type == DhcpMessageType::Nak
+            {
+                Ipv4Address::BROADCAST
+            } else {
+                request.client_ip
+            };
+
+            let repr = self.pool.reply_repr(&request, &reply);
+            let endpoint = IpEndpoint::new(destination.into(), DHCP_CLIENT_PORT);
+            let res = self
+                .socket
+                .send_to_with(repr.buffer_len(), endpoint, |buf| {
+                    let mut packet = DhcpPacket::new_unchecked(buf);
+                    repr.emit(&mut packet)
+                })
+                .await;
+            match res {
+                Ok(Ok(())) => {}
+                Ok(Err(e)) => warn!("dhcp server: failed to emit reply: {:?}", e),
+                Err(e) => warn!("dhcp server: failed to send reply: {:?}", e),
+            }
+        }
+    }
+}
+
+impl<const N: usize> Pool<N> {
+    fn new(config: Config) -> Self {
+        assert!(N > 0);
+        let pool_start = u32::from(config.pool_start);
+        let pool_end = unwrap!(pool_start.checked_add(N as u32 - 1));
+        assert!(config.server_address.contains_addr(&config.pool_start));
+        assert!(config.server_address.contains_addr(&Ipv4Address::from(pool_end)));
+        assert!(!(pool_start..=pool_end).contains(&u32::from(config.server_address.address())));
+
+        Self {
+            config,
+            leases: [None; N],
+        }
+    }
+
+    fn handle(&mut self, request: &Request, now: Instant) -> Option<Reply> {
+        match request.message_type {
+            DhcpMessageType::Discover => {
+                let index = self.find_free(request.client_hardware_address, request.requested_ip, now)?;
+                let address = self.address(index);
+                self.leases[index] = Some(Lease {
+                    hardware_address: request.client_hardware_address,
+                    address,
+                    state: LeaseState::Offered,
+                    expires_at: now + OFFER_TIMEOUT,
+                });
+                debug!(
+                    "dhcp server: offering {} to {}",
+                    address, request.client_hardware_address
+                );
+                Some(Reply {
+                    message_type: DhcpMessageType::Offer,
+                    your_ip: address,
+                    lease_duration: Some(self.config.lease_duration),
+                })
+            }
+            DhcpMessageType::Request => {
+                if let Some(server) = request.server_identifier {
+                    if server != self.config.server_address.address() {
+                        // The client accepted an offer from another server.
+                        self.release(request.client_hardware_address, LeaseState::Offered);
+                        return None;
+                    }
+                }
+
+                let address = match request.requested_ip {
+                    Some(address) => address,
+                    None if !request.client_ip.is_unspecified() => request.client_ip,
+                    None => return None,
+                };
+
+                let index = match self.index(address) {
+                    Some(index) => index,
+                    // Not one of our addresses. Only answer clients that selected us.
+                    None if request.server_identifier.is_some() => return Some(self.nak()),
+                    None => return None,
+                };
+
+                let available = match &self.leases[index] {
+                    Some(lease) if lease.hardware_address == request.client_hardware_address => {
+                        lease.state != LeaseState::Declined
+                    }
+                    Some(lease) => lease.expires_at <= now,
+                    None => true,
+                };
+                if !available {
+                    return Some(self.nak());
+                }
+
+                self.leases[index] = Some(Lease {
+                    hardware_address: request.client_hardware_address,
+                    address,
+                    state: LeaseState::Bound,
+                    expires_at: now + self.config.lease_duration,
+                });
+                info!("dhcp server: leased {} to {}", address, request.client_hardware_address);
+                Some(Reply {
+                    message_type: DhcpMessageType::Ack,
+                    your_ip: address,
+                    lease_duration: Some(self.config.lease_duration),
+                })
+            }
+            DhcpMessageType::Decline => {
+                let index = request.requested_ip.and_then(|address| self.index(address))?;
+                if let Some(lease) = &mut self.leases[index] {
+                    if lease.hardware_address == request.client_hardware_address {
+                        warn!("dhcp server: {} is already in use", lease.address);
+                        lease.state = LeaseState::Declined;
+                        lease.expires_at = now + self.config.lease_duration;
+                    }
+                }
+                None
+            }
+            DhcpMessageType::Release => {
+                self.release(request.client_hardware_address, LeaseState::Bound);
+                None
+            }
+            DhcpMessageType::Inform => Some(Reply {
+                message_type: DhcpMessageType::Ack,
+                your_ip: Ipv4Address::UNSPECIFIED,
+                lease_duration: None,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Find a pool entry for a new offer: the client's previous address if it has one, the
+    /// requested address if it's free, or else the first free address.
+    fn find_free(
+        &self,
+        hardware_address: EthernetAddress,
+        requested: Option<Ipv4Address>,
+        now: Instant,
+    ) -> Option<usize> {
+        let is_free = |lease: &Option<Lease>| match lease {
+            Some(lease) => lease.expires_at <= now,
+            None => true,
+        };
+
+        let previous = self.leases.iter().position(|lease| {
+            matches!(lease, Some(lease) if lease.hardware_address == hardware_address && lease.state != LeaseState::Declined)
+        });
+        let requested = requested
+            .and_then(|address| self.index(address))
+            .filter(|&index| is_free(&self.leases[index]));
+
+        previous
+            .or(requested)
+            .or_else(|| self.leases.iter().position(|lease| lease.is_none()))
+            .or_else(|| self.leases.iter().position(is_free))
+    }
+
+    fn release(&mut self, hardware_address: EthernetAddress, state: LeaseState) {
+        for lease in self.leases.iter_mut() {
+            if matches!(lease, Some(l) if l.hardware_address == hardware_address && l.state == state) {
+                *lease = None;
+            }
+        }
+    }
+
+    fn nak(&self) -> Reply {
+        Reply {
+            message_type: DhcpMessageType::Nak,
+            your_ip: Ipv4Address::UNSPECIFIED,
+            lease_duration: None,
+        }
+    }
+
+    fn index(&self, address: Ipv4Address) -> Option<usize> {
+        let offset = u32::from(address).checked_sub(u32::from(self.config.pool_start))? as usize;
+        (offset < N).then_some(offset)
+    }
+
+    fn address(&self, index: usize) -> Ipv4Address {
+        Ipv4Address::from(u32::from(self.config.pool_start) + index as u32)
+    }
+
+    fn reply_repr(&self, request: &Request, reply: &Reply) -> DhcpRepr<'static> {
+        let server_address = self.config.server_address.address();
+        let options = reply.message_type != DhcpMessageType::Nak;
+        let lease_duration = reply.lease_duration.map(|d| d.as_secs() as u32);
+
+        DhcpRepr {
+            message_type: reply.message_type,
+            transaction_id: request.transaction_id,
+            secs: 0,
+            client_hardware_address: request.client_hardware_address,
+            client_ip: if reply.message_type == DhcpMessageType::Ack {
+                request.client_ip
+            } else {
+                Ipv4Address::UNSPECIFIED
+            },
+            your_ip: reply.your_ip,
+            server_ip: Ipv4Address::UNSPECIFIED,
+            router: self.config.router.filter(|_| options),
+            subnet_mask: options.then(|| self.config.server_address.netmask()),
+            relay_agent_ip: Ipv4Address::UNSPECIFIED,
+            broadcast: request.broadcast,
+            requested_ip: None,
+            client_identifier: None,
+            server_identifier: Some(server_address),
+            parameter_request_list: None,
+            dns_servers: (options && !self.config.dns_servers.is_empty()).then(|| self.config.dns_servers.clone()),
+            max_size: None,
+            lease_duration,
+            renew_duration: lease_duration.map(|d| d / 2),
+            rebind_duration: lease_duration.map(|d| d / 8 * 7),
+            additional_options: &[],
+        }
+    }
+}
+
+fn parse(buf: &[u8]) -> Option<Request> {
+    let packet = DhcpPacket::new_checked(buf).ok()?;
+    let repr = DhcpRepr::parse(&packet).ok()?;
+
+    if !repr.relay_agent_ip.is_unspecified() {
+        return None;
+    }
+
+    Some(Request {
+        message_type: repr.message_type,
+        transaction_id: repr.transaction_id,
+        client_hardware_address: repr.client_hardware_address,
+        client_ip: repr.client_ip,
+        requested_ip: repr.requested_ip,
+        server_identifier: repr.server_identifier,
+        broadcast: repr.broadcast,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: Ipv4Address = Ipv4Address::new(192, 168, 7, 1);
+    const POOL_START: Ipv4Address = Ipv4Address::new(192, 168, 7, 10);
+    const LEASE: Duration = Duration::from_secs(3600);
+
+    fn pool<const N: usize>() -> Pool<N> {
+        Pool::new(Config {
+            server_address: Ipv4Cidr::new(SERVER, 24),
+            pool_start: POOL_START,
+            router: None,
+            dns_servers: Vec::new(),
+            lease_duration: LEASE,
+        })
+    }
+
+    fn mac(n: u8) -> EthernetAddress {
+        EthernetAddress([0x02, 0, 0, 0, 0, n])
+    }
+
+    fn request(message_type: DhcpMessageType, client: u8, requested_ip: Option<Ipv4Address>) -> Request {
+        Request {
+            message_type,
+            transaction_id: 0x1234,
+            client_hardware_address: mac(client),
+            client_ip: Ipv4Address::UNSPECIFIED,
+            requested_ip,
+            server_identifier: (message_type == DhcpMessageType::Request).then_some(SERVER),
+            broadcast: false,
+        }
+    }
+
+    /// Runs DISCOVER then REQUEST for `client`, returning the acknowledged address.
+    fn bind<const N: usize>(pool: &mut Pool<N>, client: u8, now: Instant) -> Ipv4Address {
+        let offer = pool
+            .handle(&request(DhcpMessageType::Discover, client, None), now)
+            .unwrap();
+        let ack = pool
+            .handle(&request(DhcpMessageType::Request, client, Some(offer.your_ip)), now)
+            .unwrap();
+        assert!(ack.message_type == DhcpMessageType::Ack);
+        ack.your_ip
+    }
+
+    #[test]
+    fn discover_offers_first_free_address() {
+        let mut pool = pool::<4>();
+        let now = Instant::from_secs(0);
+
+        let reply = pool.handle(&request(DhcpMessageType::Discover, 1, None), now).unwrap();
+        assert!(reply.message_type == DhcpMessageType::Offer);
+        assert_eq!(reply.your_ip, POOL_START);
+        assert_eq!(reply.lease_duration, Some(LEASE));
+
+        let lease = pool.leases[0].unwrap();
+        assert_eq!(lease.hardware_address, mac(1));
+        assert_eq!(lease.state, LeaseState::Offered);
+        assert_eq!(lease.expires_at, now + OFFER_TIMEOUT);
+
+        // The next client gets the next address, a repeated DISCOVER the same one.
+        let reply = pool.handle(&request(DhcpMessageType::Discover, 2, None), now).unwrap();
+        assert_eq!(reply.your_ip, Ipv4Address::new(192, 168, 7, 11));
+        let reply = pool.handle(&request(DhcpMessageType::Discover, 1, None), now).unwrap();
+        assert_eq!(reply.your_ip, POOL_START);
+    }
+
+    #[test]
+    fn discover_honors_requested_address() {
+        let mut pool = pool::<4>();
+        let requested = Ipv4Address::new(192, 168, 7, 12);
+
+        let reply = pool
+            .handle(
+                &request(DhcpMessageType::Discover, 1, Some(requested)),
+                Instant::from_secs(0),
+            )
+            .unwrap();
+        assert_eq!(reply.your_ip, requested);
+    }
+
+    #[test]
+    fn request_acks_offered_address() {
+        let mut pool = pool::<4>();
+        let now = Instant::from_secs(10);
+
+        assert_eq!(bind(&mut pool, 1, now), POOL_START);
+        let lease = pool.leases[0].unwrap();
+        assert_eq!(lease.state, LeaseState::Bound);
+        assert_eq!(lease.expires_at, now + LEASE);
+    }
+
+    #[test]
+    fn request_naks_address_bound_to_other_client() {
+        let mut pool = pool::<4>();
+        let now = Instant::from_secs(0);
+        let address = bind(&mut pool, 1, now);
+
+        let reply = pool
+            .handle(&request(DhcpMessageType::Request, 2, Some(address)), now)
+            .unwrap();
+        assert!(reply.message_type == DhcpMessageType::Nak);
+        assert_eq!(pool.leases[0].unwrap().hardware_address, mac(1));
+    }
+
+    #[test]
+    fn request_outside_pool() {
+        let mut pool = pool::<4>();
+        let now = Instant::from_secs(0);
+        let foreign = Ipv4Address::new(10, 0, 0, 2);
+
+        // A client that selected this server is told its address is wrong.
+        let reply = pool
+            .handle(&request(DhcpMessageType::Request, 1, Some(foreign)), now)
+            .unwrap();
+        assert!(reply.message_type == DhcpMessageType::Nak);
+
+        // A client renewing with another server is left alone.
+        let mut renew = request(DhcpMessageType::Request, 1, Some(foreign));
+        renew.server_identifier = None;
+        assert!(pool.handle(&renew, now).is_none());
+    }
+
+    #[test]
+    fn request_for_other_server_releases_offer() {
+        let mut pool = pool::<4>();
+        let now = Instant::from_secs(0);
+        pool.handle(&request(DhcpMessageType::Discover, 1, None), now).unwrap();
+
+        let mut other = request(DhcpMessageType::Request, 1, Some(Ipv4Address::new(192, 168, 7, 200)));
+        other.server_identifier = Some(Ipv4Address::new(192, 168, 7, 254));
+        assert!(pool.handle(&other, now).is_none());
+        assert!(pool.leases[0].is_none());
+    }
+
+    #[test]
+    fn expired_lease_returns_to_pool() {
+        let mut pool = pool::<1>();
+        let start = Instant::from_secs(0);
+        let address = bind(&mut pool, 1, start);
+
+        // While the lease is valid, another client can't get the address.
+        let before = start + LEASE - Duration::from_secs(1);
+        assert!(pool
+            .handle(&request(DhcpMessageType::Discover, 2, None), before)
+            .is_none());
+        let reply = pool
+            .handle(&request(DhcpMessageType::Request, 2, Some(address)), before)
+            .unwrap();
+        assert!(reply.message_type == DhcpMessageType::Nak);
+
+        // Once expired, it is handed out again.
+        let after = start + LEASE;
+        assert_eq!(bind(&mut pool, 2, after), address);
+        assert_eq!(pool.leases[0].unwrap().hardware_address, mac(2));
+    }
+
+    #[test]
+    fn exhausted_pool_ignores_discover() {
+        let mut pool = pool::<2>();
+        let now = Instant::from_secs(0);
+        bind(&mut pool, 1, now);
+        bind(&mut pool, 2, now);
+
+        assert!(pool.handle(&request(DhcpMessageType::Discover, 3, None), now).is_none());
+
+        // A released address is available again.
+        assert!(pool.handle(&request(DhcpMessageType::Release, 1, None), now).is_none());
+        let reply = pool.handle(&request(DhcpMessageType::Discover, 3, None), now).unwrap();
+        assert_eq!(reply.your_ip, POOL_START);
+    }
+}
@@ -1,8 +1,21 @@
+use core::cell::Cell;
 use core::task::Context;
 
 use embassy_net_driver::{Capabilities, Checksum, Driver, RxToken, TxToken};
 use smoltcp::phy::{self, Medium};
 use smoltcp::time::Instant;
+#[cfg(all(feature = "rx-error-statistics", feature = "medium-ethernet"))]
+use smoltcp::wire::{EthernetFrame, EthernetProtocol};
+#[cfg(all(feature = "rx-error-statistics", feature = "proto-ipv4"))]
+use smoltcp::wire::{Icmpv4Packet, Ipv4Packet};
+#[cfg(all(feature = "rx-error-statistics", feature = "proto-ipv6"))]
+use smoltcp::wire::{Icmpv6Packet, Ipv6Packet};
+#[cfg(feature = "rx-error-statistics")]
+use smoltcp::wire::{IpAddress, IpProtocol, TcpPacket, UdpPacket};
+
+#[cfg(feature = "raw-ethernet")]
+use crate::raw::RawEthernetState;
+use crate::Statistics;
 
 pub(crate) struct DriverAdapter<'d, 'c, T>
 where
@@ -12,6 +25,13 @@ where
     pub cx: Option<&'d mut Context<'c>>,
     pub inner: &'d mut T,
     pub medium: Medium,
+    pub stats: &'d Cell<Statistics>,
+    /// Raw Ethernet sockets received frames are copied to.
+    #[cfg(feature = "raw-ethernet")]
+    pub raw_ethernet: Option<&'d mut [Option<RawEthernetState>]>,
+    /// VLAN tag the driver inserts in sent frames.
+    #[cfg(feature = "medium-ethernet")]
+    pub vlan_tag: Option<u16>,
 }
 
 impl<'d, 'c, T> phy::Device for DriverAdapter<'d, 'c, T>
@@ -19,23 +39,51 @@ where
     T: Driver,
 {
     type RxToken<'a>
-        = RxTokenAdapter<T::RxToken<'a>>
+        = RxTokenAdapter<'a, T::RxToken<'a>>
     where
         Self: 'a;
     type TxToken<'a>
-        = TxTokenAdapter<T::TxToken<'a>>
+        = TxTokenAdapter<'a, T::TxToken<'a>>
     where
         Self: 'a;
 
     fn receive(&mut self, _timestamp: Instant) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
-        self.inner
-            .receive(unwrap!(self.cx.as_deref_mut()))
-            .map(|(rx, tx)| (RxTokenAdapter(rx), TxTokenAdapter(tx)))
+        #[cfg(feature = "rx-error-statistics")]
+        let checksum = phy::Device::capabilities(self).checksum;
+        self.inner.receive(unwrap!(self.cx.as_deref_mut())).map(|(rx, tx)| {
+            let rx = RxTokenAdapter {
+                token: rx,
+                stats: self.stats,
+                #[cfg(feature = "rx-error-statistics")]
+                medium: self.medium,
+                #[cfg(feature = "rx-error-statistics")]
+                checksum,
+                #[cfg(feature = "raw-ethernet")]
+                raw_ethernet: self.raw_ethernet.as_deref_mut(),
+            };
+            let tx = TxTokenAdapter {
+                token: tx,
+                stats: self.stats,
+                #[cfg(feature = "medium-ethernet")]
+                vlan_tag: self.vlan_tag,
+            };
+            (rx, tx)
+        })
     }
 
     /// Construct a transmit token.
     fn transmit(&mut self, _timestamp: Instant) -> Option<Self::TxToken<'_>> {
-        self.inner.transmit(unwrap!(self.cx.as_deref_mut())).map(TxTokenAdapter)
+        let stats = self.stats;
+        #[cfg(feature = "medium-ethernet")]
+        let vlan_tag = self.vlan_tag;
+        self.inner
+            .transmit(unwrap!(self.cx.as_deref_mut()))
+            .map(|token| TxTokenAdapter {
+                token,
+                stats,
+                #[cfg(feature = "medium-ethernet")]
+                vlan_tag,
+            })
     }
 
     /// Get a description of device capabilities.
@@ -70,11 +118,22 @@ where
     }
 }
 
-pub(crate) struct RxTokenAdapter<T>(T)
+pub(crate) struct RxTokenAdapter<'a, T>
 where
-    T: RxToken;
+    T: RxToken,
+{
+    token: T,
+    stats: &'a Cell<Statistics>,
+    #[cfg(feature = "rx-error-statistics")]
+    medium: Medium,
+    /// Checksums verified by the stack.
+    #[cfg(feature = "rx-error-statistics")]
+    checksum: phy::ChecksumCapabilities,
+    #[cfg(feature = "raw-ethernet")]
+    raw_ethernet: Option<&'a mut [Option<RawEthernetState>]>,
+}
 
-impl<T> phy::RxToken for RxTokenAdapter<T>
+impl<T> phy::RxToken for RxTokenAdapter<'_, T>
 where
     T: RxToken,
 {
@@ -82,27 +141,56 @@ where
     where
         F: FnOnce(&[u8]) -> R,
     {
-        self.0.consume(|buf| {
+        #[cfg(feature = "raw-ethernet")]
+        let meta = crate::raw::RawEthernetRecvMeta {
+            timestamp: self.token.timestamp(),
+            vlan_tag: self.token.vlan_tag(),
+        };
+        self.token.consume(|buf| {
             #[cfg(feature = "packet-trace")]
             trace!("embassy device rx: {:02x}", buf);
+            count_rx(self.stats, buf.len());
+            #[cfg(feature = "rx-error-statistics")]
+            match check_rx(self.medium, &self.checksum, buf) {
+                Ok(()) => {}
+                Err(RxError::Malformed) => count_rx_error(self.stats, |s| &mut s.rx_errors),
+                Err(RxError::Checksum) => count_rx_error(self.stats, |s| &mut s.rx_checksum_errors),
+                Err(RxError::Unsupported) => count_rx_error(self.stats, |s| &mut s.rx_unsupported),
+            }
+            #[cfg(feature = "raw-ethernet")]
+            if let Some(sockets) = self.raw_ethernet {
+                crate::raw::raw_ethernet_rx(sockets, buf, meta);
+            }
             f(buf)
         })
     }
 }
 
-pub(crate) struct TxTokenAdapter<T>(T)
+pub(crate) struct TxTokenAdapter<'a, T>
 where
-    T: TxToken;
+    T: TxToken,
+{
+    token: T,
+    stats: &'a Cell<Statistics>,
+    #[cfg(feature = "medium-ethernet")]
+    vlan_tag: Option<u16>,
+}
 
-impl<T> phy::TxToken for TxTokenAdapter<T>
+impl<T> phy::TxToken for TxTokenAdapter<'_, T>
 where
     T: TxToken,
 {
-    fn consume<R, F>(self, len: usize, f: F) -> R
+    #[allow(unused_mut)]
+    fn consume<R, F>(mut self, len: usize, f: F) -> R
     where
         F: FnOnce(&mut [u8]) -> R,
     {
-        self.0.consume(len, |buf| {
+        #[cfg(feature = "medium-ethernet")]
+        if let Some(tci) = self.vlan_tag {
+            self.token.set_vlan_tag(tci);
+        }
+        count_tx(self.stats, len);
+        self.token.consume(len, |buf| {
             let r = f(buf);
             #[cfg(feature = "packet-trace")]
             trace!("embassy device tx: {:02x}", buf);
@@ -110,3 +198,193 @@ where
         })
     }
 }
+
+pub(crate) fn count_rx(stats: &Cell<Statistics>, len: usize) {
+    let mut s = stats.get();
+    s.rx_packets = s.rx_packets.wrapping_add(1);
+    s.rx_bytes = s.rx_bytes.wrapping_add(len as u64);
+    stats.set(s);
+}
+
+pub(crate) fn count_tx(stats: &Cell<Statistics>, len: usize) {
+    let mut s = stats.get();
+    s.tx_packets = s.tx_packets.wrapping_add(1);
+    s.tx_bytes = s.tx_bytes.wrapping_add(len as u64);
+    stats.set(s);
+}
+
+#[cfg(feature = "rx-error-statistics")]
+fn count_rx_error(stats: &Cell<Statistics>, counter: impl FnOnce(&mut Statistics) -> &mut u64) {
+    let mut s = stats.get();
+    let c = counter(&mut s);
+    *c = c.wrapping_add(1);
+    stats.set(s);
+}
+
+/// Reason smoltcp drops a received packet without reporting it.
+#[cfg(feature = "rx-error-statistics")]
+enum RxError {
+    Malformed,
+    Checksum,
+    Unsupported,
+}
+
+/// Check the headers and the checksums the stack verifies, like smoltcp does when processing the
+/// packet, to count the packets it drops. IEEE 802.15.4 frames are not checked.
+#[cfg(feature = "rx-error-statistics")]
+#[cfg_attr(
+    not(any(feature = "medium-ethernet", feature = "medium-ip")),
+    allow(unused_variables)
+)]
+fn check_rx(medium: Medium, checksum: &phy::ChecksumCapabilities, buf: &[u8]) -> Result<(), RxError> {
+    let ip: Option<&[u8]> = match medium {
+        #[cfg(feature = "medium-ethernet")]
+        Medium::Ethernet => {
+            let frame = EthernetFrame::new_checked(buf).map_err(|_| RxError::Malformed)?;
+            match frame.ethertype() {
+                EthernetProtocol::Ipv4 | EthernetProtocol::Ipv6 => Some(frame.payload()),
+                _ => None,
+            }
+        }
+        #[cfg(feature = "medium-ip")]
+        Medium::Ip => Some(buf),
+        #[cfg(feature = "medium-ieee802154")]
+        Medium::Ieee802154 => None,
+    };
+    let Some(ip) = ip else {
+        return Ok(());
+    };
+
+    match ip.first().map(|b| b >> 4) {
+        #[cfg(feature = "proto-ipv4")]
+        Some(4) => check_ipv4(checksum, ip),
+        #[cfg(feature = "proto-ipv6")]
+        Some(6) => check_ipv6(checksum, ip),
+        Some(_) => Err(RxError::Unsupported),
+        None => Err(RxError::Malformed),
+    }
+}
+
+#[cfg(all(feature = "rx-error-statistics", feature = "proto-ipv4"))]
+fn check_ipv4(checksum: &phy::ChecksumCapabilities, buf: &[u8]) -> Result<(), RxError> {
+    let packet = Ipv4Packet::new_checked(buf).map_err(|_| RxError::Malformed)?;
+    if checksum.ipv4.rx() && !packet.verify_checksum() {
+        return Err(RxError::Checksum);
+    }
+    // Fragments are checked by smoltcp once reassembled.
+    if packet.more_frags() || packet.frag_offset() != 0 {
+        return Ok(());
+    }
+
+    let src = IpAddress::Ipv4(packet.src_addr());
+    let dst = IpAddress::Ipv4(packet.dst_addr());
+    match packet.next_header() {
+        IpProtocol::Icmp => {
+            let icmp = Icmpv4Packet::new_checked(packet.payload()).map_err(|_| RxError::Malformed)?;
+            if checksum.icmpv4.rx() && !icmp.verify_checksum() {
+                return Err(RxError::Checksum);
+            }
+            Ok(())
+        }
+        protocol => check_transport(checksum, protocol, &src, &dst, packet.payload()),
+    }
+}
+
+#[cfg(all(feature = "rx-error-statistics", feature = "proto-ipv6"))]
+fn check_ipv6(checksum: &phy::ChecksumCapabilities, buf: &[u8]) -> Result<(), RxError> {
+    let packet = Ipv6Packet::new_checked(buf).map_err(|_| RxError::Malformed)?;
+
+    let src = packet.src_addr();
+    let dst = packet.dst_addr();
+    // Packets with extension headers are not checked further.
+    match packet.next_header() {
+        IpProtocol::Icmpv6 => {
+            let icmp = Icmpv6Packet::new_checked(packet.payload()).map_err(|_| RxError::Malformed)?;
+            if checksum.icmpv6.rx() && !icmp.verify_checksum(&src, &dst) {
+                return Err(RxError::Checksum);
+            }
+            Ok(())
+        }
+        protocol => check_transport(
+            checksum,
+            protocol,
+            &IpAddress::Ipv6(src),
+            &IpAddress::Ipv6(dst),
+            packet.payload(),
+        ),
+    }
+}
+
+#[cfg(feature = "rx-error-statistics")]
+fn check_transport(
+    checksum: &phy::ChecksumCapabilities,
+    protocol: IpProtocol,
+    src: &IpAddress,
+    dst: &IpAddress,
+    payload: &[u8],
+) -> Result<(), RxError> {
+    let valid = match protocol {
+        IpProtocol::Tcp => {
+            let tcp = TcpPacket::new_checked(payload).map_err(|_| RxError::Malformed)?;
+            !checksum.tcp.rx() || tcp.verify_checksum(src, dst)
+        }
+        IpProtocol::Udp => {
+            let udp = UdpPacket::new_checked(payload).map_err(|_| RxError::Malformed)?;
+            !checksum.udp.rx() || udp.verify_checksum(src, dst)
+        }
+        _ => true,
+    };
+    match valid {
+        true => Ok(()),
+        false => Err(RxError::Checksum),
+    }
+}
+
+#[cfg(all(
+    test,
+    feature = "rx-error-statistics",
+    feature = "medium-ethernet",
+    feature = "proto-ipv4"
+))]
+mod tests {
+    use super::*;
+
+    const MACS: [u8; 12] = [0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x02, 0, 0, 0x12, 0x34, 0x56];
+
+    fn check(ethertype: [u8; 2], payload: &[u8]) -> Result<(), RxError> {
+        let mut frame = heapless::Vec::<u8, 64>::new();
+        unwrap!(frame.extend_from_slice(&MACS));
+        unwrap!(frame.extend_from_slice(&ethertype));
+        unwrap!(frame.extend_from_slice(payload));
+        check_rx(Medium::Ethernet, &phy::ChecksumCapabilities::default(), &frame)
+    }
+
+    #[test]
+    fn other_ethertypes_are_not_checked() {
+        assert!(check([0x08, 0x06], &[]).is_ok());
+    }
+
+    #[test]
+    fn truncated_frame_is_malformed() {
+        let res = check_rx(Medium::Ethernet, &phy::ChecksumCapabilities::default(), &MACS);
+        assert!(matches!(res, Err(RxError::Malformed)));
+        assert!(matches!(check([0x08, 0x00], &[]), Err(RxError::Malformed)));
+    }
+
+    #[test]
+    fn ipv4_header_checksum() {
+        let mut header = [
+            0x45, 0x00, 0x00, 0x14, 0x00, 0x00, 0x40, 0x00, 0x40, 0xff, 0x00, 0x00, 192, 168, 1, 1, 192, 168, 1, 2,
+        ];
+        assert!(matches!(check([0x08, 0x00], &header), Err(RxError::Checksum)));
+
+        let mut packet = Ipv4Packet::new_unchecked(&mut header[..]);
+        packet.fill_checksum();
+        assert!(check([0x08, 0x00], &header).is_ok());
+    }
+
+    #[test]
+    fn unknown_ip_version_is_unsupported() {
+        assert!(matches!(check([0x08, 0x00], &[0x55]), Err(RxError::Unsupported)));
+    }
+}
@@ -12,20 +12,28 @@ compile_error!("You must enable at least one of the following features: proto-ip
 // This mod MUST go first, so that the others see its macros.
 pub(crate) mod fmt;
 
+#[cfg(feature = "dhcpv4-server")]
+pub mod dhcp_server;
 #[cfg(feature = "dns")]
 pub mod dns;
 mod driver_util;
 #[cfg(feature = "icmp")]
 pub mod icmp;
+#[cfg(feature = "mdns-responder")]
+pub mod mdns;
 #[cfg(feature = "raw")]
 pub mod raw;
+#[cfg(feature = "slaac")]
+mod slaac;
+#[cfg(feature = "sntp")]
+pub mod sntp;
 #[cfg(feature = "tcp")]
 pub mod tcp;
 mod time;
 #[cfg(feature = "udp")]
 pub mod udp;
 
-use core::cell::RefCell;
+use core::cell::{Cell, RefCell};
 use core::future::{poll_fn, Future};
 use core::mem::MaybeUninit;
 use core::pin::pin;
@@ -69,6 +77,10 @@ const LOCAL_PORT_MAX: u16 = 65535;
 const MAX_QUERIES: usize = 4;
 #[cfg(feature = "dhcpv4-hostname")]
 const MAX_HOSTNAME_LEN: usize = 32;
+/// Joined groups, plus the all-hosts/all-nodes groups and the IPv6 solicited-node groups.
+#[cfg(all(feature = "multicast", feature = "medium-ethernet"))]
+const MAX_MULTICAST_MACS: usize =
+    smoltcp::config::IFACE_MAX_MULTICAST_GROUP_COUNT + smoltcp::config::IFACE_MAX_ADDR_COUNT + 2;
 
 /// Memory resources needed for a network stack.
 pub struct StackResources<const SOCK: usize> {
@@ -78,6 +90,8 @@ pub struct StackResources<const SOCK: usize> {
     queries: MaybeUninit<[Option<dns::DnsQuery>; MAX_QUERIES]>,
     #[cfg(feature = "dhcpv4-hostname")]
     hostname: HostnameResources,
+    #[cfg(feature = "slaac")]
+    slaac: slaac::SlaacResources,
 }
 
 #[cfg(feature = "dhcpv4-hostname")]
@@ -99,6 +113,8 @@ impl<const SOCK: usize> StackResources<SOCK> {
                 option: MaybeUninit::uninit(),
                 data: MaybeUninit::uninit(),
             },
+            #[cfg(feature = "slaac")]
+            slaac: slaac::SlaacResources::new(),
         }
     }
 }
@@ -167,6 +183,28 @@ impl Default for DhcpConfig {
     }
 }
 
+/// SLAAC configuration.
+#[cfg(feature = "slaac")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct SlaacConfig {
+    /// Interval between router solicitations, until a router advertisement is received.
+    pub solicitation_interval: embassy_time::Duration,
+    /// Ask a DHCPv6 server for DNS servers if the router advertisement sets the "other
+    /// configuration" flag and doesn't list DNS servers itself.
+    pub stateless_dhcpv6: bool,
+}
+
+#[cfg(feature = "slaac")]
+impl Default for SlaacConfig {
+    fn default() -> Self {
+        Self {
+            solicitation_interval: embassy_time::Duration::from_secs(4),
+            stateless_dhcpv6: true,
+        }
+    }
+}
+
 /// Network stack configuration.
 #[derive(Debug, Clone, Default)]
 #[non_exhaustive]
@@ -215,6 +253,22 @@ impl Config {
             ipv6: ConfigV6::None,
         }
     }
+
+    /// IPv6 configuration with stateless address autoconfiguration.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use embassy_net::Config;
+    /// let _cfg = Config::slaac(Default::default());
+    /// ```
+    #[cfg(feature = "slaac")]
+    pub const fn slaac(config: SlaacConfig) -> Self {
+        Self {
+            #[cfg(feature = "proto-ipv4")]
+            ipv4: ConfigV4::None,
+            ipv6: ConfigV6::Slaac(config),
+        }
+    }
 }
 
 /// Network stack IPv4 configuration.
@@ -240,6 +294,9 @@ pub enum ConfigV6 {
     None,
     /// Use a static IPv6 address configuration.
     Static(StaticConfigV6),
+    /// Use router advertisements to obtain an IP address configuration.
+    #[cfg(feature = "slaac")]
+    Slaac(SlaacConfig),
 }
 
 /// Network stack runner.
@@ -259,6 +316,39 @@ pub struct Stack<'d> {
     inner: &'d RefCell<Inner>,
 }
 
+/// Interface statistics.
+///
+/// Counts the packets and bytes exchanged with the driver, including link-layer headers, and the
+/// received packets the stack drops because of errors. Packets dropped by the driver before
+/// reaching the stack are not counted. smoltcp doesn't report TCP retransmissions, so there are
+/// no per-socket counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Statistics {
+    /// Number of packets received from the driver.
+    pub rx_packets: u64,
+    /// Number of bytes received from the driver.
+    pub rx_bytes: u64,
+    /// Number of packets handed to the driver for transmission.
+    pub tx_packets: u64,
+    /// Number of bytes handed to the driver for transmission.
+    pub tx_bytes: u64,
+    /// Number of received packets dropped because of a truncated or malformed IP, TCP, UDP or
+    /// ICMP header.
+    ///
+    /// Only counted with the `rx-error-statistics` feature.
+    pub rx_errors: u64,
+    /// Number of received packets dropped because of an IP, TCP, UDP or ICMP checksum error.
+    ///
+    /// Only counts the checksums the stack verifies, not those the driver verifies. Only counted
+    /// with the `rx-error-statistics` feature.
+    pub rx_checksum_errors: u64,
+    /// Number of received IP packets dropped because their IP version is not enabled.
+    ///
+    /// Only counted with the `rx-error-statistics` feature.
+    pub rx_unsupported: u64,
+}
+
 pub(crate) struct Inner {
     pub(crate) sockets: SocketSet<'static>, // Lifetime type-erased.
     pub(crate) iface: Interface,
@@ -269,6 +359,7 @@ pub(crate) struct Inner {
     hardware_address: HardwareAddress,
     next_local_port: u16,
     link_up: bool,
+    stats: Cell<Statistics>,
     #[cfg(feature = "proto-ipv4")]
     static_v4: Option<StaticConfigV4>,
     #[cfg(feature = "proto-ipv6")]
@@ -281,6 +372,24 @@ pub(crate) struct Inner {
     dns_waker: WakerRegistration,
     #[cfg(feature = "dhcpv4-hostname")]
     hostname: *mut HostnameResources,
+    #[cfg(feature = "slaac")]
+    slaac: Option<slaac::Slaac>,
+    #[cfg(feature = "slaac")]
+    slaac_resources: *mut slaac::SlaacResources,
+    /// Multicast groups joined through the stack.
+    #[cfg(all(feature = "multicast", feature = "medium-ethernet"))]
+    multicast_groups: Vec<IpAddress, { smoltcp::config::IFACE_MAX_MULTICAST_GROUP_COUNT }>,
+    /// Multicast addresses added to the driver's filter.
+    #[cfg(all(feature = "multicast", feature = "medium-ethernet"))]
+    multicast_macs: Vec<[u8; 6], MAX_MULTICAST_MACS>,
+    #[cfg(feature = "raw-ethernet")]
+    pub(crate) raw_ethernet: [Option<raw::RawEthernetState>; raw::MAX_RAW_ETHERNET_SOCKETS],
+    /// Largest frame the driver can send, for the raw Ethernet sockets.
+    #[cfg(feature = "raw-ethernet")]
+    pub(crate) raw_ethernet_mtu: usize,
+    /// VLAN tag inserted by the driver in the frames sent by the stack.
+    #[cfg(feature = "medium-ethernet")]
+    vlan_tag: Option<u16>,
 }
 
 fn _assert_covariant<'a, 'b: 'a>(x: Stack<'b>) -> Stack<'a> {
@@ -304,6 +413,11 @@ pub fn new<'d, D: Driver, const SOCK: usize>(
             inner: &mut driver,
             cx: None,
             medium,
+            stats: &Cell::new(Statistics::default()),
+            #[cfg(feature = "raw-ethernet")]
+            raw_ethernet: None,
+            #[cfg(feature = "medium-ethernet")]
+            vlan_tag: None,
         },
         instant_to_smoltcp(Instant::now()),
     );
@@ -334,6 +448,7 @@ pub fn new<'d, D: Driver, const SOCK: usize>(
         next_local_port,
         hardware_address,
         link_up: false,
+        stats: Cell::new(Statistics::default()),
         #[cfg(feature = "proto-ipv4")]
         static_v4: None,
         #[cfg(feature = "proto-ipv6")]
@@ -346,6 +461,20 @@ pub fn new<'d, D: Driver, const SOCK: usize>(
         dns_waker: WakerRegistration::new(),
         #[cfg(feature = "dhcpv4-hostname")]
         hostname: &mut resources.hostname,
+        #[cfg(feature = "slaac")]
+        slaac: None,
+        #[cfg(feature = "slaac")]
+        slaac_resources: &mut resources.slaac,
+        #[cfg(all(feature = "multicast", feature = "medium-ethernet"))]
+        multicast_groups: Vec::new(),
+        #[cfg(all(feature = "multicast", feature = "medium-ethernet"))]
+        multicast_macs: Vec::new(),
+        #[cfg(feature = "raw-ethernet")]
+        raw_ethernet: [const { None }; raw::MAX_RAW_ETHERNET_SOCKETS],
+        #[cfg(feature = "raw-ethernet")]
+        raw_ethernet_mtu: driver.capabilities().max_transmission_unit,
+        #[cfg(feature = "medium-ethernet")]
+        vlan_tag: None,
     };
 
     #[cfg(feature = "proto-ipv4")]
@@ -398,6 +527,38 @@ impl<'d> Stack<'d> {
         self.with(|i| i.link_up)
     }
 
+    /// Tag the frames sent by the stack with an IEEE 802.1Q VLAN tag.
+    ///
+    /// `tci` is the tag control information (priority, DEI and VLAN ID), or `None` to send
+    /// untagged frames. The tag is inserted by the driver, which must support it, see
+    /// [`Capabilities::vlan_tag_insertion`](driver::Capabilities::vlan_tag_insertion). Received
+    /// frames are not filtered by the stack, only by the driver's VLAN filter if it has one.
+    #[cfg(feature = "medium-ethernet")]
+    pub fn set_vlan_tag(&self, tci: Option<u16>) {
+        self.with_mut(|i| {
+            i.vlan_tag = tci;
+            i.waker.wake();
+        })
+    }
+
+    /// Get the VLAN tag inserted in the frames sent by the stack, see [`Self::set_vlan_tag`].
+    #[cfg(feature = "medium-ethernet")]
+    pub fn vlan_tag(&self) -> Option<u16> {
+        self.with(|i| i.vlan_tag)
+    }
+
+    /// Get the interface statistics.
+    ///
+    /// Counters start at zero when the stack is created and wrap around on overflow.
+    pub fn statistics(&self) -> Statistics {
+        self.with(|i| i.stats.get())
+    }
+
+    /// Reset the interface statistics to zero.
+    pub fn reset_statistics(&self) {
+        self.with(|i| i.stats.set(Statistics::default()))
+    }
+
     /// Check whether the network stack has a valid IP configuration.
     /// This is true if the network stack has a static IP configuration or if DHCP has completed
     pub fn is_config_up(&self) -> bool {
@@ -628,13 +789,33 @@ impl<'d> Stack<'d> {
 #[cfg(feature = "multicast")]
 impl<'d> Stack<'d> {
     /// Join a multicast group.
+    ///
+    /// If the driver filters multicast packets, it is told to receive the group's packets the next
+    /// time the stack is polled.
     pub fn join_multicast_group(&self, addr: impl Into<IpAddress>) -> Result<(), MulticastError> {
-        self.with_mut(|i| i.iface.join_multicast_group(addr))
+        let addr = addr.into();
+        self.with_mut(|i| {
+            i.iface.join_multicast_group(addr)?;
+            #[cfg(feature = "medium-ethernet")]
+            if !i.multicast_groups.contains(&addr) {
+                // Can't overflow, smoltcp has the same limit.
+                let _ = i.multicast_groups.push(addr);
+            }
+            i.waker.wake();
+            Ok(())
+        })
     }
 
     /// Leave a multicast group.
     pub fn leave_multicast_group(&self, addr: impl Into<IpAddress>) -> Result<(), MulticastError> {
-        self.with_mut(|i| i.iface.leave_multicast_group(addr))
+        let addr = addr.into();
+        self.with_mut(|i| {
+            i.iface.leave_multicast_group(addr)?;
+            #[cfg(feature = "medium-ethernet")]
+            i.multicast_groups.retain(|a| *a != addr);
+            i.waker.wake();
+            Ok(())
+        })
     }
 
     /// Get whether the network stack has joined the given multicast group.
@@ -712,10 +893,36 @@ impl Inner {
 
     #[cfg(feature = "proto-ipv6")]
     pub fn set_config_v6(&mut self, config: ConfigV6) {
-        self.static_v6 = match config {
+        // Handle static config.
+        self.static_v6 = match config.clone() {
             ConfigV6::None => None,
+            #[cfg(feature = "slaac")]
+            ConfigV6::Slaac(_) => None,
             ConfigV6::Static(c) => Some(c),
         };
+
+        // Handle SLAAC config.
+        #[cfg(feature = "slaac")]
+        match config {
+            ConfigV6::Slaac(c) => match &mut self.slaac {
+                Some(slaac) => {
+                    slaac.set_config(c);
+                    slaac.reset();
+                }
+                None => {
+                    // safety: the resources live for as long as the stack exists, because `new()`
+                    // borrows them for `'d`, and the previous sockets using them were removed.
+                    let resources = unsafe { &mut *self.slaac_resources };
+                    self.slaac = Some(slaac::Slaac::new(&mut self.sockets, resources, c));
+                }
+            },
+            _ => {
+                // Remove SLAAC sockets if any.
+                if let Some(slaac) = self.slaac.take() {
+                    slaac.remove(&mut self.sockets);
+                }
+            }
+        }
     }
 
     fn apply_static_config(&mut self) {
@@ -795,6 +1002,55 @@ impl Inner {
         self.state_waker.wake();
     }
 
+    /// Bring the driver's multicast filter in sync with the joined groups.
+    #[cfg(all(feature = "multicast", feature = "medium-ethernet"))]
+    fn update_multicast_filter<D: Driver>(&mut self, driver: &mut D) {
+        let mut macs = Vec::<[u8; 6], MAX_MULTICAST_MACS>::new();
+        let mut add = |mac: [u8; 6]| {
+            if !macs.contains(&mac) {
+                let _ = macs.push(mac);
+            }
+        };
+
+        // All-hosts group, for IGMP queries.
+        #[cfg(feature = "proto-ipv4")]
+        add([0x01, 0x00, 0x5e, 0x00, 0x00, 0x01]);
+        // All-nodes group and the solicited-node groups of our addresses, for NDISC.
+        #[cfg(feature = "proto-ipv6")]
+        {
+            add([0x33, 0x33, 0x00, 0x00, 0x00, 0x01]);
+            for cidr in self.iface.ip_addrs() {
+                #[allow(irrefutable_let_patterns)]
+                if let IpAddress::Ipv6(addr) = cidr.address() {
+                    let o = addr.octets();
+                    add([0x33, 0x33, 0xff, o[13], o[14], o[15]]);
+                }
+            }
+        }
+        for group in &self.multicast_groups {
+            add(match group {
+                #[cfg(feature = "proto-ipv4")]
+                IpAddress::Ipv4(addr) => {
+                    let o = addr.octets();
+                    [0x01, 0x00, 0x5e, o[1] & 0x7f, o[2], o[3]]
+                }
+                #[cfg(feature = "proto-ipv6")]
+                IpAddress::Ipv6(addr) => {
+                    let o = addr.octets();
+                    [0x33, 0x33, o[12], o[13], o[14], o[15]]
+                }
+            });
+        }
+
+        for mac in self.multicast_macs.iter().filter(|mac| !macs.contains(mac)) {
+            driver.remove_multicast_address(*mac);
+        }
+        for mac in macs.iter().filter(|mac| !self.multicast_macs.contains(mac)) {
+            driver.add_multicast_address(*mac);
+        }
+        self.multicast_macs = macs;
+    }
+
     fn poll<D: Driver>(&mut self, cx: &mut Context<'_>, driver: &mut D) {
         self.waker.register(cx.waker());
 
@@ -815,11 +1071,26 @@ impl Inner {
             }
         }
 
+        #[cfg(all(feature = "multicast", feature = "medium-ethernet"))]
+        if medium == Medium::Ethernet {
+            self.update_multicast_filter(driver);
+        }
+
+        #[cfg(feature = "raw-ethernet")]
+        if medium == Medium::Ethernet {
+            raw::raw_ethernet_tx(&mut self.raw_ethernet, driver, cx, &self.stats);
+        }
+
         let timestamp = instant_to_smoltcp(Instant::now());
         let mut smoldev = DriverAdapter {
             cx: Some(cx),
             inner: driver,
             medium,
+            stats: &self.stats,
+            #[cfg(feature = "raw-ethernet")]
+            raw_ethernet: (medium == Medium::Ethernet).then_some(&mut self.raw_ethernet[..]),
+            #[cfg(feature = "medium-ethernet")]
+            vlan_tag: self.vlan_tag,
         };
         self.iface.poll(timestamp, &mut smoldev, &mut self.sockets);
 
@@ -868,6 +1139,43 @@ impl Inner {
             }
         }
 
+        #[cfg(feature = "slaac")]
+        if let (Some(slaac), HardwareAddress::Ethernet(mac)) = (&mut self.slaac, self.hardware_address) {
+            let configure = if self.link_up {
+                if old_link_up != self.link_up {
+                    slaac.reset();
+                }
+                match slaac.poll(cx, &mut self.sockets, mac.0) {
+                    None => false,
+                    Some(slaac::Event::Deconfigured) => {
+                        self.static_v6 = None;
+                        true
+                    }
+                    Some(slaac::Event::Configured(config)) => {
+                        self.static_v6 = Some(config);
+                        true
+                    }
+                }
+            } else if old_link_up {
+                slaac.reset();
+                self.static_v6 = None;
+                true
+            } else {
+                false
+            };
+            if configure {
+                self.apply_static_config()
+            }
+        }
+
+        #[cfg(feature = "slaac")]
+        if let Some(poll_at) = self.slaac.as_ref().and_then(|s| s.poll_at()) {
+            let t = pin!(Timer::at(poll_at));
+            if t.poll(cx).is_ready() {
+                cx.waker().wake_by_ref();
+            }
+        }
+
         if let Some(poll_at) = self.iface.poll_at(timestamp, &mut self.sockets) {
             let t = pin!(Timer::at(instant_from_smoltcp(poll_at)));
             if t.poll(cx).is_ready() {
@@ -0,0 +1,1011 @@
+//! mDNS responder with DNS-SD service advertisement.
+//!
+//! Answers multicast DNS queries for `<hostname>.local` with the stack's addresses, so the device
+//! can be reached by name on the local network without a DNS server or a known static IP.
+//!
+//! Services registered with [`MdnsResponder::add_service`] are advertised with DNS-SD (RFC 6763)
+//! PTR, SRV and TXT records, so clients browsing for e.g. `_http._tcp` find the device and the
+//! port the service listens on.
+//!
+//! Before answering, the responder probes the network to check that no other host uses the same
+//! hostname or service instance names. If a conflict is found, either while probing or later, it
+//! picks a new hostname by appending `-2`, `-3`, ... or a new instance name by appending ` (2)`,
+//! ` (3)`, ... and probes again. [`MdnsResponder::hostname`] returns the hostname currently in use.
+//!
+//! The responder checks the stack's addresses every second, and probes and announces again when
+//! they change.
+//!
+//! Known-answer suppression and simultaneous probe tie-breaking are not implemented.
+
+use core::fmt::Write;
+
+use embassy_time::{with_deadline, Duration, Instant};
+use heapless::{String, Vec};
+#[cfg(feature = "proto-ipv4")]
+use smoltcp::wire::Ipv4Address;
+#[cfg(feature = "proto-ipv6")]
+use smoltcp::wire::Ipv6Address;
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+use crate::udp::{PacketMetadata, UdpSocket};
+use crate::Stack;
+
+/// mDNS port.
+pub const MDNS_PORT: u16 = 5353;
+/// IPv4 mDNS multicast group.
+#[cfg(feature = "proto-ipv4")]
+pub const MDNS_GROUP_V4: Ipv4Address = Ipv4Address::new(224, 0, 0, 251);
+/// IPv6 mDNS multicast group.
+#[cfg(feature = "proto-ipv6")]
+pub const MDNS_GROUP_V6: Ipv6Address = Ipv6Address::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
+
+/// Maximum length of the hostname passed to [`MdnsResponder::new`], leaving room in the DNS label
+/// for the suffix added on conflicts.
+pub const MAX_HOSTNAME_LEN: usize = 56;
+/// Maximum length of a service instance name, leaving room in the DNS label for the suffix added
+/// on conflicts.
+pub const MAX_INSTANCE_NAME_LEN: usize = 56;
+/// Maximum number of services that can be registered.
+pub const MAX_SERVICES: usize = 8;
+
+const MAX_LABEL_LEN: usize = 63;
+
+const TYPE_A: u16 = 1;
+const TYPE_PTR: u16 = 12;
+const TYPE_TXT: u16 = 16;
+const TYPE_AAAA: u16 = 28;
+const TYPE_SRV: u16 = 33;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+const CLASS_ANY: u16 = 255;
+/// Cache-flush bit of the class field in responses, unicast-response bit in questions.
+const CLASS_FLAG: u16 = 0x8000;
+
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_AUTHORITATIVE: u16 = 0x0400;
+const OPCODE_MASK: u16 = 0x7800;
+
+const PROBE_COUNT: u8 = 3;
+const PROBE_INTERVAL: Duration = Duration::from_millis(250);
+const ANNOUNCE_COUNT: u8 = 2;
+const ANNOUNCE_INTERVAL: Duration = Duration::from_secs(1);
+/// How often the stack's addresses are checked for changes once announced.
+const ADDRESS_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// TTL of the records containing a hostname, as recommended by RFC 6762.
+const HOST_TTL: u32 = 120;
+/// TTL of the other records, as recommended by RFC 6762.
+const OTHER_TTL: u32 = 4500;
+/// TTL of the records sent to legacy unicast resolvers.
+const LEGACY_TTL: u32 = 10;
+
+const SERVICES_NAME: [&str; 4] = ["_services", "_dns-sd", "_udp", "local"];
+
+/// Transport protocol of a DNS-SD service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ServiceProtocol {
+    /// TCP, `_tcp`.
+    Tcp,
+    /// Any other protocol, `_udp`.
+    Udp,
+}
+
+impl ServiceProtocol {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Tcp => "_tcp",
+            Self::Udp => "_udp",
+        }
+    }
+}
+
+/// A service advertised with DNS-SD.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Service<'a> {
+    /// Instance name shown to users, e.g. `"Living Room Sensor"`.
+    pub instance: &'a str,
+    /// Service type, e.g. `"_http"`.
+    pub service: &'a str,
+    /// Transport protocol of the service.
+    pub protocol: ServiceProtocol,
+    /// Port the service listens on.
+    pub port: u16,
+    /// TXT record strings, usually `key=value` pairs.
+    pub txt: &'a [&'a str],
+}
+
+struct ServiceEntry<'a> {
+    service: Service<'a>,
+    instance: String<MAX_LABEL_LEN>,
+    conflicts: u32,
+}
+
+impl<'a> ServiceEntry<'a> {
+    fn rename(&mut self) {
+        self.conflicts += 1;
+        warn!("mdns: service instance {} is already in use", self.instance.as_str());
+        self.instance.clear();
+        let _ = write!(self.instance, "{} ({})", self.service.instance, self.conflicts + 1);
+    }
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Probing(u8),
+    Announcing(u8),
+    Running,
+}
+
+/// The stack's addresses, announced in the A and AAAA records.
+#[derive(PartialEq, Eq)]
+struct Addresses {
+    #[cfg(feature = "proto-ipv4")]
+    v4: Option<Ipv4Address>,
+    #[cfg(feature = "proto-ipv6")]
+    v6: Option<Ipv6Address>,
+}
+
+impl Addresses {
+    fn current(stack: Stack<'_>) -> Self {
+        Self {
+            #[cfg(feature = "proto-ipv4")]
+            v4: stack.config_v4().map(|c| c.address.address()),
+            #[cfg(feature = "proto-ipv6")]
+            v6: stack.config_v6().map(|c| c.address.address()),
+        }
+    }
+
+    /// Returns whether a record with the given type and data matches one of our addresses.
+    ///
+    /// Records of other types are never a conflict.
+    fn matches(&self, rtype: u16, rdata: &[u8]) -> bool {
+        match rtype {
+            #[cfg(feature = "proto-ipv4")]
+            TYPE_A => self.v4.is_some_and(|a| a.octets() == rdata),
+            #[cfg(not(feature = "proto-ipv4"))]
+            TYPE_A => false,
+            #[cfg(feature = "proto-ipv6")]
+            TYPE_AAAA => self.v6.is_some_and(|a| a.octets() == rdata),
+            #[cfg(not(feature = "proto-ipv6"))]
+            TYPE_AAAA => false,
+            _ => true,
+        }
+    }
+
+    /// The multicast groups to send probes and announcements to.
+    fn groups(&self) -> Vec<IpEndpoint, 2> {
+        let mut groups = Vec::new();
+        #[cfg(feature = "proto-ipv4")]
+        if self.v4.is_some() {
+            let _ = groups.push(IpEndpoint::new(MDNS_GROUP_V4.into(), MDNS_PORT));
+        }
+        #[cfg(feature = "proto-ipv6")]
+        if self.v6.is_some() {
+            let _ = groups.push(IpEndpoint::new(MDNS_GROUP_V6.into(), MDNS_PORT));
+        }
+        groups
+    }
+}
+
+/// A name the responder is authoritative for.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Name {
+    /// `<hostname>.local`
+    Host,
+    /// `_services._dns-sd._udp.local`, for service type enumeration.
+    Services,
+    /// `<service>.<protocol>.local` of the given service.
+    ServiceType(usize),
+    /// `<instance>.<service>.<protocol>.local` of the given service.
+    Instance(usize),
+}
+
+/// The records a message asks for or contains. Service records are bitmasks indexed by service.
+#[derive(Clone, Copy, Default)]
+struct Want {
+    a: bool,
+    aaaa: bool,
+    services: bool,
+    ptr: u32,
+    srv: u32,
+    txt: u32,
+}
+
+impl Want {
+    /// All records with unique names, for probes.
+    fn unique(service_count: usize) -> Self {
+        let all = (1 << service_count) - 1;
+        Self {
+            a: true,
+            aaaa: true,
+            services: false,
+            ptr: 0,
+            srv: all,
+            txt: all,
+        }
+    }
+
+    /// All records, for announcements.
+    fn all(service_count: usize) -> Self {
+        Self {
+            ptr: (1 << service_count) - 1,
+            ..Self::unique(service_count)
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        !(self.a || self.aaaa || self.services) && self.ptr == 0 && self.srv == 0 && self.txt == 0
+    }
+
+    /// The records to add to the additional section of a response with these answers, as
+    /// recommended by RFC 6763.
+    fn additional(&self) -> Self {
+        let srv = self.ptr & !self.srv;
+        let txt = self.ptr & !self.txt;
+        let host = (self.srv | srv) != 0;
+        Self {
+            a: host && !self.a,
+            aaaa: host && !self.aaaa,
+            services: false,
+            ptr: 0,
+            srv,
+            txt,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Probe,
+    Response,
+    Legacy,
+}
+
+/// The names and records the responder is authoritative for.
+struct Zone<'z> {
+    hostname: &'z str,
+    addrs: &'z Addresses,
+    services: &'z [ServiceEntry<'z>],
+}
+
+impl<'z> Zone<'z> {
+    fn labels(&self, name: Name) -> Vec<&'z str, 4> {
+        unwrap!(match name {
+            Name::Host => Vec::from_slice(&[self.hostname, "local"]),
+            Name::Services => Vec::from_slice(&SERVICES_NAME),
+            Name::ServiceType(i) => {
+                let s = &self.services[i].service;
+                Vec::from_slice(&[s.service, s.protocol.label(), "local"])
+            }
+            Name::Instance(i) => {
+                let s = &self.services[i];
+                Vec::from_slice(&[
+                    s.instance.as_str(),
+                    s.service.service,
+                    s.service.protocol.label(),
+                    "local",
+                ])
+            }
+        })
+    }
+
+    fn names(&self) -> impl Iterator<Item = Name> {
+        let services = 0..self.services.len();
+        [Name::Host, Name::Services]
+            .into_iter()
+            .chain(services.clone().map(Name::ServiceType))
+            .chain(services.map(Name::Instance))
+    }
+
+    /// Read the name at `pos`, returning which of our names it is, if any, and the offset after it.
+    fn read_name(&self, msg: &[u8], pos: usize) -> Option<(Option<Name>, usize)> {
+        let mut end = 0;
+        for name in self.names() {
+            let (matched, next) = read_name(msg, pos, &self.labels(name))?;
+            if matched {
+                return Some((Some(name), next));
+            }
+            end = next;
+        }
+        Some((None, end))
+    }
+
+    /// Returns whether a SRV record for a service instance has our data.
+    fn srv_matches(&self, index: usize, msg: &[u8], rdata: usize) -> bool {
+        let port = read_u16(msg, rdata + 4);
+        let target = read_name(msg, rdata + 6, &self.labels(Name::Host));
+        port == Some(self.services[index].service.port) && matches!(target, Some((true, _)))
+    }
+
+    /// Write the records in `want`, returning how many were written.
+    fn write_records(&self, w: &mut Writer<'_>, want: Want, mode: Mode) -> u16 {
+        let unique_class = match mode {
+            Mode::Response => CLASS_IN | CLASS_FLAG,
+            Mode::Probe | Mode::Legacy => CLASS_IN,
+        };
+        let ttl = |ttl| if mode == Mode::Legacy { LEGACY_TTL } else { ttl };
+        let mut count = 0;
+
+        let host = self.labels(Name::Host);
+        #[cfg(feature = "proto-ipv4")]
+        if let Some(addr) = self.addrs.v4.filter(|_| want.a) {
+            w.record(&host, TYPE_A, unique_class, ttl(HOST_TTL), |w| w.bytes(&addr.octets()));
+            count += 1;
+        }
+        #[cfg(feature = "proto-ipv6")]
+        if let Some(addr) = self.addrs.v6.filter(|_| want.aaaa) {
+            w.record(&host, TYPE_AAAA, unique_class, ttl(HOST_TTL), |w| {
+                w.bytes(&addr.octets())
+            });
+            count += 1;
+        }
+
+        for (i, entry) in self.services.iter().enumerate() {
+            let s = &entry.service;
+            let bit = 1 << i;
+            let first_of_type = !self.services[..i]
+                .iter()
+                .any(|e| e.service.service == s.service && e.service.protocol == s.protocol);
+
+            if want.services && first_of_type {
+                w.record(&SERVICES_NAME, TYPE_PTR, CLASS_IN, ttl(OTHER_TTL), |w| {
+                    w.name(&self.labels(Name::ServiceType(i)))
+                });
+                count += 1;
+            }
+            if want.ptr & bit != 0 {
+                w.record(
+                    &self.labels(Name::ServiceType(i)),
+                    TYPE_PTR,
+                    CLASS_IN,
+                    ttl(OTHER_TTL),
+                    |w| w.name(&self.labels(Name::Instance(i))),
+                );
+                count += 1;
+            }
+            let instance = self.labels(Name::Instance(i));
+            if want.srv & bit != 0 {
+                w.record(&instance, TYPE_SRV, unique_class, ttl(HOST_TTL), |w| {
+                    w.u16(0); // priority
+                    w.u16(0); // weight
+                    w.u16(s.port);
+                    w.name(&host);
+                });
+                count += 1;
+            }
+            if want.txt & bit != 0 {
+                w.record(&instance, TYPE_TXT, unique_class, ttl(OTHER_TTL), |w| {
+                    for txt in s.txt {
+                        w.bytes(&[txt.len() as u8]);
+                        w.bytes(txt.as_bytes());
+                    }
+                    if s.txt.is_empty() {
+                        w.bytes(&[0]);
+                    }
+                });
+                count += 1;
+            }
+        }
+
+        count
+    }
+}
+
+/// The parts of a received message the responder acts upon.
+struct Message {
+    id: u16,
+    /// A response contains a record for our hostname with different data.
+    host_conflict: bool,
+    /// A response contains a SRV record for these service instances with different data.
+    service_conflicts: u32,
+    /// Records asked for by a query.
+    want: Want,
+    /// A question asked for a unicast response.
+    unicast: bool,
+    /// The last question for one of our names, echoed to legacy resolvers.
+    question: Option<(Name, u16)>,
+}
+
+/// mDNS responder.
+pub struct MdnsResponder<'a> {
+    stack: Stack<'a>,
+    socket: UdpSocket<'a>,
+    base_name: String<MAX_HOSTNAME_LEN>,
+    hostname: String<MAX_LABEL_LEN>,
+    conflicts: u32,
+    services: Vec<ServiceEntry<'a>, MAX_SERVICES>,
+}
+
+impl<'a> MdnsResponder<'a> {
+    /// Create a new mDNS responder for `<hostname>.local`, using the provided stack and socket
+    /// buffers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `hostname` is empty or longer than [`MAX_HOSTNAME_LEN`].
+    pub fn new(
+        stack: Stack<'a>,
+        rx_meta: &'a mut [PacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [PacketMetadata],
+        tx_buffer: &'a mut [u8],
+        hostname: &str,
+    ) -> Self {
+        assert!(!hostname.is_empty());
+        let base_name = unwrap!(String::try_from(hostname));
+
+        let mut socket = UdpSocket::new(stack, rx_meta, rx_buffer, tx_meta, tx_buffer);
+        unwrap!(socket.bind(MDNS_PORT));
+
+        Self {
+            stack,
+            socket,
+            hostname: unwrap!(String::try_from(hostname)),
+            base_name,
+            conflicts: 0,
+            services: Vec::new(),
+        }
+    }
+
+    /// Register a service to advertise with DNS-SD.
+    ///
+    /// Services must be added before calling [`MdnsResponder::run`]. Returns the service back if
+    /// [`MAX_SERVICES`] services are already registered.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the instance name is empty or longer than [`MAX_INSTANCE_NAME_LEN`], or if a TXT
+    /// string is longer than 255 bytes.
+    pub fn add_service(&mut self, service: Service<'a>) -> Result<(), Service<'a>> {
+        assert!(!service.instance.is_empty());
+        assert!(service.txt.iter().all(|txt| txt.len() <= 255));
+        let instance = unwrap!(String::<MAX_INSTANCE_NAME_LEN>::try_from(service.instance));
+
+        self.services
+            .push(ServiceEntry {
+                service,
+                instance: unwrap!(String::try_from(instance.as_str())),
+                conflicts: 0,
+            })
+            .map_err(|e| e.service)
+    }
+
+    /// Get the hostname currently in use, without the `.local` suffix.
+    ///
+    /// This differs from the configured hostname if another host on the network was using it.
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// Run the responder.
+    ///
+    /// This waits for the stack to be configured, probes for the hostname and service instance
+    /// names, announces them and then answers queries forever.
+    pub async fn run(&mut self) -> ! {
+        #[cfg(feature = "proto-ipv4")]
+        if let Err(e) = self.stack.join_multicast_group(MDNS_GROUP_V4) {
+            warn!("mdns: failed to join multicast group: {:?}", e);
+        }
+        #[cfg(feature = "proto-ipv6")]
+        if let Err(e) = self.stack.join_multicast_group(MDNS_GROUP_V6) {
+            warn!("mdns: failed to join multicast group: {:?}", e);
+        }
+
+        self.stack.wait_config_up().await;
+
+        let mut addrs = Addresses::current(self.stack);
+        let mut state = State::Probing(0);
+        let mut deadline = Instant::now() + PROBE_INTERVAL;
+
+        loop {
+            let current = Addresses::current(self.stack);
+            if current != addrs {
+                // RFC 6762 section 8: probe and announce again after a network change.
+                info!("mdns: addresses changed");
+                addrs = current;
+                state = State::Probing(0);
+                deadline = Instant::now() + PROBE_INTERVAL;
+            }
+
+            let zone = Zone {
+                hostname: &self.hostname,
+                addrs: &addrs,
+                services: &self.services,
+            };
+            let res = with_deadline(
+                deadline,
+                self.socket
+                    .recv_from_with(|buf, meta| (parse(buf, &zone), meta.endpoint)),
+            )
+            .await;
+
+            match res {
+                Ok((Some(msg), endpoint)) => {
+                    if msg.host_conflict || msg.service_conflicts != 0 {
+                        if msg.host_conflict {
+                            self.rename();
+                        }
+                        for (i, entry) in self.services.iter_mut().enumerate() {
+                            if msg.service_conflicts & (1 << i) != 0 {
+                                entry.rename();
+                            }
+                        }
+                        state = State::Probing(0);
+                        deadline = Instant::now() + PROBE_INTERVAL;
+                    } else if !matches!(state, State::Probing(_)) && !msg.want.is_empty() {
+                        self.respond(&msg, endpoint, &addrs).await;
+                    }
+                }
+                Ok((None, _)) => {}
+                Err(_) => {
+                    let (next, interval) = match state {
+                        State::Probing(n) if n < PROBE_COUNT => {
+                            self.probe(&addrs).await;
+                            (State::Probing(n + 1), PROBE_INTERVAL)
+                        }
+                        State::Probing(_) => {
+                            info!("mdns: using hostname {}.local", self.hostname.as_str());
+                            self.announce(&addrs).await;
+                            (State::Announcing(1), ANNOUNCE_INTERVAL)
+                        }
+                        State::Announcing(n) if n < ANNOUNCE_COUNT => {
+                            self.announce(&addrs).await;
+                            (State::Announcing(n + 1), ANNOUNCE_INTERVAL)
+                        }
+                        State::Announcing(_) | State::Running => (State::Running, ADDRESS_CHECK_INTERVAL),
+                    };
+                    state = next;
+                    deadline = Instant::now() + interval;
+                }
+            }
+        }
+    }
+
+    fn rename(&mut self) {
+        self.conflicts += 1;
+        warn!("mdns: hostname {}.local is already in use", self.hostname.as_str());
+        self.hostname.clear();
+        let _ = write!(self.hostname, "{}-{}", self.base_name, self.conflicts + 1);
+    }
+
+    async fn probe(&mut self, addrs: &Addresses) {
+        let zone = Zone {
+            hostname: &self.hostname,
+            addrs,
+            services: &self.services,
+        };
+        let questions = [Name::Host]
+            .into_iter()
+            .chain((0..self.services.len()).map(Name::Instance));
+
+        for group in addrs.groups() {
+            send(&mut self.socket, group, |w| {
+                w.header(0, 0);
+                let mut count = 0;
+                for name in questions.clone() {
+                    w.name(&zone.labels(name));
+                    w.u16(TYPE_ANY);
+                    w.u16(CLASS_IN | CLASS_FLAG);
+                    count += 1;
+                }
+                let authority = zone.write_records(w, Want::unique(zone.services.len()), Mode::Probe);
+                w.counts(count, 0, authority, 0);
+            })
+            .await;
+        }
+    }
+
+    async fn announce(&mut self, addrs: &Addresses) {
+        let zone = Zone {
+            hostname: &self.hostname,
+            addrs,
+            services: &self.services,
+        };
+
+        for group in addrs.groups() {
+            send(&mut self.socket, group, |w| {
+                w.header(0, FLAG_RESPONSE | FLAG_AUTHORITATIVE);
+                let answers = zone.write_records(w, Want::all(zone.services.len()), Mode::Response);
+                w.counts(0, answers, 0, 0);
+            })
+            .await;
+        }
+    }
+
+    async fn respond(&mut self, msg: &Message, endpoint: IpEndpoint, addrs: &Addresses) {
+        let zone = Zone {
+            hostname: &self.hostname,
+            addrs,
+            services: &self.services,
+        };
+
+        if endpoint.port != MDNS_PORT {
+            // Legacy unicast resolver, reply like a unicast DNS server.
+            let Some((name, qtype)) = msg.question else {
+                return;
+            };
+            send(&mut self.socket, endpoint, |w| {
+                w.header(msg.id, FLAG_RESPONSE | FLAG_AUTHORITATIVE);
+                w.name(&zone.labels(name));
+                w.u16(qtype);
+                w.u16(CLASS_IN);
+                let answers = zone.write_records(w, msg.want, Mode::Legacy);
+                let additional = zone.write_records(w, msg.want.additional(), Mode::Legacy);
+                w.counts(1, answers, 0, additional);
+            })
+            .await;
+            return;
+        }
+
+        let destination = if msg.unicast {
+            endpoint
+        } else {
+            match endpoint.addr {
+                #[cfg(feature = "proto-ipv4")]
+                IpAddress::Ipv4(_) => IpEndpoint::new(MDNS_GROUP_V4.into(), MDNS_PORT),
+                #[cfg(feature = "proto-ipv6")]
+                IpAddress::Ipv6(_) => IpEndpoint::new(MDNS_GROUP_V6.into(), MDNS_PORT),
+            }
+        };
+        send(&mut self.socket, destination, |w| {
+            w.header(0, FLAG_RESPONSE | FLAG_AUTHORITATIVE);
+            let answers = zone.write_records(w, msg.want, Mode::Response);
+            let additional = zone.write_records(w, msg.want.additional(), Mode::Response);
+            w.counts(0, answers, 0, additional);
+        })
+        .await;
+    }
+}
+
+/// Send a message built by `f`, which is called once to compute its length and once to write it.
+async fn send(socket: &mut UdpSocket<'_>, endpoint: IpEndpoint, f: impl Fn(&mut Writer<'_>)) {
+    let mut w = Writer::new(&mut []);
+    f(&mut w);
+
+    if let Err(e) = socket
+        .send_to_with(w.len, endpoint, |buf| f(&mut Writer::new(buf)))
+        .await
+    {
+        warn!("mdns: failed to send: {:?}", e);
+    }
+}
+
+/// Writes a DNS message. Data past the end of the buffer is dropped but still counted in `len`.
+struct Writer<'b> {
+    buf: &'b mut [u8],
+    len: usize,
+}
+
+impl<'b> Writer<'b> {
+    fn new(buf: &'b mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+
+    fn bytes(&mut self, data: &[u8]) {
+        if let Some(dst) = self.buf.get_mut(self.len..self.len + data.len()) {
+            dst.copy_from_slice(data);
+        }
+        self.len += data.len();
+    }
+
+    fn u16(&mut self, val: u16) {
+        self.bytes(&val.to_be_bytes());
+    }
+
+    fn u32(&mut self, val: u32) {
+        self.bytes(&val.to_be_bytes());
+    }
+
+    fn set_u16(&mut self, pos: usize, val: u16) {
+        if let Some(dst) = self.buf.get_mut(pos..pos + 2) {
+            dst.copy_from_slice(&val.to_be_bytes());
+        }
+    }
+
+    /// Write the header, with the section counts set to zero until [`Writer::counts`] is called.
+    fn header(&mut self, id: u16, flags: u16) {
+        for val in [id, flags, 0, 0, 0, 0] {
+            self.u16(val);
+        }
+    }
+
+    fn counts(&mut self, questions: u16, answers: u16, authority: u16, additional: u16) {
+        for (i, val) in [questions, answers, authority, additional].into_iter().enumerate() {
+            self.set_u16(4 + i * 2, val);
+        }
+    }
+
+    fn name(&mut self, labels: &[&str]) {
+        for label in labels {
+            self.bytes(&[label.len() as u8]);
+            self.bytes(label.as_bytes());
+        }
+        self.bytes(&[0]);
+    }
+
+    fn record(&mut self, name: &[&str], rtype: u16, class: u16, ttl: u32, rdata: impl FnOnce(&mut Self)) {
+        self.name(name);
+        self.u16(rtype);
+        self.u16(class);
+        self.u32(ttl);
+        let len_pos = self.len;
+        self.u16(0);
+        rdata(self);
+        let rdlen = self.len - len_pos - 2;
+        self.set_u16(len_pos, rdlen as u16);
+    }
+}
+
+fn read_u16(msg: &[u8], pos: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(msg.get(pos..pos + 2)?.try_into().ok()?))
+}
+
+/// Read the name at `pos`, following compression pointers.
+///
+/// Returns whether the name is equal to `labels`, ignoring ASCII case, and the offset after it.
+fn read_name(msg: &[u8], mut pos: usize, labels: &[&str]) -> Option<(bool, usize)> {
+    let mut end = None;
+    let mut matches = true;
+    let mut label = 0;
+    let mut jumps = 0;
+
+    loop {
+        let len = *msg.get(pos)? as usize;
+        match len & 0xC0 {
+            0x00 if len == 0 => return Some((matches && label == labels.len(), end.unwrap_or(pos + 1))),
+            0x00 => {
+                let data = msg.get(pos + 1..pos + 1 + len)?;
+                matches &= labels
+                    .get(label)
+                    .is_some_and(|l| l.as_bytes().eq_ignore_ascii_case(data));
+                label += 1;
+                pos += 1 + len;
+            }
+            0xC0 => {
+                end.get_or_insert(pos + 2);
+                jumps += 1;
+                if jumps > 16 {
+                    return None;
+                }
+                pos = ((len & 0x3F) << 8) | *msg.get(pos + 1)? as usize;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn parse(msg: &[u8], zone: &Zone<'_>) -> Option<Message> {
+    let id = read_u16(msg, 0)?;
+    let flags = read_u16(msg, 2)?;
+    let questions = read_u16(msg, 4)?;
+    let records = [read_u16(msg, 6)?, read_u16(msg, 8)?, read_u16(msg, 10)?];
+    if flags & OPCODE_MASK != 0 {
+        return None;
+    }
+
+    let response = flags & FLAG_RESPONSE != 0;
+    let mut res = Message {
+        id,
+        host_conflict: false,
+        service_conflicts: 0,
+        want: Want::default(),
+        unicast: false,
+        question: None,
+    };
+
+    let mut pos = 12;
+    for _ in 0..questions {
+        let (name, next) = zone.read_name(msg, pos)?;
+        let qtype = read_u16(msg, next)?;
+        let qclass = read_u16(msg, next + 2)?;
+        pos = next + 4;
+
+        let Some(name) = name.filter(|_| !response) else {
+            continue;
+        };
+        if !matches!(qclass & !CLASS_FLAG, CLASS_IN | CLASS_ANY) {
+            continue;
+        }
+        let want = &mut res.want;
+        match (name, qtype) {
+            (Name::Host, TYPE_A) => want.a = true,
+            (Name::Host, TYPE_AAAA) => want.aaaa = true,
+            (Name::Host, TYPE_ANY) => {
+                want.a = true;
+                want.aaaa = true;
+            }
+            (Name::Services, TYPE_PTR | TYPE_ANY) => want.services = true,
+            (Name::ServiceType(i), TYPE_PTR | TYPE_ANY) => want.ptr |= 1 << i,
+            (Name::Instance(i), TYPE_SRV) => want.srv |= 1 << i,
+            (Name::Instance(i), TYPE_TXT) => want.txt |= 1 << i,
+            (Name::Instance(i), TYPE_ANY) => {
+                want.srv |= 1 << i;
+                want.txt |= 1 << i;
+            }
+            _ => continue,
+        }
+        res.unicast |= qclass & CLASS_FLAG != 0;
+        res.question = Some((name, qtype));
+    }
+
+    if response {
+        for _ in 0..records.iter().map(|&n| n as usize).sum::<usize>() {
+            let (name, next) = zone.read_name(msg, pos)?;
+            let rtype = read_u16(msg, next)?;
+            let rdlen = read_u16(msg, next + 8)? as usize;
+            let rdata = msg.get(next + 10..next + 10 + rdlen)?;
+            pos = next + 10 + rdlen;
+
+            match name {
+                Some(Name::Host) if !zone.addrs.matches(rtype, rdata) => res.host_conflict = true,
+                Some(Name::Instance(i)) if rtype == TYPE_SRV && !zone.srv_matches(i, msg, next + 10) => {
+                    res.service_conflicts |= 1 << i
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Some(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICE: Service<'static> = Service {
+        instance: "Sensor",
+        service: "_http",
+        protocol: ServiceProtocol::Tcp,
+        port: 80,
+        txt: &[],
+    };
+
+    /// Authoritative response with one answer.
+    const RESPONSE_HEADER: [u8; 12] = [0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 0];
+
+    fn addresses() -> Addresses {
+        Addresses {
+            #[cfg(feature = "proto-ipv4")]
+            v4: Some(Ipv4Address::new(192, 168, 1, 2)),
+            #[cfg(feature = "proto-ipv6")]
+            v6: None,
+        }
+    }
+
+    fn services() -> [ServiceEntry<'static>; 1] {
+        [ServiceEntry {
+            service: SERVICE,
+            instance: unwrap!(String::try_from(SERVICE.instance)),
+            conflicts: 0,
+        }]
+    }
+
+    /// Parses `msg` with the zone of `myhost.local` and a `Sensor._http._tcp.local` service.
+    fn parse_msg(msg: &[u8]) -> Option<Message> {
+        let addrs = addresses();
+        let services = services();
+        let zone = Zone {
+            hostname: "myhost",
+            addrs: &addrs,
+            services: &services,
+        };
+        parse(msg, &zone)
+    }
+
+    fn message(header: [u8; 12], body: &[&[u8]]) -> Vec<u8, 128> {
+        let mut msg = Vec::new();
+        for part in [&header[..]].iter().chain(body) {
+            msg.extend_from_slice(part).unwrap();
+        }
+        msg
+    }
+
+    fn query(questions: u16, body: &[u8]) -> Vec<u8, 128> {
+        let [hi, lo] = questions.to_be_bytes();
+        message([0, 0, 0, 0, hi, lo, 0, 0, 0, 0, 0, 0], &[body])
+    }
+
+    #[test]
+    fn query_for_hostname() {
+        let msg = parse_msg(&query(1, b"\x06myhost\x05local\x00\x00\x01\x00\x01")).unwrap();
+        assert!(msg.want.a && !msg.want.aaaa);
+        assert!(!msg.unicast);
+        assert!(msg.question == Some((Name::Host, TYPE_A)));
+    }
+
+    #[test]
+    fn name_match_ignores_case() {
+        let msg = parse_msg(&query(1, b"\x06MyHost\x05LOCAL\x00\x00\x1c\x80\x01")).unwrap();
+        assert!(msg.want.aaaa);
+        assert!(msg.unicast);
+    }
+
+    #[test]
+    fn query_for_other_name() {
+        let msg = parse_msg(&query(1, b"\x05other\x05local\x00\x00\x01\x00\x01")).unwrap();
+        assert!(msg.want.is_empty());
+        assert!(msg.question.is_none());
+    }
+
+    #[test]
+    fn compressed_names() {
+        // The second question points to the first one, the third one ends with a pointer to the
+        // `local` label of the first one.
+        let msg = parse_msg(&query(
+            3,
+            b"\x06myhost\x05local\x00\x00\x01\x00\x01\
+              \xc0\x0c\x00\x1c\x00\x01\
+              \x05_http\x04_tcp\xc0\x13\x00\x0c\x00\x01",
+        ))
+        .unwrap();
+        assert!(msg.want.a && msg.want.aaaa);
+        assert_eq!(msg.want.ptr, 1);
+    }
+
+    #[test]
+    fn compression_pointer_loop() {
+        // The name points to itself.
+        assert!(parse_msg(&query(1, b"\xc0\x0c\x00\x01\x00\x01")).is_none());
+    }
+
+    #[test]
+    fn malformed_messages() {
+        // Truncated header.
+        assert!(parse_msg(&[0; 11]).is_none());
+        // Label longer than the message.
+        assert!(parse_msg(&query(1, b"\x06myho")).is_none());
+        // Name without terminating label.
+        assert!(parse_msg(&query(1, b"\x06myhost\x05local")).is_none());
+        // Reserved label type.
+        assert!(parse_msg(&query(1, b"\x86myhost\x05local\x00\x00\x01\x00\x01")).is_none());
+        // Pointer past the end of the message.
+        assert!(parse_msg(&query(1, b"\xc0\xff\x00\x01\x00\x01")).is_none());
+        // Missing type and class.
+        assert!(parse_msg(&query(1, b"\x06myhost\x05local\x00\x00")).is_none());
+        // More questions than present.
+        assert!(parse_msg(&query(2, b"\x06myhost\x05local\x00\x00\x01\x00\x01")).is_none());
+        // Not a standard query.
+        let mut msg = query(1, b"\x06myhost\x05local\x00\x00\x01\x00\x01");
+        msg[2] = 0x28;
+        assert!(parse_msg(&msg).is_none());
+    }
+
+    #[cfg(feature = "proto-ipv4")]
+    #[test]
+    fn response_with_other_address_is_conflict() {
+        let response = |addr: [u8; 4]| {
+            message(
+                RESPONSE_HEADER,
+                &[
+                    b"\x06myhost\x05local\x00\x00\x01\x80\x01\x00\x00\x00\x78\x00\x04",
+                    &addr,
+                ],
+            )
+        };
+
+        assert!(!parse_msg(&response([192, 168, 1, 2])).unwrap().host_conflict);
+        assert!(parse_msg(&response([192, 168, 1, 3])).unwrap().host_conflict);
+    }
+
+    #[test]
+    fn response_with_other_srv_target_is_conflict() {
+        let response = |target: &[u8]| {
+            message(
+                RESPONSE_HEADER,
+                &[
+                    b"\x06Sensor\x05_http\x04_tcp\x05local\x00\x00\x21\x80\x01\x00\x00\x00\x78",
+                    &(6 + target.len() as u16).to_be_bytes(),
+                    &[0, 0, 0, 0, 0, 80],
+                    target,
+                ],
+            )
+        };
+
+        // The target is compressed, pointing to the `local` label of the instance name.
+        assert!(parse_msg(&response(b"\x06myhost\xc0\x1e")).unwrap().service_conflicts == 0);
+        assert!(parse_msg(&response(b"\x05other\xc0\x1e")).unwrap().service_conflicts == 1);
+    }
+}
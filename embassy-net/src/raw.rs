@@ -1,16 +1,26 @@
 //! Raw sockets.
 
+#[cfg(feature = "raw-ethernet")]
+use core::cell::Cell;
 use core::future::{poll_fn, Future};
 use core::mem;
 use core::task::{Context, Poll};
 
 use embassy_net_driver::Driver;
+#[cfg(feature = "raw-ethernet")]
+use embassy_net_driver::{Timestamp, TxToken};
+#[cfg(feature = "raw-ethernet")]
+use embassy_sync::waitqueue::WakerRegistration;
 use smoltcp::iface::{Interface, SocketHandle};
 use smoltcp::socket::raw;
 pub use smoltcp::socket::raw::PacketMetadata;
+#[cfg(feature = "raw-ethernet")]
+use smoltcp::storage::PacketBuffer;
 pub use smoltcp::wire::{IpProtocol, IpVersion};
 
 use crate::Stack;
+#[cfg(feature = "raw-ethernet")]
+use crate::Statistics;
 
 /// Error returned by [`RawSocket::recv`] and [`RawSocket::send`].
 #[derive(PartialEq, Eq, Clone, Copy, Debug)]
@@ -20,6 +30,15 @@ pub enum RecvError {
     Truncated,
 }
 
+/// Error returned by [`RawEthernetSocket::send`].
+#[cfg(feature = "raw-ethernet")]
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum SendError {
+    /// The frame is larger than the driver's MTU or than the socket's send buffer.
+    PacketTooLarge,
+}
+
 /// An Raw socket.
 pub struct RawSocket<'a> {
     stack: Stack<'a>,
@@ -188,3 +207,457 @@ impl Drop for RawSocket<'_> {
 fn _assert_covariant<'a, 'b: 'a>(x: RawSocket<'b>) -> RawSocket<'a> {
     x
 }
+
+/// Maximum number of [`RawEthernetSocket`]s that can exist at the same time.
+#[cfg(feature = "raw-ethernet")]
+pub const MAX_RAW_ETHERNET_SOCKETS: usize = 4;
+
+/// Metadata of a frame received on a [`RawEthernetSocket`].
+#[cfg(feature = "raw-ethernet")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RawEthernetRecvMeta {
+    /// Time the frame was received at, if the driver timestamps packets.
+    pub timestamp: Option<Timestamp>,
+    /// Tag control information of the VLAN tag the driver stripped from the frame, if any.
+    pub vlan_tag: Option<u16>,
+}
+
+/// Options for a frame sent on a [`RawEthernetSocket`].
+#[cfg(feature = "raw-ethernet")]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct RawEthernetSendMeta {
+    /// Ask the driver to timestamp the frame, see [`RawEthernetSocket::tx_timestamp`].
+    pub request_timestamp: bool,
+    /// Tag control information of a VLAN tag for the driver to insert in the frame.
+    ///
+    /// Only supported if the driver sets
+    /// [`Capabilities::vlan_tag_insertion`](embassy_net_driver::Capabilities::vlan_tag_insertion).
+    pub vlan_tag: Option<u16>,
+}
+
+/// Metadata of a frame queued in a [`RawEthernetSocket`] buffer.
+#[cfg(feature = "raw-ethernet")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RawEthernetFrameMeta {
+    recv: RawEthernetRecvMeta,
+    send: RawEthernetSendMeta,
+}
+
+/// Metadata storage for the frames queued in a [`RawEthernetSocket`] buffer.
+#[cfg(feature = "raw-ethernet")]
+pub type RawEthernetPacketMetadata = smoltcp::storage::PacketMetadata<RawEthernetFrameMeta>;
+
+#[cfg(feature = "raw-ethernet")]
+pub(crate) struct RawEthernetState {
+    ethertype: Option<u16>,
+    // Largest frame the driver can send, Ethernet header included.
+    mtu: usize,
+    rx: PacketBuffer<'static, RawEthernetFrameMeta>,
+    tx: PacketBuffer<'static, RawEthernetFrameMeta>,
+    rx_waker: WakerRegistration,
+    tx_waker: WakerRegistration,
+    // Whether this socket sent the last frame the driver was asked to timestamp.
+    tx_timestamp_pending: bool,
+    tx_timestamp: Option<Timestamp>,
+    tx_timestamp_waker: WakerRegistration,
+}
+
+#[cfg(feature = "raw-ethernet")]
+impl RawEthernetState {
+    fn new(
+        ethertype: Option<u16>,
+        mtu: usize,
+        rx: PacketBuffer<'static, RawEthernetFrameMeta>,
+        tx: PacketBuffer<'static, RawEthernetFrameMeta>,
+    ) -> Self {
+        Self {
+            ethertype,
+            mtu,
+            rx,
+            tx,
+            rx_waker: WakerRegistration::new(),
+            tx_waker: WakerRegistration::new(),
+            tx_timestamp_pending: false,
+            tx_timestamp: None,
+            tx_timestamp_waker: WakerRegistration::new(),
+        }
+    }
+
+    fn poll_send(
+        &mut self,
+        frame: &[u8],
+        meta: RawEthernetSendMeta,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), SendError>> {
+        // A frame that can never be sent would otherwise wait for buffer space forever.
+        if frame.len() > self.mtu || frame.len() > self.tx.payload_capacity() {
+            return Poll::Ready(Err(SendError::PacketTooLarge));
+        }
+
+        let meta = RawEthernetFrameMeta {
+            recv: RawEthernetRecvMeta::default(),
+            send: meta,
+        };
+        match self.tx.enqueue(frame.len(), meta) {
+            Ok(buf) => {
+                buf.copy_from_slice(frame);
+                Poll::Ready(Ok(()))
+            }
+            Err(_) => {
+                self.tx_waker.register(cx.waker());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Copy a received frame to the raw Ethernet sockets listening for its EtherType.
+#[cfg(feature = "raw-ethernet")]
+pub(crate) fn raw_ethernet_rx(sockets: &mut [Option<RawEthernetState>], frame: &[u8], meta: RawEthernetRecvMeta) {
+    let Some(ethertype) = frame.get(12..14).map(|t| u16::from_be_bytes([t[0], t[1]])) else {
+        return;
+    };
+    let meta = RawEthernetFrameMeta {
+        recv: meta,
+        send: RawEthernetSendMeta::default(),
+    };
+    for s in sockets.iter_mut().flatten() {
+        if s.ethertype.unwrap_or(ethertype) == ethertype {
+            if let Ok(buf) = s.rx.enqueue(frame.len(), meta) {
+                buf.copy_from_slice(frame);
+                s.rx_waker.wake();
+            }
+        }
+    }
+}
+
+/// Transmit the frames queued on the raw Ethernet sockets, as long as the driver has room, and
+/// hand the driver's transmit timestamp to the socket that requested it.
+#[cfg(feature = "raw-ethernet")]
+pub(crate) fn raw_ethernet_tx<D: Driver>(
+    sockets: &mut [Option<RawEthernetState>],
+    driver: &mut D,
+    cx: &mut Context<'_>,
+    stats: &Cell<Statistics>,
+) {
+    if sockets.iter().flatten().any(|s| s.tx_timestamp_pending) {
+        if let Some(timestamp) = driver.tx_timestamp(cx) {
+            for s in sockets.iter_mut().flatten().filter(|s| s.tx_timestamp_pending) {
+                s.tx_timestamp_pending = false;
+                s.tx_timestamp = Some(timestamp);
+                s.tx_timestamp_waker.wake();
+            }
+        }
+    }
+
+    let mut requester = None;
+    'sockets: for (i, s) in sockets.iter_mut().enumerate() {
+        let Some(s) = s else {
+            continue;
+        };
+        while !s.tx.is_empty() {
+            let Some(mut token) = driver.transmit(cx) else {
+                break 'sockets;
+            };
+            let (meta, frame) = unwrap!(s.tx.dequeue().ok());
+            if let Some(tci) = meta.send.vlan_tag {
+                token.set_vlan_tag(tci);
+            }
+            if meta.send.request_timestamp {
+                token.request_timestamp();
+                requester = Some(i);
+            }
+            crate::driver_util::count_tx(stats, frame.len());
+            token.consume(frame.len(), |buf| buf.copy_from_slice(frame));
+            s.tx_waker.wake();
+        }
+    }
+
+    // The driver only keeps the timestamp of the last frame it was asked to timestamp.
+    if let Some(requester) = requester {
+        for (i, s) in sockets.iter_mut().enumerate() {
+            if let Some(s) = s {
+                s.tx_timestamp_pending = i == requester;
+            }
+        }
+    }
+}
+
+/// A raw Ethernet socket.
+///
+/// Sends and receives whole Ethernet frames, including the Ethernet header, so protocols the
+/// stack doesn't implement can be built on top of the driver, for example industrial protocols
+/// like GOOSE or Sampled Values.
+///
+/// Received frames are copied to the socket, they are still processed by the stack. Frames sent
+/// to multicast addresses are only received if the driver's multicast filter lets them through.
+#[cfg(feature = "raw-ethernet")]
+pub struct RawEthernetSocket<'a> {
+    stack: Stack<'a>,
+    index: usize,
+}
+
+#[cfg(feature = "raw-ethernet")]
+impl<'a> RawEthernetSocket<'a> {
+    /// Create a new raw Ethernet socket using the provided stack and buffers.
+    ///
+    /// The socket receives frames with the given EtherType, or all frames if `ethertype` is `None`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if [`MAX_RAW_ETHERNET_SOCKETS`] sockets already exist.
+    pub fn new(
+        stack: Stack<'a>,
+        ethertype: Option<u16>,
+        rx_meta: &'a mut [RawEthernetPacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [RawEthernetPacketMetadata],
+        tx_buffer: &'a mut [u8],
+    ) -> Self {
+        let index = stack.with_mut(|i| {
+            let rx_meta: &'static mut [RawEthernetPacketMetadata] = unsafe { mem::transmute(rx_meta) };
+            let rx_buffer: &'static mut [u8] = unsafe { mem::transmute(rx_buffer) };
+            let tx_meta: &'static mut [RawEthernetPacketMetadata] = unsafe { mem::transmute(tx_meta) };
+            let tx_buffer: &'static mut [u8] = unsafe { mem::transmute(tx_buffer) };
+            let index = unwrap!(i.raw_ethernet.iter().position(|s| s.is_none()));
+            i.raw_ethernet[index] = Some(RawEthernetState::new(
+                ethertype,
+                i.raw_ethernet_mtu,
+                PacketBuffer::new(rx_meta, rx_buffer),
+                PacketBuffer::new(tx_meta, tx_buffer),
+            ));
+            index
+        });
+
+        Self { stack, index }
+    }
+
+    fn with_mut<R>(&self, f: impl FnOnce(&mut RawEthernetState) -> R) -> R {
+        self.stack.with_mut(|i| {
+            let res = f(unwrap!(i.raw_ethernet[self.index].as_mut()));
+            i.waker.wake();
+            res
+        })
+    }
+
+    /// Wait until the socket becomes readable.
+    ///
+    /// A socket is readable when a frame has been received, or when there are queued frames in
+    /// the buffer.
+    pub fn wait_recv_ready(&self) -> impl Future<Output = ()> + '_ {
+        poll_fn(move |cx| self.poll_recv_ready(cx))
+    }
+
+    /// Wait until a frame can be read.
+    ///
+    /// When no frame is readable, this method will return `Poll::Pending` and register the current
+    /// task to be notified when a frame is received.
+    pub fn poll_recv_ready(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.with_mut(|s| {
+            if !s.rx.is_empty() {
+                Poll::Ready(())
+            } else {
+                s.rx_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Receive a frame.
+    ///
+    /// This method will wait until a frame is received.
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize, RecvError> {
+        poll_fn(move |cx| self.poll_recv(buf, cx)).await
+    }
+
+    /// Receive a frame.
+    ///
+    /// When no frame is available, this method will return `Poll::Pending` and register the
+    /// current task to be notified when a frame is received.
+    pub fn poll_recv(&self, buf: &mut [u8], cx: &mut Context<'_>) -> Poll<Result<usize, RecvError>> {
+        self.poll_recv_with_meta(buf, cx).map(|r| r.map(|(len, _)| len))
+    }
+
+    /// Receive a frame, with its timestamp and VLAN tag.
+    ///
+    /// This method will wait until a frame is received.
+    pub async fn recv_with_meta(&self, buf: &mut [u8]) -> Result<(usize, RawEthernetRecvMeta), RecvError> {
+        poll_fn(move |cx| self.poll_recv_with_meta(buf, cx)).await
+    }
+
+    /// Receive a frame, with its timestamp and VLAN tag.
+    ///
+    /// When no frame is available, this method will return `Poll::Pending` and register the
+    /// current task to be notified when a frame is received.
+    pub fn poll_recv_with_meta(
+        &self,
+        buf: &mut [u8],
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(usize, RawEthernetRecvMeta), RecvError>> {
+        self.with_mut(|s| match s.rx.dequeue() {
+            Ok((_, frame)) if frame.len() > buf.len() => Poll::Ready(Err(RecvError::Truncated)),
+            Ok((meta, frame)) => {
+                buf[..frame.len()].copy_from_slice(frame);
+                Poll::Ready(Ok((frame.len(), meta.recv)))
+            }
+            Err(_) => {
+                s.rx_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Send a frame.
+    ///
+    /// The frame must include the Ethernet header. This method will wait until the frame has been
+    /// queued for sending.
+    ///
+    /// If the frame is larger than the driver's MTU or the socket's send buffer, this method will
+    /// return `Err(SendError::PacketTooLarge)`.
+    pub fn send<'s>(&'s self, frame: &'s [u8]) -> impl Future<Output = Result<(), SendError>> + 's {
+        poll_fn(|cx| self.poll_send(frame, cx))
+    }
+
+    /// Send a frame.
+    ///
+    /// When the socket's send buffer is full, this method will return `Poll::Pending` and register
+    /// the current task to be notified when the buffer has space available.
+    ///
+    /// If the frame is larger than the driver's MTU or the socket's send buffer, this method will
+    /// return `Poll::Ready(Err(SendError::PacketTooLarge))`.
+    pub fn poll_send(&self, frame: &[u8], cx: &mut Context<'_>) -> Poll<Result<(), SendError>> {
+        self.poll_send_with_meta(frame, RawEthernetSendMeta::default(), cx)
+    }
+
+    /// Send a frame, with a timestamp request or a VLAN tag for the driver.
+    ///
+    /// This method will wait until the frame has been queued for sending.
+    ///
+    /// If the frame is larger than the driver's MTU or the socket's send buffer, this method will
+    /// return `Err(SendError::PacketTooLarge)`.
+    pub fn send_with_meta<'s>(
+        &'s self,
+        frame: &'s [u8],
+        meta: RawEthernetSendMeta,
+    ) -> impl Future<Output = Result<(), SendError>> + 's {
+        poll_fn(move |cx| self.poll_send_with_meta(frame, meta, cx))
+    }
+
+    /// Send a frame, with a timestamp request or a VLAN tag for the driver.
+    ///
+    /// When the socket's send buffer is full, this method will return `Poll::Pending` and register
+    /// the current task to be notified when the buffer has space available.
+    ///
+    /// If the frame is larger than the driver's MTU or the socket's send buffer, this method will
+    /// return `Poll::Ready(Err(SendError::PacketTooLarge))`.
+    pub fn poll_send_with_meta(
+        &self,
+        frame: &[u8],
+        meta: RawEthernetSendMeta,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), SendError>> {
+        self.with_mut(|s| s.poll_send(frame, meta, cx))
+    }
+
+    /// Wait for the transmit timestamp of the last frame sent with a timestamp request.
+    ///
+    /// The driver only keeps the timestamp of the last frame it was asked to timestamp, so a
+    /// timestamped frame sent later from another socket takes it over. This never completes if
+    /// the driver doesn't timestamp packets, see
+    /// [`Capabilities::timestamping`](embassy_net_driver::Capabilities::timestamping).
+    pub fn tx_timestamp(&self) -> impl Future<Output = Timestamp> + '_ {
+        poll_fn(|cx| self.poll_tx_timestamp(cx))
+    }
+
+    /// Wait for the transmit timestamp of the last frame sent with a timestamp request.
+    ///
+    /// When the timestamp isn't available yet, this method will return `Poll::Pending` and
+    /// register the current task to be notified when it is.
+    pub fn poll_tx_timestamp(&self, cx: &mut Context<'_>) -> Poll<Timestamp> {
+        self.with_mut(|s| match s.tx_timestamp.take() {
+            Some(timestamp) => Poll::Ready(timestamp),
+            None => {
+                s.tx_timestamp_waker.register(cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+
+    /// Flush the socket.
+    ///
+    /// This method will wait until all queued frames have been handed to the driver.
+    pub fn flush(&mut self) -> impl Future<Output = ()> + '_ {
+        poll_fn(|cx| {
+            self.with_mut(|s| {
+                if s.tx.is_empty() {
+                    Poll::Ready(())
+                } else {
+                    s.tx_waker.register(cx.waker());
+                    Poll::Pending
+                }
+            })
+        })
+    }
+}
+
+#[cfg(feature = "raw-ethernet")]
+impl Drop for RawEthernetSocket<'_> {
+    fn drop(&mut self) {
+        self.stack.with_mut(|i| i.raw_ethernet[self.index] = None);
+    }
+}
+
+#[cfg(all(test, feature = "raw-ethernet"))]
+mod tests {
+    use core::task::Waker;
+
+    use super::*;
+
+    // The buffers must outlive the returned state, like the ones handed to `RawEthernetSocket::new`.
+    fn state(mtu: usize, tx_meta: &mut [RawEthernetPacketMetadata], tx_buffer: &mut [u8]) -> RawEthernetState {
+        let tx_meta: &'static mut [RawEthernetPacketMetadata] = unsafe { mem::transmute(tx_meta) };
+        let tx_buffer: &'static mut [u8] = unsafe { mem::transmute(tx_buffer) };
+        RawEthernetState::new(
+            None,
+            mtu,
+            PacketBuffer::new(&mut [][..], &mut [][..]),
+            PacketBuffer::new(tx_meta, tx_buffer),
+        )
+    }
+
+    #[test]
+    fn send_rejects_frames_larger_than_mtu() {
+        let mut tx_meta = [RawEthernetPacketMetadata::EMPTY; 2];
+        let mut tx_buffer = [0; 256];
+        let mut s = state(64, &mut tx_meta, &mut tx_buffer);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let meta = RawEthernetSendMeta::default();
+        assert_eq!(s.poll_send(&[0; 64], meta, &mut cx), Poll::Ready(Ok(())));
+        assert_eq!(
+            s.poll_send(&[0; 65], meta, &mut cx),
+            Poll::Ready(Err(SendError::PacketTooLarge))
+        );
+    }
+
+    #[test]
+    fn send_rejects_frames_larger_than_buffer() {
+        let mut tx_meta = [RawEthernetPacketMetadata::EMPTY; 2];
+        let mut tx_buffer = [0; 100];
+        let mut s = state(1514, &mut tx_meta, &mut tx_buffer);
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let meta = RawEthernetSendMeta::default();
+        assert_eq!(
+            s.poll_send(&[0; 101], meta, &mut cx),
+            Poll::Ready(Err(SendError::PacketTooLarge))
+        );
+        assert_eq!(s.poll_send(&[0; 60], meta, &mut cx), Poll::Ready(Ok(())));
+        // Fits the buffer, just not until the queued frame has been sent.
+        assert_eq!(s.poll_send(&[0; 60], meta, &mut cx), Poll::Pending);
+        unwrap!(s.tx.dequeue());
+        assert_eq!(s.poll_send(&[0; 60], meta, &mut cx), Poll::Ready(Ok(())));
+    }
+}
@@ -0,0 +1,758 @@
+//! IPv6 router discovery and stateless address autoconfiguration (SLAAC).
+//!
+//! Router advertisements are received on an ICMPv6 raw socket. The first autonomous /64 prefix
+//! is combined with the EUI-64 interface identifier into the stack's address, the advertising
+//! router becomes the default gateway, and DNS servers are taken from the RDNSS option or, if the
+//! router sets the "other configuration" flag, asked from a DHCPv6 server with a stateless
+//! Information-request.
+//!
+//! Before the address is used, duplicate address detection sends a neighbor solicitation for it
+//! and waits for a neighbor advertisement from another host that already uses it. Solicitations
+//! of other hosts detecting the same address are not seen, as they come from the unspecified
+//! address and are dropped by the interface.
+
+use core::mem::MaybeUninit;
+use core::task::Context;
+
+use embassy_time::{Duration, Instant};
+use heapless::Vec;
+use smoltcp::iface::{SocketHandle, SocketSet};
+use smoltcp::socket::{raw, udp};
+use smoltcp::wire::{IpEndpoint, IpProtocol, IpVersion, Ipv6Address, Ipv6Cidr};
+
+use crate::{SlaacConfig, StaticConfigV6};
+
+const RAW_RX_LEN: usize = 1024;
+const RAW_TX_LEN: usize = 64;
+const UDP_RX_LEN: usize = 512;
+const UDP_TX_LEN: usize = 64;
+
+const ALL_ROUTERS: Ipv6Address = Ipv6Address::new(0xff02, 0, 0, 0, 0, 0, 0, 2);
+const ALL_DHCP_SERVERS: Ipv6Address = Ipv6Address::new(0xff02, 0, 0, 0, 0, 0, 1, 2);
+
+const ICMPV6_ROUTER_SOLICIT: u8 = 133;
+const ICMPV6_ROUTER_ADVERT: u8 = 134;
+const ICMPV6_NEIGHBOR_SOLICIT: u8 = 135;
+const ICMPV6_NEIGHBOR_ADVERT: u8 = 136;
+const RA_FLAG_OTHER: u8 = 0x40;
+const NDISC_OPTION_PREFIX_INFO: u8 = 3;
+const NDISC_OPTION_RDNSS: u8 = 25;
+const PREFIX_FLAG_AUTONOMOUS: u8 = 0x40;
+
+/// How long to wait for a neighbor advertisement after the DAD neighbor solicitation, the
+/// default RetransTimer of RFC 4861.
+const DAD_TIMEOUT: Duration = Duration::from_secs(1);
+/// Unauthenticated router advertisements can't lower the valid lifetime of an address below
+/// this, from RFC 4862 section 5.5.3.
+const MIN_VALID_LIFETIME: Duration = Duration::from_secs(2 * 60 * 60);
+
+const DHCPV6_CLIENT_PORT: u16 = 546;
+const DHCPV6_SERVER_PORT: u16 = 547;
+const DHCPV6_REPLY: u8 = 7;
+const DHCPV6_INFORMATION_REQUEST: u8 = 11;
+const DHCPV6_OPTION_CLIENTID: u16 = 1;
+const DHCPV6_OPTION_ORO: u16 = 6;
+const DHCPV6_OPTION_ELAPSED_TIME: u16 = 8;
+const DHCPV6_OPTION_DNS_SERVERS: u16 = 23;
+const DHCPV6_MAX_TRIES: u8 = 3;
+/// Header, client identifier, option request and elapsed time options.
+const INFORMATION_REQUEST_LEN: usize = 4 + 14 + 6 + 6;
+
+/// Socket buffers for SLAAC, part of [`StackResources`](crate::StackResources).
+pub(crate) struct SlaacResources {
+    raw_rx_meta: MaybeUninit<[raw::PacketMetadata; 2]>,
+    raw_rx: MaybeUninit<[u8; RAW_RX_LEN]>,
+    raw_tx_meta: MaybeUninit<[raw::PacketMetadata; 1]>,
+    raw_tx: MaybeUninit<[u8; RAW_TX_LEN]>,
+    udp_rx_meta: MaybeUninit<[udp::PacketMetadata; 1]>,
+    udp_rx: MaybeUninit<[u8; UDP_RX_LEN]>,
+    udp_tx_meta: MaybeUninit<[udp::PacketMetadata; 1]>,
+    udp_tx: MaybeUninit<[u8; UDP_TX_LEN]>,
+}
+
+impl SlaacResources {
+    pub(crate) const fn new() -> Self {
+        Self {
+            raw_rx_meta: MaybeUninit::uninit(),
+            raw_rx: MaybeUninit::uninit(),
+            raw_tx_meta: MaybeUninit::uninit(),
+            raw_tx: MaybeUninit::uninit(),
+            udp_rx_meta: MaybeUninit::uninit(),
+            udp_rx: MaybeUninit::uninit(),
+            udp_tx_meta: MaybeUninit::uninit(),
+            udp_tx: MaybeUninit::uninit(),
+        }
+    }
+}
+
+/// Change of the IPv6 configuration.
+pub(crate) enum Event {
+    Configured(StaticConfigV6),
+    Deconfigured,
+}
+
+/// An address undergoing duplicate address detection.
+struct Tentative {
+    address: Ipv6Cidr,
+    valid_until: Instant,
+    /// When detection succeeds, or `None` until the neighbor solicitation is sent.
+    done_at: Option<Instant>,
+}
+
+struct Dhcpv6 {
+    transaction_id: u32,
+    started_at: Instant,
+    next_send: Instant,
+    tries: u8,
+}
+
+pub(crate) struct Slaac {
+    config: SlaacConfig,
+    raw: SocketHandle,
+    udp: SocketHandle,
+    address: Option<(Ipv6Cidr, Instant)>,
+    tentative: Option<Tentative>,
+    /// An address another host on the link already uses.
+    duplicate: Option<Ipv6Address>,
+    router: Option<(Ipv6Address, Instant)>,
+    dns_servers: Vec<Ipv6Address, 3>,
+    next_solicit: Option<Instant>,
+    dhcpv6: Option<Dhcpv6>,
+    dhcpv6_done: bool,
+    configured: Option<StaticConfigV6>,
+}
+
+impl Slaac {
+    /// Create the SLAAC sockets.
+    pub(crate) fn new(
+        sockets: &mut SocketSet<'static>,
+        resources: &'static mut SlaacResources,
+        config: SlaacConfig,
+    ) -> Self {
+        let raw = sockets.add(raw::Socket::new(
+            IpVersion::Ipv6,
+            IpProtocol::Icmpv6,
+            raw::PacketBuffer::new(
+                &mut resources.raw_rx_meta.write([raw::PacketMetadata::EMPTY; 2])[..],
+                &mut resources.raw_rx.write([0; RAW_RX_LEN])[..],
+            ),
+            raw::PacketBuffer::new(
+                &mut resources.raw_tx_meta.write([raw::PacketMetadata::EMPTY; 1])[..],
+                &mut resources.raw_tx.write([0; RAW_TX_LEN])[..],
+            ),
+        ));
+
+        let mut udp_socket = udp::Socket::new(
+            udp::PacketBuffer::new(
+                &mut resources.udp_rx_meta.write([udp::PacketMetadata::EMPTY; 1])[..],
+                &mut resources.udp_rx.write([0; UDP_RX_LEN])[..],
+            ),
+            udp::PacketBuffer::new(
+                &mut resources.udp_tx_meta.write([udp::PacketMetadata::EMPTY; 1])[..],
+                &mut resources.udp_tx.write([0; UDP_TX_LEN])[..],
+            ),
+        );
+        unwrap!(udp_socket.bind(DHCPV6_CLIENT_PORT));
+        let udp = sockets.add(udp_socket);
+
+        Self {
+            config,
+            raw,
+            udp,
+            address: None,
+            tentative: None,
+            duplicate: None,
+            router: None,
+            dns_servers: Vec::new(),
+            next_solicit: Some(Instant::now()),
+            dhcpv6: None,
+            dhcpv6_done: false,
+            configured: None,
+        }
+    }
+
+    pub(crate) fn remove(self, sockets: &mut SocketSet<'static>) {
+        sockets.remove(self.raw);
+        sockets.remove(self.udp);
+    }
+
+    pub(crate) fn set_config(&mut self, config: SlaacConfig) {
+        self.config = config;
+    }
+
+    /// Forget the learned configuration and start soliciting routers again.
+    pub(crate) fn reset(&mut self) {
+        self.address = None;
+        self.tentative = None;
+        self.duplicate = None;
+        self.router = None;
+        self.dns_servers.clear();
+        self.next_solicit = Some(Instant::now());
+        self.dhcpv6 = None;
+        self.dhcpv6_done = false;
+        self.configured = None;
+    }
+
+    /// When [`Slaac::poll`] must be called next, to send a solicitation or expire a lifetime.
+    pub(crate) fn poll_at(&self) -> Option<Instant> {
+        [
+            self.next_solicit,
+            self.tentative.as_ref().map(|t| t.done_at.unwrap_or(Instant::MIN)),
+            self.dhcpv6.as_ref().map(|d| d.next_send),
+            self.address.map(|(_, t)| t),
+            self.router.map(|(_, t)| t),
+        ]
+        .into_iter()
+        .flatten()
+        .min()
+    }
+
+    pub(crate) fn poll(
+        &mut self,
+        cx: &mut Context<'_>,
+        sockets: &mut SocketSet<'static>,
+        mac: [u8; 6],
+    ) -> Option<Event> {
+        let now = Instant::now();
+
+        let raw = sockets.get_mut::<raw::Socket>(self.raw);
+        while let Ok(packet) = raw.recv() {
+            self.process_icmpv6(packet, mac, now);
+        }
+
+        let udp = sockets.get_mut::<udp::Socket>(self.udp);
+        while let Ok((packet, _)) = udp.recv() {
+            self.process_dhcpv6_reply(packet);
+        }
+
+        // Expire lifetimes.
+        if self.address.is_some_and(|(_, t)| t <= now) {
+            info!("SLAAC: address lifetime expired");
+            self.reset();
+        }
+        if self.router.is_some_and(|(_, t)| t <= now) {
+            self.router = None;
+        }
+
+        // Duplicate address detection.
+        if let Some(tentative) = &mut self.tentative {
+            match tentative.done_at {
+                None => {
+                    let raw = sockets.get_mut::<raw::Socket>(self.raw);
+                    if let Ok(buf) = raw.send(64) {
+                        write_neighbor_solicit(buf, &tentative.address.address());
+                        tentative.done_at = Some(now + DAD_TIMEOUT);
+                        cx.waker().wake_by_ref();
+                    }
+                }
+                Some(t) if t <= now => {
+                    info!("SLAAC: address {}", tentative.address);
+                    self.address = Some((tentative.address, tentative.valid_until));
+                    self.tentative = None;
+                }
+                Some(_) => {}
+            }
+        }
+
+        // Solicit routers until one advertises.
+        if let Some(t) = self.next_solicit.filter(|&t| t <= now) {
+            let raw = sockets.get_mut::<raw::Socket>(self.raw);
+            if let Ok(buf) = raw.send(48) {
+                write_router_solicit(buf);
+                cx.waker().wake_by_ref();
+            }
+            self.next_solicit = Some(t.max(now) + self.config.solicitation_interval);
+        }
+
+        if let Some(dhcpv6) = self.dhcpv6.as_mut().filter(|d| d.next_send <= now) {
+            if dhcpv6.tries >= DHCPV6_MAX_TRIES {
+                warn!("SLAAC: no reply from DHCPv6 server");
+                self.dhcpv6 = None;
+                self.dhcpv6_done = true;
+            } else {
+                let udp = sockets.get_mut::<udp::Socket>(self.udp);
+                let endpoint = IpEndpoint::new(ALL_DHCP_SERVERS.into(), DHCPV6_SERVER_PORT);
+                if let Ok(buf) = udp.send(INFORMATION_REQUEST_LEN, endpoint) {
+                    let elapsed = (now - dhcpv6.started_at).as_millis() / 10;
+                    write_information_request(buf, dhcpv6.transaction_id, elapsed.min(0xffff) as u16, mac);
+                    cx.waker().wake_by_ref();
+                }
+                dhcpv6.tries += 1;
+                dhcpv6.next_send = now + self.config.solicitation_interval;
+            }
+        }
+
+        let config = self.address.map(|(address, _)| StaticConfigV6 {
+            address,
+            gateway: self.router.map(|(router, _)| router),
+            dns_servers: self.dns_servers.clone(),
+        });
+        if config == self.configured {
+            return None;
+        }
+        self.configured = config.clone();
+        Some(match config {
+            Some(config) => Event::Configured(config),
+            None => Event::Deconfigured,
+        })
+    }
+
+    fn process_icmpv6(&mut self, packet: &[u8], mac: [u8; 6], now: Instant) {
+        // IPv6 header, without extension headers. Neighbor discovery messages must have a hop
+        // limit of 255, so they can't have been forwarded.
+        if packet.len() < 40 || packet[0] >> 4 != 6 || packet[6] != 58 || packet[7] != 255 {
+            return;
+        }
+        let src = address_at(packet, 8);
+        let dst = address_at(packet, 24);
+        let len = u16::from_be_bytes([packet[4], packet[5]]) as usize;
+        let Some(icmp) = packet.get(40..40 + len) else {
+            return;
+        };
+        if icmp.len() < 4 || icmp[1] != 0 || checksum(&src, &dst, icmp) != 0 {
+            return;
+        }
+
+        match icmp[0] {
+            ICMPV6_ROUTER_ADVERT => self.process_router_advert(src, icmp, mac, now),
+            ICMPV6_NEIGHBOR_ADVERT => self.process_neighbor_advert(icmp),
+            _ => {}
+        }
+    }
+
+    fn process_neighbor_advert(&mut self, icmp: &[u8]) {
+        if icmp.len() < 24 {
+            return;
+        }
+        let target = address_at(icmp, 8);
+        if self.tentative.as_ref().is_some_and(|t| t.address.address() == target) {
+            error!("SLAAC: address {} is already in use", target);
+            self.tentative = None;
+            self.duplicate = Some(target);
+        }
+    }
+
+    /// Process a router advertisement, `icmp` being its checksummed ICMPv6 message.
+    fn process_router_advert(&mut self, src: Ipv6Address, icmp: &[u8], mac: [u8; 6], now: Instant) {
+        // Router advertisements must come from a link-local address.
+        let o = src.octets();
+        if o[0] != 0xfe || o[1] & 0xc0 != 0x80 || icmp.len() < 16 {
+            return;
+        }
+
+        self.next_solicit = None;
+
+        let flags = icmp[5];
+        let router_lifetime = u16::from_be_bytes([icmp[6], icmp[7]]);
+        if router_lifetime != 0 {
+            self.router = Some((src, now + Duration::from_secs(router_lifetime as u64)));
+        } else if self.router.is_some_and(|(router, _)| router == src) {
+            self.router = None;
+        }
+
+        let mut has_rdnss = false;
+        let mut options = &icmp[16..];
+        while options.len() >= 8 {
+            let len = options[1] as usize * 8;
+            let Some(option) = options.get(..len).filter(|_| len != 0) else {
+                return;
+            };
+            options = &options[len..];
+
+            match option[0] {
+                NDISC_OPTION_PREFIX_INFO if len == 32 => {
+                    let prefix_len = option[2];
+                    let valid_lifetime = u32::from_be_bytes([option[4], option[5], option[6], option[7]]);
+                    let preferred_lifetime = u32::from_be_bytes([option[8], option[9], option[10], option[11]]);
+                    let prefix = address_at(option, 16);
+                    if prefix_len != 64
+                        || option[3] & PREFIX_FLAG_AUTONOMOUS == 0
+                        || preferred_lifetime > valid_lifetime
+                    {
+                        continue;
+                    }
+
+                    let mut addr = prefix.octets();
+                    addr[8..].copy_from_slice(&interface_identifier(mac));
+                    let address = Ipv6Cidr::new(Ipv6Address::from(addr), 64);
+                    let valid_until = match (&mut self.address, &mut self.tentative) {
+                        (Some((a, valid_until)), _)
+                        | (
+                            _,
+                            Some(Tentative {
+                                address: a,
+                                valid_until,
+                                ..
+                            }),
+                        ) => {
+                            if *a != address {
+                                continue;
+                            }
+                            valid_until
+                        }
+                        (None, None) => {
+                            if valid_lifetime != 0 && self.duplicate != Some(address.address()) {
+                                self.tentative = Some(Tentative {
+                                    address,
+                                    valid_until: lifetime_end(now, valid_lifetime),
+                                    done_at: None,
+                                });
+                            }
+                            continue;
+                        }
+                    };
+                    *valid_until = update_valid_lifetime(now, *valid_until, valid_lifetime);
+                }
+                NDISC_OPTION_RDNSS if len >= 24 => {
+                    let lifetime = u32::from_be_bytes([option[4], option[5], option[6], option[7]]);
+                    self.dns_servers.clear();
+                    if lifetime != 0 {
+                        for server in option[8..].chunks_exact(16) {
+                            let _ = self.dns_servers.push(address_at(server, 0));
+                        }
+                    }
+                    has_rdnss = true;
+                }
+                _ => {}
+            }
+        }
+
+        if flags & RA_FLAG_OTHER != 0
+            && self.config.stateless_dhcpv6
+            && !has_rdnss
+            && (self.address.is_some() || self.tentative.is_some())
+            && self.dhcpv6.is_none()
+            && !self.dhcpv6_done
+        {
+            self.dhcpv6 = Some(Dhcpv6 {
+                transaction_id: now.as_ticks() as u32 & 0x00ff_ffff,
+                started_at: now,
+                next_send: now,
+                tries: 0,
+            });
+        }
+    }
+
+    fn process_dhcpv6_reply(&mut self, packet: &[u8]) {
+        let Some(dhcpv6) = &self.dhcpv6 else {
+            return;
+        };
+        if packet.len() < 4 || packet[0] != DHCPV6_REPLY {
+            return;
+        }
+        if u32::from_be_bytes([0, packet[1], packet[2], packet[3]]) != dhcpv6.transaction_id {
+            return;
+        }
+
+        let mut options = &packet[4..];
+        while options.len() >= 4 {
+            let code = u16::from_be_bytes([options[0], options[1]]);
+            let len = u16::from_be_bytes([options[2], options[3]]) as usize;
+            let Some(data) = options.get(4..4 + len) else {
+                break;
+            };
+            options = &options[4 + len..];
+
+            if code == DHCPV6_OPTION_DNS_SERVERS {
+                self.dns_servers.clear();
+                for server in data.chunks_exact(16) {
+                    let _ = self.dns_servers.push(address_at(server, 0));
+                }
+            }
+        }
+
+        self.dhcpv6 = None;
+        self.dhcpv6_done = true;
+    }
+}
+
+fn address_at(buf: &[u8], pos: usize) -> Ipv6Address {
+    let mut octets = [0; 16];
+    octets.copy_from_slice(&buf[pos..pos + 16]);
+    Ipv6Address::from(octets)
+}
+
+fn lifetime_end(now: Instant, lifetime: u32) -> Instant {
+    match lifetime {
+        u32::MAX => Instant::MAX,
+        secs => now + Duration::from_secs(secs as u64),
+    }
+}
+
+/// The end of the valid lifetime of an address after receiving a prefix option with
+/// `received` seconds of valid lifetime, following RFC 4862 section 5.5.3 (e).
+fn update_valid_lifetime(now: Instant, valid_until: Instant, received: u32) -> Instant {
+    let received = lifetime_end(now, received);
+    if received > now + MIN_VALID_LIFETIME || received > valid_until {
+        received
+    } else if valid_until <= now + MIN_VALID_LIFETIME {
+        valid_until
+    } else {
+        now + MIN_VALID_LIFETIME
+    }
+}
+
+/// Modified EUI-64 interface identifier, from RFC 4291 appendix A.
+fn interface_identifier(mac: [u8; 6]) -> [u8; 8] {
+    [mac[0] ^ 0x02, mac[1], mac[2], 0xff, 0xfe, mac[3], mac[4], mac[5]]
+}
+
+/// ICMPv6 checksum over the IPv6 pseudo-header and `data`.
+///
+/// Returns 0 when computed over a message with a valid checksum.
+fn checksum(src: &Ipv6Address, dst: &Ipv6Address, data: &[u8]) -> u16 {
+    let mut sum = 0u32;
+    let len = (data.len() as u32).to_be_bytes();
+    for part in [&src.octets()[..], &dst.octets()[..], &len[..], &[0, 0, 0, 58][..], data] {
+        for word in part.chunks(2) {
+            sum += u16::from_be_bytes([word[0], *word.get(1).unwrap_or(&0)]) as u32;
+        }
+    }
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+/// Write a router solicitation from the unspecified address, IPv6 header included.
+fn write_router_solicit(buf: &mut [u8]) {
+    buf.fill(0);
+    buf[0] = 0x60;
+    buf[5] = 8; // payload length
+    buf[6] = 58; // next header: ICMPv6
+    buf[7] = 255; // hop limit
+    buf[24..40].copy_from_slice(&ALL_ROUTERS.octets());
+    buf[40] = ICMPV6_ROUTER_SOLICIT;
+    let sum = checksum(&Ipv6Address::UNSPECIFIED, &ALL_ROUTERS, &buf[40..48]);
+    buf[42..44].copy_from_slice(&sum.to_be_bytes());
+}
+
+/// Write a DAD neighbor solicitation for `target` from the unspecified address, IPv6 header
+/// included.
+fn write_neighbor_solicit(buf: &mut [u8], target: &Ipv6Address) {
+    // Solicited-node multicast address of the target.
+    let mut dst = [0xff, 0x02, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0x01, 0xff, 0, 0, 0];
+    dst[13..].copy_from_slice(&target.octets()[13..]);
+    let dst = Ipv6Address::from(dst);
+
+    buf.fill(0);
+    buf[0] = 0x60;
+    buf[5] = 24; // payload length
+    buf[6] = 58; // next header: ICMPv6
+    buf[7] = 255; // hop limit
+    buf[24..40].copy_from_slice(&dst.octets());
+    buf[40] = ICMPV6_NEIGHBOR_SOLICIT;
+    buf[48..64].copy_from_slice(&target.octets());
+    let sum = checksum(&Ipv6Address::UNSPECIFIED, &dst, &buf[40..64]);
+    buf[42..44].copy_from_slice(&sum.to_be_bytes());
+}
+
+/// Write a DHCPv6 Information-request asking for DNS servers.
+fn write_information_request(buf: &mut [u8], transaction_id: u32, elapsed: u16, mac: [u8; 6]) {
+    buf[..4].copy_from_slice(&transaction_id.to_be_bytes());
+    buf[0] = DHCPV6_INFORMATION_REQUEST;
+
+    // Client identifier, DUID-LL with an Ethernet address.
+    buf[4..6].copy_from_slice(&DHCPV6_OPTION_CLIENTID.to_be_bytes());
+    buf[6..8].copy_from_slice(&10u16.to_be_bytes());
+    buf[8..12].copy_from_slice(&[0, 3, 0, 1]);
+    buf[12..18].copy_from_slice(&mac);
+
+    buf[18..20].copy_from_slice(&DHCPV6_OPTION_ORO.to_be_bytes());
+    buf[20..22].copy_from_slice(&2u16.to_be_bytes());
+    buf[22..24].copy_from_slice(&DHCPV6_OPTION_DNS_SERVERS.to_be_bytes());
+
+    buf[24..26].copy_from_slice(&DHCPV6_OPTION_ELAPSED_TIME.to_be_bytes());
+    buf[26..28].copy_from_slice(&2u16.to_be_bytes());
+    buf[28..30].copy_from_slice(&elapsed.to_be_bytes());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAC: [u8; 6] = [0x02, 0, 0, 0x12, 0x34, 0x56];
+    const ROUTER: Ipv6Address = Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+    const ALL_NODES: Ipv6Address = Ipv6Address::new(0xff02, 0, 0, 0, 0, 0, 0, 1);
+    const PREFIX: Ipv6Address = Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 0);
+    const ADDRESS: Ipv6Address = Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0xff, 0xfe12, 0x3456);
+    const HOUR: Duration = Duration::from_secs(60 * 60);
+
+    fn slaac() -> Slaac {
+        Slaac {
+            config: SlaacConfig::default(),
+            raw: SocketHandle::default(),
+            udp: SocketHandle::default(),
+            address: None,
+            tentative: None,
+            duplicate: None,
+            router: None,
+            dns_servers: Vec::new(),
+            next_solicit: Some(Instant::from_secs(0)),
+            dhcpv6: None,
+            dhcpv6_done: false,
+            configured: None,
+        }
+    }
+
+    /// An ICMPv6 packet with its IPv6 header, sent from `src` to all nodes.
+    fn icmpv6(src: Ipv6Address, message: &[u8]) -> Vec<u8, 128> {
+        let mut packet = Vec::new();
+        packet.extend_from_slice(&[0x60, 0, 0, 0]).unwrap();
+        packet.extend_from_slice(&(message.len() as u16).to_be_bytes()).unwrap();
+        packet.extend_from_slice(&[58, 255]).unwrap();
+        packet.extend_from_slice(&src.octets()).unwrap();
+        packet.extend_from_slice(&ALL_NODES.octets()).unwrap();
+        packet.extend_from_slice(message).unwrap();
+        let sum = checksum(&src, &ALL_NODES, &packet[40..]);
+        packet[42..44].copy_from_slice(&sum.to_be_bytes());
+        packet
+    }
+
+    /// A router advertisement from [`ROUTER`] with a prefix information option for [`PREFIX`].
+    fn router_advert(valid_lifetime: u32, preferred_lifetime: u32) -> Vec<u8, 128> {
+        let mut message: Vec<u8, 64> = Vec::new();
+        message
+            .extend_from_slice(&[ICMPV6_ROUTER_ADVERT, 0, 0, 0, 64, 0])
+            .unwrap();
+        message.extend_from_slice(&1800u16.to_be_bytes()).unwrap();
+        message.extend_from_slice(&[0; 8]).unwrap();
+        message
+            .extend_from_slice(&[NDISC_OPTION_PREFIX_INFO, 4, 64, 0x80 | PREFIX_FLAG_AUTONOMOUS])
+            .unwrap();
+        message.extend_from_slice(&valid_lifetime.to_be_bytes()).unwrap();
+        message.extend_from_slice(&preferred_lifetime.to_be_bytes()).unwrap();
+        message.extend_from_slice(&[0; 4]).unwrap();
+        message.extend_from_slice(&PREFIX.octets()).unwrap();
+        icmpv6(ROUTER, &message)
+    }
+
+    fn neighbor_advert(target: Ipv6Address) -> Vec<u8, 128> {
+        let mut message: Vec<u8, 64> = Vec::new();
+        message
+            .extend_from_slice(&[ICMPV6_NEIGHBOR_ADVERT, 0, 0, 0, 0x20, 0, 0, 0])
+            .unwrap();
+        message.extend_from_slice(&target.octets()).unwrap();
+        icmpv6(Ipv6Address::new(0xfe80, 0, 0, 0, 0, 0, 0, 2), &message)
+    }
+
+    #[test]
+    fn router_advert_starts_duplicate_address_detection() {
+        let mut slaac = slaac();
+        let now = Instant::from_secs(10);
+        slaac.process_icmpv6(&router_advert(7200, 3600), MAC, now);
+
+        let tentative = slaac.tentative.as_ref().unwrap();
+        assert_eq!(tentative.address, Ipv6Cidr::new(ADDRESS, 64));
+        assert_eq!(tentative.valid_until, now + 2 * HOUR);
+        assert!(tentative.done_at.is_none());
+        assert!(slaac.address.is_none());
+        assert_eq!(slaac.router, Some((ROUTER, now + Duration::from_secs(1800))));
+        assert!(slaac.next_solicit.is_none());
+    }
+
+    #[test]
+    fn neighbor_advert_for_tentative_address_is_duplicate() {
+        let mut slaac = slaac();
+        let now = Instant::from_secs(0);
+        slaac.process_icmpv6(&router_advert(7200, 3600), MAC, now);
+
+        // Advertisements for other addresses are no conflict.
+        slaac.process_icmpv6(
+            &neighbor_advert(Ipv6Address::new(0x2001, 0xdb8, 0, 1, 0, 0, 0, 1)),
+            MAC,
+            now,
+        );
+        assert!(slaac.tentative.is_some());
+
+        slaac.process_icmpv6(&neighbor_advert(ADDRESS), MAC, now);
+        assert!(slaac.tentative.is_none());
+        assert_eq!(slaac.duplicate, Some(ADDRESS));
+
+        // The address is not tried again.
+        slaac.process_icmpv6(&router_advert(7200, 3600), MAC, now);
+        assert!(slaac.tentative.is_none());
+        assert!(slaac.address.is_none());
+    }
+
+    #[test]
+    fn invalid_router_adverts_are_ignored() {
+        let now = Instant::from_secs(0);
+
+        // Preferred lifetime longer than the valid lifetime.
+        let mut slaac = slaac();
+        slaac.process_icmpv6(&router_advert(3600, 7200), MAC, now);
+        assert!(slaac.tentative.is_none());
+
+        // Zero valid lifetime for a new prefix.
+        slaac.process_icmpv6(&router_advert(0, 0), MAC, now);
+        assert!(slaac.tentative.is_none());
+
+        // Bad checksum.
+        let mut slaac = self::slaac();
+        let mut packet = router_advert(7200, 3600);
+        packet[42] ^= 0xff;
+        slaac.process_icmpv6(&packet, MAC, now);
+        assert!(slaac.router.is_none());
+        assert!(slaac.tentative.is_none());
+
+        // Hop limit below 255.
+        let mut packet = router_advert(7200, 3600);
+        packet[7] = 64;
+        slaac.process_icmpv6(&packet, MAC, now);
+        assert!(slaac.router.is_none());
+        assert!(slaac.tentative.is_none());
+    }
+
+    #[test]
+    fn router_advert_refreshes_valid_lifetime() {
+        let mut slaac = slaac();
+        let start = Instant::from_secs(0);
+        slaac.address = Some((Ipv6Cidr::new(ADDRESS, 64), start + 10 * HOUR));
+
+        // A longer lifetime is always accepted.
+        slaac.process_icmpv6(&router_advert(20 * 3600, 3600), MAC, start);
+        assert_eq!(slaac.address.unwrap().1, start + 20 * HOUR);
+
+        // A zero lifetime doesn't remove the address, but shortens it to two hours.
+        slaac.process_icmpv6(&router_advert(0, 0), MAC, start);
+        assert_eq!(slaac.address.unwrap().1, start + 2 * HOUR);
+    }
+
+    #[test]
+    fn two_hour_rule() {
+        let now = Instant::from_secs(1000);
+
+        // The received lifetime is longer than two hours, or than the remaining one.
+        assert_eq!(update_valid_lifetime(now, now + HOUR, 3 * 3600), now + 3 * HOUR);
+        assert_eq!(update_valid_lifetime(now, now + 5 * HOUR, 3 * 3600), now + 3 * HOUR);
+        assert_eq!(update_valid_lifetime(now, now + 10 * HOUR, u32::MAX), Instant::MAX);
+        assert_eq!(update_valid_lifetime(now, now + HOUR / 2, 3600), now + HOUR);
+
+        // The remaining lifetime is two hours or less, a shorter lifetime is ignored.
+        assert_eq!(update_valid_lifetime(now, now + HOUR, 60), now + HOUR);
+        assert_eq!(update_valid_lifetime(now, now + 2 * HOUR, 0), now + 2 * HOUR);
+
+        // Otherwise the lifetime is shortened to two hours.
+        assert_eq!(update_valid_lifetime(now, now + 5 * HOUR, 60), now + 2 * HOUR);
+        assert_eq!(update_valid_lifetime(now, Instant::MAX, 0), now + 2 * HOUR);
+    }
+
+    #[test]
+    fn neighbor_solicit() {
+        let mut buf = [0; 64];
+        write_neighbor_solicit(&mut buf, &ADDRESS);
+
+        let dst = address_at(&buf, 24);
+        assert_eq!(dst, Ipv6Address::new(0xff02, 0, 0, 0, 0, 1, 0xff12, 0x3456));
+        assert_eq!(address_at(&buf, 8), Ipv6Address::UNSPECIFIED);
+        assert_eq!(buf[40], ICMPV6_NEIGHBOR_SOLICIT);
+        assert_eq!(address_at(&buf, 48), ADDRESS);
+        assert_eq!(checksum(&Ipv6Address::UNSPECIFIED, &dst, &buf[40..]), 0);
+    }
+
+    #[test]
+    fn interface_identifier_from_mac() {
+        assert_eq!(interface_identifier(MAC), [0, 0, 0, 0xff, 0xfe, 0x12, 0x34, 0x56]);
+    }
+}
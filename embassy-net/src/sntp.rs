@@ -0,0 +1,297 @@
+//! SNTP client.
+//!
+//! Periodically queries an NTP server with the simple network time protocol (RFC 4330) and
+//! reports the offset between the server's wall clock and the local [`Instant`] clock, for
+//! example to set an RTC or to keep a wall-clock time for timestamps.
+//!
+//! Only a single unicast server is queried, there is no server selection or clock filtering.
+
+use embassy_time::{Duration, Instant, Timer, WithTimeout};
+use smoltcp::wire::{IpAddress, IpEndpoint};
+
+use crate::udp::{self, PacketMetadata, UdpSocket};
+use crate::Stack;
+
+/// NTP server port.
+pub const NTP_PORT: u16 = 123;
+
+const PACKET_LEN: usize = 48;
+/// Large enough for a reply with a key identifier and message digest appended.
+const MAX_REPLY_LEN: usize = 96;
+
+/// Seconds between the NTP epoch (1900-01-01) and the Unix epoch (1970-01-01).
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// SNTP client configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Address of the NTP server.
+    pub server: IpAddress,
+    /// Time between two successful queries.
+    pub poll_interval: Duration,
+    /// Time to wait for a reply, and between retries after a failed query.
+    pub timeout: Duration,
+}
+
+impl Config {
+    /// Create a configuration querying `server` every 15 minutes.
+    pub fn new(server: IpAddress) -> Self {
+        Self {
+            server,
+            poll_interval: Duration::from_secs(15 * 60),
+            timeout: Duration::from_secs(5),
+        }
+    }
+}
+
+/// Error returned by [`SntpClient::query`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// Sending the request failed.
+    Send(udp::SendError),
+    /// No reply was received in time.
+    Timeout,
+    /// The reply was malformed or did not match the request.
+    InvalidReply,
+    /// The server is not synchronized, or asked the client to stop querying it.
+    Unsynchronized,
+}
+
+/// Result of a successful time query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Sample {
+    /// Offset in microseconds to add to [`Instant::as_micros`] to get the Unix time in microseconds.
+    pub offset_us: i64,
+    /// Round-trip delay of the query, excluding the server's processing time.
+    pub round_trip: Duration,
+    /// Stratum of the server.
+    pub stratum: u8,
+}
+
+impl Sample {
+    /// Unix time in microseconds at the given instant.
+    pub fn unix_time_us(&self, at: Instant) -> u64 {
+        (at.as_micros() as i64 + self.offset_us) as u64
+    }
+
+    /// Current Unix time in microseconds.
+    pub fn now_us(&self) -> u64 {
+        self.unix_time_us(Instant::now())
+    }
+}
+
+/// An SNTP client.
+pub struct SntpClient<'a> {
+    socket: UdpSocket<'a>,
+    config: Config,
+}
+
+impl<'a> SntpClient<'a> {
+    /// Create a new SNTP client using the provided stack and socket buffers.
+    ///
+    /// The buffers are used for the client's UDP socket, each receive and transmit buffer should
+    /// fit at least one 48-byte NTP message.
+    pub fn new(
+        stack: Stack<'a>,
+        rx_meta: &'a mut [PacketMetadata],
+        rx_buffer: &'a mut [u8],
+        tx_meta: &'a mut [PacketMetadata],
+        tx_buffer: &'a mut [u8],
+        config: Config,
+    ) -> Self {
+        let mut socket = UdpSocket::new(stack, rx_meta, rx_buffer, tx_meta, tx_buffer);
+        unwrap!(socket.bind(0));
+
+        Self { socket, config }
+    }
+
+    /// Query the server once.
+    pub async fn query(&mut self) -> Result<Sample, Error> {
+        let server = IpEndpoint::new(self.config.server, NTP_PORT);
+
+        // The transmit timestamp is echoed back by the server, use the local send time so replies
+        // to earlier requests are recognized and ignored.
+        let t1 = Instant::now();
+        let mut request = [0; PACKET_LEN];
+        // LI = 0, VN = 4, Mode = 3 (client)
+        request[0] = 0x23;
+        request[40..48].copy_from_slice(&t1.as_micros().to_be_bytes());
+        self.socket.send_to(&request, server).await.map_err(Error::Send)?;
+
+        let mut reply = [0; MAX_REPLY_LEN];
+        let deadline = t1 + self.config.timeout;
+        loop {
+            let timeout = deadline.saturating_duration_since(Instant::now());
+            let (n, meta) = match self.socket.recv_from(&mut reply).with_timeout(timeout).await {
+                Ok(Ok(r)) => r,
+                // Datagrams that large can't be NTP replies.
+                Ok(Err(udp::RecvError::Truncated)) => continue,
+                Err(_) => return Err(Error::Timeout),
+            };
+            let t4 = Instant::now();
+            if meta.endpoint != server || n < PACKET_LEN || reply[24..32] != request[40..48] {
+                continue;
+            }
+            return parse_reply(&reply[..n], t1, t4);
+        }
+    }
+
+    /// Run the client.
+    ///
+    /// Queries the server every [`Config::poll_interval`] and calls `on_sample` with the result of
+    /// each successful query. Failed queries are retried after [`Config::timeout`].
+    pub async fn run(&mut self, mut on_sample: impl FnMut(Sample)) -> ! {
+        loop {
+            match self.query().await {
+                Ok(sample) => {
+                    debug!(
+                        "sntp: offset {} us, round trip {} us",
+                        sample.offset_us,
+                        sample.round_trip.as_micros()
+                    );
+                    on_sample(sample);
+                    Timer::after(self.config.poll_interval).await;
+                }
+                Err(e) => {
+                    warn!("sntp: query failed: {:?}", e);
+                    Timer::after(self.config.timeout).await;
+                }
+            }
+        }
+    }
+}
+
+fn parse_reply(reply: &[u8], t1: Instant, t4: Instant) -> Result<Sample, Error> {
+    if reply.len() < PACKET_LEN {
+        return Err(Error::InvalidReply);
+    }
+    let leap = reply[0] >> 6;
+    let mode = reply[0] & 0x07;
+    let stratum = reply[1];
+    if mode != 4 {
+        return Err(Error::InvalidReply);
+    }
+    // Stratum 0 is a kiss-o'-death message.
+    if leap == 3 || stratum == 0 {
+        return Err(Error::Unsynchronized);
+    }
+
+    let t2 = ntp_to_unix_us(&reply[32..40]).ok_or(Error::InvalidReply)?;
+    let t3 = ntp_to_unix_us(&reply[40..48]).ok_or(Error::InvalidReply)?;
+    if t3 < t2 {
+        return Err(Error::InvalidReply);
+    }
+
+    let t1 = t1.as_micros() as i64;
+    let t4 = t4.as_micros() as i64;
+    let offset_us = ((t2 - t1) + (t3 - t4)) / 2;
+    let round_trip = ((t4 - t1) - (t3 - t2)).max(0) as u64;
+
+    Ok(Sample {
+        offset_us,
+        round_trip: Duration::from_micros(round_trip),
+        stratum,
+    })
+}
+
+/// Convert an NTP timestamp to Unix time in microseconds.
+///
+/// Returns `None` for timestamps before the Unix epoch.
+fn ntp_to_unix_us(ts: &[u8]) -> Option<i64> {
+    let mut secs = u32::from_be_bytes([ts[0], ts[1], ts[2], ts[3]]) as u64;
+    let frac = u32::from_be_bytes([ts[4], ts[5], ts[6], ts[7]]) as u64;
+    // Timestamps with the top bit clear are in NTP era 1, starting in 2036.
+    if secs & 0x8000_0000 == 0 {
+        secs += 1 << 32;
+    }
+    let micros = (frac * 1_000_000) >> 32;
+    let secs = secs.checked_sub(NTP_UNIX_OFFSET)?;
+    Some((secs * 1_000_000 + micros) as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2024-01-01T00:00:00Z
+    const UNIX_2024: u64 = 1_704_067_200;
+
+    fn timestamp(secs: u32, frac: u32) -> [u8; 8] {
+        let mut ts = [0; 8];
+        ts[..4].copy_from_slice(&secs.to_be_bytes());
+        ts[4..].copy_from_slice(&frac.to_be_bytes());
+        ts
+    }
+
+    fn reply(receive: [u8; 8], transmit: [u8; 8]) -> [u8; PACKET_LEN] {
+        let mut reply = [0; PACKET_LEN];
+        // LI = 0, VN = 4, Mode = 4 (server)
+        reply[0] = 0x24;
+        reply[1] = 2;
+        reply[32..40].copy_from_slice(&receive);
+        reply[40..48].copy_from_slice(&transmit);
+        reply
+    }
+
+    #[test]
+    fn era_0_timestamp() {
+        let ts = timestamp((UNIX_2024 + NTP_UNIX_OFFSET) as u32, 0x8000_0000);
+        assert_eq!(ntp_to_unix_us(&ts), Some((UNIX_2024 * 1_000_000 + 500_000) as i64));
+    }
+
+    #[test]
+    fn era_1_timestamp() {
+        // 2036-02-07T06:28:32Z, 16 seconds into era 1.
+        let ts = timestamp(16, 0);
+        assert_eq!(
+            ntp_to_unix_us(&ts),
+            Some(((1 << 32) + 16 - NTP_UNIX_OFFSET as i64) * 1_000_000)
+        );
+    }
+
+    #[test]
+    fn pre_epoch_timestamp() {
+        assert_eq!(ntp_to_unix_us(&timestamp(0x8000_0000, 0)), None);
+        assert_eq!(ntp_to_unix_us(&timestamp(NTP_UNIX_OFFSET as u32 - 1, 0)), None);
+        assert_eq!(ntp_to_unix_us(&timestamp(NTP_UNIX_OFFSET as u32, 0)), Some(0));
+    }
+
+    #[test]
+    fn offset_and_round_trip() {
+        let ts = timestamp((UNIX_2024 + NTP_UNIX_OFFSET) as u32, 0);
+        let t1 = Instant::from_secs(10);
+        let t4 = Instant::from_secs(10) + Duration::from_millis(20);
+        let sample = unwrap!(parse_reply(&reply(ts, ts), t1, t4));
+        assert_eq!(sample.offset_us, (UNIX_2024 * 1_000_000) as i64 - 10_010_000);
+        assert_eq!(sample.round_trip, Duration::from_millis(20));
+        assert_eq!(sample.stratum, 2);
+        assert_eq!(sample.unix_time_us(t4), UNIX_2024 * 1_000_000 + 10_000);
+    }
+
+    #[test]
+    fn pre_epoch_reply_is_invalid() {
+        let ts = timestamp(0x8000_0000, 0);
+        let t = Instant::from_secs(10);
+        assert_eq!(parse_reply(&reply(ts, ts), t, t), Err(Error::InvalidReply));
+    }
+
+    #[test]
+    fn truncated_reply_is_invalid() {
+        let ts = timestamp((UNIX_2024 + NTP_UNIX_OFFSET) as u32, 0);
+        let t = Instant::from_secs(10);
+        let reply = reply(ts, ts);
+        assert!(parse_reply(&reply, t, t).is_ok());
+        assert_eq!(parse_reply(&reply[..PACKET_LEN - 1], t, t), Err(Error::InvalidReply));
+        assert_eq!(parse_reply(&[], t, t), Err(Error::InvalidReply));
+    }
+
+    #[test]
+    fn unsynchronized_server() {
+        let ts = timestamp((UNIX_2024 + NTP_UNIX_OFFSET) as u32, 0);
+        let t = Instant::from_secs(10);
+        let mut reply = reply(ts, ts);
+        reply[1] = 0;
+        assert_eq!(parse_reply(&reply, t, t), Err(Error::Unsynchronized));
+    }
+}
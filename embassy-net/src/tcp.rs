@@ -18,7 +18,7 @@ use smoltcp::socket::tcp;
 pub use smoltcp::socket::tcp::State;
 use smoltcp::wire::{IpEndpoint, IpListenEndpoint};
 
-use crate::time::duration_to_smoltcp;
+use crate::time::{duration_from_smoltcp, duration_to_smoltcp};
 use crate::Stack;
 
 /// Error returned by TcpSocket read/write functions.
@@ -346,6 +346,9 @@ impl<'a> TcpSocket<'a> {
     /// If the timeout is set, the socket will be closed if no data is received for the
     /// specified duration.
     ///
+    /// This also caps how long unacknowledged data is retransmitted: if the remote doesn't ACK
+    /// within the timeout, the connection is reset instead of retrying forever.
+    ///
     /// # Note:
     /// Set a keep alive interval ([`set_keep_alive`] to prevent timeouts when
     /// the remote could still respond.
@@ -354,6 +357,13 @@ impl<'a> TcpSocket<'a> {
             .with_mut(|s, _| s.set_timeout(duration.map(duration_to_smoltcp)))
     }
 
+    /// Get the timeout for the socket.
+    ///
+    /// See [`set_timeout`](Self::set_timeout).
+    pub fn timeout(&self) -> Option<Duration> {
+        self.io.with(|s, _| s.timeout().map(duration_from_smoltcp))
+    }
+
     /// Set the keep-alive interval for the socket.
     ///
     /// If the keep-alive interval is set, the socket will send keep-alive packets after
@@ -368,6 +378,41 @@ impl<'a> TcpSocket<'a> {
             .with_mut(|s, _| s.set_keep_alive(interval.map(duration_to_smoltcp)))
     }
 
+    /// Get the keep-alive interval for the socket.
+    ///
+    /// See [`set_keep_alive`](Self::set_keep_alive).
+    pub fn keep_alive(&self) -> Option<Duration> {
+        self.io.with(|s, _| s.keep_alive().map(duration_from_smoltcp))
+    }
+
+    /// Enable or disable Nagle's algorithm.
+    ///
+    /// When enabled (the default), small writes are buffered while previously sent data is not yet
+    /// ACKed, so they can be coalesced into fewer segments. Disable it for latency-sensitive
+    /// protocols that send small messages and wait for a reply.
+    pub fn set_nagle_enabled(&mut self, enabled: bool) {
+        self.io.with_mut(|s, _| s.set_nagle_enabled(enabled))
+    }
+
+    /// Return whether Nagle's algorithm is enabled.
+    pub fn nagle_enabled(&self) -> bool {
+        self.io.with(|s, _| s.nagle_enabled())
+    }
+
+    /// Set the delay before sending an ACK for received data.
+    ///
+    /// Delaying ACKs lets them be sent together with response data. `None` sends ACKs
+    /// immediately. The default is 10 milliseconds.
+    pub fn set_ack_delay(&mut self, duration: Option<Duration>) {
+        self.io
+            .with_mut(|s, _| s.set_ack_delay(duration.map(duration_to_smoltcp)))
+    }
+
+    /// Get the delay before sending an ACK for received data.
+    pub fn ack_delay(&self) -> Option<Duration> {
+        self.io.with(|s, _| s.ack_delay().map(duration_from_smoltcp))
+    }
+
     /// Set the hop limit field in the IP header of sent packets.
     pub fn set_hop_limit(&mut self, hop_limit: Option<u8>) {
         self.io.with_mut(|s, _| s.set_hop_limit(hop_limit))
@@ -403,6 +448,29 @@ impl<'a> TcpSocket<'a> {
         self.io.with_mut(|s, _| s.close())
     }
 
+    /// Wait until the connection is closed.
+    ///
+    /// This waits for the socket to reach the `CLOSED` or `TIME-WAIT` state, either because both
+    /// sides closed the connection, the remote host reset it, or it timed out. Use it with
+    /// [`set_timeout`](Self::set_timeout) and [`set_keep_alive`](Self::set_keep_alive) to detect
+    /// dead peers on long-lived connections.
+    ///
+    /// The socket has a single waker slot for reads, which this uses, so it takes the socket
+    /// exclusively: it can't run concurrently with reads or writes, nor while the socket is split.
+    pub fn wait_for_close(&mut self) -> impl Future<Output = ()> + '_ {
+        poll_fn(|cx| {
+            self.io.with_mut(|s, _| {
+                if matches!(s.state(), State::Closed | State::TimeWait) {
+                    Poll::Ready(())
+                } else {
+                    // smoltcp wakes both the read and write wakers on every state change.
+                    s.register_recv_waker(cx.waker());
+                    Poll::Pending
+                }
+            })
+        })
+    }
+
     /// Forcibly close the socket.
     ///
     /// This instantly closes both the read and write halves of the socket. Any pending data
@@ -261,6 +261,10 @@ fn main() {
         Some("tim22") => "TIM22",
         Some("tim23") => "TIM23",
         Some("tim24") => "TIM24",
+        Some("lptim1") => "LPTIM1",
+        Some("lptim2") => "LPTIM2",
+        // The RTC stays available to the application, for the calendar and backup registers.
+        Some("rtc") => "",
         Some("any") => {
             // Order of TIM candidators:
             // 1. 2CH -> 2CH_CMP -> GP16 -> GP32 -> ADV
@@ -281,9 +285,17 @@ fn main() {
     if !time_driver_singleton.is_empty() {
         cfgs.enable(format!("time_driver_{}", time_driver_singleton.to_lowercase()));
     }
+    if time_driver_singleton.starts_with("LPTIM") {
+        cfgs.enable("time_driver_lptim");
+    }
+    cfgs.declare("time_driver_lptim");
+    if time_driver.as_deref() == Some("rtc") {
+        cfgs.enable("time_driver_rtc");
+    }
+    cfgs.declare("time_driver_rtc");
     for tim in [
         "tim1", "tim2", "tim3", "tim4", "tim5", "tim8", "tim9", "tim12", "tim15", "tim20", "tim21", "tim22", "tim23",
-        "tim24",
+        "tim24", "lptim1", "lptim2",
     ] {
         cfgs.declare(format!("time_driver_{}", tim));
     }
@@ -437,7 +449,7 @@ fn main() {
     // ========
     // Generate RccPeripheral impls
 
-    // count how many times each xxENR field is used, to enable refcounting if used more than once.
+    // count how many times each xxENR field is used, as shared fields can't be reset by one of their users.
     let mut rcc_field_count: HashMap<_, usize> = HashMap::new();
     for p in METADATA.peripherals {
         if let Some(rcc) = &p.rcc {
@@ -544,7 +556,9 @@ fn main() {
             }
         }
 
-        fn gen_clock(&mut self, peripheral: &str, name: &str) -> TokenStream {
+        /// Generate the frequency of clock `name`, panicking if it is not running, or as an
+        /// `Option` if `optional` is set.
+        fn gen_clock(&mut self, peripheral: &str, name: &str, optional: bool) -> TokenStream {
             let name = name.to_ascii_lowercase();
             let (name, frac) = Self::parse_mul_div(&name);
             let clock_name = format_ident!("{}", name);
@@ -559,6 +573,9 @@ fn main() {
                 let val = frac.denom;
                 muldiv.extend(quote!(/ #val));
             }
+            if optional {
+                return quote!(unsafe { crate::rcc::get_freqs().#clock_name.to_hertz().map(|f| f #muldiv) });
+            }
             quote!(unsafe {
                 unwrap!(
                     crate::rcc::get_freqs().#clock_name.to_hertz(),
@@ -571,7 +588,7 @@ fn main() {
             })
         }
 
-        fn gen_mux(&mut self, peripheral: &str, mux: &PeripheralRccRegister) -> TokenStream {
+        fn gen_mux(&mut self, peripheral: &str, mux: &PeripheralRccRegister, optional: bool) -> TokenStream {
             let ir = &self.rcc_registers.ir;
             let fieldset_name = mux.register.to_ascii_lowercase();
             let fieldset = ir
@@ -596,30 +613,39 @@ fn main() {
             for v in enumm.variants.iter().filter(|v| v.name != "DISABLE") {
                 let variant_name = format_ident!("{}", v.name);
                 let expr = if let Some(mux) = self.chained_muxes.get(&v.name) {
-                    self.gen_mux(peripheral, mux)
+                    self.gen_mux(peripheral, mux, optional)
                 } else {
-                    self.gen_clock(peripheral, v.name)
+                    self.gen_clock(peripheral, v.name, optional)
                 };
                 match_arms.extend(quote! {
                     crate::pac::rcc::vals::#enum_name::#variant_name => #expr,
                 });
             }
 
-            quote! {
-                match crate::pac::RCC.#fieldset_name().read().#field_name() {
-                    #match_arms
-                    #[allow(unreachable_patterns)]
-                    _ => panic!(
+            let invalid = if optional {
+                quote!(None)
+            } else {
+                quote! {
+                    panic!(
                         "attempted to use peripheral '{}' but its clock mux is not set to a valid \
                          clock. Change 'config.rcc.mux' to another clock.",
                         #peripheral
                     )
                 }
+            };
+
+            quote! {
+                match crate::pac::RCC.#fieldset_name().read().#field_name() {
+                    #match_arms
+                    #[allow(unreachable_patterns)]
+                    _ => #invalid,
+                }
             }
         }
     }
 
     let mut refcount_idxs = HashMap::new();
+    let mut peripheral_clocks = TokenStream::new();
 
     for p in METADATA.peripherals {
         if !singletons.contains(&p.name.to_string()) {
@@ -668,23 +694,26 @@ fn main() {
             };
             let enable_offset_and_bit = get_offset_and_bit(en_reg);
 
-            let needs_refcount = *rcc_field_count.get(&(en_reg.register, en_reg.field)).unwrap() > 1;
-            let refcount_idx = if needs_refcount {
-                let next_refcount_idx = refcount_idxs.len() as u8;
-                let refcount_idx = *refcount_idxs
-                    .entry((en_reg.register, en_reg.field))
-                    .or_insert(next_refcount_idx);
-                quote! { Some(#refcount_idx) }
-            } else {
-                quote! { None }
-            };
+            // Every xxENR field is refcounted, so the clock is only disabled once all its users are
+            // dropped. Peripherals sharing the same field share the same refcount.
+            let next_refcount_idx: u8 = refcount_idxs.len().try_into().unwrap();
+            assert!(next_refcount_idx != 0xff, "too many refcounted peripherals");
+            let refcount_idx = *refcount_idxs
+                .entry((en_reg.register, en_reg.field))
+                .or_insert(next_refcount_idx);
+            let refcount_idx = quote! { Some(#refcount_idx) };
+            let enable_shared = *rcc_field_count.get(&(en_reg.register, en_reg.field)).unwrap() > 1;
 
             let clock_frequency = match &rcc.kernel_clock {
-                PeripheralRccKernelClock::Mux(mux) => clock_gen.gen_mux(p.name, mux),
-                PeripheralRccKernelClock::Clock(clock) => clock_gen.gen_clock(p.name, clock),
+                PeripheralRccKernelClock::Mux(mux) => clock_gen.gen_mux(p.name, mux, false),
+                PeripheralRccKernelClock::Clock(clock) => clock_gen.gen_clock(p.name, clock, false),
+            };
+            let optional_clock_frequency = match &rcc.kernel_clock {
+                PeripheralRccKernelClock::Mux(mux) => clock_gen.gen_mux(p.name, mux, true),
+                PeripheralRccKernelClock::Clock(clock) => clock_gen.gen_clock(p.name, clock, true),
             };
 
-            let bus_clock_frequency = clock_gen.gen_clock(p.name, &rcc.bus_clock);
+            let bus_clock_frequency = clock_gen.gen_clock(p.name, &rcc.bus_clock, false);
 
             // A refcount leak can result if the same field is shared by peripherals with different stop modes
             // This condition should be checked in stm32-data
@@ -708,6 +737,7 @@ fn main() {
                             #reset_offset_and_bit,
                             #enable_offset_and_bit,
                             #refcount_idx,
+                            #enable_shared,
                             #[cfg(feature = "low-power")]
                             #stop_mode,
                         )
@@ -716,6 +746,15 @@ fn main() {
 
                 impl crate::rcc::RccPeripheral for peripherals::#pname {}
             });
+
+            let pname_str = p.name;
+            peripheral_clocks.extend(quote! {
+                crate::rcc::PeripheralClock {
+                    name: #pname_str,
+                    rcc_info: &<peripherals::#pname as crate::rcc::SealedRccPeripheral>::RCC_INFO,
+                    frequency: || #optional_clock_frequency,
+                },
+            });
         }
     }
 
@@ -724,6 +763,8 @@ fn main() {
         let refcount_zeros: TokenStream = refcount_idxs.iter().map(|_| quote! { 0u8, }).collect();
         quote! {
             pub(crate) static mut REFCOUNTS: [u8; #refcounts_len] = [#refcount_zeros];
+
+            pub(crate) static PERIPHERAL_CLOCKS: &[crate::rcc::PeripheralClock] = &[#peripheral_clocks];
         }
     });
 
@@ -811,6 +852,7 @@ fn main() {
     }
 
     let clock_idents: Vec<_> = clock_gen.clock_names.iter().map(|n| format_ident!("{}", n)).collect();
+    let clock_name_strs: Vec<_> = clock_gen.clock_names.iter().map(|n| n.as_str()).collect();
     g.extend(quote! {
         #[derive(Clone, Copy, Debug)]
         #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -820,6 +862,16 @@ fn main() {
                 pub #clock_idents: crate::time::MaybeHertz,
             )*
         }
+
+        impl Clocks {
+            /// Iterate over the name and frequency of every clock, `None` if it is not running.
+            pub fn iter(&self) -> impl Iterator<Item = (&'static str, Option<crate::time::Hertz>)> + '_ {
+                [#(
+                    (#clock_name_strs, self.#clock_idents.to_hertz()),
+                )*]
+                .into_iter()
+            }
+        }
     });
 
     let clocks_macro = quote!(
@@ -1117,9 +1169,16 @@ fn main() {
         (("hrtim", "CHE2"), quote!(crate::hrtim::ChannelEComplementaryPin)),
         (("hrtim", "CHF1"), quote!(crate::hrtim::ChannelFPin)),
         (("hrtim", "CHF2"), quote!(crate::hrtim::ChannelFComplementaryPin)),
+        (("hrtim", "FLT1"), quote!(crate::hrtim::Fault1Pin)),
+        (("hrtim", "FLT2"), quote!(crate::hrtim::Fault2Pin)),
+        (("hrtim", "FLT3"), quote!(crate::hrtim::Fault3Pin)),
+        (("hrtim", "FLT4"), quote!(crate::hrtim::Fault4Pin)),
+        (("hrtim", "FLT5"), quote!(crate::hrtim::Fault5Pin)),
+        (("hrtim", "FLT6"), quote!(crate::hrtim::Fault6Pin)),
         (("lptim", "CH1"), quote!(crate::lptim::Channel1Pin)),
         (("lptim", "CH2"), quote!(crate::lptim::Channel2Pin)),
         (("lptim", "OUT"), quote!(crate::lptim::OutputPin)),
+        (("lptim", "IN1"), quote!(crate::lptim::Input1Pin)),
         (("sdmmc", "CK"), quote!(crate::sdmmc::CkPin)),
         (("sdmmc", "CMD"), quote!(crate::sdmmc::CmdPin)),
         (("sdmmc", "D0"), quote!(crate::sdmmc::D0Pin)),
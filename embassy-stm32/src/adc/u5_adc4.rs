@@ -450,7 +450,7 @@ impl<'d, T: Instance> Adc4<'d, T> {
             reg.set_adstart(true);
         });
 
-        transfer.await;
+        unwrap!(transfer.await);
 
         // Ensure conversions are finished.
         Self::cancel_conversions();
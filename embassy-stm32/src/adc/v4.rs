@@ -439,7 +439,7 @@ impl<'d, T: Instance> Adc<'d, T> {
         });
 
         // Wait for conversion sequence to finish.
-        transfer.await;
+        unwrap!(transfer.await);
 
         // Ensure conversions are finished.
         Self::cancel_conversions();
@@ -152,6 +152,22 @@ impl Mask32 {
         }
     }
 
+    /// Returns the masks accepting all frames with a standard ID in `first..=last`.
+    ///
+    /// A range of IDs generally can't be expressed as a single mask, so it is split in aligned
+    /// power-of-two blocks, each needing one filter bank. Ranges aligned to a power of two need a
+    /// single bank. Extended frames will be rejected.
+    pub fn std_id_range(first: StandardId, last: StandardId) -> IdRangeMasks {
+        IdRangeMasks::new(first.as_raw().into(), last.as_raw().into(), 11, false)
+    }
+
+    /// Returns the masks accepting all frames with an extended ID in `first..=last`.
+    ///
+    /// See [`Mask32::std_id_range`]. Standard frames will be rejected.
+    pub fn ext_id_range(first: ExtendedId, last: ExtendedId) -> IdRangeMasks {
+        IdRangeMasks::new(first.as_raw(), last.as_raw(), 29, true)
+    }
+
     /// Make the filter accept data frames only.
     pub fn data_frames_only(&mut self) -> &mut Self {
         self.id &= !F32_RTR; // RTR = 0
@@ -167,6 +183,68 @@ impl Mask32 {
     }
 }
 
+/// Iterator over the 32-bit identifier masks that together accept exactly a range of IDs.
+///
+/// Created by [`Mask32::std_id_range`] and [`Mask32::ext_id_range`]. Each mask is meant to be
+/// loaded into its own filter bank, for example with [`MasterFilters::enable_banks`].
+#[derive(Debug, Clone)]
+pub struct IdRangeMasks {
+    next: u32,
+    last: u32,
+    bits: u32,
+    extended: bool,
+    done: bool,
+}
+
+impl IdRangeMasks {
+    fn new(first: u32, last: u32, bits: u32, extended: bool) -> Self {
+        Self {
+            next: first,
+            last,
+            bits,
+            extended,
+            done: first > last,
+        }
+    }
+}
+
+impl Iterator for IdRangeMasks {
+    type Item = Mask32;
+
+    fn next(&mut self) -> Option<Mask32> {
+        if self.done {
+            return None;
+        }
+
+        let (base, size_log2) = next_range_block(self.next, self.last, self.bits);
+        let id_mask = ((1u32 << self.bits) - 1) & !((1u32 << size_log2) - 1);
+
+        match base.checked_add(1 << size_log2) {
+            Some(next) if next <= self.last => self.next = next,
+            _ => self.done = true,
+        }
+
+        Some(if self.extended {
+            Mask32::frames_with_ext_id(ExtendedId::new(base).unwrap(), ExtendedId::new(id_mask).unwrap())
+        } else {
+            Mask32::frames_with_std_id(
+                StandardId::new(base as u16).unwrap(),
+                StandardId::new(id_mask as u16).unwrap(),
+            )
+        })
+    }
+}
+
+/// Returns the largest aligned power-of-two block of IDs starting at `first` that does not go
+/// past `last`, as `(base, log2(size))`.
+fn next_range_block(first: u32, last: u32, bits: u32) -> (u32, u32) {
+    let mut size_log2 = first.trailing_zeros().min(bits);
+    while size_log2 > 0 && (first as u64 + (1u64 << size_log2) - 1) > last as u64 {
+        size_log2 -= 1;
+    }
+    (first, size_log2)
+}
+
 /// The configuration of a filter bank.
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -289,6 +367,26 @@ impl MasterFilters<'_> {
         self.banks_imm().enable(index, fifo, config.into());
         self
     }
+
+    /// Configures consecutive filter banks starting at `first_index` and enables them.
+    ///
+    /// This is mostly useful with [`Mask32::std_id_range`] and [`Mask32::ext_id_range`]. Returns
+    /// the index of the first bank that was left untouched.
+    ///
+    /// If there are not enough filter banks, this will panic.
+    pub fn enable_banks<C: Into<BankConfig>>(
+        &mut self,
+        first_index: u8,
+        fifo: Fifo,
+        configs: impl IntoIterator<Item = C>,
+    ) -> u8 {
+        let mut index = first_index;
+        for config in configs {
+            self.banks_imm().enable(index, fifo, config.into());
+            index += 1;
+        }
+        index
+    }
 }
 
 impl MasterFilters<'_> {
@@ -372,6 +470,26 @@ impl SlaveFilters<'_> {
         self.banks_imm().enable(index, fifo, config.into());
         self
     }
+
+    /// Configures consecutive filter banks starting at `first_index` and enables them.
+    ///
+    /// This is mostly useful with [`Mask32::std_id_range`] and [`Mask32::ext_id_range`]. Returns
+    /// the index of the first bank that was left untouched.
+    ///
+    /// If there are not enough filter banks, this will panic.
+    pub fn enable_banks<C: Into<BankConfig>>(
+        &mut self,
+        first_index: u8,
+        fifo: Fifo,
+        configs: impl IntoIterator<Item = C>,
+    ) -> u8 {
+        let mut index = first_index;
+        for config in configs {
+            self.banks_imm().enable(index, fifo, config.into());
+            index += 1;
+        }
+        index
+    }
 }
 
 struct FilterBanks {
@@ -476,4 +594,26 @@ mod tests {
         assert_eq!(filter_bitmask(8, 1), 0x100);
         assert_eq!(filter_bitmask(8, 4), 0xf00);
     }
+
+    fn range_blocks(first: u32, last: u32, bits: u32) -> Vec<(u32, u32)> {
+        let mut blocks = Vec::new();
+        let mut next = first;
+        loop {
+            let (base, size_log2) = next_range_block(next, last, bits);
+            blocks.push((base, size_log2));
+            match base.checked_add(1 << size_log2) {
+                Some(n) if n <= last => next = n,
+                _ => return blocks,
+            }
+        }
+    }
+
+    #[test]
+    fn test_range_blocks() {
+        assert_eq!(range_blocks(0x100, 0x1FF, 11), [(0x100, 8)]);
+        assert_eq!(range_blocks(0x123, 0x123, 11), [(0x123, 0)]);
+        assert_eq!(range_blocks(0x101, 0x104, 11), [(0x101, 0), (0x102, 1), (0x104, 0)]);
+        assert_eq!(range_blocks(0, 0x7FF, 11), [(0, 11)]);
+        assert_eq!(range_blocks(0, 0x1FFF_FFFF, 29), [(0, 29)]);
+    }
 }
@@ -1,23 +1,27 @@
 pub mod filter;
 mod registers;
 
-use core::future::poll_fn;
+use core::cmp::Ordering;
+use core::future::{poll_fn, Future};
 use core::marker::PhantomData;
-use core::task::Poll;
+use core::pin::Pin;
+use core::sync::atomic::{self, AtomicU32};
+use core::task::{Context, Poll};
 
 use embassy_hal_internal::interrupt::InterruptExt;
 use embassy_hal_internal::PeripheralType;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
-use embassy_sync::channel::Channel;
+use embassy_sync::channel::{Channel, TrySendError};
+use embassy_sync::priority_channel::{Min, PriorityChannel};
 use embassy_sync::waitqueue::AtomicWaker;
 pub use embedded_can::{ExtendedId, Id, StandardId};
 
 use self::filter::MasterFilters;
 use self::registers::{Registers, RxFifo};
-pub use super::common::{BufferedCanReceiver, BufferedCanSender};
+pub use super::common::BufferedCanReceiver;
 use super::frame::{Envelope, Frame};
 use super::util;
-use crate::can::enums::{BusError, InternalOperation, TryReadError};
+use crate::can::enums::{BusError, BusErrorMode, InternalOperation, TryReadError};
 use crate::gpio::{AfType, OutputType, Pull, Speed};
 use crate::interrupt::typelevel::Interrupt;
 use crate::rcc::{self, RccPeripheral};
@@ -144,6 +148,18 @@ impl CanConfig<'_> {
         self.info.regs.set_automatic_retransmit(enabled);
         self
     }
+
+    /// Enables or disables automatic bus-off recovery.
+    ///
+    /// If this is enabled, the CAN peripheral leaves the bus-off state on its own once it has
+    /// seen 128 occurrences of 11 consecutive recessive bits. Otherwise, recovery has to be
+    /// started with [`Can::recover_from_bus_off`].
+    ///
+    /// Automatic bus-off recovery is enabled by default.
+    pub fn set_automatic_bus_off_recovery(self, enabled: bool) -> Self {
+        self.info.regs.set_automatic_bus_off_recovery(enabled);
+        self
+    }
 }
 
 impl Drop for CanConfig<'_> {
@@ -206,6 +222,7 @@ impl<'d> Can<'d> {
                 // Enable timestamps on rx messages
 
                 w.set_ttcm(true);
+                w.set_abom(true);
             });
         }
 
@@ -326,6 +343,52 @@ impl<'d> Can<'d> {
         self.info.regs.tx_fifo_scheduling_enabled()
     }
 
+    /// Get the CAN RX error counter
+    pub fn rx_error_count(&self) -> u8 {
+        self.info.regs.rx_error_count()
+    }
+
+    /// Get the CAN TX error counter
+    pub fn tx_error_count(&self) -> u8 {
+        self.info.regs.tx_error_count()
+    }
+
+    /// Get the current bus error mode
+    pub fn bus_error_mode(&self) -> BusErrorMode {
+        self.info.regs.bus_error_mode()
+    }
+
+    /// Waits until the peripheral enters the bus-off state.
+    ///
+    /// Returns immediately if it already is. A bus error that has not been reported by
+    /// [`read()`][Self::read] yet is acknowledged while waiting.
+    pub async fn wait_for_bus_off(&self) {
+        poll_fn(|cx| {
+            self.state.err_waker.register(cx.waker());
+            if self.info.regs.0.esr().read().boff() {
+                return Poll::Ready(());
+            }
+
+            // The SCE interrupt is disabled until a pending error has been reported, make sure
+            // entering bus-off wakes us up.
+            self.info.regs.0.msr().write(|w| w.set_erri(true));
+            self.info.regs.0.ier().modify(|w| w.set_errie(true));
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Starts the bus-off recovery sequence and re-enables the peripheral.
+    ///
+    /// This is only needed if automatic bus-off recovery is disabled, see
+    /// [`CanConfig::set_automatic_bus_off_recovery`]. The peripheral leaves the bus-off state once
+    /// it has seen 128 occurrences of 11 consecutive recessive bits.
+    pub async fn recover_from_bus_off(&mut self) {
+        self.info.regs.enter_init_mode();
+        self.info.regs.leave_init_mode();
+        self.enable().await;
+    }
+
     /// Queues the message to be sent.
     ///
     /// If the TX queue is full, this will wait until there is space, therefore exerting backpressure.
@@ -463,6 +526,27 @@ impl<'d> Can<'d> {
     }
 }
 
+impl embedded_can::nb::Can for Can<'_> {
+    type Frame = Frame;
+    type Error = BusError;
+
+    fn transmit(&mut self, frame: &Frame) -> nb::Result<Option<Frame>, BusError> {
+        match self.info.regs.transmit(frame) {
+            Ok(status) => Ok(status.dequeued_frame().copied()),
+            Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+            Err(nb::Error::Other(e)) => match e {},
+        }
+    }
+
+    fn receive(&mut self) -> nb::Result<Frame, BusError> {
+        match self.try_read() {
+            Ok(envelope) => Ok(envelope.frame),
+            Err(TryReadError::Empty) => Err(nb::Error::WouldBlock),
+            Err(TryReadError::BusError(e)) => Err(nb::Error::Other(e)),
+        }
+    }
+}
+
 /// Buffered CAN driver.
 pub struct BufferedCan<'d, const TX_BUF_SIZE: usize, const RX_BUF_SIZE: usize> {
     tx: BufferedCanTx<'d, TX_BUF_SIZE>,
@@ -502,6 +586,13 @@ impl<'d, const TX_BUF_SIZE: usize, const RX_BUF_SIZE: usize> BufferedCan<'d, TX_
         self.rx.reader()
     }
 
+    /// Returns the number of received frames lost since buffered mode was entered.
+    ///
+    /// See [`BufferedCanRx::rx_overruns`].
+    pub fn rx_overruns(&self) -> u32 {
+        self.rx.rx_overruns()
+    }
+
     /// Accesses the filter banks owned by this CAN peripheral.
     ///
     /// To modify filters of a slave peripheral, `modify_filters` has to be called on the master
@@ -638,8 +729,163 @@ impl<'d> CanTx<'d> {
     }
 }
 
-/// User supplied buffer for TX buffering
-pub type TxBuf<const BUF_SIZE: usize> = Channel<CriticalSectionRawMutex, Frame, BUF_SIZE>;
+/// User supplied buffer for TX buffering.
+///
+/// Frames are handed to the transmit mailboxes in order of their ID priority, so a high priority
+/// frame is not blocked behind lower priority frames queued earlier. Frames with the same ID are
+/// transmitted in the order they were queued.
+///
+/// When all three mailboxes are pending and the next queued frame has a higher priority than the
+/// lowest priority pending frame, that frame is aborted and queued again, ahead of the frames with
+/// the same priority that were queued after it. With FIFO scheduling enabled, frames are never
+/// aborted.
+pub type TxBuf<const BUF_SIZE: usize> = PriorityChannel<CriticalSectionRawMutex, PrioritizedFrame, Min, BUF_SIZE>;
+
+/// A [`TxBuf`] of any size, so the driver state doesn't depend on the buffer size.
+trait TxQueue: Sync {
+    fn try_send(&self, frame: PrioritizedFrame) -> Result<(), TrySendError<PrioritizedFrame>>;
+    fn poll_send(&self, frame: PrioritizedFrame, cx: &mut Context<'_>) -> Poll<()>;
+    fn try_receive(&self) -> Option<PrioritizedFrame>;
+    fn poll_ready_to_send(&self, cx: &mut Context<'_>) -> Poll<()>;
+}
+
+impl<const BUF_SIZE: usize> TxQueue for TxBuf<BUF_SIZE> {
+    fn try_send(&self, frame: PrioritizedFrame) -> Result<(), TrySendError<PrioritizedFrame>> {
+        PriorityChannel::try_send(self, frame)
+    }
+
+    fn poll_send(&self, frame: PrioritizedFrame, cx: &mut Context<'_>) -> Poll<()> {
+        // Frames are `Copy`, the caller still has the frame to send it again if the queue is full.
+        Pin::new(&mut self.send(frame)).poll(cx)
+    }
+
+    fn try_receive(&self) -> Option<PrioritizedFrame> {
+        PriorityChannel::try_receive(self).ok()
+    }
+
+    fn poll_ready_to_send(&self, cx: &mut Context<'_>) -> Poll<()> {
+        PriorityChannel::poll_ready_to_send(self, cx)
+    }
+}
+
+/// Sender that can be used for sending Classic CAN frames.
+pub struct BufferedCanSender {
+    tx_buf: &'static dyn TxQueue,
+    waker: fn(),
+    internal_operation: fn(InternalOperation),
+    state: &'static State,
+}
+
+impl BufferedCanSender {
+    /// Attempt to write a frame to the TX buffer without waiting.
+    pub fn try_write(&mut self, frame: impl Into<Frame>) -> Result<(), TrySendError<Frame>> {
+        self.tx_buf
+            .try_send(PrioritizedFrame::queued(frame.into(), self.state))
+            .map_err(|e| match e {
+                TrySendError::Full(queued) => TrySendError::Full(queued.frame),
+                TrySendError::Closed(queued) => TrySendError::Closed(queued.frame),
+            })?;
+        (self.waker)();
+        Ok(())
+    }
+
+    /// Async write frame to TX buffer.
+    pub async fn write(&mut self, frame: impl Into<Frame>) {
+        let frame = PrioritizedFrame::queued(frame.into(), self.state);
+        poll_fn(|cx| self.tx_buf.poll_send(frame, cx)).await;
+        (self.waker)();
+    }
+
+    /// Allows a poll_fn to poll until the channel is ready to write
+    pub fn poll_ready_to_send(&self, cx: &mut Context<'_>) -> Poll<()> {
+        self.tx_buf.poll_ready_to_send(cx)
+    }
+}
+
+impl Clone for BufferedCanSender {
+    fn clone(&self) -> Self {
+        (self.internal_operation)(InternalOperation::NotifySenderCreated);
+        Self {
+            tx_buf: self.tx_buf,
+            waker: self.waker,
+            internal_operation: self.internal_operation,
+            state: self.state,
+        }
+    }
+}
+
+impl Drop for BufferedCanSender {
+    fn drop(&mut self) {
+        (self.internal_operation)(InternalOperation::NotifySenderDestroyed);
+    }
+}
+
+/// A frame queued in a [`TxBuf`].
+///
+/// Frames are ordered by bus arbitration priority first, then by the order they were queued in.
+#[derive(Debug, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct PrioritizedFrame {
+    frame: Frame,
+    requeued: bool,
+    seq: u32,
+}
+
+impl PrioritizedFrame {
+    /// Get the queued frame.
+    pub fn frame(&self) -> &Frame {
+        &self.frame
+    }
+
+    /// A frame queued for transmission by the instance with the given `state`.
+    fn queued(frame: Frame, state: &State) -> Self {
+        Self {
+            frame,
+            requeued: false,
+            seq: state.next_tx_sequence(),
+        }
+    }
+
+    /// A frame that was aborted from a mailbox to make room for a higher priority frame. It was
+    /// queued before any frame with the same priority still in the queue, so it goes first.
+    fn requeued(frame: Frame) -> Self {
+        Self {
+            frame,
+            requeued: true,
+            seq: 0,
+        }
+    }
+}
+
+impl PartialEq for PrioritizedFrame {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PrioritizedFrame {}
+
+impl PartialOrd for PrioritizedFrame {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PrioritizedFrame {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Lower values win arbitration. The sequence number wraps around, so compare the
+        // difference instead of the raw values.
+        self.frame
+            .priority()
+            .cmp(&other.frame.priority())
+            .then_with(|| other.requeued.cmp(&self.requeued))
+            .then_with(|| (self.seq.wrapping_sub(other.seq) as i32).cmp(&0))
+    }
+}
+
+pub(crate) struct BufferedTxInner {
+    tx_buf: &'static dyn TxQueue,
+}
 
 /// Buffered CAN driver, transmit half.
 pub struct BufferedCanTx<'d, const TX_BUF_SIZE: usize> {
@@ -663,9 +909,7 @@ impl<'d, const TX_BUF_SIZE: usize> BufferedCanTx<'d, TX_BUF_SIZE> {
     fn setup(self) -> Self {
         // We don't want interrupts being processed while we change modes.
         critical_section::with(|_| {
-            let tx_inner = super::common::ClassicBufferedTxInner {
-                tx_receiver: self.tx_buf.receiver().into(),
-            };
+            let tx_inner = BufferedTxInner { tx_buf: self.tx_buf };
             let state = self.state as *const State;
             unsafe {
                 let mut_state = state as *mut State;
@@ -677,7 +921,7 @@ impl<'d, const TX_BUF_SIZE: usize> BufferedCanTx<'d, TX_BUF_SIZE> {
 
     /// Async write frame to TX buffer.
     pub async fn write(&mut self, frame: &Frame) {
-        self.tx_buf.send(*frame).await;
+        self.tx_buf.send(PrioritizedFrame::queued(*frame, self.state)).await;
         let waker = self.info.tx_waker;
         waker(); // Wake for Tx
     }
@@ -686,9 +930,10 @@ impl<'d, const TX_BUF_SIZE: usize> BufferedCanTx<'d, TX_BUF_SIZE> {
     pub fn writer(&self) -> BufferedCanSender {
         (self.info.internal_operation)(InternalOperation::NotifySenderCreated);
         BufferedCanSender {
-            tx_buf: self.tx_buf.sender().into(),
+            tx_buf: self.tx_buf,
             waker: self.info.tx_waker,
             internal_operation: self.info.internal_operation,
+            state: self.state,
         }
     }
 }
@@ -771,6 +1016,7 @@ impl<'d, const RX_BUF_SIZE: usize> BufferedCanRx<'d, RX_BUF_SIZE> {
     fn setup(self) -> Self {
         // We don't want interrupts being processed while we change modes.
         critical_section::with(|_| {
+            self.state.rx_overruns.store(0, atomic::Ordering::Relaxed);
             let rx_inner = super::common::ClassicBufferedRxInner {
                 rx_sender: self.rx_buf.sender().into(),
             };
@@ -785,7 +1031,7 @@ impl<'d, const RX_BUF_SIZE: usize> BufferedCanRx<'d, RX_BUF_SIZE> {
 
     /// Async read frame from RX buffer.
     pub async fn read(&mut self) -> Result<Envelope, BusError> {
-        self.rx_buf.receive().await
+        unwrap!(self.rx_buf.receive().await)
     }
 
     /// Attempts to read a CAN frame without blocking.
@@ -827,6 +1073,14 @@ impl<'d, const RX_BUF_SIZE: usize> BufferedCanRx<'d, RX_BUF_SIZE> {
         }
     }
 
+    /// Returns the number of received frames lost since buffered mode was entered.
+    ///
+    /// Frames are lost when the RX buffer is full, or when a hardware FIFO overruns because the
+    /// interrupt could not be serviced in time.
+    pub fn rx_overruns(&self) -> u32 {
+        self.state.rx_overruns.load(atomic::Ordering::Relaxed)
+    }
+
     /// Accesses the filter banks owned by this CAN peripheral.
     ///
     /// To modify filters of a slave peripheral, `modify_filters` has to be called on the master
@@ -916,11 +1170,23 @@ impl RxMode {
                 waker.wake();
             }
             Self::Buffered(buf) => {
+                let fifo_idx = match fifo {
+                    RxFifo::Fifo0 => 0usize,
+                    RxFifo::Fifo1 => 1usize,
+                };
+                let rfr = T::regs().rfr(fifo_idx);
+                if rfr.read().fovr() {
+                    rfr.write(|w| w.set_fovr(true));
+                    T::state().count_rx_overrun();
+                }
+
                 loop {
                     match Registers(T::regs()).receive_fifo(fifo) {
                         Some(envelope) => {
                             // NOTE: consensus was reached that if rx_queue is full, packets should be dropped
-                            let _ = buf.rx_sender.try_send(Ok(envelope));
+                            if buf.rx_sender.try_send(Ok(envelope)).is_err() {
+                                T::state().count_rx_overrun();
+                            }
                         }
                         None => return,
                     };
@@ -999,7 +1265,7 @@ impl RxMode {
 
 pub(crate) enum TxMode {
     NonBuffered(AtomicWaker),
-    Buffered(super::common::ClassicBufferedTxInner),
+    Buffered(BufferedTxInner),
 }
 
 impl TxMode {
@@ -1011,16 +1277,33 @@ impl TxMode {
         match &T::state().tx_mode {
             TxMode::NonBuffered(waker) => waker.wake(),
             TxMode::Buffered(buf) => {
-                while self.buffer_free::<T>() {
-                    match buf.tx_receiver.try_receive() {
-                        Ok(frame) => {
-                            _ = Registers(T::regs()).transmit(&frame);
+                // Senders can't queue frames while we move them, so there is always room to put a
+                // frame back into the queue.
+                critical_section::with(|_| loop {
+                    let regs = Registers(T::regs());
+                    let Some(queued) = buf.tx_buf.try_receive() else {
+                        break;
+                    };
+                    if !self.buffer_free::<T>()
+                        && (regs.tx_fifo_scheduling_enabled() || !regs.outranks_pending(queued.frame()))
+                    {
+                        // All the mailboxes are pending with frames that go first.
+                        _ = buf.tx_buf.try_send(queued);
+                        break;
+                    }
+                    match regs.transmit(queued.frame()) {
+                        Ok(status) => {
+                            if let Some(frame) = status.dequeued_frame() {
+                                _ = buf.tx_buf.try_send(PrioritizedFrame::requeued(*frame));
+                            }
                         }
                         Err(_) => {
+                            // A frame with the same priority is still pending in a mailbox.
+                            _ = buf.tx_buf.try_send(queued);
                             break;
                         }
                     }
-                }
+                })
             }
         }
     }
@@ -1041,6 +1324,11 @@ pub(crate) struct State {
     pub(crate) rx_mode: RxMode,
     pub(crate) tx_mode: TxMode,
     pub err_waker: AtomicWaker,
+    /// Number of received frames lost in buffered mode.
+    rx_overruns: AtomicU32,
+    /// Sequence number of the last frame queued for transmission, used to keep frames with the
+    /// same priority in FIFO order.
+    tx_sequence: AtomicU32,
     receiver_instance_count: usize,
     sender_instance_count: usize,
 }
@@ -1051,10 +1339,29 @@ impl State {
             rx_mode: RxMode::NonBuffered(AtomicWaker::new()),
             tx_mode: TxMode::NonBuffered(AtomicWaker::new()),
             err_waker: AtomicWaker::new(),
+            rx_overruns: AtomicU32::new(0),
+            tx_sequence: AtomicU32::new(0),
             receiver_instance_count: 1,
             sender_instance_count: 1,
         }
     }
+
+    fn count_rx_overrun(&self) {
+        // Both RX interrupts update the counter, and they can preempt each other.
+        critical_section::with(|_| {
+            let count = self.rx_overruns.load(atomic::Ordering::Relaxed);
+            self.rx_overruns.store(count.wrapping_add(1), atomic::Ordering::Relaxed);
+        });
+    }
+
+    fn next_tx_sequence(&self) -> u32 {
+        // Frames can be queued from any priority level.
+        critical_section::with(|_| {
+            let seq = self.tx_sequence.load(atomic::Ordering::Relaxed).wrapping_add(1);
+            self.tx_sequence.store(seq, atomic::Ordering::Relaxed);
+            seq
+        })
+    }
 }
 
 pub(crate) struct Info {
@@ -1249,3 +1556,32 @@ impl Index for Mailbox {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(id: u16, seq: u32) -> PrioritizedFrame {
+        PrioritizedFrame {
+            frame: unwrap!(Frame::new_standard(id, &[])),
+            requeued: false,
+            seq,
+        }
+    }
+
+    #[test]
+    fn tx_queue_order() {
+        // Lower IDs go first, whatever the order they were queued in.
+        assert!(queued(0x100, 2) < queued(0x200, 1));
+
+        // Frames with the same ID keep their queue order, also when the sequence number wraps.
+        assert!(queued(0x100, 1) < queued(0x100, 2));
+        assert!(queued(0x100, u32::MAX) < queued(0x100, 0));
+
+        // An aborted frame goes before the frames with the same ID queued after it, but not before
+        // higher priority frames.
+        let requeued = PrioritizedFrame::requeued(unwrap!(Frame::new_standard(0x100, &[])));
+        assert!(requeued < queued(0x100, 1));
+        assert!(queued(0x0FF, 1) < requeued);
+    }
+}
@@ -5,7 +5,7 @@ pub use embedded_can::{ExtendedId, Id, StandardId};
 use stm32_metapac::can::vals::{Lec, Rtr};
 
 use super::{Mailbox, TransmitStatus};
-use crate::can::enums::BusError;
+use crate::can::enums::{BusError, BusErrorMode};
 use crate::can::frame::{Envelope, Frame, Header};
 
 pub(crate) struct Registers(pub crate::pac::can::Can);
@@ -67,7 +67,15 @@ impl Registers {
     ///
     /// Automatic retransmission is enabled by default.
     pub fn set_automatic_retransmit(&self, enabled: bool) {
-        self.0.mcr().modify(|reg| reg.set_nart(enabled));
+        self.0.mcr().modify(|reg| reg.set_nart(!enabled));
+    }
+
+    /// Enables or disables automatic bus-off recovery.
+    ///
+    /// If this is disabled, recovery has to be started by software by entering and leaving
+    /// initialization mode.
+    pub fn set_automatic_bus_off_recovery(&self, enabled: bool) {
+        self.0.mcr().modify(|reg| reg.set_abom(enabled));
     }
 
     /// Enables or disables loopback mode: Internally connects the TX and RX
@@ -100,7 +108,6 @@ impl Registers {
         let msr = self.0.msr().read();
         if msr.slak() {
             self.0.mcr().modify(|reg| {
-                reg.set_abom(true);
                 reg.set_sleep(false);
             });
             Err(nb::Error::WouldBlock)
@@ -181,6 +188,26 @@ impl Registers {
         None
     }
 
+    /// Returns the receive error counter.
+    pub fn rx_error_count(&self) -> u8 {
+        self.0.esr().read().rec()
+    }
+
+    /// Returns the transmit error counter.
+    pub fn tx_error_count(&self) -> u8 {
+        self.0.esr().read().tec()
+    }
+
+    /// Returns the current error state of the node.
+    pub fn bus_error_mode(&self) -> BusErrorMode {
+        let esr = self.0.esr().read();
+        match (esr.boff(), esr.epvf()) {
+            (false, false) => BusErrorMode::ErrorActive,
+            (false, true) => BusErrorMode::ErrorPassive,
+            (true, _) => BusErrorMode::BusOff,
+        }
+    }
+
     /// Enables or disables FIFO scheduling of outgoing mailboxes.
     ///
     /// If this is enabled, mailboxes are scheduled based on the time when the transmit request bit of the mailbox was set.
@@ -275,6 +302,20 @@ impl Registers {
         })
     }
 
+    /// Returns `true` if `frame` has a higher priority than the lowest priority frame pending in
+    /// the transmit mailboxes, so that [`Self::transmit`] replaces it when all mailboxes are full.
+    pub fn outranks_pending(&self, frame: &Frame) -> bool {
+        // When all mailboxes are full, CODE is the index of the lowest priority mailbox.
+        let idx = self.0.tsr().read().code() as usize;
+        let tir = self.0.tx(idx).tir().read();
+
+        let mut id: IdReg = frame.id().into();
+        if frame.header().rtr() {
+            id = IdReg(id.0 | IdReg::RTR_MASK);
+        }
+        !tir.txrq() || id > IdReg::from_register(tir.0)
+    }
+
     /// Returns `Ok` when the mailbox is free or if it contains pending frame with a
     /// different priority from the identifier `id`.
     fn check_priority(&self, idx: usize, id: IdReg) -> nb::Result<(), Infallible> {
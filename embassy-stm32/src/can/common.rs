@@ -6,6 +6,7 @@ use super::frame::*;
 pub(crate) struct ClassicBufferedRxInner {
     pub rx_sender: SendDynamicSender<'static, Result<Envelope, BusError>>,
 }
+#[cfg(any(can_fdcan_v1, can_fdcan_h7))]
 pub(crate) struct ClassicBufferedTxInner {
     pub tx_receiver: SendDynamicReceiver<'static, Frame>,
 }
@@ -22,23 +23,25 @@ pub(crate) struct FdBufferedTxInner {
 }
 
 /// Sender that can be used for sending CAN frames.
+#[cfg(any(can_fdcan_v1, can_fdcan_h7))]
 pub struct BufferedSender<'ch, FRAME> {
     pub(crate) tx_buf: embassy_sync::channel::SendDynamicSender<'ch, FRAME>,
     pub(crate) waker: fn(),
     pub(crate) internal_operation: fn(InternalOperation),
 }
 
+#[cfg(any(can_fdcan_v1, can_fdcan_h7))]
 impl<'ch, FRAME> BufferedSender<'ch, FRAME> {
     /// Async write frame to TX buffer.
-    pub fn try_write(&mut self, frame: FRAME) -> Result<(), embassy_sync::channel::TrySendError<FRAME>> {
-        self.tx_buf.try_send(frame)?;
+    pub fn try_write(&mut self, frame: impl Into<FRAME>) -> Result<(), embassy_sync::channel::TrySendError<FRAME>> {
+        self.tx_buf.try_send(frame.into())?;
         (self.waker)();
         Ok(())
     }
 
     /// Async write frame to TX buffer.
-    pub async fn write(&mut self, frame: FRAME) {
-        self.tx_buf.send(frame).await;
+    pub async fn write(&mut self, frame: impl Into<FRAME>) {
+        unwrap!(self.tx_buf.send(frame.into()).await.ok());
         (self.waker)();
     }
 
@@ -48,6 +51,7 @@ impl<'ch, FRAME> BufferedSender<'ch, FRAME> {
     }
 }
 
+#[cfg(any(can_fdcan_v1, can_fdcan_h7))]
 impl<'ch, FRAME> Clone for BufferedSender<'ch, FRAME> {
     fn clone(&self) -> Self {
         (self.internal_operation)(InternalOperation::NotifySenderCreated);
@@ -59,6 +63,7 @@ impl<'ch, FRAME> Clone for BufferedSender<'ch, FRAME> {
     }
 }
 
+#[cfg(any(can_fdcan_v1, can_fdcan_h7))]
 impl<'ch, FRAME> Drop for BufferedSender<'ch, FRAME> {
     fn drop(&mut self) {
         (self.internal_operation)(InternalOperation::NotifySenderDestroyed);
@@ -66,6 +71,7 @@ impl<'ch, FRAME> Drop for BufferedSender<'ch, FRAME> {
 }
 
 /// Sender that can be used for sending Classic CAN frames.
+#[cfg(any(can_fdcan_v1, can_fdcan_h7))]
 pub type BufferedCanSender = BufferedSender<'static, Frame>;
 
 /// Receiver that can be used for receiving CAN frames. Note, each CAN frame will only be received by one receiver.
@@ -78,8 +84,8 @@ impl<'ch, ENVELOPE> BufferedReceiver<'ch, ENVELOPE> {
     /// Receive the next frame.
     ///
     /// See [`Channel::receive()`].
-    pub fn receive(&self) -> embassy_sync::channel::DynamicReceiveFuture<'_, Result<ENVELOPE, BusError>> {
-        self.rx_buf.receive()
+    pub async fn receive(&self) -> Result<ENVELOPE, BusError> {
+        unwrap!(self.rx_buf.receive().await)
     }
 
     /// Attempt to immediately receive the next frame.
@@ -100,7 +106,7 @@ impl<'ch, ENVELOPE> BufferedReceiver<'ch, ENVELOPE> {
     ///
     /// See [`Channel::poll_receive()`]
     pub fn poll_receive(&self, cx: &mut core::task::Context<'_>) -> core::task::Poll<Result<ENVELOPE, BusError>> {
-        self.rx_buf.poll_receive(cx)
+        self.rx_buf.poll_receive(cx).map(|r| unwrap!(r))
     }
 }
 
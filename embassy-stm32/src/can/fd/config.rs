@@ -78,12 +78,15 @@ pub struct DataBitTiming {
     pub sync_jump_width: NonZeroU8,
 }
 impl DataBitTiming {
-    // #[inline]
-    // fn tdc(&self) -> u8 {
-    //     let tsd = self.transceiver_delay_compensation as u8;
-    //     //TODO: stm32g4 does not export the TDC field
-    //     todo!()
-    // }
+    /// Transmitter delay compensation offset, in kernel clock cycles.
+    ///
+    /// The secondary sample point is placed at the data phase sample point, which is the
+    /// position recommended by the reference manual.
+    #[inline]
+    pub(crate) fn tdco(&self) -> u8 {
+        let sample_point = u16::from(self.prescaler) * (1 + u8::from(self.seg1) as u16);
+        sample_point.min(0x7F) as u8
+    }
     #[inline]
     pub(crate) fn dbrp(&self) -> u8 {
         (u16::from(self.prescaler) & 0x001F) as u8
@@ -328,6 +331,14 @@ pub struct FdCanConfig {
     ///
     /// Automatic retransmission is enabled by default.
     pub automatic_retransmit: bool,
+    /// Enables or disables automatic bus-off recovery
+    ///
+    /// If this is enabled, the bus-off recovery sequence is started from the interrupt handler
+    /// as soon as the peripheral enters the bus-off state. Otherwise, it has to be started with
+    /// [`Can::recover_from_bus_off`](crate::can::Can::recover_from_bus_off).
+    ///
+    /// Automatic bus-off recovery is enabled by default.
+    pub automatic_bus_off_recovery: bool,
     /// The transmit pause feature is intended for use in CAN systems where the CAN message
     /// identifiers are permanently specified to specific values and cannot easily be changed.
     ///
@@ -389,6 +400,15 @@ impl FdCanConfig {
         self
     }
 
+    /// Enables or disables automatic bus-off recovery
+    ///
+    /// Automatic bus-off recovery is enabled by default.
+    #[inline]
+    pub const fn set_automatic_bus_off_recovery(mut self, enabled: bool) -> Self {
+        self.automatic_bus_off_recovery = enabled;
+        self
+    }
+
     /// Enabled or disables the pausing between transmissions
     ///
     /// This feature looses up burst transmissions coming from a single node and it protects against
@@ -465,13 +485,14 @@ impl Default for FdCanConfig {
             nbtr: NominalBitTiming::default(),
             dbtr: DataBitTiming::default(),
             automatic_retransmit: true,
+            automatic_bus_off_recovery: true,
             transmit_pause: false,
             frame_transmit: FrameTransmissionConfig::ClassicCanOnly,
             non_iso_mode: false,
             edge_filtering: false,
             protocol_exception_handling: true,
             clock_divider: ClockDivider::_1,
-            timestamp_source: TimestampSource::None,
+            timestamp_source: TimestampSource::Prescaler(TimestampPrescaler::_1),
             global_filter: GlobalFilter::default(),
             tx_buffer_mode: TxBufferMode::Priority,
         }
@@ -8,7 +8,7 @@ use cfg_if::cfg_if;
 use crate::can::enums::*;
 use crate::can::fd::config::*;
 use crate::can::fd::message_ram::enums::*;
-use crate::can::fd::message_ram::{RegisterBlock, RxFifoElement, TxBufferElement};
+use crate::can::fd::message_ram::{RegisterBlock, RxFifoElement, TxBufferElement, TxEventElement};
 use crate::can::frame::*;
 
 /// Loopback Mode
@@ -88,10 +88,10 @@ impl Registers {
         ts_val
     }
 
-    pub fn put_tx_frame(&self, bufidx: usize, header: &Header, buffer: &[u8]) {
+    pub fn put_tx_frame(&self, bufidx: usize, header: &Header, buffer: &[u8], event: Event) {
         let mailbox = self.tx_buffer_element(bufidx);
         mailbox.reset();
-        put_tx_header(mailbox, header);
+        put_tx_header(mailbox, header, event);
         put_tx_data(mailbox, buffer);
 
         // Set <idx as Mailbox> as ready to transmit
@@ -229,6 +229,16 @@ impl Registers {
     }
 
     pub fn write<F: embedded_can::Frame + CanHeader>(&self, frame: &F) -> nb::Result<Option<F>, Infallible> {
+        self.write_with_event(frame, Event::NoEvent)
+    }
+
+    /// Same as [`Self::write`], with the `event` field of the TX buffer element set, so that the
+    /// FDCAN stores an element in the TX event FIFO once the frame has been transmitted.
+    pub fn write_with_event<F: embedded_can::Frame + CanHeader>(
+        &self,
+        frame: &F,
+        event: Event,
+    ) -> nb::Result<Option<F>, Infallible> {
         let (idx, pending_frame) = if self.tx_queue_is_full() {
             if self.tx_queue_mode() == TxBufferMode::Fifo {
                 // Does not make sense to cancel a pending frame when using FIFO
@@ -255,11 +265,46 @@ impl Registers {
             (idx, None)
         };
 
-        self.put_tx_frame(idx as usize, frame.header(), frame.data());
+        self.put_tx_frame(idx as usize, frame.header(), frame.data(), event);
 
         Ok(pending_frame)
     }
 
+    /// Pops the oldest element of the TX event FIFO.
+    ///
+    /// Returns the frame header, the message marker, and the raw timestamp counter value captured
+    /// at the start of frame.
+    pub fn read_tx_event(&self) -> Option<(Header, u8, u16)> {
+        let txefs = self.regs.txefs().read();
+        if txefs.effl() < 1 {
+            return None;
+        }
+
+        let get_idx = txefs.efgi();
+        let element: &TxEventElement = &self.msg_ram_mut().efsa[get_idx as usize];
+        let event = element.read();
+
+        let id = make_id(event.id().bits(), event.xtd().bits());
+        let len = event.to_data_length().len();
+        let header = if event.edl().is_fdcan_length() {
+            Header::new_fd(id, len, event.rtr().bits(), event.brs().bits())
+        } else {
+            Header::new(id, len, event.rtr().bits())
+        };
+        let marker = event.mm().bits();
+        let timestamp = event.txts().bits();
+
+        // Acknowledge, frees the element and increments the get index
+        self.regs.txefa().modify(|w| w.set_efai(get_idx));
+
+        Some((header, marker, timestamp))
+    }
+
+    /// Returns the current value of the timestamp counter (TSCV).
+    pub fn timestamp_counter(&self) -> u16 {
+        self.regs.tscv().read().tsc()
+    }
+
     #[inline]
     fn reset_msg_ram(&self) {
         self.msg_ram_mut().reset();
@@ -302,6 +347,7 @@ impl Registers {
     #[inline]
     fn set_normal_operations(&self, _enabled: bool) {
         self.set_loopback_mode(LoopbackMode::None);
+        self.set_restricted_operations(false);
     }
 
     #[inline]
@@ -370,13 +416,7 @@ impl Registers {
 
         self.configure_msg_ram();
 
-        // Enable timestamping
-        #[cfg(not(can_fdcan_h7))]
-        self.regs
-            .tscc()
-            .write(|w| w.set_tss(stm32_metapac::can::vals::Tss::INCREMENT));
-        #[cfg(can_fdcan_h7)]
-        self.regs.tscc().write(|w| w.set_tss(0x01));
+        self.set_timestamp_counter_source(config.timestamp_source);
 
         // this isn't really documented in the reference manual
         // but corresponding txbtie bit has to be set for the TC (TxComplete) interrupt to fire
@@ -385,6 +425,7 @@ impl Registers {
             w.set_rfne(0, true); // Rx Fifo 0 New Msg
             w.set_rfne(1, true); // Rx Fifo 1 New Msg
             w.set_tce(true); //  Tx Complete
+            w.set_tefne(true); // Tx Event Fifo New Entry
             w.set_boe(true); // Bus-Off Status Changed
         });
         self.regs.ile().modify(|w| {
@@ -416,6 +457,8 @@ impl Registers {
     /// Moves out of ConfigMode and into specified mode
     #[inline]
     pub fn into_mode(&self, config: FdCanConfig, mode: crate::can::_version::OperatingMode) {
+        // Start from normal operation so that no test or monitoring bit is left over.
+        self.set_normal_operations(true);
         match mode {
             crate::can::OperatingMode::InternalLoopbackMode => self.set_loopback_mode(LoopbackMode::Internal),
             crate::can::OperatingMode::ExternalLoopbackMode => self.set_loopback_mode(LoopbackMode::External),
@@ -450,6 +493,9 @@ impl Registers {
 
     /// Configures the data bit timings for the FdCan Variable Bitrates.
     /// This is not used when frame_transmit is set to anything other than AllowFdCanAndBRS.
+    ///
+    /// Transmitter delay compensation is required by most transceivers for data bit rates above
+    /// 1 Mbit/s.
     #[inline]
     pub fn set_data_bit_timing(&self, btr: DataBitTiming) {
         self.regs.dbtp().write(|w| {
@@ -457,7 +503,14 @@ impl Registers {
             w.set_dtseg1(btr.dtseg1() - 1);
             w.set_dtseg2(btr.dtseg2() - 1);
             w.set_dsjw(btr.dsjw() - 1);
+            w.set_tdc(btr.transceiver_delay_compensation);
         });
+        if btr.transceiver_delay_compensation {
+            self.regs.tdcr().write(|w| {
+                w.set_tdcf(0);
+                w.set_tdco(btr.tdco());
+            });
+        }
     }
 
     /// Enables or disables automatic retransmission of messages
@@ -523,19 +576,19 @@ impl Registers {
 
     /// Configures and resets the timestamp counter
     #[inline]
-    #[allow(unused)]
     pub fn set_timestamp_counter_source(&self, select: TimestampSource) {
+        // TCP holds the prescaler value minus one.
         #[cfg(can_fdcan_h7)]
         let (tcp, tss) = match select {
             TimestampSource::None => (0, 0),
-            TimestampSource::Prescaler(p) => (p as u8, 1),
+            TimestampSource::Prescaler(p) => (p as u8 - 1, 1),
             TimestampSource::FromTIM3 => (0, 2),
         };
 
         #[cfg(not(can_fdcan_h7))]
         let (tcp, tss) = match select {
             TimestampSource::None => (0, stm32_metapac::can::vals::Tss::ZERO),
-            TimestampSource::Prescaler(p) => (p as u8, stm32_metapac::can::vals::Tss::INCREMENT),
+            TimestampSource::Prescaler(p) => (p as u8 - 1, stm32_metapac::can::vals::Tss::INCREMENT),
             TimestampSource::FromTIM3 => (0, stm32_metapac::can::vals::Tss::EXTERNAL),
         };
 
@@ -667,7 +720,7 @@ fn make_id(id: u32, extended: bool) -> embedded_can::Id {
     }
 }
 
-fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header) {
+fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header, event: Event) {
     let (id, id_type) = match header.id() {
         // A standard identifier has to be written to ID[28:18].
         embedded_can::Id::Standard(id) => ((id.as_raw() as u32) << 18, IdType::StandardId),
@@ -689,7 +742,7 @@ fn put_tx_header(mailbox: &mut TxBufferElement, header: &Header) {
             .xtd()
             .set_id_type(id_type)
             .set_len(DataLength::new(header.len(), frame_format))
-            .set_event(Event::NoEvent)
+            .set_event(event)
             .fdf()
             .set_format(frame_format)
             .brs()
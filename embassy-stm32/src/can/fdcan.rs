@@ -9,6 +9,7 @@ use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::channel::Channel;
 use embassy_sync::waitqueue::AtomicWaker;
 
+use crate::can::fd::message_ram::enums::Event;
 use crate::can::fd::peripheral::Registers;
 use crate::gpio::{AfType, OutputType, Pull, SealedPin as _, Speed};
 use crate::interrupt::typelevel::Interrupt;
@@ -88,7 +89,12 @@ impl<T: Instance> interrupt::typelevel::Handler<T::IT0Interrupt> for IT0Interrup
 
         if ir.bo() {
             regs.ir().write(|w| w.set_bo(true));
-            if regs.psr().read().bo() {
+            let automatic_recovery = T::info().state.lock(|s| {
+                let state = s.borrow();
+                state.err_waker.wake();
+                state.automatic_bus_off_recovery
+            });
+            if automatic_recovery && regs.psr().read().bo() {
                 // Initiate bus-off recovery sequence by resetting CCCR.INIT
                 regs.cccr().modify(|w| w.set_init(false));
             }
@@ -147,9 +153,15 @@ pub enum OperatingMode {
 fn calc_ns_per_timer_tick(
     info: &'static Info,
     freq: crate::time::Hertz,
-    mode: crate::can::fd::config::FrameTransmissionConfig,
+    config: &crate::can::fd::config::FdCanConfig,
 ) -> u64 {
-    match mode {
+    // The timestamp counter only counts CAN bit times when clocked by the internal prescaler.
+    // When it is disabled or clocked by TIM3, the time the RX frame or TX event is read out of
+    // the message RAM is used instead.
+    if !matches!(config.timestamp_source, TimestampSource::Prescaler(_)) {
+        return 0;
+    }
+    match config.frame_transmit {
         // Use timestamp from Rx FIFO to adjust timestamp reported to user
         crate::can::fd::config::FrameTransmissionConfig::ClassicCanOnly => {
             let prescale: u64 = ({ info.regs.regs.nbtp().read().nbrp() } + 1) as u64
@@ -262,9 +274,11 @@ impl<'d> CanConfigurator<'d> {
 
     /// Start in mode.
     pub fn start(self, mode: OperatingMode) -> Can<'d> {
-        let ns_per_timer_tick = calc_ns_per_timer_tick(self.info, self.periph_clock, self.config.frame_transmit);
+        let ns_per_timer_tick = calc_ns_per_timer_tick(self.info, self.periph_clock, &self.config);
         self.info.state.lock(|s| {
-            s.borrow_mut().ns_per_timer_tick = ns_per_timer_tick;
+            let mut state = s.borrow_mut();
+            state.ns_per_timer_tick = ns_per_timer_tick;
+            state.automatic_bus_off_recovery = self.config.automatic_bus_off_recovery;
         });
         self.info.regs.into_mode(self.config, mode);
         (self.info.internal_operation)(InternalOperation::NotifySenderCreated);
@@ -292,6 +306,28 @@ impl<'d> CanConfigurator<'d> {
     pub fn into_external_loopback_mode(self) -> Can<'d> {
         self.start(OperatingMode::ExternalLoopbackMode)
     }
+
+    /// Start, entering mode. Does same as start(mode)
+    ///
+    /// This is a listen-only mode: frames are received but nothing, not even an acknowledge, is
+    /// sent on the bus.
+    pub fn into_bus_monitoring_mode(self) -> Can<'d> {
+        self.start(OperatingMode::BusMonitoringMode)
+    }
+
+    /// Start, entering mode. Does same as start(mode)
+    pub fn into_restricted_operation_mode(self) -> Can<'d> {
+        self.start(OperatingMode::RestrictedOperationMode)
+    }
+
+    /// Enables or disables non-ISO CAN FD mode, see
+    /// [`FdCanConfig::set_non_iso_mode`](crate::can::config::FdCanConfig::set_non_iso_mode).
+    ///
+    /// This is needed to talk to nodes implementing the original Bosch CAN FD specification,
+    /// which uses a different CRC than ISO 11898-1:2015.
+    pub fn set_non_iso_mode(&mut self, enabled: bool) {
+        self.config = self.config.set_non_iso_mode(enabled);
+    }
 }
 
 impl<'d> Drop for CanConfigurator<'d> {
@@ -316,6 +352,42 @@ impl<'d> Can<'d> {
         &self.properties
     }
 
+    /// Get the mode the peripheral was started in.
+    pub fn operating_mode(&self) -> OperatingMode {
+        self._mode
+    }
+
+    /// Waits until the peripheral enters the bus-off state.
+    ///
+    /// Returns immediately if it already is. The error counters and state can be read through
+    /// [`Properties`].
+    pub async fn wait_for_bus_off(&self) {
+        poll_fn(|cx| {
+            self.info.state.lock(|s| {
+                s.borrow().err_waker.register(cx.waker());
+            });
+
+            if self.info.regs.regs.psr().read().bo() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Starts the bus-off recovery sequence.
+    ///
+    /// This is only needed if automatic bus-off recovery is disabled, see
+    /// [`FdCanConfig::set_automatic_bus_off_recovery`](crate::can::config::FdCanConfig::set_automatic_bus_off_recovery).
+    /// The peripheral leaves the bus-off state once it has seen 128 occurrences of 11 consecutive
+    /// recessive bits. Does nothing if the peripheral is not in the bus-off state.
+    pub fn recover_from_bus_off(&mut self) {
+        if self.info.regs.regs.psr().read().bo() {
+            self.info.regs.regs.cccr().modify(|w| w.set_init(false));
+        }
+    }
+
     /// Flush one of the TX mailboxes.
     pub async fn flush(&self, idx: usize) {
         poll_fn(|cx| {
@@ -362,6 +434,36 @@ impl<'d> Can<'d> {
         RxMode::read_fd(self.info).await
     }
 
+    /// Queues the frame like [`Can::write`], and has the FDCAN store an element tagged with
+    /// `marker` in its TX event FIFO once the frame has been transmitted.
+    ///
+    /// The element can be retrieved with [`Can::read_tx_event`].
+    pub async fn write_with_marker(&mut self, frame: &Frame, marker: u8) -> Option<Frame> {
+        TxMode::write_generic::<_>(self.info, frame, Event::Event(marker)).await
+    }
+
+    /// Queues the FD frame like [`Can::write_fd`], and has the FDCAN store an element tagged with
+    /// `marker` in its TX event FIFO once the frame has been transmitted.
+    ///
+    /// The element can be retrieved with [`Can::read_tx_event`].
+    pub async fn write_fd_with_marker(&mut self, frame: &FdFrame, marker: u8) -> Option<FdFrame> {
+        TxMode::write_generic::<_>(self.info, frame, Event::Event(marker)).await
+    }
+
+    /// Returns the next element of the TX event FIFO, if any.
+    pub fn try_read_tx_event(&mut self) -> Option<TxEvent> {
+        TxMode::try_read_event(self.info)
+    }
+
+    /// Waits for the next element of the TX event FIFO.
+    ///
+    /// Elements are only stored for frames queued with [`Can::write_with_marker`] or
+    /// [`Can::write_fd_with_marker`]. When the FIFO in the message RAM is full, the FDCAN drops
+    /// new elements, so it should be read regularly.
+    pub async fn read_tx_event(&mut self) -> TxEvent {
+        TxMode::read_event(self.info).await
+    }
+
     /// Split instance into separate portions: Tx(write), Rx(read), common properties
     pub fn split(self) -> (CanTx<'d>, CanRx<'d>, Properties) {
         (self.info.internal_operation)(InternalOperation::NotifySenderCreated);
@@ -422,6 +524,32 @@ impl<'d> Drop for Can<'d> {
     }
 }
 
+/// Classic CAN frames only, use [`Can::write_fd`] and [`Can::read_fd`] for FD frames.
+impl embedded_can::nb::Can for Can<'_> {
+    type Frame = Frame;
+    type Error = BusError;
+
+    fn transmit(&mut self, frame: &Frame) -> nb::Result<Option<Frame>, BusError> {
+        match self.info.regs.write(frame) {
+            Ok(dequeued) => Ok(dequeued),
+            Err(nb::Error::WouldBlock) => Err(nb::Error::WouldBlock),
+            Err(nb::Error::Other(e)) => match e {},
+        }
+    }
+
+    fn receive(&mut self) -> nb::Result<Frame, BusError> {
+        if let Some((frame, _)) = self.info.regs.read(0) {
+            Ok(frame)
+        } else if let Some((frame, _)) = self.info.regs.read(1) {
+            Ok(frame)
+        } else if let Some(err) = self.info.regs.curr_error() {
+            Err(nb::Error::Other(err))
+        } else {
+            Err(nb::Error::WouldBlock)
+        }
+    }
+}
+
 /// User supplied buffer for RX Buffering
 pub type RxBuf<const BUF_SIZE: usize> = Channel<CriticalSectionRawMutex, Result<Envelope, BusError>, BUF_SIZE>;
 
@@ -480,14 +608,14 @@ impl<'c, 'd, const TX_BUF_SIZE: usize, const RX_BUF_SIZE: usize> BufferedCan<'d,
 
     /// Async write frame to TX buffer.
     pub async fn write(&mut self, frame: Frame) {
-        self.tx_buf.send(frame).await;
+        unwrap!(self.tx_buf.send(frame).await.ok());
         self.info.interrupt0.pend(); // Wake for Tx
                                      //T::IT0Interrupt::pend(); // Wake for Tx
     }
 
     /// Async read frame from RX buffer.
     pub async fn read(&mut self) -> Result<Envelope, BusError> {
-        self.rx_buf.receive().await
+        unwrap!(self.rx_buf.receive().await)
     }
 
     /// Returns a sender that can be used for sending CAN frames.
@@ -581,14 +709,14 @@ impl<'c, 'd, const TX_BUF_SIZE: usize, const RX_BUF_SIZE: usize> BufferedCanFd<'
 
     /// Async write frame to TX buffer.
     pub async fn write(&mut self, frame: FdFrame) {
-        self.tx_buf.send(frame).await;
+        unwrap!(self.tx_buf.send(frame).await.ok());
         self.info.interrupt0.pend(); // Wake for Tx
                                      //T::IT0Interrupt::pend(); // Wake for Tx
     }
 
     /// Async read frame from RX buffer.
     pub async fn read(&mut self) -> Result<FdEnvelope, BusError> {
-        self.rx_buf.receive().await
+        unwrap!(self.rx_buf.receive().await)
     }
 
     /// Returns a sender that can be used for sending CAN frames.
@@ -667,6 +795,28 @@ impl<'c, 'd> CanTx<'d> {
     pub async fn write_fd(&mut self, frame: &FdFrame) -> Option<FdFrame> {
         TxMode::write_fd(self.info, frame).await
     }
+
+    /// Queues the frame like [`CanTx::write`], and has the FDCAN store an element tagged with
+    /// `marker` in its TX event FIFO once the frame has been transmitted.
+    pub async fn write_with_marker(&mut self, frame: &Frame, marker: u8) -> Option<Frame> {
+        TxMode::write_generic::<_>(self.info, frame, Event::Event(marker)).await
+    }
+
+    /// Queues the FD frame like [`CanTx::write_fd`], and has the FDCAN store an element tagged
+    /// with `marker` in its TX event FIFO once the frame has been transmitted.
+    pub async fn write_fd_with_marker(&mut self, frame: &FdFrame, marker: u8) -> Option<FdFrame> {
+        TxMode::write_generic::<_>(self.info, frame, Event::Event(marker)).await
+    }
+
+    /// Returns the next element of the TX event FIFO, if any.
+    pub fn try_read_tx_event(&mut self) -> Option<TxEvent> {
+        TxMode::try_read_event(self.info)
+    }
+
+    /// Waits for the next element of the TX event FIFO.
+    pub async fn read_tx_event(&mut self) -> TxEvent {
+        TxMode::read_event(self.info).await
+    }
 }
 
 impl<'d> Drop for CanTx<'d> {
@@ -809,13 +959,17 @@ impl TxMode {
     /// frame is dropped from the mailbox, it is returned.  If no lower-priority frames
     /// can be replaced, this call asynchronously waits for a frame to be successfully
     /// transmitted, then tries again.
-    async fn write_generic<F: embedded_can::Frame + CanHeader>(info: &'static Info, frame: &F) -> Option<F> {
+    async fn write_generic<F: embedded_can::Frame + CanHeader>(
+        info: &'static Info,
+        frame: &F,
+        event: Event,
+    ) -> Option<F> {
         poll_fn(|cx| {
             info.state.lock(|s| {
                 s.borrow_mut().tx_mode.register(cx.waker());
             });
 
-            if let Ok(dropped) = info.regs.write(frame) {
+            if let Ok(dropped) = info.regs.write_with_event(frame, event) {
                 return Poll::Ready(dropped);
             }
 
@@ -831,7 +985,7 @@ impl TxMode {
     /// can be replaced, this call asynchronously waits for a frame to be successfully
     /// transmitted, then tries again.
     async fn write(info: &'static Info, frame: &Frame) -> Option<Frame> {
-        TxMode::write_generic::<_>(info, frame).await
+        TxMode::write_generic::<_>(info, frame, Event::NoEvent).await
     }
 
     /// Queues the message to be sent but exerts backpressure.  If a lower-priority
@@ -839,8 +993,48 @@ impl TxMode {
     /// can be replaced, this call asynchronously waits for a frame to be successfully
     /// transmitted, then tries again.
     async fn write_fd(info: &'static Info, frame: &FdFrame) -> Option<FdFrame> {
-        TxMode::write_generic::<_>(info, frame).await
+        TxMode::write_generic::<_>(info, frame, Event::NoEvent).await
+    }
+
+    fn try_read_event(info: &'static Info) -> Option<TxEvent> {
+        let ns_per_timer_tick = info.state.lock(|s| s.borrow().ns_per_timer_tick);
+        info.regs.read_tx_event().map(|(header, marker, ts)| TxEvent {
+            header,
+            marker,
+            ts: info.regs.calc_timestamp(ns_per_timer_tick, ts),
+        })
     }
+
+    async fn read_event(info: &'static Info) -> TxEvent {
+        poll_fn(|cx| {
+            info.state.lock(|s| {
+                s.borrow_mut().tx_mode.register(cx.waker());
+            });
+
+            match TxMode::try_read_event(info) {
+                Some(event) => Poll::Ready(event),
+                None => Poll::Pending,
+            }
+        })
+        .await
+    }
+}
+
+/// Element of the FDCAN TX event FIFO, stored once a frame queued with a marker has been
+/// transmitted.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct TxEvent {
+    /// Header of the transmitted frame.
+    pub header: Header,
+    /// Marker given when the frame was queued.
+    pub marker: u8,
+    /// Start of frame of the transmission, captured from the FDCAN timestamp counter.
+    ///
+    /// When the timestamp counter isn't clocked by the internal prescaler, see
+    /// [`FdCanConfig::set_timestamp_source`](crate::can::config::FdCanConfig::set_timestamp_source),
+    /// this is the time the element was read instead.
+    pub ts: Timestamp,
 }
 
 /// Common driver properties, including filters and error counters
@@ -886,6 +1080,15 @@ impl Properties {
         }
     }
 
+    /// Get the current value of the 16-bit FDCAN timestamp counter (TSCV).
+    ///
+    /// The RX frame and TX event timestamps are captured from this counter at the start of frame.
+    /// With [`TimestampSource::Prescaler`] it counts CAN bit times divided by the prescaler, with
+    /// [`TimestampSource::FromTIM3`] it follows TIM3, and it stays at 0 when disabled.
+    pub fn timestamp_counter(&self) -> u16 {
+        self.info.regs.timestamp_counter()
+    }
+
     /// Get the CAN RX error counter
     pub fn rx_error_count(&self) -> u8 {
         self.info.regs.regs.ecr().read().rec()
@@ -914,6 +1117,7 @@ struct State {
     pub rx_mode: RxMode,
     pub tx_mode: TxMode,
     pub ns_per_timer_tick: u64,
+    automatic_bus_off_recovery: bool,
     receiver_instance_count: usize,
     sender_instance_count: usize,
     tx_pin_port: Option<u8>,
@@ -928,6 +1132,7 @@ impl State {
             rx_mode: RxMode::NonBuffered(AtomicWaker::new()),
             tx_mode: TxMode::NonBuffered(AtomicWaker::new()),
             ns_per_timer_tick: 0,
+            automatic_bus_off_recovery: true,
             err_waker: AtomicWaker::new(),
             receiver_instance_count: 0,
             sender_instance_count: 0,
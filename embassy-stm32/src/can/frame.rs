@@ -347,6 +347,23 @@ impl FdData {
         }
     }
 
+    /// Returns the smallest length that can be encoded in an FDCAN DLC and holds `len` bytes.
+    ///
+    /// Returns `None` if `len` is more than 64 bytes.
+    pub const fn padded_len(len: usize) -> Option<usize> {
+        match len {
+            0..=8 => Some(len),
+            9..=12 => Some(12),
+            13..=16 => Some(16),
+            17..=20 => Some(20),
+            21..=24 => Some(24),
+            25..=32 => Some(32),
+            33..=48 => Some(48),
+            49..=64 => Some(64),
+            _ => None,
+        }
+    }
+
     /// Creates an empty data payload containing 0 bytes.
     #[inline]
     pub const fn empty() -> Self {
@@ -387,6 +404,32 @@ impl FdFrame {
         }
     }
 
+    /// Create new CAN FD frame, optionally using bit rate switching for the data phase.
+    ///
+    /// `raw_data` must have a length that can be encoded in an FDCAN DLC, see
+    /// [`FdData::is_valid_len`] and [`FdData::padded_len`].
+    pub fn new_fd(id: impl Into<embedded_can::Id>, raw_data: &[u8], brs: bool) -> Result<Self, FrameCreateError> {
+        Self::new(Header::new_fd(id.into(), raw_data.len() as u8, false, brs), raw_data)
+    }
+
+    /// Create new extended CAN FD frame, optionally using bit rate switching.
+    pub fn new_fd_extended(raw_id: u32, raw_data: &[u8], brs: bool) -> Result<Self, FrameCreateError> {
+        if let Some(id) = embedded_can::ExtendedId::new(raw_id) {
+            Self::new_fd(id, raw_data, brs)
+        } else {
+            Err(FrameCreateError::InvalidCanId)
+        }
+    }
+
+    /// Create new standard CAN FD frame, optionally using bit rate switching.
+    pub fn new_fd_standard(raw_id: u16, raw_data: &[u8], brs: bool) -> Result<Self, FrameCreateError> {
+        if let Some(id) = embedded_can::StandardId::new(raw_id) {
+            Self::new_fd(id, raw_data, brs)
+        } else {
+            Err(FrameCreateError::InvalidCanId)
+        }
+    }
+
     /// Create new remote frame
     pub fn new_remote(id: impl Into<embedded_can::Id>, len: usize) -> Result<Self, FrameCreateError> {
         if len <= 8 {
@@ -427,7 +427,9 @@ impl<'d, T: Instance> Cordic<'d, T> {
                 Default::default(),
             );
 
-            embassy_futures::join::join(write_transfer, read_transfer).await;
+            let (write_result, read_result) = embassy_futures::join::join(write_transfer, read_transfer).await;
+            unwrap!(write_result);
+            unwrap!(read_result);
         }
 
         Ok(res_cnt)
@@ -566,7 +568,9 @@ impl<'d, T: Instance> Cordic<'d, T> {
                 Default::default(),
             );
 
-            embassy_futures::join::join(write_transfer, read_transfer).await;
+            let (write_result, read_result) = embassy_futures::join::join(write_transfer, read_transfer).await;
+            unwrap!(write_result);
+            unwrap!(read_result);
         }
 
         Ok(res_cnt)
@@ -1817,14 +1817,13 @@ impl<'d, T: Instance> Cryp<'d, T, Async> {
         let num_words = blocks.len() / 4;
         let src_ptr: *const [u8] = ptr::slice_from_raw_parts(blocks.as_ptr().cast(), num_words);
         let options = TransferOptions {
-            #[cfg(not(gpdma))]
             priority: crate::dma::Priority::High,
             ..Default::default()
         };
         let dma_transfer = unsafe { dma.write_raw(src_ptr, dst_ptr, options) };
         T::regs().dmacr().modify(|w| w.set_dien(true));
         // Wait for the transfer to complete.
-        dma_transfer.await;
+        unwrap!(dma_transfer.await);
     }
 
     #[cfg(any(cryp_v2, cryp_v3, cryp_v4))]
@@ -1839,14 +1838,13 @@ impl<'d, T: Instance> Cryp<'d, T, Async> {
         let num_words = blocks.len();
         let src_ptr: *const [u32] = ptr::slice_from_raw_parts(blocks.as_ptr().cast(), num_words);
         let options = TransferOptions {
-            #[cfg(not(gpdma))]
             priority: crate::dma::Priority::High,
             ..Default::default()
         };
         let dma_transfer = unsafe { dma.write_raw(src_ptr, dst_ptr, options) };
         T::regs().dmacr().modify(|w| w.set_dien(true));
         // Wait for the transfer to complete.
-        dma_transfer.await;
+        unwrap!(dma_transfer.await);
     }
 
     async fn read_bytes(dma: &mut ChannelAndRequest<'d>, block_size: usize, blocks: &mut [u8]) {
@@ -1860,14 +1858,13 @@ impl<'d, T: Instance> Cryp<'d, T, Async> {
         let num_words = blocks.len() / 4;
         let dst_ptr = ptr::slice_from_raw_parts_mut(blocks.as_mut_ptr().cast(), num_words);
         let options = TransferOptions {
-            #[cfg(not(gpdma))]
             priority: crate::dma::Priority::VeryHigh,
             ..Default::default()
         };
         let dma_transfer = unsafe { dma.read_raw(src_ptr, dst_ptr, options) };
         T::regs().dmacr().modify(|w| w.set_doen(true));
         // Wait for the transfer to complete.
-        dma_transfer.await;
+        unwrap!(dma_transfer.await);
     }
 }
 
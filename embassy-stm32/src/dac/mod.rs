@@ -179,7 +179,7 @@ impl<'d, T: Instance, C: Channel> DacChannel<'d, T, C, Async> {
             },
         };
 
-        tx_f.await;
+        unwrap!(tx_f.await);
 
         T::regs().cr().modify(|w| {
             w.set_en(C::IDX, false);
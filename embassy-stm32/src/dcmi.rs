@@ -6,6 +6,8 @@ use core::task::Poll;
 use embassy_hal_internal::PeripheralType;
 use embassy_sync::waitqueue::AtomicWaker;
 
+#[cfg(dma)]
+use crate::dma::DoubleBuffer;
 use crate::dma::Transfer;
 use crate::gpio::{AfType, Pull};
 use crate::interrupt::typelevel::Interrupt;
@@ -83,6 +85,61 @@ pub enum Error {
     PeripheralError,
 }
 
+/// Embedded synchronization codes, used by the `new_es_*` constructors.
+///
+/// Each code is only compared on the bits set in its mask.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SyncCodes {
+    /// Frame start code. With `0xFF`, the frame starts at the first line start after a frame end.
+    pub frame_start: u8,
+    /// Line start code.
+    pub line_start: u8,
+    /// Line end code.
+    pub line_end: u8,
+    /// Frame end code.
+    pub frame_end: u8,
+    /// Frame start code mask.
+    pub frame_start_mask: u8,
+    /// Line start code mask.
+    pub line_start_mask: u8,
+    /// Line end code mask.
+    pub line_end_mask: u8,
+    /// Frame end code mask.
+    pub frame_end_mask: u8,
+}
+
+impl Default for SyncCodes {
+    /// ITU-R BT.656 codes of the active video and vertical blanking lines.
+    fn default() -> Self {
+        Self {
+            frame_start: 0xFF,
+            line_start: 0x80,
+            line_end: 0x9D,
+            frame_end: 0xB6,
+            frame_start_mask: 0xFF,
+            line_start_mask: 0xFF,
+            line_end_mask: 0xFF,
+            frame_end_mask: 0xFF,
+        }
+    }
+}
+
+/// Crop window, to capture only part of the frames.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct CropWindow {
+    /// Number of pixel clocks to skip at the start of each line, below `2^14`.
+    pub x: u16,
+    /// Number of lines to skip at the start of each frame, below `2^13`.
+    pub y: u16,
+    /// Number of pixel clocks captured in each line, for example twice the number of pixels for
+    /// 16-bit pixels on an 8-bit interface.
+    pub width: u16,
+    /// Number of lines captured in each frame.
+    pub height: u16,
+}
+
 /// DCMI configuration.
 #[non_exhaustive]
 pub struct Config {
@@ -92,6 +149,13 @@ pub struct Config {
     pub hsync_level: HSyncDataInvalidLevel,
     /// PIXCLK polarity.
     pub pixclk_polarity: PixelClockPolarity,
+    /// JPEG mode, for compressed data: the frames have a variable length, so they should be
+    /// captured with [`Dcmi::capture_frame`]. The crop window is not supported.
+    pub jpeg: bool,
+    /// Embedded synchronization codes, used by the `new_es_*` constructors.
+    pub sync_codes: SyncCodes,
+    /// Crop window.
+    pub crop: Option<CropWindow>,
 }
 
 impl Default for Config {
@@ -100,6 +164,9 @@ impl Default for Config {
             vsync_level: VSyncDataInvalidLevel::High,
             hsync_level: HSyncDataInvalidLevel::Low,
             pixclk_polarity: PixelClockPolarity::RisingEdge,
+            jpeg: false,
+            sync_codes: SyncCodes::default(),
+            crop: None,
         }
     }
 }
@@ -342,7 +409,38 @@ where
     ) -> Self {
         rcc::enable_and_reset::<T>();
 
-        peri.regs().cr().modify(|r| {
+        let regs = peri.regs();
+        if use_embedded_synchronization {
+            let codes = config.sync_codes;
+            regs.escr().write(|w| {
+                w.set_fsc(codes.frame_start);
+                w.set_lsc(codes.line_start);
+                w.set_lec(codes.line_end);
+                w.set_fec(codes.frame_end);
+            });
+            regs.esur().write(|w| {
+                w.set_fsu(codes.frame_start_mask);
+                w.set_lsu(codes.line_start_mask);
+                w.set_leu(codes.line_end_mask);
+                w.set_feu(codes.frame_end_mask);
+            });
+        }
+        if let Some(crop) = config.crop {
+            assert!(!config.jpeg, "the crop window is not supported in JPEG mode");
+            assert!(crop.x < 1 << 14 && crop.y < 1 << 13, "crop window offset out of range");
+            assert!(crop.width > 0 && crop.width <= 1 << 14 && crop.height > 0 && crop.height <= 1 << 14);
+            regs.cwstrt().write(|w| {
+                w.set_hoffcnt(crop.x);
+                w.set_vst(crop.y);
+            });
+            // CAPCNT and VLINE count from 0.
+            regs.cwsize().write(|w| {
+                w.set_capcnt(crop.width - 1);
+                w.set_vline(crop.height - 1);
+            });
+        }
+
+        regs.cr().modify(|r| {
             r.set_cm(true); // disable continuous mode (snapshot mode)
             r.set_ess(use_embedded_synchronization);
             r.set_pckpol(config.pixclk_polarity == PixelClockPolarity::RisingEdge);
@@ -350,6 +448,8 @@ where
             r.set_hspol(config.hsync_level == HSyncDataInvalidLevel::High);
             r.set_fcrc(0x00); // capture every frame
             r.set_edm(edm); // extended data mode
+            r.set_jpeg(config.jpeg);
+            r.set_crop(config.crop.is_some());
         });
 
         T::Interrupt::unpend();
@@ -381,20 +481,9 @@ where
         })
     }
 
-    /// This method starts the capture and finishes when both the dma transfer and DCMI finish the frame transfer.
-    /// The implication is that the input buffer size must be exactly the size of the captured frame.
-    pub async fn capture(&mut self, buffer: &mut [u32]) -> Result<(), Error> {
-        let r = self.inner.regs();
-        let src = r.dr().as_ptr() as *mut u32;
-        let request = self.dma.request();
-        let dma_read = unsafe { Transfer::new_read(self.dma.reborrow(), request, src, buffer, Default::default()) };
-
-        Self::clear_interrupt_flags();
-        Self::enable_irqs();
-
-        Self::toggle(true);
-
-        let result = poll_fn(|cx| {
+    /// Wait until the DCMI finishes the frame, or reports an error.
+    async fn wait_frame() -> Result<(), Error> {
+        poll_fn(|cx| {
             STATE.waker.register(cx.waker());
 
             let ris = crate::pac::DCMI.ris().read();
@@ -410,14 +499,131 @@ where
             } else {
                 Poll::Pending
             }
-        });
+        })
+        .await
+    }
+
+    /// This method starts the capture and finishes when both the dma transfer and DCMI finish the frame transfer.
+    /// The implication is that the input buffer size must be exactly the size of the captured frame.
+    pub async fn capture(&mut self, buffer: &mut [u32]) -> Result<(), Error> {
+        let r = self.inner.regs();
+        let src = r.dr().as_ptr() as *mut u32;
+        let request = self.dma.request();
+        let dma_read = unsafe { Transfer::new_read(self.dma.reborrow(), request, src, buffer, Default::default()) };
+
+        Self::clear_interrupt_flags();
+        Self::enable_irqs();
+
+        Self::toggle(true);
 
-        let (_, result) = embassy_futures::join::join(dma_read, result).await;
+        let (dma_result, result) = embassy_futures::join::join(dma_read, Self::wait_frame()).await;
 
         Self::toggle(false);
 
+        unwrap!(dma_result);
         result
     }
+
+    /// Capture a single frame into `buffer`, and return the number of words received.
+    ///
+    /// Unlike [`capture`](Self::capture), the buffer can be larger than the frame, which is
+    /// needed for the variable length frames of JPEG mode. Returns `Error::Overrun` if the frame
+    /// doesn't fit in the buffer.
+    pub async fn capture_frame(&mut self, buffer: &mut [u32]) -> Result<usize, Error> {
+        let len = buffer.len();
+        let r = self.inner.regs();
+        let src = r.dr().as_ptr() as *mut u32;
+        let request = self.dma.request();
+        let mut dma_read = unsafe { Transfer::new_read(self.dma.reborrow(), request, src, buffer, Default::default()) };
+
+        Self::clear_interrupt_flags();
+        Self::enable_irqs();
+
+        Self::toggle(true);
+        let result = Self::wait_frame().await;
+        Self::toggle(false);
+
+        // Stopping the DMA flushes its FIFO, after which the remaining count is final.
+        dma_read.request_stop();
+        while dma_read.is_running() {}
+        let remaining = dma_read.get_remaining_transfers() as usize;
+        // GPDMA counts bytes.
+        #[cfg(gpdma)]
+        let remaining = remaining / 4;
+
+        result?;
+        Ok(len - remaining)
+    }
+
+    /// Start capturing frames continuously, alternating between two buffers.
+    ///
+    /// Each buffer must be exactly the size of a frame. Frames are then received with
+    /// [`ContinuousCapture::capture_frame`].
+    #[cfg(dma)]
+    pub fn continuous<'a>(&'a mut self, buffers: [&'a mut [u32]; 2]) -> ContinuousCapture<'a> {
+        let r = self.inner.regs();
+        let src = r.dr().as_ptr() as *mut u32;
+        let request = self.dma.request();
+        let mut dma = unsafe { DoubleBuffer::new_read(self.dma.reborrow(), request, src, buffers, Default::default()) };
+        dma.start();
+
+        Self::clear_interrupt_flags();
+        crate::pac::DCMI.ier().modify(|r| {
+            r.set_err_ie(true);
+            r.set_ovr_ie(true);
+        });
+        crate::pac::DCMI.cr().modify(|r| r.set_cm(false));
+        Self::toggle(true);
+
+        ContinuousCapture { dma }
+    }
+}
+
+/// Continuous capture into two buffers, created by [`Dcmi::continuous`].
+///
+/// The capture is stopped when this is dropped.
+#[cfg(dma)]
+pub struct ContinuousCapture<'a> {
+    dma: DoubleBuffer<'a, u32>,
+}
+
+#[cfg(dma)]
+impl<'a> ContinuousCapture<'a> {
+    /// Wait for the next frame, and call `f` with it.
+    ///
+    /// `f` must return before the next frame is captured. Returns `Error::Overrun` if a frame was
+    /// captured before this was called, or while `f` was running, in which case frames were lost.
+    pub async fn capture_frame<R>(&mut self, f: impl FnOnce(&[u32]) -> R) -> Result<R, Error> {
+        let result = self.dma.process(|buffer| f(buffer)).await;
+
+        let ris = crate::pac::DCMI.ris().read();
+        if ris.err_ris() {
+            crate::pac::DCMI.icr().write(|r| r.set_err_isc(true));
+            crate::pac::DCMI.ier().modify(|r| r.set_err_ie(true));
+            return Err(Error::PeripheralError);
+        }
+        if ris.ovr_ris() {
+            crate::pac::DCMI.icr().write(|r| r.set_ovr_isc(true));
+            crate::pac::DCMI.ier().modify(|r| r.set_ovr_ie(true));
+            return Err(Error::Overrun);
+        }
+        result.map_err(|_| Error::Overrun)
+    }
+}
+
+#[cfg(dma)]
+impl<'a> Drop for ContinuousCapture<'a> {
+    fn drop(&mut self) {
+        crate::pac::DCMI.cr().modify(|r| {
+            r.set_enable(false);
+            r.set_capture(false);
+            r.set_cm(true);
+        });
+        crate::pac::DCMI.ier().modify(|r| {
+            r.set_err_ie(false);
+            r.set_ovr_ie(false);
+        });
+    }
 }
 
 trait SealedInstance: crate::rcc::RccPeripheral {
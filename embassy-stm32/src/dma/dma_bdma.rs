@@ -1,4 +1,6 @@
 use core::future::{poll_fn, Future};
+#[cfg(dma)]
+use core::marker::PhantomData;
 use core::pin::Pin;
 use core::sync::atomic::{fence, AtomicUsize, Ordering};
 use core::task::{Context, Poll, Waker};
@@ -8,7 +10,7 @@ use embassy_sync::waitqueue::AtomicWaker;
 
 use super::ringbuffer::{DmaCtrl, Error, ReadableDmaRingBuffer, WritableDmaRingBuffer};
 use super::word::{Word, WordSize};
-use super::{AnyChannel, Channel, Dir, Request, STATE};
+use super::{AnyChannel, Channel, Dir, ErrorState, Request, TransferError, STATE};
 use crate::interrupt::typelevel::Interrupt;
 use crate::{interrupt, pac};
 
@@ -46,8 +48,14 @@ pub struct TransferOptions {
     /// FIFO threshold for DMA FIFO mode. If none, direct mode is used.
     #[cfg(dma)]
     pub fifo_threshold: Option<FifoThreshold>,
+    /// Enable direct mode error interrupt, making the transfer resolve to
+    /// [`TransferError::DirectMode`] if data was lost. Ring buffers panic on the error instead.
+    #[cfg(dma)]
+    pub direct_mode_error_ir: bool,
     /// Request priority level
     pub priority: Priority,
+    /// Increment the peripheral address, for reading or writing a block of registers
+    pub peripheral_increment: bool,
     /// Enable circular DMA
     ///
     /// Note:
@@ -71,7 +79,10 @@ impl Default for TransferOptions {
             flow_ctrl: FlowControl::Dma,
             #[cfg(dma)]
             fifo_threshold: None,
+            #[cfg(dma)]
+            direct_mode_error_ir: false,
             priority: Priority::VeryHigh,
+            peripheral_increment: false,
             circular: false,
             half_transfer_ir: false,
             complete_transfer_ir: true,
@@ -243,12 +254,14 @@ mod bdma_only {
 pub(crate) struct ChannelState {
     waker: AtomicWaker,
     complete_count: AtomicUsize,
+    error: ErrorState,
 }
 
 impl ChannelState {
     pub(crate) const NEW: Self = Self {
         waker: AtomicWaker::new(),
         complete_count: AtomicUsize::new(0),
+        error: ErrorState::NEW,
     };
 }
 
@@ -286,7 +299,16 @@ impl AnyChannel {
                 let isr = r.isr(info.num / 4).read();
 
                 if isr.teif(info.num % 4) {
-                    panic!("DMA: error on DMA@{:08x} channel {}", r.as_ptr() as u32, info.num);
+                    // The stream was disabled by the hardware.
+                    r.ifcr(info.num / 4).write(|w| w.set_teif(info.num % 4, true));
+                    state.error.set(TransferError::Bus);
+                    state.waker.wake();
+                    return;
+                }
+                if isr.dmeif(info.num % 4) && cr.read().dmeie() {
+                    // Data was lost, but the stream keeps running. Report the error at the end.
+                    r.ifcr(info.num / 4).write(|w| w.set_dmeif(info.num % 4, true));
+                    state.error.set(TransferError::DirectMode);
                 }
 
                 if isr.htif(info.num % 4) && cr.read().htie() {
@@ -307,7 +329,11 @@ impl AnyChannel {
                 let cr = r.ch(info.num).cr();
 
                 if isr.teif(info.num) {
-                    panic!("DMA: error on BDMA@{:08x} channel {}", r.as_ptr() as u32, info.num);
+                    // The channel was disabled by the hardware.
+                    r.ifcr().write(|w| w.set_teif(info.num, true));
+                    state.error.set(TransferError::Bus);
+                    state.waker.wake();
+                    return;
                 }
 
                 if isr.htif(info.num) && cr.read().htie() {
@@ -366,6 +392,7 @@ impl AnyChannel {
                 fence(Ordering::SeqCst);
 
                 state.complete_count.store(0, Ordering::Release);
+                state.error.clear();
                 self.clear_irqs();
 
                 ch.par().write_value(peri_addr as u32);
@@ -387,8 +414,9 @@ impl AnyChannel {
                     w.set_psize(peripheral_size.into());
                     w.set_pl(options.priority.into());
                     w.set_minc(incr_mem);
-                    w.set_pinc(false);
+                    w.set_pinc(options.peripheral_increment);
                     w.set_teie(true);
+                    w.set_dmeie(options.direct_mode_error_ir);
                     w.set_htie(options.half_transfer_ir);
                     w.set_tcie(options.complete_transfer_ir);
                     w.set_circ(options.circular);
@@ -411,6 +439,7 @@ impl AnyChannel {
                 let ch = r.ch(info.num);
 
                 state.complete_count.store(0, Ordering::Release);
+                state.error.clear();
                 self.clear_irqs();
 
                 ch.par().write_value(peri_addr as u32);
@@ -420,6 +449,7 @@ impl AnyChannel {
                     w.set_psize(peripheral_size.into());
                     w.set_msize(mem_size.into());
                     w.set_minc(incr_mem);
+                    w.set_pinc(options.peripheral_increment);
                     w.set_dir(dir.into());
                     w.set_teie(true);
                     w.set_tcie(options.complete_transfer_ir);
@@ -460,6 +490,7 @@ impl AnyChannel {
                     w.set_htif(isrbit, true);
                     w.set_tcif(isrbit, true);
                     w.set_teif(isrbit, true);
+                    w.set_dmeif(isrbit, true);
                 });
             }
             #[cfg(bdma)]
@@ -556,6 +587,35 @@ impl AnyChannel {
         }
     }
 
+    /// Switch a configured channel to double-buffer mode, with `mem1_addr` as the second buffer.
+    #[cfg(dma)]
+    unsafe fn configure_double_buffer(&self, mem1_addr: *mut u32) {
+        let info = self.info();
+        match self.info().dma {
+            DmaInfo::Dma(r) => {
+                let ch = r.st(info.num);
+                ch.m1ar().write_value(mem1_addr as u32);
+                ch.cr().modify(|w| w.set_dbm(true));
+            }
+            #[cfg(bdma)]
+            DmaInfo::Bdma(_) => panic!("DMA: double-buffer mode is not supported by BDMA channels"),
+        }
+    }
+
+    /// Index of the buffer being transferred in double-buffer mode.
+    #[cfg(dma)]
+    fn current_target(&self) -> usize {
+        let info = self.info();
+        match self.info().dma {
+            DmaInfo::Dma(r) => match r.st(info.num).cr().read().ct() {
+                pac::dma::vals::Ct::MEMORY0 => 0,
+                _ => 1,
+            },
+            #[cfg(bdma)]
+            DmaInfo::Bdma(_) => unreachable!(),
+        }
+    }
+
     fn poll_stop(&self) -> Poll<()> {
         use core::sync::atomic::compiler_fence;
         compiler_fence(Ordering::SeqCst);
@@ -572,6 +632,9 @@ impl AnyChannel {
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Transfer<'a> {
     channel: Peri<'a, AnyChannel>,
+    /// DMA doesn't run in stop mode.
+    #[cfg(feature = "low-power")]
+    _stop_limit: crate::low_power::StopModeLimit,
 }
 
 impl<'a> Transfer<'a> {
@@ -690,7 +753,11 @@ impl<'a> Transfer<'a> {
             options,
         );
         channel.start();
-        Self { channel }
+        Self {
+            channel,
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
+        }
     }
 
     /// Request the transfer to stop.
@@ -725,13 +792,23 @@ impl<'a> Transfer<'a> {
     }
 
     /// Blocking wait until the transfer finishes.
-    pub fn blocking_wait(mut self) {
+    pub fn blocking_wait(mut self) -> Result<(), TransferError> {
         while self.is_running() {}
+        let result = STATE[self.channel.id as usize].error.get();
 
         // "Subsequent reads and writes cannot be moved ahead of preceding reads."
         fence(Ordering::SeqCst);
 
+        // `forget` skips stopping the finished transfer, but the stop mode limit must be released.
+        #[cfg(feature = "low-power")]
+        // SAFETY: `self` is forgotten right after, so the limit is dropped exactly once and
+        // never used again.
+        unsafe {
+            core::ptr::drop_in_place(&mut self._stop_limit)
+        };
         core::mem::forget(self);
+
+        result
     }
 }
 
@@ -747,7 +824,7 @@ impl<'a> Drop for Transfer<'a> {
 
 impl<'a> Unpin for Transfer<'a> {}
 impl<'a> Future for Transfer<'a> {
-    type Output = ();
+    type Output = Result<(), TransferError>;
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let state: &ChannelState = &STATE[self.channel.id as usize];
 
@@ -756,7 +833,7 @@ impl<'a> Future for Transfer<'a> {
         if self.is_running() {
             Poll::Pending
         } else {
-            Poll::Ready(())
+            Poll::Ready(state.error.get())
         }
     }
 }
@@ -772,6 +849,10 @@ impl<'a> DmaCtrl for DmaCtrlImpl<'a> {
 
     fn reset_complete_count(&mut self) -> usize {
         let state = &STATE[self.0.id as usize];
+        // Ring buffers have no way to report transfer errors.
+        if let Err(e) = state.error.get() {
+            panic!("DMA: transfer error {:?} on channel {}", e, self.0.id);
+        }
         #[cfg(not(armv6m))]
         return state.complete_count.swap(0, Ordering::AcqRel);
         #[cfg(armv6m)]
@@ -788,8 +869,17 @@ impl<'a> DmaCtrl for DmaCtrlImpl<'a> {
 }
 
 /// Ringbuffer for receiving data using DMA circular mode.
+///
+/// The DMA continuously copies the peripheral data register to the buffer, which can be attached
+/// to any peripheral generating DMA requests, for example to write a streaming driver. Data is
+/// taken out with [`read`](Self::read), [`read_exact`](Self::read_exact) or
+/// [`wait_readable`](Self::wait_readable). Data which is not read before the DMA wraps around is
+/// lost, and reported as [`RingBufferError::Overrun`](super::RingBufferError::Overrun).
 pub struct ReadableRingBuffer<'a, W: Word> {
     channel: Peri<'a, AnyChannel>,
+    /// DMA doesn't run in stop mode.
+    #[cfg(feature = "low-power")]
+    _stop_limit: crate::low_power::StopModeLimit,
     ringbuf: ReadableDmaRingBuffer<'a, W>,
 }
 
@@ -828,6 +918,8 @@ impl<'a, W: Word> ReadableRingBuffer<'a, W> {
         Self {
             channel,
             ringbuf: ReadableDmaRingBuffer::new(buffer),
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
         }
     }
 
@@ -869,6 +961,15 @@ impl<'a, W: Word> ReadableRingBuffer<'a, W> {
             .await
     }
 
+    /// Wait until data is available for reading, and return how many elements can be read.
+    ///
+    /// Error is returned if the DMA controller overwrote unread data.
+    pub async fn wait_readable(&mut self) -> Result<usize, Error> {
+        self.ringbuf
+            .wait_readable(&mut DmaCtrlImpl(self.channel.reborrow()))
+            .await
+    }
+
     /// The current length of the ringbuffer
     pub fn len(&mut self) -> Result<usize, Error> {
         Ok(self.ringbuf.len(&mut DmaCtrlImpl(self.channel.reborrow()))?)
@@ -940,8 +1041,17 @@ impl<'a, W: Word> Drop for ReadableRingBuffer<'a, W> {
 }
 
 /// Ringbuffer for writing data using DMA circular mode.
+///
+/// The DMA continuously copies the buffer to the peripheral data register, which can be attached
+/// to any peripheral generating DMA requests. Data is put in with [`write`](Self::write) or
+/// [`write_exact`](Self::write_exact), and [`wait_writable`](Self::wait_writable) waits for free
+/// space. If data is not written before the DMA wraps around, stale data is sent, and reported as
+/// [`RingBufferError::Overrun`](super::RingBufferError::Overrun).
 pub struct WritableRingBuffer<'a, W: Word> {
     channel: Peri<'a, AnyChannel>,
+    /// DMA doesn't run in stop mode.
+    #[cfg(feature = "low-power")]
+    _stop_limit: crate::low_power::StopModeLimit,
     ringbuf: WritableDmaRingBuffer<'a, W>,
 }
 
@@ -980,6 +1090,8 @@ impl<'a, W: Word> WritableRingBuffer<'a, W> {
         Self {
             channel,
             ringbuf: WritableDmaRingBuffer::new(buffer),
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
         }
     }
 
@@ -1021,6 +1133,15 @@ impl<'a, W: Word> WritableRingBuffer<'a, W> {
             .await
     }
 
+    /// Wait until space is available for writing, and return how many elements can be written.
+    ///
+    /// Error is returned if the DMA controller read data which was not written.
+    pub async fn wait_writable(&mut self) -> Result<usize, Error> {
+        self.ringbuf
+            .wait_writable(&mut DmaCtrlImpl(self.channel.reborrow()))
+            .await
+    }
+
     /// The current length of the ringbuffer
     pub fn len(&mut self) -> Result<usize, Error> {
         Ok(self.ringbuf.len(&mut DmaCtrlImpl(self.channel.reborrow()))?)
@@ -1088,3 +1209,170 @@ impl<'a, W: Word> Drop for WritableRingBuffer<'a, W> {
         fence(Ordering::SeqCst);
     }
 }
+
+/// Double-buffered DMA transfer, also known as ping-pong mode.
+///
+/// The DMA alternates between two buffers of the same length: while it transfers one, the other is
+/// handed to the application by [`process`](Self::process). For peripheral to memory transfers, the
+/// buffer holds the received data. For memory to peripheral transfers, it is filled with the data
+/// to send next, so both buffers must be filled before starting.
+///
+/// This is only supported by DMA channels, not BDMA channels.
+#[cfg(dma)]
+pub struct DoubleBuffer<'a, W: Word> {
+    channel: Peri<'a, AnyChannel>,
+    /// DMA doesn't run in stop mode.
+    #[cfg(feature = "low-power")]
+    _stop_limit: crate::low_power::StopModeLimit,
+    buffers: [*mut W; 2],
+    len: usize,
+    _phantom: PhantomData<&'a mut [W]>,
+}
+
+#[cfg(dma)]
+impl<'a, W: Word> DoubleBuffer<'a, W> {
+    /// Create a new double-buffered read (peripheral to memory).
+    pub unsafe fn new_read(
+        channel: Peri<'a, impl Channel>,
+        request: Request,
+        peri_addr: *mut W,
+        buffers: [&'a mut [W]; 2],
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(
+            channel.into(),
+            request,
+            Dir::PeripheralToMemory,
+            peri_addr,
+            buffers,
+            options,
+        )
+    }
+
+    /// Create a new double-buffered write (memory to peripheral).
+    pub unsafe fn new_write(
+        channel: Peri<'a, impl Channel>,
+        request: Request,
+        buffers: [&'a mut [W]; 2],
+        peri_addr: *mut W,
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(
+            channel.into(),
+            request,
+            Dir::MemoryToPeripheral,
+            peri_addr,
+            buffers,
+            options,
+        )
+    }
+
+    unsafe fn new_inner(
+        channel: Peri<'a, AnyChannel>,
+        request: Request,
+        dir: Dir,
+        peri_addr: *mut W,
+        buffers: [&'a mut [W]; 2],
+        mut options: TransferOptions,
+    ) -> Self {
+        let [buffer0, buffer1] = buffers;
+        assert_eq!(buffer0.len(), buffer1.len(), "buffers must have the same length");
+        let len = buffer0.len();
+
+        options.half_transfer_ir = false;
+        options.complete_transfer_ir = true;
+        options.circular = true;
+
+        channel.configure(
+            request,
+            dir,
+            peri_addr as *mut u32,
+            buffer0.as_mut_ptr() as *mut u32,
+            len,
+            true,
+            W::size(),
+            W::size(),
+            options,
+        );
+        channel.configure_double_buffer(buffer1.as_mut_ptr() as *mut u32);
+
+        Self {
+            channel,
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
+            buffers: [buffer0.as_mut_ptr(), buffer1.as_mut_ptr()],
+            len,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Start the transfer.
+    ///
+    /// You must call this after creating it for it to work.
+    pub fn start(&mut self) {
+        self.channel.start();
+    }
+
+    /// Wait until the DMA completes a buffer, and call `f` with it.
+    ///
+    /// `f` must return before the DMA completes the other buffer. Returns `Error::Overrun` if a
+    /// buffer completed before this was called, or while `f` was running, in which case received
+    /// data was lost or data was sent twice.
+    pub async fn process<R>(&mut self, f: impl FnOnce(&mut [W]) -> R) -> Result<R, Error> {
+        let completed = poll_fn(|cx| {
+            let mut ctrl = DmaCtrlImpl(self.channel.reborrow());
+            ctrl.set_waker(cx.waker());
+            match ctrl.reset_complete_count() {
+                0 => Poll::Pending,
+                n => Poll::Ready(n),
+            }
+        })
+        .await;
+        if completed > 1 {
+            return Err(Error::Overrun);
+        }
+
+        // The DMA switched to the other buffer when completing this one.
+        let index = 1 - self.channel.current_target();
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+
+        let buffer = unsafe { core::slice::from_raw_parts_mut(self.buffers[index], self.len) };
+        let result = f(buffer);
+
+        // "Preceding reads and writes cannot be moved past subsequent writes."
+        fence(Ordering::SeqCst);
+        if STATE[self.channel.id as usize].complete_count.load(Ordering::Acquire) != 0 {
+            return Err(Error::Overrun);
+        }
+        Ok(result)
+    }
+
+    /// Length of each buffer.
+    pub const fn buffer_len(&self) -> usize {
+        self.len
+    }
+
+    /// Request the DMA to stop.
+    ///
+    /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
+    pub fn request_stop(&mut self) {
+        self.channel.request_stop()
+    }
+
+    /// Return whether DMA is still running.
+    pub fn is_running(&mut self) -> bool {
+        self.channel.is_running()
+    }
+}
+
+#[cfg(dma)]
+impl<'a, W: Word> Drop for DoubleBuffer<'a, W> {
+    fn drop(&mut self) {
+        self.request_stop();
+        while self.is_running() {}
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+    }
+}
@@ -1,19 +1,20 @@
 #![macro_use]
 
-use core::future::Future;
+use core::cell::UnsafeCell;
+use core::future::{poll_fn, Future};
 use core::pin::Pin;
-use core::sync::atomic::{fence, Ordering};
-use core::task::{Context, Poll};
+use core::sync::atomic::{fence, AtomicUsize, Ordering};
+use core::task::{Context, Poll, Waker};
 
 use embassy_hal_internal::Peri;
 use embassy_sync::waitqueue::AtomicWaker;
 
+use super::ringbuffer::{DmaCtrl, Error, ReadableDmaRingBuffer, WritableDmaRingBuffer};
 use super::word::{Word, WordSize};
-use super::{AnyChannel, Channel, Dir, Request, STATE};
+use super::{AnyChannel, Channel, Dir, ErrorState, Request, TransferError, STATE};
 use crate::interrupt::typelevel::Interrupt;
-use crate::interrupt::Priority;
-use crate::pac;
-use crate::pac::gpdma::vals;
+use crate::pac::gpdma::{regs, vals};
+use crate::{interrupt, pac};
 
 pub(crate) struct ChannelInfo {
     pub(crate) dma: pac::gpdma::Gpdma,
@@ -26,11 +27,53 @@ pub(crate) struct ChannelInfo {
 #[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[non_exhaustive]
-pub struct TransferOptions {}
+pub struct TransferOptions {
+    /// Request priority level
+    pub priority: Priority,
+    /// Source burst length in beats, from 1 to 64
+    pub src_burst_len: u8,
+    /// Destination burst length in beats, from 1 to 64
+    pub dst_burst_len: u8,
+    /// Increment the peripheral address, for reading or writing a block of registers
+    pub peripheral_increment: bool,
+}
 
 impl Default for TransferOptions {
     fn default() -> Self {
-        Self {}
+        Self {
+            priority: Priority::Low,
+            src_burst_len: 1,
+            dst_burst_len: 1,
+            peripheral_increment: false,
+        }
+    }
+}
+
+/// GPDMA request priority
+///
+/// Requests of the low priorities are arbitrated round-robin, weighted by their priority. The very
+/// high priority is served first, and is meant for time-sensitive transfers.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Priority {
+    /// Low priority, low weight
+    Low,
+    /// Low priority, medium weight
+    Medium,
+    /// Low priority, high weight
+    High,
+    /// High priority
+    VeryHigh,
+}
+
+impl From<Priority> for vals::Prio {
+    fn from(value: Priority) -> Self {
+        match value {
+            Priority::Low => Self::from_bits(0b00),
+            Priority::Medium => Self::from_bits(0b01),
+            Priority::High => Self::from_bits(0b10),
+            Priority::VeryHigh => Self::from_bits(0b11),
+        }
     }
 }
 
@@ -44,18 +87,163 @@ impl From<WordSize> for vals::Dw {
     }
 }
 
+/// Linked-list item of a GPDMA channel, describing one block transfer.
+///
+/// The channel loads the next item from memory at the end of each block, so a list of items
+/// performs chained or scattered transfers without the CPU. Items are chained by
+/// [`Transfer::new_linked_list`], and must all be in the same 64 KiB memory region.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[repr(C, align(4))]
+pub struct LinkedListItem {
+    tr1: regs::ChTr1,
+    tr2: regs::ChTr2,
+    br1: regs::ChBr1,
+    sar: u32,
+    dar: u32,
+    llr: regs::ChLlr,
+}
+
+impl LinkedListItem {
+    /// Create a read block transfer (peripheral to memory).
+    pub unsafe fn new_read<W: Word>(
+        request: Request,
+        peri_addr: *mut W,
+        buf: *mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(
+            request,
+            Dir::PeripheralToMemory,
+            peri_addr as *const u32,
+            buf as *mut W as *mut u32,
+            buf.len(),
+            true,
+            W::size(),
+            W::size(),
+            options,
+        )
+    }
+
+    /// Create a write block transfer (memory to peripheral).
+    pub unsafe fn new_write<MW: Word, PW: Word>(
+        request: Request,
+        buf: *const [MW],
+        peri_addr: *mut PW,
+        options: TransferOptions,
+    ) -> Self {
+        Self::new_inner(
+            request,
+            Dir::MemoryToPeripheral,
+            peri_addr as *const u32,
+            buf as *const MW as *mut u32,
+            buf.len(),
+            true,
+            MW::size(),
+            PW::size(),
+            options,
+        )
+    }
+
+    fn new_inner(
+        request: Request,
+        dir: Dir,
+        peri_addr: *const u32,
+        mem_addr: *mut u32,
+        mem_len: usize,
+        incr_mem: bool,
+        data_size: WordSize,
+        dst_size: WordSize,
+        options: TransferOptions,
+    ) -> Self {
+        assert!((1..=64).contains(&options.src_burst_len) && (1..=64).contains(&options.dst_burst_len));
+
+        // BNDT is specified as bytes, not as number of transfers.
+        let Ok(bndt) = u16::try_from(mem_len * data_size.bytes()) else {
+            panic!("DMA transfers may not be larger than 65535 bytes.");
+        };
+
+        let (incr_src, incr_dst) = match dir {
+            Dir::MemoryToPeripheral => (incr_mem, options.peripheral_increment),
+            Dir::PeripheralToMemory => (options.peripheral_increment, incr_mem),
+        };
+        let mut tr1 = regs::ChTr1(0);
+        tr1.set_sdw(data_size.into());
+        tr1.set_ddw(dst_size.into());
+        tr1.set_sbl_1(options.src_burst_len - 1);
+        tr1.set_dbl_1(options.dst_burst_len - 1);
+        tr1.set_sinc(incr_src);
+        tr1.set_dinc(incr_dst);
+
+        let mut tr2 = regs::ChTr2(0);
+        tr2.set_dreq(match dir {
+            Dir::MemoryToPeripheral => vals::Dreq::DESTINATION_PERIPHERAL,
+            Dir::PeripheralToMemory => vals::Dreq::SOURCE_PERIPHERAL,
+        });
+        tr2.set_reqsel(request);
+
+        let mut br1 = regs::ChBr1(0);
+        br1.set_bndt(bndt);
+
+        let (sar, dar) = match dir {
+            Dir::MemoryToPeripheral => (mem_addr as u32, peri_addr as u32),
+            Dir::PeripheralToMemory => (peri_addr as u32, mem_addr as u32),
+        };
+
+        Self {
+            tr1,
+            tr2,
+            br1,
+            sar,
+            dar,
+            llr: regs::ChLlr(0),
+        }
+    }
+
+    /// Link this item to `next`, which must be in the same 64 KiB memory region.
+    fn link(&mut self, next: *const LinkedListItem) {
+        // Update all the registers from the next item.
+        self.llr = regs::ChLlr(0);
+        self.llr.set_ut1(true);
+        self.llr.set_ut2(true);
+        self.llr.set_ub1(true);
+        self.llr.set_usa(true);
+        self.llr.set_uda(true);
+        self.llr.set_ull(true);
+        self.llr.set_la(((next as u32 & 0xFFFF) >> 2) as u16);
+    }
+}
+
+struct LinkedListItemCell(UnsafeCell<LinkedListItem>);
+
+// Safety: only accessed by the owner of the channel.
+unsafe impl Sync for LinkedListItemCell {}
+
 pub(crate) struct ChannelState {
     waker: AtomicWaker,
+    complete_count: AtomicUsize,
+    error: ErrorState,
+    /// Linked-list item of the ring buffers, looping on itself.
+    ring_item: LinkedListItemCell,
 }
 
 impl ChannelState {
     pub(crate) const NEW: Self = Self {
         waker: AtomicWaker::new(),
+        complete_count: AtomicUsize::new(0),
+        error: ErrorState::NEW,
+        ring_item: LinkedListItemCell(UnsafeCell::new(LinkedListItem {
+            tr1: regs::ChTr1(0),
+            tr2: regs::ChTr2(0),
+            br1: regs::ChBr1(0),
+            sar: 0,
+            dar: 0,
+            llr: regs::ChLlr(0),
+        })),
     };
 }
 
 /// safety: must be called only once
-pub(crate) unsafe fn init(cs: critical_section::CriticalSection, irq_priority: Priority) {
+pub(crate) unsafe fn init(cs: critical_section::CriticalSection, irq_priority: interrupt::Priority) {
     foreach_interrupt! {
         ($peri:ident, gpdma, $block:ident, $signal_name:ident, $irq:ident) => {
             crate::interrupt::typelevel::$irq::set_priority_with_cs(cs, irq_priority);
@@ -81,35 +269,121 @@ impl AnyChannel {
         let ch = info.dma.ch(info.num);
         let sr = ch.sr().read();
 
-        if sr.dtef() {
-            panic!(
-                "DMA: data transfer error on DMA@{:08x} channel {}",
-                info.dma.as_ptr() as u32,
-                info.num
-            );
-        }
-        if sr.usef() {
-            panic!(
-                "DMA: user settings error on DMA@{:08x} channel {}",
-                info.dma.as_ptr() as u32,
-                info.num
-            );
+        if sr.dtef() || sr.usef() {
+            // The channel was disabled by the hardware. Disable all xxIEs to prevent the irq from
+            // firing again, the flags are cleared by the next transfer.
+            ch.cr().write(|_| {});
+
+            let error = if sr.dtef() {
+                TransferError::Bus
+            } else {
+                TransferError::UserSetting
+            };
+            state.error.set(error);
+            state.waker.wake();
+            return;
         }
 
-        if sr.suspf() || sr.tcf() {
+        if sr.suspf() {
+            // Only clear the flag, the channel keeps its configuration to be resumed.
+            ch.fcr().write(|w| w.set_suspf(true));
+
+            // Wake the future. It'll see the channel is suspended.
+            state.waker.wake();
+        } else if sr.tcf() && !ch.llr().read().ull() {
             // disable all xxIEs to prevent the irq from firing again.
             ch.cr().write(|_| {});
 
             // Wake the future. It'll look at tcf and see it's set.
             state.waker.wake();
+        } else if sr.tcf() || sr.htf() {
+            // A block of a circular linked list completed, the channel keeps running.
+            if sr.tcf() {
+                state.complete_count.fetch_add(1, Ordering::Release);
+            }
+            ch.fcr().write(|w| {
+                w.set_tcf(true);
+                w.set_htf(true);
+            });
+            state.waker.wake();
         }
     }
+
+    /// Load the first item of a linked list and start the channel.
+    unsafe fn start_linked_list(
+        &self,
+        first: &LinkedListItem,
+        priority: Priority,
+        half_transfer_ir: bool,
+        enable: bool,
+    ) {
+        let info = self.info();
+        let ch = info.dma.ch(info.num);
+
+        // "Preceding reads and writes cannot be moved past subsequent writes."
+        fence(Ordering::SeqCst);
+
+        let state = &STATE[self.id as usize];
+        state.complete_count.store(0, Ordering::Release);
+        state.error.clear();
+
+        ch.cr().write(|w| w.set_reset(true));
+        ch.fcr().write(|w| w.0 = 0xFFFF_FFFF); // clear all irqs
+        ch.lbar().write(|w| w.set_lba((first as *const _ as u32 >> 16) as u16));
+        ch.tr1().write_value(first.tr1);
+        ch.tr2().write_value(first.tr2);
+        ch.tr3().write(|_| {}); // no address offsets.
+        ch.br1().write_value(first.br1);
+        ch.sar().write_value(first.sar);
+        ch.dar().write_value(first.dar);
+        ch.llr().write_value(first.llr);
+
+        ch.cr().write(|w| {
+            w.set_prio(priority.into());
+
+            // Enable interrupts
+            w.set_tcie(true);
+            w.set_htie(half_transfer_ir);
+            w.set_useie(true);
+            w.set_dteie(true);
+            w.set_suspie(true);
+
+            w.set_en(enable);
+        });
+    }
+
+    /// Resume a suspended channel, or start a configured one.
+    fn resume(&self) {
+        let info = self.info();
+        let ch = info.dma.ch(info.num);
+
+        ch.cr().modify(|w| {
+            w.set_susp(false);
+            w.set_en(true);
+        });
+    }
+
+    fn request_suspend(&self) {
+        let info = self.info();
+        info.dma.ch(info.num).cr().modify(|w| w.set_susp(true))
+    }
+
+    fn is_running(&self) -> bool {
+        let info = self.info();
+        let ch = info.dma.ch(info.num);
+
+        // A suspended channel is idle.
+        !ch.sr().read().idlef()
+    }
 }
 
 /// DMA transfer.
 #[must_use = "futures do nothing unless you `.await` or poll them"]
 pub struct Transfer<'a> {
     channel: Peri<'a, AnyChannel>,
+    /// DMA doesn't run in stop mode.
+    #[cfg(feature = "low-power")]
+    _stop_limit: crate::low_power::StopModeLimit,
 }
 
 impl<'a> Transfer<'a> {
@@ -212,73 +486,76 @@ impl<'a> Transfer<'a> {
         incr_mem: bool,
         data_size: WordSize,
         dst_size: WordSize,
-        _options: TransferOptions,
+        options: TransferOptions,
     ) -> Self {
-        // BNDT is specified as bytes, not as number of transfers.
-        let Ok(bndt) = (mem_len * data_size.bytes()).try_into() else {
-            panic!("DMA transfers may not be larger than 65535 bytes.");
-        };
-
-        let info = channel.info();
-        let ch = info.dma.ch(info.num);
-
-        // "Preceding reads and writes cannot be moved past subsequent writes."
-        fence(Ordering::SeqCst);
-
-        let this = Self { channel };
-
-        ch.cr().write(|w| w.set_reset(true));
-        ch.fcr().write(|w| w.0 = 0xFFFF_FFFF); // clear all irqs
-        ch.llr().write(|_| {}); // no linked list
-        ch.tr1().write(|w| {
-            w.set_sdw(data_size.into());
-            w.set_ddw(dst_size.into());
-            w.set_sinc(dir == Dir::MemoryToPeripheral && incr_mem);
-            w.set_dinc(dir == Dir::PeripheralToMemory && incr_mem);
-        });
-        ch.tr2().write(|w| {
-            w.set_dreq(match dir {
-                Dir::MemoryToPeripheral => vals::Dreq::DESTINATION_PERIPHERAL,
-                Dir::PeripheralToMemory => vals::Dreq::SOURCE_PERIPHERAL,
-            });
-            w.set_reqsel(request);
-        });
-        ch.tr3().write(|_| {}); // no address offsets.
-        ch.br1().write(|w| w.set_bndt(bndt));
+        let item = LinkedListItem::new_inner(
+            request, dir, peri_addr, mem_addr, mem_len, incr_mem, data_size, dst_size, options,
+        );
+        channel.start_linked_list(&item, options.priority, false, true);
+
+        Self {
+            channel,
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
+        }
+    }
 
-        match dir {
-            Dir::MemoryToPeripheral => {
-                ch.sar().write_value(mem_addr as _);
-                ch.dar().write_value(peri_addr as _);
+    /// Create a transfer of a linked list of block transfers.
+    ///
+    /// The items are chained in order, and loaded by the DMA at the end of each block. If
+    /// `circular` is true, the last item is chained to the first one, so the transfer never
+    /// completes and must be stopped with [`request_stop`](Self::request_stop).
+    ///
+    /// Panics if the items are not all in the same 64 KiB memory region.
+    pub unsafe fn new_linked_list(
+        channel: Peri<'a, impl Channel>,
+        items: &'a mut [LinkedListItem],
+        circular: bool,
+        options: TransferOptions,
+    ) -> Self {
+        let channel: Peri<'a, AnyChannel> = channel.into();
+        assert!(!items.is_empty());
+
+        let base = items.as_ptr() as u32 & 0xFFFF_0000;
+        let end = items.as_ptr_range().end as u32 - 1;
+        assert!(
+            end & 0xFFFF_0000 == base,
+            "linked-list items must be in the same 64 KiB region"
+        );
+
+        let first = items.as_ptr();
+        let count = items.len();
+        for (i, item) in items.iter_mut().enumerate() {
+            if circular {
+                // Acknowledged by the interrupt handler, as the channel never completes.
+                item.tr2.set_tcem(vals::Tcem::BLOCK);
+            } else {
+                // Only complete at the end of the list.
+                item.tr2.set_tcem(vals::Tcem::LAST_LINKED_LIST_ITEM);
             }
-            Dir::PeripheralToMemory => {
-                ch.sar().write_value(peri_addr as _);
-                ch.dar().write_value(mem_addr as _);
+            if i + 1 < count {
+                item.link(first.add(i + 1));
+            } else if circular {
+                item.link(first);
+            } else {
+                item.llr = regs::ChLlr(0);
             }
         }
 
-        ch.cr().write(|w| {
-            // Enable interrupts
-            w.set_tcie(true);
-            w.set_useie(true);
-            w.set_dteie(true);
-            w.set_suspie(true);
+        channel.start_linked_list(&items[0], options.priority, false, true);
 
-            // Start it
-            w.set_en(true);
-        });
-
-        this
+        Self {
+            channel,
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
+        }
     }
 
     /// Request the transfer to stop.
     ///
     /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
     pub fn request_stop(&mut self) {
-        let info = self.channel.info();
-        let ch = info.dma.ch(info.num);
-
-        ch.cr().modify(|w| w.set_susp(true))
+        self.channel.request_suspend()
     }
 
     /// Return whether this transfer is still running.
@@ -289,8 +566,10 @@ impl<'a> Transfer<'a> {
         let info = self.channel.info();
         let ch = info.dma.ch(info.num);
 
+        // The TC flag of a circular linked list is cleared by the interrupt handler.
         let sr = ch.sr().read();
-        !sr.tcf() && !sr.suspf()
+        let suspended = sr.idlef() && ch.cr().read().susp();
+        !(sr.tcf() && !ch.llr().read().ull()) && !suspended && !sr.dtef() && !sr.usef()
     }
 
     /// Gets the total remaining transfers for the channel
@@ -303,13 +582,23 @@ impl<'a> Transfer<'a> {
     }
 
     /// Blocking wait until the transfer finishes.
-    pub fn blocking_wait(mut self) {
+    pub fn blocking_wait(mut self) -> Result<(), TransferError> {
         while self.is_running() {}
+        let result = STATE[self.channel.id as usize].error.get();
 
         // "Subsequent reads and writes cannot be moved ahead of preceding reads."
         fence(Ordering::SeqCst);
 
+        // `forget` skips stopping the finished transfer, but the stop mode limit must be released.
+        #[cfg(feature = "low-power")]
+        // SAFETY: `self` is forgotten right after, so the limit is dropped exactly once and
+        // never used again.
+        unsafe {
+            core::ptr::drop_in_place(&mut self._stop_limit)
+        };
         core::mem::forget(self);
+
+        result
     }
 }
 
@@ -325,7 +614,7 @@ impl<'a> Drop for Transfer<'a> {
 
 impl<'a> Unpin for Transfer<'a> {}
 impl<'a> Future for Transfer<'a> {
-    type Output = ();
+    type Output = Result<(), TransferError>;
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         let state = &STATE[self.channel.id as usize];
         state.waker.register(cx.waker());
@@ -333,7 +622,328 @@ impl<'a> Future for Transfer<'a> {
         if self.is_running() {
             Poll::Pending
         } else {
-            Poll::Ready(())
+            Poll::Ready(state.error.get())
+        }
+    }
+}
+
+// ==============================
+
+impl AnyChannel {
+    /// Configure the channel for a ring buffer: a single linked-list item looping on itself.
+    unsafe fn configure_ring(&self, mut item: LinkedListItem, priority: Priority) {
+        let ring_item = STATE[self.id as usize].ring_item.0.get();
+        item.link(ring_item);
+        ring_item.write(item);
+        self.start_linked_list(&*ring_item, priority, true, false);
+    }
+}
+
+struct DmaCtrlImpl<'a>(Peri<'a, AnyChannel>, WordSize);
+
+impl<'a> DmaCtrl for DmaCtrlImpl<'a> {
+    fn get_remaining_transfers(&self) -> usize {
+        let info = self.0.info();
+        let ch = info.dma.ch(info.num);
+        // BNDT is specified as bytes, not as number of transfers.
+        ch.br1().read().bndt() as usize / self.1.bytes()
+    }
+
+    fn reset_complete_count(&mut self) -> usize {
+        let state = &STATE[self.0.id as usize];
+        // Ring buffers have no way to report transfer errors.
+        if let Err(e) = state.error.get() {
+            panic!("DMA: transfer error {:?} on channel {}", e, self.0.id);
+        }
+        state.complete_count.swap(0, Ordering::AcqRel)
+    }
+
+    fn set_waker(&mut self, waker: &Waker) {
+        STATE[self.0.id as usize].waker.register(waker);
+    }
+}
+
+/// Ringbuffer for receiving data using a circular GPDMA linked list.
+///
+/// The DMA continuously copies the peripheral data register to the buffer, which can be attached
+/// to any peripheral generating DMA requests, for example to write a streaming driver. Data is
+/// taken out with [`read`](Self::read), [`read_exact`](Self::read_exact) or
+/// [`wait_readable`](Self::wait_readable). Data which is not read before the DMA wraps around is
+/// lost, and reported as [`RingBufferError::Overrun`](super::RingBufferError::Overrun).
+pub struct ReadableRingBuffer<'a, W: Word> {
+    channel: Peri<'a, AnyChannel>,
+    /// DMA doesn't run in stop mode.
+    #[cfg(feature = "low-power")]
+    _stop_limit: crate::low_power::StopModeLimit,
+    ringbuf: ReadableDmaRingBuffer<'a, W>,
+}
+
+impl<'a, W: Word> ReadableRingBuffer<'a, W> {
+    /// Create a new ring buffer.
+    pub unsafe fn new(
+        channel: Peri<'a, impl Channel>,
+        request: Request,
+        peri_addr: *mut W,
+        buffer: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        let channel: Peri<'a, AnyChannel> = channel.into();
+        channel.configure_ring(
+            LinkedListItem::new_read(request, peri_addr, buffer, options),
+            options.priority,
+        );
+
+        Self {
+            channel,
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
+            ringbuf: ReadableDmaRingBuffer::new(buffer),
+        }
+    }
+
+    /// Start the ring buffer operation.
+    ///
+    /// You must call this after creating it for it to work.
+    pub fn start(&mut self) {
+        self.channel.resume();
+    }
+
+    /// Clear all data in the ring buffer.
+    pub fn clear(&mut self) {
+        self.ringbuf.reset(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()));
+    }
+
+    /// Read elements from the ring buffer
+    /// Return a tuple of the length read and the length remaining in the buffer
+    /// If not all of the elements were read, then there will be some elements in the buffer remaining
+    /// The length remaining is the capacity, ring_buf.len(), less the elements remaining after the read
+    /// Error is returned if the portion to be read was overwritten by the DMA controller.
+    pub fn read(&mut self, buf: &mut [W]) -> Result<(usize, usize), Error> {
+        self.ringbuf
+            .read(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()), buf)
+    }
+
+    /// Read an exact number of elements from the ringbuffer.
+    ///
+    /// Returns the remaining number of elements available for immediate reading.
+    /// Error is returned if the portion to be read was overwritten by the DMA controller.
+    pub async fn read_exact(&mut self, buffer: &mut [W]) -> Result<usize, Error> {
+        self.ringbuf
+            .read_exact(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()), buffer)
+            .await
+    }
+
+    /// Wait until data is available for reading, and return how many elements can be read.
+    ///
+    /// Error is returned if the DMA controller overwrote unread data.
+    pub async fn wait_readable(&mut self) -> Result<usize, Error> {
+        self.ringbuf
+            .wait_readable(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()))
+            .await
+    }
+
+    /// The current length of the ringbuffer
+    pub fn len(&mut self) -> Result<usize, Error> {
+        Ok(self.ringbuf.len(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()))?)
+    }
+
+    /// The capacity of the ringbuffer
+    pub const fn capacity(&self) -> usize {
+        self.ringbuf.cap()
+    }
+
+    /// Set a waker to be woken when at least one byte is received.
+    pub fn set_waker(&mut self, waker: &Waker) {
+        DmaCtrlImpl(self.channel.reborrow(), W::size()).set_waker(waker);
+    }
+
+    /// Request the DMA to stop.
+    ///
+    /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
+    pub fn request_stop(&mut self) {
+        self.channel.request_suspend()
+    }
+
+    /// Request the transfer to pause, keeping the existing configuration for this channel.
+    /// To restart the transfer, call [`start`](Self::start) again.
+    ///
+    /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
+    pub fn request_pause(&mut self) {
+        self.channel.request_suspend()
+    }
+
+    /// Return whether DMA is still running.
+    pub fn is_running(&mut self) -> bool {
+        self.channel.is_running()
+    }
+
+    /// Stop the DMA transfer and await until it is stopped.
+    pub async fn stop(&mut self) {
+        self.request_stop();
+        poll_fn(|cx| {
+            self.set_waker(cx.waker());
+            if self.is_running() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+}
+
+impl<'a, W: Word> Drop for ReadableRingBuffer<'a, W> {
+    fn drop(&mut self) {
+        self.request_stop();
+        while self.is_running() {}
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+    }
+}
+
+/// Ringbuffer for writing data using a circular GPDMA linked list.
+///
+/// The DMA continuously copies the buffer to the peripheral data register, which can be attached
+/// to any peripheral generating DMA requests. Data is put in with [`write`](Self::write) or
+/// [`write_exact`](Self::write_exact), and [`wait_writable`](Self::wait_writable) waits for free
+/// space. If data is not written before the DMA wraps around, stale data is sent, and reported as
+/// [`RingBufferError::Overrun`](super::RingBufferError::Overrun).
+pub struct WritableRingBuffer<'a, W: Word> {
+    channel: Peri<'a, AnyChannel>,
+    /// DMA doesn't run in stop mode.
+    #[cfg(feature = "low-power")]
+    _stop_limit: crate::low_power::StopModeLimit,
+    ringbuf: WritableDmaRingBuffer<'a, W>,
+}
+
+impl<'a, W: Word> WritableRingBuffer<'a, W> {
+    /// Create a new ring buffer.
+    pub unsafe fn new(
+        channel: Peri<'a, impl Channel>,
+        request: Request,
+        peri_addr: *mut W,
+        buffer: &'a mut [W],
+        options: TransferOptions,
+    ) -> Self {
+        let channel: Peri<'a, AnyChannel> = channel.into();
+        channel.configure_ring(
+            LinkedListItem::new_write(request, buffer, peri_addr, options),
+            options.priority,
+        );
+
+        Self {
+            channel,
+            #[cfg(feature = "low-power")]
+            _stop_limit: crate::low_power::StopModeLimit::no_stop(),
+            ringbuf: WritableDmaRingBuffer::new(buffer),
         }
     }
+
+    /// Start the ring buffer operation.
+    ///
+    /// You must call this after creating it for it to work.
+    pub fn start(&mut self) {
+        self.channel.resume();
+    }
+
+    /// Clear all data in the ring buffer.
+    pub fn clear(&mut self) {
+        self.ringbuf.reset(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()));
+    }
+
+    /// Write elements directly to the raw buffer.
+    /// This can be used to fill the buffer before starting the DMA transfer.
+    pub fn write_immediate(&mut self, buf: &[W]) -> Result<(usize, usize), Error> {
+        self.ringbuf.write_immediate(buf)
+    }
+
+    /// Write elements from the ring buffer
+    /// Return a tuple of the length written and the length remaining in the buffer
+    pub fn write(&mut self, buf: &[W]) -> Result<(usize, usize), Error> {
+        self.ringbuf
+            .write(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()), buf)
+    }
+
+    /// Write an exact number of elements to the ringbuffer.
+    pub async fn write_exact(&mut self, buffer: &[W]) -> Result<usize, Error> {
+        self.ringbuf
+            .write_exact(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()), buffer)
+            .await
+    }
+
+    /// Wait for any ring buffer write error.
+    pub async fn wait_write_error(&mut self) -> Result<usize, Error> {
+        self.ringbuf
+            .wait_write_error(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()))
+            .await
+    }
+
+    /// Wait until space is available for writing, and return how many elements can be written.
+    ///
+    /// Error is returned if the DMA controller read data which was not written.
+    pub async fn wait_writable(&mut self) -> Result<usize, Error> {
+        self.ringbuf
+            .wait_writable(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()))
+            .await
+    }
+
+    /// The current length of the ringbuffer
+    pub fn len(&mut self) -> Result<usize, Error> {
+        Ok(self.ringbuf.len(&mut DmaCtrlImpl(self.channel.reborrow(), W::size()))?)
+    }
+
+    /// The capacity of the ringbuffer
+    pub const fn capacity(&self) -> usize {
+        self.ringbuf.cap()
+    }
+
+    /// Set a waker to be woken when at least one byte is sent.
+    pub fn set_waker(&mut self, waker: &Waker) {
+        DmaCtrlImpl(self.channel.reborrow(), W::size()).set_waker(waker);
+    }
+
+    /// Request the DMA to stop.
+    ///
+    /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
+    pub fn request_stop(&mut self) {
+        self.channel.request_suspend()
+    }
+
+    /// Request the transfer to pause, keeping the existing configuration for this channel.
+    /// To restart the transfer, call [`start`](Self::start) again.
+    ///
+    /// This doesn't immediately stop the transfer, you have to wait until [`is_running`](Self::is_running) returns false.
+    pub fn request_pause(&mut self) {
+        self.channel.request_suspend()
+    }
+
+    /// Return whether DMA is still running.
+    pub fn is_running(&mut self) -> bool {
+        self.channel.is_running()
+    }
+
+    /// Stop the DMA transfer and await until it is stopped.
+    pub async fn stop(&mut self) {
+        self.request_stop();
+        poll_fn(|cx| {
+            self.set_waker(cx.waker());
+            if self.is_running() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+}
+
+impl<'a, W: Word> Drop for WritableRingBuffer<'a, W> {
+    fn drop(&mut self) {
+        self.request_stop();
+        while self.is_running() {}
+
+        // "Subsequent reads and writes cannot be moved ahead of preceding reads."
+        fence(Ordering::SeqCst);
+    }
 }
@@ -20,8 +20,11 @@ mod util;
 pub(crate) use util::*;
 
 pub(crate) mod ringbuffer;
+pub use ringbuffer::Error as RingBufferError;
 pub mod word;
 
+use core::sync::atomic::{AtomicU8, Ordering};
+
 use embassy_hal_internal::{impl_peripheral, PeripheralType};
 
 use crate::interrupt;
@@ -33,6 +36,59 @@ enum Dir {
     PeripheralToMemory,
 }
 
+/// DMA transfer error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
+pub enum TransferError {
+    /// Bus error while accessing the memory or the peripheral, for example at an invalid
+    /// address. The channel is stopped.
+    Bus,
+    /// Direct mode error: a peripheral request came before the previous data was transferred,
+    /// so data was lost (DMA in direct mode only).
+    DirectMode,
+    /// Invalid channel configuration (GPDMA only). The channel is stopped.
+    UserSetting,
+}
+
+impl core::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let message = match self {
+            Self::Bus => "Bus Error",
+            Self::DirectMode => "Direct Mode Error",
+            Self::UserSetting => "User Setting Error",
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl core::error::Error for TransferError {}
+
+/// Error of a channel, recorded by the interrupt handler until the transfer completes.
+struct ErrorState(AtomicU8);
+
+impl ErrorState {
+    const NEW: Self = Self(AtomicU8::new(0));
+
+    fn set(&self, error: TransferError) {
+        self.0.store(error as u8 + 1, Ordering::Release);
+    }
+
+    fn clear(&self) {
+        self.0.store(0, Ordering::Release);
+    }
+
+    fn get(&self) -> Result<(), TransferError> {
+        match self.0.load(Ordering::Acquire) {
+            0 => Ok(()),
+            1 => Err(TransferError::Bus),
+            2 => Err(TransferError::DirectMode),
+            _ => Err(TransferError::UserSetting),
+        }
+    }
+}
+
 /// DMA request type alias. (also known as DMA channel number in some chips)
 #[cfg(any(dma_v2, bdma_v2, gpdma, dmamux))]
 pub type Request = u8;
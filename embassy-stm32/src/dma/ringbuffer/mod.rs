@@ -1,5 +1,3 @@
-#![cfg_attr(gpdma, allow(unused))]
-
 use core::future::poll_fn;
 use core::task::{Poll, Waker};
 
@@ -17,9 +15,12 @@ pub trait DmaCtrl {
     fn set_waker(&mut self, waker: &Waker);
 }
 
-#[derive(Debug, PartialEq)]
+/// Ring buffer error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum Error {
+    /// The DMA overwrote data before it was read, or read data before it was written. The ring
+    /// buffer was reset.
     Overrun,
     /// the newly read DMA positions don't make sense compared to the previous
     /// ones. This can usually only occur due to wrong Driver implementation, if
@@ -28,6 +29,19 @@ pub enum Error {
     DmaUnsynced,
 }
 
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let message = match self {
+            Self::Overrun => "Ring Buffer Overrun",
+            Self::DmaUnsynced => "DMA Position Out of Sync",
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl core::error::Error for Error {}
+
 #[derive(Debug, Clone, Copy, Default)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 struct DmaIndex {
@@ -169,6 +183,27 @@ impl<'a, W: Word> ReadableDmaRingBuffer<'a, W> {
         .await
     }
 
+    /// Wait until elements are available for reading, and return how many.
+    ///
+    /// This wakes with the same granularity as [`read_exact`](Self::read_exact). Error is returned
+    /// if the DMA controller overwrote unread elements, in which case the ringbuffer will
+    /// automatically reset itself.
+    pub async fn wait_readable(&mut self, dma: &mut impl DmaCtrl) -> Result<usize, Error> {
+        poll_fn(|cx| {
+            dma.set_waker(cx.waker());
+
+            match self.len(dma) {
+                Ok(0) => Poll::Pending,
+                Ok(len) => Poll::Ready(Ok(len)),
+                Err(e) => {
+                    self.reset(dma);
+                    Poll::Ready(Err(e))
+                }
+            }
+        })
+        .await
+    }
+
     fn read_raw(&mut self, dma: &mut impl DmaCtrl, buf: &mut [W]) -> Result<(usize, usize), Error> {
         let readable = self.len(dma)?.min(buf.len());
         for i in 0..readable {
@@ -307,6 +342,26 @@ impl<'a, W: Word> WritableDmaRingBuffer<'a, W> {
         .await
     }
 
+    /// Wait until space is available for writing, and return how many elements fit.
+    ///
+    /// Error is returned if the DMA controller read elements which were not written, in which case
+    /// the ringbuffer will automatically reset itself.
+    pub async fn wait_writable(&mut self, dma: &mut impl DmaCtrl) -> Result<usize, Error> {
+        poll_fn(|cx| {
+            dma.set_waker(cx.waker());
+
+            match self.len(dma) {
+                Ok(0) => Poll::Pending,
+                Ok(len) => Poll::Ready(Ok(len)),
+                Err(e) => {
+                    self.reset(dma);
+                    Poll::Ready(Err(e))
+                }
+            }
+        })
+        .await
+    }
+
     fn write_raw(&mut self, dma: &mut impl DmaCtrl, buf: &[W]) -> Result<(usize, usize), Error> {
         let writable = self.len(dma)?.min(buf.len());
         for i in 0..writable {
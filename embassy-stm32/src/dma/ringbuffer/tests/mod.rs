@@ -87,4 +87,30 @@ fn dma_index_advancing_increases_as_index() {
     assert_eq!(index.as_index(CAP, 0), 1);
 }
 
+#[test]
+fn wait_readable_returns_available_len() {
+    let mut dma = TestCircularTransfer::new(CAP);
+    let mut dma_buf = [0u8; CAP];
+    let mut ringbuf = ReadableDmaRingBuffer::new(&mut dma_buf);
+
+    dma.setup(vec![
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(4),
+    ]);
+    assert_eq!(embassy_futures::block_on(ringbuf.wait_readable(&mut dma)), Ok(4));
+}
+
+#[test]
+fn wait_writable_returns_free_len() {
+    let mut dma = TestCircularTransfer::new(CAP);
+    let mut dma_buf = [0u8; CAP];
+    let mut ringbuf = WritableDmaRingBuffer::new(&mut dma_buf);
+
+    dma.setup(vec![
+        TestCircularTransferRequest::ResetCompleteCount(0),
+        TestCircularTransferRequest::PositionRequest(4),
+    ]);
+    assert_eq!(embassy_futures::block_on(ringbuf.wait_writable(&mut dma)), Ok(4));
+}
+
 mod prop_test;
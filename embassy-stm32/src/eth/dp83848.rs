@@ -0,0 +1,92 @@
+//! Texas Instruments DP83848 Ethernet PHY
+
+use core::task::Context;
+
+#[cfg(feature = "time")]
+use embassy_time::Duration;
+
+use super::{Duplex, GenericPhy, LinkSpeed, Phy, StationManagement};
+
+/// PHY status register
+const PHY_REG_PHYSTS: u8 = 0x10;
+const PHY_REG_PHYSTS_SPEED_10M: u16 = 1 << 1;
+const PHY_REG_PHYSTS_FULL_DUPLEX: u16 = 1 << 2;
+
+/// Texas Instruments DP83848 Ethernet PHY, as found on the STM3220G/STM3240G evaluation boards.
+pub struct Dp83848 {
+    phy: GenericPhy,
+    link: Option<(LinkSpeed, Duplex)>,
+}
+
+impl Dp83848 {
+    /// Construct the PHY at the SMI address `phy_addr`.
+    ///
+    /// # Panics
+    /// `phy_addr` must be in range `0..32`
+    pub fn new(phy_addr: u8) -> Self {
+        Self {
+            phy: GenericPhy::new(phy_addr),
+            link: None,
+        }
+    }
+
+    /// Construct the PHY, probing all SMI addresses during initialization.
+    ///
+    /// # Panics
+    /// Initialization panics if PHY didn't respond on any address
+    pub fn new_auto() -> Self {
+        Self {
+            phy: GenericPhy::new_auto(),
+            link: None,
+        }
+    }
+
+    /// Set the SMI polling interval.
+    #[cfg(feature = "time")]
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.phy.set_poll_interval(poll_interval)
+    }
+
+    /// Get the SMI address of the PHY.
+    pub fn phy_addr(&self) -> u8 {
+        self.phy.phy_addr()
+    }
+
+    /// Speed and duplex mode negotiated at the last link poll, or `None` if the link is down.
+    pub fn link(&self) -> Option<(LinkSpeed, Duplex)> {
+        self.link
+    }
+}
+
+impl Phy for Dp83848 {
+    fn phy_reset<S: StationManagement>(&mut self, sm: &mut S) {
+        self.phy.phy_reset(sm);
+    }
+
+    fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
+        self.phy.enable_autonegotiation(sm);
+    }
+
+    fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> bool {
+        self.link = None;
+        if !self.phy.poll_link(sm, cx) {
+            return false;
+        }
+
+        let physts = sm.smi_read(self.phy.phy_addr(), PHY_REG_PHYSTS);
+        let speed = match physts & PHY_REG_PHYSTS_SPEED_10M {
+            0 => LinkSpeed::Mbps100,
+            _ => LinkSpeed::Mbps10,
+        };
+        let duplex = match physts & PHY_REG_PHYSTS_FULL_DUPLEX {
+            0 => Duplex::Half,
+            _ => Duplex::Full,
+        };
+        self.link = Some((speed, duplex));
+        true
+    }
+
+    fn link_mode(&self) -> Option<(LinkSpeed, Duplex)> {
+        self.link
+    }
+}
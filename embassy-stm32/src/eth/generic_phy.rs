@@ -114,14 +114,10 @@ impl Phy for GenericPhy {
 
     fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
         // Clear WU CSR
-        self.smi_write_ext(sm, PHY_REG_WUCSR, 0);
+        sm.smi_write_mmd(self.phy_addr, 3, PHY_REG_WUCSR, 0);
 
         // Enable auto-negotiation
-        sm.smi_write(
-            self.phy_addr,
-            PHY_REG_BCR,
-            PHY_REG_BCR_AN | PHY_REG_BCR_ANRST | PHY_REG_BCR_100M,
-        );
+        self.enable_autonegotiation(sm);
     }
 
     fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> bool {
@@ -155,11 +151,19 @@ impl GenericPhy {
         self.poll_interval = poll_interval
     }
 
-    // Writes a value to an extended PHY register in MMD address space
-    fn smi_write_ext<S: StationManagement>(&mut self, sm: &mut S, reg_addr: u16, reg_data: u16) {
-        sm.smi_write(self.phy_addr, PHY_REG_CTL, 0x0003); // set address
-        sm.smi_write(self.phy_addr, PHY_REG_ADDAR, reg_addr);
-        sm.smi_write(self.phy_addr, PHY_REG_CTL, 0x4003); // set data
-        sm.smi_write(self.phy_addr, PHY_REG_ADDAR, reg_data);
+    /// Get the SMI address of the PHY.
+    ///
+    /// With [`GenericPhy::new_auto`], this is only valid once the PHY was reset.
+    pub fn phy_addr(&self) -> u8 {
+        self.phy_addr
+    }
+
+    /// Enable auto-negotiation, advertising all 10/100 Mbit/s modes.
+    pub(super) fn enable_autonegotiation<S: StationManagement>(&mut self, sm: &mut S) {
+        sm.smi_write(
+            self.phy_addr,
+            PHY_REG_BCR,
+            PHY_REG_BCR_AN | PHY_REG_BCR_ANRST | PHY_REG_BCR_100M,
+        );
     }
 }
@@ -0,0 +1,116 @@
+//! Microchip KSZ8081 Ethernet PHY
+
+use core::task::Context;
+
+#[cfg(feature = "time")]
+use embassy_time::Duration;
+
+use super::{Duplex, GenericPhy, LinkSpeed, Phy, StationManagement};
+
+/// PHY control 1 register
+const PHY_REG_CTRL1: u8 = 0x1E;
+const PHY_REG_CTRL1_MODE_MASK: u16 = 0b111;
+const PHY_REG_CTRL1_MODE_100M: u16 = 0b010;
+const PHY_REG_CTRL1_MODE_FULL_DUPLEX: u16 = 0b100;
+
+/// PHY control 2 register
+const PHY_REG_CTRL2: u8 = 0x1F;
+const PHY_REG_CTRL2_RMII_REF_CLK_SEL: u16 = 1 << 7;
+
+/// Microchip KSZ8081 Ethernet PHY.
+pub struct Ksz8081 {
+    phy: GenericPhy,
+    ref_clk_50mhz: bool,
+    link: Option<(LinkSpeed, Duplex)>,
+}
+
+impl Ksz8081 {
+    /// Construct the PHY at the SMI address `phy_addr`.
+    ///
+    /// # Panics
+    /// `phy_addr` must be in range `0..32`
+    pub fn new(phy_addr: u8) -> Self {
+        Self {
+            phy: GenericPhy::new(phy_addr),
+            ref_clk_50mhz: false,
+            link: None,
+        }
+    }
+
+    /// Construct the PHY, probing all SMI addresses during initialization.
+    ///
+    /// # Panics
+    /// Initialization panics if PHY didn't respond on any address
+    pub fn new_auto() -> Self {
+        Self {
+            phy: GenericPhy::new_auto(),
+            ref_clk_50mhz: false,
+            link: None,
+        }
+    }
+
+    /// Select the 50 MHz RMII reference clock mode instead of the 25 MHz crystal mode.
+    ///
+    /// Must be called before the PHY is initialized, i.e. before creating the `Ethernet` driver.
+    pub fn set_ref_clk_50mhz(&mut self, enabled: bool) {
+        self.ref_clk_50mhz = enabled;
+    }
+
+    /// Set the SMI polling interval.
+    #[cfg(feature = "time")]
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.phy.set_poll_interval(poll_interval)
+    }
+
+    /// Get the SMI address of the PHY.
+    pub fn phy_addr(&self) -> u8 {
+        self.phy.phy_addr()
+    }
+
+    /// Speed and duplex mode negotiated at the last link poll, or `None` if the link is down.
+    pub fn link(&self) -> Option<(LinkSpeed, Duplex)> {
+        self.link
+    }
+}
+
+impl Phy for Ksz8081 {
+    fn phy_reset<S: StationManagement>(&mut self, sm: &mut S) {
+        self.phy.phy_reset(sm);
+    }
+
+    fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
+        let addr = self.phy.phy_addr();
+        let ctrl2 = sm.smi_read(addr, PHY_REG_CTRL2);
+        let ctrl2 = if self.ref_clk_50mhz {
+            ctrl2 | PHY_REG_CTRL2_RMII_REF_CLK_SEL
+        } else {
+            ctrl2 & !PHY_REG_CTRL2_RMII_REF_CLK_SEL
+        };
+        sm.smi_write(addr, PHY_REG_CTRL2, ctrl2);
+
+        self.phy.enable_autonegotiation(sm);
+    }
+
+    fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> bool {
+        self.link = None;
+        if !self.phy.poll_link(sm, cx) {
+            return false;
+        }
+
+        let mode = sm.smi_read(self.phy.phy_addr(), PHY_REG_CTRL1) & PHY_REG_CTRL1_MODE_MASK;
+        let speed = match mode & PHY_REG_CTRL1_MODE_100M {
+            0 => LinkSpeed::Mbps10,
+            _ => LinkSpeed::Mbps100,
+        };
+        let duplex = match mode & PHY_REG_CTRL1_MODE_FULL_DUPLEX {
+            0 => Duplex::Half,
+            _ => Duplex::Full,
+        };
+        self.link = Some((speed, duplex));
+        true
+    }
+
+    fn link_mode(&self) -> Option<(LinkSpeed, Duplex)> {
+        self.link
+    }
+}
@@ -0,0 +1,98 @@
+//! Microchip LAN8742A Ethernet PHY
+
+use core::task::Context;
+
+#[cfg(feature = "time")]
+use embassy_time::Duration;
+
+use super::{Duplex, GenericPhy, LinkSpeed, Phy, StationManagement};
+
+/// PHY special control/status register
+const PHY_REG_PSCSR: u8 = 0x1F;
+const PHY_REG_PSCSR_SPEED_100M: u16 = 1 << 3;
+const PHY_REG_PSCSR_FULL_DUPLEX: u16 = 1 << 4;
+
+/// Wake-up control and status register, in MMD 3
+const PHY_REG_WUCSR: u16 = 0x8010;
+
+/// Microchip LAN8742A Ethernet PHY, as found on the ST Nucleo-144 boards.
+pub struct Lan8742 {
+    phy: GenericPhy,
+    link: Option<(LinkSpeed, Duplex)>,
+}
+
+impl Lan8742 {
+    /// Construct the PHY at the SMI address `phy_addr`.
+    ///
+    /// # Panics
+    /// `phy_addr` must be in range `0..32`
+    pub fn new(phy_addr: u8) -> Self {
+        Self {
+            phy: GenericPhy::new(phy_addr),
+            link: None,
+        }
+    }
+
+    /// Construct the PHY, probing all SMI addresses during initialization.
+    ///
+    /// # Panics
+    /// Initialization panics if PHY didn't respond on any address
+    pub fn new_auto() -> Self {
+        Self {
+            phy: GenericPhy::new_auto(),
+            link: None,
+        }
+    }
+
+    /// Set the SMI polling interval.
+    #[cfg(feature = "time")]
+    pub fn set_poll_interval(&mut self, poll_interval: Duration) {
+        self.phy.set_poll_interval(poll_interval)
+    }
+
+    /// Get the SMI address of the PHY.
+    pub fn phy_addr(&self) -> u8 {
+        self.phy.phy_addr()
+    }
+
+    /// Speed and duplex mode negotiated at the last link poll, or `None` if the link is down.
+    pub fn link(&self) -> Option<(LinkSpeed, Duplex)> {
+        self.link
+    }
+}
+
+impl Phy for Lan8742 {
+    fn phy_reset<S: StationManagement>(&mut self, sm: &mut S) {
+        self.phy.phy_reset(sm);
+    }
+
+    fn phy_init<S: StationManagement>(&mut self, sm: &mut S) {
+        // Clear the wake-up flags, which survive a soft reset.
+        sm.smi_write_mmd(self.phy.phy_addr(), 3, PHY_REG_WUCSR, 0);
+
+        self.phy.enable_autonegotiation(sm);
+    }
+
+    fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> bool {
+        self.link = None;
+        if !self.phy.poll_link(sm, cx) {
+            return false;
+        }
+
+        let pscsr = sm.smi_read(self.phy.phy_addr(), PHY_REG_PSCSR);
+        let speed = match pscsr & PHY_REG_PSCSR_SPEED_100M {
+            0 => LinkSpeed::Mbps10,
+            _ => LinkSpeed::Mbps100,
+        };
+        let duplex = match pscsr & PHY_REG_PSCSR_FULL_DUPLEX {
+            0 => Duplex::Half,
+            _ => Duplex::Full,
+        };
+        self.link = Some((speed, duplex));
+        true
+    }
+
+    fn link_mode(&self) -> Option<(LinkSpeed, Duplex)> {
+        self.link
+    }
+}
@@ -4,28 +4,334 @@
 #[cfg_attr(any(eth_v1a, eth_v1b, eth_v1c), path = "v1/mod.rs")]
 #[cfg_attr(eth_v2, path = "v2/mod.rs")]
 mod _version;
+mod dp83848;
 mod generic_phy;
+mod ksz8081;
+mod lan8742;
 
+use core::marker::PhantomData;
 use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicU8, Ordering};
 use core::task::Context;
 
 use embassy_hal_internal::PeripheralType;
-use embassy_net_driver::{Capabilities, HardwareAddress, LinkState};
+pub use embassy_net_driver::Timestamp;
+use embassy_net_driver::{Capabilities, Checksum, HardwareAddress, LinkState};
 use embassy_sync::waitqueue::AtomicWaker;
 
 pub use self::_version::{InterruptHandler, *};
+pub use self::dp83848::*;
 pub use self::generic_phy::*;
+pub use self::ksz8081::*;
+pub use self::lan8742::*;
 use crate::rcc::RccPeripheral;
 
-#[allow(unused)]
-const MTU: usize = 1514;
-const TX_BUFFER_SIZE: usize = 1514;
-const RX_BUFFER_SIZE: usize = 1536;
+/// Default size of a transmit buffer, for a 1514 bytes MTU.
+pub const TX_BUFFER_SIZE: usize = 1514;
+/// Default size of a receive buffer, large enough for a 1514 bytes MTU and the frame CRC.
+pub const RX_BUFFER_SIZE: usize = 1536;
+
+/// Largest buffer a descriptor can point to.
+#[cfg(any(eth_v1a, eth_v1b, eth_v1c))]
+const MAX_BUFFER_SIZE: usize = 0x0FFF;
+#[cfg(eth_v2)]
+const MAX_BUFFER_SIZE: usize = 0x3FFF;
+
+/// Checksum offload to the MAC.
+///
+/// Covers the IPv4 header checksum and the TCP, UDP and ICMP checksums over IPv4 and IPv6.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChecksumOffload {
+    /// Insert the checksums in sent frames.
+    pub tx: bool,
+    /// Verify the checksums of received frames, and drop those with errors.
+    pub rx: bool,
+}
+
+impl ChecksumOffload {
+    /// Checksum behavior left to the network stack.
+    fn stack_checksum(&self) -> Checksum {
+        match (self.tx, self.rx) {
+            (false, false) => Checksum::Both,
+            (false, true) => Checksum::Tx,
+            (true, false) => Checksum::Rx,
+            (true, true) => Checksum::None,
+        }
+    }
+}
+
+/// Compute the PTP sub-second increment in nanoseconds and the nominal addend for `hclk`.
+///
+/// The 32-bit accumulator is fed with HCLK and increments the clock when it overflows, which
+/// must happen at most at half the HCLK frequency.
+fn ptp_increment(hclk: u32) -> (u8, u32) {
+    let increment = 2_000_000_000u32.div_ceil(hclk);
+    assert!(increment <= 0xFF, "HCLK too low for the PTP clock");
+    let addend = (((1_000_000_000 / increment) as u64) << 32) / hclk as u64;
+    (increment as u8, addend as u32)
+}
+
+/// Scale the nominal PTP addend by `ppb` parts per billion.
+fn ptp_scale_addend(addend: u32, ppb: i32) -> u32 {
+    let adjusted = addend as i64 + (addend as i64 * ppb as i64) / 1_000_000_000;
+    adjusted.clamp(0, u32::MAX as i64) as u32
+}
+
+/// Handle to the IEEE 1588 (PTP) clock of the MAC, returned by [`Ethernet::enable_ptp`].
+///
+/// The handle can be copied, and stays usable once the [`Ethernet`] driver is owned by the
+/// network stack, so a PTP servo can read and correct the clock.
+#[derive(Clone, Copy)]
+pub struct PtpClock<'d, T: Instance> {
+    /// Nominal addend, for the clock running from HCLK.
+    addend: u32,
+    _peri: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> PtpClock<'d, T> {
+    fn new(addend: u32) -> Self {
+        Self {
+            addend,
+            _peri: PhantomData,
+        }
+    }
+}
+
+/// Multicast addresses accepted by the MAC, in the perfect filter registers or the hash table.
+pub(crate) struct MulticastFilter {
+    perfect: [Option<[u8; 6]>; 3],
+    /// Number of addresses hashed to each bit of the hash table.
+    hashed: [u8; 64],
+}
+
+impl MulticastFilter {
+    pub(crate) const fn new() -> Self {
+        Self {
+            perfect: [None; 3],
+            hashed: [0; 64],
+        }
+    }
+
+    fn add(&mut self, addr: [u8; 6]) {
+        match self.perfect.iter_mut().find(|slot| slot.is_none()) {
+            Some(slot) => *slot = Some(addr),
+            None => {
+                let bit = &mut self.hashed[multicast_hash(&addr)];
+                *bit = bit.saturating_add(1);
+            }
+        }
+    }
+
+    fn remove(&mut self, addr: [u8; 6]) {
+        match self.perfect.iter_mut().find(|slot| **slot == Some(addr)) {
+            Some(slot) => *slot = None,
+            None => {
+                let bit = &mut self.hashed[multicast_hash(&addr)];
+                *bit = bit.saturating_sub(1);
+            }
+        }
+    }
+
+    /// True if no address was added, all multicast frames are then accepted.
+    pub(crate) fn pass_all(&self) -> bool {
+        self.perfect.iter().all(Option::is_none) && self.hashed.iter().all(|&n| n == 0)
+    }
+
+    /// Values of the high and low registers of MAC addresses 1 to 3.
+    pub(crate) fn address_registers(&self) -> [(u32, u32); 3] {
+        self.perfect.map(|addr| match addr {
+            // Address enable, with the address in the same layout as MAC address 0.
+            Some(a) => (
+                1 << 31 | u32::from(a[4]) | (u32::from(a[5]) << 8),
+                u32::from_le_bytes([a[0], a[1], a[2], a[3]]),
+            ),
+            None => (0, 0),
+        })
+    }
+
+    /// Value of the 64-bit hash table.
+    pub(crate) fn hash_table(&self) -> u64 {
+        let mut table = 0;
+        for (i, &n) in self.hashed.iter().enumerate() {
+            if n != 0 {
+                table |= 1 << i;
+            }
+        }
+        table
+    }
+}
+
+/// Index in the multicast hash table: the upper 6 bits of the bit-reversed Ethernet CRC.
+fn multicast_hash(addr: &[u8; 6]) -> usize {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in addr {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    ((!crc).reverse_bits() >> 26) as usize
+}
+
+/// Number of remote wakeup frame filters of the power management block.
+pub const WAKEUP_FILTER_COUNT: usize = 4;
+
+/// Remote wakeup frame filter of the power management block.
+///
+/// A frame matches when the CRC-16 of the bytes selected by `mask`, counted from `offset` bytes
+/// into the frame, equals `crc`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeupFilter {
+    /// Offset of the first byte compared. Must be at least 12, to skip the MAC addresses.
+    pub offset: u8,
+    /// Bytes compared, bit `i` selects byte `offset + i`. Bit 31 must be clear.
+    pub mask: u32,
+    /// CRC-16 of the selected bytes.
+    pub crc: u16,
+    /// Only match multicast frames.
+    pub multicast: bool,
+}
+
+impl WakeupFilter {
+    /// Create a filter matching frames whose bytes selected by `mask` are equal to `pattern`.
+    ///
+    /// `pattern[i]` is compared with byte `offset + i` of the frame when bit `i` of `mask` is set.
+    pub fn new(offset: u8, pattern: &[u8], mask: u32, multicast: bool) -> Self {
+        assert!(offset >= 12);
+        assert!(mask & (1 << 31) == 0);
+        assert!(32 - mask.leading_zeros() as usize <= pattern.len());
+
+        // CRC-16 with polynomial x^16 + x^15 + x^2 + 1, bits processed LSB first.
+        let mut crc = 0xFFFFu16;
+        for (i, &byte) in pattern.iter().enumerate().take(31) {
+            if mask & (1 << i) == 0 {
+                continue;
+            }
+            crc ^= byte as u16;
+            for _ in 0..8 {
+                crc = if crc & 1 != 0 { (crc >> 1) ^ 0xA001 } else { crc >> 1 };
+            }
+        }
+
+        Self {
+            offset,
+            mask,
+            crc,
+            multicast,
+        }
+    }
+}
+
+/// Values to write, in order, to the remote wakeup frame filter register.
+fn wakeup_filter_registers(filters: &[WakeupFilter]) -> [u32; 8] {
+    assert!(filters.len() <= WAKEUP_FILTER_COUNT);
+
+    let mut regs = [0; 8];
+    for (i, f) in filters.iter().enumerate() {
+        regs[i] = f.mask;
+        // Filter enable and address type, one byte per filter.
+        regs[4] |= (1 | ((f.multicast as u32) << 3)) << (8 * i);
+        regs[5] |= (f.offset as u32) << (8 * i);
+        regs[6 + i / 2] |= (f.crc as u32) << (16 * (i % 2));
+    }
+    regs
+}
+
+/// Wake-on-LAN configuration for [`PowerManagement::power_down`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeupConfig<'a> {
+    /// Wake up on magic packets addressed to the MAC address.
+    pub magic_packet: bool,
+    /// Wake up on frames matching one of these filters, at most [`WAKEUP_FILTER_COUNT`].
+    pub filters: &'a [WakeupFilter],
+    /// Check the filters against all unicast frames, not only those addressed to the MAC address.
+    pub global_unicast: bool,
+}
+
+/// Events that woke the MAC from power-down mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct WakeupEvents {
+    /// A magic packet was received.
+    pub magic_packet: bool,
+    /// A frame matching a wakeup filter was received.
+    pub wakeup_frame: bool,
+}
+
+/// Power management events latched by the interrupt handler, see [`WakeupEvents`].
+static PMT_EVENTS: AtomicU8 = AtomicU8::new(0);
+const PMT_MAGIC_PACKET: u8 = 1 << 0;
+const PMT_WAKEUP_FRAME: u8 = 1 << 1;
+
+/// Take the power management events latched since the last call.
+fn take_wakeup_events() -> WakeupEvents {
+    let events = PMT_EVENTS.swap(0, Ordering::Relaxed);
+    WakeupEvents {
+        magic_packet: events & PMT_MAGIC_PACKET != 0,
+        wakeup_frame: events & PMT_WAKEUP_FRAME != 0,
+    }
+}
+
+/// Handle to the Wake-on-LAN power management of the MAC, returned by
+/// [`Ethernet::power_management`].
+///
+/// The handle can be copied, and stays usable once the [`Ethernet`] driver is owned by the
+/// network stack.
+#[derive(Clone, Copy)]
+pub struct PowerManagement<'d, T: Instance> {
+    _peri: PhantomData<&'d T>,
+}
 
 #[repr(C, align(8))]
 #[derive(Copy, Clone)]
 pub(crate) struct Packet<const N: usize>([u8; N]);
 
+/// Packet buffers of a descriptor ring, type-erased over their size.
+pub(crate) struct PacketBuffers<'a> {
+    data: &'a mut [u8],
+    /// Size of a buffer.
+    size: usize,
+    /// Distance between two buffers, including the alignment padding.
+    stride: usize,
+}
+
+impl<'a> PacketBuffers<'a> {
+    pub(crate) fn new<const N: usize>(packets: &'a mut [Packet<N>]) -> Self {
+        let stride = core::mem::size_of::<Packet<N>>();
+        let len = packets.len() * stride;
+        Self {
+            data: unsafe { core::slice::from_raw_parts_mut(packets.as_mut_ptr() as *mut u8, len) },
+            size: N,
+            stride,
+        }
+    }
+
+    /// Number of buffers.
+    pub(crate) fn len(&self) -> usize {
+        self.data.len() / self.stride
+    }
+
+    /// Size of a buffer.
+    pub(crate) fn size(&self) -> usize {
+        self.size
+    }
+
+    pub(crate) fn get(&mut self, index: usize) -> &mut [u8] {
+        &mut self.data[index * self.stride..][..self.size]
+    }
+
+    pub(crate) fn as_mut_ptr(&mut self, index: usize) -> *mut u8 {
+        self.get(index).as_mut_ptr()
+    }
+}
+
 /// Ethernet packet queue.
 ///
 /// This struct owns the memory used for reading and writing packets.
@@ -34,21 +340,33 @@ pub(crate) struct Packet<const N: usize>([u8; N]);
 /// queue. A bigger queue allows the hardware to receive more packets while the
 /// CPU is busy doing other things, which may increase performance (especially for RX)
 /// at the cost of more RAM usage.
-pub struct PacketQueue<const TX: usize, const RX: usize> {
+///
+/// `TX_BUF` and `RX_BUF` are the sizes of the transmit and receive buffers. The MTU is the
+/// smaller of `TX_BUF` and `RX_BUF - 4`, as received frames include the CRC. Smaller buffers
+/// save RAM on networks known to only carry small frames; larger frames are then dropped.
+/// `RX_BUF` must be a multiple of 8.
+pub struct PacketQueue<
+    const TX: usize,
+    const RX: usize,
+    const TX_BUF: usize = TX_BUFFER_SIZE,
+    const RX_BUF: usize = RX_BUFFER_SIZE,
+> {
     tx_desc: [TDes; TX],
     rx_desc: [RDes; RX],
-    tx_buf: [Packet<TX_BUFFER_SIZE>; TX],
-    rx_buf: [Packet<RX_BUFFER_SIZE>; RX],
+    tx_buf: [Packet<TX_BUF>; TX],
+    rx_buf: [Packet<RX_BUF>; RX],
 }
 
-impl<const TX: usize, const RX: usize> PacketQueue<TX, RX> {
+impl<const TX: usize, const RX: usize, const TX_BUF: usize, const RX_BUF: usize> PacketQueue<TX, RX, TX_BUF, RX_BUF> {
     /// Create a new packet queue.
     pub const fn new() -> Self {
+        assert!(TX_BUF >= 64 && TX_BUF <= MAX_BUFFER_SIZE);
+        assert!(RX_BUF >= 64 && RX_BUF <= MAX_BUFFER_SIZE && RX_BUF % 8 == 0);
         Self {
             tx_desc: [const { TDes::new() }; TX],
             rx_desc: [const { RDes::new() }; RX],
-            tx_buf: [Packet([0; TX_BUFFER_SIZE]); TX],
-            rx_buf: [Packet([0; RX_BUFFER_SIZE]); RX],
+            tx_buf: [Packet([0; TX_BUF]); TX],
+            rx_buf: [Packet([0; RX_BUF]); RX],
         }
     }
 
@@ -86,7 +404,14 @@ impl<'d, T: Instance, P: Phy> embassy_net_driver::Driver for Ethernet<'d, T, P>
     fn receive(&mut self, cx: &mut Context) -> Option<(Self::RxToken<'_>, Self::TxToken<'_>)> {
         WAKER.register(cx.waker());
         if self.rx.available().is_some() && self.tx.available().is_some() {
-            Some((RxToken { rx: &mut self.rx }, TxToken { tx: &mut self.tx }))
+            Some((
+                RxToken { rx: &mut self.rx },
+                TxToken {
+                    tx: &mut self.tx,
+                    timestamp: false,
+                    vlan_tag: None,
+                },
+            ))
         } else {
             None
         }
@@ -95,7 +420,11 @@ impl<'d, T: Instance, P: Phy> embassy_net_driver::Driver for Ethernet<'d, T, P>
     fn transmit(&mut self, cx: &mut Context) -> Option<Self::TxToken<'_>> {
         WAKER.register(cx.waker());
         if self.tx.available().is_some() {
-            Some(TxToken { tx: &mut self.tx })
+            Some(TxToken {
+                tx: &mut self.tx,
+                timestamp: false,
+                vlan_tag: None,
+            })
         } else {
             None
         }
@@ -103,13 +432,27 @@ impl<'d, T: Instance, P: Phy> embassy_net_driver::Driver for Ethernet<'d, T, P>
 
     fn capabilities(&self) -> Capabilities {
         let mut caps = Capabilities::default();
-        caps.max_transmission_unit = MTU;
+        caps.max_transmission_unit = self.tx.buffer_size().min(self.rx.buffer_size() - 4);
         caps.max_burst_size = Some(self.tx.len());
+        let checksum = self.checksum_offload.stack_checksum();
+        caps.checksum.ipv4 = checksum;
+        caps.checksum.udp = checksum;
+        caps.checksum.tcp = checksum;
+        caps.checksum.icmpv4 = checksum;
+        caps.timestamping = self.ptp_addend != 0;
+        caps.vlan_tag_insertion = self.tx.vlan_tag_insertion;
         caps
     }
 
     fn link_state(&mut self, cx: &mut Context) -> LinkState {
-        if self.phy.poll_link(&mut self.station_management, cx) {
+        let up = self.phy.poll_link(&mut self.station_management, cx);
+        let mode = if up { self.phy.link_mode() } else { None };
+        if let Some((speed, duplex)) = mode {
+            self.set_link_mode(speed, duplex);
+        }
+        LINK_MODE.store(encode_link_mode(mode), Ordering::Relaxed);
+
+        if up {
             LinkState::Up
         } else {
             LinkState::Down
@@ -119,6 +462,21 @@ impl<'d, T: Instance, P: Phy> embassy_net_driver::Driver for Ethernet<'d, T, P>
     fn hardware_address(&self) -> HardwareAddress {
         HardwareAddress::Ethernet(self.mac_addr)
     }
+
+    fn tx_timestamp(&mut self, cx: &mut Context) -> Option<Timestamp> {
+        WAKER.register(cx.waker());
+        self.tx.timestamp()
+    }
+
+    fn add_multicast_address(&mut self, addr: [u8; 6]) {
+        self.multicast.add(addr);
+        self.apply_multicast_filter();
+    }
+
+    fn remove_multicast_address(&mut self, addr: [u8; 6]) {
+        self.multicast.remove(addr);
+        self.apply_multicast_filter();
+    }
 }
 
 /// `embassy-net` RX token.
@@ -137,11 +495,21 @@ impl<'a, 'd> embassy_net_driver::RxToken for RxToken<'a, 'd> {
         self.rx.pop_packet();
         r
     }
+
+    fn timestamp(&self) -> Option<Timestamp> {
+        self.rx.timestamp()
+    }
+
+    fn vlan_tag(&self) -> Option<u16> {
+        self.rx.vlan_tag()
+    }
 }
 
 /// `embassy-net` TX token.
 pub struct TxToken<'a, 'd> {
     tx: &'a mut TDesRing<'d>,
+    timestamp: bool,
+    vlan_tag: Option<u16>,
 }
 
 impl<'a, 'd> embassy_net_driver::TxToken for TxToken<'a, 'd> {
@@ -152,9 +520,17 @@ impl<'a, 'd> embassy_net_driver::TxToken for TxToken<'a, 'd> {
         // NOTE(unwrap): we checked the queue wasn't full when creating the token.
         let pkt = unwrap!(self.tx.available());
         let r = f(&mut pkt[..len]);
-        self.tx.transmit(len);
+        self.tx.transmit(len, self.timestamp, self.vlan_tag);
         r
     }
+
+    fn request_timestamp(&mut self) {
+        self.timestamp = true;
+    }
+
+    fn set_vlan_tag(&mut self, tci: u16) {
+        self.vlan_tag = Some(tci);
+    }
 }
 
 /// Station Management Interface (SMI) on an ethernet PHY
@@ -163,6 +539,104 @@ pub trait StationManagement {
     fn smi_read(&mut self, phy_addr: u8, reg: u8) -> u16;
     /// Write a register over SMI.
     fn smi_write(&mut self, phy_addr: u8, reg: u8, val: u16);
+
+    /// Read an extended register of MMD `device` through the MMD access registers.
+    fn smi_read_mmd(&mut self, phy_addr: u8, device: u8, reg: u16) -> u16 {
+        self.smi_write(phy_addr, SMI_REG_MMD_CTRL, device as u16 & 0x1F);
+        self.smi_write(phy_addr, SMI_REG_MMD_DATA, reg);
+        self.smi_write(phy_addr, SMI_REG_MMD_CTRL, SMI_MMD_CTRL_DATA | (device as u16 & 0x1F));
+        self.smi_read(phy_addr, SMI_REG_MMD_DATA)
+    }
+
+    /// Write an extended register of MMD `device` through the MMD access registers.
+    fn smi_write_mmd(&mut self, phy_addr: u8, device: u8, reg: u16, val: u16) {
+        self.smi_write(phy_addr, SMI_REG_MMD_CTRL, device as u16 & 0x1F);
+        self.smi_write(phy_addr, SMI_REG_MMD_DATA, reg);
+        self.smi_write(phy_addr, SMI_REG_MMD_CTRL, SMI_MMD_CTRL_DATA | (device as u16 & 0x1F));
+        self.smi_write(phy_addr, SMI_REG_MMD_DATA, val);
+    }
+}
+
+/// MMD access control register
+const SMI_REG_MMD_CTRL: u8 = 0x0D;
+/// MMD access address/data register
+const SMI_REG_MMD_DATA: u8 = 0x0E;
+/// MMD access function: data, no post increment
+const SMI_MMD_CTRL_DATA: u16 = 0x4000;
+
+/// Speed of an established link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LinkSpeed {
+    /// 10 Mbit/s
+    Mbps10,
+    /// 100 Mbit/s
+    Mbps100,
+}
+
+/// Duplex mode of an established link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Duplex {
+    /// Half duplex
+    Half,
+    /// Full duplex
+    Full,
+}
+
+/// Speed and duplex mode of the link, last reported by the PHY.
+static LINK_MODE: AtomicU8 = AtomicU8::new(0);
+const LINK_MODE_VALID: u8 = 1 << 0;
+const LINK_MODE_100M: u8 = 1 << 1;
+const LINK_MODE_FULL_DUPLEX: u8 = 1 << 2;
+
+fn encode_link_mode(mode: Option<(LinkSpeed, Duplex)>) -> u8 {
+    match mode {
+        None => 0,
+        Some((speed, duplex)) => {
+            let mut bits = LINK_MODE_VALID;
+            if speed == LinkSpeed::Mbps100 {
+                bits |= LINK_MODE_100M;
+            }
+            if duplex == Duplex::Full {
+                bits |= LINK_MODE_FULL_DUPLEX;
+            }
+            bits
+        }
+    }
+}
+
+fn decode_link_mode(bits: u8) -> Option<(LinkSpeed, Duplex)> {
+    if bits & LINK_MODE_VALID == 0 {
+        return None;
+    }
+    let speed = match bits & LINK_MODE_100M {
+        0 => LinkSpeed::Mbps10,
+        _ => LinkSpeed::Mbps100,
+    };
+    let duplex = match bits & LINK_MODE_FULL_DUPLEX {
+        0 => Duplex::Half,
+        _ => Duplex::Full,
+    };
+    Some((speed, duplex))
+}
+
+/// Handle to the link status of the Ethernet driver, returned by [`Ethernet::link_monitor`].
+///
+/// The handle can be copied, and stays usable once the [`Ethernet`] driver is owned by the
+/// network stack, which polls the PHY.
+#[derive(Clone, Copy)]
+pub struct LinkMonitor<'d, T: Instance> {
+    _peri: PhantomData<&'d T>,
+}
+
+impl<'d, T: Instance> LinkMonitor<'d, T> {
+    /// Speed and duplex mode negotiated at the last link poll.
+    ///
+    /// Returns `None` if the link is down, or if the PHY doesn't report the negotiated mode.
+    pub fn link(&self) -> Option<(LinkSpeed, Duplex)> {
+        decode_link_mode(LINK_MODE.load(Ordering::Relaxed))
+    }
 }
 
 /// Trait for an Ethernet PHY
@@ -173,6 +647,13 @@ pub trait Phy {
     fn phy_init<S: StationManagement>(&mut self, sm: &mut S);
     /// Poll link to see if it is up and FD with 100Mbps
     fn poll_link<S: StationManagement>(&mut self, sm: &mut S, cx: &mut Context) -> bool;
+    /// Speed and duplex mode negotiated at the last link poll, the MAC is configured to match it.
+    ///
+    /// Returns `None` if the link is down, or if the PHY doesn't report the negotiated mode. The
+    /// MAC then keeps its current mode, 100 Mbit/s full duplex after initialization.
+    fn link_mode(&self) -> Option<(LinkSpeed, Duplex)> {
+        None
+    }
 }
 
 impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
@@ -192,6 +673,18 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
     pub fn phy_mut(&mut self) -> &mut P {
         &mut self.phy
     }
+
+    /// Get a handle to the link status, which stays usable once the driver is owned by the
+    /// network stack.
+    pub fn link_monitor(&self) -> LinkMonitor<'d, T> {
+        LinkMonitor { _peri: PhantomData }
+    }
+
+    /// Get a handle to put the MAC in power-down mode and back, which stays usable once the driver
+    /// is owned by the network stack.
+    pub fn power_management(&self) -> PowerManagement<'d, T> {
+        PowerManagement { _peri: PhantomData }
+    }
 }
 
 trait SealedInstance {
@@ -7,7 +7,7 @@ use core::marker::PhantomData;
 use core::sync::atomic::{fence, Ordering};
 
 use embassy_hal_internal::Peri;
-use stm32_metapac::eth::vals::{Apcs, Cr, Dm, DmaomrSr, Fes, Ftf, Ifg, MbProgress, Mw, Pbl, Rsf, St, Tsf};
+use stm32_metapac::eth::vals::{Apcs, Cr, Dm, DmaomrSr, Fes, Ftf, Ifg, MbProgress, Mw, Pbl, Rpd, Rsf, St, Tsf};
 
 pub(crate) use self::rx_desc::{RDes, RDesRing};
 pub(crate) use self::tx_desc::{TDes, TDesRing};
@@ -42,6 +42,20 @@ impl interrupt::typelevel::Handler<interrupt::typelevel::ETH> for InterruptHandl
         // Delay two peripheral's clock
         dma.dmasr().read();
         dma.dmasr().read();
+
+        // Reading the PMT status clears the interrupt, latch the wakeup events for `power_up`.
+        let mac = ETH.ethernet_mac();
+        if mac.macsr().read().pmts() {
+            let pmtcsr = mac.macpmtcsr().read();
+            let mut events = 0;
+            if pmtcsr.mpr() {
+                events |= PMT_MAGIC_PACKET;
+            }
+            if pmtcsr.wfr() {
+                events |= PMT_WAKEUP_FRAME;
+            }
+            PMT_EVENTS.fetch_or(events, Ordering::Relaxed);
+        }
     }
 }
 
@@ -55,6 +69,10 @@ pub struct Ethernet<'d, T: Instance, P: Phy> {
     pub(crate) phy: P,
     pub(crate) station_management: EthernetStationManagement<T>,
     pub(crate) mac_addr: [u8; 6],
+    /// Nominal PTP addend, zero while the PTP clock is disabled.
+    pub(crate) ptp_addend: u32,
+    pub(crate) multicast: MulticastFilter,
+    pub(crate) checksum_offload: ChecksumOffload,
 }
 
 /// Pins of ethernet driver.
@@ -99,8 +117,8 @@ macro_rules! config_pins {
 
 impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
     /// safety: the returned instance is not leak-safe
-    pub fn new<const TX: usize, const RX: usize>(
-        queue: &'d mut PacketQueue<TX, RX>,
+    pub fn new<const TX: usize, const RX: usize, const TX_BUF: usize, const RX_BUF: usize>(
+        queue: &'d mut PacketQueue<TX, RX, TX_BUF, RX_BUF>,
         peri: Peri<'d, T>,
         irq: impl interrupt::typelevel::Binding<interrupt::typelevel::ETH, InterruptHandler> + 'd,
         ref_clk: Peri<'d, impl RefClkPin<T>>,
@@ -167,8 +185,8 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
         Self::new_inner(queue, peri, irq, pins, phy, mac_addr)
     }
 
-    fn new_inner<const TX: usize, const RX: usize>(
-        queue: &'d mut PacketQueue<TX, RX>,
+    fn new_inner<const TX: usize, const RX: usize, const TX_BUF: usize, const RX_BUF: usize>(
+        queue: &'d mut PacketQueue<TX, RX, TX_BUF, RX_BUF>,
         peri: Peri<'d, T>,
         _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::ETH, InterruptHandler> + 'd,
         pins: Pins<'d>,
@@ -248,8 +266,11 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
                 clock_range: clock_range,
             },
             mac_addr,
-            tx: TDesRing::new(&mut queue.tx_desc, &mut queue.tx_buf),
-            rx: RDesRing::new(&mut queue.rx_desc, &mut queue.rx_buf),
+            ptp_addend: 0,
+            multicast: MulticastFilter::new(),
+            checksum_offload: ChecksumOffload::default(),
+            tx: TDesRing::new(&mut queue.tx_desc, PacketBuffers::new(&mut queue.tx_buf)),
+            rx: RDesRing::new(&mut queue.rx_desc, PacketBuffers::new(&mut queue.rx_buf)),
         };
 
         fence(Ordering::SeqCst);
@@ -286,8 +307,8 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
     }
 
     /// Create a new MII ethernet driver using 14 pins.
-    pub fn new_mii<const TX: usize, const RX: usize>(
-        queue: &'d mut PacketQueue<TX, RX>,
+    pub fn new_mii<const TX: usize, const RX: usize, const TX_BUF: usize, const RX_BUF: usize>(
+        queue: &'d mut PacketQueue<TX, RX, TX_BUF, RX_BUF>,
         peri: Peri<'d, T>,
         irq: impl interrupt::typelevel::Binding<interrupt::typelevel::ETH, InterruptHandler> + 'd,
         rx_clk: Peri<'d, impl RXClkPin<T>>,
@@ -367,8 +388,190 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
     }
 }
 
-/// Ethernet station management interface.
-pub(crate) struct EthernetStationManagement<T: Instance> {
+impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
+    /// Configure checksum offload, which is disabled by default.
+    ///
+    /// The network stack is told through the driver capabilities to skip the offloaded checksums.
+    pub fn set_checksum_offload(&mut self, offload: ChecksumOffload) {
+        let mac = T::regs().ethernet_mac();
+
+        mac.maccr().modify(|w| w.set_ipco(offload.rx));
+        self.tx.checksum_insertion = offload.tx;
+        self.rx.checksum_check = offload.rx;
+        self.checksum_offload = offload;
+    }
+
+    /// Program the multicast addresses in the MAC address registers 1 to 3 and the hash table.
+    pub(crate) fn apply_multicast_filter(&mut self) {
+        let mac = T::regs().ethernet_mac();
+        let [a1, a2, a3] = self.multicast.address_registers();
+        let hash = self.multicast.hash_table();
+
+        // Writing the low register latches both, so it must be written last.
+        mac.maca1hr().write(|w| w.0 = a1.0);
+        mac.maca1lr().write(|w| w.0 = a1.1);
+        mac.maca2hr().write(|w| w.0 = a2.0);
+        mac.maca2lr().write(|w| w.0 = a2.1);
+        mac.maca3hr().write(|w| w.0 = a3.0);
+        mac.maca3lr().write(|w| w.0 = a3.1);
+        mac.machthr().write(|w| w.0 = (hash >> 32) as u32);
+        mac.machtlr().write(|w| w.0 = hash as u32);
+
+        mac.macffr().modify(|w| {
+            // Accept all multicast frames until an address was added.
+            w.set_pam(self.multicast.pass_all());
+            // Accept multicast frames matching a perfect filter or the hash table.
+            w.set_hm(true);
+            w.set_hpf(true);
+        });
+    }
+
+    /// Enable the IEEE 1588 (PTP) clock and timestamping of all sent and received frames.
+    ///
+    /// The clock is started at zero and runs from HCLK with fine correction. Timestamps are reported through [`embassy_net_driver::RxToken::timestamp`] and
+    /// [`embassy_net_driver::Driver::tx_timestamp`].
+    ///
+    /// Returns a handle to read and correct the clock, which stays usable once the driver is owned
+    /// by the network stack.
+    pub fn enable_ptp(&mut self) -> PtpClock<'d, T> {
+        let mac = T::regs().ethernet_mac();
+        let ptp = T::regs().ethernet_ptp();
+
+        #[cfg(any(eth_v1b, eth_v1c))]
+        critical_section::with(|_| {
+            RCC.ahb1enr().modify(|w| w.set_ethmacptpen(true));
+        });
+
+        let (increment, addend) = ptp_increment(<T as SealedRccPeripheral>::frequency().0);
+
+        // The timestamp trigger interrupt is not used.
+        mac.macimr().modify(|w| w.set_tstim(true));
+
+        ptp.ptptscr().modify(|w| {
+            w.set_tse(true);
+            // Snapshot all received frames, not only PTP event messages.
+            w.set_tssarfe(true);
+            // The sub-second register counts nanoseconds.
+            w.set_tsssr(true);
+        });
+        ptp.ptpssir().write(|w| w.set_stssi(increment));
+
+        ptp.ptptsar().write(|w| w.set_tsa(addend));
+        ptp.ptptscr().modify(|w| w.set_ttsaru(true));
+        while ptp.ptptscr().read().ttsaru() {}
+        ptp.ptptscr().modify(|w| w.set_tsfcu(true));
+
+        self.ptp_addend = addend;
+        let clock = PtpClock::new(addend);
+        clock.set_time(Timestamp {
+            seconds: 0,
+            nanoseconds: 0,
+        });
+
+        self.tx.timestamping = true;
+        self.rx.timestamping = true;
+        clock
+    }
+
+    /// Get a handle to the PTP clock, if it was enabled with [`Self::enable_ptp`].
+    pub fn ptp_clock(&self) -> Option<PtpClock<'d, T>> {
+        (self.ptp_addend != 0).then(|| PtpClock::new(self.ptp_addend))
+    }
+}
+
+impl<'d, T: Instance> PtpClock<'d, T> {
+    /// Read the current time of the clock.
+    pub fn time(&self) -> Timestamp {
+        let ptp = T::regs().ethernet_ptp();
+
+        // Read the seconds again in case the nanoseconds rolled over in between.
+        loop {
+            let seconds = ptp.ptptshr().read().sts();
+            let nanoseconds = ptp.ptptslr().read().stss();
+            if ptp.ptptshr().read().sts() == seconds {
+                return Timestamp {
+                    seconds: seconds as u64,
+                    nanoseconds,
+                };
+            }
+        }
+    }
+
+    /// Set the time of the clock.
+    ///
+    /// The hardware counts seconds on 32 bits, the upper bits of `time.seconds` are ignored.
+    pub fn set_time(&self, time: Timestamp) {
+        let ptp = T::regs().ethernet_ptp();
+
+        ptp.ptptshur().write(|w| w.set_tsus(time.seconds as u32));
+        ptp.ptptslur().write(|w| w.set_tsuss(time.nanoseconds));
+        ptp.ptptscr().modify(|w| w.set_tssti(true));
+        while ptp.ptptscr().read().tssti() {}
+    }
+
+    /// Step the clock forward or backward by `offset_ns` nanoseconds.
+    pub fn adjust_time(&self, offset_ns: i64) {
+        let ptp = T::regs().ethernet_ptp();
+        let offset = offset_ns.unsigned_abs();
+
+        ptp.ptptshur().write(|w| w.set_tsus((offset / 1_000_000_000) as u32));
+        ptp.ptptslur().write(|w| {
+            w.set_tsupns(offset_ns < 0);
+            w.set_tsuss((offset % 1_000_000_000) as u32);
+        });
+        ptp.ptptscr().modify(|w| w.set_tsstu(true));
+        while ptp.ptptscr().read().tsstu() {}
+    }
+
+    /// Get the addend currently used by the clock fine correction.
+    pub fn addend(&self) -> u32 {
+        T::regs().ethernet_ptp().ptptsar().read().tsa()
+    }
+
+    /// Set the addend used by the clock fine correction.
+    ///
+    /// The clock is incremented each time adding the addend to a 32-bit accumulator at the HCLK
+    /// frequency overflows. Prefer [`Self::adjust_frequency`] unless the servo computes the
+    /// addend itself.
+    pub fn set_addend(&self, addend: u32) {
+        let ptp = T::regs().ethernet_ptp();
+
+        while ptp.ptptscr().read().ttsaru() {}
+        ptp.ptptsar().write(|w| w.set_tsa(addend));
+        ptp.ptptscr().modify(|w| w.set_ttsaru(true));
+    }
+
+    /// Make the clock run faster or slower than nominal by `ppb` parts per billion.
+    pub fn adjust_frequency(&self, ppb: i32) {
+        self.set_addend(ptp_scale_addend(self.addend, ppb));
+    }
+}
+
+impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
+    /// Configure the MAC for the speed and duplex mode negotiated by the PHY.
+    pub(crate) fn set_link_mode(&mut self, speed: LinkSpeed, duplex: Duplex) {
+        let mac = T::regs().ethernet_mac();
+        let fes = match speed {
+            LinkSpeed::Mbps10 => Fes::FES10,
+            LinkSpeed::Mbps100 => Fes::FES100,
+        };
+        let dm = match duplex {
+            Duplex::Half => Dm::HALF_DUPLEX,
+            Duplex::Full => Dm::FULL_DUPLEX,
+        };
+
+        let maccr = mac.maccr().read();
+        if maccr.fes() != fes || maccr.dm() != dm {
+            mac.maccr().modify(|w| {
+                w.set_fes(fes);
+                w.set_dm(dm);
+            });
+        }
+    }
+}
+
+/// Ethernet SMI driver.
+pub struct EthernetStationManagement<T: Instance> {
     peri: PhantomData<T>,
     clock_range: Cr,
 }
@@ -403,21 +606,88 @@ impl<T: Instance> StationManagement for EthernetStationManagement<T> {
     }
 }
 
-impl<'d, T: Instance, P: Phy> Drop for Ethernet<'d, T, P> {
-    fn drop(&mut self) {
+impl<'d, T: Instance> PowerManagement<'d, T> {
+    /// Put the MAC in power-down mode, waiting for a Wake-on-LAN event.
+    ///
+    /// Transmission and reception stop. The MAC leaves power-down mode by itself when it receives
+    /// a magic packet or a frame matching one of the filters enabled in `config`, and raises the
+    /// ETH interrupt, which wakes the core from sleep. Call [`Self::power_up`] to resume operation.
+    pub fn power_down(&self, config: &WakeupConfig) {
+        let mac = T::regs().ethernet_mac();
+
+        stop::<T>();
+
+        mac.macpmtcsr().modify(|w| w.set_wffrpr(true));
+        for reg in wakeup_filter_registers(config.filters) {
+            mac.macrwuffr().write(|w| w.0 = reg);
+        }
+
+        take_wakeup_events();
+        mac.macimr().modify(|w| w.set_pmtim(false));
+        mac.macpmtcsr().modify(|w| {
+            w.set_mpe(config.magic_packet);
+            w.set_wfe(!config.filters.is_empty());
+            w.set_gu(config.global_unicast);
+            w.set_pd(true);
+        });
+
+        // The receiver must be enabled to detect wakeup frames.
+        mac.maccr().modify(|w| w.set_re(true));
+    }
+
+    /// Check whether the MAC is still in power-down mode.
+    pub fn is_powered_down(&self) -> bool {
+        T::regs().ethernet_mac().macpmtcsr().read().pd()
+    }
+
+    /// Leave power-down mode and resume transmission and reception.
+    ///
+    /// Returns the events that woke the MAC up, if any.
+    pub fn power_up(&self) -> WakeupEvents {
         let dma = T::regs().ethernet_dma();
         let mac = T::regs().ethernet_mac();
 
-        // Disable the TX DMA and wait for any previous transmissions to be completed
-        dma.dmaomr().modify(|w| w.set_st(St::STOPPED));
+        mac.macpmtcsr().modify(|w| {
+            w.set_pd(false);
+            w.set_mpe(false);
+            w.set_wfe(false);
+        });
+        mac.macimr().modify(|w| w.set_pmtim(true));
 
-        // Disable MAC transmitter and receiver
         mac.maccr().modify(|w| {
-            w.set_re(false);
-            w.set_te(false);
+            w.set_re(true);
+            w.set_te(true);
+        });
+        dma.dmaomr().modify(|w| {
+            w.set_st(St::STARTED);
+            w.set_sr(DmaomrSr::STARTED);
         });
+        dma.dmarpdr().write(|w| w.set_rpd(Rpd::POLL));
 
-        dma.dmaomr().modify(|w| w.set_sr(DmaomrSr::STOPPED));
+        take_wakeup_events()
+    }
+}
+
+/// Stop the DMA and the MAC.
+fn stop<T: Instance>() {
+    let dma = T::regs().ethernet_dma();
+    let mac = T::regs().ethernet_mac();
+
+    // Disable the TX DMA and wait for any previous transmissions to be completed
+    dma.dmaomr().modify(|w| w.set_st(St::STOPPED));
+
+    // Disable MAC transmitter and receiver
+    mac.maccr().modify(|w| {
+        w.set_re(false);
+        w.set_te(false);
+    });
+
+    dma.dmaomr().modify(|w| w.set_sr(DmaomrSr::STOPPED));
+}
+
+impl<'d, T: Instance, P: Phy> Drop for Ethernet<'d, T, P> {
+    fn drop(&mut self) {
+        stop::<T>();
 
         critical_section::with(|_| {
             for pin in match self.pins {
@@ -3,7 +3,7 @@ use core::sync::atomic::{compiler_fence, fence, Ordering};
 use stm32_metapac::eth::vals::{Rpd, Rps};
 use vcell::VolatileCell;
 
-use crate::eth::RX_BUFFER_SIZE;
+use crate::eth::{PacketBuffers, Timestamp};
 use crate::pac::ETH;
 
 mod rx_consts {
@@ -15,6 +15,11 @@ mod rx_consts {
     pub const RXDESC_0_LS: u32 = 1 << 8;
     /// Error summary
     pub const RXDESC_0_ES: u32 = 1 << 15;
+    /// Timestamp valid, or IP header checksum error if timestamping is disabled
+    pub const RXDESC_0_TSV: u32 = 1 << 7;
+    pub const RXDESC_0_IPHCE: u32 = 1 << 7;
+    /// Payload checksum error
+    pub const RXDESC_0_PCE: u32 = 1 << 0;
     /// Frame length
     pub const RXDESC_0_FL_MASK: u32 = 0x3FFF;
     pub const RXDESC_0_FL_SHIFT: usize = 16;
@@ -28,14 +33,12 @@ mod rx_consts {
 
 use rx_consts::*;
 
-use super::Packet;
-
 /// Receive Descriptor representation
 ///
 /// * rdes0: OWN and Status
 /// * rdes1: allocated buffer length
-/// * rdes2: data buffer address
-/// * rdes3: next descriptor address
+/// * rdes2: data buffer address, or timestamp low after reception
+/// * rdes3: next descriptor address, or timestamp high after reception
 #[repr(C)]
 pub(crate) struct RDes {
     rdes0: VolatileCell<u32>,
@@ -72,9 +75,9 @@ impl RDes {
 
     /// Configures the reception buffer address and length and passed descriptor ownership to the DMA
     #[inline(always)]
-    fn set_ready(&self, buf: *mut u8) {
+    fn set_ready(&self, buf: *mut u8, len: usize) {
         self.rdes1
-            .set(self.rdes1.get() | (RX_BUFFER_SIZE as u32) & RXDESC_1_RBS_MASK);
+            .set(self.rdes1.get() & !RXDESC_1_RBS_MASK | (len as u32) & RXDESC_1_RBS_MASK);
         self.rdes2.set(buf as u32);
 
         // "Preceding reads and writes cannot be moved past subsequent writes."
@@ -100,12 +103,36 @@ impl RDes {
         self.rdes1.set(self.rdes1.get() | RXDESC_1_RER);
     }
 
+    /// Return true if the checksum offload engine found an error in the frame.
+    #[inline(always)]
+    fn checksum_error(&self, timestamping: bool) -> bool {
+        let errors = if timestamping {
+            RXDESC_0_PCE
+        } else {
+            RXDESC_0_PCE | RXDESC_0_IPHCE
+        };
+        self.rdes0.get() & errors != 0
+    }
+
+    /// Timestamp of the received frame, if one was captured.
+    #[inline(always)]
+    fn timestamp(&self) -> Option<Timestamp> {
+        if self.rdes0.get() & RXDESC_0_TSV != 0 {
+            Some(Timestamp {
+                seconds: self.rdes3.get() as u64,
+                nanoseconds: self.rdes2.get(),
+            })
+        } else {
+            None
+        }
+    }
+
     #[inline(always)]
     fn packet_len(&self) -> usize {
         ((self.rdes0.get() >> RXDESC_0_FL_SHIFT) & RXDESC_0_FL_MASK) as usize
     }
 
-    fn setup(&self, next: Option<&Self>, buf: *mut u8) {
+    fn setup(&self, next: Option<&Self>, buf: *mut u8, len: usize) {
         // Defer this initialization to this function, so we can have `RingEntry` on bss.
         self.rdes1.set(self.rdes1.get() | RXDESC_1_RCH);
 
@@ -117,7 +144,7 @@ impl RDes {
             }
         }
 
-        self.set_ready(buf);
+        self.set_ready(buf, len);
     }
 }
 
@@ -132,17 +159,20 @@ pub enum RunningState {
 /// Rx ring of descriptors and packets
 pub(crate) struct RDesRing<'a> {
     descriptors: &'a mut [RDes],
-    buffers: &'a mut [Packet<RX_BUFFER_SIZE>],
+    buffers: PacketBuffers<'a>,
     index: usize,
+    pub(crate) timestamping: bool,
+    /// Drop frames the checksum offload engine found errors in.
+    pub(crate) checksum_check: bool,
 }
 
 impl<'a> RDesRing<'a> {
-    pub(crate) fn new(descriptors: &'a mut [RDes], buffers: &'a mut [Packet<RX_BUFFER_SIZE>]) -> Self {
+    pub(crate) fn new(descriptors: &'a mut [RDes], mut buffers: PacketBuffers<'a>) -> Self {
         assert!(descriptors.len() > 1);
         assert!(descriptors.len() == buffers.len());
 
         for (i, entry) in descriptors.iter().enumerate() {
-            entry.setup(descriptors.get(i + 1), buffers[i].0.as_mut_ptr());
+            entry.setup(descriptors.get(i + 1), buffers.as_mut_ptr(i), buffers.size());
         }
 
         // Register rx descriptor start
@@ -155,6 +185,8 @@ impl<'a> RDesRing<'a> {
             descriptors,
             buffers,
             index: 0,
+            timestamping: false,
+            checksum_check: false,
         }
     }
 
@@ -206,20 +238,53 @@ impl<'a> RDesRing<'a> {
                 continue;
             }
 
+            if self.checksum_check && descriptor.checksum_error(self.timestamping) {
+                debug!("checksum error: {:08x}", descriptor.rdes0.get());
+                self.pop_packet();
+                continue;
+            }
+
             break;
         }
 
         let descriptor = &mut self.descriptors[self.index];
         let len = descriptor.packet_len();
-        return Some(&mut self.buffers[self.index].0[..len]);
+        return Some(&mut self.buffers.get(self.index)[..len]);
+    }
+
+    pub(crate) fn buffer_size(&self) -> usize {
+        self.buffers.size()
+    }
+
+    /// Timestamp of the packet returned by `available`.
+    pub(crate) fn timestamp(&self) -> Option<Timestamp> {
+        if self.timestamping {
+            self.descriptors[self.index].timestamp()
+        } else {
+            None
+        }
+    }
+
+    /// VLAN tag stripped from the packet returned by `available`.
+    ///
+    /// The MAC doesn't strip VLAN tags, they are left in the packet.
+    pub(crate) fn vlan_tag(&self) -> Option<u16> {
+        None
     }
 
     /// Pop the packet previously returned by `available`.
     pub(crate) fn pop_packet(&mut self) {
+        let next = match self.descriptors.get(self.index + 1) {
+            Some(next) => next as *const RDes as *const u8,
+            None => 0 as *const u8,
+        };
         let descriptor = &mut self.descriptors[self.index];
         assert!(descriptor.available());
 
-        self.descriptors[self.index].set_ready(self.buffers[self.index].0.as_mut_ptr());
+        // The timestamp may have overwritten the chain address.
+        descriptor.set_buffer2(next);
+        let len = self.buffers.size();
+        self.descriptors[self.index].set_ready(self.buffers.as_mut_ptr(self.index), len);
 
         self.demand_poll();
 
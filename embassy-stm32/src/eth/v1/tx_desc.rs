@@ -2,7 +2,7 @@ use core::sync::atomic::{compiler_fence, fence, Ordering};
 
 use vcell::VolatileCell;
 
-use crate::eth::TX_BUFFER_SIZE;
+use crate::eth::{PacketBuffers, Timestamp};
 use crate::pac::ETH;
 
 /// Transmit and Receive Descriptor fields
@@ -18,6 +18,12 @@ mod tx_consts {
     pub const TXDESC_0_TER: u32 = 1 << 21;
     // Second address chained
     pub const TXDESC_0_TCH: u32 = 1 << 20;
+    // Transmit timestamp enable
+    pub const TXDESC_0_TTSE: u32 = 1 << 25;
+    // Transmit timestamp status
+    pub const TXDESC_0_TTSS: u32 = 1 << 17;
+    // Checksum insertion control: IP header and payload, with pseudo-header
+    pub const TXDESC_0_CIC_FULL: u32 = 0b11 << 22;
     // Error status
     pub const TXDESC_0_ES: u32 = 1 << 15;
 
@@ -27,14 +33,12 @@ mod tx_consts {
 }
 use tx_consts::*;
 
-use super::Packet;
-
 /// Transmit Descriptor representation
 ///
 /// * tdes0: control
 /// * tdes1: buffer lengths
-/// * tdes2: data buffer address
-/// * tdes3: next descriptor address
+/// * tdes2: data buffer address, or timestamp low after transmission
+/// * tdes3: next descriptor address, or timestamp high after transmission
 #[repr(C)]
 pub(crate) struct TDes {
     tdes0: VolatileCell<u32>,
@@ -89,6 +93,30 @@ impl TDes {
         self.tdes0.set(self.tdes0.get() | TXDESC_0_TER);
     }
 
+    /// Request a timestamp of the frame, and clear the status of a previous one.
+    fn set_timestamp_enabled(&self, enabled: bool) {
+        let tdes0 = self.tdes0.get() & !(TXDESC_0_TTSE | TXDESC_0_TTSS);
+        self.tdes0.set(if enabled { tdes0 | TXDESC_0_TTSE } else { tdes0 });
+    }
+
+    /// Let the MAC insert the IP header and TCP/UDP/ICMP checksums.
+    fn set_checksum_insertion(&self, enabled: bool) {
+        let tdes0 = self.tdes0.get() & !TXDESC_0_CIC_FULL;
+        self.tdes0.set(if enabled { tdes0 | TXDESC_0_CIC_FULL } else { tdes0 });
+    }
+
+    /// Timestamp of the transmitted frame, if one was captured.
+    fn timestamp(&self) -> Option<Timestamp> {
+        if self.tdes0.get() & TXDESC_0_TTSS != 0 {
+            Some(Timestamp {
+                seconds: self.tdes3.get() as u64,
+                nanoseconds: self.tdes2.get(),
+            })
+        } else {
+            None
+        }
+    }
+
     // set up as a part fo the ring buffer - configures the tdes
     fn setup(&self, next: Option<&Self>) {
         // Defer this initialization to this function, so we can have `RingEntry` on bss.
@@ -105,13 +133,19 @@ impl TDes {
 
 pub(crate) struct TDesRing<'a> {
     descriptors: &'a mut [TDes],
-    buffers: &'a mut [Packet<TX_BUFFER_SIZE>],
+    buffers: PacketBuffers<'a>,
     index: usize,
+    pub(crate) timestamping: bool,
+    pub(crate) checksum_insertion: bool,
+    /// Always false, the MAC can't insert VLAN tags.
+    pub(crate) vlan_tag_insertion: bool,
+    pending_timestamp: Option<usize>,
+    last_timestamp: Option<Timestamp>,
 }
 
 impl<'a> TDesRing<'a> {
     /// Initialise this TDesRing. Assume TDesRing is corrupt
-    pub(crate) fn new(descriptors: &'a mut [TDes], buffers: &'a mut [Packet<TX_BUFFER_SIZE>]) -> Self {
+    pub(crate) fn new(descriptors: &'a mut [TDes], buffers: PacketBuffers<'a>) -> Self {
         assert!(descriptors.len() > 0);
         assert!(descriptors.len() == buffers.len());
 
@@ -128,6 +162,11 @@ impl<'a> TDesRing<'a> {
             descriptors,
             buffers,
             index: 0,
+            timestamping: false,
+            checksum_insertion: false,
+            vlan_tag_insertion: false,
+            pending_timestamp: None,
+            last_timestamp: None,
         }
     }
 
@@ -135,23 +174,42 @@ impl<'a> TDesRing<'a> {
         self.descriptors.len()
     }
 
+    pub(crate) fn buffer_size(&self) -> usize {
+        self.buffers.size()
+    }
+
     /// Return the next available packet buffer for transmitting, or None
     pub(crate) fn available(&mut self) -> Option<&mut [u8]> {
         let descriptor = &mut self.descriptors[self.index];
         if descriptor.available() {
-            Some(&mut self.buffers[self.index].0)
+            Some(self.buffers.get(self.index))
         } else {
             None
         }
     }
 
     /// Transmit the packet written in a buffer returned by `available`.
-    pub(crate) fn transmit(&mut self, len: usize) {
+    ///
+    /// `vlan_tag` is ignored, see `vlan_tag_insertion`.
+    pub(crate) fn transmit(&mut self, len: usize, timestamp: bool, _vlan_tag: Option<u16>) {
+        if self.pending_timestamp == Some(self.index) {
+            self.collect_timestamp();
+        }
+        let timestamp = timestamp && self.timestamping;
+        if timestamp {
+            self.pending_timestamp = Some(self.index);
+        }
+
+        let next = self.next_descriptor(self.index);
         let descriptor = &mut self.descriptors[self.index];
         assert!(descriptor.available());
 
-        descriptor.set_buffer1(self.buffers[self.index].0.as_ptr());
+        descriptor.set_buffer1(self.buffers.as_mut_ptr(self.index));
         descriptor.set_buffer1_len(len);
+        // A timestamp of the previous frame may have overwritten the chain address.
+        descriptor.set_buffer2(next);
+        descriptor.set_timestamp_enabled(timestamp);
+        descriptor.set_checksum_insertion(self.checksum_insertion);
 
         descriptor.set_owned();
 
@@ -168,4 +226,27 @@ impl<'a> TDesRing<'a> {
         // Request the DMA engine to poll the latest tx descriptor
         ETH.ethernet_dma().dmatpdr().modify(|w| w.0 = 1)
     }
+
+    /// Take the timestamp of the last frame transmitted with a timestamp request, once sent.
+    pub(crate) fn timestamp(&mut self) -> Option<Timestamp> {
+        self.collect_timestamp();
+        self.last_timestamp.take()
+    }
+
+    fn collect_timestamp(&mut self) {
+        if let Some(index) = self.pending_timestamp {
+            let descriptor = &self.descriptors[index];
+            if descriptor.available() {
+                self.last_timestamp = descriptor.timestamp();
+                self.pending_timestamp = None;
+            }
+        }
+    }
+
+    fn next_descriptor(&self, index: usize) -> *const u8 {
+        match self.descriptors.get(index + 1) {
+            Some(next) => next as *const TDes as *const u8,
+            None => 0 as *const u8,
+        }
+    }
 }
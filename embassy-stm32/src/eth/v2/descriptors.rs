@@ -2,7 +2,7 @@ use core::sync::atomic::{fence, Ordering};
 
 use vcell::VolatileCell;
 
-use crate::eth::{Packet, RX_BUFFER_SIZE, TX_BUFFER_SIZE};
+use crate::eth::{PacketBuffers, Timestamp};
 use crate::pac::ETH;
 
 /// Transmit and Receive Descriptor fields
@@ -16,19 +16,32 @@ mod emac_consts {
     pub const EMAC_DES0_BUF1AP: u32 = 0xFFFF_FFFF;
 
     pub const EMAC_TDES2_IOC: u32 = 0x8000_0000;
+    pub const EMAC_TDES2_TTSE: u32 = 0x4000_0000;
     pub const EMAC_TDES2_B1L: u32 = 0x0000_3FFF;
+    pub const EMAC_TDES2_VTIR_INSERT: u32 = 0x0000_8000;
+    pub const EMAC_TDES3_TTSS: u32 = 0x0002_0000;
+    pub const EMAC_TDES3_VLTV: u32 = 0x0001_0000;
+    pub const EMAC_TDES3_VT: u32 = 0x0000_FFFF;
+    pub const EMAC_TDES3_CIC_FULL: u32 = 0x0003_0000;
 
     pub const EMAC_RDES3_IOC: u32 = 0x4000_0000;
     pub const EMAC_RDES3_PL: u32 = 0x0000_7FFF;
     pub const EMAC_RDES3_BUF1V: u32 = 0x0100_0000;
     pub const EMAC_RDES3_PKTLEN: u32 = 0x0000_7FFF;
+    pub const EMAC_RDES1_TSA: u32 = 0x0000_4000;
+    pub const EMAC_RDES1_IPCE: u32 = 0x0000_0080;
+    pub const EMAC_RDES1_IPHE: u32 = 0x0000_0008;
+    pub const EMAC_RDES0_OVT: u32 = 0x0000_FFFF;
+    pub const EMAC_RDES3_LT: u32 = 0x0007_0000;
+    pub const EMAC_RDES3_LT_VLAN: u32 = 0x0004_0000;
+    pub const EMAC_RDES3_LT_DOUBLE_VLAN: u32 = 0x0005_0000;
 }
 use emac_consts::*;
 
 /// Transmit Descriptor representation
 ///
-/// * tdes0: transmit buffer address
-/// * tdes1:
+/// * tdes0: transmit buffer address, or timestamp low after transmission
+/// * tdes1: timestamp high after transmission
 /// * tdes2: buffer lengths
 /// * tdes3: control and payload/frame length
 #[repr(C)]
@@ -53,17 +66,35 @@ impl TDes {
     fn available(&self) -> bool {
         self.tdes3.get() & EMAC_DES3_OWN == 0
     }
+
+    /// Timestamp of the transmitted packet, if one was captured.
+    fn timestamp(&self) -> Option<Timestamp> {
+        if self.tdes3.get() & EMAC_TDES3_TTSS != 0 {
+            Some(Timestamp {
+                seconds: self.tdes1.get() as u64,
+                nanoseconds: self.tdes0.get(),
+            })
+        } else {
+            None
+        }
+    }
 }
 
 pub(crate) struct TDesRing<'a> {
     descriptors: &'a mut [TDes],
-    buffers: &'a mut [Packet<TX_BUFFER_SIZE>],
+    buffers: PacketBuffers<'a>,
     index: usize,
+    pub(crate) timestamping: bool,
+    pub(crate) checksum_insertion: bool,
+    /// Packets with a VLAN tag are preceded by a context descriptor holding the tag.
+    pub(crate) vlan_tag_insertion: bool,
+    pending_timestamp: Option<usize>,
+    last_timestamp: Option<Timestamp>,
 }
 
 impl<'a> TDesRing<'a> {
     /// Initialise this TDesRing. Assume TDesRing is corrupt.
-    pub fn new(descriptors: &'a mut [TDes], buffers: &'a mut [Packet<TX_BUFFER_SIZE>]) -> Self {
+    pub fn new(descriptors: &'a mut [TDes], buffers: PacketBuffers<'a>) -> Self {
         assert!(descriptors.len() > 0);
         assert!(descriptors.len() == buffers.len());
 
@@ -82,6 +113,11 @@ impl<'a> TDesRing<'a> {
             descriptors,
             buffers,
             index: 0,
+            timestamping: false,
+            checksum_insertion: false,
+            vlan_tag_insertion: false,
+            pending_timestamp: None,
+            last_timestamp: None,
         }
     }
 
@@ -89,30 +125,84 @@ impl<'a> TDesRing<'a> {
         self.descriptors.len()
     }
 
+    pub(crate) fn buffer_size(&self) -> usize {
+        self.buffers.size()
+    }
+
     /// Return the next available packet buffer for transmitting, or None
     pub(crate) fn available(&mut self) -> Option<&mut [u8]> {
+        // With VLAN tag insertion, a context descriptor may be needed in front of the packet.
+        let next = &self.descriptors[(self.index + 1) % self.descriptors.len()];
+        if !self.vlan_tag_insertion || next.available() {
+            self.available_descriptor()
+        } else {
+            None
+        }
+    }
+
+    fn available_descriptor(&mut self) -> Option<&mut [u8]> {
         let d = &mut self.descriptors[self.index];
         if d.available() {
-            Some(&mut self.buffers[self.index].0)
+            Some(self.buffers.get(self.index))
         } else {
             None
         }
     }
 
     /// Transmit the packet written in a buffer returned by `available`.
-    pub(crate) fn transmit(&mut self, len: usize) {
+    pub(crate) fn transmit(&mut self, len: usize, timestamp: bool, vlan_tag: Option<u16>) {
+        // The descriptors used below must not hold a timestamp not collected yet.
+        self.collect_timestamp();
+
+        let buffer = self.buffers.as_mut_ptr(self.index);
+        let vlan_tag = vlan_tag.filter(|_| self.vlan_tag_insertion);
+
+        // The VLAN tag is passed in a context descriptor, the packet then goes in the next
+        // descriptor, still with the buffer returned by `available`.
+        let context = vlan_tag.map(|tci| (self.index, tci));
+        if context.is_some() {
+            self.index = (self.index + 1) % self.descriptors.len();
+        }
+
+        let timestamp = timestamp && self.timestamping;
+        if timestamp {
+            self.pending_timestamp = Some(self.index);
+        }
+
         let td = &mut self.descriptors[self.index];
         assert!(td.available());
         assert!(len as u32 <= EMAC_TDES2_B1L);
 
         // Read format
-        td.tdes0.set(self.buffers[self.index].0.as_ptr() as u32);
-        td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC);
+        td.tdes0.set(buffer as u32);
+        let ttse = if timestamp { EMAC_TDES2_TTSE } else { 0 };
+        let vtir = if context.is_some() { EMAC_TDES2_VTIR_INSERT } else { 0 };
+        td.tdes2.set(len as u32 & EMAC_TDES2_B1L | EMAC_TDES2_IOC | ttse | vtir);
 
         // FD: Contains first buffer of packet
         // LD: Contains last buffer of packet
         // Give the DMA engine ownership
-        td.tdes3.set(EMAC_DES3_FD | EMAC_DES3_LD | EMAC_DES3_OWN);
+        // CIC: Insert the IP header and TCP/UDP/ICMP checksums
+        let cic = if self.checksum_insertion {
+            EMAC_TDES3_CIC_FULL
+        } else {
+            0
+        };
+        td.tdes3.set(EMAC_DES3_FD | EMAC_DES3_LD | EMAC_DES3_OWN | cic);
+
+        if let Some((index, tci)) = context {
+            let cd = &mut self.descriptors[index];
+            assert!(cd.available());
+            cd.tdes0.set(0);
+            cd.tdes1.set(0);
+            cd.tdes2.set(0);
+
+            // The DMA engine must not see the context descriptor before the packet is ready.
+            fence(Ordering::Release);
+            cd.tdes3
+                .set(EMAC_DES3_OWN | EMAC_DES3_CTXT | EMAC_TDES3_VLTV | (tci as u32 & EMAC_TDES3_VT));
+        }
+        let td = &self.descriptors[self.index];
 
         // Ensure changes to the descriptor are committed before DMA engine sees tail pointer store.
         // This will generate an DMB instruction.
@@ -125,12 +215,28 @@ impl<'a> TDesRing<'a> {
 
         self.index = (self.index + 1) % self.descriptors.len();
     }
+
+    /// Take the timestamp of the last packet transmitted with a timestamp request, once sent.
+    pub(crate) fn timestamp(&mut self) -> Option<Timestamp> {
+        self.collect_timestamp();
+        self.last_timestamp.take()
+    }
+
+    fn collect_timestamp(&mut self) {
+        if let Some(index) = self.pending_timestamp {
+            let td = &self.descriptors[index];
+            if td.available() {
+                self.last_timestamp = td.timestamp();
+                self.pending_timestamp = None;
+            }
+        }
+    }
 }
 
 /// Receive Descriptor representation
 ///
-/// * rdes0: receive buffer address
-/// * rdes1:
+/// * rdes0: receive buffer address, or timestamp low in a context descriptor
+/// * rdes1: status, or timestamp high in a context descriptor
 /// * rdes2:
 /// * rdes3: OWN and Status
 #[repr(C)]
@@ -168,6 +274,18 @@ impl RDes {
         self.rdes3.get() & EMAC_DES3_OWN == 0 // Owned by us
     }
 
+    /// Return true if the checksum offload engine found an error in the packet
+    #[inline(always)]
+    fn checksum_error(&self) -> bool {
+        self.rdes1.get() & (EMAC_RDES1_IPCE | EMAC_RDES1_IPHE) != 0
+    }
+
+    /// Return true if this is a context descriptor, holding the timestamp of the previous packet
+    #[inline(always)]
+    fn is_context(&self) -> bool {
+        self.rdes3.get() & EMAC_DES3_CTXT != 0
+    }
+
     #[inline(always)]
     fn set_ready(&mut self, buf: *mut u8) {
         self.rdes0.set(buf as u32);
@@ -178,18 +296,23 @@ impl RDes {
 /// Rx ring of descriptors and packets
 pub(crate) struct RDesRing<'a> {
     descriptors: &'a mut [RDes],
-    buffers: &'a mut [Packet<RX_BUFFER_SIZE>],
+    buffers: PacketBuffers<'a>,
     index: usize,
+    pub(crate) timestamping: bool,
+    /// Drop packets the checksum offload engine found errors in.
+    pub(crate) checksum_check: bool,
+    /// The MAC strips VLAN tags and writes them back to the descriptor.
+    pub(crate) vlan_stripping: bool,
 }
 
 impl<'a> RDesRing<'a> {
-    pub(crate) fn new(descriptors: &'a mut [RDes], buffers: &'a mut [Packet<RX_BUFFER_SIZE>]) -> Self {
+    pub(crate) fn new(descriptors: &'a mut [RDes], mut buffers: PacketBuffers<'a>) -> Self {
         assert!(descriptors.len() > 1);
         assert!(descriptors.len() == buffers.len());
 
         for (i, desc) in descriptors.iter_mut().enumerate() {
             *desc = RDes::new();
-            desc.set_ready(buffers[i].0.as_mut_ptr());
+            desc.set_ready(buffers.as_mut_ptr(i));
         }
 
         let dma = ETH.ethernet_dma();
@@ -201,6 +324,9 @@ impl<'a> RDesRing<'a> {
             descriptors,
             buffers,
             index: 0,
+            timestamping: false,
+            checksum_check: false,
+            vlan_stripping: false,
         }
     }
 
@@ -218,6 +344,12 @@ impl<'a> RDesRing<'a> {
                 return None;
             }
 
+            // Skip the timestamp of a packet already popped.
+            if descriptor.is_context() {
+                self.pop_packet();
+                continue;
+            }
+
             // If packet is invalid, pop it and try again.
             if !descriptor.valid() {
                 warn!("invalid packet: {:08x}", descriptor.rdes0.get());
@@ -225,12 +357,52 @@ impl<'a> RDesRing<'a> {
                 continue;
             }
 
+            if self.checksum_check && descriptor.checksum_error() {
+                debug!("checksum error: {:08x}", descriptor.rdes1.get());
+                self.pop_packet();
+                continue;
+            }
+
             break;
         }
 
         let descriptor = &mut self.descriptors[self.index];
         let len = (descriptor.rdes3.get() & EMAC_RDES3_PKTLEN) as usize;
-        return Some(&mut self.buffers[self.index].0[..len]);
+        return Some(&mut self.buffers.get(self.index)[..len]);
+    }
+
+    pub(crate) fn buffer_size(&self) -> usize {
+        self.buffers.size()
+    }
+
+    /// Timestamp of the packet returned by `available`.
+    ///
+    /// The timestamp is written to the context descriptor following the packet.
+    pub(crate) fn timestamp(&self) -> Option<Timestamp> {
+        if !self.timestamping || self.descriptors[self.index].rdes1.get() & EMAC_RDES1_TSA == 0 {
+            return None;
+        }
+
+        let context = &self.descriptors[(self.index + 1) % self.descriptors.len()];
+        if context.available() && context.is_context() {
+            Some(Timestamp {
+                seconds: context.rdes1.get() as u64,
+                nanoseconds: context.rdes0.get(),
+            })
+        } else {
+            None
+        }
+    }
+
+    /// VLAN tag stripped from the packet returned by `available`.
+    pub(crate) fn vlan_tag(&self) -> Option<u16> {
+        let rd = &self.descriptors[self.index];
+        match rd.rdes3.get() & EMAC_RDES3_LT {
+            EMAC_RDES3_LT_VLAN | EMAC_RDES3_LT_DOUBLE_VLAN if self.vlan_stripping => {
+                Some((rd.rdes0.get() & EMAC_RDES0_OVT) as u16)
+            }
+            _ => None,
+        }
     }
 
     /// Pop the packet previously returned by `available`.
@@ -238,7 +410,7 @@ impl<'a> RDesRing<'a> {
         let rd = &mut self.descriptors[self.index];
         assert!(rd.available());
 
-        rd.set_ready(self.buffers[self.index].0.as_mut_ptr());
+        rd.set_ready(self.buffers.as_mut_ptr(self.index));
 
         // "Preceding reads and writes cannot be moved past subsequent writes."
         fence(Ordering::Release);
@@ -32,6 +32,20 @@ impl interrupt::typelevel::Handler<interrupt::typelevel::ETH> for InterruptHandl
         // Delay two peripheral's clock
         dma.dmacsr().read();
         dma.dmacsr().read();
+
+        // Reading the PMT status clears the interrupt, latch the wakeup events for `power_up`.
+        let mac = ETH.ethernet_mac();
+        if mac.macisr().read().pmtis() {
+            let pcsr = mac.macpcsr().read();
+            let mut events = 0;
+            if pcsr.mgkprcvd() {
+                events |= PMT_MAGIC_PACKET;
+            }
+            if pcsr.rwkprcvd() {
+                events |= PMT_WAKEUP_FRAME;
+            }
+            PMT_EVENTS.fetch_or(events, Ordering::Relaxed);
+        }
     }
 }
 
@@ -44,6 +58,10 @@ pub struct Ethernet<'d, T: Instance, P: Phy> {
     pub(crate) phy: P,
     pub(crate) station_management: EthernetStationManagement<T>,
     pub(crate) mac_addr: [u8; 6],
+    /// Nominal PTP addend, zero while the PTP clock is disabled.
+    pub(crate) ptp_addend: u32,
+    pub(crate) multicast: MulticastFilter,
+    pub(crate) checksum_offload: ChecksumOffload,
 }
 
 /// Pins of ethernet driver.
@@ -65,8 +83,8 @@ macro_rules! config_pins {
 
 impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
     /// Create a new RMII ethernet driver using 9 pins.
-    pub fn new<const TX: usize, const RX: usize>(
-        queue: &'d mut PacketQueue<TX, RX>,
+    pub fn new<const TX: usize, const RX: usize, const TX_BUF: usize, const RX_BUF: usize>(
+        queue: &'d mut PacketQueue<TX, RX, TX_BUF, RX_BUF>,
         peri: Peri<'d, T>,
         irq: impl interrupt::typelevel::Binding<interrupt::typelevel::ETH, InterruptHandler> + 'd,
         ref_clk: Peri<'d, impl RefClkPin<T>>,
@@ -110,8 +128,8 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
     }
 
     /// Create a new MII ethernet driver using 14 pins.
-    pub fn new_mii<const TX: usize, const RX: usize>(
-        queue: &'d mut PacketQueue<TX, RX>,
+    pub fn new_mii<const TX: usize, const RX: usize, const TX_BUF: usize, const RX_BUF: usize>(
+        queue: &'d mut PacketQueue<TX, RX, TX_BUF, RX_BUF>,
         peri: Peri<'d, T>,
         irq: impl interrupt::typelevel::Binding<interrupt::typelevel::ETH, InterruptHandler> + 'd,
         rx_clk: Peri<'d, impl RXClkPin<T>>,
@@ -166,8 +184,8 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
         Self::new_inner(queue, peri, irq, pins, phy, mac_addr)
     }
 
-    fn new_inner<const TX: usize, const RX: usize>(
-        queue: &'d mut PacketQueue<TX, RX>,
+    fn new_inner<const TX: usize, const RX: usize, const TX_BUF: usize, const RX_BUF: usize>(
+        queue: &'d mut PacketQueue<TX, RX, TX_BUF, RX_BUF>,
         peri: Peri<'d, T>,
         _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::ETH, InterruptHandler> + 'd,
         pins: Pins<'d>,
@@ -232,7 +250,7 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
         dma.dmactx_cr().modify(|w| w.set_txpbl(1)); // 32 ?
         dma.dmacrx_cr().modify(|w| {
             w.set_rxpbl(1); // 32 ?
-            w.set_rbsz(RX_BUFFER_SIZE as u16);
+            w.set_rbsz(RX_BUF as u16);
         });
 
         let hclk = <T as SealedRccPeripheral>::frequency();
@@ -253,8 +271,8 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
 
         let mut this = Self {
             _peri: peri,
-            tx: TDesRing::new(&mut queue.tx_desc, &mut queue.tx_buf),
-            rx: RDesRing::new(&mut queue.rx_desc, &mut queue.rx_buf),
+            tx: TDesRing::new(&mut queue.tx_desc, PacketBuffers::new(&mut queue.tx_buf)),
+            rx: RDesRing::new(&mut queue.rx_desc, PacketBuffers::new(&mut queue.rx_buf)),
             pins,
             phy,
             station_management: EthernetStationManagement {
@@ -262,6 +280,9 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
                 clock_range: clock_range,
             },
             mac_addr,
+            ptp_addend: 0,
+            multicast: MulticastFilter::new(),
+            checksum_offload: ChecksumOffload::default(),
         };
 
         fence(Ordering::SeqCst);
@@ -296,6 +317,242 @@ impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
     }
 }
 
+impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
+    /// Configure checksum offload, which is disabled by default.
+    ///
+    /// The network stack is told through the driver capabilities to skip the offloaded checksums.
+    pub fn set_checksum_offload(&mut self, offload: ChecksumOffload) {
+        let mac = T::regs().ethernet_mac();
+
+        mac.maccr().modify(|w| w.set_ipc(offload.rx));
+        self.tx.checksum_insertion = offload.tx;
+        self.rx.checksum_check = offload.rx;
+        self.checksum_offload = offload;
+    }
+
+    /// Program the multicast addresses in the MAC address registers 1 to 3 and the hash table.
+    pub(crate) fn apply_multicast_filter(&mut self) {
+        let mac = T::regs().ethernet_mac();
+        let [a1, a2, a3] = self.multicast.address_registers();
+        let hash = self.multicast.hash_table();
+
+        // Writing the low register latches both, so it must be written last.
+        mac.maca1hr().write(|w| w.0 = a1.0);
+        mac.maca1lr().write(|w| w.0 = a1.1);
+        mac.maca2hr().write(|w| w.0 = a2.0);
+        mac.maca2lr().write(|w| w.0 = a2.1);
+        mac.maca3hr().write(|w| w.0 = a3.0);
+        mac.maca3lr().write(|w| w.0 = a3.1);
+        mac.macht1r().write(|w| w.0 = (hash >> 32) as u32);
+        mac.macht0r().write(|w| w.0 = hash as u32);
+
+        mac.macpfr().modify(|w| {
+            // Accept all multicast frames until an address was added.
+            w.set_pm(self.multicast.pass_all());
+            // Accept multicast frames matching a perfect filter or the hash table.
+            w.set_hmc(true);
+            w.set_hpf(true);
+        });
+    }
+
+    /// Only accept VLAN tagged packets with one of the VLAN IDs `vids`, using the VLAN hash filter.
+    ///
+    /// The filter is a 16-bit hash table, so packets of some other VLANs may pass too. Untagged
+    /// packets are always accepted. An empty `vids` disables VLAN filtering.
+    pub fn set_vlan_filter(&mut self, vids: &[u16]) {
+        let mac = T::regs().ethernet_mac();
+
+        let mut hash = 0u16;
+        for &vid in vids {
+            hash |= 1 << vlan_hash(vid);
+        }
+        mac.macvhtr().write(|w| w.set_vlht(hash));
+        mac.macvtr().modify(|w| {
+            // Compare the 12-bit VLAN ID through the hash table.
+            w.set_etv(true);
+            w.set_vthm(!vids.is_empty());
+        });
+        mac.macpfr().modify(|w| w.set_vtfe(!vids.is_empty()));
+    }
+
+    /// Strip the VLAN tag of received packets.
+    ///
+    /// The stripped tag is reported by [`embassy_net_driver::RxToken::vlan_tag`].
+    pub fn set_vlan_stripping(&mut self, enabled: bool) {
+        let mac = T::regs().ethernet_mac();
+
+        mac.macvtr().modify(|w| {
+            w.set_evls(if enabled { 0b11 } else { 0b00 });
+            w.set_evlrxs(enabled);
+        });
+        self.rx.vlan_stripping = enabled;
+    }
+
+    /// Insert the VLAN tag set with [`embassy_net_driver::TxToken::set_vlan_tag`] in sent packets.
+    ///
+    /// The tag of each packet is passed to the MAC in an extra descriptor, so this needs a transmit
+    /// queue of at least 2 packets.
+    pub fn set_vlan_tag_insertion(&mut self, enabled: bool) {
+        assert!(!enabled || self.tx.len() >= 2);
+        let mac = T::regs().ethernet_mac();
+
+        // Take the tag from the context descriptor.
+        mac.macvir().modify(|w| w.set_vlti(enabled));
+        self.tx.vlan_tag_insertion = enabled;
+    }
+
+    /// Enable the IEEE 1588 (PTP) clock and timestamping of all sent and received packets.
+    ///
+    /// The clock is started at zero and runs from HCLK with fine correction. Timestamps are reported
+    /// through [`embassy_net_driver::RxToken::timestamp`] and [`embassy_net_driver::Driver::tx_timestamp`].
+    ///
+    /// Returns a handle to read and correct the clock, which stays usable once the driver is owned
+    /// by the network stack.
+    pub fn enable_ptp(&mut self) -> PtpClock<'d, T> {
+        let mac = T::regs().ethernet_mac();
+
+        let (increment, addend) = ptp_increment(<T as SealedRccPeripheral>::frequency().0);
+
+        mac.mactscr().modify(|w| {
+            w.set_tsena(true);
+            // Timestamp all received packets, not only PTP event messages.
+            w.set_tsenall(true);
+            // The sub-second register counts nanoseconds.
+            w.set_tsctrlssr(true);
+        });
+        mac.macssir().write(|w| w.set_ssinc(increment));
+
+        mac.mactsar().write(|w| w.set_tsar(addend));
+        mac.mactscr().modify(|w| w.set_tsaddreg(true));
+        while mac.mactscr().read().tsaddreg() {}
+        mac.mactscr().modify(|w| w.set_tscfupdt(true));
+
+        self.ptp_addend = addend;
+        let clock = PtpClock::new(addend);
+        clock.set_time(Timestamp {
+            seconds: 0,
+            nanoseconds: 0,
+        });
+
+        self.tx.timestamping = true;
+        self.rx.timestamping = true;
+        clock
+    }
+
+    /// Get a handle to the PTP clock, if it was enabled with [`Self::enable_ptp`].
+    pub fn ptp_clock(&self) -> Option<PtpClock<'d, T>> {
+        (self.ptp_addend != 0).then(|| PtpClock::new(self.ptp_addend))
+    }
+}
+
+impl<'d, T: Instance> PtpClock<'d, T> {
+    /// Read the current time of the clock.
+    pub fn time(&self) -> Timestamp {
+        let mac = T::regs().ethernet_mac();
+
+        // Read the seconds again in case the nanoseconds rolled over in between.
+        loop {
+            let seconds = mac.macstsr().read().tss();
+            let nanoseconds = mac.macstnr().read().tsss();
+            if mac.macstsr().read().tss() == seconds {
+                return Timestamp {
+                    seconds: seconds as u64,
+                    nanoseconds,
+                };
+            }
+        }
+    }
+
+    /// Set the time of the clock.
+    ///
+    /// The hardware counts seconds on 32 bits, the upper bits of `time.seconds` are ignored.
+    pub fn set_time(&self, time: Timestamp) {
+        let mac = T::regs().ethernet_mac();
+
+        mac.macstsur().write(|w| w.set_tssu(time.seconds as u32));
+        mac.macstnur().write(|w| w.set_tsssu(time.nanoseconds));
+        mac.mactscr().modify(|w| w.set_tsinit(true));
+        while mac.mactscr().read().tsinit() {}
+    }
+
+    /// Step the clock forward or backward by `offset_ns` nanoseconds.
+    pub fn adjust_time(&self, offset_ns: i64) {
+        let mac = T::regs().ethernet_mac();
+        let offset = offset_ns.unsigned_abs();
+        let mut seconds = (offset / 1_000_000_000) as u32;
+        let mut nanoseconds = (offset % 1_000_000_000) as u32;
+
+        // Subtraction is programmed as the complement of the offset.
+        if offset_ns < 0 {
+            seconds = seconds.wrapping_neg();
+            if nanoseconds != 0 {
+                nanoseconds = 1_000_000_000 - nanoseconds;
+            }
+        }
+
+        mac.macstsur().write(|w| w.set_tssu(seconds));
+        mac.macstnur().write(|w| {
+            w.set_addsub(offset_ns < 0);
+            w.set_tsssu(nanoseconds);
+        });
+        mac.mactscr().modify(|w| w.set_tsupdt(true));
+        while mac.mactscr().read().tsupdt() {}
+    }
+
+    /// Get the addend currently used by the clock fine correction.
+    pub fn addend(&self) -> u32 {
+        T::regs().ethernet_mac().mactsar().read().tsar()
+    }
+
+    /// Set the addend used by the clock fine correction.
+    ///
+    /// The clock is incremented each time adding the addend to a 32-bit accumulator at the HCLK
+    /// frequency overflows. Prefer [`Self::adjust_frequency`] unless the servo computes the
+    /// addend itself.
+    pub fn set_addend(&self, addend: u32) {
+        let mac = T::regs().ethernet_mac();
+
+        while mac.mactscr().read().tsaddreg() {}
+        mac.mactsar().write(|w| w.set_tsar(addend));
+        mac.mactscr().modify(|w| w.set_tsaddreg(true));
+    }
+
+    /// Make the clock run faster or slower than nominal by `ppb` parts per billion.
+    pub fn adjust_frequency(&self, ppb: i32) {
+        self.set_addend(ptp_scale_addend(self.addend, ppb));
+    }
+}
+
+impl<'d, T: Instance, P: Phy> Ethernet<'d, T, P> {
+    /// Configure the MAC for the speed and duplex mode negotiated by the PHY.
+    pub(crate) fn set_link_mode(&mut self, speed: LinkSpeed, duplex: Duplex) {
+        let mac = T::regs().ethernet_mac();
+        let fes = speed == LinkSpeed::Mbps100;
+        let dm = duplex == Duplex::Full;
+
+        let maccr = mac.maccr().read();
+        if maccr.fes() != fes || maccr.dm() != dm {
+            mac.maccr().modify(|w| {
+                w.set_fes(fes);
+                w.set_dm(dm);
+            });
+        }
+    }
+}
+
+/// Index in the VLAN hash table: the upper 4 bits of the bit-reversed CRC of the 12-bit VLAN ID.
+fn vlan_hash(vid: u16) -> usize {
+    let mut crc = 0xFFFF_FFFFu32;
+    for i in 0..12 {
+        let bit = (crc ^ (vid >> i) as u32) & 1;
+        crc >>= 1;
+        if bit != 0 {
+            crc ^= 0xEDB8_8320;
+        }
+    }
+    ((!crc).reverse_bits() >> 28) as usize
+}
+
 /// Ethernet SMI driver.
 pub struct EthernetStationManagement<T: Instance> {
     peri: PhantomData<T>,
@@ -332,31 +589,96 @@ impl<T: Instance> StationManagement for EthernetStationManagement<T> {
     }
 }
 
-impl<'d, T: Instance, P: Phy> Drop for Ethernet<'d, T, P> {
-    fn drop(&mut self) {
+impl<'d, T: Instance> PowerManagement<'d, T> {
+    /// Put the MAC in power-down mode, waiting for a Wake-on-LAN event.
+    ///
+    /// Transmission and reception stop once the frames being transferred are completed. The MAC
+    /// leaves power-down mode by itself when it receives a magic packet or a frame matching one of
+    /// the filters enabled in `config`, and raises the ETH interrupt, which wakes the core from
+    /// sleep. Call [`Self::power_up`] to resume operation.
+    pub fn power_down(&self, config: &WakeupConfig) {
+        let mac = T::regs().ethernet_mac();
+
+        stop::<T>();
+
+        mac.macpcsr().modify(|w| w.set_rwkfiltrst(true));
+        for reg in wakeup_filter_registers(config.filters) {
+            mac.macrwkpfr().write(|w| w.0 = reg);
+        }
+
+        take_wakeup_events();
+        mac.macier().modify(|w| w.set_pmtie(true));
+        mac.macpcsr().modify(|w| {
+            w.set_mgkpkten(config.magic_packet);
+            w.set_rwkpkten(!config.filters.is_empty());
+            w.set_glblucast(config.global_unicast);
+            w.set_pwrdwn(true);
+        });
+
+        // The receiver must be enabled to detect wakeup frames.
+        mac.maccr().modify(|w| w.set_re(true));
+    }
+
+    /// Check whether the MAC is still in power-down mode.
+    pub fn is_powered_down(&self) -> bool {
+        T::regs().ethernet_mac().macpcsr().read().pwrdwn()
+    }
+
+    /// Leave power-down mode and resume transmission and reception.
+    ///
+    /// Returns the events that woke the MAC up, if any.
+    pub fn power_up(&self) -> WakeupEvents {
         let dma = T::regs().ethernet_dma();
         let mac = T::regs().ethernet_mac();
-        let mtl = T::regs().ethernet_mtl();
 
-        // Disable the TX DMA and wait for any previous transmissions to be completed
-        dma.dmactx_cr().modify(|w| w.set_st(false));
-        while {
-            let txqueue = mtl.mtltx_qdr().read();
-            txqueue.trcsts() == 0b01 || txqueue.txqsts()
-        } {}
+        mac.macpcsr().modify(|w| {
+            w.set_pwrdwn(false);
+            w.set_mgkpkten(false);
+            w.set_rwkpkten(false);
+        });
+        mac.macier().modify(|w| w.set_pmtie(false));
 
-        // Disable MAC transmitter and receiver
         mac.maccr().modify(|w| {
-            w.set_re(false);
-            w.set_te(false);
+            w.set_re(true);
+            w.set_te(true);
         });
+        dma.dmactx_cr().modify(|w| w.set_st(true));
+        dma.dmacrx_cr().modify(|w| w.set_sr(true));
 
-        // Wait for previous receiver transfers to be completed and then disable the RX DMA
-        while {
-            let rxqueue = mtl.mtlrx_qdr().read();
-            rxqueue.rxqsts() != 0b00 || rxqueue.prxq() != 0
-        } {}
-        dma.dmacrx_cr().modify(|w| w.set_sr(false));
+        take_wakeup_events()
+    }
+}
+
+/// Stop the DMA and the MAC once the frames being transferred are completed.
+fn stop<T: Instance>() {
+    let dma = T::regs().ethernet_dma();
+    let mac = T::regs().ethernet_mac();
+    let mtl = T::regs().ethernet_mtl();
+
+    // Disable the TX DMA and wait for any previous transmissions to be completed
+    dma.dmactx_cr().modify(|w| w.set_st(false));
+    while {
+        let txqueue = mtl.mtltx_qdr().read();
+        txqueue.trcsts() == 0b01 || txqueue.txqsts()
+    } {}
+
+    // Disable MAC transmitter and receiver
+    mac.maccr().modify(|w| {
+        w.set_re(false);
+        w.set_te(false);
+    });
+
+    // Wait for previous receiver transfers to be completed and then disable the RX DMA
+    while {
+        let rxqueue = mtl.mtlrx_qdr().read();
+        rxqueue.rxqsts() != 0b00 || rxqueue.prxq() != 0
+    } {}
+    dma.dmacrx_cr().modify(|w| w.set_sr(false));
+}
+
+impl<'d, T: Instance, P: Phy> Drop for Ethernet<'d, T, P> {
+    fn drop(&mut self) {
+        stop::<T>();
 
         critical_section::with(|_| {
             for pin in match self.pins {
@@ -5,8 +5,12 @@ use core::marker::PhantomData;
 use core::pin::Pin;
 use core::task::{Context, Poll};
 
+#[cfg(feature = "time")]
+use embassy_futures::select::{select, Either};
 use embassy_hal_internal::{impl_peripheral, PeripheralType};
 use embassy_sync::waitqueue::AtomicWaker;
+#[cfg(feature = "time")]
+use embassy_time::{Duration, Timer};
 
 use crate::gpio::{AnyPin, Input, Level, Pin as GpioPin, Pull};
 use crate::pac::exti::regs::Lines;
@@ -169,6 +173,139 @@ impl<'d> ExtiInput<'d> {
     pub async fn wait_for_any_edge(&mut self) {
         ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), true, true).await
     }
+
+    /// Asynchronously wait until the pin changes level and stays stable, and return the new level.
+    ///
+    /// The pin must keep its new level for `duration` after its last edge, otherwise the edges
+    /// are considered glitches or contact bounces, and ignored. Use [`Debounced`] to keep track
+    /// of the stable level across calls.
+    #[cfg(feature = "time")]
+    pub async fn wait_for_debounced_edge(&mut self, duration: Duration) -> Level {
+        let stable = self.get_level();
+        self.debounce_from(stable, duration).await
+    }
+
+    #[cfg(feature = "time")]
+    async fn debounce_from(&mut self, stable: Level, duration: Duration) -> Level {
+        loop {
+            match stable {
+                Level::High => self.wait_for_low().await,
+                Level::Low => self.wait_for_high().await,
+            }
+
+            // Restart the delay on each edge, until the pin is quiet for `duration`.
+            loop {
+                let edge = ExtiInputFuture::new(self.pin.pin.pin.pin(), self.pin.pin.pin.port(), true, true);
+                if let Either::First(()) = select(Timer::after(duration), edge).await {
+                    break;
+                }
+            }
+
+            // The pin may have bounced back to its stable level.
+            let level = self.get_level();
+            if level != stable {
+                return level;
+            }
+        }
+    }
+}
+
+/// Edges awaited by [`wait_for_any`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    /// Rising edges.
+    Rising,
+    /// Falling edges.
+    Falling,
+    /// Both rising and falling edges.
+    Any,
+}
+
+/// Asynchronously wait until any of `inputs` sees an `edge`, and return which ones did.
+///
+/// Bit `i` of the returned mask is set if `inputs[i]` fired. Several bits are set if edges came
+/// on several inputs before the waiting task ran. This waits on all the inputs with a single
+/// future, for example to scan a keypad or serve several sensor interrupts from one task.
+pub async fn wait_for_any(inputs: &mut [ExtiInput<'_>], edge: Edge) -> u16 {
+    assert!(!inputs.is_empty(), "no inputs to wait for");
+    let (rising, falling) = match edge {
+        Edge::Rising => (true, false),
+        Edge::Falling => (false, true),
+        Edge::Any => (true, true),
+    };
+
+    let pins = inputs
+        .iter()
+        .map(|input| (input.pin.pin.pin.pin(), input.pin.pin.pin.port()));
+    let fired = ExtiGroupFuture::new(pins, rising, falling).await;
+
+    let mut mask = 0;
+    for (i, input) in inputs.iter().enumerate() {
+        if fired & (1 << input.pin.pin.pin.pin()) != 0 {
+            mask |= 1 << i;
+        }
+    }
+    mask
+}
+
+/// EXTI input filtering out glitches and contact bounces, for buttons and switches.
+///
+/// Level changes are only reported once the pin kept its new level for the debounce duration.
+#[cfg(feature = "time")]
+pub struct Debounced<'d> {
+    input: ExtiInput<'d>,
+    duration: Duration,
+    level: Level,
+}
+
+#[cfg(feature = "time")]
+impl<'d> Debounced<'d> {
+    /// Debounce `input`, which must keep a level for `duration` to be considered stable.
+    pub fn new(input: ExtiInput<'d>, duration: Duration) -> Self {
+        let level = input.get_level();
+        Self { input, duration, level }
+    }
+
+    /// Get the stable pin level.
+    pub fn get_level(&self) -> Level {
+        self.level
+    }
+
+    /// Get whether the stable pin level is high.
+    pub fn is_high(&self) -> bool {
+        self.level == Level::High
+    }
+
+    /// Get whether the stable pin level is low.
+    pub fn is_low(&self) -> bool {
+        self.level == Level::Low
+    }
+
+    /// Asynchronously wait until the stable pin level changes, and return the new level.
+    pub async fn wait_for_any_edge(&mut self) -> Level {
+        self.level = self.input.debounce_from(self.level, self.duration).await;
+        self.level
+    }
+
+    /// Asynchronously wait until the stable pin level becomes high.
+    ///
+    /// If the stable level is already high, it will wait for it to go low then back high.
+    pub async fn wait_for_rising_edge(&mut self) {
+        while self.wait_for_any_edge().await != Level::High {}
+    }
+
+    /// Asynchronously wait until the stable pin level becomes low.
+    ///
+    /// If the stable level is already low, it will wait for it to go high then back low.
+    pub async fn wait_for_falling_edge(&mut self) {
+        while self.wait_for_any_edge().await != Level::Low {}
+    }
+
+    /// Return the underlying EXTI input.
+    pub fn into_inner(self) -> ExtiInput<'d> {
+        self.input
+    }
 }
 
 impl<'d> embedded_hal_02::digital::v2::InputPin for ExtiInput<'d> {
@@ -232,23 +369,7 @@ struct ExtiInputFuture<'a> {
 
 impl<'a> ExtiInputFuture<'a> {
     fn new(pin: u8, port: u8, rising: bool, falling: bool) -> Self {
-        critical_section::with(|_| {
-            let pin = pin as usize;
-            exticr_regs().exticr(pin / 4).modify(|w| w.set_exti(pin % 4, port));
-            EXTI.rtsr(0).modify(|w| w.set_line(pin, rising));
-            EXTI.ftsr(0).modify(|w| w.set_line(pin, falling));
-
-            // clear pending bit
-            #[cfg(not(any(exti_c0, exti_g0, exti_u0, exti_l5, exti_u5, exti_h5, exti_h50)))]
-            EXTI.pr(0).write(|w| w.set_line(pin, true));
-            #[cfg(any(exti_c0, exti_g0, exti_u0, exti_l5, exti_u5, exti_h5, exti_h50))]
-            {
-                EXTI.rpr(0).write(|w| w.set_line(pin, true));
-                EXTI.fpr(0).write(|w| w.set_line(pin, true));
-            }
-
-            cpu_regs().imr(0).modify(|w| w.set_line(pin, true));
-        });
+        critical_section::with(|_| enable_line(pin, port, rising, falling));
 
         Self {
             pin,
@@ -281,6 +402,77 @@ impl<'a> Future for ExtiInputFuture<'a> {
     }
 }
 
+/// Route the line of `pin` to `port`, and enable its interrupt on the selected edges.
+///
+/// Must be called in a critical section.
+fn enable_line(pin: u8, port: u8, rising: bool, falling: bool) {
+    let pin = pin as usize;
+    exticr_regs().exticr(pin / 4).modify(|w| w.set_exti(pin % 4, port));
+    EXTI.rtsr(0).modify(|w| w.set_line(pin, rising));
+    EXTI.ftsr(0).modify(|w| w.set_line(pin, falling));
+
+    // clear pending bit
+    #[cfg(not(any(exti_c0, exti_g0, exti_u0, exti_l5, exti_u5, exti_h5, exti_h50)))]
+    EXTI.pr(0).write(|w| w.set_line(pin, true));
+    #[cfg(any(exti_c0, exti_g0, exti_u0, exti_l5, exti_u5, exti_h5, exti_h50))]
+    {
+        EXTI.rpr(0).write(|w| w.set_line(pin, true));
+        EXTI.fpr(0).write(|w| w.set_line(pin, true));
+    }
+
+    cpu_regs().imr(0).modify(|w| w.set_line(pin, true));
+}
+
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+struct ExtiGroupFuture<'a> {
+    lines: u32,
+    phantom: PhantomData<&'a mut AnyPin>,
+}
+
+impl<'a> ExtiGroupFuture<'a> {
+    fn new(pins: impl Iterator<Item = (u8, u8)>, rising: bool, falling: bool) -> Self {
+        let mut lines = 0;
+        critical_section::with(|_| {
+            for (pin, port) in pins {
+                enable_line(pin, port, rising, falling);
+                lines |= 1 << pin;
+            }
+        });
+
+        Self {
+            lines,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<'a> Drop for ExtiGroupFuture<'a> {
+    fn drop(&mut self) {
+        critical_section::with(|_| {
+            cpu_regs().imr(0).modify(|w| w.0 &= !self.lines);
+        });
+    }
+}
+
+impl<'a> Future for ExtiGroupFuture<'a> {
+    /// Lines which fired.
+    type Output = u32;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        for line in BitIter(self.lines) {
+            EXTI_WAKERS[line as usize].register(cx.waker());
+        }
+
+        // The interrupt handler masks the lines which fired.
+        let fired = self.lines & !cpu_regs().imr(0).read().0;
+        if fired != 0 {
+            Poll::Ready(fired)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
 macro_rules! foreach_exti_irq {
     ($action:ident) => {
         foreach_interrupt!(
@@ -147,6 +147,8 @@ pub(super) async unsafe fn erase_sectored(base: u32, from: u32, to: u32) -> Resu
 }
 
 foreach_flash_region! {
+    // The OTP region can't be erased, see the `otp` module.
+    (OTPRegion, $write_size:literal, $erase_size:literal) => {};
     ($type_name:ident, $write_size:literal, $erase_size:literal) => {
         impl crate::_generated::flash_regions::$type_name<'_, Async> {
             /// Async read.
@@ -84,6 +84,10 @@ pub(super) fn blocking_read(base: u32, size: u32, offset: u32, bytes: &mut [u8])
 
     let flash_data = unsafe { core::slice::from_raw_parts(start_address as *const u8, bytes.len()) };
     bytes.copy_from_slice(flash_data);
+
+    #[cfg(any(flash_l4, flash_g4c2, flash_g4c3, flash_g4c4, flash_h7, flash_h7ab))]
+    super::ecc::check_read(start_address, bytes.len() as u32)?;
+
     Ok(())
 }
 
@@ -245,6 +249,8 @@ impl<MODE> embedded_storage::nor_flash::NorFlash for Flash<'_, MODE> {
 }
 
 foreach_flash_region! {
+    // The OTP region can't be erased, see the `otp` module.
+    (OTPRegion, $write_size:literal, $erase_size:literal) => {};
     ($type_name:ident, $write_size:literal, $erase_size:literal) => {
         impl<MODE> crate::_generated::flash_regions::$type_name<'_, MODE> {
             /// Blocking read.
@@ -0,0 +1,313 @@
+//! Flash ECC error detection and reporting.
+//!
+//! Each flash word (a double word on G4 and L4) is protected by an ECC. Single bit errors are
+//! corrected on the fly, while double bit errors can't be corrected.
+//!
+//! - Uncorrectable errors make reads through [`Flash`] return [`Error::Ecc`]. On G4 and L4 they
+//!   raise a non-maskable interrupt (NMI), which must be handled for the read to complete: call
+//!   [`on_nmi`] from the `NonMaskableInt` exception handler.
+//! - Corrected errors can be reported to a callback set with [`set_ecc_callback`], from the FLASH
+//!   interrupt, after enabling it with [`enable_ecc_correction_interrupt`]. The FLASH interrupt must
+//!   be bound to [`InterruptHandler`](super::InterruptHandler). On H7, which has no async flash
+//!   driver, the FLASH interrupt must also be enabled in the NVIC by the application.
+//! - [`Flash::blocking_scrub`] refreshes the pages with corrected errors, before a second error in
+//!   the same flash word makes them uncorrectable.
+
+use core::cell::Cell;
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+
+use super::{ensure_sector_aligned, get_flash_regions, get_sector, Error, Flash, FlashBank, FLASH_BASE};
+#[cfg(any(flash_h7, flash_h7ab))]
+use crate::interrupt;
+
+#[cfg(any(flash_l4, flash_g4c2, flash_g4c3, flash_g4c4))]
+mod hw {
+    use super::EccError;
+    use crate::flash::{FlashBank, BANK1_REGION, FLASH_BASE};
+    use crate::pac;
+
+    #[cfg(flash_l4)]
+    const ADDR_MASK: u32 = 0x7_FFFF;
+    #[cfg(flash_l4)]
+    const BK: u32 = 1 << 19;
+    #[cfg(flash_l4)]
+    const SYSF: u32 = 1 << 20;
+
+    #[cfg(not(flash_l4))]
+    const ADDR_MASK: u32 = 0x1F_FFFF;
+    #[cfg(not(flash_l4))]
+    const BK: u32 = 1 << 21;
+    #[cfg(not(flash_l4))]
+    const SYSF: u32 = 1 << 22;
+
+    const ECCCIE: u32 = 1 << 24;
+    const ECCC: u32 = 1 << 30;
+    const ECCD: u32 = 1 << 31;
+
+    pub const WORD_SIZE: u32 = 8;
+
+    pub fn take() -> Option<EccError> {
+        let eccr = pac::FLASH.eccr().read().0;
+        if eccr & (ECCC | ECCD) == 0 {
+            return None;
+        }
+        // Clear the flags, which are "write 1 to clear", keeping the interrupt enable.
+        pac::FLASH
+            .eccr()
+            .write_value(pac::flash::regs::Eccr(eccr & (ECCC | ECCD | ECCCIE)));
+
+        // BK_ECC is the physical bank, which is mapped second when booting from bank 2.
+        let bank = if (eccr & BK != 0) != swapped() {
+            FlashBank::Bank2
+        } else {
+            FlashBank::Bank1
+        };
+        let bank_offset = if bank == FlashBank::Bank2 { BANK1_REGION.size } else { 0 };
+        Some(EccError {
+            address: FLASH_BASE as u32 + bank_offset + (eccr & ADDR_MASK),
+            bank,
+            system_flash: eccr & SYSF != 0,
+            uncorrectable: eccr & ECCD != 0,
+        })
+    }
+
+    #[cfg(any(flash_l4, flash_g4c3))]
+    fn swapped() -> bool {
+        crate::flash::banks_swapped()
+    }
+
+    #[cfg(not(any(flash_l4, flash_g4c3)))]
+    fn swapped() -> bool {
+        false
+    }
+
+    pub fn corrected_pending() -> bool {
+        pac::FLASH.eccr().read().0 & ECCC != 0
+    }
+
+    pub fn uncorrectable_pending() -> bool {
+        pac::FLASH.eccr().read().0 & ECCD != 0
+    }
+
+    pub fn interrupt_enabled() -> bool {
+        pac::FLASH.eccr().read().0 & ECCCIE != 0
+    }
+
+    pub fn set_interrupt_enabled(enabled: bool) {
+        pac::FLASH.eccr().modify(|w| {
+            // Don't clear pending flags.
+            w.0 = (w.0 & !(ECCC | ECCD | ECCCIE)) | if enabled { ECCCIE } else { 0 };
+        });
+    }
+}
+
+#[cfg(any(flash_h7, flash_h7ab))]
+mod hw {
+    use super::EccError;
+    use crate::flash::{FlashBank, BANK1_REGION, FLASH_BASE, FLASH_REGIONS, WRITE_SIZE};
+    use crate::pac;
+
+    pub const WORD_SIZE: u32 = WRITE_SIZE as u32;
+
+    fn banks() -> impl Iterator<Item = (FlashBank, pac::flash::Bank)> {
+        // The bank registers follow the address ranges, whether the banks are swapped or not.
+        let count = if FLASH_REGIONS.len() >= 2 { 2 } else { 1 };
+        [FlashBank::Bank1, FlashBank::Bank2]
+            .into_iter()
+            .zip([pac::FLASH.bank(0), pac::FLASH.bank(1)])
+            .take(count)
+    }
+
+    pub fn take() -> Option<EccError> {
+        banks().find_map(|(bank, regs)| {
+            let sr = regs.sr().read();
+            if !sr.sneccerr1() && !sr.dbeccerr() {
+                return None;
+            }
+            let word = regs.far().read().fail_ecc_addr() as u32;
+            regs.ccr().write(|w| {
+                w.set_clr_sneccerr(true);
+                w.set_clr_dbeccerr(true);
+            });
+
+            let bank_offset = if bank == FlashBank::Bank2 { BANK1_REGION.size } else { 0 };
+            Some(EccError {
+                address: FLASH_BASE as u32 + bank_offset + word * WORD_SIZE,
+                bank,
+                system_flash: false,
+                uncorrectable: sr.dbeccerr(),
+            })
+        })
+    }
+
+    pub fn corrected_pending() -> bool {
+        banks().any(|(_, regs)| regs.sr().read().sneccerr1())
+    }
+
+    pub fn uncorrectable_pending() -> bool {
+        banks().any(|(_, regs)| regs.sr().read().dbeccerr())
+    }
+
+    pub fn interrupt_enabled() -> bool {
+        pac::FLASH.bank(0).cr().read().sneccerrie()
+    }
+
+    pub fn set_interrupt_enabled(enabled: bool) {
+        for (_, regs) in banks() {
+            regs.cr().modify(|w| w.set_sneccerrie(enabled));
+        }
+    }
+}
+
+static CALLBACK: Mutex<CriticalSectionRawMutex, Cell<Option<fn(EccError)>>> = Mutex::new(Cell::new(None));
+static PENDING: Mutex<CriticalSectionRawMutex, Cell<Option<EccError>>> = Mutex::new(Cell::new(None));
+
+/// Flash ECC error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct EccError {
+    /// Absolute address of the failing flash word. Only valid if `system_flash` is false.
+    pub address: u32,
+    /// Bank mapped at `address`.
+    pub bank: FlashBank,
+    /// Whether the error is in the system flash, instead of the user flash.
+    pub system_flash: bool,
+    /// Whether the error couldn't be corrected.
+    pub uncorrectable: bool,
+}
+
+impl EccError {
+    fn contains(&self, start: u32, len: u32) -> bool {
+        !self.system_flash && self.address + hw::WORD_SIZE > start && self.address < start + len
+    }
+}
+
+/// Read and clear the ECC error flags.
+pub fn take_ecc_error() -> Option<EccError> {
+    critical_section::with(|cs| hw::take().or_else(|| PENDING.borrow(cs).take()))
+}
+
+/// Set a callback called with the ECC errors reported from the FLASH interrupt and the NMI.
+///
+/// The callback runs in interrupt context.
+pub fn set_ecc_callback(callback: Option<fn(EccError)>) {
+    CALLBACK.lock(|c| c.set(callback));
+}
+
+/// Enable or disable the FLASH interrupt on corrected ECC errors.
+pub fn enable_ecc_correction_interrupt(enabled: bool) {
+    hw::set_interrupt_enabled(enabled);
+}
+
+/// Handle an uncorrectable ECC error, which raises the NMI.
+///
+/// Call this from the `NonMaskableInt` exception handler. The error is reported to the callback set
+/// with [`set_ecc_callback`], and is returned by the flash read that caused it.
+#[cfg(any(flash_l4, flash_g4c2, flash_g4c3, flash_g4c4))]
+pub fn on_nmi() -> Option<EccError> {
+    let error = take_ecc_error()?;
+    if error.uncorrectable {
+        PENDING.lock(|p| p.set(Some(error)));
+    }
+    report(error);
+    Some(error)
+}
+
+pub(super) fn on_interrupt() {
+    if hw::corrected_pending() {
+        if let Some(error) = take_ecc_error() {
+            report(error);
+        }
+    }
+}
+
+fn report(error: EccError) {
+    if let Some(callback) = CALLBACK.lock(|c| c.get()) {
+        callback(error);
+    }
+}
+
+/// Check for uncorrectable errors after reading `len` bytes at `start`.
+pub(super) fn check_read(start: u32, len: u32) -> Result<(), Error> {
+    let uncorrectable = critical_section::with(|cs| {
+        let pending = PENDING.borrow(cs);
+        match pending.get() {
+            Some(error) if error.contains(start, len) => {
+                pending.set(None);
+                true
+            }
+            _ => hw::uncorrectable_pending() && take_ecc_error().is_some_and(|e| e.contains(start, len)),
+        }
+    });
+    if uncorrectable {
+        Err(Error::Ecc)
+    } else {
+        Ok(())
+    }
+}
+
+impl<MODE> Flash<'_, MODE> {
+    /// Scrub the flash from `from` to `to`, returning the number of refreshed pages.
+    ///
+    /// Each page is read into `buf`, which must be as large as the largest page in the range, or
+    /// [`Error::Size`] is returned. If a corrected ECC error is detected, the page is erased and
+    /// rewritten with the corrected data. Uncorrectable errors return [`Error::Ecc`].
+    ///
+    /// The range must not contain the running code, as its page might be erased. Corrected errors
+    /// are not reported to the ECC callback while scrubbing.
+    ///
+    /// NOTE: `from` and `to` are offsets from the flash start, NOT an absolute address.
+    pub fn blocking_scrub(&mut self, from: u32, to: u32, buf: &mut [u8]) -> Result<u32, Error> {
+        let regions = get_flash_regions();
+        let start_address = FLASH_BASE as u32 + from;
+        let end_address = FLASH_BASE as u32 + to;
+        ensure_sector_aligned(start_address, end_address, regions)?;
+
+        let interrupt_enabled = hw::interrupt_enabled();
+        enable_ecc_correction_interrupt(false);
+        let mut refreshed = 0;
+        let mut address = start_address;
+        let result = loop {
+            if address >= end_address {
+                break Ok(refreshed);
+            }
+            let sector = get_sector(address, regions);
+            let Some(data) = buf.get_mut(..sector.size as usize) else {
+                break Err(Error::Size);
+            };
+            let offset = address - FLASH_BASE as u32;
+
+            // Clear errors from other reads.
+            take_ecc_error();
+            if let Err(e) = self.blocking_read(offset, data) {
+                break Err(e);
+            }
+            if take_ecc_error().is_some_and(|e| e.contains(address, sector.size)) {
+                trace!("Refreshing sector: {:?}", sector);
+                if let Err(e) = self
+                    .blocking_erase(offset, offset + sector.size)
+                    .and_then(|_| self.blocking_write(offset, data))
+                {
+                    break Err(e);
+                }
+                refreshed += 1;
+            }
+            address += sector.size;
+        };
+        enable_ecc_correction_interrupt(interrupt_enabled);
+        result
+    }
+}
+
+/// Interrupt handler
+#[cfg(any(flash_h7, flash_h7ab))]
+pub struct InterruptHandler;
+
+#[cfg(any(flash_h7, flash_h7ab))]
+impl interrupt::typelevel::Handler<crate::interrupt::typelevel::FLASH> for InterruptHandler {
+    unsafe fn on_interrupt() {
+        on_interrupt();
+    }
+}
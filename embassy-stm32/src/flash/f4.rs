@@ -165,7 +165,7 @@ pub(crate) async fn wait_ready() -> Result<(), Error> {
     .await
 }
 
-unsafe fn blocking_wait_ready() -> Result<(), Error> {
+pub(crate) unsafe fn blocking_wait_ready() -> Result<(), Error> {
     loop {
         let sr = pac::FLASH.sr().read();
 
@@ -2,11 +2,28 @@ use core::ptr::write_volatile;
 use core::sync::atomic::{fence, Ordering};
 
 use cortex_m::interrupt;
+use embassy_sync::waitqueue::AtomicWaker;
+use pac::flash::regs::Sr;
 
 use super::{FlashSector, WRITE_SIZE};
 use crate::flash::Error;
 use crate::pac;
 
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+pub(crate) unsafe fn on_interrupt() {
+    // Clear IRQ flags
+    pac::FLASH.sr().write(|w| {
+        w.set_operr(true);
+        w.set_eop(true);
+    });
+
+    #[cfg(any(flash_g4c2, flash_g4c3, flash_g4c4))]
+    super::ecc::on_interrupt();
+
+    WAKER.wake();
+}
+
 pub(crate) unsafe fn lock() {
     pac::FLASH.cr().modify(|w| w.set_lock(true));
 }
@@ -21,6 +38,23 @@ pub(crate) unsafe fn unlock() {
     }
 }
 
+pub(crate) unsafe fn enable_write() {
+    assert_eq!(0, WRITE_SIZE % 4);
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(true);
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+}
+
+pub(crate) unsafe fn disable_write() {
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+}
+
 pub(crate) unsafe fn enable_blocking_write() {
     assert_eq!(0, WRITE_SIZE % 4);
     pac::FLASH.cr().write(|w| w.set_pg(true));
@@ -30,7 +64,17 @@ pub(crate) unsafe fn disable_blocking_write() {
     pac::FLASH.cr().write(|w| w.set_pg(false));
 }
 
+pub(crate) async unsafe fn write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready().await
+}
+
 pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready_blocking()
+}
+
+unsafe fn write_start(start_address: u32, buf: &[u8; WRITE_SIZE]) {
     let mut address = start_address;
     for val in buf.chunks(4) {
         write_volatile(address as *mut u32, u32::from_le_bytes(unwrap!(val.try_into())));
@@ -39,51 +83,87 @@ pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE])
         // prevents parallelism errors
         fence(Ordering::SeqCst);
     }
+}
 
-    wait_ready_blocking()
+pub(crate) async unsafe fn erase_sector(sector: &FlashSector) -> Result<(), Error> {
+    wait_busy();
+    clear_all_err();
+
+    pac::FLASH.cr().modify(|w| {
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+    interrupt::free(|_| erase_start(sector));
+
+    let ret: Result<(), Error> = wait_ready().await;
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+    clear_all_err();
+    ret
 }
 
 pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), Error> {
-    let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
     wait_busy();
     clear_all_err();
 
-    interrupt::free(|_| {
-        pac::FLASH.cr().modify(|w| {
-            w.set_per(true);
-            #[cfg(any(flash_g0x0, flash_g0x1, flash_g4c3))]
-            w.set_bker(sector.bank == crate::flash::FlashBank::Bank2);
-            #[cfg(flash_g0x0)]
-            w.set_pnb(idx as u16);
-            #[cfg(not(flash_g0x0))]
-            w.set_pnb(idx as u8);
-            w.set_strt(true);
-        });
-    });
+    interrupt::free(|_| erase_start(sector));
 
     let ret: Result<(), Error> = wait_ready_blocking();
     pac::FLASH.cr().modify(|w| w.set_per(false));
     ret
 }
 
-pub(crate) unsafe fn wait_ready_blocking() -> Result<(), Error> {
-    while pac::FLASH.sr().read().bsy() {}
+fn erase_start(sector: &FlashSector) {
+    let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(true);
+        #[cfg(any(flash_g0x0, flash_g0x1))]
+        w.set_bker(sector.bank == crate::flash::FlashBank::Bank2);
+        // BKER selects the physical bank, which is mapped second when booting from bank 2.
+        #[cfg(flash_g4c3)]
+        w.set_bker((sector.bank == crate::flash::FlashBank::Bank2) != banks_swapped());
+        #[cfg(flash_g0x0)]
+        w.set_pnb(idx as u16);
+        #[cfg(not(flash_g0x0))]
+        w.set_pnb(idx as u8);
+        w.set_strt(true);
+    });
+}
 
-    let sr = pac::FLASH.sr().read();
+pub(crate) async fn wait_ready() -> Result<(), Error> {
+    use core::future::poll_fn;
+    use core::task::Poll;
 
-    if sr.progerr() {
-        return Err(Error::Prog);
-    }
+    poll_fn(|cx| {
+        WAKER.register(cx.waker());
 
-    if sr.wrperr() {
-        return Err(Error::Protected);
-    }
+        if is_busy() {
+            Poll::Pending
+        } else {
+            Poll::Ready(get_result(pac::FLASH.sr().read()))
+        }
+    })
+    .await
+}
 
-    if sr.pgaerr() {
-        return Err(Error::Unaligned);
-    }
+pub(crate) unsafe fn wait_ready_blocking() -> Result<(), Error> {
+    wait_busy();
+    get_result(pac::FLASH.sr().read())
+}
 
-    Ok(())
+fn get_result(sr: Sr) -> Result<(), Error> {
+    if sr.progerr() {
+        Err(Error::Prog)
+    } else if sr.wrperr() {
+        Err(Error::Protected)
+    } else if sr.pgaerr() {
+        Err(Error::Unaligned)
+    } else {
+        Ok(())
+    }
 }
 
 pub(crate) unsafe fn clear_all_err() {
@@ -93,13 +173,55 @@ pub(crate) unsafe fn clear_all_err() {
 }
 
 #[cfg(any(flash_g0x0, flash_g0x1))]
-fn wait_busy() {
-    while pac::FLASH.sr().read().bsy() | pac::FLASH.sr().read().bsy2() {}
+fn is_busy() -> bool {
+    pac::FLASH.sr().read().bsy() | pac::FLASH.sr().read().bsy2()
 }
 
 #[cfg(not(any(flash_g0x0, flash_g0x1)))]
-fn wait_busy() {
-    while pac::FLASH.sr().read().bsy() {}
+fn is_busy() -> bool {
+    pac::FLASH.sr().read().bsy()
+}
+
+pub(crate) fn wait_busy() {
+    while is_busy() {}
+}
+
+/// Get whether flash bank 2 is mapped at the start of the flash, because the chip booted from it.
+#[cfg(flash_g4c3)]
+pub fn banks_swapped() -> bool {
+    pac::SYSCFG.memrmp().read().fb_mode()
+}
+
+/// Toggle the BFB2 option, to boot from the other flash bank.
+///
+/// This allows the application to write a new firmware blob into bank 2, then
+/// boot from it. When BFB2 is set the system bootloader jumps to bank 2 if it holds a
+/// valid firmware and maps it at the start of the flash, see [`banks_swapped`].
+///
+/// Swap does not take effect until the option bytes are reloaded, on power-on reset or with
+/// [`Flash::reload_option_bytes`](crate::flash::Flash::reload_option_bytes), which resets the chip.
+#[cfg(flash_g4c3)]
+pub fn perform_bank_swap() {
+    unsafe {
+        wait_busy();
+        clear_all_err();
+        unlock();
+
+        // unlock OPTLOCK
+        if pac::FLASH.cr().read().optlock() {
+            pac::FLASH.optkeyr().write_value(0x0819_2A3B);
+            pac::FLASH.optkeyr().write_value(0x4C5D_6E7F);
+        }
+
+        // toggle BFB2 option
+        pac::FLASH.optr().modify(|w| w.set_bfb2(!w.bfb2()));
+        pac::FLASH.cr().modify(|w| w.set_optstrt(true));
+        wait_busy();
+
+        // re-lock OPTLOCK
+        pac::FLASH.cr().modify(|w| w.set_optlock(true));
+        lock();
+    }
 }
 
 #[cfg(all(bank_setup_configurable, any(flash_g4c2, flash_g4c3, flash_g4c4)))]
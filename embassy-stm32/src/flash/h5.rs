@@ -97,12 +97,17 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
     }
     clear_all_err();
 
+    // BKSEL selects the physical bank, which is swapped with the logical bank when SWAP_BANK is set.
+    let physical_bank2 = match sector.bank {
+        crate::flash::FlashBank::Bank1 => banks_swapped(),
+        crate::flash::FlashBank::Bank2 => !banks_swapped(),
+        _ => unreachable!(),
+    };
+
     pac::FLASH.nscr().modify(|r| {
-        // TODO: later check bank swap
-        r.set_bksel(match sector.bank {
-            crate::flash::FlashBank::Bank1 => stm32_metapac::flash::vals::NscrBksel::B_0X0,
-            crate::flash::FlashBank::Bank2 => stm32_metapac::flash::vals::NscrBksel::B_0X1,
-            _ => unreachable!(),
+        r.set_bksel(match physical_bank2 {
+            false => stm32_metapac::flash::vals::NscrBksel::B_0X0,
+            true => stm32_metapac::flash::vals::NscrBksel::B_0X1,
         });
         r.set_snb(sector.index_in_bank);
         r.set_ser(true);
@@ -130,6 +135,45 @@ pub(crate) unsafe fn clear_all_err() {
     pac::FLASH.nssr().modify(|_| {})
 }
 
+/// Get the current SWAP_BANK option.
+///
+/// This value is only loaded on system or power-on reset. `perform_bank_swap()`
+/// will not reflect here.
+pub fn banks_swapped() -> bool {
+    pac::FLASH.optcr().read().swap_bank()
+}
+
+/// Logical, persistent swap of flash banks 1 and 2.
+///
+/// This allows the application to write a new firmware blob into bank 2, then
+/// swap the banks and perform a reset, loading the new firmware.
+///
+/// Swap does not take effect until system or power-on reset.
+pub fn perform_bank_swap() {
+    while pac::FLASH.nssr().read().bsy() {}
+
+    unsafe {
+        clear_all_err();
+    }
+
+    // unlock OPTLOCK
+    if pac::FLASH.optcr().read().optlock() {
+        pac::FLASH.optkeyr().write_value(0x0819_2A3B);
+        pac::FLASH.optkeyr().write_value(0x4C5D_6E7F);
+    }
+    while pac::FLASH.optcr().read().optlock() {}
+
+    // toggle SWAP_BANK option
+    pac::FLASH.optsr_prg().modify(|w| w.set_swap_bank(!banks_swapped()));
+
+    // load option bytes
+    pac::FLASH.optcr().modify(|w| w.set_optstrt(true));
+    while pac::FLASH.optcr().read().optstrt() {}
+
+    // re-lock OPTLOCK
+    pac::FLASH.optcr().modify(|w| w.set_optlock(true));
+}
+
 unsafe fn blocking_wait_ready() -> Result<(), Error> {
     loop {
         let sr = pac::FLASH.nssr().read();
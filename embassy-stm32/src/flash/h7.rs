@@ -42,7 +42,8 @@ pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE])
     } else {
         pac::FLASH.bank(1)
     };
-    bank.cr().write(|w| {
+    // Modify, to keep the ECC interrupt enables.
+    bank.cr().modify(|w| {
         w.set_pg(true);
         #[cfg(flash_h7)]
         w.set_psize(2); // 32 bits at once
@@ -72,7 +73,7 @@ pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE])
     cortex_m::asm::dsb();
     fence(Ordering::SeqCst);
 
-    bank.cr().write(|w| w.set_pg(false));
+    bank.cr().modify(|w| w.set_pg(false));
 
     unwrap!(res)
 }
@@ -101,6 +102,43 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
     ret
 }
 
+/// Get the current SWAP_BANK option.
+///
+/// This value is only loaded on system or power-on reset. `perform_bank_swap()`
+/// will not reflect here.
+pub fn banks_swapped() -> bool {
+    is_dual_bank() && pac::FLASH.optcr().read().swap_bank()
+}
+
+/// Logical, persistent swap of flash banks 1 and 2.
+///
+/// This allows the application to write a new firmware blob into bank 2, then
+/// swap the banks and perform a reset, loading the new firmware.
+///
+/// Swap does not take effect until system or power-on reset.
+///
+/// Panics on single bank chips.
+pub fn perform_bank_swap() {
+    assert!(is_dual_bank());
+
+    // unlock OPTLOCK
+    if pac::FLASH.optcr().read().optlock() {
+        pac::FLASH.optkeyr().write_value(0x0819_2A3B);
+        pac::FLASH.optkeyr().write_value(0x4C5D_6E7F);
+    }
+    while pac::FLASH.optcr().read().optlock() {}
+
+    // toggle SWAP_BANK option
+    pac::FLASH.optsr_prg().modify(|w| w.set_swap_bank_opt(!banks_swapped()));
+
+    // load option bytes
+    pac::FLASH.optcr().modify(|w| w.set_optstart(true));
+    while pac::FLASH.optsr_cur().read().opt_busy() {}
+
+    // re-lock OPTLOCK
+    pac::FLASH.optcr().modify(|w| w.set_optlock(true));
+}
+
 pub(crate) unsafe fn clear_all_err() {
     bank_clear_all_err(pac::FLASH.bank(0));
     bank_clear_all_err(pac::FLASH.bank(1));
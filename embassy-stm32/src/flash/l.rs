@@ -1,10 +1,30 @@
 use core::ptr::write_volatile;
 use core::sync::atomic::{fence, Ordering};
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+use embassy_sync::waitqueue::AtomicWaker;
+
 use super::{FlashSector, WRITE_SIZE};
 use crate::flash::Error;
 use crate::pac;
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+static WAKER: AtomicWaker = AtomicWaker::new();
+
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) unsafe fn on_interrupt() {
+    // Clear IRQ flags
+    pac::FLASH.sr().write(|w| {
+        w.set_operr(true);
+        w.set_eop(true);
+    });
+
+    #[cfg(flash_l4)]
+    super::ecc::on_interrupt();
+
+    WAKER.wake();
+}
+
 pub(crate) unsafe fn lock() {
     #[cfg(any(flash_wl, flash_wb, flash_l4))]
     pac::FLASH.cr().modify(|w| w.set_lock(true));
@@ -51,6 +71,25 @@ pub(crate) unsafe fn unlock() {
     }
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) unsafe fn enable_write() {
+    assert_eq!(0, WRITE_SIZE % 4);
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(true);
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+}
+
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) unsafe fn disable_write() {
+    pac::FLASH.cr().write(|w| {
+        w.set_pg(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+}
+
 pub(crate) unsafe fn enable_blocking_write() {
     assert_eq!(0, WRITE_SIZE % 4);
 
@@ -69,7 +108,18 @@ pub(crate) unsafe fn disable_blocking_write() {
     pac::FLASH.nscr().write(|w| w.set_nspg(false));
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) async unsafe fn write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready().await
+}
+
 pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE]) -> Result<(), Error> {
+    write_start(start_address, buf);
+    wait_ready_blocking()
+}
+
+unsafe fn write_start(start_address: u32, buf: &[u8; WRITE_SIZE]) {
     let mut address = start_address;
     for val in buf.chunks(4) {
         write_volatile(address as *mut u32, u32::from_le_bytes(unwrap!(val.try_into())));
@@ -78,8 +128,24 @@ pub(crate) unsafe fn blocking_write(start_address: u32, buf: &[u8; WRITE_SIZE])
         // prevents parallelism errors
         fence(Ordering::SeqCst);
     }
+}
 
-    wait_ready_blocking()
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) async unsafe fn erase_sector(sector: &FlashSector) -> Result<(), Error> {
+    pac::FLASH.cr().modify(|w| {
+        w.set_eopie(true);
+        w.set_errie(true);
+    });
+    erase_start(sector);
+
+    let ret: Result<(), Error> = wait_ready().await;
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(false);
+        w.set_eopie(false);
+        w.set_errie(false);
+    });
+    clear_all_err();
+    ret
 }
 
 pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), Error> {
@@ -93,14 +159,13 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
         write_volatile(sector.start as *mut u32, 0xFFFFFFFF);
     }
 
-    #[cfg(any(flash_wl, flash_wb, flash_l4, flash_l5))]
+    #[cfg(any(flash_wl, flash_wb, flash_l4))]
+    erase_start(sector);
+
+    #[cfg(flash_l5)]
     {
         let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
 
-        #[cfg(flash_l4)]
-        let (idx, bank) = if idx > 255 { (idx - 256, true) } else { (idx, false) };
-
-        #[cfg(flash_l5)]
         let (idx, bank) = if pac::FLASH.optr().read().dbank() {
             if idx > 255 {
                 (idx - 256, Some(true))
@@ -111,19 +176,6 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
             (idx, None)
         };
 
-        #[cfg(not(flash_l5))]
-        pac::FLASH.cr().modify(|w| {
-            w.set_per(true);
-            w.set_pnb(idx as u8);
-            #[cfg(any(flash_wl, flash_wb))]
-            w.set_strt(true);
-            #[cfg(any(flash_l4))]
-            w.set_start(true);
-            #[cfg(any(flash_l4))]
-            w.set_bker(bank);
-        });
-
-        #[cfg(flash_l5)]
         pac::FLASH.nscr().modify(|w| {
             w.set_nsper(true);
             w.set_nspnb(idx as u8);
@@ -152,6 +204,30 @@ pub(crate) unsafe fn blocking_erase_sector(sector: &FlashSector) -> Result<(), E
     ret
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+unsafe fn erase_start(sector: &FlashSector) {
+    let idx = (sector.start - super::FLASH_BASE as u32) / super::BANK1_REGION.erase_size as u32;
+
+    // BKER selects the physical bank, which is mapped second when booting from bank 2.
+    #[cfg(flash_l4)]
+    let (idx, bank) = if idx > 255 {
+        (idx - 256, !banks_swapped())
+    } else {
+        (idx, banks_swapped())
+    };
+
+    pac::FLASH.cr().modify(|w| {
+        w.set_per(true);
+        w.set_pnb(idx as u8);
+        #[cfg(any(flash_wl, flash_wb))]
+        w.set_strt(true);
+        #[cfg(any(flash_l4))]
+        w.set_start(true);
+        #[cfg(any(flash_l4))]
+        w.set_bker(bank);
+    });
+}
+
 pub(crate) unsafe fn clear_all_err() {
     // read and write back the same value.
     // This clears all "write 1 to clear" bits.
@@ -162,6 +238,48 @@ pub(crate) unsafe fn clear_all_err() {
     pac::FLASH.nssr().modify(|_| {});
 }
 
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) async fn wait_ready() -> Result<(), Error> {
+    use core::future::poll_fn;
+    use core::task::Poll;
+
+    poll_fn(|cx| {
+        WAKER.register(cx.waker());
+
+        let sr = pac::FLASH.sr().read();
+        if !sr.bsy() {
+            Poll::Ready(get_result(sr))
+        } else {
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+fn get_result(sr: pac::flash::regs::Sr) -> Result<(), Error> {
+    if sr.progerr() {
+        Err(Error::Prog)
+    } else if sr.wrperr() {
+        Err(Error::Protected)
+    } else if sr.pgaerr() {
+        Err(Error::Unaligned)
+    } else if sr.sizerr() {
+        Err(Error::Size)
+    } else if sr.miserr() {
+        Err(Error::Miss)
+    } else if sr.pgserr() {
+        Err(Error::Seq)
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(any(flash_wl, flash_wb, flash_l4))]
+pub(crate) fn wait_busy() {
+    while pac::FLASH.sr().read().bsy() {}
+}
+
 pub(crate) unsafe fn wait_ready_blocking() -> Result<(), Error> {
     loop {
         #[cfg(not(flash_l5))]
@@ -231,6 +349,44 @@ pub(crate) unsafe fn wait_ready_blocking() -> Result<(), Error> {
     }
 }
 
+/// Get whether flash bank 2 is mapped at the start of the flash, because the chip booted from it.
+#[cfg(flash_l4)]
+pub fn banks_swapped() -> bool {
+    pac::SYSCFG.memrmp().read().fb_mode()
+}
+
+/// Toggle the BFB2 option, to boot from the other flash bank.
+///
+/// This allows the application to write a new firmware blob into bank 2, then
+/// boot from it. When BFB2 is set the system bootloader jumps to bank 2 if it holds a
+/// valid firmware and maps it at the start of the flash, see [`banks_swapped`].
+///
+/// Swap does not take effect until the option bytes are reloaded, on power-on reset or with
+/// [`Flash::reload_option_bytes`](crate::flash::Flash::reload_option_bytes), which resets the chip.
+#[cfg(flash_l4)]
+pub fn perform_bank_swap() {
+    unsafe {
+        wait_busy();
+        clear_all_err();
+        unlock();
+
+        // unlock OPTLOCK
+        if pac::FLASH.cr().read().optlock() {
+            pac::FLASH.optkeyr().write_value(0x0819_2A3B);
+            pac::FLASH.optkeyr().write_value(0x4C5D_6E7F);
+        }
+
+        // toggle BFB2 option
+        pac::FLASH.optr().modify(|w| w.set_bfb2(!w.bfb2()));
+        pac::FLASH.cr().modify(|w| w.set_optstrt(true));
+        wait_busy();
+
+        // re-lock OPTLOCK
+        pac::FLASH.cr().modify(|w| w.set_optlock(true));
+        lock();
+    }
+}
+
 #[cfg(all(bank_setup_configurable, flash_l5))]
 pub(crate) fn check_bank_setup() {
     if cfg!(feature = "single-bank") && pac::FLASH.optr().read().dbank() {
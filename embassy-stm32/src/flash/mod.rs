@@ -1,20 +1,37 @@
 //! Flash memory (FLASH)
 use embedded_storage::nor_flash::{NorFlashError, NorFlashErrorKind};
 
-#[cfg(flash_f4)]
+#[cfg(any(
+    flash_f4, flash_g0x0, flash_g0x1, flash_g4c2, flash_g4c3, flash_g4c4, flash_l4, flash_wb, flash_wl
+))]
 mod asynch;
 #[cfg(flash)]
 mod common;
+#[cfg(any(flash_l4, flash_g4c2, flash_g4c3, flash_g4c4, flash_h7, flash_h7ab))]
+mod ecc;
 #[cfg(eeprom)]
 mod eeprom;
+#[cfg(any(flash_f4, flash_l4, flash_g4c2, flash_g4c3, flash_g4c4))]
+mod option_bytes;
+#[cfg(flash)]
+mod otp;
 
-#[cfg(flash_f4)]
+#[cfg(any(
+    flash_f4, flash_g0x0, flash_g0x1, flash_g4c2, flash_g4c3, flash_g4c4, flash_l4, flash_wb, flash_wl
+))]
 pub use asynch::InterruptHandler;
 #[cfg(flash)]
 pub use common::*;
+#[cfg(any(flash_l4, flash_g4c2, flash_g4c3, flash_g4c4, flash_h7, flash_h7ab))]
+pub use ecc::*;
 #[cfg(eeprom)]
 #[allow(unused_imports)]
 pub use eeprom::*;
+#[cfg(any(flash_f4, flash_l4, flash_g4c2, flash_g4c3, flash_g4c4))]
+pub use option_bytes::*;
+#[cfg(flash)]
+#[allow(unused_imports)]
+pub use otp::*;
 
 pub use crate::_generated::flash_regions::*;
 #[cfg(eeprom)]
@@ -132,6 +149,8 @@ pub enum Error {
     Protected,
     Unaligned,
     Parallelism,
+    /// Uncorrectable ECC error
+    Ecc,
 }
 
 impl NorFlashError for Error {
@@ -0,0 +1,342 @@
+//! Option bytes.
+//!
+//! The option bytes configure the readout protection, brownout reset, watchdog and boot
+//! behaviour of the chip. They're read with [`OptionBytes::read`], modified in memory, and
+//! programmed with [`Flash::blocking_program_option_bytes`].
+//!
+//! PLEASE READ THE REFERENCE MANUAL before changing the option bytes: some configurations, like
+//! readout protection level 2 or write protecting the running code, can't be undone.
+
+use core::sync::atomic::{fence, Ordering};
+
+use embassy_hal_internal::drop::OnDrop;
+
+use super::{family, Error, Flash};
+use crate::pac;
+
+/// Readout protection level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RdpLevel {
+    /// No protection.
+    Level0,
+    /// Flash memory can't be read by a debugger or when booting from RAM or the system bootloader.
+    ///
+    /// Going back to level 0 mass erases the flash.
+    Level1,
+    /// All debug features are permanently disabled. **This is irreversible.**
+    Level2,
+}
+
+impl RdpLevel {
+    fn from_bits(bits: u8) -> Self {
+        match bits {
+            0xAA => Self::Level0,
+            0xCC => Self::Level2,
+            _ => Self::Level1,
+        }
+    }
+
+    fn to_bits(self) -> u8 {
+        match self {
+            Self::Level0 => 0xAA,
+            Self::Level1 => 0xBB,
+            Self::Level2 => 0xCC,
+        }
+    }
+}
+
+/// Write protection area of bank 1, see [`OptionBytes::write_protection`].
+#[cfg(not(flash_f4))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum WrpArea {
+    /// Area A.
+    A,
+    /// Area B.
+    B,
+}
+
+/// Option bytes values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct OptionBytes {
+    #[cfg(not(flash_f4))]
+    optr: u32,
+    #[cfg(not(flash_f4))]
+    wrp: [u32; 2],
+    #[cfg(flash_f4)]
+    optcr: u32,
+}
+
+const RDP_MASK: u32 = 0xFF;
+
+#[cfg(not(flash_f4))]
+mod bits {
+    pub const RDP: u32 = 0;
+    pub const BOR_LEV: u32 = 8;
+    pub const BOR_LEV_MASK: u32 = 0x7;
+    pub const IWDG_SW: u32 = 16;
+    pub const N_BOOT1: u32 = 23;
+    pub const N_SWBOOT0: u32 = 26;
+    pub const N_BOOT0: u32 = 27;
+    pub const WRP_END: u32 = 16;
+    #[cfg(flash_l4)]
+    pub const WRP_PAGE_MASK: u32 = 0xFF;
+    #[cfg(not(flash_l4))]
+    pub const WRP_PAGE_MASK: u32 = 0x7F;
+}
+
+#[cfg(flash_f4)]
+mod bits {
+    pub const RDP: u32 = 8;
+    pub const BOR_LEV: u32 = 2;
+    pub const BOR_LEV_MASK: u32 = 0x3;
+    pub const WDG_SW: u32 = 5;
+    pub const N_WRP: u32 = 16;
+    /// OPTLOCK and OPTSTRT
+    pub const CONTROL_MASK: u32 = 0x3;
+}
+
+impl OptionBytes {
+    /// Read the current option bytes.
+    pub fn read() -> Self {
+        Self {
+            #[cfg(not(flash_f4))]
+            optr: pac::FLASH.optr().read().0,
+            #[cfg(not(flash_f4))]
+            wrp: [pac::FLASH.wrp1ar().read().0, pac::FLASH.wrp1br().read().0],
+            #[cfg(flash_f4)]
+            optcr: pac::FLASH.optcr().read().0,
+        }
+    }
+
+    #[cfg(not(flash_f4))]
+    fn word(&mut self) -> &mut u32 {
+        &mut self.optr
+    }
+
+    #[cfg(flash_f4)]
+    fn word(&mut self) -> &mut u32 {
+        &mut self.optcr
+    }
+
+    fn field(mut self, offset: u32, mask: u32) -> u32 {
+        (*self.word() >> offset) & mask
+    }
+
+    fn set_field(&mut self, offset: u32, mask: u32, value: u32) {
+        let word = self.word();
+        *word = (*word & !(mask << offset)) | ((value & mask) << offset);
+    }
+
+    fn bit(self, bit: u32) -> bool {
+        self.field(bit, 1) != 0
+    }
+
+    fn set_bit(&mut self, bit: u32, value: bool) {
+        self.set_field(bit, 1, value as u32)
+    }
+
+    /// Readout protection level.
+    pub fn rdp(self) -> RdpLevel {
+        RdpLevel::from_bits(self.field(bits::RDP, RDP_MASK) as u8)
+    }
+
+    /// Set the readout protection level to level 0 or 1.
+    ///
+    /// Going from level 1 to level 0 mass erases the flash, including the running firmware.
+    ///
+    /// Panics for level 2, use [`set_rdp_level2_irreversible`](Self::set_rdp_level2_irreversible).
+    pub fn set_rdp(&mut self, level: RdpLevel) {
+        assert!(level != RdpLevel::Level2, "use set_rdp_level2_irreversible");
+        self.set_rdp_bits(level);
+    }
+
+    /// Set the readout protection level to level 2.
+    ///
+    /// **This is irreversible**: once programmed, the option bytes can never be changed again and
+    /// debug access is permanently disabled.
+    pub fn set_rdp_level2_irreversible(&mut self) {
+        self.set_rdp_bits(RdpLevel::Level2);
+    }
+
+    fn set_rdp_bits(&mut self, level: RdpLevel) {
+        self.set_field(bits::RDP, RDP_MASK, level.to_bits() as u32)
+    }
+
+    /// Brownout reset threshold level, see the reference manual for the voltages.
+    pub fn bor_level(self) -> u8 {
+        self.field(bits::BOR_LEV, bits::BOR_LEV_MASK) as u8
+    }
+
+    /// Set the brownout reset threshold level.
+    pub fn set_bor_level(&mut self, level: u8) {
+        assert!(level as u32 <= bits::BOR_LEV_MASK);
+        self.set_field(bits::BOR_LEV, bits::BOR_LEV_MASK, level as u32)
+    }
+
+    /// Whether the independent watchdog is started by software, instead of automatically on reset.
+    pub fn iwdg_sw(self) -> bool {
+        #[cfg(not(flash_f4))]
+        return self.bit(bits::IWDG_SW);
+        #[cfg(flash_f4)]
+        return self.bit(bits::WDG_SW);
+    }
+
+    /// Set whether the independent watchdog is started by software, instead of automatically on reset.
+    pub fn set_iwdg_sw(&mut self, software: bool) {
+        #[cfg(not(flash_f4))]
+        self.set_bit(bits::IWDG_SW, software);
+        #[cfg(flash_f4)]
+        self.set_bit(bits::WDG_SW, software);
+    }
+
+    /// The nBOOT0 option, used instead of the BOOT0 pin when [`n_swboot0`](Self::n_swboot0) is cleared.
+    #[cfg(not(flash_f4))]
+    pub fn n_boot0(self) -> bool {
+        self.bit(bits::N_BOOT0)
+    }
+
+    /// Set the nBOOT0 option.
+    #[cfg(not(flash_f4))]
+    pub fn set_n_boot0(&mut self, value: bool) {
+        self.set_bit(bits::N_BOOT0, value)
+    }
+
+    /// The nSWBOOT0 option: when set, the BOOT0 pin selects the boot mode, otherwise the nBOOT0 option.
+    #[cfg(not(flash_f4))]
+    pub fn n_swboot0(self) -> bool {
+        self.bit(bits::N_SWBOOT0)
+    }
+
+    /// Set the nSWBOOT0 option.
+    #[cfg(not(flash_f4))]
+    pub fn set_n_swboot0(&mut self, value: bool) {
+        self.set_bit(bits::N_SWBOOT0, value)
+    }
+
+    /// The nBOOT1 option, selecting between system memory and SRAM boot when BOOT0 is set.
+    #[cfg(not(flash_f4))]
+    pub fn n_boot1(self) -> bool {
+        self.bit(bits::N_BOOT1)
+    }
+
+    /// Set the nBOOT1 option.
+    #[cfg(not(flash_f4))]
+    pub fn set_n_boot1(&mut self, value: bool) {
+        self.set_bit(bits::N_BOOT1, value)
+    }
+
+    /// The range of bank 1 pages write protected by `area`, as inclusive page indices, or `None` if
+    /// the area is disabled.
+    #[cfg(not(flash_f4))]
+    pub fn write_protection(self, area: WrpArea) -> Option<(u8, u8)> {
+        let wrp = self.wrp[area as usize];
+        let start = (wrp & bits::WRP_PAGE_MASK) as u8;
+        let end = ((wrp >> bits::WRP_END) & bits::WRP_PAGE_MASK) as u8;
+        (start <= end).then_some((start, end))
+    }
+
+    /// Write protect the bank 1 pages from `start` to `end` inclusive with `area`, or disable the
+    /// area with `None`.
+    #[cfg(not(flash_f4))]
+    pub fn set_write_protection(&mut self, area: WrpArea, pages: Option<(u8, u8)>) {
+        let (start, end) = pages.unwrap_or((bits::WRP_PAGE_MASK as u8, 0));
+        assert!(start as u32 <= bits::WRP_PAGE_MASK && end as u32 <= bits::WRP_PAGE_MASK);
+        let mask = bits::WRP_PAGE_MASK | (bits::WRP_PAGE_MASK << bits::WRP_END);
+        let wrp = &mut self.wrp[area as usize];
+        *wrp = (*wrp & !mask) | start as u32 | ((end as u32) << bits::WRP_END);
+    }
+
+    /// Whether the sector is write protected. Only sectors 0 to 11 are supported.
+    #[cfg(flash_f4)]
+    pub fn write_protection(self, sector: u8) -> bool {
+        assert!(sector < 12);
+        // nWRP bits are active low
+        !self.bit(bits::N_WRP + sector as u32)
+    }
+
+    /// Set whether the sector is write protected. Only sectors 0 to 11 are supported.
+    #[cfg(flash_f4)]
+    pub fn set_write_protection(&mut self, sector: u8, protected: bool) {
+        assert!(sector < 12);
+        self.set_bit(bits::N_WRP + sector as u32, !protected)
+    }
+}
+
+impl<MODE> Flash<'_, MODE> {
+    /// Program the option bytes.
+    ///
+    /// On F4 the new option bytes take effect after a reset. On the other families they must be
+    /// reloaded with [`reload_option_bytes`](Self::reload_option_bytes), which resets the chip.
+    pub fn blocking_program_option_bytes(&mut self, option_bytes: &OptionBytes) -> Result<(), Error> {
+        critical_section::with(|_| unsafe {
+            family::clear_all_err();
+            fence(Ordering::SeqCst);
+            family::unlock();
+            unlock_option_bytes();
+            fence(Ordering::SeqCst);
+
+            let _on_drop = OnDrop::new(|| {
+                lock_option_bytes();
+                family::lock();
+            });
+
+            #[cfg(not(flash_f4))]
+            {
+                family::wait_busy();
+                pac::FLASH.optr().write_value(pac::flash::regs::Optr(option_bytes.optr));
+                pac::FLASH
+                    .wrp1ar()
+                    .write_value(pac::flash::regs::Wrp1ar(option_bytes.wrp[0]));
+                pac::FLASH
+                    .wrp1br()
+                    .write_value(pac::flash::regs::Wrp1br(option_bytes.wrp[1]));
+                pac::FLASH.cr().modify(|w| w.set_optstrt(true));
+                family::wait_ready_blocking()
+            }
+
+            #[cfg(flash_f4)]
+            {
+                let optcr =
+                    (option_bytes.optcr & !bits::CONTROL_MASK) | (pac::FLASH.optcr().read().0 & bits::CONTROL_MASK);
+                pac::FLASH.optcr().write_value(pac::flash::regs::Optcr(optcr));
+                pac::FLASH.optcr().modify(|w| w.set_optstrt(true));
+                family::blocking_wait_ready()
+            }
+        })
+    }
+
+    /// Reload the option bytes, applying the programmed values. **This resets the chip.**
+    #[cfg(not(flash_f4))]
+    pub fn reload_option_bytes(&mut self) -> ! {
+        unsafe {
+            family::unlock();
+            unlock_option_bytes();
+        }
+        pac::FLASH.cr().modify(|w| w.set_obl_launch(true));
+        loop {
+            cortex_m::asm::nop();
+        }
+    }
+}
+
+unsafe fn unlock_option_bytes() {
+    #[cfg(not(flash_f4))]
+    let locked = pac::FLASH.cr().read().optlock();
+    #[cfg(flash_f4)]
+    let locked = pac::FLASH.optcr().read().optlock();
+
+    if locked {
+        pac::FLASH.optkeyr().write_value(0x0819_2A3B);
+        pac::FLASH.optkeyr().write_value(0x4C5D_6E7F);
+    }
+}
+
+unsafe fn lock_option_bytes() {
+    #[cfg(not(flash_f4))]
+    pac::FLASH.cr().modify(|w| w.set_optlock(true));
+    #[cfg(flash_f4)]
+    pac::FLASH.optcr().modify(|w| w.set_optlock(true));
+}
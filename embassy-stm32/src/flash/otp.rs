@@ -0,0 +1,130 @@
+//! One-time programmable (OTP) area.
+//!
+//! The OTP area can't be erased: each location can only be programmed once, so it is suitable for
+//! data which must never change after production, like serial numbers, keys or calibration
+//! constants. For this reason the OTP region doesn't implement `NorFlash`, and programming
+//! functions are explicitly named as irreversible.
+//!
+//! On F2, F4 and F7 chips, the OTP area is split in 16 blocks of 32 bytes, each of which can be
+//! locked to prevent programming the remaining erased bytes of the block.
+
+#[allow(unused_imports)]
+use super::{blocking_read, blocking_write, write_chunk_with_critical_section, Error, READ_SIZE, WRITE_SIZE};
+
+/// Size of the OTP data, not including the lock bytes.
+#[cfg(any(flash_f2, flash_f4, flash_f7))]
+const OTP_DATA_SIZE: u32 = 512;
+/// Size of a lockable OTP block.
+#[cfg(any(flash_f2, flash_f4, flash_f7))]
+pub const OTP_BLOCK_SIZE: u32 = 32;
+
+foreach_flash_region! {
+    (OTPRegion, $write_size:literal, $erase_size:literal) => {
+        impl<MODE> crate::_generated::flash_regions::OTPRegion<'_, MODE> {
+            fn data_size(&self) -> u32 {
+                #[cfg(any(flash_f2, flash_f4, flash_f7))]
+                return self.0.size.min(OTP_DATA_SIZE);
+                #[cfg(not(any(flash_f2, flash_f4, flash_f7)))]
+                return self.0.size;
+            }
+
+            /// Blocking read.
+            ///
+            /// NOTE: `offset` is an offset from the start of the OTP area.
+            pub fn blocking_read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Error> {
+                blocking_read(self.0.base, self.data_size(), offset, bytes)
+            }
+
+            /// Returns whether the bytes at `offset` are still erased, so they can be programmed.
+            pub fn is_erased(&mut self, offset: u32, len: u32) -> Result<bool, Error> {
+                let mut erased = true;
+                let mut byte = [0];
+                for i in 0..len {
+                    self.blocking_read(offset + i, &mut byte)?;
+                    erased &= byte[0] == self.0.erase_value;
+                }
+                Ok(erased)
+            }
+
+            /// Program `bytes` at `offset` in the OTP area.
+            ///
+            /// **This is irreversible**: the programmed bytes can never be erased or changed.
+            ///
+            /// The bytes must be erased, and on F2/F4/F7 chips in unlocked blocks, or `Error::Protected`
+            /// is returned without programming anything. `offset` and the length of `bytes` must be
+            /// multiples of the write size.
+            pub fn blocking_program_irreversible(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Error> {
+                const { assert!($write_size == WRITE_SIZE) };
+                if offset + bytes.len() as u32 > self.data_size() {
+                    return Err(Error::Size);
+                }
+                if !self.is_erased(offset, bytes.len() as u32)? {
+                    return Err(Error::Protected);
+                }
+                #[cfg(any(flash_f2, flash_f4, flash_f7))]
+                if !bytes.is_empty() {
+                    for block in offset / OTP_BLOCK_SIZE..=(offset + bytes.len() as u32 - 1) / OTP_BLOCK_SIZE {
+                        if self.is_block_locked(block as u8)? {
+                            return Err(Error::Protected);
+                        }
+                    }
+                }
+                unsafe {
+                    blocking_write(
+                        self.0.base,
+                        self.data_size(),
+                        offset,
+                        bytes,
+                        write_chunk_with_critical_section,
+                    )
+                }
+            }
+
+            /// Returns whether the OTP block is locked.
+            #[cfg(any(flash_f2, flash_f4, flash_f7))]
+            pub fn is_block_locked(&mut self, block: u8) -> Result<bool, Error> {
+                assert!((block as u32) < OTP_DATA_SIZE / OTP_BLOCK_SIZE);
+                let mut lock = [0];
+                blocking_read(self.0.base, OTP_DATA_SIZE + 16, OTP_DATA_SIZE + block as u32, &mut lock)?;
+                Ok(lock[0] == 0x00)
+            }
+
+            /// Lock an OTP block, so that its remaining erased bytes can't be programmed.
+            ///
+            /// **This is irreversible**: a locked block can never be unlocked.
+            #[cfg(any(flash_f2, flash_f4, flash_f7))]
+            pub fn blocking_lock_block_irreversible(&mut self, block: u8) -> Result<(), Error> {
+                assert!((block as u32) < OTP_DATA_SIZE / OTP_BLOCK_SIZE);
+                // Lock bytes are programmed a write unit at a time: writing 0xFF leaves the other blocks untouched.
+                let offset = OTP_DATA_SIZE + block as u32 / WRITE_SIZE as u32 * WRITE_SIZE as u32;
+                let mut word = [0xFF; WRITE_SIZE];
+                word[block as usize % WRITE_SIZE] = 0x00;
+                unsafe {
+                    blocking_write(
+                        self.0.base,
+                        OTP_DATA_SIZE + 16,
+                        offset,
+                        &word,
+                        write_chunk_with_critical_section,
+                    )
+                }
+            }
+        }
+
+        impl<MODE> embedded_storage::nor_flash::ErrorType for crate::_generated::flash_regions::OTPRegion<'_, MODE> {
+            type Error = Error;
+        }
+
+        impl<MODE> embedded_storage::nor_flash::ReadNorFlash for crate::_generated::flash_regions::OTPRegion<'_, MODE> {
+            const READ_SIZE: usize = READ_SIZE;
+
+            fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+                self.blocking_read(offset, bytes)
+            }
+
+            fn capacity(&self) -> usize {
+                self.data_size() as usize
+            }
+        }
+    };
+}
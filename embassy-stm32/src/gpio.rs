@@ -156,6 +156,67 @@ impl<'d> Flex<'d> {
         });
     }
 
+    /// Change the internal pull-up and pull-down resistors, keeping the current mode.
+    #[inline(never)]
+    #[cfg(gpio_v2)]
+    pub fn set_pull(&mut self, pull: Pull) {
+        critical_section::with(|_| {
+            let n = self.pin.pin() as usize;
+            self.pin.block().pupdr().modify(|w| w.set_pupdr(n, pull.to_pupdr()));
+        });
+    }
+
+    /// Change the output speed, keeping the current mode.
+    #[inline(never)]
+    #[cfg(gpio_v2)]
+    pub fn set_speed(&mut self, speed: Speed) {
+        critical_section::with(|_| {
+            self.pin.set_speed(speed);
+        });
+    }
+
+    /// Change the output drive type, keeping the current mode.
+    ///
+    /// This only has an effect while the pin is in output or AF mode.
+    #[inline(never)]
+    #[cfg(gpio_v2)]
+    pub fn set_output_type(&mut self, output_type: OutputType) {
+        critical_section::with(|_| {
+            let n = self.pin.pin() as usize;
+            self.pin.block().otyper().modify(|w| w.set_ot(n, output_type.to_ot()));
+        });
+    }
+
+    /// Lock the pin configuration until the next reset.
+    ///
+    /// The mode, drive type, speed, pull and alternate function can no longer be changed, even by
+    /// a stray register write, which protects the pins of safety-critical outputs such as gate
+    /// drivers. The output level can still be set. The configuration is also kept when the `Flex`
+    /// is dropped.
+    ///
+    /// The lock register of a port is frozen by the first lock, so this returns
+    /// [`LockError::AlreadyLocked`] if a pin of the same port was already locked. Use
+    /// [`PortBus::lock`] to lock several pins of a port.
+    #[inline]
+    pub fn lock(&mut self) -> Result<(), LockError> {
+        lock_pins(self.pin.block(), 1 << self.pin.pin())
+    }
+
+    /// Get whether the pin configuration is locked.
+    #[inline]
+    pub fn is_locked(&self) -> bool {
+        self.pin.block().lckr().read().lck(self.pin.pin() as usize)
+    }
+
+    /// Borrow the pin to reconfigure it temporarily.
+    ///
+    /// The mode, pull, speed, drive type, alternate function and output level are restored when
+    /// the returned [`TemporaryFlex`] is dropped.
+    #[inline]
+    pub fn temporary(&mut self) -> TemporaryFlex<'_, 'd> {
+        TemporaryFlex::new(self)
+    }
+
     /// Get whether the pin input level is high.
     #[inline]
     pub fn is_high(&self) -> bool {
@@ -235,6 +296,103 @@ impl<'d> Drop for Flex<'d> {
     }
 }
 
+/// Configuration of a pin, saved by [`TemporaryFlex`].
+struct PinConfig {
+    #[cfg(gpio_v1)]
+    mode: vals::Mode,
+    #[cfg(gpio_v1)]
+    cnf: vals::CnfIn,
+    #[cfg(gpio_v2)]
+    moder: vals::Moder,
+    #[cfg(gpio_v2)]
+    ot: vals::Ot,
+    #[cfg(gpio_v2)]
+    ospeedr: vals::Ospeedr,
+    #[cfg(gpio_v2)]
+    pupdr: vals::Pupdr,
+    #[cfg(gpio_v2)]
+    afr: u8,
+    odr: bool,
+}
+
+/// A pin temporarily borrowed as a [`Flex`], to drive it in a different mode.
+///
+/// This is useful to share a pin between modes, for example to briefly drive an input low for
+/// charge-transfer sensing, or to clock out a stuck I2C bus. The pin can be reconfigured freely
+/// through [`Deref`](core::ops::Deref) to [`Flex`], and its previous configuration is restored on
+/// drop.
+pub struct TemporaryFlex<'a, 'd> {
+    flex: &'a mut Flex<'d>,
+    config: PinConfig,
+}
+
+impl<'a, 'd> TemporaryFlex<'a, 'd> {
+    fn new(flex: &'a mut Flex<'d>) -> Self {
+        let r = flex.pin.block();
+        let n = flex.pin.pin() as usize;
+        let config = critical_section::with(|_| PinConfig {
+            #[cfg(gpio_v1)]
+            mode: r.cr(n / 8).read().mode(n % 8),
+            // The CNF field is exposed as both `cnf_in` and `cnf_out`, either restores it.
+            #[cfg(gpio_v1)]
+            cnf: r.cr(n / 8).read().cnf_in(n % 8),
+            #[cfg(gpio_v2)]
+            moder: r.moder().read().moder(n),
+            #[cfg(gpio_v2)]
+            ot: r.otyper().read().ot(n),
+            #[cfg(gpio_v2)]
+            ospeedr: r.ospeedr().read().ospeedr(n),
+            #[cfg(gpio_v2)]
+            pupdr: r.pupdr().read().pupdr(n),
+            #[cfg(gpio_v2)]
+            afr: r.afr(n / 8).read().afr(n % 8),
+            odr: flex.is_set_high(),
+        });
+        Self { flex, config }
+    }
+}
+
+impl<'a, 'd> core::ops::Deref for TemporaryFlex<'a, 'd> {
+    type Target = Flex<'d>;
+
+    fn deref(&self) -> &Flex<'d> {
+        self.flex
+    }
+}
+
+impl<'a, 'd> core::ops::DerefMut for TemporaryFlex<'a, 'd> {
+    fn deref_mut(&mut self) -> &mut Flex<'d> {
+        self.flex
+    }
+}
+
+impl<'a, 'd> Drop for TemporaryFlex<'a, 'd> {
+    fn drop(&mut self) {
+        let r = self.flex.pin.block();
+        let n = self.flex.pin.pin() as usize;
+        let c = &self.config;
+        critical_section::with(|_| {
+            // Restore the output level first, so the pin doesn't glitch when going back to output mode.
+            self.flex.set_level(c.odr.into());
+
+            #[cfg(gpio_v1)]
+            r.cr(n / 8).modify(|w| {
+                w.set_mode(n % 8, c.mode);
+                w.set_cnf_in(n % 8, c.cnf);
+            });
+
+            #[cfg(gpio_v2)]
+            {
+                r.pupdr().modify(|w| w.set_pupdr(n, c.pupdr));
+                r.otyper().modify(|w| w.set_ot(n, c.ot));
+                r.ospeedr().modify(|w| w.set_ospeedr(n, c.ospeedr));
+                r.afr(n / 8).modify(|w| w.set_afr(n % 8, c.afr));
+                r.moder().modify(|w| w.set_moder(n, c.moder));
+            }
+        });
+    }
+}
+
 /// Pull setting for an input.
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -315,6 +473,29 @@ impl<'d> Input<'d> {
         Self { pin }
     }
 
+    /// Change the internal pull-up and pull-down resistors.
+    #[inline]
+    #[cfg(gpio_v2)]
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.pin.set_pull(pull)
+    }
+
+    /// Borrow the pin to use it temporarily in another mode, for example to drive it low.
+    ///
+    /// The input configuration is restored when the returned [`TemporaryFlex`] is dropped.
+    #[inline]
+    pub fn as_flex(&mut self) -> TemporaryFlex<'_, 'd> {
+        self.pin.temporary()
+    }
+
+    /// Lock the pin configuration until the next reset.
+    ///
+    /// See [`Flex::lock`].
+    #[inline]
+    pub fn lock(&mut self) -> Result<(), LockError> {
+        self.pin.lock()
+    }
+
     /// Get whether the pin input level is high.
     #[inline]
     pub fn is_high(&self) -> bool {
@@ -384,6 +565,44 @@ impl<'d> Output<'d> {
         Self { pin }
     }
 
+    /// Change the output speed.
+    #[inline]
+    #[cfg(gpio_v2)]
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.pin.set_speed(speed)
+    }
+
+    /// Change the output drive type.
+    #[inline]
+    #[cfg(gpio_v2)]
+    pub fn set_output_type(&mut self, output_type: OutputType) {
+        self.pin.set_output_type(output_type)
+    }
+
+    /// Change the internal pull-up and pull-down resistors.
+    #[inline]
+    #[cfg(gpio_v2)]
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.pin.set_pull(pull)
+    }
+
+    /// Borrow the pin to use it temporarily in another mode, for example to read it as an input.
+    ///
+    /// The output configuration and level are restored when the returned [`TemporaryFlex`] is
+    /// dropped.
+    #[inline]
+    pub fn as_flex(&mut self) -> TemporaryFlex<'_, 'd> {
+        self.pin.temporary()
+    }
+
+    /// Lock the pin configuration until the next reset.
+    ///
+    /// See [`Flex::lock`].
+    #[inline]
+    pub fn lock(&mut self) -> Result<(), LockError> {
+        self.pin.lock()
+    }
+
     /// Set the output as high.
     #[inline]
     pub fn set_high(&mut self) {
@@ -463,6 +682,37 @@ impl<'d> OutputOpenDrain<'d> {
         Self { pin }
     }
 
+    /// Change the output speed.
+    #[inline]
+    #[cfg(gpio_v2)]
+    pub fn set_speed(&mut self, speed: Speed) {
+        self.pin.set_speed(speed)
+    }
+
+    /// Change the internal pull-up and pull-down resistors.
+    #[inline]
+    #[cfg(gpio_v2)]
+    pub fn set_pull(&mut self, pull: Pull) {
+        self.pin.set_pull(pull)
+    }
+
+    /// Borrow the pin to use it temporarily in another mode, for example as a push-pull output.
+    ///
+    /// The open-drain configuration and level are restored when the returned [`TemporaryFlex`] is
+    /// dropped.
+    #[inline]
+    pub fn as_flex(&mut self) -> TemporaryFlex<'_, 'd> {
+        self.pin.temporary()
+    }
+
+    /// Lock the pin configuration until the next reset.
+    ///
+    /// See [`Flex::lock`].
+    #[inline]
+    pub fn lock(&mut self) -> Result<(), LockError> {
+        self.pin.lock()
+    }
+
     /// Get whether the pin input level is high.
     #[inline]
     pub fn is_high(&self) -> bool {
@@ -524,6 +774,122 @@ impl<'d> OutputOpenDrain<'d> {
     }
 }
 
+/// Group of pins of one GPIO port, read and written as a parallel bus.
+///
+/// Bit `i` of the bus value is the level of the `i`-th pin, whatever its number in the port. The
+/// bus is read with a single read of the input register, and written with a single write of the
+/// set/reset register, so all lines change at the same time, for example to drive an 8080 LCD
+/// interface or an R-2R DAC.
+///
+/// Note that pins will **return to their floating state** when `PortBus` is dropped.
+pub struct PortBus<'d, const N: usize> {
+    pins: [Flex<'d>; N],
+    block: gpio::Gpio,
+}
+
+impl<'d, const N: usize> PortBus<'d, N> {
+    /// Create a bus from `pins`, which must all be in the same port.
+    ///
+    /// The pins remain disconnected until the bus is put into input or output mode.
+    pub fn new(pins: [Peri<'d, AnyPin>; N]) -> Self {
+        assert!(N > 0 && N <= 16, "a port bus has 1 to 16 pins");
+
+        let pins = pins.map(Flex::new);
+        let port = pins[0].pin._port();
+        let mut used = 0u16;
+        for pin in &pins {
+            assert_eq!(pin.pin._port(), port, "port bus pins must be in the same port");
+            let mask = 1 << pin.pin._pin();
+            assert_eq!(used & mask, 0, "port bus pins must be distinct");
+            used |= mask;
+        }
+
+        let block = pins[0].pin.block();
+        Self { pins, block }
+    }
+
+    /// Put the pins into input mode.
+    pub fn set_as_input(&mut self, pull: Pull) {
+        for pin in &mut self.pins {
+            pin.set_as_input(pull);
+        }
+    }
+
+    /// Put the pins into push-pull output mode.
+    ///
+    /// The output value will be whatever was set before. If you want it to begin at a specific
+    /// value, call [`write`](Self::write) first.
+    pub fn set_as_output(&mut self, speed: Speed) {
+        for pin in &mut self.pins {
+            pin.set_as_output(speed);
+        }
+    }
+
+    /// Put the pins into input + open-drain output mode, for a bus shared with other devices.
+    pub fn set_as_input_output(&mut self, speed: Speed) {
+        for pin in &mut self.pins {
+            pin.set_as_input_output(speed);
+        }
+    }
+
+    /// Read the input levels of the pins.
+    #[inline]
+    pub fn read(&self) -> u16 {
+        self.gather(self.block.idr().read().0)
+    }
+
+    /// Get the output levels of the pins.
+    #[inline]
+    pub fn output_value(&self) -> u16 {
+        self.gather(self.block.odr().read().0)
+    }
+
+    /// Set the output levels of the pins, all at once.
+    #[inline]
+    pub fn write(&mut self, value: u16) {
+        let mut set = 0u32;
+        let mut reset = 0u32;
+        for (i, pin) in self.pins.iter().enumerate() {
+            let mask = 1 << pin.pin._pin();
+            if value & (1 << i) != 0 {
+                set |= mask;
+            } else {
+                reset |= mask;
+            }
+        }
+        self.block.bsrr().write(|w| w.0 = (reset << 16) | set);
+    }
+
+    /// Lock the configuration of the pins until the next reset.
+    ///
+    /// See [`Flex::lock`].
+    pub fn lock(&mut self) -> Result<(), LockError> {
+        let mut pins = 0;
+        for pin in &self.pins {
+            pins |= 1 << pin.pin._pin();
+        }
+        lock_pins(self.block, pins)
+    }
+
+    fn gather(&self, port_value: u32) -> u16 {
+        let mut value = 0;
+        for (i, pin) in self.pins.iter().enumerate() {
+            if port_value & (1 << pin.pin._pin()) != 0 {
+                value |= 1 << i;
+            }
+        }
+        value
+    }
+}
+
+/// Error returned when locking the pin configuration.
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LockError {
+    /// The lock register of the port is already frozen by an earlier lock, until the next reset.
+    AlreadyLocked,
+}
+
 /// GPIO output type
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -667,6 +1033,37 @@ fn set_speed(pin_port: u8, speed: Speed) {
     r.ospeedr().modify(|w| w.set_ospeedr(n, speed.to_ospeedr()));
 }
 
+/// Apply the LCKR lock key sequence to `pins`, which must not be interrupted.
+#[inline(never)]
+fn lock_pins(r: gpio::Gpio, pins: u32) -> Result<(), LockError> {
+    // The key sequence writes the same pins each time, with LCKK set, cleared, then set again.
+    let key = |w: &mut gpio::regs::Lckr, lckk: bool| {
+        for n in 0..16 {
+            w.set_lck(n, pins & (1 << n) != 0);
+        }
+        w.set_lckk(lckk);
+    };
+
+    critical_section::with(|_| {
+        if r.lckr().read().lckk() {
+            return Err(LockError::AlreadyLocked);
+        }
+
+        r.lckr().write(|w| key(w, true));
+        r.lckr().write(|w| key(w, false));
+        r.lckr().write(|w| key(w, true));
+        // The lock is only applied after reading LCKR back.
+        let _ = r.lckr().read();
+
+        let lckr = r.lckr().read();
+        if lckr.lckk() && (0..16).all(|n| pins & (1 << n) == 0 || lckr.lck(n)) {
+            Ok(())
+        } else {
+            Err(LockError::AlreadyLocked)
+        }
+    })
+}
+
 #[inline(never)]
 fn set_as_analog(pin_port: u8) {
     let pin = unsafe { AnyPin::steal(pin_port) };
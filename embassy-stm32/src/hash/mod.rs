@@ -572,7 +572,7 @@ impl<'d, T: Instance> Hash<'d, T, Async> {
         T::regs().cr().modify(|w| w.set_dmae(true));
 
         // Wait for the transfer to complete.
-        dma_transfer.await;
+        unwrap!(dma_transfer.await);
     }
 }
 
@@ -7,7 +7,7 @@ use core::marker::PhantomData;
 use embassy_hal_internal::Peri;
 pub use traits::Instance;
 
-use crate::gpio::{AfType, AnyPin, OutputType, Speed};
+use crate::gpio::{AfType, AnyPin, OutputType, Pull, Speed};
 use crate::rcc;
 use crate::time::Hertz;
 
@@ -211,6 +211,442 @@ impl<'d, T: Instance> AdvancedPwm<'d, T> {
     }
 }
 
+/// Burst mode clock source.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BurstClock {
+    /// Master timer reset/roll-over.
+    Master,
+    /// Timer A reset/roll-over.
+    TimerA,
+    /// Timer B reset/roll-over.
+    TimerB,
+    /// Timer C reset/roll-over.
+    TimerC,
+    /// Timer D reset/roll-over.
+    TimerD,
+    /// Timer E reset/roll-over.
+    TimerE,
+    /// Prescaled HRTIM clock.
+    Prescaled,
+}
+
+impl From<BurstClock> for u8 {
+    fn from(val: BurstClock) -> Self {
+        match val {
+            BurstClock::Master => 0b0000,
+            BurstClock::TimerA => 0b0001,
+            BurstClock::TimerB => 0b0010,
+            BurstClock::TimerC => 0b0011,
+            BurstClock::TimerD => 0b0100,
+            BurstClock::TimerE => 0b0101,
+            BurstClock::Prescaled => 0b1010,
+        }
+    }
+}
+
+impl<T: Instance> BurstController<T> {
+    /// Set the burst mode clock source.
+    ///
+    /// The prescaler is a power of two exponent (0..=15) and only applies to [`BurstClock::Prescaled`].
+    /// The burst mode controller must be stopped.
+    pub fn set_clock(&mut self, clock: BurstClock, prescaler: u8) {
+        assert!(prescaler < 16);
+
+        T::regs().bmcr().modify(|w| {
+            w.set_bmclk(clock.into());
+            w.set_bmprsc(prescaler);
+            w.set_bmpren(true);
+        });
+    }
+
+    /// Select whether the given timer is idled during the burst.
+    pub fn set_timer_enabled<C: AdvancedChannel<T>>(&mut self, _channel: &C, enable: bool) {
+        T::regs().bmcr().modify(|w| w.set_tbm(C::raw(), enable));
+    }
+
+    /// Set the burst period, in burst clock ticks.
+    pub fn set_period(&mut self, period: u16) {
+        T::regs().bmper().modify(|w| w.set_bmper(period));
+    }
+
+    /// Set the idle duration within a burst period, in burst clock ticks.
+    ///
+    /// Must be lower than the burst period.
+    pub fn set_idle_duration(&mut self, idle: u16) {
+        T::regs().bmcmpr().modify(|w| w.set_bmcmp(idle));
+    }
+
+    /// Start the burst mode controller.
+    ///
+    /// In continuous mode, burst periods repeat until [`stop`](Self::stop) is called. Otherwise,
+    /// a single idle period is generated.
+    pub fn start(&mut self, continuous: bool) {
+        T::regs().bmcr().modify(|w| {
+            w.set_bmom(continuous);
+            w.set_bme(true);
+        });
+        T::regs().bmtrgr().write(|w| w.set_sw(true));
+    }
+
+    /// Stop the burst mode controller.
+    pub fn stop(&mut self) {
+        T::regs().bmcr().modify(|w| w.set_bme(false));
+    }
+
+    /// Check whether a burst is currently ongoing.
+    pub fn is_bursting(&self) -> bool {
+        T::regs().bmcr().read().bmstat()
+    }
+}
+
+/// Fault input.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FaultInput {
+    /// Fault input 1.
+    Flt1,
+    /// Fault input 2.
+    Flt2,
+    /// Fault input 3.
+    Flt3,
+    /// Fault input 4.
+    Flt4,
+    /// Fault input 5.
+    Flt5,
+    /// Fault input 6.
+    #[cfg(hrtim_v2)]
+    Flt6,
+}
+
+impl FaultInput {
+    fn index(&self) -> usize {
+        match self {
+            FaultInput::Flt1 => 0,
+            FaultInput::Flt2 => 1,
+            FaultInput::Flt3 => 2,
+            FaultInput::Flt4 => 3,
+            FaultInput::Flt5 => 4,
+            #[cfg(hrtim_v2)]
+            FaultInput::Flt6 => 5,
+        }
+    }
+}
+
+/// Fault input polarity.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FaultPolarity {
+    /// The fault is signalled by a low level.
+    ActiveLow,
+    /// The fault is signalled by a high level.
+    ActiveHigh,
+}
+
+/// Fault input configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy)]
+pub struct FaultConfig {
+    /// Input polarity.
+    pub polarity: FaultPolarity,
+    /// Digital filter, 0 (no filter) to 15.
+    ///
+    /// See the reference manual for the sampling frequency and number of samples of each value.
+    pub filter: u8,
+}
+
+impl Default for FaultConfig {
+    fn default() -> Self {
+        Self {
+            polarity: FaultPolarity::ActiveLow,
+            filter: 0,
+        }
+    }
+}
+
+/// HRTIM fault input pin.
+pub struct FaultPin<'d, T> {
+    _pin: Peri<'d, AnyPin>,
+    input: FaultInput,
+    phantom: PhantomData<T>,
+}
+
+macro_rules! fault_pin_impl {
+    ($new_fltx:ident, $input:ident, $pin_trait:ident) => {
+        impl<'d, T: Instance> FaultPin<'d, T> {
+            #[doc = concat!("Create a new ", stringify!($input), " fault input pin instance.")]
+            pub fn $new_fltx(pin: Peri<'d, impl $pin_trait<T>>) -> Self {
+                critical_section::with(|_| {
+                    pin.set_as_af(pin.af_num(), AfType::input(Pull::None));
+                });
+                FaultPin {
+                    _pin: pin.into(),
+                    input: FaultInput::$input,
+                    phantom: PhantomData,
+                }
+            }
+        }
+    };
+}
+
+fault_pin_impl!(new_flt1, Flt1, Fault1Pin);
+fault_pin_impl!(new_flt2, Flt2, Fault2Pin);
+fault_pin_impl!(new_flt3, Flt3, Fault3Pin);
+fault_pin_impl!(new_flt4, Flt4, Fault4Pin);
+fault_pin_impl!(new_flt5, Flt5, Fault5Pin);
+#[cfg(hrtim_v2)]
+fault_pin_impl!(new_flt6, Flt6, Fault6Pin);
+
+impl<'d, T: Instance> FaultPin<'d, T> {
+    /// Get the fault input this pin is connected to.
+    pub fn input(&self) -> FaultInput {
+        self.input
+    }
+}
+
+impl<'d, T: Instance> AdvancedPwm<'d, T> {
+    /// Enable a fault input.
+    ///
+    /// Faults are then routed to the timers with [`SubTimer::enable_fault`].
+    pub fn enable_fault_input(&mut self, pin: &FaultPin<'d, T>, config: FaultConfig) {
+        assert!(config.filter < 16);
+
+        let n = pin.input.index();
+        let polarity = config.polarity == FaultPolarity::ActiveHigh;
+
+        if n < 4 {
+            T::regs().fltinr1().modify(|w| {
+                w.set_fltp(n, polarity);
+                w.set_fltf(n, config.filter);
+                w.set_flte(n, true);
+            });
+        } else {
+            T::regs().fltinr2().modify(|w| {
+                w.set_fltp(n - 4, polarity);
+                w.set_fltf(n - 4, config.filter);
+                w.set_flte(n - 4, true);
+            });
+        }
+    }
+
+    /// Check whether a fault has been detected on the given input.
+    ///
+    /// When a fault is detected, the outputs of the timers that have it enabled are forced to
+    /// their fault state until the flag is cleared with [`clear_fault`](Self::clear_fault).
+    pub fn is_fault_active(&self, input: FaultInput) -> bool {
+        T::regs().isr().read().flt(input.index())
+    }
+
+    /// Clear the fault flag of the given input and re-enable the outputs.
+    ///
+    /// If the fault condition is still present, the fault is immediately reported again.
+    pub fn clear_fault(&mut self, input: FaultInput) {
+        T::regs().icr().write(|w| w.set_fltc(input.index(), true));
+    }
+}
+
+/// Event that changes the state of a timer output.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum OutputEvent {
+    /// Timer period event.
+    Period,
+    /// Compare unit event, 0..=3.
+    Compare(usize),
+}
+
+impl OutputEvent {
+    fn check(self) {
+        if let OutputEvent::Compare(n) = self {
+            assert!(n < 4, "compare unit must be 0..=3");
+        }
+    }
+}
+
+/// Output state while a fault is active.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum FaultState {
+    /// The output is not affected by the fault.
+    NoAction,
+    /// The output is forced active.
+    Active,
+    /// The output is forced inactive.
+    Inactive,
+    /// The output is set in high-impedance.
+    HighZ,
+}
+
+impl From<FaultState> for u8 {
+    fn from(val: FaultState) -> Self {
+        match val {
+            FaultState::NoAction => 0b00,
+            FaultState::Active => 0b01,
+            FaultState::Inactive => 0b10,
+            FaultState::HighZ => 0b11,
+        }
+    }
+}
+
+/// Generic HRTIM sub-timer driver.
+///
+/// This gives direct control over the period, the four compare units and the events driving
+/// both outputs of a single timer, for topologies not covered by [`BridgeConverter`] and
+/// [`ResonantConverter`].
+pub struct SubTimer<T: Instance, C: AdvancedChannel<T>> {
+    timer: PhantomData<T>,
+    channel: PhantomData<C>,
+}
+
+impl<T: Instance, C: AdvancedChannel<T>> SubTimer<T, C> {
+    /// Create a new sub-timer driver running in continuous mode at the given frequency.
+    ///
+    /// Both outputs are enabled, with no set or reset events.
+    pub fn new(_channel: C, frequency: Hertz) -> Self {
+        T::set_channel_frequency(C::raw(), frequency);
+
+        // Always enable preload
+        T::regs().tim(C::raw()).cr().modify(|w| {
+            w.set_preen(true);
+            w.set_repu(true);
+            w.set_cont(true);
+        });
+
+        // Enable timer outputs
+        T::regs().oenr().modify(|w| {
+            w.set_t1oen(C::raw(), true);
+            w.set_t2oen(C::raw(), true);
+        });
+
+        Self {
+            timer: PhantomData,
+            channel: PhantomData,
+        }
+    }
+
+    /// Start the timer.
+    pub fn start(&mut self) {
+        T::regs().mcr().modify(|w| w.set_tcen(C::raw(), true));
+    }
+
+    /// Stop the timer.
+    pub fn stop(&mut self) {
+        T::regs().mcr().modify(|w| w.set_tcen(C::raw(), false));
+    }
+
+    /// Set the timer period.
+    pub fn set_period(&mut self, period: u16) {
+        T::regs().tim(C::raw()).per().modify(|w| w.set_per(period));
+    }
+
+    /// Get the timer period.
+    pub fn get_period(&self) -> u16 {
+        T::regs().tim(C::raw()).per().read().per()
+    }
+
+    /// Set the value of a compare unit (0..=3).
+    pub fn set_compare_value(&mut self, compare: usize, value: u16) {
+        assert!(compare < 4);
+        T::regs().tim(C::raw()).cmp(compare).modify(|w| w.set_cmp(value));
+    }
+
+    /// Get the value of a compare unit (0..=3).
+    pub fn get_compare_value(&self, compare: usize) -> u16 {
+        assert!(compare < 4);
+        T::regs().tim(C::raw()).cmp(compare).read().cmp()
+    }
+
+    /// Add an event that sets an output (0 or 1) active.
+    pub fn add_set_event(&mut self, output: usize, event: OutputEvent) {
+        assert!(output < 2);
+        event.check();
+        T::regs().tim(C::raw()).setr(output).modify(|w| match event {
+            OutputEvent::Period => w.set_per(true),
+            OutputEvent::Compare(n) => w.set_cmp(n, true),
+        });
+    }
+
+    /// Add an event that resets an output (0 or 1) to inactive.
+    pub fn add_reset_event(&mut self, output: usize, event: OutputEvent) {
+        assert!(output < 2);
+        event.check();
+        T::regs().tim(C::raw()).rstr(output).modify(|w| match event {
+            OutputEvent::Period => w.set_per(true),
+            OutputEvent::Compare(n) => w.set_cmp(n, true),
+        });
+    }
+
+    /// Remove all set and reset events of an output (0 or 1).
+    pub fn clear_events(&mut self, output: usize) {
+        assert!(output < 2);
+        T::regs().tim(C::raw()).setr(output).write(|_| {});
+        T::regs().tim(C::raw()).rstr(output).write(|_| {});
+    }
+
+    /// Enable the dead-time generator, with the given dead time as a proportion of the maximum
+    /// compare value.
+    ///
+    /// Output 2 is then the complement of output 1, and its own set/reset events are ignored.
+    pub fn enable_dead_time(&mut self, dead_time: u16) {
+        T::set_channel_dead_time(C::raw(), dead_time);
+        T::regs().tim(C::raw()).outr().modify(|w| w.set_dten(true));
+    }
+
+    /// Disable the dead-time generator.
+    pub fn disable_dead_time(&mut self) {
+        T::regs().tim(C::raw()).outr().modify(|w| w.set_dten(false));
+    }
+
+    /// Route a fault input to this timer.
+    pub fn enable_fault(&mut self, input: FaultInput) {
+        T::regs()
+            .tim(C::raw())
+            .fltr()
+            .modify(|w| w.set_flten(input.index(), true));
+    }
+
+    /// Stop routing a fault input to this timer.
+    pub fn disable_fault(&mut self, input: FaultInput) {
+        T::regs()
+            .tim(C::raw())
+            .fltr()
+            .modify(|w| w.set_flten(input.index(), false));
+    }
+
+    /// Set the state `output` (0 or 1) is forced to while a fault is active.
+    ///
+    /// This setting is write-protected while the outputs are enabled, so call this before enabling
+    /// them.
+    pub fn set_fault_state(&mut self, output: usize, state: FaultState) {
+        assert!(output < 2);
+        T::regs()
+            .tim(C::raw())
+            .outr()
+            .modify(|w| w.set_fault(output, state.into()));
+    }
+
+    /// Enable burst mode for this timer, with the outputs in their idle state during the burst.
+    ///
+    /// The burst itself is driven by the [`BurstController`].
+    pub fn enable_burst_mode(&mut self, idle_active: bool) {
+        T::regs().tim(C::raw()).outr().modify(|w| {
+            w.set_idlem(0, true);
+            w.set_idlem(1, true);
+
+            w.set_idles(0, idle_active);
+            w.set_idles(1, idle_active);
+        })
+    }
+
+    /// Disable burst mode for this timer.
+    pub fn disable_burst_mode(&mut self) {
+        T::regs().tim(C::raw()).outr().modify(|w| {
+            w.set_idlem(0, false);
+            w.set_idlem(1, false);
+        })
+    }
+}
+
 /// Fixed-frequency bridge converter driver.
 ///
 /// Our implementation of the bridge converter uses a single channel and three compare registers,
@@ -438,3 +874,10 @@ pin_trait!(ChannelEComplementaryPin, Instance);
 pin_trait!(ChannelFPin, Instance);
 #[cfg(hrtim_v2)]
 pin_trait!(ChannelFComplementaryPin, Instance);
+pin_trait!(Fault1Pin, Instance);
+pin_trait!(Fault2Pin, Instance);
+pin_trait!(Fault3Pin, Instance);
+pin_trait!(Fault4Pin, Instance);
+pin_trait!(Fault5Pin, Instance);
+#[cfg(hrtim_v2)]
+pin_trait!(Fault6Pin, Instance);
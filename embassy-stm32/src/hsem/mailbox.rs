@@ -0,0 +1,189 @@
+//! Cross-core mailbox.
+
+use core::cell::UnsafeCell;
+use core::future::poll_fn;
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::task::Poll;
+
+use super::{core_id_to_index, get_current_coreid, WAKERS};
+use crate::pac;
+
+const INIT_DONE_FLAG: usize = 0x6d61_696c;
+
+/// A fixed-capacity message queue between the two cores.
+///
+/// The mailbox lives in memory shared by both cores, and is protected by a hardware semaphore
+/// so both cores can send and receive. Releasing the semaphore after each operation raises the
+/// HSEM interrupt on the other core, which wakes the task waiting in [`send`](Self::send) or
+/// [`receive`](Self::receive). For this, each core must create its [`HardwareSemaphore`](super::HardwareSemaphore)
+/// with [`new_with_interrupt`](super::HardwareSemaphore::new_with_interrupt).
+///
+/// Like [`SharedData`](crate::SharedData), the mailbox must be placed at the same address for
+/// both cores, in memory that isn't initialized by either core's runtime:
+///
+/// ```rust,ignore
+/// #[link_section = ".ram_d3.mailbox"]
+/// static TO_CM4: MaybeUninit<Mailbox<Command, 8>> = MaybeUninit::uninit();
+///
+/// // On one core, before the other core uses it:
+/// let to_cm4 = Mailbox::init(&TO_CM4, 1);
+/// // On the other core:
+/// let to_cm4 = Mailbox::get(&TO_CM4);
+/// ```
+///
+/// If the data cache of the Cortex-M7 is enabled, the mailbox must be in a region configured as
+/// non-cacheable with the MPU.
+///
+/// Tasks on one core can't be spawned by the other core, since each core runs its own program
+/// with its own executor. Send a message to a task on the other core instead, or use a
+/// [`RemoteSpawner`](super::RemoteSpawner) to have that core spawn it.
+///
+/// Only one task per core may wait on the same mailbox at a time.
+#[repr(C)]
+pub struct Mailbox<M: Copy, const N: usize> {
+    init_flag: AtomicUsize,
+    sem_id: AtomicUsize,
+    // Total number of messages sent and received. Only modified while holding the semaphore.
+    sent: AtomicUsize,
+    received: AtomicUsize,
+    buf: [UnsafeCell<MaybeUninit<M>>; N],
+}
+
+unsafe impl<M: Copy + Send, const N: usize> Sync for Mailbox<M, N> {}
+
+impl<M: Copy, const N: usize> Mailbox<M, N> {
+    /// Initialize the mailbox, using hardware semaphore `sem_id` to protect it.
+    ///
+    /// This must be called by one of the cores before the other core calls [`Mailbox::get`],
+    /// and the semaphore must not be used for anything else.
+    pub fn init(mailbox: &'static MaybeUninit<Self>, sem_id: u8) -> &'static Self {
+        assert!((sem_id as usize) < super::NUM_SEMAPHORES);
+        let mailbox = unsafe { mailbox.assume_init_ref() };
+
+        mailbox.init_flag.store(0, Ordering::SeqCst);
+        mailbox.sem_id.store(sem_id as usize, Ordering::Relaxed);
+        mailbox.sent.store(0, Ordering::Relaxed);
+        mailbox.received.store(0, Ordering::Relaxed);
+        mailbox.init_flag.store(INIT_DONE_FLAG, Ordering::SeqCst);
+
+        mailbox
+    }
+
+    /// Get the mailbox initialized by the other core with [`Mailbox::init`].
+    ///
+    /// Returns `None` if the other core hasn't initialized it yet.
+    pub fn try_get(mailbox: &'static MaybeUninit<Self>) -> Option<&'static Self> {
+        let mailbox = unsafe { mailbox.assume_init_ref() };
+        (mailbox.init_flag.load(Ordering::SeqCst) == INIT_DONE_FLAG).then_some(mailbox)
+    }
+
+    /// Get the mailbox initialized by the other core with [`Mailbox::init`].
+    ///
+    /// If the other core hasn't initialized it yet, this will spinloop wait on it.
+    pub fn get(mailbox: &'static MaybeUninit<Self>) -> &'static Self {
+        loop {
+            if let Some(mailbox) = Self::try_get(mailbox) {
+                return mailbox;
+            }
+        }
+    }
+
+    fn sem_id(&self) -> usize {
+        self.sem_id.load(Ordering::Relaxed)
+    }
+
+    fn with_lock<R>(&self, f: impl FnOnce(&Self) -> R) -> R {
+        let sem_id = self.sem_id();
+        let core_id = get_current_coreid() as u8;
+        critical_section::with(|_| {
+            // 1-step lock: reading RLR locks the semaphore if it's free.
+            while pac::HSEM.rlr(sem_id).read().coreid() != core_id {}
+            let r = f(self);
+            // Unlocking raises the HSEM interrupt on the cores that enabled it for this semaphore.
+            pac::HSEM.r(sem_id).write(|w| {
+                w.set_coreid(core_id);
+                w.set_lock(false);
+            });
+            r
+        })
+    }
+
+    /// Number of messages in the mailbox.
+    pub fn len(&self) -> usize {
+        let sent = self.sent.load(Ordering::Acquire);
+        let received = self.received.load(Ordering::Acquire);
+        sent.wrapping_sub(received)
+    }
+
+    /// Returns whether the mailbox is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Try to send a message, returning it back if the mailbox is full.
+    pub fn try_send(&self, msg: M) -> Result<(), M> {
+        self.with_lock(|this| {
+            let sent = this.sent.load(Ordering::Relaxed);
+            if sent.wrapping_sub(this.received.load(Ordering::Relaxed)) >= N {
+                return Err(msg);
+            }
+            unsafe { (*this.buf[sent % N].get()).write(msg) };
+            this.sent.store(sent.wrapping_add(1), Ordering::Release);
+            Ok(())
+        })
+    }
+
+    /// Try to receive a message, returning `None` if the mailbox is empty.
+    pub fn try_receive(&self) -> Option<M> {
+        self.with_lock(|this| {
+            let received = this.received.load(Ordering::Relaxed);
+            if this.sent.load(Ordering::Acquire) == received {
+                return None;
+            }
+            let msg = unsafe { (*this.buf[received % N].get()).assume_init() };
+            this.received.store(received.wrapping_add(1), Ordering::Release);
+            Some(msg)
+        })
+    }
+
+    /// Wait for the HSEM interrupt, unless `ready` became true in the meantime.
+    fn register(&self, cx: &core::task::Context<'_>, ready: impl FnOnce(&Self) -> bool) {
+        let sem_id = self.sem_id();
+        let index = core_id_to_index(get_current_coreid());
+        WAKERS[sem_id].register(cx.waker());
+        critical_section::with(|_| {
+            // Our own unlock sets the interrupt flag too, clear it so it doesn't wake us right away.
+            pac::HSEM.icr(index).write(|w| w.set_isc(sem_id, true));
+            pac::HSEM.ier(index).modify(|w| w.set_ise(sem_id, true));
+        });
+        // The other core may have unlocked the semaphore before the flag was cleared.
+        if ready(self) {
+            cx.waker().wake_by_ref();
+        }
+    }
+
+    /// Send a message, waiting until there is space in the mailbox.
+    pub async fn send(&self, msg: M) {
+        poll_fn(|cx| match self.try_send(msg) {
+            Ok(()) => Poll::Ready(()),
+            Err(_) => {
+                self.register(cx, |this| this.len() < N);
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Receive a message, waiting until one is available.
+    pub async fn receive(&self) -> M {
+        poll_fn(|cx| match self.try_receive() {
+            Some(msg) => Poll::Ready(msg),
+            None => {
+                self.register(cx, |this| !this.is_empty());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
@@ -1,15 +1,33 @@
 //! Hardware Semaphore (HSEM)
+//!
+//! Hardware semaphores synchronize the cores of dual-core chips. Besides locking them directly
+//! with [`HardwareSemaphore`], they can protect a critical section across both cores with
+//! [`HardwareSemaphore::with_lock`], a [`Mailbox`] to pass messages between the cores, or a
+//! [`RemoteSpawner`] to spawn tasks on the other core.
+
+use core::marker::PhantomData;
 
 use embassy_hal_internal::PeripheralType;
+use embassy_sync::waitqueue::AtomicWaker;
 
-use crate::pac;
+use crate::interrupt::typelevel::Interrupt;
 use crate::rcc::RccPeripheral;
+use crate::{interrupt, pac};
 // TODO: This code works for all HSEM implemenations except for the STM32WBA52/4/5xx MCUs.
 // Those MCUs have a different HSEM implementation (Secure semaphore lock support,
 // Privileged / unprivileged semaphore lock support, Semaphore lock protection via semaphore attribute),
 // which is not yet supported by this code.
 use crate::Peri;
 
+mod mailbox;
+mod spawner;
+pub use mailbox::Mailbox;
+pub use spawner::RemoteSpawner;
+
+const NUM_SEMAPHORES: usize = 32;
+
+static WAKERS: [AtomicWaker; NUM_SEMAPHORES] = [const { AtomicWaker::new() }; NUM_SEMAPHORES];
+
 /// HSEM error.
 #[derive(Debug)]
 pub enum HsemError {
@@ -72,6 +90,29 @@ fn core_id_to_index(core: CoreId) -> usize {
     }
 }
 
+/// HSEM interrupt handler.
+///
+/// Bind it to the HSEM interrupt of the current core, for example `HSEM1` on the Cortex-M7 and
+/// `HSEM2` on the Cortex-M4 of the STM32H7 dual-core chips.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance, I: Interrupt> interrupt::typelevel::Handler<I> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        let index = core_id_to_index(get_current_coreid());
+        let isr = T::regs().isr(index).read();
+        for sem_id in 0..NUM_SEMAPHORES {
+            if isr.isf(sem_id) {
+                // Disable the interrupt until a task waits on this semaphore again.
+                T::regs().ier(index).modify(|w| w.set_ise(sem_id, false));
+                T::regs().icr(index).write(|w| w.set_isc(sem_id, true));
+                WAKERS[sem_id].wake();
+            }
+        }
+    }
+}
+
 /// HSEM driver
 pub struct HardwareSemaphore<'d, T: Instance> {
     _peri: Peri<'d, T>,
@@ -83,6 +124,18 @@ impl<'d, T: Instance> HardwareSemaphore<'d, T> {
         HardwareSemaphore { _peri: peripheral }
     }
 
+    /// Creates a new HardwareSemaphore instance, with the interrupt used to wake the tasks waiting
+    /// on a [`Mailbox`].
+    pub fn new_with_interrupt<I: Interrupt>(
+        peripheral: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<I, InterruptHandler<T>> + 'd,
+    ) -> Self {
+        I::unpend();
+        unsafe { I::enable() };
+
+        HardwareSemaphore { _peri: peripheral }
+    }
+
     /// Locks the semaphore.
     /// The 2-step lock procedure consists in a write to lock the semaphore, followed by a read to
     /// check if the lock has been successful, carried out from the HSEM_Rx register.
@@ -109,11 +162,30 @@ impl<'d, T: Instance> HardwareSemaphore<'d, T> {
     pub fn one_step_lock(&mut self, sem_id: u8) -> Result<(), HsemError> {
         let reg = T::regs().rlr(sem_id as usize).read();
         match (reg.lock(), reg.coreid() == get_current_coreid() as u8, reg.procid()) {
-            (false, true, 0) => Ok(()),
+            (true, true, 0) => Ok(()),
             _ => Err(HsemError::LockFailed),
         }
     }
 
+    /// Locks the semaphore with the 1-step procedure, spinning until the other core unlocks it.
+    pub fn blocking_lock(&mut self, sem_id: u8) {
+        while self.one_step_lock(sem_id).is_err() {}
+    }
+
+    /// Runs `f` in a critical section spanning both cores.
+    ///
+    /// Interrupts on the current core are disabled, and the semaphore is locked to keep the other
+    /// core out. The other core must also access the protected data with `with_lock` and the
+    /// same semaphore.
+    pub fn with_lock<R>(&mut self, sem_id: u8, f: impl FnOnce() -> R) -> R {
+        critical_section::with(|_| {
+            self.blocking_lock(sem_id);
+            let r = f();
+            self.unlock(sem_id, 0);
+            r
+        })
+    }
+
     /// Unlocks the semaphore.
     /// Unlocking a semaphore is a protected process, to prevent accidental clearing by a AHB bus
     /// core ID or by a process not having the semaphore lock right.
@@ -167,7 +239,7 @@ impl<'d, T: Instance> HardwareSemaphore<'d, T> {
     pub fn clear_interrupt(&mut self, core_id: CoreId, sem_x: usize) {
         T::regs()
             .icr(core_id_to_index(core_id))
-            .write(|w| w.set_isc(sem_x, false));
+            .write(|w| w.set_isc(sem_x, true));
     }
 }
 
@@ -0,0 +1,94 @@
+//! Spawning tasks on the other core.
+
+use core::mem::MaybeUninit;
+
+use super::Mailbox;
+
+/// Spawns tasks on the other core.
+///
+/// Each core runs its own program with its own executor, so a task can't be spawned directly on
+/// the other core. Instead, the spawning core sends a request describing the task and its
+/// arguments, and a task on the target core spawns it with its `Spawner` in [`run`](Self::run):
+///
+/// ```rust,ignore
+/// #[derive(Clone, Copy)]
+/// enum Request {
+///     Blink { period_ms: u32 },
+/// }
+///
+/// #[link_section = ".ram_d3.cm4_spawner"]
+/// static CM4_SPAWNER: MaybeUninit<RemoteSpawner<Request, 4>> = MaybeUninit::uninit();
+///
+/// // On the Cortex-M7, before the Cortex-M4 uses it:
+/// let cm4 = RemoteSpawner::init(&CM4_SPAWNER, 3);
+/// cm4.spawn(Request::Blink { period_ms: 100 }).await;
+///
+/// // On the Cortex-M4:
+/// RemoteSpawner::get(&CM4_SPAWNER)
+///     .run(|request| match request {
+///         Request::Blink { period_ms } => unwrap!(spawner.spawn(blink(period_ms))),
+///     })
+///     .await;
+/// ```
+///
+/// The requests are passed in a [`Mailbox`], so the same placement rules apply, and both cores
+/// must create their [`HardwareSemaphore`](super::HardwareSemaphore) with
+/// [`new_with_interrupt`](super::HardwareSemaphore::new_with_interrupt).
+#[repr(transparent)]
+pub struct RemoteSpawner<R: Copy, const N: usize> {
+    requests: Mailbox<R, N>,
+}
+
+impl<R: Copy, const N: usize> RemoteSpawner<R, N> {
+    fn mailbox(spawner: &'static MaybeUninit<Self>) -> &'static MaybeUninit<Mailbox<R, N>> {
+        // SAFETY: `RemoteSpawner` is a transparent wrapper around the mailbox.
+        unsafe { &*(spawner as *const MaybeUninit<Self>).cast() }
+    }
+
+    fn wrap(requests: &'static Mailbox<R, N>) -> &'static Self {
+        // SAFETY: `RemoteSpawner` is a transparent wrapper around the mailbox.
+        unsafe { &*(requests as *const Mailbox<R, N>).cast() }
+    }
+
+    /// Initialize the spawner, using hardware semaphore `sem_id` to protect its requests.
+    ///
+    /// This must be called by one of the cores before the other core calls
+    /// [`RemoteSpawner::get`], see [`Mailbox::init`].
+    pub fn init(spawner: &'static MaybeUninit<Self>, sem_id: u8) -> &'static Self {
+        Self::wrap(Mailbox::init(Self::mailbox(spawner), sem_id))
+    }
+
+    /// Get the spawner initialized by the other core with [`RemoteSpawner::init`].
+    ///
+    /// Returns `None` if the other core hasn't initialized it yet.
+    pub fn try_get(spawner: &'static MaybeUninit<Self>) -> Option<&'static Self> {
+        Mailbox::try_get(Self::mailbox(spawner)).map(Self::wrap)
+    }
+
+    /// Get the spawner initialized by the other core with [`RemoteSpawner::init`].
+    ///
+    /// If the other core hasn't initialized it yet, this will spinloop wait on it.
+    pub fn get(spawner: &'static MaybeUninit<Self>) -> &'static Self {
+        Self::wrap(Mailbox::get(Self::mailbox(spawner)))
+    }
+
+    /// Ask the other core to spawn a task, waiting until there is room for the request.
+    pub async fn spawn(&self, request: R) {
+        self.requests.send(request).await
+    }
+
+    /// Ask the other core to spawn a task, returning the request back if too many are pending.
+    pub fn try_spawn(&self, request: R) -> Result<(), R> {
+        self.requests.try_send(request)
+    }
+
+    /// Handle the requests sent by the other core.
+    ///
+    /// Run this on the target core, `spawn` is called with each request and spawns the task it
+    /// describes.
+    pub async fn run(&self, mut spawn: impl FnMut(R)) -> ! {
+        loop {
+            spawn(self.requests.receive().await);
+        }
+    }
+}
@@ -790,7 +790,7 @@ impl<'d, T: Instance> Hspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
 
         finish_dma(T::REGS);
 
@@ -820,7 +820,7 @@ impl<'d, T: Instance> Hspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
 
         finish_dma(T::REGS);
 
@@ -860,7 +860,7 @@ impl<'d, T: Instance> Hspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.await;
+        unwrap!(transfer.await);
 
         finish_dma(T::REGS);
 
@@ -890,7 +890,7 @@ impl<'d, T: Instance> Hspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.await;
+        unwrap!(transfer.await);
 
         finish_dma(T::REGS);
 
@@ -19,7 +19,7 @@ use embassy_time::{Duration, Instant};
 use mode::MasterMode;
 pub use mode::{Master, MultiMaster};
 
-use crate::dma::ChannelAndRequest;
+use crate::dma::{ChannelAndRequest, TransferError};
 use crate::gpio::{AnyPin, SealedPin as _};
 use crate::interrupt::typelevel::Interrupt;
 use crate::mode::{Async, Blocking, Mode};
@@ -30,6 +30,7 @@ use crate::{interrupt, peripherals};
 /// I2C error.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error {
     /// Bus error
     Bus,
@@ -45,6 +46,8 @@ pub enum Error {
     Overrun,
     /// Zero-length transfers are not allowed.
     ZeroLengthTransfer,
+    /// DMA transfer error
+    Dma(TransferError),
 }
 
 impl core::fmt::Display for Error {
@@ -57,6 +60,7 @@ impl core::fmt::Display for Error {
             Self::Crc => "CRC Mismatch",
             Self::Overrun => "Buffer Overrun",
             Self::ZeroLengthTransfer => "Zero-Length Transfers are not allowed",
+            Self::Dma(_) => "DMA Transfer Error",
         };
 
         write!(f, "{}", message)
@@ -65,6 +69,12 @@ impl core::fmt::Display for Error {
 
 impl core::error::Error for Error {}
 
+impl From<TransferError> for Error {
+    fn from(error: TransferError) -> Self {
+        Self::Dma(error)
+    }
+}
+
 /// I2C modes
 pub mod mode {
     trait SealedMode {}
@@ -388,6 +398,7 @@ impl embedded_hal_1::i2c::Error for Error {
             Self::Crc => embedded_hal_1::i2c::ErrorKind::Other,
             Self::Overrun => embedded_hal_1::i2c::ErrorKind::Overrun,
             Self::ZeroLengthTransfer => embedded_hal_1::i2c::ErrorKind::Other,
+            Self::Dma(_) => embedded_hal_1::i2c::ErrorKind::Other,
         }
     }
 }
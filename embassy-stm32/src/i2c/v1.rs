@@ -458,6 +458,7 @@ impl<'d, IM: MasterMode> I2c<'d, Async, IM> {
 
         // Wait for either the DMA transfer to successfully finish, or an I2C error to occur.
         match select(dma_transfer, poll_error).await {
+            Either::First(Err(e)) => Err(e.into()),
             Either::Second(Err(e)) => Err(e),
             _ => Ok(()),
         }?;
@@ -652,6 +653,7 @@ impl<'d, IM: MasterMode> I2c<'d, Async, IM> {
         });
 
         match select(dma_transfer, poll_error).await {
+            Either::First(Err(e)) => Err(e.into()),
             Either::Second(Err(e)) => Err(e),
             _ => Ok(()),
         }?;
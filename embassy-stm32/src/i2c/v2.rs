@@ -576,7 +576,7 @@ impl<'d, IM: MasterMode> I2c<'d, Async, IM> {
         })
         .await?;
 
-        dma_transfer.await;
+        dma_transfer.await?;
         if last_slice {
             // This should be done already
             self.wait_tc(timeout)?;
@@ -684,7 +684,7 @@ impl<'d, IM: MasterMode> I2c<'d, Async, IM> {
         })
         .await?;
 
-        dma_transfer.await;
+        dma_transfer.await?;
         drop(on_drop);
 
         Ok(())
@@ -943,6 +943,10 @@ impl<'d, M: Mode> I2c<'d, M, MultiMaster> {
     /// The listen method is an asynchronous method but it does not require DMA to be asynchronous.
     pub async fn listen(&mut self) -> Result<SlaveCommand, Error> {
         let state = self.state;
+        // Address matching in stop mode needs the wakeup from stop to be configured, which isn't
+        // supported by this driver.
+        #[cfg(feature = "low-power")]
+        let _stop_limit = crate::low_power::StopModeLimit::no_stop();
         self.info.regs.cr1().modify(|reg| {
             reg.set_addrie(true);
         });
@@ -1055,7 +1059,7 @@ impl<'d> I2c<'d, Async, MultiMaster> {
         })
         .await?;
 
-        dma_transfer.await;
+        dma_transfer.await?;
 
         drop(on_drop);
 
@@ -1120,7 +1124,7 @@ impl<'d> I2c<'d, Async, MultiMaster> {
         })
         .await?;
 
-        dma_transfer.await;
+        dma_transfer.await?;
 
         drop(on_drop);
 
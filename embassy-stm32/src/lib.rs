@@ -46,6 +46,8 @@ pub mod dma;
 pub mod gpio;
 pub mod rcc;
 #[cfg(feature = "_time-driver")]
+#[cfg_attr(time_driver_lptim, path = "time_driver_lptim.rs")]
+#[cfg_attr(time_driver_rtc, path = "time_driver_rtc.rs")]
 mod time_driver;
 pub mod timer;
 
@@ -97,6 +99,7 @@ pub mod low_power;
 pub mod lptim;
 #[cfg(ltdc)]
 pub mod ltdc;
+pub mod onewire;
 #[cfg(opamp)]
 pub mod opamp;
 #[cfg(octospi)]
@@ -125,6 +128,8 @@ pub mod uid;
 pub mod usart;
 #[cfg(any(usb, otg))]
 pub mod usb;
+#[cfg(any(stm32l4, stm32l5, stm32g4, stm32wb, stm32wl, stm32h7))]
+pub mod vbat;
 #[cfg(iwdg)]
 pub mod wdg;
 #[cfg(xspi)]
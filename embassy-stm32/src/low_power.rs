@@ -50,6 +50,20 @@
 //!     // your application here...
 //! }
 //! ```
+//!
+//! With the `time-driver-lptim1` or `time-driver-lptim2` feature and the LPTIM clocked from LSE or
+//! LSI, the time driver keeps counting in Stop mode and wakes the core with its own interrupt, so
+//! the `RTC` doesn't need to be given to the executor.
+//!
+//! On L4, L5 and U0, [`standby_with_rtc`] enters Standby mode between periodic tasks, waking up
+//! with the RTC wakeup timer. The chip restarts from reset, and [`woke_from_standby`] tells
+//! whether it did so from Standby.
+//!
+//! Drivers take a [`StopModeLimit`] while they need more than their peripheral clock, for example
+//! DMA transfers and ring buffers hold one while they exist, since DMA doesn't run in stop mode.
+//! A driver can also keep one and [`set`](StopModeLimit::set) it as its operations change. The
+//! executor then stops only as deep as [`deepest_stop_mode`] allows, and only sleeps while a limit
+//! forbids stop modes.
 
 // TODO: Usage of `static mut` here is unsound. Fix then remove this `allow`.`
 #![allow(static_mut_refs)]
@@ -70,7 +84,8 @@ use crate::rtc::Rtc;
 
 static mut EXECUTOR: Option<Executor> = None;
 
-#[cfg(not(stm32u0))]
+// With the RTC time driver, the wakeup interrupt is handled by the time driver.
+#[cfg(all(not(stm32u0), not(time_driver_rtc)))]
 foreach_interrupt! {
     (RTC, rtc, $block:ident, WKUP, $irq:ident) => {
         #[interrupt]
@@ -81,7 +96,7 @@ foreach_interrupt! {
     };
 }
 
-#[cfg(stm32u0)]
+#[cfg(all(stm32u0, not(time_driver_rtc)))]
 foreach_interrupt! {
     (RTC, rtc, $block:ident, TAMP, $irq:ident) => {
         #[interrupt]
@@ -102,21 +117,138 @@ pub fn stop_with_rtc(rtc: &'static Rtc) {
     unsafe { EXECUTOR.as_mut().unwrap() }.stop_with_rtc(rtc)
 }
 
+/// Enter Standby mode, waking up after `seconds` with the RTC wakeup timer.
+///
+/// RAM and registers are lost in Standby: the chip restarts from reset when it wakes up, and
+/// [`woke_from_standby`] then returns true. The RTC calendar and backup registers are kept, so
+/// the application can resume from there, for example for a periodic measurement.
+#[cfg(any(stm32l4, stm32l5, stm32u0))]
+pub fn standby_with_rtc(rtc: &Rtc, seconds: u32) -> ! {
+    cortex_m::interrupt::disable();
+
+    rtc.start_wakeup_timer_seconds(seconds);
+    crate::pac::PWR.cr1().modify(|w| w.set_lpms(Lpms::STANDBY));
+    unsafe { cortex_m::Peripherals::steal() }.SCB.set_sleepdeep();
+
+    loop {
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+    }
+}
+
+/// Return whether the chip was reset by waking up from Standby mode, and clear the Standby flag.
+#[cfg(any(stm32l4, stm32l5, stm32u0))]
+pub fn woke_from_standby() -> bool {
+    // SBF in SR1, cleared with CSBF in SCR.
+    const SBF: u32 = 1 << 8;
+
+    let standby = crate::pac::PWR.sr1().read().0 & SBF != 0;
+    if standby {
+        crate::pac::PWR.scr().write(|w| w.0 = SBF);
+    }
+    standby
+}
+
 /// Get whether the core is ready to enter the given stop mode.
 ///
 /// This will return false if some peripheral driver is in use that
 /// prevents entering the given stop mode.
 pub fn stop_ready(stop_mode: StopMode) -> bool {
-    match unsafe { EXECUTOR.as_mut().unwrap() }.stop_mode() {
+    match deepest_stop_mode() {
         Some(StopMode::Stop2) => true,
         Some(StopMode::Stop1) => stop_mode == StopMode::Stop1,
         None => false,
     }
 }
 
+/// Get the deepest stop mode allowed by the enabled peripherals and the [`StopModeLimit`]s.
+///
+/// Returns `None` if the executor may only sleep.
+pub fn deepest_stop_mode() -> Option<StopMode> {
+    critical_section::with(|_| unsafe {
+        if crate::rcc::REFCOUNT_STOP1 != 0 {
+            None
+        } else if crate::rcc::REFCOUNT_STOP2 != 0 {
+            Some(StopMode::Stop1)
+        } else {
+            Some(StopMode::Stop2)
+        }
+    })
+}
+
+/// Limits how deep the executor may stop while alive.
+///
+/// The executor already stays out of the stop modes that would stop the clock of an enabled
+/// peripheral. Drivers take a `StopModeLimit` for operations that need more than the peripheral
+/// clock, for example a DMA transfer or listening for an I2C address match, and applications can
+/// take one while a peripheral is used in a way the driver doesn't know about.
+#[must_use = "the limit is released when dropped"]
+pub struct StopModeLimit {
+    deepest: Option<StopMode>,
+}
+
+impl StopModeLimit {
+    /// Don't enter stop modes deeper than `deepest`.
+    pub fn new(deepest: StopMode) -> Self {
+        Self::with(Some(deepest))
+    }
+
+    /// Don't enter any stop mode, only sleep.
+    pub fn no_stop() -> Self {
+        Self::with(None)
+    }
+
+    fn with(deepest: Option<StopMode>) -> Self {
+        critical_section::with(|_| unsafe { Self::acquire(deepest) });
+        Self { deepest }
+    }
+
+    /// Get the deepest stop mode this limit allows, `None` if it only allows sleep.
+    pub fn deepest(&self) -> Option<StopMode> {
+        self.deepest
+    }
+
+    /// Change the limit to `deepest`, or to only sleep if `None`.
+    ///
+    /// This lets a driver keep a single limit, and loosen or tighten it as it starts and finishes
+    /// operations.
+    pub fn set(&mut self, deepest: Option<StopMode>) {
+        critical_section::with(|_| unsafe {
+            Self::acquire(deepest);
+            Self::release(self.deepest);
+        });
+        self.deepest = deepest;
+    }
+
+    /// Safety: must be called in a critical section.
+    unsafe fn acquire(deepest: Option<StopMode>) {
+        match deepest {
+            Some(StopMode::Stop1) => crate::rcc::REFCOUNT_STOP2 += 1,
+            Some(StopMode::Stop2) => {}
+            None => crate::rcc::REFCOUNT_STOP1 += 1,
+        }
+    }
+
+    /// Safety: must be called in a critical section, with a limit taken with `acquire`.
+    unsafe fn release(deepest: Option<StopMode>) {
+        match deepest {
+            Some(StopMode::Stop1) => crate::rcc::REFCOUNT_STOP2 -= 1,
+            Some(StopMode::Stop2) => {}
+            None => crate::rcc::REFCOUNT_STOP1 -= 1,
+        }
+    }
+}
+
+impl Drop for StopModeLimit {
+    fn drop(&mut self) {
+        critical_section::with(|_| unsafe { Self::release(self.deepest) });
+    }
+}
+
 /// Available Stop modes.
 #[non_exhaustive]
-#[derive(PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
 pub enum StopMode {
     /// STOP 1
     Stop1,
@@ -186,16 +318,6 @@ impl Executor {
         trace!("low power: stop with rtc configured");
     }
 
-    fn stop_mode(&self) -> Option<StopMode> {
-        if unsafe { crate::rcc::REFCOUNT_STOP2 == 0 } && unsafe { crate::rcc::REFCOUNT_STOP1 == 0 } {
-            Some(StopMode::Stop2)
-        } else if unsafe { crate::rcc::REFCOUNT_STOP1 == 0 } {
-            Some(StopMode::Stop1)
-        } else {
-            None
-        }
-    }
-
     #[allow(unused_variables)]
     fn configure_stop(&mut self, stop_mode: StopMode) {
         #[cfg(any(stm32l4, stm32l5, stm32u5, stm32u0))]
@@ -213,7 +335,7 @@ impl Executor {
 
         compiler_fence(Ordering::SeqCst);
 
-        let stop_mode = self.stop_mode();
+        let stop_mode = deepest_stop_mode();
 
         if stop_mode.is_none() {
             trace!("low power: not ready to stop");
@@ -255,6 +377,17 @@ impl Executor {
     ///
     /// This function never returns.
     pub fn run(&'static mut self, init: impl FnOnce(Spawner)) -> ! {
+        self.run_with_idle(init, || {})
+    }
+
+    /// Run the executor, calling `idle` each time it goes to sleep.
+    ///
+    /// `idle` is called after the sleep or stop mode has been configured, right before the `WFE`.
+    /// It can for example turn off external components, or take a [`StopModeLimit`] for the next
+    /// time the executor goes to sleep.
+    ///
+    /// See [`Executor::run`] for details.
+    pub fn run_with_idle(&'static mut self, init: impl FnOnce(Spawner), mut idle: impl FnMut()) -> ! {
         let executor = unsafe { EXECUTOR.as_mut().unwrap() };
         init(executor.inner.spawner());
 
@@ -262,6 +395,7 @@ impl Executor {
             unsafe {
                 executor.inner.poll();
                 self.configure_pwr();
+                idle();
                 asm!("wfe");
             };
         }
@@ -0,0 +1,161 @@
+//! Pulse counter driver.
+//!
+//! Counts the edges seen on the timer's Input1 pin. The counter keeps running in Stop mode when
+//! the kernel clock is LSE or LSI.
+
+use embassy_hal_internal::Peri;
+
+use super::timer::Timer;
+use super::{Input1Pin, Instance, InterruptHandler};
+use crate::gpio::{AfType, AnyPin, Pull};
+use crate::interrupt::typelevel::{Binding, Interrupt};
+use crate::pac::lptim::vals;
+
+/// Edge of the input signal on which the counter is incremented.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Edge {
+    /// Count rising edges.
+    Rising,
+    /// Count falling edges.
+    Falling,
+    /// Count both edges.
+    Both,
+}
+
+impl From<Edge> for vals::Ckpol {
+    fn from(edge: Edge) -> Self {
+        match edge {
+            Edge::Rising => vals::Ckpol::RISING_EDGE,
+            Edge::Falling => vals::Ckpol::FALLING_EDGE,
+            Edge::Both => vals::Ckpol::BOTH_EDGES,
+        }
+    }
+}
+
+/// Digital filter applied to the input signal.
+///
+/// An edge is only counted once the input has been stable for the given number of kernel
+/// clock cycles.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Filter {
+    /// No filtering.
+    None,
+    /// 2 clock cycles.
+    Clocks2,
+    /// 4 clock cycles.
+    Clocks4,
+    /// 8 clock cycles.
+    Clocks8,
+}
+
+impl From<Filter> for vals::Filter {
+    fn from(filter: Filter) -> Self {
+        match filter {
+            Filter::None => vals::Filter::CLOCKS1,
+            Filter::Clocks2 => vals::Filter::CLOCKS2,
+            Filter::Clocks4 => vals::Filter::CLOCKS4,
+            Filter::Clocks8 => vals::Filter::CLOCKS8,
+        }
+    }
+}
+
+/// Pulse counter configuration.
+#[non_exhaustive]
+#[derive(Clone, Copy)]
+pub struct Config {
+    /// Edge on which the counter is incremented.
+    pub edge: Edge,
+    /// Input filter.
+    pub filter: Filter,
+    /// Input pin pull.
+    pub pull: Pull,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            edge: Edge::Rising,
+            filter: Filter::None,
+            pull: Pull::None,
+        }
+    }
+}
+
+/// Pulse counter driver.
+pub struct Counter<'d, T: Instance> {
+    inner: Timer<'d, T>,
+    _pin: Peri<'d, AnyPin>,
+}
+
+impl<'d, T: Instance> Counter<'d, T> {
+    /// Create a new pulse counter driver.
+    pub fn new(
+        tim: Peri<'d, T>,
+        pin: Peri<'d, impl Input1Pin<T>>,
+        _irq: impl Binding<T::Interrupt, InterruptHandler<T>> + 'd,
+        config: Config,
+    ) -> Self {
+        critical_section::with(|_| {
+            pin.set_as_af(pin.af_num(), AfType::input(config.pull));
+        });
+
+        let inner = Timer::new(tim);
+
+        T::regs().cfgr().modify(|w| {
+            w.set_countmode(true);
+            w.set_ckpol(config.edge.into());
+            w.set_ckflt(config.filter.into());
+        });
+        inner.enable_autoreload_match_interrupt();
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        let this = Self {
+            inner,
+            _pin: pin.into(),
+        };
+        this.start(u16::MAX);
+        this
+    }
+
+    fn start(&self, limit: u16) {
+        // Disabling the timer resets the counter.
+        self.inner.disable();
+        self.inner.clear_autoreload_match();
+        self.inner.enable();
+        self.inner.set_autoreload(limit);
+        self.inner.continuous_mode_start();
+    }
+
+    /// Get the number of pulses counted since the last reset.
+    ///
+    /// The counter wraps around to 0 after 65535.
+    pub fn count(&self) -> u16 {
+        self.inner.get_counter()
+    }
+
+    /// Reset the counter to 0.
+    pub fn reset(&mut self) {
+        self.start(u16::MAX);
+    }
+
+    /// Reset the counter, then wait until `pulses` edges have been counted.
+    ///
+    /// The counter is reset again once the given number of pulses is reached.
+    pub async fn wait_for_pulses(&mut self, pulses: u16) {
+        assert!(pulses > 0);
+
+        self.start(pulses);
+        self.inner.wait_autoreload_match().await;
+        self.start(u16::MAX);
+    }
+}
+
+impl<'d, T: Instance> Drop for Counter<'d, T> {
+    fn drop(&mut self) {
+        self.inner.disable();
+    }
+}
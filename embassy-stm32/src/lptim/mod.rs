@@ -1,8 +1,16 @@
 //! Low-power timer (LPTIM)
 
+pub mod counter;
 pub mod pwm;
+pub mod timeout;
 pub mod timer;
 
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::waitqueue::AtomicWaker;
+
+use crate::interrupt;
 use crate::rcc::RccPeripheral;
 
 /// Timer channel.
@@ -15,9 +23,26 @@ use embassy_hal_internal::PeripheralType;
 pin_trait!(OutputPin, BasicInstance);
 pin_trait!(Channel1Pin, BasicInstance);
 pin_trait!(Channel2Pin, BasicInstance);
+pin_trait!(Input1Pin, BasicInstance);
+
+pub(crate) struct State {
+    waker: AtomicWaker,
+    /// Set by the interrupt handler when the counter reached the auto-reload value.
+    arr_match: AtomicBool,
+}
+
+impl State {
+    const fn new() -> Self {
+        Self {
+            waker: AtomicWaker::new(),
+            arr_match: AtomicBool::new(false),
+        }
+    }
+}
 
 pub(crate) trait SealedInstance: RccPeripheral {
     fn regs() -> crate::pac::lptim::Lptim;
+    fn state() -> &'static State;
 }
 pub(crate) trait SealedBasicInstance: RccPeripheral {}
 
@@ -27,7 +52,33 @@ pub trait BasicInstance: PeripheralType + SealedBasicInstance + 'static {}
 
 /// LPTIM instance trait.
 #[allow(private_bounds)]
-pub trait Instance: BasicInstance + SealedInstance + 'static {}
+pub trait Instance: BasicInstance + SealedInstance + 'static {
+    /// Global interrupt for this timer.
+    type Interrupt: interrupt::typelevel::Interrupt;
+}
+
+/// LPTIM global interrupt handler.
+///
+/// Required by the [`timeout::Timeout`] and [`counter::Counter`] drivers.
+pub struct InterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        #[cfg(feature = "low-power")]
+        crate::low_power::on_wakeup_irq();
+
+        // On some LPTIM versions the interrupt enable register can only be written while the timer
+        // is disabled, so instead of masking the interrupt we clear the flag and record the event.
+        let regs = T::regs();
+        if regs.isr().read().arrm() {
+            regs.icr().write(|w| w.set_arrmcf(true));
+            T::state().arr_match.store(true, Ordering::Release);
+            T::state().waker.wake();
+        }
+    }
+}
 
 foreach_interrupt! {
     ($inst:ident, lptim, LPTIM, GLOBAL, $irq:ident) => {
@@ -35,11 +86,18 @@ foreach_interrupt! {
             fn regs() -> crate::pac::lptim::Lptim {
                 crate::pac::$inst
             }
+
+            fn state() -> &'static State {
+                static STATE: State = State::new();
+                &STATE
+            }
         }
         impl SealedBasicInstance for crate::peripherals::$inst {
         }
         impl BasicInstance for crate::peripherals::$inst {}
-        impl Instance for crate::peripherals::$inst {}
+        impl Instance for crate::peripherals::$inst {
+            type Interrupt = crate::interrupt::typelevel::$irq;
+        }
     };
     ($inst:ident, lptim, LPTIM_BASIC, GLOBAL, $irq:ident) => {
         impl SealedBasicInstance for crate::peripherals::$inst {
@@ -0,0 +1,76 @@
+//! Timeout and wakeup driver.
+//!
+//! The LPTIM keeps counting in Stop mode when its kernel clock is LSE or LSI, so this driver can
+//! be used to wake the core after a given amount of time without keeping a high-speed clock
+//! running. The kernel clock is selected through the `lptimXsel` RCC mux.
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_hal_internal::Peri;
+
+use super::timer::Timer;
+use super::{Instance, InterruptHandler};
+use crate::interrupt::typelevel::{Binding, Interrupt};
+use crate::time::Hertz;
+
+/// Largest number of kernel clock cycles that fit in a single timer period.
+const MAX_TICKS_PER_PERIOD: u64 = u16::MAX as u64 * 128;
+
+/// Timeout driver.
+pub struct Timeout<'d, T: Instance> {
+    inner: Timer<'d, T>,
+}
+
+impl<'d, T: Instance> Timeout<'d, T> {
+    /// Create a new timeout driver.
+    pub fn new(tim: Peri<'d, T>, _irq: impl Binding<T::Interrupt, InterruptHandler<T>> + 'd) -> Self {
+        let inner = Timer::new(tim);
+
+        inner.enable_autoreload_match_interrupt();
+
+        T::Interrupt::unpend();
+        unsafe { T::Interrupt::enable() };
+
+        Self { inner }
+    }
+
+    /// Get the kernel clock frequency of the timer.
+    pub fn get_clock_frequency(&self) -> Hertz {
+        self.inner.get_clock_frequency()
+    }
+
+    /// Wait for the given number of kernel clock cycles.
+    ///
+    /// Long waits are split in several timer periods, each rounded down to a multiple of the
+    /// prescaler used for that period.
+    pub async fn wait_ticks(&mut self, ticks: u64) {
+        let _on_drop = OnDrop::new(|| self.inner.disable());
+
+        let mut remaining = ticks;
+        while remaining > 0 {
+            let chunk = remaining.min(MAX_TICKS_PER_PERIOD) as u32;
+            remaining -= chunk as u64;
+
+            self.inner.disable();
+            let arr = self.inner.set_prescaler_for_ticks(chunk);
+            if arr == 0 {
+                continue;
+            }
+
+            self.inner.clear_autoreload_match();
+            self.inner.enable();
+            self.inner.set_autoreload(arr);
+            self.inner.single_mode_start();
+
+            self.inner.wait_autoreload_match().await;
+        }
+    }
+
+    /// Wait for the given duration.
+    #[cfg(feature = "time")]
+    pub async fn wait(&mut self, duration: embassy_time::Duration) {
+        let f = self.inner.get_clock_frequency().0 as u64;
+        let ticks = duration.as_micros().saturating_mul(f) / 1_000_000;
+
+        self.wait_ticks(ticks).await
+    }
+}
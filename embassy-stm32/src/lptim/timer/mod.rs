@@ -1,6 +1,10 @@
 //! Low-level timer driver.
 mod prescaler;
 
+use core::future::poll_fn;
+use core::sync::atomic::Ordering;
+use core::task::Poll;
+
 use embassy_hal_internal::Peri;
 
 #[cfg(any(lptim_v2a, lptim_v2b))]
@@ -82,6 +86,75 @@ impl<'d, T: Instance> Timer<'d, T> {
     pub fn get_max_compare_value(&self) -> u16 {
         T::regs().arr().read().arr()
     }
+
+    /// Set the auto-reload value.
+    ///
+    /// The timer must be enabled. This blocks until the new value has been transferred to the
+    /// timer clock domain.
+    pub fn set_autoreload(&self, value: u16) {
+        let regs = T::regs();
+
+        regs.icr().write(|w| w.set_arrokcf(true));
+        regs.arr().modify(|w| w.set_arr(value));
+        while !regs.isr().read().arrok() {}
+        regs.icr().write(|w| w.set_arrokcf(true));
+    }
+
+    /// Get the current counter value.
+    ///
+    /// The counter may run from a clock that is asynchronous to the APB clock, so it is read until
+    /// two consecutive reads return the same value.
+    pub fn get_counter(&self) -> u16 {
+        let regs = T::regs();
+
+        loop {
+            let a = regs.cnt().read().cnt();
+            let b = regs.cnt().read().cnt();
+            if a == b {
+                return a;
+            }
+        }
+    }
+
+    /// Select the smallest prescaler for which `ticks` kernel clock cycles fit in a timer period,
+    /// and return the corresponding number of timer ticks.
+    ///
+    /// The timer must be disabled.
+    pub(crate) fn set_prescaler_for_ticks(&self, ticks: u32) -> u16 {
+        let psc = Prescaler::from_ticks(ticks);
+        T::regs().cfgr().modify(|w| w.set_presc((&psc).into()));
+        psc.scale_down(ticks)
+    }
+
+    /// Enable the auto-reload match interrupt.
+    ///
+    /// On some LPTIM versions the interrupt enable register can only be written while the
+    /// timer is disabled.
+    pub(crate) fn enable_autoreload_match_interrupt(&self) {
+        #[cfg(not(any(lptim_v2a, lptim_v2b)))]
+        T::regs().ier().modify(|w| w.set_arrmie(true));
+        #[cfg(any(lptim_v2a, lptim_v2b))]
+        T::regs().dier().modify(|w| w.set_arrmie(true));
+    }
+
+    /// Clear a pending auto-reload match event recorded by the interrupt handler.
+    pub(crate) fn clear_autoreload_match(&self) {
+        T::state().arr_match.store(false, Ordering::Release);
+    }
+
+    /// Wait until the interrupt handler records an auto-reload match.
+    pub(crate) async fn wait_autoreload_match(&self) {
+        poll_fn(|cx| {
+            T::state().waker.register(cx.waker());
+
+            if T::state().arr_match.swap(false, Ordering::AcqRel) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
 }
 
 #[cfg(any(lptim_v2a, lptim_v2b))]
@@ -77,7 +77,7 @@ impl From<u32> for Prescaler {
 impl Prescaler {
     pub fn from_ticks(ticks: u32) -> Self {
         // We need to scale down to a 16-bit range
-        (ticks >> 16).next_power_of_two().into()
+        ticks.div_ceil(u16::MAX as u32).next_power_of_two().into()
     }
 
     pub fn scale_down(&self, ticks: u32) -> u16 {
@@ -9,7 +9,7 @@ use core::task::Poll;
 use embassy_hal_internal::PeripheralType;
 use embassy_sync::waitqueue::AtomicWaker;
 use stm32_metapac::ltdc::regs::Dccr;
-use stm32_metapac::ltdc::vals::{Bf1, Bf2, Cfuif, Clif, Crrif, Cterrif, Pf, Vbr};
+use stm32_metapac::ltdc::vals::{Bf1, Bf2, Cfuif, Clif, Crrif, Cterrif, Imr, Pf, Vbr};
 
 use crate::gpio::{AfType, OutputType, Speed};
 use crate::interrupt::typelevel::Interrupt;
@@ -156,6 +156,16 @@ impl PixelFormat {
     }
 }
 
+/// Blending of a layer with the layers below it and the background.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Blending {
+    /// Blend with the constant alpha of the layer.
+    ConstantAlpha,
+    /// Blend with the alpha of each pixel, multiplied by the constant alpha of the layer.
+    PixelAlpha,
+}
+
 /// Ltdc Blending Layer
 #[repr(usize)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -417,12 +427,12 @@ impl<'d, T: Instance> Ltdc<'d, T> {
         let mut bits = T::regs().isr().read();
 
         // if all clear
-        if !bits.fuif() && !bits.lif() && !bits.rrif() && !bits.terrif() {
+        if !bits.fuif() && !bits.rrif() && !bits.terrif() {
             // wait for interrupt
             poll_fn(|cx| {
                 // quick check to avoid registration if already done.
                 let bits = T::regs().isr().read();
-                if bits.fuif() || bits.lif() || bits.rrif() || bits.terrif() {
+                if bits.fuif() || bits.rrif() || bits.terrif() {
                     return Poll::Ready(());
                 }
 
@@ -442,7 +452,7 @@ impl<'d, T: Instance> Ltdc<'d, T> {
                 // need to check condition after register to avoid a race
                 // condition that would result in lost notifications.
                 let bits = T::regs().isr().read();
-                if bits.fuif() || bits.lif() || bits.rrif() || bits.terrif() {
+                if bits.fuif() || bits.rrif() || bits.terrif() {
                     Poll::Ready(())
                 } else {
                     Poll::Pending
@@ -458,8 +468,6 @@ impl<'d, T: Instance> Ltdc<'d, T> {
             Err(Error::FifoUnderrun)
         } else if bits.terrif() {
             Err(Error::TransferError)
-        } else if bits.lif() {
-            panic!("line interrupt event is disabled")
         } else if bits.rrif() {
             // register reload flag is expected
             Ok(())
@@ -471,6 +479,97 @@ impl<'d, T: Instance> Ltdc<'d, T> {
         result
     }
 
+    /// Wait for the start of the next vertical blanking period.
+    ///
+    /// Drawing into a framebuffer which is not displayed, then swapping it in with
+    /// [`set_buffer`](Self::set_buffer), avoids tearing. When drawing into the displayed framebuffer
+    /// instead, waiting for the vertical blanking gives the most time before the controller reads
+    /// it again.
+    pub async fn wait_for_vsync(&mut self) -> Result<(), Error> {
+        let ltdc = T::regs();
+
+        // Interrupt at the first line after the active area, or the last line without front porch.
+        let line = (ltdc.awcr().read().aah() + 1).min(ltdc.twcr().read().totalh());
+        ltdc.lipcr().write(|w| w.set_lipos(line));
+        ltdc.icr().write(|w| w.set_clif(Clif::CLEAR));
+
+        poll_fn(|cx| {
+            LTDC_WAKER.register(cx.waker());
+
+            let bits = ltdc.isr().read();
+            if bits.fuif() {
+                ltdc.icr().write(|w| w.set_cfuif(Cfuif::CLEAR));
+                return Poll::Ready(Err(Error::FifoUnderrun));
+            }
+            if bits.terrif() {
+                ltdc.icr().write(|w| w.set_cterrif(Cterrif::CLEAR));
+                return Poll::Ready(Err(Error::TransferError));
+            }
+            if bits.lif() {
+                ltdc.icr().write(|w| w.set_clif(Clif::CLEAR));
+                return Poll::Ready(Ok(()));
+            }
+
+            // The interrupt handler disables the interrupts, enable them again for each wait.
+            ltdc.ier().modify(|w| {
+                w.set_fuie(true);
+                w.set_lie(true);
+                w.set_terrie(true);
+            });
+            unsafe { T::Interrupt::enable() };
+            Poll::Pending
+        })
+        .await
+    }
+
+    /// Set the background color, displayed where no layer is enabled.
+    pub fn set_background_color(&mut self, color: RgbColor) {
+        T::regs().bccr().modify(|w| {
+            w.set_bcred(color.red);
+            w.set_bcgreen(color.green);
+            w.set_bcblue(color.blue);
+        });
+    }
+
+    /// Set the blending and the constant alpha of a layer.
+    ///
+    /// Like the other layer settings, this takes effect at the next [`set_buffer`](Self::set_buffer)
+    /// or [`reload`](Self::reload).
+    pub fn set_layer_blending(&mut self, layer: LtdcLayer, blending: Blending, alpha: u8) {
+        let layer = T::regs().layer(layer as usize);
+        layer.cacr().write(|w| w.set_consta(alpha));
+        layer.bfcr().modify(|w| match blending {
+            Blending::ConstantAlpha => {
+                w.set_bf1(Bf1::CONSTANT);
+                w.set_bf2(Bf2::CONSTANT);
+            }
+            Blending::PixelAlpha => {
+                w.set_bf1(Bf1::PIXEL);
+                w.set_bf2(Bf2::PIXEL);
+            }
+        });
+    }
+
+    /// Set the color and alpha displayed outside of the window of a layer.
+    pub fn set_layer_default_color(&mut self, layer: LtdcLayer, color: RgbColor, alpha: u8) {
+        T::regs().layer(layer as usize).dccr().write(|w| {
+            w.set_dcred(color.red);
+            w.set_dcgreen(color.green);
+            w.set_dcblue(color.blue);
+            w.set_dcalpha(alpha);
+        });
+    }
+
+    /// Enable or disable a layer.
+    pub fn set_layer_enabled(&mut self, layer: LtdcLayer, enabled: bool) {
+        T::regs().layer(layer as usize).cr().modify(|w| w.set_len(enabled));
+    }
+
+    /// Apply the layer settings immediately, instead of at the next vertical blanking.
+    pub fn reload(&mut self) {
+        T::regs().srcr().write(|w| w.set_imr(Imr::RELOAD));
+    }
+
     fn setup_clocks() {
         critical_section::with(|_cs| {
             // RM says the pllsaidivr should only be changed when pllsai is off. But this could have other unintended side effects. So let's just give it a try like this.
@@ -0,0 +1,300 @@
+//! 1-Wire (Dallas) bus master.
+//!
+//! The bus is driven by an open-drain pin, which needs an external pull-up resistor, typically
+//! 4.7 kOhm. The protocol slots are only a few microseconds wide, which is finer than the
+//! resolution of `embassy-time`, so they are timed by busy-waiting on a timer counting at 1 MHz.
+//! Each slot runs in a critical section, so interrupts can't stretch it.
+
+use crate::gpio::{Flex, Pin, Speed};
+use crate::time::Hertz;
+use crate::timer::low_level::Timer;
+use crate::timer::CoreInstance;
+use crate::Peri;
+
+/// Search ROM command.
+const SEARCH_ROM: u8 = 0xF0;
+/// Read ROM command.
+const READ_ROM: u8 = 0x33;
+/// Match ROM command.
+const MATCH_ROM: u8 = 0x55;
+/// Skip ROM command.
+const SKIP_ROM: u8 = 0xCC;
+
+/// 1-Wire error.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Error {
+    /// No device answered the reset pulse.
+    NoPresence,
+    /// The CRC of the received data is invalid.
+    Crc,
+}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let message = match self {
+            Self::NoPresence => "No device present",
+            Self::Crc => "CRC Error",
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+impl core::error::Error for Error {}
+
+/// State of a ROM search, see [`OneWire::search`].
+#[derive(Debug, Clone)]
+pub struct RomSearch {
+    rom: u64,
+    last_discrepancy: u8,
+    done: bool,
+}
+
+impl RomSearch {
+    /// Start a new search.
+    pub const fn new() -> Self {
+        Self {
+            rom: 0,
+            last_discrepancy: 0,
+            done: false,
+        }
+    }
+}
+
+impl Default for RomSearch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 1-Wire bus master.
+pub struct OneWire<'d, T: CoreInstance> {
+    pin: Flex<'d>,
+    timer: Timer<'d, T>,
+}
+
+impl<'d, T: CoreInstance> OneWire<'d, T> {
+    /// Create a 1-Wire master on `pin`, timed by `tim`.
+    ///
+    /// The timer clock must be at least 1 MHz.
+    pub fn new(pin: Peri<'d, impl Pin>, tim: Peri<'d, T>) -> Self {
+        let mut pin = Flex::new(pin);
+        pin.set_high();
+        pin.set_as_input_output(Speed::Low);
+
+        let mut timer = Timer::new(tim);
+        timer.set_tick_freq(Hertz::mhz(1));
+        timer.set_max_compare_value(u16::MAX as u32);
+        timer.start();
+
+        Self { pin, timer }
+    }
+
+    /// Send a reset pulse, and return whether a device answered with a presence pulse.
+    pub fn reset(&mut self) -> bool {
+        self.pin.set_low();
+        self.wait_us(480);
+        let presence = critical_section::with(|_| {
+            self.pin.set_high();
+            self.wait_us(70);
+            self.pin.is_low()
+        });
+        self.wait_us(410);
+        presence
+    }
+
+    /// Write a bit.
+    pub fn write_bit(&mut self, bit: bool) {
+        critical_section::with(|_| {
+            self.pin.set_low();
+            if bit {
+                self.wait_us(6);
+                self.pin.set_high();
+                self.wait_us(64);
+            } else {
+                self.wait_us(60);
+                self.pin.set_high();
+                self.wait_us(10);
+            }
+        });
+    }
+
+    /// Read a bit.
+    pub fn read_bit(&mut self) -> bool {
+        critical_section::with(|_| {
+            self.pin.set_low();
+            self.wait_us(6);
+            self.pin.set_high();
+            self.wait_us(9);
+            let bit = self.pin.is_high();
+            self.wait_us(55);
+            bit
+        })
+    }
+
+    /// Write a byte, least significant bit first.
+    pub fn write_byte(&mut self, byte: u8) {
+        for i in 0..8 {
+            self.write_bit(byte & (1 << i) != 0);
+        }
+    }
+
+    /// Read a byte, least significant bit first.
+    pub fn read_byte(&mut self) -> u8 {
+        let mut byte = 0;
+        for i in 0..8 {
+            if self.read_bit() {
+                byte |= 1 << i;
+            }
+        }
+        byte
+    }
+
+    /// Write bytes.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.write_byte(byte);
+        }
+    }
+
+    /// Read bytes.
+    pub fn read_bytes(&mut self, bytes: &mut [u8]) {
+        for byte in bytes {
+            *byte = self.read_byte();
+        }
+    }
+
+    /// Reset the bus, and address all the devices with the Skip ROM command.
+    pub fn skip_rom(&mut self) -> Result<(), Error> {
+        self.reset_checked()?;
+        self.write_byte(SKIP_ROM);
+        Ok(())
+    }
+
+    /// Reset the bus, and address the device with the `rom` code with the Match ROM command.
+    pub fn match_rom(&mut self, rom: u64) -> Result<(), Error> {
+        self.reset_checked()?;
+        self.write_byte(MATCH_ROM);
+        self.write_bytes(&rom.to_le_bytes());
+        Ok(())
+    }
+
+    /// Reset the bus, and read the ROM code of the only device on the bus.
+    pub fn read_rom(&mut self) -> Result<u64, Error> {
+        self.reset_checked()?;
+        self.write_byte(READ_ROM);
+        let mut rom = [0; 8];
+        self.read_bytes(&mut rom);
+        if crc8(&rom) != 0 {
+            return Err(Error::Crc);
+        }
+        Ok(u64::from_le_bytes(rom))
+    }
+
+    /// Find the ROM code of the next device on the bus.
+    ///
+    /// Call this repeatedly with the same `search` to enumerate all the devices. This returns
+    /// `Ok(None)` once all of them were found.
+    pub fn search(&mut self, search: &mut RomSearch) -> Result<Option<u64>, Error> {
+        if search.done {
+            return Ok(None);
+        }
+        if let Err(e) = self.reset_checked() {
+            *search = RomSearch::new();
+            return Err(e);
+        }
+        self.write_byte(SEARCH_ROM);
+
+        let mut last_zero = 0;
+        for bit_number in 1..=64u8 {
+            let mask = 1 << (bit_number - 1);
+            let id_bit = self.read_bit();
+            let complement = self.read_bit();
+
+            let direction = match (id_bit, complement) {
+                // No device answered this bit.
+                (true, true) => {
+                    *search = RomSearch::new();
+                    return Err(Error::NoPresence);
+                }
+                // All the remaining devices have the same bit.
+                (true, false) => true,
+                (false, true) => false,
+                // Discrepancy: take the same branch as the previous search before the last
+                // discrepancy, the 1 branch at it, and the 0 branch after it.
+                (false, false) => {
+                    let direction = if bit_number < search.last_discrepancy {
+                        search.rom & mask != 0
+                    } else {
+                        bit_number == search.last_discrepancy
+                    };
+                    if !direction {
+                        last_zero = bit_number;
+                    }
+                    direction
+                }
+            };
+
+            if direction {
+                search.rom |= mask;
+            } else {
+                search.rom &= !mask;
+            }
+            self.write_bit(direction);
+        }
+
+        search.last_discrepancy = last_zero;
+        search.done = last_zero == 0;
+
+        if crc8(&search.rom.to_le_bytes()) != 0 {
+            *search = RomSearch::new();
+            return Err(Error::Crc);
+        }
+        Ok(Some(search.rom))
+    }
+
+    fn reset_checked(&mut self) -> Result<(), Error> {
+        match self.reset() {
+            true => Ok(()),
+            false => Err(Error::NoPresence),
+        }
+    }
+
+    fn wait_us(&self, us: u32) {
+        // The counter wraps at 16 bits or more.
+        let start = self.timer.get_counter();
+        while self.timer.get_counter().wrapping_sub(start) & 0xFFFF < us {}
+    }
+}
+
+/// Compute the 1-Wire CRC8 of `data`.
+///
+/// The CRC of data followed by its own CRC is 0, as in a ROM code or a scratchpad read.
+pub fn crc8(data: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for &byte in data {
+        let mut byte = byte;
+        for _ in 0..8 {
+            let mix = (crc ^ byte) & 0x01;
+            crc >>= 1;
+            if mix != 0 {
+                crc ^= 0x8C;
+            }
+            byte >>= 1;
+        }
+    }
+    crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc8_of_rom_code() {
+        let rom = [0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00];
+        assert_eq!(crc8(&rom), 0xA2);
+        assert_eq!(crc8(&[0x02, 0x1C, 0xB8, 0x01, 0x00, 0x00, 0x00, 0xA2]), 0);
+    }
+}
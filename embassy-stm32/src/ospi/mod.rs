@@ -1069,7 +1069,7 @@ impl<'d, T: Instance> Ospi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
 
         finish_dma(T::REGS);
 
@@ -1099,7 +1099,7 @@ impl<'d, T: Instance> Ospi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
 
         finish_dma(T::REGS);
 
@@ -1139,7 +1139,7 @@ impl<'d, T: Instance> Ospi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.await;
+        unwrap!(transfer.await);
 
         finish_dma(T::REGS);
 
@@ -1169,7 +1169,7 @@ impl<'d, T: Instance> Ospi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.await;
+        unwrap!(transfer.await);
 
         finish_dma(T::REGS);
 
@@ -387,13 +387,13 @@ impl<'d, T: Instance> Qspi<'d, T, Async> {
     /// Blocking read data, using DMA.
     pub fn blocking_read_dma(&mut self, buf: &mut [u8], transaction: TransferConfig) {
         let transfer = self.start_read_transfer(transaction, buf);
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
     }
 
     /// Async read data, using DMA.
     pub async fn read_dma(&mut self, buf: &mut [u8], transaction: TransferConfig) {
         let transfer = self.start_read_transfer(transaction, buf);
-        transfer.await;
+        unwrap!(transfer.await);
     }
 
     fn start_read_transfer<'a>(
@@ -427,13 +427,13 @@ impl<'d, T: Instance> Qspi<'d, T, Async> {
     /// Blocking write data, using DMA.
     pub fn blocking_write_dma(&mut self, buf: &[u8], transaction: TransferConfig) {
         let transfer = self.start_write_transfer(transaction, buf);
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
     }
 
     /// Async write data, using DMA.
     pub async fn write_dma(&mut self, buf: &[u8], transaction: TransferConfig) {
         let transfer = self.start_write_transfer(transaction, buf);
-        transfer.await;
+        unwrap!(transfer.await);
     }
 
     fn start_write_transfer<'a>(&'a mut self, transaction: TransferConfig, buf: &'a [u8]) -> crate::dma::Transfer<'a> {
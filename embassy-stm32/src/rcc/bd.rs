@@ -1,4 +1,4 @@
-use core::sync::atomic::{compiler_fence, Ordering};
+use core::sync::atomic::{compiler_fence, AtomicU32, Ordering};
 
 use crate::pac::common::{Reg, RW};
 pub use crate::pac::rcc::vals::Rtcsel as RtcClockSource;
@@ -9,6 +9,17 @@ pub const LSI_FREQ: Hertz = Hertz(40_000);
 #[cfg(not(any(stm32f0, stm32f1, stm32f3)))]
 pub const LSI_FREQ: Hertz = Hertz(32_000);
 
+/// Measured LSI frequency, or 0 if it wasn't measured.
+pub(crate) static LSI_MEASURED: AtomicU32 = AtomicU32::new(0);
+
+/// LSI frequency, as measured by `measure_ls_clock`, or [`LSI_FREQ`] if it wasn't measured.
+pub fn lsi_freq() -> Hertz {
+    match LSI_MEASURED.load(Ordering::Relaxed) {
+        0 => LSI_FREQ,
+        freq => Hertz(freq),
+    }
+}
+
 #[allow(dead_code)]
 #[derive(Clone, Copy)]
 pub enum LseMode {
@@ -1,7 +1,9 @@
 use stm32_metapac::flash::vals::Latency;
 
+use crate::pac::rcc::regs::Sscgr;
 #[cfg(any(stm32f413, stm32f423, stm32f412))]
 pub use crate::pac::rcc::vals::Plli2ssrc as Plli2sSource;
+use crate::pac::rcc::vals::Spreadsel;
 pub use crate::pac::rcc::vals::{
     Hpre as AHBPrescaler, Pllm as PllPreDiv, Plln as PllMul, Pllp as PllPDiv, Pllq as PllQDiv, Pllr as PllRDiv,
     Pllsrc as PllSource, Ppre as APBPrescaler, Sw as Sysclk,
@@ -77,6 +79,57 @@ pub enum VoltageScale {
     Range3,
 }
 
+/// Voltage scale of the main regulator.
+///
+/// Lower scales reduce the power consumption, but also the maximum clock frequency.
+#[cfg(any(stm32f4, stm32f7))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoltageScale {
+    /// Highest voltage, for the highest frequencies.
+    Scale1,
+    /// Intermediate voltage.
+    Scale2,
+    /// Lowest voltage.
+    #[cfg(not(any(stm32f405, stm32f407, stm32f415, stm32f417)))]
+    Scale3,
+}
+
+#[cfg(any(stm32f4, stm32f7))]
+impl From<VoltageScale> for crate::pac::pwr::vals::Vos {
+    fn from(scale: VoltageScale) -> Self {
+        match scale {
+            VoltageScale::Scale1 => Self::SCALE1,
+            VoltageScale::Scale2 => Self::SCALE2,
+            #[cfg(not(any(stm32f405, stm32f407, stm32f415, stm32f417)))]
+            VoltageScale::Scale3 => Self::SCALE3,
+        }
+    }
+}
+
+/// Spread spectrum modulation of the main PLL, to reduce electromagnetic interference.
+///
+/// The PLL output frequency is modulated with a triangle wave around (center spread) or below
+/// (down spread) its nominal frequency. The modulation is applied to all PLL outputs, so it should
+/// not be used when the USB, I2S or SAI clocks come from the main PLL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SpreadSpectrum {
+    /// Modulation frequency, up to 10 kHz.
+    pub modulation_freq: Hertz,
+    /// Peak modulation depth, in hundredths of a percent, up to 200 (2 %).
+    pub depth: u16,
+    /// Modulation around or below the nominal frequency.
+    pub mode: SpreadMode,
+}
+
+/// Spread spectrum modulation mode.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpreadMode {
+    /// Modulation around the nominal frequency.
+    Center,
+    /// Modulation below the nominal frequency.
+    Down,
+}
+
 /// Configuration of the core clocks
 #[non_exhaustive]
 #[derive(Clone, Copy)]
@@ -90,6 +143,8 @@ pub struct Config {
     pub external_i2s_clock: Option<Hertz>,
 
     pub pll: Option<Pll>,
+    /// Spread spectrum modulation of the main PLL.
+    pub pll_spread_spectrum: Option<SpreadSpectrum>,
     #[cfg(any(stm32f2, all(stm32f4, not(stm32f410)), stm32f7))]
     pub plli2s: Option<Pll>,
     #[cfg(any(stm32f446, stm32f427, stm32f437, stm32f4x9, stm32f7))]
@@ -106,6 +161,15 @@ pub struct Config {
 
     #[cfg(stm32f2)]
     pub voltage: VoltageScale,
+
+    /// Voltage scale of the main regulator, which limits the maximum HCLK frequency.
+    #[cfg(any(stm32f4, stm32f7))]
+    pub voltage_scale: VoltageScale,
+    /// Enable the regulator over-drive, needed for the highest HCLK frequency of each voltage scale.
+    ///
+    /// Over-drive is only available with `VoltageScale::Scale1` and `VoltageScale::Scale2`.
+    #[cfg(any(stm32f446, stm32f4x9, stm32f427, stm32f437, stm32f7))]
+    pub overdrive: bool,
 }
 
 impl Config {
@@ -118,6 +182,7 @@ impl Config {
             #[cfg(any(stm32f412, stm32f413, stm32f423))]
             external_i2s_clock: None,
             pll: None,
+            pll_spread_spectrum: None,
             #[cfg(any(stm32f2, all(stm32f4, not(stm32f410)), stm32f7))]
             plli2s: None,
             #[cfg(any(stm32f446, stm32f427, stm32f437, stm32f4x9, stm32f7))]
@@ -131,6 +196,10 @@ impl Config {
 
             #[cfg(stm32f2)]
             voltage: VoltageScale::Range3,
+            #[cfg(any(stm32f4, stm32f7))]
+            voltage_scale: VoltageScale::Scale1,
+            #[cfg(any(stm32f446, stm32f4x9, stm32f427, stm32f437, stm32f7))]
+            overdrive: true,
             mux: super::mux::ClockMux::default(),
         }
     }
@@ -143,22 +212,11 @@ impl Default for Config {
 }
 
 pub(crate) unsafe fn init(config: Config) {
-    // set VOS to SCALE1, if use PLL
-    // TODO: check real clock speed before set VOS
-    #[cfg(any(stm32f4, stm32f7))]
-    if config.pll.is_some() {
-        PWR.cr1().modify(|w| w.set_vos(crate::pac::pwr::vals::Vos::SCALE1));
-    }
-
-    // always enable overdrive for now. Make it configurable in the future.
     #[cfg(any(stm32f446, stm32f4x9, stm32f427, stm32f437, stm32f7))]
-    {
-        PWR.cr1().modify(|w| w.set_oden(true));
-        while !PWR.csr1().read().odrdy() {}
-
-        PWR.cr1().modify(|w| w.set_odswen(true));
-        while !PWR.csr1().read().odswrdy() {}
-    }
+    let overdrive = config.overdrive;
+    #[cfg(all(any(stm32f4, stm32f7), not(any(stm32f446, stm32f4x9, stm32f427, stm32f437, stm32f7))))]
+    #[allow(unused_variables)]
+    let overdrive = false;
 
     // Turn on the HSI
     RCC.cr().modify(|w| w.set_hsion(true));
@@ -168,6 +226,22 @@ pub(crate) unsafe fn init(config: Config) {
     RCC.cfgr().modify(|w| w.set_sw(Sysclk::HSI));
     while RCC.cfgr().read().sws() != Sysclk::HSI {}
 
+    // The over-drive must be disabled before changing the voltage scale, which can only be changed
+    // while the main PLL is off and takes effect once it is on.
+    #[cfg(any(stm32f446, stm32f4x9, stm32f427, stm32f437, stm32f7))]
+    PWR.cr1().modify(|w| {
+        w.set_odswen(false);
+        w.set_oden(false);
+    });
+    #[cfg(any(stm32f4, stm32f7))]
+    {
+        #[cfg(any(stm32f446, stm32f4x9, stm32f427, stm32f437, stm32f7))]
+        rcc_assert!(!overdrive || config.voltage_scale != VoltageScale::Scale3);
+
+        pll_enable(PllInstance::Pll, false);
+        PWR.cr1().modify(|w| w.set_vos(config.voltage_scale.into()));
+    }
+
     // Configure HSI
     let hsi = match config.hsi {
         false => None,
@@ -200,8 +274,24 @@ pub(crate) unsafe fn init(config: Config) {
         #[cfg(any(stm32f412, stm32f413, stm32f423))]
         external: config.external_i2s_clock,
         source: config.pll_src,
+        spread_spectrum: config.pll_spread_spectrum,
     };
     let pll = init_pll(PllInstance::Pll, config.pll, &pll_input);
+    #[cfg(any(stm32f4, stm32f7))]
+    if config.pll.is_some() {
+        while !PWR.csr1().read().vosrdy() {}
+    }
+
+    // The over-drive is enabled once the PLL is on, before switching the system clock to it.
+    #[cfg(any(stm32f446, stm32f4x9, stm32f427, stm32f437, stm32f7))]
+    if overdrive {
+        PWR.cr1().modify(|w| w.set_oden(true));
+        while !PWR.csr1().read().odrdy() {}
+
+        PWR.cr1().modify(|w| w.set_odswen(true));
+        while !PWR.csr1().read().odswrdy() {}
+    }
+
     #[cfg(any(stm32f2, all(stm32f4, not(stm32f410)), stm32f7))]
     let plli2s = init_pll(PllInstance::Plli2s, config.plli2s, &pll_input);
     #[cfg(any(stm32f446, stm32f427, stm32f437, stm32f4x9, stm32f7))]
@@ -221,6 +311,8 @@ pub(crate) unsafe fn init(config: Config) {
 
     rcc_assert!(max::SYSCLK.contains(&sys));
     rcc_assert!(max::HCLK.contains(&hclk));
+    #[cfg(any(stm32f4, stm32f7))]
+    rcc_assert!(hclk <= max::hclk(config.voltage_scale, overdrive));
     rcc_assert!(max::PCLK1.contains(&pclk1));
     rcc_assert!(max::PCLK2.contains(&pclk2));
 
@@ -333,6 +425,7 @@ struct PllInput {
     hse: Option<Hertz>,
     #[cfg(any(stm32f412, stm32f413, stm32f423))]
     external: Option<Hertz>,
+    spread_spectrum: Option<SpreadSpectrum>,
 }
 
 #[derive(Default)]
@@ -461,12 +554,42 @@ fn init_pll(instance: PllInstance, config: Option<Pll>, input: &PllInput) -> Pll
         }),
     }
 
+    if instance == PllInstance::Pll {
+        RCC.sscgr().write_value(sscgr(input.spread_spectrum, in_freq, pll.mul));
+    }
+
     // Enable PLL
     pll_enable(instance, true);
 
     PllOutput { p, q, r }
 }
 
+/// Compute the SSCGR register of the main PLL, which must be written while it is off.
+fn sscgr(spread_spectrum: Option<SpreadSpectrum>, in_freq: Hertz, mul: PllMul) -> Sscgr {
+    let mut sscgr = Sscgr(0);
+    let Some(ss) = spread_spectrum else { return sscgr };
+
+    assert!(ss.modulation_freq.0 > 0 && ss.modulation_freq.0 <= 10_000);
+    assert!(ss.depth > 0 && ss.depth <= 200);
+
+    // MODPER = round(fPLL_IN / (4 * fMod))
+    // INCSTEP = round((2^15 - 1) * md * PLLN / (100 * 5 * MODPER)), with md in percent
+    let modper = (in_freq.0 + 2 * ss.modulation_freq.0) / (4 * ss.modulation_freq.0);
+    let incstep_num = 0x7FFF * ss.depth as u64 * mul.to_bits() as u64;
+    let incstep_den = 50_000 * modper as u64;
+    let incstep = ((incstep_num + incstep_den / 2) / incstep_den) as u32;
+    assert!(modper < 1 << 13 && incstep < 1 << 15 && modper * incstep <= 0x7FFF);
+
+    sscgr.set_modper(modper as u16);
+    sscgr.set_incstep(incstep as u16);
+    sscgr.set_spreadsel(match ss.mode {
+        SpreadMode::Center => Spreadsel::CENTER,
+        SpreadMode::Down => Spreadsel::DOWN,
+    });
+    sscgr.set_sscgen(true);
+    sscgr
+}
+
 #[cfg(stm32f7)]
 mod max {
     use core::ops::RangeInclusive;
@@ -483,6 +606,18 @@ mod max {
 
     pub(crate) const PLL_IN: RangeInclusive<Hertz> = Hertz(1_000_000)..=Hertz(2_100_000);
     pub(crate) const PLL_VCO: RangeInclusive<Hertz> = Hertz(100_000_000)..=Hertz(432_000_000);
+
+    /// Maximum HCLK frequency in a voltage scale.
+    pub(crate) const fn hclk(scale: super::VoltageScale, overdrive: bool) -> Hertz {
+        use super::VoltageScale;
+        match (scale, overdrive) {
+            (VoltageScale::Scale1, true) => Hertz(216_000_000),
+            (VoltageScale::Scale1, false) => Hertz(180_000_000),
+            (VoltageScale::Scale2, true) => Hertz(180_000_000),
+            (VoltageScale::Scale2, false) => Hertz(168_000_000),
+            (VoltageScale::Scale3, _) => Hertz(144_000_000),
+        }
+    }
 }
 
 #[cfg(stm32f4)]
@@ -514,6 +649,36 @@ mod max {
 
     pub(crate) const PLL_IN: RangeInclusive<Hertz> = Hertz(1_000_000)..=Hertz(2_100_000);
     pub(crate) const PLL_VCO: RangeInclusive<Hertz> = Hertz(100_000_000)..=Hertz(432_000_000);
+
+    /// Maximum HCLK frequency in a voltage scale.
+    #[allow(unused_variables)]
+    pub(crate) const fn hclk(scale: super::VoltageScale, overdrive: bool) -> Hertz {
+        use super::VoltageScale;
+        #[cfg(any(stm32f427, stm32f429, stm32f437, stm32f439, stm32f446, stm32f469, stm32f479))]
+        return match (scale, overdrive) {
+            (VoltageScale::Scale1, true) => Hertz(180_000_000),
+            (VoltageScale::Scale1, false) => Hertz(168_000_000),
+            (VoltageScale::Scale2, true) => Hertz(168_000_000),
+            (VoltageScale::Scale2, false) => Hertz(144_000_000),
+            (VoltageScale::Scale3, _) => Hertz(120_000_000),
+        };
+        #[cfg(any(stm32f405, stm32f407, stm32f415, stm32f417))]
+        return match scale {
+            VoltageScale::Scale1 => Hertz(168_000_000),
+            VoltageScale::Scale2 => Hertz(144_000_000),
+        };
+        #[cfg(stm32f401)]
+        return match scale {
+            VoltageScale::Scale1 | VoltageScale::Scale2 => Hertz(84_000_000),
+            VoltageScale::Scale3 => Hertz(60_000_000),
+        };
+        #[cfg(any(stm32f410, stm32f411, stm32f412, stm32f413, stm32f423))]
+        return match scale {
+            VoltageScale::Scale1 => Hertz(100_000_000),
+            VoltageScale::Scale2 => Hertz(84_000_000),
+            VoltageScale::Scale3 => Hertz(64_000_000),
+        };
+    }
 }
 
 #[cfg(stm32f2)]
@@ -0,0 +1,154 @@
+//! Measurement of the low-speed oscillators.
+//!
+//! The LSI oscillator is only accurate to a few percent, which makes the watchdog timeouts and the
+//! RTC drift by as much. [`measure_ls_clock`] routes the LSI or LSE to an input capture of a timer,
+//! and measures its frequency against the timer clock. The measured frequency replaces the nominal
+//! one in the IWDG timeout and RTC prescaler calculations.
+//!
+//! The measurement is only as accurate as the timer clock, so the system clock should come from
+//! the HSE, or from a PLL driven by it.
+
+use super::{get_freqs, set_freqs, RtcClockSource, LSI_MEASURED};
+use crate::pac::timer::TimGp16;
+use crate::pac::RCC;
+use crate::peripherals;
+use crate::time::Hertz;
+use crate::timer::low_level::{InputTISelection, Timer};
+use crate::Peri;
+
+/// Timer whose input capture can be connected to the low-speed oscillators.
+#[cfg(stm32f4)]
+pub type LsMeasureTimer = peripherals::TIM5;
+/// Timer whose input capture can be connected to the low-speed oscillators.
+#[cfg(stm32l4)]
+pub type LsMeasureTimer = peripherals::TIM16;
+
+// Input capture channel, connected through the TI4_RMP field of TIM5_OR on F4, and the TI1_RMP
+// field of TIM16_OR1 on L4.
+#[cfg(stm32f4)]
+const CHANNEL: usize = 3;
+#[cfg(stm32l4)]
+const CHANNEL: usize = 0;
+#[cfg(stm32f4)]
+const RMP_SHIFT: u32 = 6;
+#[cfg(stm32l4)]
+const RMP_SHIFT: u32 = 0;
+const OR_OFFSET: usize = 0x50;
+
+// The counter wraps at 32 bits on TIM5, and 16 bits on TIM16.
+#[cfg(stm32f4)]
+const MAX_COUNTER: u32 = u32::MAX;
+#[cfg(stm32l4)]
+const MAX_COUNTER: u32 = u16::MAX as u32;
+
+#[cfg(stm32f4)]
+fn regs() -> TimGp16 {
+    // Safety: the 16-bit registers are a subset of the 32-bit ones.
+    unsafe { TimGp16::from_ptr(crate::pac::TIM5.as_ptr()) }
+}
+
+#[cfg(stm32l4)]
+fn regs() -> TimGp16 {
+    // Safety: the registers of TIM16 are at the same offsets as the general purpose ones.
+    unsafe { TimGp16::from_ptr(crate::pac::TIM16.as_ptr()) }
+}
+
+#[cfg(stm32f4)]
+fn read_capture() -> u32 {
+    crate::pac::TIM5.ccr(CHANNEL).read()
+}
+
+#[cfg(stm32l4)]
+fn read_capture() -> u32 {
+    regs().ccr(CHANNEL).read().ccr() as u32
+}
+
+/// Number of oscillator periods per capture.
+const CAPTURE_PRESCALER: u32 = 8;
+/// Number of captures averaged by a measurement.
+const CAPTURES: u32 = 32;
+
+/// Low-speed oscillator.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LsClock {
+    /// Low-speed internal RC oscillator.
+    Lsi,
+    /// Low-speed external oscillator.
+    Lse,
+}
+
+/// Measure the frequency of the `clock` oscillator, which must be enabled.
+///
+/// The oscillator is averaged over 256 periods, so this blocks for about 8 ms. The measured
+/// frequency is used from then on by [`IndependentWatchdog::new`](crate::wdg::IndependentWatchdog::new)
+/// for the LSI, and by [`Rtc::new`](crate::rtc::Rtc::new) if the RTC is clocked by `clock`.
+pub fn measure_ls_clock<'a>(
+    _rcc: &'a mut Peri<'a, peripherals::RCC>,
+    tim: Peri<'_, LsMeasureTimer>,
+    clock: LsClock,
+) -> Hertz {
+    let freq = measure(&Timer::new(tim), clock);
+    trace!("rcc: measured {:?} at {} Hz", clock, freq.0);
+
+    let rtcsel = match clock {
+        LsClock::Lsi => RtcClockSource::LSI,
+        LsClock::Lse => RtcClockSource::LSE,
+    };
+    critical_section::with(|_| unsafe {
+        if clock == LsClock::Lsi {
+            LSI_MEASURED.store(freq.0, core::sync::atomic::Ordering::Relaxed);
+        }
+        if RCC.bdcr().read().rtcen() && RCC.bdcr().read().rtcsel() == rtcsel {
+            let mut freqs = *get_freqs();
+            freqs.rtc = Some(freq).into();
+            set_freqs(freqs);
+        }
+    });
+
+    freq
+}
+
+/// Measure the frequency of `clock` against the nominal frequency of the timer clock.
+pub(crate) fn measure(timer: &Timer<'_, LsMeasureTimer>, clock: LsClock) -> Hertz {
+    let (ready, rmp) = match clock {
+        LsClock::Lsi => (RCC.csr().read().lsirdy(), 0b01),
+        LsClock::Lse => (RCC.bdcr().read().lserdy(), 0b10),
+    };
+    assert!(ready, "the measured oscillator must be enabled");
+
+    let timer_freq = timer.get_clock_frequency();
+    timer.set_max_compare_value(MAX_COUNTER);
+
+    let r = regs();
+    // Connect the oscillator to the input capture, through the option register.
+    unsafe {
+        let or = (r.as_ptr() as *mut u8).add(OR_OFFSET) as *mut u32;
+        or.write_volatile((or.read_volatile() & !(0b11 << RMP_SHIFT)) | (rmp << RMP_SHIFT));
+    }
+    r.ccmr_input(CHANNEL / 2).modify(|w| {
+        w.set_ccs(CHANNEL % 2, InputTISelection::Normal.into());
+        // Capture every 8 edges.
+        w.set_icpsc(CHANNEL % 2, 0b11);
+    });
+    r.ccer().modify(|w| w.set_cce(CHANNEL, true));
+    r.cr1().modify(|w| w.set_cen(true));
+
+    let wait_capture = || {
+        while !r.sr().read().ccif(CHANNEL) {}
+        // Reading the capture clears the flag.
+        read_capture()
+    };
+
+    let mut last = wait_capture();
+    let mut ticks = 0u64;
+    for _ in 0..CAPTURES {
+        let capture = wait_capture();
+        ticks += (capture.wrapping_sub(last) & MAX_COUNTER) as u64;
+        last = capture;
+    }
+
+    r.cr1().modify(|w| w.set_cen(false));
+
+    Hertz((timer_freq.0 as u64 * (CAPTURE_PRESCALER * CAPTURES) as u64 / ticks) as u32)
+}
@@ -19,6 +19,16 @@ mod hsi48;
 #[cfg(crs)]
 pub use hsi48::*;
 
+#[cfg(any(stm32f4, stm32l4))]
+mod ls_measure;
+#[cfg(any(stm32f4, stm32l4))]
+pub use ls_measure::*;
+
+#[cfg(any(stm32f2, stm32f4, stm32f7, stm32l4))]
+mod trim;
+#[cfg(any(stm32f2, stm32f4, stm32f7, stm32l4))]
+pub use trim::*;
+
 #[cfg_attr(any(stm32f0, stm32f1, stm32f3), path = "f013.rs")]
 #[cfg_attr(any(stm32f2, stm32f4, stm32f7), path = "f247.rs")]
 #[cfg_attr(stm32c0, path = "c0.rs")]
@@ -104,6 +114,51 @@ pub fn clocks<'a>(_rcc: &'a crate::Peri<'a, crate::peripherals::RCC>) -> &'a Clo
     unsafe { get_freqs() }
 }
 
+/// Iterate over the name and kernel clock frequency of the peripherals whose clock is enabled.
+///
+/// This includes the peripherals used by a driver, and the ones enabled by [`enable_and_reset`] or
+/// [`keep_enabled`]. The frequency is `None` if the clock mux of the peripheral selects a clock
+/// which is not running.
+pub fn enabled_peripheral_clocks<'a>(
+    _rcc: &'a crate::Peri<'a, crate::peripherals::RCC>,
+) -> impl Iterator<Item = (&'static str, Option<Hertz>)> + 'a {
+    crate::_generated::PERIPHERAL_CLOCKS
+        .iter()
+        .filter(|p| p.rcc_info.is_enabled())
+        .map(|p| (p.name, (p.frequency)()))
+}
+
+/// Get the kernel clock frequency of the peripheral `T`.
+///
+/// Panics if the clock mux of the peripheral selects a clock which is not running.
+pub fn frequency<T: RccPeripheral>() -> Hertz {
+    T::frequency()
+}
+
+/// Log the frequency of every clock, and the kernel clock of every enabled peripheral.
+///
+/// Clocks which are not running are logged as "off".
+///
+/// This helps diagnosing a peripheral running at the wrong speed, like a wrong UART baudrate or
+/// I2C timing.
+pub fn dump_clocks<'a>(rcc: &'a crate::Peri<'a, crate::peripherals::RCC>) {
+    info!("clocks:");
+    for (name, freq) in clocks(rcc).iter() {
+        log_clock(name, freq);
+    }
+    info!("peripheral kernel clocks:");
+    for (name, freq) in enabled_peripheral_clocks(rcc) {
+        log_clock(name, freq);
+    }
+}
+
+fn log_clock(name: &str, freq: Option<Hertz>) {
+    match freq {
+        Some(freq) => info!("  {}: {} Hz", name, freq.0),
+        None => info!("  {}: off", name),
+    }
+}
+
 pub(crate) trait SealedRccPeripheral {
     fn frequency() -> Hertz;
     #[allow(dead_code)]
@@ -125,10 +180,12 @@ pub(crate) struct RccInfo {
     enable_offset: u8,
     /// Position of the xxxEN bit within the xxxENR register (0..=31).
     enable_bit: u8,
-    /// If this peripheral shares the same xxxRSTR bit and xxxEN bit with other peripherals, we
-    /// maintain a refcount in `crate::_generated::REFCOUNTS` at this index. If the bit is not
-    /// shared, this is 0xff (we don't use an `Option` to save one byte of storage).
+    /// Index of the refcount of the xxxEN bit in `crate::_generated::REFCOUNTS`, shared by the
+    /// peripherals with the same xxxEN bit, or 0xff if the bit is not refcounted (we don't use an
+    /// `Option` to save one byte of storage).
     refcount_idx_or_0xff: u8,
+    /// Whether the xxxEN bit is shared with other peripherals.
+    enable_shared: bool,
     /// Stop mode of the peripheral, used to maintain `REFCOUNT_STOP1` and `REFCOUNT_STOP2`.
     #[cfg(feature = "low-power")]
     stop_mode: StopMode,
@@ -147,11 +204,13 @@ impl RccInfo {
     /// - `reset_offset_and_bit`, if set, must correspond to valid xxxRST bit
     /// - `enable_offset_and_bit` must correspond to valid xxxEN bit
     /// - `refcount_idx`, if set, must correspond to valid refcount in `_generated::REFCOUNTS`
+    /// - `enable_shared` must be set if other peripherals use the same xxxEN bit
     /// - `stop_mode` must be valid
     pub(crate) const unsafe fn new(
         reset_offset_and_bit: Option<(u8, u8)>,
         enable_offset_and_bit: (u8, u8),
         refcount_idx: Option<u8>,
+        enable_shared: bool,
         #[cfg(feature = "low-power")] stop_mode: StopMode,
     ) -> Self {
         let (reset_offset_or_0xff, reset_bit) = match reset_offset_and_bit {
@@ -169,13 +228,23 @@ impl RccInfo {
             enable_offset,
             enable_bit,
             refcount_idx_or_0xff,
+            enable_shared,
             #[cfg(feature = "low-power")]
             stop_mode,
         }
     }
 
     // TODO: should this be `unsafe`?
-    pub(crate) fn enable_and_reset_with_cs(&self, _cs: CriticalSection) {
+    pub(crate) fn enable_and_reset_with_cs(&self, cs: CriticalSection) {
+        self.enable_with_cs(true, cs)
+    }
+
+    /// Take a reference on the peripheral clock, enabling it without reset if it was disabled.
+    pub(crate) fn keep_enabled_with_cs(&self, cs: CriticalSection) {
+        self.enable_with_cs(false, cs)
+    }
+
+    fn enable_with_cs(&self, reset: bool, cs: CriticalSection) {
         if self.refcount_idx_or_0xff != 0xff {
             let refcount_idx = self.refcount_idx_or_0xff as usize;
 
@@ -185,8 +254,15 @@ impl RccInfo {
             if let Some(refcount) =
                 unsafe { (*core::ptr::addr_of_mut!(crate::_generated::REFCOUNTS)).get_mut(refcount_idx) }
             {
-                *refcount += 1;
+                // Drivers without `Drop` never release their reference, so saturate instead of
+                // overflowing.
+                *refcount = refcount.saturating_add(1);
                 if *refcount > 1 {
+                    // Still reset a peripheral with its own xxxEN bit, as it is re-created by a
+                    // driver. A shared bit can't be reset without breaking the other users.
+                    if reset && !self.enable_shared {
+                        self.reset_with_cs(cs);
+                    }
                     return;
                 }
             } else {
@@ -206,7 +282,7 @@ impl RccInfo {
         }
 
         // set the xxxRST bit
-        let reset_ptr = self.reset_ptr();
+        let reset_ptr = self.reset_ptr().filter(|_| reset);
         if let Some(reset_ptr) = reset_ptr {
             unsafe {
                 let val = reset_ptr.read_volatile();
@@ -251,8 +327,12 @@ impl RccInfo {
             if let Some(refcount) =
                 unsafe { (*core::ptr::addr_of_mut!(crate::_generated::REFCOUNTS)).get_mut(refcount_idx) }
             {
-                *refcount -= 1;
-                if *refcount > 0 {
+                debug_assert!(*refcount > 0, "peripheral clock disabled more times than enabled");
+                let Some(count) = refcount.checked_sub(1) else {
+                    return;
+                };
+                *refcount = count;
+                if count > 0 {
                     return;
                 }
             } else {
@@ -279,6 +359,41 @@ impl RccInfo {
         }
     }
 
+    /// Release (`allow = true`) or take back the stop mode requirement of an enabled peripheral.
+    ///
+    /// This is for peripherals that don't need their clock for a while and can wake up the core
+    /// by themselves, like a suspended USB peripheral.
+    #[cfg(feature = "low-power")]
+    pub(crate) fn allow_stop_with_cs(&self, allow: bool, _cs: CriticalSection) {
+        match (&self.stop_mode, allow) {
+            (StopMode::Standby, _) => {}
+            (StopMode::Stop2, true) => unsafe {
+                REFCOUNT_STOP2 -= 1;
+            },
+            (StopMode::Stop2, false) => unsafe {
+                REFCOUNT_STOP2 += 1;
+            },
+            (StopMode::Stop1, true) => unsafe {
+                REFCOUNT_STOP1 -= 1;
+            },
+            (StopMode::Stop1, false) => unsafe {
+                REFCOUNT_STOP1 += 1;
+            },
+        }
+    }
+
+    /// Pulse the xxxRST bit of an enabled peripheral.
+    pub(crate) fn reset_with_cs(&self, _cs: CriticalSection) {
+        if let Some(reset_ptr) = self.reset_ptr() {
+            unsafe {
+                let val = reset_ptr.read_volatile();
+                reset_ptr.write_volatile(val | 1u32 << self.reset_bit);
+                cortex_m::asm::dsb();
+                reset_ptr.write_volatile(val & !(1u32 << self.reset_bit));
+            }
+        }
+    }
+
     // TODO: should this be `unsafe`?
     pub(crate) fn enable_and_reset(&self) {
         critical_section::with(|cs| self.enable_and_reset_with_cs(cs))
@@ -300,6 +415,18 @@ impl RccInfo {
     fn enable_ptr(&self) -> *mut u32 {
         unsafe { (RCC.as_ptr() as *mut u32).add(self.enable_offset as _) }
     }
+
+    /// Whether the xxxEN bit is set.
+    fn is_enabled(&self) -> bool {
+        unsafe { self.enable_ptr().read_volatile() & (1u32 << self.enable_bit) != 0 }
+    }
+}
+
+/// Kernel clock of a peripheral, in the generated `PERIPHERAL_CLOCKS` table.
+pub(crate) struct PeripheralClock {
+    pub(crate) name: &'static str,
+    pub(crate) rcc_info: &'static RccInfo,
+    pub(crate) frequency: fn() -> Option<Hertz>,
 }
 
 #[allow(unused)]
@@ -381,17 +508,54 @@ pub fn disable<T: RccPeripheral>() {
     T::RCC_INFO.disable();
 }
 
+/// Resets peripheral `T`, without changing its clock.
+///
+/// All the registers of the peripheral go back to their reset values, so drivers using it must
+/// configure it again.
+pub fn reset<T: RccPeripheral>() {
+    critical_section::with(|cs| T::RCC_INFO.reset_with_cs(cs))
+}
+
+/// Keeps the clock of peripheral `T` enabled, even after the drivers using it are dropped.
+///
+/// Peripheral clocks are refcounted: drivers enable the clock when created and disable it when
+/// dropped, and the clock is only gated once no driver uses it anymore. This takes an additional
+/// reference, which is released with [`disable`]. The peripheral is not reset if its clock was
+/// already enabled.
+pub fn keep_enabled<T: RccPeripheral>() {
+    critical_section::with(|cs| T::RCC_INFO.keep_enabled_with_cs(cs))
+}
+
 /// Re-initialize the `embassy-stm32` clock configuration with the provided configuration.
 ///
-/// This is useful when you need to alter the CPU clock after configuring peripherals.
-/// For instance, configure an external clock via spi or i2c.
+/// This is useful when you need to alter the CPU clock after configuring peripherals, for
+/// instance to configure an external clock via spi or i2c, or to lower the clocks while idle to
+/// save power and raise them again later.
+///
+/// The time driver keeps its time, only lagging by the duration of the reconfiguration, and
+/// [`clocks`] returns the new frequencies. Drivers are not notified, and keep the dividers
+/// computed from the previous frequencies. After the clocks change:
+///
+/// - Call `SetConfig::set_config` again on the UART, SPI, I2C, OSPI, HSPI and XSPI drivers.
+/// - Call `set_frequency` again on the timer drivers, like `SimplePwm`, and `set_bitrate` on the
+///   CAN drivers.
+/// - Re-create the other drivers whose timing depends on their clock, like ADC, I2S, SAI, SDMMC
+///   and QSPI.
 ///
-/// Please not this only re-configures the rcc and the time driver (not GPIO, EXTI, etc).
+/// Drivers running from a clock that doesn't change, like the RTC or the watchdog on LSI, are not
+/// affected. Peripherals should not be transferring data while the clocks change.
 ///
 /// This should only be called after `init`.
 #[cfg(not(feature = "_dual-core"))]
 pub fn reinit<'a>(config: Config, _rcc: &'a mut crate::Peri<'a, crate::peripherals::RCC>) {
-    critical_section::with(|cs| init_rcc(cs, config))
+    critical_section::with(|_cs| {
+        #[cfg(feature = "_time-driver")]
+        crate::time_driver::reinit(_cs, || unsafe { init(config) });
+        #[cfg(not(feature = "_time-driver"))]
+        unsafe {
+            init(config)
+        };
+    })
 }
 
 pub(crate) fn init_rcc(_cs: CriticalSection, config: Config) {
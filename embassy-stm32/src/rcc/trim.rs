@@ -0,0 +1,103 @@
+//! Runtime trimming of the internal RC oscillators.
+//!
+//! The HSI and MSI are factory calibrated at 30 °C, and drift by up to a few percent over
+//! temperature. Their trim value can be adjusted at runtime to hold the tolerance needed by UART or
+//! crystal-less USB, against the LSE with [`trim_hsi_to_lse`], or against any external reference
+//! with [`trim_hsi_with`].
+
+#[cfg(any(stm32f4, stm32l4))]
+use super::ls_measure::{measure, LsClock, LsMeasureTimer};
+use crate::pac::RCC;
+#[cfg(any(stm32f4, stm32l4))]
+use crate::timer::low_level::Timer;
+#[cfg(any(stm32f4, stm32l4))]
+use crate::Peri;
+
+/// Largest HSI trim value. Each step changes the HSI frequency by about 0.3 % to 0.5 %.
+#[cfg(not(any(
+    stm32l4p5, stm32l4q5, stm32l4r5, stm32l4r7, stm32l4r9, stm32l4s5, stm32l4s7, stm32l4s9
+)))]
+pub const HSI_TRIM_MAX: u8 = 0x1F;
+/// Largest HSI trim value. Each step changes the HSI frequency by about 0.3 % to 0.5 %.
+#[cfg(any(
+    stm32l4p5, stm32l4q5, stm32l4r5, stm32l4r7, stm32l4r9, stm32l4s5, stm32l4s7, stm32l4s9
+))]
+pub const HSI_TRIM_MAX: u8 = 0x7F;
+
+/// Return the HSI trim value.
+pub fn hsi_trim() -> u8 {
+    #[cfg(any(stm32f2, stm32f4, stm32f7))]
+    let trim = RCC.cr().read().hsitrim();
+    #[cfg(stm32l4)]
+    let trim = RCC.icscr().read().hsitrim();
+    trim
+}
+
+/// Set the HSI trim value, up to [`HSI_TRIM_MAX`]. Higher values increase the HSI frequency.
+///
+/// The clock frequencies reported by [`clocks`](super::clocks) are not changed.
+pub fn set_hsi_trim(trim: u8) {
+    assert!(trim <= HSI_TRIM_MAX);
+    #[cfg(any(stm32f2, stm32f4, stm32f7))]
+    RCC.cr().modify(|w| w.set_hsitrim(trim));
+    #[cfg(stm32l4)]
+    RCC.icscr().modify(|w| w.set_hsitrim(trim));
+}
+
+/// Return the MSI trim value.
+#[cfg(stm32l4)]
+pub fn msi_trim() -> u8 {
+    RCC.icscr().read().msitrim()
+}
+
+/// Set the MSI trim value, which is added to the factory calibration.
+///
+/// With a 32.768 kHz LSE, the MSI is instead automatically calibrated by its PLL mode.
+#[cfg(stm32l4)]
+pub fn set_msi_trim(trim: u8) {
+    RCC.icscr().modify(|w| w.set_msitrim(trim));
+}
+
+/// Trim the HSI against an external reference, returning the selected trim value.
+///
+/// `error` is called after each trim change, and returns the error of the HSI frequency against
+/// the reference, in any unit: positive if the HSI is too fast, and negative if it is too slow. The
+/// trim value with the smallest error is found by a binary search, and kept.
+pub fn trim_hsi_with(mut error: impl FnMut() -> i32) -> u8 {
+    let mut error_at = |trim| {
+        set_hsi_trim(trim);
+        error()
+    };
+
+    // The HSI frequency increases with the trim value: find the first value where it is not too
+    // slow, then check whether the value below it is closer.
+    let (mut low, mut high) = (0, HSI_TRIM_MAX);
+    while low < high {
+        let mid = (low + high) / 2;
+        if error_at(mid) < 0 {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    let mut best = low;
+    if low > 0 && error_at(low - 1).abs() < error_at(low).abs() {
+        best = low - 1;
+    }
+    set_hsi_trim(best);
+    best
+}
+
+/// Trim the HSI against a 32.768 kHz LSE, returning the selected trim value.
+///
+/// The LSE is measured with an input capture of `tim`, so the timer clock must come from the HSI,
+/// directly or through the PLL. The LSE must be enabled. This blocks for about 50 ms.
+#[cfg(any(stm32f4, stm32l4))]
+pub fn trim_hsi_to_lse<'a>(_rcc: &'a mut Peri<'a, crate::peripherals::RCC>, tim: Peri<'_, LsMeasureTimer>) -> u8 {
+    let timer = Timer::new(tim);
+    // A fast HSI makes the LSE look slow.
+    let trim = trim_hsi_with(|| 32_768 - measure(&timer, LsClock::Lse).0 as i32);
+    trace!("rcc: HSI trimmed to {}", trim);
+    trim
+}
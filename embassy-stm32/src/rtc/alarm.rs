@@ -0,0 +1,262 @@
+//! RTC alarms.
+//!
+//! The RTC has two alarms, A and B, which trigger when the calendar matches a [`DateTimeMatch`].
+//! Fields left to `None` are masked, so an alarm can repeat every minute, hour, day, week or month.
+//! The alarm interrupt is routed through EXTI, so it also wakes the chip up from Stop mode. The
+//! `RTC_ALARM` interrupt must be bound to [`AlarmInterruptHandler`].
+
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_sync::waitqueue::AtomicWaker;
+
+use super::{day_of_week_to_u8, DateTimeError, DayOfWeek, Rtc, RtcError, SealedInstance};
+use crate::interrupt::typelevel::Interrupt;
+use crate::pac::rtc::regs::Alrmr;
+use crate::peripherals::RTC;
+use crate::{interrupt, pac};
+
+#[cfg(any(stm32f4, stm32wb, stm32g4))]
+const EXTI_ALARM_LINE: usize = 17;
+#[cfg(stm32l4)]
+const EXTI_ALARM_LINE: usize = 18;
+
+const ALRMR_MSK1: u32 = 1 << 7;
+const ALRMR_MSK2: u32 = 1 << 15;
+const ALRMR_MSK3: u32 = 1 << 23;
+const ALRMR_WDSEL: u32 = 1 << 30;
+const ALRMR_MSK4: u32 = 1 << 31;
+
+const CR_ALRE: u32 = 1 << 8;
+const CR_ALRIE: u32 = 1 << 12;
+
+static WAKERS: [AtomicWaker; 2] = [const { AtomicWaker::new() }; 2];
+static FIRED: [AtomicBool; 2] = [const { AtomicBool::new(false) }; 2];
+
+/// RTC alarm interrupt handler.
+pub struct AlarmInterruptHandler;
+
+impl interrupt::typelevel::Handler<interrupt::typelevel::RTC_ALARM> for AlarmInterruptHandler {
+    unsafe fn on_interrupt() {
+        on_interrupt()
+    }
+}
+
+fn on_interrupt() {
+    let r = RTC::regs();
+    for alarm in [Alarm::A, Alarm::B] {
+        let n = alarm as usize;
+        if alarm_flag(r, n) {
+            clear_alarm_flag(r, n);
+            FIRED[n].store(true, Ordering::Release);
+            WAKERS[n].wake();
+        }
+    }
+
+    #[cfg(any(exti_v1, stm32wb))]
+    pac::EXTI.pr(0).write(|w| w.set_line(EXTI_ALARM_LINE, true));
+}
+
+#[cfg(any(rtc_v2f4, rtc_v2l4, rtc_v2wb))]
+fn alarm_flag(r: pac::rtc::Rtc, n: usize) -> bool {
+    r.isr().read().0 & (1 << (8 + n)) != 0
+}
+
+#[cfg(rtc_v3)]
+fn alarm_flag(r: pac::rtc::Rtc, n: usize) -> bool {
+    r.sr().read().0 & (1 << n) != 0
+}
+
+#[cfg(any(rtc_v2f4, rtc_v2l4, rtc_v2wb))]
+fn clear_alarm_flag(r: pac::rtc::Rtc, n: usize) {
+    // The flags are cleared by writing 0, writing back the other flags leaves them untouched.
+    r.isr().modify(|w| w.0 &= !(1 << (8 + n)));
+}
+
+#[cfg(rtc_v3)]
+fn clear_alarm_flag(r: pac::rtc::Rtc, n: usize) {
+    r.scr().write(|w| w.0 = 1 << n);
+}
+
+/// RTC alarm.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Alarm {
+    /// Alarm A.
+    A = 0,
+    /// Alarm B.
+    B = 1,
+}
+
+/// Day an alarm matches on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DayMatch {
+    /// Day of the month, `1..=31`.
+    Date(u8),
+    /// Day of the week.
+    Weekday(DayOfWeek),
+}
+
+/// Calendar fields an alarm matches on.
+///
+/// `None` fields are ignored, so they match any value.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct DateTimeMatch {
+    /// Second, `0..=59`.
+    pub second: Option<u8>,
+    /// Minute, `0..=59`.
+    pub minute: Option<u8>,
+    /// Hour, `0..=23`.
+    pub hour: Option<u8>,
+    /// Day.
+    pub day: Option<DayMatch>,
+}
+
+impl DateTimeMatch {
+    /// Match every minute, at `second`.
+    pub const fn every_minute(second: u8) -> Self {
+        Self {
+            second: Some(second),
+            minute: None,
+            hour: None,
+            day: None,
+        }
+    }
+
+    /// Match every hour, at `minute:second`.
+    pub const fn every_hour(minute: u8, second: u8) -> Self {
+        Self {
+            second: Some(second),
+            minute: Some(minute),
+            hour: None,
+            day: None,
+        }
+    }
+
+    /// Match every day, at `hour:minute:second`.
+    pub const fn every_day(hour: u8, minute: u8, second: u8) -> Self {
+        Self {
+            second: Some(second),
+            minute: Some(minute),
+            hour: Some(hour),
+            day: None,
+        }
+    }
+
+    /// Match every week, on `weekday` at `hour:minute:second`.
+    pub const fn every_week(weekday: DayOfWeek, hour: u8, minute: u8, second: u8) -> Self {
+        Self {
+            second: Some(second),
+            minute: Some(minute),
+            hour: Some(hour),
+            day: Some(DayMatch::Weekday(weekday)),
+        }
+    }
+
+    /// Match every month, on day `date` at `hour:minute:second`.
+    pub const fn every_month(date: u8, hour: u8, minute: u8, second: u8) -> Self {
+        Self {
+            second: Some(second),
+            minute: Some(minute),
+            hour: Some(hour),
+            day: Some(DayMatch::Date(date)),
+        }
+    }
+
+    fn to_alrmr(self) -> Result<Alrmr, DateTimeError> {
+        fn bcd(value: u8) -> u32 {
+            (((value / 10) << 4) | (value % 10)) as u32
+        }
+
+        let mut bits = 0;
+        match self.second {
+            Some(s) if s > 59 => return Err(DateTimeError::InvalidSecond),
+            Some(s) => bits |= bcd(s),
+            None => bits |= ALRMR_MSK1,
+        }
+        match self.minute {
+            Some(m) if m > 59 => return Err(DateTimeError::InvalidMinute),
+            Some(m) => bits |= bcd(m) << 8,
+            None => bits |= ALRMR_MSK2,
+        }
+        match self.hour {
+            Some(h) if h > 23 => return Err(DateTimeError::InvalidHour),
+            Some(h) => bits |= bcd(h) << 16,
+            None => bits |= ALRMR_MSK3,
+        }
+        match self.day {
+            Some(DayMatch::Date(d)) if !(1..=31).contains(&d) => return Err(DateTimeError::InvalidDay),
+            Some(DayMatch::Date(d)) => bits |= bcd(d) << 24,
+            Some(DayMatch::Weekday(w)) => bits |= ALRMR_WDSEL | (day_of_week_to_u8(w) as u32) << 24,
+            None => bits |= ALRMR_MSK4,
+        }
+        Ok(Alrmr(bits))
+    }
+}
+
+impl Rtc {
+    /// Wait until the calendar matches `m`, using `alarm`.
+    ///
+    /// The alarm is disabled when the future completes or is dropped, so call this again to wait
+    /// for the next match of a repeating alarm.
+    ///
+    /// # Errors
+    ///
+    /// Will return `RtcError::InvalidDateTime` if a field of `m` is out of range.
+    pub async fn wait_for_alarm(
+        &mut self,
+        _irq: impl interrupt::typelevel::Binding<interrupt::typelevel::RTC_ALARM, AlarmInterruptHandler>,
+        alarm: Alarm,
+        m: DateTimeMatch,
+    ) -> Result<(), RtcError> {
+        let alrmr = m.to_alrmr().map_err(RtcError::InvalidDateTime)?;
+        let n = alarm as usize;
+
+        self.write(false, |r| {
+            r.cr().modify(|w| w.0 &= !((CR_ALRE | CR_ALRIE) << n));
+            #[cfg(any(rtc_v2f4, rtc_v2l4, rtc_v2wb))]
+            while r.isr().read().0 & (1 << n) == 0 {}
+
+            r.alrmr(n).write_value(alrmr);
+            clear_alarm_flag(r, n);
+            FIRED[n].store(false, Ordering::Relaxed);
+
+            r.cr().modify(|w| w.0 |= (CR_ALRE | CR_ALRIE) << n);
+        });
+
+        enable_alarm_line();
+
+        let _on_drop = OnDrop::new(|| {
+            self.write(false, |r| r.cr().modify(|w| w.0 &= !((CR_ALRE | CR_ALRIE) << n)));
+        });
+
+        poll_fn(|cx| {
+            WAKERS[n].register(cx.waker());
+            if FIRED[n].swap(false, Ordering::Acquire) {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        Ok(())
+    }
+}
+
+fn enable_alarm_line() {
+    use crate::pac::EXTI;
+
+    EXTI.rtsr(0).modify(|w| w.set_line(EXTI_ALARM_LINE, true));
+    #[cfg(not(stm32wb))]
+    EXTI.imr(0).modify(|w| w.set_line(EXTI_ALARM_LINE, true));
+    #[cfg(stm32wb)]
+    EXTI.cpu(0).imr(0).modify(|w| w.set_line(EXTI_ALARM_LINE, true));
+
+    interrupt::typelevel::RTC_ALARM::unpend();
+    unsafe { interrupt::typelevel::RTC_ALARM::enable() };
+}
@@ -0,0 +1,214 @@
+//! Typed access to the RTC backup registers.
+//!
+//! The backup registers retain their values during wakes from standby mode and system resets, and
+//! when Vdd is switched off as long as V_BAT is powered. [`BackupRegister`] stores a single value
+//! in one register, and [`BackupFrame`] stores a checksummed byte buffer over several registers,
+//! for example for boot flags or crash breadcrumbs.
+
+use core::marker::PhantomData;
+
+use super::Rtc;
+
+/// Value stored in a backup register.
+pub trait BackupValue: Sized {
+    /// Convert the value to the register content.
+    fn to_bits(self) -> u32;
+    /// Convert the register content to a value.
+    fn from_bits(bits: u32) -> Self;
+}
+
+macro_rules! impl_backup_value {
+    ($($t:ty),*) => {
+        $(
+            impl BackupValue for $t {
+                fn to_bits(self) -> u32 {
+                    self as u32
+                }
+
+                fn from_bits(bits: u32) -> Self {
+                    bits as $t
+                }
+            }
+        )*
+    };
+}
+
+impl_backup_value!(u8, u16, u32, i8, i16, i32);
+
+impl BackupValue for bool {
+    fn to_bits(self) -> u32 {
+        self as u32
+    }
+
+    fn from_bits(bits: u32) -> Self {
+        bits != 0
+    }
+}
+
+impl BackupValue for f32 {
+    fn to_bits(self) -> u32 {
+        f32::to_bits(self)
+    }
+
+    fn from_bits(bits: u32) -> Self {
+        f32::from_bits(bits)
+    }
+}
+
+/// A value of type `T` stored in a backup register.
+///
+/// Declare the registers used by the application as constants, so they don't overlap:
+///
+/// ```rust,ignore
+/// const BOOT_COUNT: BackupRegister<u32> = BackupRegister::new(0);
+///
+/// let count = BOOT_COUNT.read(&rtc).unwrap_or(0);
+/// BOOT_COUNT.write(&rtc, count + 1);
+/// ```
+pub struct BackupRegister<T> {
+    index: usize,
+    _phantom: PhantomData<T>,
+}
+
+impl<T: BackupValue> BackupRegister<T> {
+    /// Create a typed backup register at `index`.
+    ///
+    /// Panics if `index` is not lower than [`Rtc::BACKUP_REGISTER_COUNT`].
+    pub const fn new(index: usize) -> Self {
+        assert!(index < Rtc::BACKUP_REGISTER_COUNT, "backup register out of range");
+        Self {
+            index,
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Read the value.
+    ///
+    /// Returns `None` if the backup registers can't be read on this chip.
+    pub fn read(&self, rtc: &Rtc) -> Option<T> {
+        rtc.read_backup_register(self.index).map(T::from_bits)
+    }
+
+    /// Write the value.
+    pub fn write(&self, rtc: &Rtc, value: T) {
+        rtc.write_backup_register(self.index, value.to_bits())
+    }
+}
+
+/// Errors of [`BackupFrame`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BackupFrameError {
+    /// No frame was written, or it was cleared.
+    Empty,
+    /// The frame header or checksum is invalid, for example after a backup domain reset or a
+    /// reset while writing.
+    Corrupted,
+    /// The data doesn't fit in the frame or in the read buffer.
+    TooLarge,
+}
+
+/// A byte buffer stored over consecutive backup registers.
+///
+/// The first register holds a header with the length and a checksum of the data, so a frame
+/// which was never written or was only partially written is detected when reading it.
+pub struct BackupFrame {
+    first: usize,
+    count: usize,
+}
+
+const FRAME_MAGIC: u32 = 0xB5;
+
+impl BackupFrame {
+    /// Create a frame over the `count` registers starting at `first`.
+    ///
+    /// Panics if the registers are out of range, or if `count` is lower than 2.
+    pub const fn new(first: usize, count: usize) -> Self {
+        assert!(count >= 2, "a backup frame needs at least 2 registers");
+        assert!(
+            first + count <= Rtc::BACKUP_REGISTER_COUNT,
+            "backup registers out of range"
+        );
+        Self { first, count }
+    }
+
+    /// Maximum number of bytes stored in the frame.
+    pub const fn capacity(&self) -> usize {
+        let capacity = (self.count - 1) * 4;
+        if capacity > 255 {
+            255
+        } else {
+            capacity
+        }
+    }
+
+    /// Write `data` to the frame.
+    pub fn write(&self, rtc: &Rtc, data: &[u8]) -> Result<(), BackupFrameError> {
+        if data.len() > self.capacity() {
+            return Err(BackupFrameError::TooLarge);
+        }
+
+        // Invalidate the frame first, so a reset while writing leaves it corrupted rather than
+        // mixing old and new data.
+        rtc.write_backup_register(self.first, 0);
+        for (i, chunk) in data.chunks(4).enumerate() {
+            let mut word = [0; 4];
+            word[..chunk.len()].copy_from_slice(chunk);
+            rtc.write_backup_register(self.first + 1 + i, u32::from_le_bytes(word));
+        }
+        rtc.write_backup_register(self.first, header(data));
+        Ok(())
+    }
+
+    /// Read the frame into `buf`, returning the number of bytes read.
+    pub fn read(&self, rtc: &Rtc, buf: &mut [u8]) -> Result<usize, BackupFrameError> {
+        let header = rtc.read_backup_register(self.first).unwrap_or(0);
+        if header == 0 {
+            return Err(BackupFrameError::Empty);
+        }
+        let len = ((header >> 16) & 0xFF) as usize;
+        if header >> 24 != FRAME_MAGIC || len > self.capacity() {
+            return Err(BackupFrameError::Corrupted);
+        }
+        if len > buf.len() {
+            return Err(BackupFrameError::TooLarge);
+        }
+
+        let data = &mut buf[..len];
+        for (i, chunk) in data.chunks_mut(4).enumerate() {
+            let word = rtc.read_backup_register(self.first + 1 + i).unwrap_or(0).to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+        if self::header(data) != header {
+            return Err(BackupFrameError::Corrupted);
+        }
+        Ok(len)
+    }
+
+    /// Clear the frame, so reading it returns [`BackupFrameError::Empty`].
+    pub fn clear(&self, rtc: &Rtc) {
+        rtc.write_backup_register(self.first, 0);
+    }
+}
+
+/// Frame header: magic, length and Fletcher-16 checksum of `data`.
+fn header(data: &[u8]) -> u32 {
+    let (mut sum1, mut sum2) = (0u16, 0u16);
+    for &byte in data {
+        sum1 = (sum1 + byte as u16) % 255;
+        sum2 = (sum2 + sum1) % 255;
+    }
+    (FRAME_MAGIC << 24) | ((data.len() as u32) << 16) | ((sum2 as u32) << 8) | sum1 as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header_is_never_empty() {
+        assert_eq!(header(&[]), 0xB500_0000);
+        assert_eq!(header(&[0x01, 0x02]), 0xB502_0403);
+        assert_ne!(header(&[0x01, 0x02]), header(&[0x02, 0x01]));
+    }
+}
@@ -1,8 +1,15 @@
 //! Real Time Clock (RTC)
+#[cfg(any(stm32f4, stm32l4, stm32wb, stm32g4))]
+mod alarm;
+mod backup;
 mod datetime;
 
 #[cfg(feature = "low-power")]
 mod low_power;
+#[cfg(any(
+    stm32f4, stm32l0, stm32g4, stm32l4, stm32l5, stm32wb, stm32h5, stm32g0, stm32u5, stm32u0
+))]
+mod wakeup;
 
 #[cfg(feature = "low-power")]
 use core::cell::Cell;
@@ -12,8 +19,15 @@ use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 #[cfg(feature = "low-power")]
 use embassy_sync::blocking_mutex::Mutex;
 
+#[cfg(any(stm32f4, stm32l4, stm32wb, stm32g4))]
+pub use self::alarm::{Alarm, AlarmInterruptHandler, DateTimeMatch, DayMatch};
+pub use self::backup::{BackupFrame, BackupFrameError, BackupRegister, BackupValue};
 use self::datetime::{day_of_week_from_u8, day_of_week_to_u8};
 pub use self::datetime::{DateTime, DayOfWeek, Error as DateTimeError};
+#[cfg(any(
+    stm32f4, stm32l0, stm32g4, stm32l4, stm32l5, stm32wb, stm32h5, stm32g0, stm32u5, stm32u0
+))]
+pub use self::wakeup::MAX_WAKEUP_PERIOD_SECONDS;
 use crate::pac::rtc::regs::{Dr, Tr};
 use crate::time::Hertz;
 
@@ -149,6 +163,18 @@ pub enum RtcCalibrationCyclePeriod {
     Seconds32,
 }
 
+/// Calibration output on the RTC_OUT pin.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RtcCalibrationOutput {
+    /// 512 Hz output, from the RTC clock divided by the asynchronous prescaler, which is not
+    /// affected by the smooth calibration.
+    ///
+    /// This is 512 Hz with a 32.768 kHz RTC clock and a subsecond frequency of 256 Hz.
+    Hz512,
+    /// 1 Hz output, from the calendar clock, which is affected by the smooth calibration.
+    Hz1,
+}
+
 impl Rtc {
     /// Create a new RTC instance.
     pub fn new(_rtc: Peri<'static, RTC>, rtc_config: RtcConfig) -> Self {
@@ -161,6 +187,14 @@ impl Rtc {
             _private: (),
         };
 
+        // The subsecond counter is the time base of the RTC time driver.
+        #[cfg(time_driver_rtc)]
+        assert_eq!(
+            rtc_config.frequency.0 as u64,
+            embassy_time_driver::TICK_HZ,
+            "RtcConfig::frequency must be TICK_HZ with time-driver-rtc"
+        );
+
         let frequency = Self::frequency();
         let async_psc = ((frequency.0 / rtc_config.frequency.0) - 1) as u8;
         let sync_psc = (rtc_config.frequency.0 - 1) as u16;
@@ -252,6 +286,41 @@ impl Rtc {
         })
     }
 
+    /// Return the smooth calibration set by `calibrate`, in ppm.
+    ///
+    /// This can be used to adjust the calibration from a time reference, like SNTP, by the
+    /// measured drift.
+    #[cfg(not(any(rtc_v1, rtc_v2f2)))]
+    pub fn calibration(&self) -> f32 {
+        use crate::pac::rtc::vals::Calp;
+
+        const RTC_CALR_RESOLUTION_PPM: f32 = 0.9537;
+
+        // Extra pulses during calibration cycle period: CALP * 512 - CALM
+        let calr = RTC::regs().calr().read();
+        let calp = if calr.calp() == Calp::INCREASE_FREQ { 512 } else { 0 };
+        (calp - calr.calm() as i32) as f32 * RTC_CALR_RESOLUTION_PPM
+    }
+
+    /// Enable or disable the calibration output on the RTC_OUT pin.
+    ///
+    /// The output is used to measure the RTC clock in production, before trimming it with
+    /// `calibrate`.
+    #[cfg(not(any(rtc_v1, rtc_v2f2)))]
+    pub fn set_calibration_output(&mut self, output: Option<RtcCalibrationOutput>) {
+        use crate::pac::rtc::vals::Cosel;
+
+        self.write(false, |rtc| {
+            rtc.cr().modify(|w| {
+                w.set_coe(output.is_some());
+                w.set_cosel(match output {
+                    Some(RtcCalibrationOutput::Hz1) => Cosel::CALFREQ_1HZ,
+                    _ => Cosel::CALFREQ_512HZ,
+                });
+            });
+        })
+    }
+
     /// Number of backup registers of this instance.
     pub const BACKUP_REGISTER_COUNT: usize = RTC::BACKUP_REGISTER_COUNT;
 
@@ -292,14 +361,14 @@ pub(crate) fn bcd2_to_byte(bcd: (u8, u8)) -> u8 {
     tmp + (value & 0x0F)
 }
 
-trait SealedInstance {
+pub(crate) trait SealedInstance {
     const BACKUP_REGISTER_COUNT: usize;
 
-    #[cfg(feature = "low-power")]
+    #[cfg(any(feature = "low-power", time_driver_rtc))]
     #[cfg(not(any(stm32u5, stm32u0)))]
     const EXTI_WAKEUP_LINE: usize;
 
-    #[cfg(feature = "low-power")]
+    #[cfg(any(feature = "low-power", time_driver_rtc))]
     type WakeupInterrupt: crate::interrupt::typelevel::Interrupt;
 
     fn regs() -> crate::pac::rtc::Rtc {
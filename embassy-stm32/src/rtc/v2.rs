@@ -128,22 +128,22 @@ impl super::Rtc {
 impl SealedInstance for crate::peripherals::RTC {
     const BACKUP_REGISTER_COUNT: usize = 20;
 
-    #[cfg(all(feature = "low-power", stm32f4))]
+    #[cfg(all(any(feature = "low-power", time_driver_rtc), stm32f4))]
     const EXTI_WAKEUP_LINE: usize = 22;
 
-    #[cfg(all(feature = "low-power", stm32l4))]
+    #[cfg(all(any(feature = "low-power", time_driver_rtc), stm32l4))]
     const EXTI_WAKEUP_LINE: usize = 20;
 
-    #[cfg(all(feature = "low-power", stm32l0))]
+    #[cfg(all(any(feature = "low-power", time_driver_rtc), stm32l0))]
     const EXTI_WAKEUP_LINE: usize = 20;
 
-    #[cfg(all(feature = "low-power", stm32wb))]
+    #[cfg(all(any(feature = "low-power", time_driver_rtc), stm32wb))]
     const EXTI_WAKEUP_LINE: usize = 19;
 
-    #[cfg(all(feature = "low-power", any(stm32f4, stm32l4, stm32wb)))]
+    #[cfg(all(any(feature = "low-power", time_driver_rtc), any(stm32f4, stm32l4, stm32wb)))]
     type WakeupInterrupt = crate::interrupt::typelevel::RTC_WKUP;
 
-    #[cfg(all(feature = "low-power", stm32l0))]
+    #[cfg(all(any(feature = "low-power", time_driver_rtc), stm32l0))]
     type WakeupInterrupt = crate::interrupt::typelevel::RTC;
 
     fn read_backup_register(rtc: Rtc, register: usize) -> Option<u32> {
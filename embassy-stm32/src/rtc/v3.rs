@@ -129,7 +129,7 @@ impl super::Rtc {
 impl SealedInstance for crate::peripherals::RTC {
     const BACKUP_REGISTER_COUNT: usize = 32;
 
-    #[cfg(feature = "low-power")]
+    #[cfg(any(feature = "low-power", time_driver_rtc))]
     cfg_if::cfg_if!(
         if #[cfg(stm32g4)] {
             const EXTI_WAKEUP_LINE: usize = 20;
@@ -140,7 +140,7 @@ impl SealedInstance for crate::peripherals::RTC {
         }
     );
 
-    #[cfg(feature = "low-power")]
+    #[cfg(any(feature = "low-power", time_driver_rtc))]
     cfg_if::cfg_if!(
         if #[cfg(stm32g4)] {
             type WakeupInterrupt = crate::interrupt::typelevel::RTC_WKUP;
@@ -0,0 +1,78 @@
+use super::Rtc;
+use crate::pac::rtc::vals::Wucksel;
+
+/// Longest wakeup timer period, in seconds.
+pub const MAX_WAKEUP_PERIOD_SECONDS: u32 = 1 << 17;
+
+impl Rtc {
+    /// Start the wakeup timer, to trigger every `seconds`, from 1 second to
+    /// [`MAX_WAKEUP_PERIOD_SECONDS`] (about 36 hours).
+    ///
+    /// The wakeup timer wakes the chip up from Stop and Standby modes. It is also used by the
+    /// low-power executor to leave Stop mode once the RTC is given to it, in that case use
+    /// [`low_power::standby_with_rtc`](crate::low_power::standby_with_rtc) to enter Standby instead.
+    #[cfg(not(time_driver_rtc))]
+    pub fn start_periodic_wakeup(&mut self, seconds: u32) {
+        self.start_wakeup_timer_seconds(seconds)
+    }
+
+    /// Stop the wakeup timer.
+    #[cfg(not(time_driver_rtc))]
+    pub fn stop_periodic_wakeup(&mut self) {
+        self.write(false, |regs| {
+            regs.cr().modify(|w| {
+                w.set_wutie(false);
+                w.set_wute(false);
+            });
+        });
+        clear_wakeup_flag();
+    }
+
+    #[allow(dead_code)]
+    pub(crate) fn start_wakeup_timer_seconds(&self, seconds: u32) {
+        assert!(
+            (1..=MAX_WAKEUP_PERIOD_SECONDS).contains(&seconds),
+            "wakeup period must be between 1 and {} seconds",
+            MAX_WAKEUP_PERIOD_SECONDS
+        );
+
+        // The 1 Hz clock counts `WUT + 1` seconds, or `WUT + 2^16 + 1` seconds with the offset.
+        let (wucksel, wut) = if seconds > 1 << 16 {
+            (Wucksel::from_bits(0b110), (seconds - (1 << 16) - 1) as u16)
+        } else {
+            (Wucksel::from_bits(0b100), (seconds - 1) as u16)
+        };
+
+        self.write(false, |regs| {
+            regs.cr().modify(|w| w.set_wute(false));
+
+            #[cfg(any(
+                rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+            ))]
+            while !regs.isr().read().wutwf() {}
+            #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+            while !regs.icsr().read().wutwf() {}
+
+            regs.cr().modify(|w| w.set_wucksel(wucksel));
+            regs.wutr().write(|w| w.set_wut(wut));
+            regs.cr().modify(|w| {
+                w.set_wute(true);
+                w.set_wutie(true);
+            });
+        });
+        clear_wakeup_flag();
+    }
+}
+
+/// Clear the wakeup timer flag, which otherwise wakes up from Standby right away.
+pub(crate) fn clear_wakeup_flag() {
+    let regs = crate::pac::RTC;
+
+    #[cfg(any(
+        rtc_v2f0, rtc_v2f2, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+    ))]
+    regs.isr().modify(|w| w.set_wutf(false));
+
+    #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+    regs.scr().write(|w| w.set_cwutf(crate::pac::rtc::vals::Calrf::CLEAR));
+}
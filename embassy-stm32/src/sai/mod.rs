@@ -974,7 +974,7 @@ impl<'d, T: Instance, W: word::Word> Sai<'d, T, W> {
 
     /// Reset SAI operation.
     pub fn reset() {
-        rcc::enable_and_reset::<T>();
+        rcc::reset::<T>();
     }
 
     /// Enable or disable mute.
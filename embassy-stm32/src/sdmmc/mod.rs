@@ -271,7 +271,9 @@ const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOp
     mburst: crate::dma::Burst::Incr4,
     flow_ctrl: crate::dma::FlowControl::Peripheral,
     fifo_threshold: Some(crate::dma::FifoThreshold::Full),
+    direct_mode_error_ir: false,
     priority: crate::dma::Priority::VeryHigh,
+    peripheral_increment: false,
     circular: false,
     half_transfer_ir: false,
     complete_transfer_ir: true,
@@ -279,6 +281,7 @@ const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOp
 #[cfg(all(sdmmc_v1, not(dma)))]
 const DMA_TRANSFER_OPTIONS: crate::dma::TransferOptions = crate::dma::TransferOptions {
     priority: crate::dma::Priority::VeryHigh,
+    peripheral_increment: false,
     circular: false,
     half_transfer_ir: false,
     complete_transfer_ir: true,
@@ -8,7 +8,7 @@ use embassy_embedded_hal::SetConfig;
 use embassy_futures::join::join;
 pub use embedded_hal_02::spi::{Mode, Phase, Polarity, MODE_0, MODE_1, MODE_2, MODE_3};
 
-use crate::dma::{word, ChannelAndRequest};
+use crate::dma::{word, ChannelAndRequest, TransferError};
 use crate::gpio::{AfType, AnyPin, OutputType, Pull, SealedPin as _, Speed};
 use crate::mode::{Async, Blocking, Mode as PeriMode};
 use crate::pac::spi::{regs, vals, Spi as Regs};
@@ -19,6 +19,7 @@ use crate::Peri;
 /// SPI error.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[non_exhaustive]
 pub enum Error {
     /// Invalid framing.
     Framing,
@@ -28,6 +29,8 @@ pub enum Error {
     ModeFault,
     /// Overrun.
     Overrun,
+    /// DMA transfer error.
+    Dma(TransferError),
 }
 
 impl core::fmt::Display for Error {
@@ -37,6 +40,7 @@ impl core::fmt::Display for Error {
             Self::Crc => "Hardware CRC Check Failed",
             Self::ModeFault => "Mode Fault",
             Self::Overrun => "Buffer Overrun",
+            Self::Dma(_) => "DMA Transfer Error",
         };
 
         write!(f, "{}", message)
@@ -45,6 +49,12 @@ impl core::fmt::Display for Error {
 
 impl core::error::Error for Error {}
 
+impl From<TransferError> for Error {
+    fn from(error: TransferError) -> Self {
+        Self::Dma(error)
+    }
+}
+
 /// SPI bit order
 #[derive(Copy, Clone)]
 pub enum BitOrder {
@@ -682,10 +692,11 @@ impl<'d> Spi<'d, Async> {
             w.set_cstart(true);
         });
 
-        tx_f.await;
+        let result = tx_f.await;
 
         finish_dma(self.info.regs);
 
+        result?;
         Ok(())
     }
 
@@ -725,6 +736,7 @@ impl<'d> Spi<'d, Async> {
 
         let rx_src = regs.rx_ptr();
 
+        let mut result = Ok(());
         for mut chunk in data.chunks_mut(u16::max_value().into()) {
             set_rxdmaen(regs, true);
 
@@ -749,9 +761,13 @@ impl<'d> Spi<'d, Async> {
                 w.set_cstart(true);
             });
 
-            transfer.await;
+            result = transfer.await;
 
             finish_dma(regs);
+
+            if result.is_err() {
+                break;
+            }
         }
 
         regs.cr1().modify(|w| {
@@ -773,6 +789,7 @@ impl<'d> Spi<'d, Async> {
             });
         }
 
+        result?;
         Ok(())
     }
 
@@ -818,10 +835,12 @@ impl<'d> Spi<'d, Async> {
             w.set_cstart(true);
         });
 
-        join(tx_f, rx_f).await;
+        let (tx_result, rx_result) = join(tx_f, rx_f).await;
 
         finish_dma(self.info.regs);
 
+        tx_result?;
+        rx_result?;
         Ok(())
     }
 
@@ -863,10 +882,12 @@ impl<'d> Spi<'d, Async> {
             w.set_cstart(true);
         });
 
-        join(tx_f, rx_f).await;
+        let (tx_result, rx_result) = join(tx_f, rx_f).await;
 
         finish_dma(self.info.regs);
 
+        tx_result?;
+        rx_result?;
         Ok(())
     }
 
@@ -1182,6 +1203,7 @@ impl embedded_hal_1::spi::Error for Error {
             Self::Crc => embedded_hal_1::spi::ErrorKind::Other,
             Self::ModeFault => embedded_hal_1::spi::ErrorKind::ModeFault,
             Self::Overrun => embedded_hal_1::spi::ErrorKind::Overrun,
+            Self::Dma(_) => embedded_hal_1::spi::ErrorKind::Other,
         }
     }
 }
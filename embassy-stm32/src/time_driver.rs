@@ -8,14 +8,14 @@ use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::blocking_mutex::Mutex;
 use embassy_time_driver::{Driver, TICK_HZ};
 use embassy_time_queue_utils::Queue;
-use stm32_metapac::timer::{regs, TimGp16};
+use stm32_metapac::timer::{regs, TimGp16, TimGp32};
 
 use crate::interrupt::typelevel::Interrupt;
 use crate::pac::timer::vals;
 use crate::rcc::{self, SealedRccPeripheral};
 #[cfg(feature = "low-power")]
 use crate::rtc::Rtc;
-use crate::timer::{CoreInstance, GeneralInstance1Channel};
+use crate::timer::{CoreInstance, GeneralInstance1Channel, TimerBits};
 use crate::{interrupt, peripherals};
 
 // NOTE regarding ALARM_COUNT:
@@ -173,25 +173,85 @@ fn regs_gp16() -> TimGp16 {
     unsafe { TimGp16::from_ptr(T::regs()) }
 }
 
+fn regs_gp32() -> TimGp32 {
+    unsafe { TimGp32::from_ptr(T::regs()) }
+}
+
 // Clock timekeeping works with something we call "periods", which are time intervals
-// of 2^15 ticks. The Clock counter value is 16 bits, so one "overflow cycle" is 2 periods.
+// of 2^PERIOD_BITS ticks: 2^15 ticks with a 16-bit counter and 2^31 ticks with a 32-bit counter.
+// One "overflow cycle" of the counter is 2 periods.
 //
 // A `period` count is maintained in parallel to the Timer hardware `counter`, like this:
 // - `period` and `counter` start at 0
 // - `period` is incremented on overflow (at counter value 0)
-// - `period` is incremented "midway" between overflows (at counter value 0x8000)
+// - `period` is incremented "midway" between overflows (at counter value 2^PERIOD_BITS)
 //
-// Therefore, when `period` is even, counter is in 0..0x7FFF. When odd, counter is in 0x8000..0xFFFF
-// This allows for now() to return the correct value even if it races an overflow.
+// Therefore, when `period` is even, counter is in the lower half of its range. When odd, counter
+// is in the upper half. This allows for now() to return the correct value even if it races an overflow.
 //
 // To get `now()`, `period` is read first, then `counter` is read. If the counter value matches
 // the expected range for the `period` parity, we're done. If it doesn't, this means that
 // a new period start has raced us between reading `period` and `counter`, so we assume the `counter` value
 // corresponds to the next period.
 //
-// `period` is a 32bit integer, so It overflows on 2^32 * 2^15 / 32768 seconds of uptime, which is 136 years.
-fn calc_now(period: u32, counter: u16) -> u64 {
-    ((period as u64) << 15) + ((counter as u32 ^ ((period & 1) << 15)) as u64)
+// The period interrupts are the only ones that fire without a pending alarm. With a 32-bit timer
+// they happen every 2^31 ticks (35 minutes at 1 MHz) instead of every 2^15 ticks, so idle
+// wakeups are rare and the alarm can be programmed directly for expirations far in the future.
+//
+// `period` is a 32bit integer, so with a 16-bit counter it overflows on 2^32 * 2^15 / 32768
+// seconds of uptime, which is 136 years.
+const PERIOD_BITS: u32 = match T::BITS {
+    TimerBits::Bits16 => 15,
+    #[cfg(not(stm32l0))]
+    TimerBits::Bits32 => 31,
+};
+
+/// Alarms closer than this to the start of the current period are enabled right away, later ones
+/// are enabled by the period interrupts.
+const ALARM_WINDOW: u64 = 3 << (PERIOD_BITS - 1);
+
+fn calc_now(period: u32, counter: u32) -> u64 {
+    ((period as u64) << PERIOD_BITS) + ((counter ^ ((period & 1) << PERIOD_BITS)) as u64)
+}
+
+fn read_counter() -> u32 {
+    match T::BITS {
+        TimerBits::Bits16 => regs_gp16().cnt().read().cnt() as u32,
+        #[cfg(not(stm32l0))]
+        TimerBits::Bits32 => regs_gp32().cnt().read(),
+    }
+}
+
+/// Prescaler dividing the timer kernel clock to `TICK_HZ`.
+fn prescaler() -> u16 {
+    let timer_freq = T::frequency();
+
+    // High tick rates (above 1 MHz) need a timer kernel clock of at least the tick rate,
+    // and should divide it evenly for the tick to be accurate.
+    if (timer_freq.0 as u64) < TICK_HZ {
+        panic!("timer frequency {} Hz is lower than the tick rate", timer_freq.0);
+    }
+    let psc = (timer_freq.0 as u64 / TICK_HZ) as u32 - 1;
+    match psc.try_into() {
+        Err(_) => panic!("psc division overflow: {}", psc),
+        Ok(n) => n,
+    }
+}
+
+fn write_counter(value: u32) {
+    match T::BITS {
+        TimerBits::Bits16 => regs_gp16().cnt().write(|w| w.set_cnt(value as u16)),
+        #[cfg(not(stm32l0))]
+        TimerBits::Bits32 => regs_gp32().cnt().write_value(value),
+    }
+}
+
+fn write_ccr(n: usize, value: u32) {
+    match T::BITS {
+        TimerBits::Bits16 => regs_gp16().ccr(n).write(|w| w.set_ccr(value as u16)),
+        #[cfg(not(stm32l0))]
+        TimerBits::Bits32 => regs_gp32().ccr(n).write_value(value),
+    }
 }
 
 struct AlarmState {
@@ -231,19 +291,15 @@ impl RtcDriver {
 
         rcc::enable_and_reset_with_cs::<T>(cs);
 
-        let timer_freq = T::frequency();
-
         r.cr1().modify(|w| w.set_cen(false));
-        r.cnt().write(|w| w.set_cnt(0));
-
-        let psc = timer_freq.0 / TICK_HZ as u32 - 1;
-        let psc: u16 = match psc.try_into() {
-            Err(_) => panic!("psc division overflow: {}", psc),
-            Ok(n) => n,
-        };
+        write_counter(0);
 
-        r.psc().write_value(psc);
-        r.arr().write(|w| w.set_arr(u16::MAX));
+        r.psc().write_value(prescaler());
+        match T::BITS {
+            TimerBits::Bits16 => r.arr().write(|w| w.set_arr(u16::MAX)),
+            #[cfg(not(stm32l0))]
+            TimerBits::Bits32 => regs_gp32().arr().write_value(u32::MAX),
+        }
 
         // Set URS, generate update and clear URS
         r.cr1().modify(|w| w.set_urs(vals::Urs::COUNTER_ONLY));
@@ -251,7 +307,7 @@ impl RtcDriver {
         r.cr1().modify(|w| w.set_urs(vals::Urs::ANY_EVENT));
 
         // Mid-way point
-        r.ccr(0).write(|w| w.set_ccr(0x8000));
+        write_ccr(0, 1 << PERIOD_BITS);
 
         // Enable overflow and half-overflow interrupts
         r.dier().write(|w| {
@@ -265,6 +321,28 @@ impl RtcDriver {
         r.cr1().modify(|w| w.set_cen(true));
     }
 
+    /// Reconfigure the clocks with `f`, keeping the time.
+    ///
+    /// The timer is stopped while the clocks change, so the time lags by the duration of `f`.
+    fn reinit(&self, f: impl FnOnce()) {
+        let r = regs_gp16();
+
+        r.cr1().modify(|w| w.set_cen(false));
+        let counter = read_counter();
+
+        f();
+
+        r.psc().write_value(prescaler());
+
+        // Load the prescaler without an update interrupt, and restore the counter it resets.
+        r.cr1().modify(|w| w.set_urs(vals::Urs::COUNTER_ONLY));
+        r.egr().write(|w| w.set_ug(true));
+        r.cr1().modify(|w| w.set_urs(vals::Urs::ANY_EVENT));
+        write_counter(counter);
+
+        r.cr1().modify(|w| w.set_cen(true));
+    }
+
     fn on_interrupt(&self) {
         let r = regs_gp16();
 
@@ -300,7 +378,7 @@ impl RtcDriver {
         // We only modify the period from the timer interrupt, so we know this can't race.
         let period = self.period.load(Ordering::Relaxed) + 1;
         self.period.store(period, Ordering::Relaxed);
-        let t = (period as u64) << 15;
+        let t = (period as u64) << PERIOD_BITS;
 
         critical_section::with(move |cs| {
             r.dier().modify(move |w| {
@@ -308,7 +386,7 @@ impl RtcDriver {
                 let alarm = self.alarm.borrow(cs);
                 let at = alarm.timestamp.get();
 
-                if at < t + 0xc000 {
+                if at < t + ALARM_WINDOW {
                     // just enable it. `set_alarm` has already set the correct CCR val.
                     w.set_ccie(n + 1, true);
                 }
@@ -338,34 +416,14 @@ impl RtcDriver {
     #[cfg(feature = "low-power")]
     /// Add the given offset to the current time
     fn add_time(&self, offset: embassy_time::Duration, cs: CriticalSection) {
-        let offset = offset.as_ticks();
-        let cnt = regs_gp16().cnt().read().cnt() as u32;
-        let period = self.period.load(Ordering::SeqCst);
-
-        // Correct the race, if it exists
-        let period = if period & 1 == 1 && cnt < u16::MAX as u32 / 2 {
-            period + 1
-        } else {
-            period
-        };
-
-        // Normalize to the full overflow
-        let period = (period / 2) * 2;
-
-        // Add the offset
-        let period = period + 2 * (offset / u16::MAX as u64) as u32;
-        let cnt = cnt + (offset % u16::MAX as u64) as u32;
+        let now = self.now() + offset.as_ticks();
 
-        let (cnt, period) = if cnt > u16::MAX as u32 {
-            (cnt - u16::MAX as u32, period + 2)
-        } else {
-            (cnt, period)
-        };
-
-        let period = if cnt > u16::MAX as u32 / 2 { period + 1 } else { period };
+        // Split the new time in a period and a counter value of matching parity, see `calc_now`.
+        let period = (now >> PERIOD_BITS) as u32;
+        let cnt = (now & ((2 << PERIOD_BITS) - 1)) as u32;
 
         self.period.store(period, Ordering::SeqCst);
-        regs_gp16().cnt().write(|w| w.set_cnt(cnt as u16));
+        write_counter(cnt);
 
         // Now, recompute alarm
         let alarm = self.alarm.borrow(cs);
@@ -464,11 +522,11 @@ impl RtcDriver {
 
         // Write the CCR value regardless of whether we're going to enable it now or not.
         // This way, when we enable it later, the right value is already set.
-        r.ccr(n + 1).write(|w| w.set_ccr(timestamp as u16));
+        write_ccr(n + 1, timestamp as u32);
 
         // Enable it if it'll happen soon. Otherwise, `next_period` will enable it.
         let diff = timestamp - t;
-        r.dier().modify(|w| w.set_ccie(n + 1, diff < 0xc000));
+        r.dier().modify(|w| w.set_ccie(n + 1, diff < ALARM_WINDOW));
 
         // Reevaluate if the alarm timestamp is still in the future
         let t = self.now();
@@ -491,11 +549,9 @@ impl RtcDriver {
 
 impl Driver for RtcDriver {
     fn now(&self) -> u64 {
-        let r = regs_gp16();
-
         let period = self.period.load(Ordering::Relaxed);
         compiler_fence(Ordering::Acquire);
-        let counter = r.cnt().read().cnt();
+        let counter = read_counter();
         calc_now(period, counter)
     }
 
@@ -521,3 +577,7 @@ pub(crate) fn get_driver() -> &'static RtcDriver {
 pub(crate) fn init(cs: CriticalSection) {
     DRIVER.init(cs)
 }
+
+pub(crate) fn reinit(_cs: CriticalSection, f: impl FnOnce()) {
+    DRIVER.reinit(f)
+}
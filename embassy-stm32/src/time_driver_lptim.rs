@@ -0,0 +1,352 @@
+//! embassy-time driver using a low-power timer (LPTIM).
+//!
+//! Unlike the general-purpose timers, the LPTIM keeps counting in Stop mode when its kernel clock
+//! is LSE or LSI, so time keeps advancing without having to be corrected by the RTC wakeup timer.
+//! The LPTIM kernel clock divided by `TICK_HZ` must be a power of two no larger than 128, which
+//! typically means LSE with the `tick-hz-32_768` feature of `embassy-time`.
+//!
+//! The driver is selected at build time with the `time-driver-lptim1` or `time-driver-lptim2`
+//! feature, since embassy-time only has a single global driver. The kernel clock is selected in
+//! the RCC configuration passed to [`crate::init`], for example on STM32L4:
+//!
+//! ```rust,ignore
+//! let mut config = embassy_stm32::Config::default();
+//! config.rcc.ls = LsConfig::default_lse();
+//! config.rcc.mux.lptim1sel = mux::Lptim1sel::LSE;
+//! let p = embassy_stm32::init(config);
+//! ```
+
+#![allow(non_snake_case)]
+
+use core::cell::{Cell, RefCell};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use critical_section::CriticalSection;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time_driver::{Driver, TICK_HZ};
+use embassy_time_queue_utils::Queue;
+
+use crate::interrupt::typelevel::Interrupt;
+use crate::lptim::{Instance, SealedInstance};
+use crate::pac::lptim::{vals, Lptim};
+use crate::rcc::{self, SealedRccPeripheral};
+#[cfg(feature = "low-power")]
+use crate::rtc::Rtc;
+use crate::{interrupt, peripherals};
+
+#[cfg(time_driver_lptim1)]
+type T = peripherals::LPTIM1;
+#[cfg(time_driver_lptim2)]
+type T = peripherals::LPTIM2;
+
+foreach_interrupt! {
+    (LPTIM1, lptim, $block:ident, GLOBAL, $irq:ident) => {
+        #[cfg(time_driver_lptim1)]
+        #[cfg(feature = "rt")]
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+    (LPTIM2, lptim, $block:ident, GLOBAL, $irq:ident) => {
+        #[cfg(time_driver_lptim2)]
+        #[cfg(feature = "rt")]
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+}
+
+fn regs() -> Lptim {
+    <T as SealedInstance>::regs()
+}
+
+// The counter counts from 0 to `PERIOD_MAX` (inclusive), so one "period" is 2^15 ticks. The
+// period count is incremented by the auto-reload match interrupt.
+//
+// To get `now()`, `period` is read first, then `counter`, then the auto-reload match flag, and
+// finally `period` again. If `period` changed, the interrupt ran in between and we retry. If the
+// auto-reload match flag is pending and the counter is in the lower half, the counter has wrapped
+// but the interrupt has not run yet, so the counter value belongs to the next period.
+//
+// `period` is a 32bit integer, so It overflows on 2^32 * 2^15 / 32768 seconds of uptime, which is 136 years.
+const PERIOD_MAX: u16 = 0x7FFF;
+
+fn calc_now(period: u32, counter: u16, wrap_pending: bool) -> u64 {
+    let period = if wrap_pending && counter < 0x4000 {
+        period + 1
+    } else {
+        period
+    };
+    ((period as u64) << 15) + counter as u64
+}
+
+fn read_counter() -> u16 {
+    // The counter runs asynchronously to the APB clock, so it must be read until two consecutive
+    // reads return the same value.
+    loop {
+        let a = regs().cnt().read().cnt();
+        let b = regs().cnt().read().cnt();
+        if a == b {
+            return a;
+        }
+    }
+}
+
+fn compare_match() -> bool {
+    #[cfg(not(any(lptim_v2a, lptim_v2b)))]
+    return regs().isr().read().cmpm();
+    #[cfg(any(lptim_v2a, lptim_v2b))]
+    return regs().isr().read().ccif(0);
+}
+
+fn clear_compare_match() {
+    #[cfg(not(any(lptim_v2a, lptim_v2b)))]
+    regs().icr().write(|w| w.set_cmpmcf(true));
+    #[cfg(any(lptim_v2a, lptim_v2b))]
+    regs().icr().write(|w| w.set_cccf(0, true));
+}
+
+fn set_compare(value: u16) {
+    let r = regs();
+
+    #[cfg(not(any(lptim_v2a, lptim_v2b)))]
+    {
+        if r.cmp().read().cmp() == value {
+            return;
+        }
+        r.icr().write(|w| w.set_cmpokcf(true));
+        r.cmp().write(|w| w.set_cmp(value));
+        while !r.isr().read().cmpok() {}
+        r.icr().write(|w| w.set_cmpokcf(true));
+    }
+    #[cfg(any(lptim_v2a, lptim_v2b))]
+    {
+        if r.ccr(0).read().ccr() == value {
+            return;
+        }
+        r.icr().write(|w| w.set_cmpokcf(0, true));
+        r.ccr(0).write(|w| w.set_ccr(value));
+        while !r.isr().read().cmpok(0) {}
+        r.icr().write(|w| w.set_cmpokcf(0, true));
+    }
+}
+
+struct AlarmState {
+    timestamp: Cell<u64>,
+}
+
+unsafe impl Send for AlarmState {}
+
+impl AlarmState {
+    const fn new() -> Self {
+        Self {
+            timestamp: Cell::new(u64::MAX),
+        }
+    }
+}
+
+pub(crate) struct RtcDriver {
+    /// Number of 2^15 periods elapsed since boot.
+    period: AtomicU32,
+    alarm: Mutex<CriticalSectionRawMutex, AlarmState>,
+    queue: Mutex<CriticalSectionRawMutex, RefCell<Queue>>,
+}
+
+embassy_time_driver::time_driver_impl!(static DRIVER: RtcDriver = RtcDriver {
+    period: AtomicU32::new(0),
+    alarm: Mutex::const_new(CriticalSectionRawMutex::new(), AlarmState::new()),
+    queue: Mutex::new(RefCell::new(Queue::new()))
+});
+
+impl RtcDriver {
+    fn init(&'static self, cs: critical_section::CriticalSection) {
+        let r = regs();
+
+        rcc::enable_and_reset_with_cs::<T>(cs);
+
+        let timer_freq = T::frequency().0;
+        let div = timer_freq / TICK_HZ as u32;
+        if div * TICK_HZ as u32 != timer_freq || !div.is_power_of_two() || div > 128 {
+            panic!(
+                "LPTIM kernel clock {} Hz is not a power-of-two multiple (up to 128) of TICK_HZ",
+                timer_freq
+            );
+        }
+
+        // CFGR and the interrupt enables can only be written while the timer is disabled.
+        r.cr().modify(|w| w.set_enable(false));
+        r.cfgr()
+            .write(|w| w.set_presc(vals::Presc::from_bits(div.trailing_zeros() as u8)));
+
+        #[cfg(not(any(lptim_v2a, lptim_v2b)))]
+        r.ier().write(|w| {
+            w.set_arrmie(true);
+            w.set_cmpmie(true);
+        });
+        #[cfg(any(lptim_v2a, lptim_v2b))]
+        r.dier().write(|w| {
+            w.set_arrmie(true);
+            w.set_ccie(0, true);
+        });
+
+        r.cr().modify(|w| w.set_enable(true));
+
+        r.icr().write(|w| w.set_arrokcf(true));
+        r.arr().write(|w| w.set_arr(PERIOD_MAX));
+        while !r.isr().read().arrok() {}
+        r.icr().write(|w| w.set_arrokcf(true));
+
+        set_compare(PERIOD_MAX - 1);
+
+        <T as Instance>::Interrupt::unpend();
+        unsafe { <T as Instance>::Interrupt::enable() };
+
+        r.cr().modify(|w| w.set_cntstrt(true));
+    }
+
+    fn on_interrupt(&self) {
+        let r = regs();
+
+        critical_section::with(|cs| {
+            // Overflow
+            if r.isr().read().arrm() {
+                r.icr().write(|w| w.set_arrmcf(true));
+                self.next_period();
+            }
+
+            // Alarm. The compare interrupt is always enabled and matches once per period, so
+            // check that the alarm is actually due.
+            if compare_match() {
+                clear_compare_match();
+            }
+
+            if self.alarm.borrow(cs).timestamp.get() <= self.now() {
+                self.trigger_alarm(cs);
+            }
+        })
+    }
+
+    fn next_period(&self) {
+        // We only modify the period from the timer interrupt, so we know this can't race.
+        let period = self.period.load(Ordering::Relaxed) + 1;
+        self.period.store(period, Ordering::Release);
+    }
+
+    fn trigger_alarm(&self, cs: CriticalSection) {
+        let mut next = self.queue.borrow(cs).borrow_mut().next_expiration(self.now());
+        while !self.set_alarm(cs, next) {
+            next = self.queue.borrow(cs).borrow_mut().next_expiration(self.now());
+        }
+    }
+
+    /*
+        Low-power public functions: all create a critical section
+    */
+    #[cfg(feature = "low-power")]
+    /// The LPTIM keeps running in Stop mode, so the RTC is not needed to keep track of time.
+    pub(crate) fn set_rtc(&self, _rtc: &'static Rtc) {}
+
+    #[cfg(feature = "low-power")]
+    /// The minimum pause time beyond which the executor will enter a low-power state.
+    pub(crate) const MIN_STOP_PAUSE: embassy_time::Duration = embassy_time::Duration::from_millis(1);
+
+    #[cfg(feature = "low-power")]
+    /// Check whether the next alarm is far enough in the future to enter a low-power state.
+    ///
+    /// The timer itself is not paused since it keeps running in Stop mode.
+    pub(crate) fn pause_time(&self) -> Result<(), ()> {
+        critical_section::with(|cs| {
+            let now = self.now();
+            let until = self.alarm.borrow(cs).timestamp.get().saturating_sub(now);
+            if embassy_time::Duration::from_ticks(until) < Self::MIN_STOP_PAUSE {
+                Err(())
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    #[cfg(feature = "low-power")]
+    /// Nothing to do, see [`Self::pause_time`].
+    pub(crate) fn resume_time(&self) {}
+
+    fn set_alarm(&self, cs: CriticalSection, timestamp: u64) -> bool {
+        self.alarm.borrow(cs).timestamp.set(timestamp);
+
+        let t = self.now();
+        if timestamp <= t {
+            // If alarm timestamp has passed the alarm will not fire.
+            // Disarm the alarm and return `false` to indicate that.
+            self.alarm.borrow(cs).timestamp.set(u64::MAX);
+
+            return false;
+        }
+
+        // The compare value must be lower than the auto-reload value. If the alarm is at the very
+        // end of a period, it is handled by the auto-reload match interrupt instead.
+        set_compare((timestamp as u16 & PERIOD_MAX).min(PERIOD_MAX - 1));
+
+        // Writing the compare register takes a few kernel clock cycles, reevaluate if the alarm
+        // timestamp is still in the future.
+        let t = self.now();
+        if timestamp <= t {
+            // If alarm timestamp has passed since we set it, we have a race condition and
+            // the alarm may or may not have fired.
+            // Disarm the alarm and return `false` to indicate that.
+            // It is the caller's responsibility to handle this ambiguity.
+            self.alarm.borrow(cs).timestamp.set(u64::MAX);
+
+            return false;
+        }
+
+        // We're confident the alarm will ring in the future.
+        true
+    }
+}
+
+impl Driver for RtcDriver {
+    fn now(&self) -> u64 {
+        loop {
+            let period = self.period.load(Ordering::Acquire);
+            let counter = read_counter();
+            let wrap_pending = regs().isr().read().arrm();
+            if self.period.load(Ordering::Acquire) == period {
+                return calc_now(period, counter, wrap_pending);
+            }
+        }
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &core::task::Waker) {
+        critical_section::with(|cs| {
+            let mut queue = self.queue.borrow(cs).borrow_mut();
+
+            if queue.schedule_wake(at, waker) {
+                let mut next = queue.next_expiration(self.now());
+                while !self.set_alarm(cs, next) {
+                    next = queue.next_expiration(self.now());
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "low-power")]
+pub(crate) fn get_driver() -> &'static RtcDriver {
+    &DRIVER
+}
+
+pub(crate) fn init(cs: CriticalSection) {
+    DRIVER.init(cs)
+}
+
+/// The LPTIM is clocked independently of the system clock, which is reconfigured by `f`.
+pub(crate) fn reinit(_cs: CriticalSection, f: impl FnOnce()) {
+    let timer_freq = T::frequency();
+    f();
+    if T::frequency() != timer_freq {
+        panic!("the LPTIM kernel clock can't change while it is used as the time driver");
+    }
+}
@@ -0,0 +1,403 @@
+//! embassy-time driver using the real-time clock (RTC).
+//!
+//! The time is read from the RTC calendar, which keeps running in Stop and Standby modes and
+//! across resets as long as the backup domain is powered, so `Instant::now()` is still correct
+//! after waking up from Standby. Alarms are scheduled with the RTC wakeup timer.
+//!
+//! `TICK_HZ` is the frequency of the RTC subsecond counter: the RTC clock divided by `TICK_HZ`
+//! must be an integer no larger than 128, which typically means LSE with the `tick-hz-32_768`
+//! feature of `embassy-time`.
+//!
+//! The calendar is the time base, so setting it with [`Rtc::set_datetime`](crate::rtc::Rtc::set_datetime)
+//! moves `Instant::now()` too. Only set it before timers are in use.
+
+#![allow(non_snake_case)]
+
+#[cfg(any(
+    rtc_v1,
+    rtc_v2f2,
+    not(any(
+        stm32f4, stm32l4, stm32l0, stm32wb, stm32g4, stm32g0, stm32l5, stm32h5, stm32u5, stm32u0
+    ))
+))]
+compile_error!("time-driver-rtc is not supported on this chip");
+
+use core::cell::{Cell, RefCell};
+
+use critical_section::CriticalSection;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_time_driver::{Driver, TICK_HZ};
+use embassy_time_queue_utils::Queue;
+
+use crate::interrupt::typelevel::Interrupt;
+use crate::pac::rtc::vals::Wucksel;
+use crate::pac::rtc::Rtc as Regs;
+use crate::peripherals::RTC;
+#[cfg(feature = "low-power")]
+use crate::rtc::Rtc;
+use crate::rtc::{bcd2_to_byte, SealedInstance};
+use crate::{interrupt, rcc};
+
+#[cfg(not(stm32u0))]
+foreach_interrupt! {
+    (RTC, rtc, $block:ident, WKUP, $irq:ident) => {
+        #[cfg(feature = "rt")]
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+}
+
+#[cfg(stm32u0)]
+foreach_interrupt! {
+    (RTC, rtc, $block:ident, TAMP, $irq:ident) => {
+        #[cfg(feature = "rt")]
+        #[interrupt]
+        fn $irq() {
+            DRIVER.on_interrupt()
+        }
+    };
+}
+
+fn regs() -> Regs {
+    <RTC as SealedInstance>::regs()
+}
+
+/// Longest wakeup timer period with the 1 Hz clock, in seconds.
+const MAX_WAKEUP_SECONDS: u64 = 1 << 16;
+
+/// Run `f` with the RTC registers write protection disabled.
+fn unlocked<R>(f: impl FnOnce(Regs) -> R) -> R {
+    let r = regs();
+    #[cfg(any(
+        rtc_v2f0, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+    ))]
+    {
+        r.wpr().write(|w| w.set_key(0xca));
+        r.wpr().write(|w| w.set_key(0x53));
+    }
+    #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+    {
+        use crate::pac::rtc::vals::Key;
+        r.wpr().write(|w| w.set_key(Key::DEACTIVATE1));
+        r.wpr().write(|w| w.set_key(Key::DEACTIVATE2));
+    }
+
+    let res = f(r);
+
+    #[cfg(any(
+        rtc_v2f0, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+    ))]
+    r.wpr().write(|w| w.set_key(0xff));
+    #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+    r.wpr().write(|w| w.set_key(crate::pac::rtc::vals::Key::ACTIVATE));
+    res
+}
+
+/// Number of days from 2000-01-01 to the given date of the 21st century.
+fn days_since_2000(year: u8, month: u8, day: u8) -> u64 {
+    const DAYS_BEFORE_MONTH: [u16; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+
+    let year = year as u64;
+    // 2000 is a leap year, so the years before `year` contain `(year + 3) / 4` leap days.
+    let mut days = year * 365 + (year + 3) / 4;
+    days += DAYS_BEFORE_MONTH[(month.clamp(1, 12) - 1) as usize] as u64;
+    if month > 2 && year % 4 == 0 {
+        days += 1;
+    }
+    days + day.max(1) as u64 - 1
+}
+
+fn clear_wakeup_flag(r: Regs) {
+    #[cfg(any(
+        rtc_v2f0, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+    ))]
+    r.isr().modify(|w| w.set_wutf(false));
+    #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+    r.scr().write(|w| w.set_cwutf(crate::pac::rtc::vals::Calrf::CLEAR));
+
+    // The wakeup event is routed through a configurable EXTI line on these chips, see
+    // `Rtc::stop_wakeup_alarm`.
+    #[cfg(any(exti_v1, stm32h7, stm32wb))]
+    crate::pac::EXTI
+        .pr(0)
+        .modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
+}
+
+/// Program the wakeup timer to fire in `ticks` ticks, or earlier if that is too far away.
+fn start_wakeup_timer(ticks: u64) {
+    let rtc_hz = TICK_HZ * (regs().prer().read().prediv_a() as u64 + 1);
+
+    // RTCCLK / 2 gives a resolution of a few ticks for up to a few seconds. Longer periods use
+    // the 1 Hz clock and wake up early, the remaining time is then scheduled with RTCCLK / 2.
+    let fast_ticks = ticks * rtc_hz / 2 / TICK_HZ;
+    let (wucksel, wut) = if fast_ticks <= u16::MAX as u64 {
+        (Wucksel::DIV2, fast_ticks.max(1) - 1)
+    } else {
+        let seconds = (ticks / TICK_HZ).clamp(1, MAX_WAKEUP_SECONDS);
+        (Wucksel::from_bits(0b100), seconds - 1)
+    };
+
+    unlocked(|r| {
+        r.cr().modify(|w| w.set_wute(false));
+        #[cfg(any(
+            rtc_v2f0, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+        ))]
+        while !r.isr().read().wutwf() {}
+        #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+        while !r.icsr().read().wutwf() {}
+
+        clear_wakeup_flag(r);
+        r.cr().modify(|w| w.set_wucksel(wucksel));
+        r.wutr().write(|w| w.set_wut(wut as u16));
+        r.cr().modify(|w| {
+            w.set_wutie(true);
+            w.set_wute(true);
+        });
+    });
+}
+
+struct AlarmState {
+    timestamp: Cell<u64>,
+}
+
+unsafe impl Send for AlarmState {}
+
+impl AlarmState {
+    const fn new() -> Self {
+        Self {
+            timestamp: Cell::new(u64::MAX),
+        }
+    }
+}
+
+pub(crate) struct RtcDriver {
+    alarm: Mutex<CriticalSectionRawMutex, AlarmState>,
+    queue: Mutex<CriticalSectionRawMutex, RefCell<Queue>>,
+}
+
+embassy_time_driver::time_driver_impl!(static DRIVER: RtcDriver = RtcDriver {
+    alarm: Mutex::const_new(CriticalSectionRawMutex::new(), AlarmState::new()),
+    queue: Mutex::new(RefCell::new(Queue::new()))
+});
+
+impl RtcDriver {
+    fn init(&'static self, cs: critical_section::CriticalSection) {
+        #[cfg(not(stm32l0))]
+        rcc::enable_and_reset_with_cs::<RTC>(cs);
+        #[cfg(stm32l0)]
+        let _ = cs;
+
+        let rtc_hz = unwrap!(unsafe { rcc::get_freqs() }.rtc.to_hertz()).0;
+        let div = rtc_hz / TICK_HZ as u32;
+        if div * TICK_HZ as u32 != rtc_hz || div == 0 || div > 128 || TICK_HZ > 32768 {
+            panic!("RTC clock {} Hz is not a multiple (up to 128) of TICK_HZ", rtc_hz);
+        }
+        let prediv_a = (div - 1) as u8;
+        let prediv_s = (TICK_HZ - 1) as u16;
+
+        // The calendar keeps running across resets and Standby, only stop it if the prescalers
+        // have to change, which is the case after a backup domain reset.
+        let prer = regs().prer().read();
+        unlocked(|r| {
+            if prer.prediv_a() != prediv_a || prer.prediv_s() != prediv_s {
+                #[cfg(any(
+                    rtc_v2f0, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+                ))]
+                {
+                    r.isr().modify(|w| w.set_init(true));
+                    while !r.isr().read().initf() {}
+                }
+                #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+                {
+                    r.icsr().modify(|w| w.set_init(true));
+                    while !r.icsr().read().initf() {}
+                }
+
+                r.prer().modify(|w| {
+                    w.set_prediv_s(prediv_s);
+                    w.set_prediv_a(prediv_a);
+                });
+
+                #[cfg(any(
+                    rtc_v2f0, rtc_v2f3, rtc_v2f4, rtc_v2f7, rtc_v2h7, rtc_v2l0, rtc_v2l1, rtc_v2l4, rtc_v2wb
+                ))]
+                r.isr().modify(|w| w.set_init(false));
+                #[cfg(any(rtc_v3, rtc_v3u5, rtc_v3l5))]
+                r.icsr().modify(|w| w.set_init(false));
+            }
+
+            // Read the counters directly, without waiting for the shadow registers to be updated.
+            r.cr().modify(|w| w.set_bypshad(true));
+        });
+
+        #[cfg(not(any(stm32u5, stm32u0)))]
+        {
+            use crate::pac::EXTI;
+            EXTI.rtsr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
+
+            #[cfg(not(stm32wb))]
+            EXTI.imr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
+            #[cfg(stm32wb)]
+            EXTI.cpu(0).imr(0).modify(|w| w.set_line(RTC::EXTI_WAKEUP_LINE, true));
+        }
+        #[cfg(stm32u5)]
+        {
+            use crate::pac::RCC;
+            RCC.srdamr().modify(|w| w.set_rtcapbamen(true));
+            RCC.apb3smenr().modify(|w| w.set_rtcapbsmen(true));
+        }
+
+        // Nothing is scheduled yet, wake up with the longest period to keep the alarm logic simple.
+        start_wakeup_timer(MAX_WAKEUP_SECONDS * TICK_HZ);
+
+        <RTC as SealedInstance>::WakeupInterrupt::unpend();
+        unsafe { <RTC as SealedInstance>::WakeupInterrupt::enable() };
+    }
+
+    fn on_interrupt(&self) {
+        critical_section::with(|cs| {
+            unlocked(clear_wakeup_flag);
+
+            // The wakeup timer may fire before the alarm when it is far away, in that case the
+            // remaining time is scheduled again.
+            let timestamp = self.alarm.borrow(cs).timestamp.get();
+            if timestamp <= self.now() {
+                self.trigger_alarm(cs);
+            } else if !self.set_alarm(cs, timestamp) {
+                self.trigger_alarm(cs);
+            }
+        })
+    }
+
+    fn trigger_alarm(&self, cs: CriticalSection) {
+        let mut next = self.queue.borrow(cs).borrow_mut().next_expiration(self.now());
+        while !self.set_alarm(cs, next) {
+            next = self.queue.borrow(cs).borrow_mut().next_expiration(self.now());
+        }
+    }
+
+    /*
+        Low-power public functions: all create a critical section
+    */
+    #[cfg(feature = "low-power")]
+    /// The RTC keeps running in Stop mode, it is not needed to keep track of time.
+    pub(crate) fn set_rtc(&self, _rtc: &'static Rtc) {}
+
+    #[cfg(feature = "low-power")]
+    /// The minimum pause time beyond which the executor will enter a low-power state.
+    pub(crate) const MIN_STOP_PAUSE: embassy_time::Duration = embassy_time::Duration::from_millis(1);
+
+    #[cfg(feature = "low-power")]
+    /// Check whether the next alarm is far enough in the future to enter a low-power state.
+    ///
+    /// The RTC itself is not paused since it keeps running in Stop mode.
+    pub(crate) fn pause_time(&self) -> Result<(), ()> {
+        critical_section::with(|cs| {
+            let now = self.now();
+            let until = self.alarm.borrow(cs).timestamp.get().saturating_sub(now);
+            if embassy_time::Duration::from_ticks(until) < Self::MIN_STOP_PAUSE {
+                Err(())
+            } else {
+                Ok(())
+            }
+        })
+    }
+
+    #[cfg(feature = "low-power")]
+    /// Nothing to do, see [`Self::pause_time`].
+    pub(crate) fn resume_time(&self) {}
+
+    fn set_alarm(&self, cs: CriticalSection, timestamp: u64) -> bool {
+        self.alarm.borrow(cs).timestamp.set(timestamp);
+
+        let t = self.now();
+        if timestamp <= t {
+            // If alarm timestamp has passed the alarm will not fire.
+            // Disarm the alarm and return `false` to indicate that.
+            self.alarm.borrow(cs).timestamp.set(u64::MAX);
+
+            return false;
+        }
+
+        start_wakeup_timer(timestamp - t);
+
+        // Starting the wakeup timer takes a few RTC clock cycles, reevaluate if the alarm
+        // timestamp is still in the future.
+        let t = self.now();
+        if timestamp <= t {
+            // If alarm timestamp has passed since we set it, we have a race condition and
+            // the alarm may or may not have fired.
+            // Disarm the alarm and return `false` to indicate that.
+            // It is the caller's responsibility to handle this ambiguity.
+            self.alarm.borrow(cs).timestamp.set(u64::MAX);
+
+            return false;
+        }
+
+        // We're confident the alarm will ring in the future.
+        true
+    }
+}
+
+impl Driver for RtcDriver {
+    fn now(&self) -> u64 {
+        let r = regs();
+        let prediv_s = r.prer().read().prediv_s() as u64;
+
+        // The calendar is only updated when the subsecond counter reloads, so the time and date
+        // are consistent if it didn't change while they were read.
+        let mut ss = r.ssr().read().ss();
+        loop {
+            let tr = r.tr().read();
+            let dr = r.dr().read();
+            let ss_after = r.ssr().read().ss();
+            if ss != ss_after {
+                ss = ss_after;
+                continue;
+            }
+
+            let days = days_since_2000(
+                bcd2_to_byte((dr.yt(), dr.yu())),
+                bcd2_to_byte((dr.mt() as u8, dr.mu())),
+                bcd2_to_byte((dr.dt(), dr.du())),
+            );
+            let seconds = days * 86400
+                + bcd2_to_byte((tr.ht(), tr.hu())) as u64 * 3600
+                + bcd2_to_byte((tr.mnt(), tr.mnu())) as u64 * 60
+                + bcd2_to_byte((tr.st(), tr.su())) as u64;
+
+            // The subsecond counter counts down from PREDIV_S.
+            return seconds * TICK_HZ + prediv_s.saturating_sub(ss as u64);
+        }
+    }
+
+    fn schedule_wake(&self, at: u64, waker: &core::task::Waker) {
+        critical_section::with(|cs| {
+            let mut queue = self.queue.borrow(cs).borrow_mut();
+
+            if queue.schedule_wake(at, waker) {
+                let mut next = queue.next_expiration(self.now());
+                while !self.set_alarm(cs, next) {
+                    next = queue.next_expiration(self.now());
+                }
+            }
+        })
+    }
+}
+
+#[cfg(feature = "low-power")]
+pub(crate) fn get_driver() -> &'static RtcDriver {
+    &DRIVER
+}
+
+pub(crate) fn init(cs: CriticalSection) {
+    DRIVER.init(cs)
+}
+
+/// The RTC is clocked independently of the system clock, which is reconfigured by `f`.
+pub(crate) fn reinit(_cs: CriticalSection, f: impl FnOnce()) {
+    f()
+}
@@ -7,10 +7,11 @@ use stm32_metapac::timer::vals::Ckd;
 use super::low_level::{CountingMode, OutputPolarity, Timer};
 use super::simple_pwm::{Ch1, Ch2, Ch3, Ch4, PwmPin};
 use super::{
-    AdvancedInstance4Channel, Channel, Channel1ComplementaryPin, Channel2ComplementaryPin, Channel3ComplementaryPin,
-    Channel4ComplementaryPin,
+    AdvancedInstance4Channel, CaptureCompareInterruptHandler, Channel, Channel1ComplementaryPin,
+    Channel2ComplementaryPin, Channel3ComplementaryPin, Channel4ComplementaryPin, UpdateInterruptHandler,
 };
 use crate::gpio::{AnyPin, OutputType};
+use crate::interrupt::typelevel::{Binding, Interrupt};
 use crate::time::Hertz;
 use crate::timer::low_level::OutputCompareMode;
 use crate::Peri;
@@ -52,6 +53,7 @@ complementary_channel_impl!(new_ch4, Ch4, Channel4ComplementaryPin);
 /// PWM driver with support for standard and complementary outputs.
 pub struct ComplementaryPwm<'d, T: AdvancedInstance4Channel> {
     inner: Timer<'d, T>,
+    interrupts: bool,
 }
 
 impl<'d, T: AdvancedInstance4Channel> ComplementaryPwm<'d, T> {
@@ -73,8 +75,42 @@ impl<'d, T: AdvancedInstance4Channel> ComplementaryPwm<'d, T> {
         Self::new_inner(tim, freq, counting_mode)
     }
 
+    /// Create a new complementary PWM driver, with the timer interrupts needed by
+    /// [`Self::wait_for_update`] and [`Self::wait_for_compare`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_interrupts(
+        tim: Peri<'d, T>,
+        _ch1: Option<PwmPin<'d, T, Ch1>>,
+        _ch1n: Option<ComplementaryPwmPin<'d, T, Ch1>>,
+        _ch2: Option<PwmPin<'d, T, Ch2>>,
+        _ch2n: Option<ComplementaryPwmPin<'d, T, Ch2>>,
+        _ch3: Option<PwmPin<'d, T, Ch3>>,
+        _ch3n: Option<ComplementaryPwmPin<'d, T, Ch3>>,
+        _ch4: Option<PwmPin<'d, T, Ch4>>,
+        _ch4n: Option<ComplementaryPwmPin<'d, T, Ch4>>,
+        _irq: impl Binding<T::UpdateInterrupt, UpdateInterruptHandler<T>>
+            + Binding<T::CaptureCompareInterrupt, CaptureCompareInterruptHandler<T>>
+            + 'd,
+        freq: Hertz,
+        counting_mode: CountingMode,
+    ) -> Self {
+        let mut this = Self::new_inner(tim, freq, counting_mode);
+
+        // enable NVIC interrupts
+        T::UpdateInterrupt::unpend();
+        unsafe { T::UpdateInterrupt::enable() };
+        T::CaptureCompareInterrupt::unpend();
+        unsafe { T::CaptureCompareInterrupt::enable() };
+        this.interrupts = true;
+
+        this
+    }
+
     fn new_inner(tim: Peri<'d, T>, freq: Hertz, counting_mode: CountingMode) -> Self {
-        let mut this = Self { inner: Timer::new(tim) };
+        let mut this = Self {
+            inner: Timer::new(tim),
+            interrupts: false,
+        };
 
         this.inner.set_counting_mode(counting_mode);
         this.set_frequency(freq);
@@ -145,6 +181,22 @@ impl<'d, T: AdvancedInstance4Channel> ComplementaryPwm<'d, T> {
         self.inner.set_dead_time_clock_division(ckd);
         self.inner.set_dead_time_value(value);
     }
+
+    /// Wait for the next update event, i.e. the start of the next PWM period.
+    ///
+    /// This panics if the driver wasn't created with [`Self::new_with_interrupts`].
+    pub async fn wait_for_update(&mut self) {
+        assert!(self.interrupts, "timer interrupts are not bound");
+        self.inner.wait_for_update().await
+    }
+
+    /// Wait until the counter matches the duty cycle of the given channel.
+    ///
+    /// This panics if the driver wasn't created with [`Self::new_with_interrupts`].
+    pub async fn wait_for_compare(&mut self, channel: Channel) {
+        assert!(self.interrupts, "timer interrupts are not bound");
+        self.inner.wait_for_compare(channel).await
+    }
 }
 
 impl<'d, T: AdvancedInstance4Channel> embedded_hal_02::Pwm for ComplementaryPwm<'d, T> {
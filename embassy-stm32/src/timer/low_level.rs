@@ -6,13 +6,17 @@
 //!
 //! The available functionality depends on the timer type.
 
+use core::future::poll_fn;
 use core::mem::ManuallyDrop;
+use core::task::Poll;
 
+use embassy_hal_internal::drop::OnDrop;
 use embassy_hal_internal::Peri;
 // Re-export useful enums
-pub use stm32_metapac::timer::vals::{FilterValue, Sms as SlaveMode, Ts as TriggerSource};
+pub use stm32_metapac::timer::vals::{FilterValue, Mms as MasterMode, Sms as SlaveMode, Ts as TriggerSource};
 
 use super::*;
+use crate::interrupt::typelevel::Interrupt;
 use crate::pac::timer::vals;
 use crate::rcc;
 use crate::time::Hertz;
@@ -233,6 +237,15 @@ impl<'d, T: CoreInstance> Timer<'d, T> {
         self.regs_core().cnt().write(|r| r.set_cnt(0));
     }
 
+    /// Get the counter value.
+    pub fn get_counter(&self) -> u32 {
+        match T::BITS {
+            TimerBits::Bits16 => self.regs_core().cnt().read().cnt() as u32,
+            #[cfg(not(stm32l0))]
+            TimerBits::Bits32 => self.regs_gp32_unchecked().cnt().read(),
+        }
+    }
+
     /// get the capability of the timer
     pub fn bits(&self) -> TimerBits {
         T::BITS
@@ -331,6 +344,39 @@ impl<'d, T: CoreInstance> Timer<'d, T> {
         self.regs_core().dier().modify(|r| r.set_uie(enable));
     }
 
+    /// Wait for the next update event.
+    ///
+    /// This requires [`UpdateInterruptHandler`] to be bound to the update interrupt of the timer,
+    /// which this method enables.
+    pub async fn wait_for_update(&self) {
+        let regs = self.regs_core();
+
+        // Bits in SR are "write 0 to clear", so only clear UIF and leave other flags untouched.
+        regs.sr().write(|w| {
+            w.0 = !0;
+            w.set_uif(false);
+        });
+
+        T::UpdateInterrupt::unpend();
+        unsafe { T::UpdateInterrupt::enable() };
+
+        let _on_drop = OnDrop::new(|| regs.dier().modify(|w| w.set_uie(false)));
+
+        regs.dier().modify(|w| w.set_uie(true));
+
+        // The interrupt handler disables UIE once the update event happened.
+        poll_fn(|cx| {
+            T::state().up_waker.register(cx.waker());
+
+            if regs.dier().read().uie() {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+
     /// Enable/disable autoreload preload.
     pub fn set_autoreload_preload(&self, enable: bool) {
         self.regs_core().cr1().modify(|r| r.set_arpe(enable));
@@ -388,6 +434,11 @@ impl<'d, T: BasicNoCr2Instance> Timer<'d, T> {
 }
 
 impl<'d, T: BasicInstance> Timer<'d, T> {
+    /// Set the master mode, selecting the event sent as trigger output (TRGO) to other timers.
+    pub fn set_master_mode(&self, mms: MasterMode) {
+        self.regs_basic().cr2().modify(|r| r.set_mms(mms));
+    }
+
     /// Get access to the Baisc 16bit timer registers.
     ///
     /// Note: This works even if the timer is more capable, because registers
@@ -612,6 +663,39 @@ impl<'d, T: GeneralInstance4Channel> Timer<'d, T> {
         self.get_compare_value(channel)
     }
 
+    /// Wait for the next compare (or capture) event on the given channel.
+    ///
+    /// This requires [`CaptureCompareInterruptHandler`] to be bound to the capture/compare
+    /// interrupt of the timer, which this method enables.
+    pub async fn wait_for_compare(&self, channel: Channel) {
+        let regs = self.regs_gp16();
+
+        // Bits in SR are "write 0 to clear", so only clear CCxIF and leave other flags untouched.
+        regs.sr().write(|w| {
+            w.0 = !0;
+            w.set_ccif(channel.index(), false);
+        });
+
+        T::CaptureCompareInterrupt::unpend();
+        unsafe { T::CaptureCompareInterrupt::enable() };
+
+        let _on_drop = OnDrop::new(|| regs.dier().modify(|w| w.set_ccie(channel.index(), false)));
+
+        regs.dier().modify(|w| w.set_ccie(channel.index(), true));
+
+        // The interrupt handler disables CCxIE once the compare event happened.
+        poll_fn(|cx| {
+            T::state().cc_waker[channel.index()].register(cx.waker());
+
+            if regs.dier().read().ccie(channel.index()) {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+
     /// Set output compare preload.
     pub fn set_output_compare_preload(&self, channel: Channel, preload: bool) {
         let channel_index = channel.index();
@@ -4,10 +4,15 @@ use core::marker::PhantomData;
 use core::mem::ManuallyDrop;
 
 use super::low_level::{CountingMode, OutputCompareMode, OutputPolarity, Timer};
-use super::{Channel, Channel1Pin, Channel2Pin, Channel3Pin, Channel4Pin, GeneralInstance4Channel, TimerBits};
+use super::{
+    CaptureCompareInterruptHandler, Channel, Channel1Pin, Channel2Pin, Channel3Pin, Channel4Pin,
+    GeneralInstance4Channel, TimerBits, UpdateInterruptHandler,
+};
+use crate::dma::ChannelAndRequest;
 #[cfg(gpio_v2)]
 use crate::gpio::Pull;
 use crate::gpio::{AfType, AnyPin, OutputType, Speed};
+use crate::interrupt::typelevel::{Binding, Interrupt};
 use crate::time::Hertz;
 use crate::Peri;
 
@@ -92,6 +97,7 @@ channel_impl!(new_ch4, new_ch4_with_config, Ch4, Channel4Pin);
 pub struct SimplePwmChannel<'d, T: GeneralInstance4Channel> {
     timer: ManuallyDrop<Timer<'d, T>>,
     channel: Channel,
+    interrupts: bool,
 }
 
 // TODO: check for RMW races
@@ -175,6 +181,14 @@ impl<'d, T: GeneralInstance4Channel> SimplePwmChannel<'d, T> {
     pub fn set_output_compare_mode(&mut self, mode: OutputCompareMode) {
         self.timer.set_output_compare_mode(self.channel, mode);
     }
+
+    /// Wait until the counter matches the duty cycle of this channel.
+    ///
+    /// This panics if the driver wasn't created with [`SimplePwm::new_with_interrupts`].
+    pub async fn wait_for_compare(&mut self) {
+        assert!(self.interrupts, "timer interrupts are not bound");
+        self.timer.wait_for_compare(self.channel).await
+    }
 }
 
 /// A group of four [`SimplePwmChannel`]s, obtained from [`SimplePwm::split`].
@@ -192,6 +206,7 @@ pub struct SimplePwmChannels<'d, T: GeneralInstance4Channel> {
 /// Simple PWM driver.
 pub struct SimplePwm<'d, T: GeneralInstance4Channel> {
     inner: Timer<'d, T>,
+    interrupts: bool,
 }
 
 impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
@@ -208,8 +223,38 @@ impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
         Self::new_inner(tim, freq, counting_mode)
     }
 
+    /// Create a new simple PWM driver, with the timer interrupts needed by
+    /// [`Self::wait_for_update`] and [`Self::wait_for_compare`].
+    #[allow(clippy::too_many_arguments)]
+    pub fn new_with_interrupts(
+        tim: Peri<'d, T>,
+        _ch1: Option<PwmPin<'d, T, Ch1>>,
+        _ch2: Option<PwmPin<'d, T, Ch2>>,
+        _ch3: Option<PwmPin<'d, T, Ch3>>,
+        _ch4: Option<PwmPin<'d, T, Ch4>>,
+        _irq: impl Binding<T::UpdateInterrupt, UpdateInterruptHandler<T>>
+            + Binding<T::CaptureCompareInterrupt, CaptureCompareInterruptHandler<T>>
+            + 'd,
+        freq: Hertz,
+        counting_mode: CountingMode,
+    ) -> Self {
+        let mut this = Self::new_inner(tim, freq, counting_mode);
+
+        // enable NVIC interrupts
+        T::UpdateInterrupt::unpend();
+        unsafe { T::UpdateInterrupt::enable() };
+        T::CaptureCompareInterrupt::unpend();
+        unsafe { T::CaptureCompareInterrupt::enable() };
+        this.interrupts = true;
+
+        this
+    }
+
     fn new_inner(tim: Peri<'d, T>, freq: Hertz, counting_mode: CountingMode) -> Self {
-        let mut this = Self { inner: Timer::new(tim) };
+        let mut this = Self {
+            inner: Timer::new(tim),
+            interrupts: false,
+        };
 
         this.inner.set_counting_mode(counting_mode);
         this.set_frequency(freq);
@@ -234,6 +279,7 @@ impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
         SimplePwmChannel {
             timer: unsafe { self.inner.clone_unchecked() },
             channel,
+            interrupts: self.interrupts,
         }
     }
 
@@ -284,11 +330,13 @@ impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
         'd: 'static,
     {
         // without this, the timer would be disabled at the end of this function
+        let interrupts = self.interrupts;
         let timer = ManuallyDrop::new(self.inner);
 
         let ch = |channel| SimplePwmChannel {
             timer: unsafe { timer.clone_unchecked() },
             channel,
+            interrupts,
         };
 
         SimplePwmChannels {
@@ -322,6 +370,22 @@ impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
         max as u16 + 1
     }
 
+    /// Wait for the next update event, i.e. the start of the next PWM period.
+    ///
+    /// This panics if the driver wasn't created with [`Self::new_with_interrupts`].
+    pub async fn wait_for_update(&mut self) {
+        assert!(self.interrupts, "timer interrupts are not bound");
+        self.inner.wait_for_update().await
+    }
+
+    /// Wait until the counter matches the duty cycle of the given channel.
+    ///
+    /// This panics if the driver wasn't created with [`Self::new_with_interrupts`].
+    pub async fn wait_for_compare(&mut self, channel: Channel) {
+        assert!(self.interrupts, "timer interrupts are not bound");
+        self.inner.wait_for_compare(channel).await
+    }
+
     /// Generate a sequence of PWM waveform
     ///
     /// Note:
@@ -355,14 +419,16 @@ impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
                 ..Default::default()
             };
 
-            Transfer::new_write(
-                dma,
-                req,
-                duty,
-                self.inner.regs_1ch().ccr(channel.index()).as_ptr() as *mut u16,
-                dma_transfer_option,
+            unwrap!(
+                Transfer::new_write(
+                    dma,
+                    req,
+                    duty,
+                    self.inner.regs_1ch().ccr(channel.index()).as_ptr() as *mut u16,
+                    dma_transfer_option,
+                )
+                .await
             )
-            .await
         };
 
         // restore output compare state
@@ -450,14 +516,16 @@ impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
                 ..Default::default()
             };
 
-            Transfer::new_write(
-                dma,
-                req,
-                duty,
-                self.inner.regs_gp16().dmar().as_ptr() as *mut u16,
-                dma_transfer_option,
+            unwrap!(
+                Transfer::new_write(
+                    dma,
+                    req,
+                    duty,
+                    self.inner.regs_gp16().dmar().as_ptr() as *mut u16,
+                    dma_transfer_option,
+                )
+                .await
             )
-            .await
         };
 
         if !original_update_dma_state {
@@ -466,6 +534,121 @@ impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
     }
 }
 
+impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
+    /// Create a [`DutyStream`] updating channels `first` up to and including `last` from memory
+    /// at every update event.
+    ///
+    /// The channels are enabled, and the timer update DMA request stays enabled until the
+    /// stream is dropped, which restores the DMA burst configuration and update DMA request.
+    ///
+    /// Note:
+    /// you will need to provide corresponding TIMx_UP DMA channel to use this method.
+    pub fn duty_stream<'a>(
+        &'a mut self,
+        dma: Peri<'a, impl super::UpDma<T>>,
+        first: Channel,
+        last: Channel,
+    ) -> DutyStream<'a, 'd, T> {
+        assert!(first.index() <= last.index());
+
+        let original_dcr = self.inner.regs_gp16().dcr().read();
+        let original_update_dma_state = self.inner.get_update_dma_state();
+        if first.index() != last.index() {
+            let cr1_addr = self.inner.regs_gp16().cr1().as_ptr() as u32;
+            let ccrx_addr = self.inner.regs_gp16().ccr(first.index()).as_ptr() as u32;
+            self.inner.regs_gp16().dcr().modify(|w| {
+                w.set_dba(((ccrx_addr - cr1_addr) / 4) as u8);
+                w.set_dbl((last.index() - first.index()) as u8);
+            });
+        }
+
+        for index in first.index()..=last.index() {
+            self.inner.enable_channel(CHANNELS[index], true);
+        }
+
+        self.inner.enable_update_dma(true);
+
+        DutyStream {
+            dma: new_dma_nonopt!(dma),
+            pwm: self,
+            first,
+            last,
+            original_dcr,
+            original_update_dma_state,
+        }
+    }
+}
+
+const CHANNELS: [Channel; 4] = [Channel::Ch1, Channel::Ch2, Channel::Ch3, Channel::Ch4];
+
+/// Stream of duty cycle values written to one or more PWM channels by DMA.
+///
+/// Obtained from [`SimplePwm::duty_stream`]. At every update event, the next value of the buffer
+/// is written to the compare register of each channel of the stream. This is the usual way of
+/// driving WS2812 LEDs or large banks of servos without bit-banging.
+pub struct DutyStream<'a, 'd, T: GeneralInstance4Channel> {
+    pwm: &'a mut SimplePwm<'d, T>,
+    dma: ChannelAndRequest<'a>,
+    first: Channel,
+    last: Channel,
+    original_dcr: crate::pac::timer::regs::Dcr,
+    original_update_dma_state: bool,
+}
+
+impl<'a, 'd, T: GeneralInstance4Channel> DutyStream<'a, 'd, T> {
+    /// Number of channels updated at each update event.
+    pub fn channel_count(&self) -> usize {
+        self.last.index() - self.first.index() + 1
+    }
+
+    /// Get max duty value.
+    pub fn max_duty_cycle(&self) -> u16 {
+        self.pwm.max_duty_cycle()
+    }
+
+    /// Stream duty values to the channels.
+    ///
+    /// With several channels, `duties` is a flattened array with one row per update event and one
+    /// column per channel, see [`SimplePwm::waveform_up_multi_channel`].
+    ///
+    /// The channels keep the last value of the buffer once the transfer completes, so the buffer
+    /// should end with the idle duty (for example 0 for WS2812 LEDs).
+    pub async fn write_duties(&mut self, duties: &[u16]) {
+        assert!(duties.len() % self.channel_count() == 0);
+
+        use crate::dma::TransferOptions;
+        #[cfg(not(any(bdma, gpdma)))]
+        use crate::dma::{Burst, FifoThreshold};
+
+        let dma_transfer_option = TransferOptions {
+            #[cfg(not(any(bdma, gpdma)))]
+            fifo_threshold: Some(FifoThreshold::Full),
+            #[cfg(not(any(bdma, gpdma)))]
+            mburst: if self.channel_count() == 1 {
+                Burst::Incr8
+            } else {
+                Burst::Incr4
+            },
+            ..Default::default()
+        };
+
+        let dst = if self.channel_count() == 1 {
+            self.pwm.inner.regs_gp16().ccr(self.first.index()).as_ptr() as *mut u16
+        } else {
+            self.pwm.inner.regs_gp16().dmar().as_ptr() as *mut u16
+        };
+
+        unwrap!(unsafe { self.dma.write(duties, dst, dma_transfer_option).await });
+    }
+}
+
+impl<'a, 'd, T: GeneralInstance4Channel> Drop for DutyStream<'a, 'd, T> {
+    fn drop(&mut self) {
+        self.pwm.inner.enable_update_dma(self.original_update_dma_state);
+        self.pwm.inner.regs_gp16().dcr().write_value(self.original_dcr);
+    }
+}
+
 macro_rules! impl_waveform_chx {
     ($fn_name:ident, $dma_ch:ident, $cc_ch:ident) => {
         impl<'d, T: GeneralInstance4Channel> SimplePwm<'d, T> {
@@ -511,14 +694,16 @@ macro_rules! impl_waveform_chx {
 
                     match self.inner.bits() {
                         TimerBits::Bits16 => {
-                            Transfer::new_write(
-                                dma,
-                                req,
-                                duty,
-                                self.inner.regs_gp16().ccr(cc_channel.index()).as_ptr() as *mut u16,
-                                dma_transfer_option,
+                            unwrap!(
+                                Transfer::new_write(
+                                    dma,
+                                    req,
+                                    duty,
+                                    self.inner.regs_gp16().ccr(cc_channel.index()).as_ptr() as *mut u16,
+                                    dma_transfer_option,
+                                )
+                                .await
                             )
-                            .await
                         }
                         #[cfg(not(any(stm32l0)))]
                         TimerBits::Bits32 => {
@@ -526,14 +711,16 @@ macro_rules! impl_waveform_chx {
                             panic!("unsupported timer bits");
 
                             #[cfg(any(bdma, gpdma))]
-                            Transfer::new_write(
-                                dma,
-                                req,
-                                duty,
-                                self.inner.regs_gp16().ccr(cc_channel.index()).as_ptr() as *mut u32,
-                                dma_transfer_option,
+                            unwrap!(
+                                Transfer::new_write(
+                                    dma,
+                                    req,
+                                    duty,
+                                    self.inner.regs_gp16().ccr(cc_channel.index()).as_ptr() as *mut u32,
+                                    dma_transfer_option,
+                                )
+                                .await
                             )
-                            .await
                         }
                     };
                 };
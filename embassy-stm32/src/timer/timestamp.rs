@@ -0,0 +1,116 @@
+//! 64-bit free-running timestamp counter.
+//!
+//! Two timers are chained: the master timer counts at the requested tick frequency, and its
+//! update event is routed through TRGO to the slave timer, which counts the master overflows.
+//! The counter is independent of embassy-time and its tick rate, which makes it suitable for
+//! timestamping events with a fine resolution.
+
+use embassy_hal_internal::Peri;
+
+use super::low_level::{CountingMode, MasterMode, SlaveMode, Timer, TriggerSource};
+use super::{GeneralInstance4Channel, TimerBits};
+use crate::time::Hertz;
+
+fn bits_of(bits: TimerBits) -> u32 {
+    match bits {
+        TimerBits::Bits16 => 16,
+        #[cfg(not(stm32l0))]
+        TimerBits::Bits32 => 32,
+    }
+}
+
+/// 64-bit free-running timestamp counter.
+///
+/// With two 32-bit timers the counter is 64 bits wide and never wraps in practice. With 16-bit
+/// timers it is narrower and wraps after `2^(master bits + slave bits)` ticks, see
+/// [`Timestamp::wrap_ticks`].
+pub struct Timestamp<'d, M: GeneralInstance4Channel, S: GeneralInstance4Channel> {
+    master: Timer<'d, M>,
+    slave: Timer<'d, S>,
+}
+
+impl<'d, M: GeneralInstance4Channel, S: GeneralInstance4Channel> Timestamp<'d, M, S> {
+    /// Create a new timestamp counter, counting at `tick_freq`.
+    ///
+    /// `trigger` is the internal trigger input of the slave timer connected to the TRGO output of
+    /// the master timer. This connection is chip-specific, refer to the "TIMx internal trigger
+    /// connection" table of the reference manual.
+    pub fn new(master: Peri<'d, M>, slave: Peri<'d, S>, trigger: TriggerSource, tick_freq: Hertz) -> Self {
+        let mut master = Timer::new(master);
+        let slave = Timer::new(slave);
+
+        // The slave counts every master overflow.
+        slave.set_counting_mode(CountingMode::EdgeAlignedUp);
+        slave.set_max_compare_value(u32::MAX >> (32 - bits_of(S::BITS)));
+        slave.set_trigger_source(trigger);
+        slave.set_slave_mode(SlaveMode::EXT_CLOCK_MODE);
+        slave.reset();
+        slave.start();
+
+        master.set_counting_mode(CountingMode::EdgeAlignedUp);
+        master.set_tick_freq(tick_freq);
+        master.set_max_compare_value(u32::MAX >> (32 - bits_of(M::BITS)));
+        master.set_master_mode(MasterMode::UPDATE);
+        master.reset();
+        master.start();
+
+        Self { master, slave }
+    }
+
+    /// Get the tick frequency of the counter.
+    pub fn tick_frequency(&self) -> Hertz {
+        let regs = self.master.regs_core();
+        self.master.get_clock_frequency() / (regs.psc().read() as u32 + 1)
+    }
+
+    /// Number of ticks after which the counter wraps around, or `None` if it is 64 bits wide.
+    pub fn wrap_ticks(&self) -> Option<u64> {
+        let bits = bits_of(M::BITS) + bits_of(S::BITS);
+        if bits >= 64 {
+            None
+        } else {
+            Some(1 << bits)
+        }
+    }
+
+    /// Get the current timestamp, in ticks.
+    ///
+    /// The slave counter is read before and after the master counter. If it changed, the master
+    /// overflowed in between and the read is retried, so the result is always consistent.
+    pub fn now(&self) -> u64 {
+        let master_bits = bits_of(M::BITS);
+
+        loop {
+            let high = self.slave.get_counter();
+            let low = self.master.get_counter();
+            if self.slave.get_counter() == high {
+                return ((high as u64) << master_bits) | low as u64;
+            }
+        }
+    }
+
+    /// Get the current timestamp, in microseconds.
+    pub fn now_micros(&self) -> u64 {
+        self.ticks_to_nanos(self.now()) / 1_000
+    }
+
+    /// Convert a number of ticks to nanoseconds.
+    pub fn ticks_to_nanos(&self, ticks: u64) -> u64 {
+        let f = self.tick_frequency().0 as u128;
+        (ticks as u128 * 1_000_000_000 / f) as u64
+    }
+}
+
+impl<'d, M: GeneralInstance4Channel, S: GeneralInstance4Channel> Drop for Timestamp<'d, M, S> {
+    fn drop(&mut self) {
+        self.master.stop();
+        self.slave.stop();
+
+        self.master.enable_update_interrupt(false);
+        self.slave.enable_update_interrupt(false);
+        self.master.set_master_mode(MasterMode::RESET);
+        self.slave.set_slave_mode(SlaveMode::DISABLED);
+
+        // The peripheral clocks are disabled when the timers are dropped.
+    }
+}
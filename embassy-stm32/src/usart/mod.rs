@@ -13,7 +13,7 @@ use embassy_hal_internal::PeripheralType;
 use embassy_sync::waitqueue::AtomicWaker;
 use futures_util::future::{select, Either};
 
-use crate::dma::ChannelAndRequest;
+use crate::dma::{ChannelAndRequest, TransferError};
 use crate::gpio::{AfType, AnyPin, OutputType, Pull, SealedPin as _, Speed};
 use crate::interrupt::typelevel::Interrupt as _;
 use crate::interrupt::{self, Interrupt, InterruptExt};
@@ -303,6 +303,8 @@ pub enum Error {
     Parity,
     /// Buffer too large for DMA
     BufferTooLong,
+    /// DMA transfer error
+    Dma(TransferError),
 }
 
 impl core::fmt::Display for Error {
@@ -313,6 +315,7 @@ impl core::fmt::Display for Error {
             Self::Overrun => "RX Buffer Overrun",
             Self::Parity => "Parity Check Error",
             Self::BufferTooLong => "Buffer too large for DMA",
+            Self::Dma(_) => "DMA Transfer Error",
         };
 
         write!(f, "{}", message)
@@ -321,6 +324,12 @@ impl core::fmt::Display for Error {
 
 impl core::error::Error for Error {}
 
+impl From<TransferError> for Error {
+    fn from(error: TransferError) -> Self {
+        Self::Dma(error)
+    }
+}
+
 enum ReadCompletionEvent {
     // DMA Read transfer completed first
     DmaCompleted,
@@ -468,7 +477,7 @@ impl<'d> UartTx<'d, Async> {
         // If we don't assign future to a variable, the data register pointer
         // is held across an await and makes the future non-Send.
         let transfer = unsafe { ch.write(buffer, tdr(r), Default::default()) };
-        transfer.await;
+        transfer.await?;
         Ok(())
     }
 
@@ -859,7 +868,10 @@ impl<'d> UartRx<'d, Async> {
         // when transfer is dropped, it will stop the DMA request
         let r = match select(transfer, abort).await {
             // DMA transfer completed first
-            Either::Left(((), _)) => Ok(ReadCompletionEvent::DmaCompleted),
+            Either::Left((Ok(()), _)) => Ok(ReadCompletionEvent::DmaCompleted),
+
+            // DMA transfer failed
+            Either::Left((Err(e), _)) => Err(e.into()),
 
             // Idle line detected first
             Either::Right((Ok(()), transfer)) => Ok(ReadCompletionEvent::Idle(
@@ -1840,6 +1852,7 @@ impl embedded_hal_nb::serial::Error for Error {
             Self::Overrun => embedded_hal_nb::serial::ErrorKind::Overrun,
             Self::Parity => embedded_hal_nb::serial::ErrorKind::Parity,
             Self::BufferTooLong => embedded_hal_nb::serial::ErrorKind::Other,
+            Self::Dma(_) => embedded_hal_nb::serial::ErrorKind::Other,
         }
     }
 }
@@ -1951,9 +1964,7 @@ pub use buffered::*;
 pub use crate::usart::buffered::InterruptHandler as BufferedInterruptHandler;
 mod buffered;
 
-#[cfg(not(gpdma))]
 mod ringbuffered;
-#[cfg(not(gpdma))]
 pub use ringbuffered::RingBufferedUartRx;
 
 #[cfg(any(usart_v1, usart_v2))]
@@ -5,32 +5,75 @@
 mod _version;
 pub use _version::*;
 
+use core::cell::Cell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+
+use self::_version::SealedInstance as _;
 use crate::interrupt::typelevel::Interrupt;
 use crate::rcc;
 
-/// clock, power initialization stuff that's common for USB and OTG.
-fn common_init<T: Instance>() {
-    // Check the USB clock is enabled and running at exactly 48 MHz.
-    // frequency() will panic if not enabled
-    let freq = T::frequency();
+/// Suspend state of a USB instance.
+pub(crate) struct SuspendState {
+    suspended: AtomicBool,
+    hook: CriticalSectionMutex<Cell<Option<fn(bool)>>>,
+}
 
-    // On the H7RS, the USBPHYC embeds a PLL accepting one of the input frequencies listed below and providing 48MHz to OTG_FS and 60MHz to OTG_HS internally
-    #[cfg(any(stm32h7rs, all(stm32u5, peri_usb_otg_hs)))]
-    if ![16_000_000, 19_200_000, 20_000_000, 24_000_000, 26_000_000, 32_000_000].contains(&freq.0) {
-        panic!(
-            "USB clock should be one of 16, 19.2, 20, 24, 26, 32Mhz but is {} Hz. Please double-check your RCC settings.",
-            freq.0
-        )
+impl SuspendState {
+    pub(crate) const fn new() -> Self {
+        Self {
+            suspended: AtomicBool::new(false),
+            hook: CriticalSectionMutex::new(Cell::new(None)),
+        }
     }
-    // Check frequency is within the 0.25% tolerance allowed by the spec.
-    // Clock might not be exact 48Mhz due to rounding errors in PLL calculation, or if the user
-    // has tight clock restrictions due to something else (like audio).
-    #[cfg(not(any(stm32h7rs, all(stm32u5, peri_usb_otg_hs))))]
-    if freq.0.abs_diff(48_000_000) > 120_000 {
-        panic!(
-            "USB clock should be 48Mhz but is {} Hz. Please double-check your RCC settings.",
-            freq.0
-        )
+}
+
+/// Set a hook called from the interrupt of the USB instance `T` when its bus is suspended or resumed.
+///
+/// On suspend, the hook is called with `true` once the peripheral is in low-power mode and no
+/// longer needs its 48 MHz clock, so the hook may gate it. On resume, the hook is called with
+/// `false` before the peripheral leaves low-power mode, and must restore the 48 MHz clock if it
+/// was stopped, for example because the core was in Stop mode.
+///
+/// With the `low-power` feature, the USB peripheral doesn't prevent the executor from entering
+/// Stop mode while the bus is suspended. The USB wakeup event must then be routed to the core
+/// (on some chips through an EXTI line) for the device to resume.
+pub fn set_suspend_hook<T: Instance>(hook: fn(bool)) {
+    T::suspend_state().hook.lock(|h| h.set(Some(hook)));
+}
+
+/// Called from the interrupt handlers when the bus is suspended or resumed.
+fn on_suspend<T: Instance>(suspended: bool) {
+    let state = T::suspend_state();
+    if state.suspended.swap(suspended, Ordering::Relaxed) == suspended {
+        return;
+    }
+
+    #[cfg(feature = "low-power")]
+    critical_section::with(|cs| T::RCC_INFO.allow_stop_with_cs(suspended, cs));
+
+    if let Some(hook) = state.hook.lock(Cell::get) {
+        hook(suspended);
+    }
+}
+
+/// Get a USB serial number string unique to this device.
+///
+/// This is the 96-bit unique device ID encoded into 24 hexadecimal digits, which can be used
+/// directly as `embassy_usb::Config::serial_number`.
+#[cfg(uid)]
+pub fn serial_number() -> &'static str {
+    crate::uid::uid_hex()
+}
+
+/// clock, power initialization stuff that's common for USB and OTG.
+///
+/// `external_phy` skips the kernel clock check: an external ULPI PHY supplies its own 60 MHz clock
+/// to the core, so the 48 MHz USB clock is not needed.
+fn common_init<T: Instance>(external_phy: bool) {
+    if !external_phy {
+        check_clock::<T>();
     }
 
     #[cfg(any(stm32l4, stm32l5, stm32wb, stm32u0))]
@@ -107,3 +150,28 @@ fn common_init<T: Instance>() {
 
     rcc::enable_and_reset::<T>();
 }
+
+/// Check the USB clock is enabled and running at the frequency required by the PHY.
+fn check_clock<T: Instance>() {
+    // frequency() will panic if not enabled
+    let freq = T::frequency();
+
+    // On the H7RS, the USBPHYC embeds a PLL accepting one of the input frequencies listed below and providing 48MHz to OTG_FS and 60MHz to OTG_HS internally
+    #[cfg(any(stm32h7rs, all(stm32u5, peri_usb_otg_hs)))]
+    if ![16_000_000, 19_200_000, 20_000_000, 24_000_000, 26_000_000, 32_000_000].contains(&freq.0) {
+        panic!(
+            "USB clock should be one of 16, 19.2, 20, 24, 26, 32Mhz but is {} Hz. Please double-check your RCC settings.",
+            freq.0
+        )
+    }
+    // Check frequency is within the 0.25% tolerance allowed by the spec.
+    // Clock might not be exact 48Mhz due to rounding errors in PLL calculation, or if the user
+    // has tight clock restrictions due to something else (like audio).
+    #[cfg(not(any(stm32h7rs, all(stm32u5, peri_usb_otg_hs))))]
+    if freq.0.abs_diff(48_000_000) > 120_000 {
+        panic!(
+            "USB clock should be 48Mhz but is {} Hz. Please double-check your RCC settings.",
+            freq.0
+        )
+    }
+}
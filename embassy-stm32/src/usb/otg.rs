@@ -1,7 +1,14 @@
 use core::marker::PhantomData;
 
 use embassy_hal_internal::PeripheralType;
+#[cfg(feature = "time")]
+use embassy_usb_driver::host::{ChannelAllocError, DeviceEvent, HostError, Speed as UsbSpeed, UsbHostDriver};
+#[cfg(feature = "time")]
+use embassy_usb_driver::EndpointInfo;
 use embassy_usb_driver::{EndpointAddress, EndpointAllocError, EndpointType, Event, Unsupported};
+use embassy_usb_synopsys_otg::host::{on_host_interrupt as on_host_interrupt_impl, HostState};
+#[cfg(feature = "time")]
+use embassy_usb_synopsys_otg::host::{Channel, Host as OtgHost};
 use embassy_usb_synopsys_otg::otg_v1::vals::Dspd;
 use embassy_usb_synopsys_otg::otg_v1::Otg;
 pub use embassy_usb_synopsys_otg::Config;
@@ -16,6 +23,7 @@ use crate::rcc::{self, RccPeripheral};
 use crate::{interrupt, Peri};
 
 const MAX_EP_COUNT: usize = 9;
+const MAX_CHANNEL_COUNT: usize = embassy_usb_synopsys_otg::host::MAX_CHANNEL_COUNT;
 
 /// Interrupt handler.
 pub struct InterruptHandler<T: Instance> {
@@ -26,7 +34,28 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
     unsafe fn on_interrupt() {
         let r = T::regs();
         let state = T::state();
+
+        let ints = r.gintsts().read();
+        if ints.wkupint() || ints.usbrst() {
+            super::on_suspend::<T>(false);
+        }
+
         on_interrupt_impl(r, state, T::ENDPOINT_COUNT);
+
+        if ints.usbsusp() {
+            super::on_suspend::<T>(true);
+        }
+    }
+}
+
+/// Host mode interrupt handler.
+pub struct HostInterruptHandler<T: Instance> {
+    _phantom: PhantomData<T>,
+}
+
+impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for HostInterruptHandler<T> {
+    unsafe fn on_interrupt() {
+        on_host_interrupt_impl(T::regs(), T::host_state(), T::CHANNEL_COUNT);
     }
 }
 
@@ -58,7 +87,8 @@ impl<'d, T: Instance> Driver<'d, T> {
     /// # Arguments
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
-    /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// Must be large enough to fit all OUT endpoint max packet sizes, and the extra buffers
+    /// of double-buffered bulk endpoints (see [`Config::double_buffered_bulk`]).
     /// Endpoint allocation will fail if it is too small.
     pub fn new_fs(
         _peri: Peri<'d, T>,
@@ -94,7 +124,8 @@ impl<'d, T: Instance> Driver<'d, T> {
     /// # Arguments
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
-    /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// Must be large enough to fit all OUT endpoint max packet sizes, and the extra buffers
+    /// of double-buffered bulk endpoints (see [`Config::double_buffered_bulk`]).
     /// Endpoint allocation will fail if it is too small.
     pub fn new_hs(
         _peri: Peri<'d, T>,
@@ -132,7 +163,8 @@ impl<'d, T: Instance> Driver<'d, T> {
     /// # Arguments
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
-    /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// Must be large enough to fit all OUT endpoint max packet sizes, and the extra buffers
+    /// of double-buffered bulk endpoints (see [`Config::double_buffered_bulk`]).
     /// Endpoint allocation will fail if it is too small.
     pub fn new_fs_ulpi(
         _peri: Peri<'d, T>,
@@ -178,7 +210,8 @@ impl<'d, T: Instance> Driver<'d, T> {
     /// # Arguments
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
-    /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// Must be large enough to fit all OUT endpoint max packet sizes, and the extra buffers
+    /// of double-buffered bulk endpoints (see [`Config::double_buffered_bulk`]).
     /// Endpoint allocation will fail if it is too small.
     pub fn new_hs_ulpi(
         _peri: Peri<'d, T>,
@@ -269,66 +302,7 @@ pub struct Bus<'d, T: Instance> {
 
 impl<'d, T: Instance> Bus<'d, T> {
     fn init(&mut self) {
-        super::common_init::<T>();
-
-        // Enable ULPI clock if external PHY is used
-        let phy_type = self.inner.phy_type();
-        let _ulpien = !phy_type.internal();
-
-        #[cfg(any(stm32f2, stm32f4, stm32f7))]
-        if T::HIGH_SPEED {
-            critical_section::with(|_| {
-                let rcc = crate::pac::RCC;
-                rcc.ahb1enr().modify(|w| w.set_usb_otg_hsulpien(_ulpien));
-                rcc.ahb1lpenr().modify(|w| w.set_usb_otg_hsulpilpen(_ulpien));
-            });
-        }
-
-        #[cfg(stm32h7)]
-        critical_section::with(|_| {
-            let rcc = crate::pac::RCC;
-            if T::HIGH_SPEED {
-                rcc.ahb1enr().modify(|w| w.set_usb_otg_hs_ulpien(_ulpien));
-                rcc.ahb1lpenr().modify(|w| w.set_usb_otg_hs_ulpilpen(_ulpien));
-            } else {
-                rcc.ahb1enr().modify(|w| w.set_usb_otg_fs_ulpien(_ulpien));
-                rcc.ahb1lpenr().modify(|w| w.set_usb_otg_fs_ulpilpen(_ulpien));
-            }
-        });
-
-        #[cfg(stm32h7rs)]
-        critical_section::with(|_| {
-            let rcc = crate::pac::RCC;
-            rcc.ahb1enr().modify(|w| {
-                w.set_usbphycen(true);
-                w.set_usb_otg_hsen(true);
-            });
-            rcc.ahb1lpenr().modify(|w| {
-                w.set_usbphyclpen(true);
-                w.set_usb_otg_hslpen(true);
-            });
-        });
-
-        #[cfg(all(stm32u5, peri_usb_otg_hs))]
-        {
-            crate::pac::SYSCFG.otghsphycr().modify(|w| {
-                w.set_en(true);
-            });
-
-            critical_section::with(|_| {
-                crate::pac::RCC.ahb2enr1().modify(|w| {
-                    w.set_usb_otg_hsen(true);
-                    w.set_usb_otg_hs_phyen(true);
-                });
-            });
-        }
-
-        let r = T::regs();
-        let core_id = r.cid().read().0;
-        trace!("Core id {:08x}", core_id);
-
-        // Wait for AHB ready.
-        while !r.grstctl().read().ahbidl() {}
+        let core_id = init_core::<T>(self.inner.phy_type());
 
         // Configure as device.
         self.inner.configure_as_device();
@@ -345,6 +319,8 @@ impl<'d, T: Instance> Bus<'d, T> {
     fn disable(&mut self) {
         T::Interrupt::disable();
 
+        // Take back the stop mode requirement released on suspend.
+        super::on_suspend::<T>(false);
         rcc::disable::<T>();
         self.inited = false;
 
@@ -396,13 +372,186 @@ impl<'d, T: Instance> Drop for Bus<'d, T> {
     }
 }
 
-trait SealedInstance {
+/// USB host driver.
+///
+/// VBUS is not driven by the peripheral, it must be switched on by the application, usually with
+/// a GPIO controlling a power switch.
+#[cfg(feature = "time")]
+pub struct UsbHost<'d, T: Instance> {
+    phantom: PhantomData<&'d mut T>,
+    inner: OtgHost<'d, MAX_CHANNEL_COUNT>,
+}
+
+#[cfg(feature = "time")]
+impl<'d, T: Instance> UsbHost<'d, T> {
+    /// Initializes USB OTG peripheral as a host with internal Full-Speed PHY.
+    pub fn new_fs(
+        _peri: Peri<'d, T>,
+        _irq: impl interrupt::typelevel::Binding<T::Interrupt, HostInterruptHandler<T>> + 'd,
+        dp: Peri<'d, impl DpPin<T>>,
+        dm: Peri<'d, impl DmPin<T>>,
+    ) -> Self {
+        dp.set_as_af(dp.af_num(), AfType::output(OutputType::PushPull, Speed::VeryHigh));
+        dm.set_as_af(dm.af_num(), AfType::output(OutputType::PushPull, Speed::VeryHigh));
+
+        let phy_type = PhyType::InternalFullSpeed;
+        let core_id = init_core::<T>(phy_type);
+
+        let mut inner = OtgHost::new(
+            T::regs(),
+            T::host_state(),
+            phy_type,
+            T::FIFO_DEPTH_WORDS,
+            T::CHANNEL_COUNT,
+        );
+        inner.configure_as_host();
+
+        // Configuring Vbus sense
+        match core_id {
+            0x0000_1200 | 0x0000_1100 | 0x0000_1000 => inner.config_v1(),
+            0x0000_2000 | 0x0000_2100 | 0x0000_2300 | 0x0000_3000 | 0x0000_3100 => inner.config_v2v3(),
+            0x0000_5000 => inner.config_v5(),
+            _ => unimplemented!("Unknown USB core id {:X}", core_id),
+        }
+
+        inner.init();
+
+        Self {
+            phantom: PhantomData,
+            inner,
+        }
+    }
+
+    /// Turns the port power on or off.
+    pub fn set_port_power(&mut self, enabled: bool) {
+        self.inner.set_port_power(enabled);
+    }
+}
+
+#[cfg(feature = "time")]
+impl<'d, T: Instance> UsbHostDriver for UsbHost<'d, T> {
+    type Channel = Channel<'d>;
+
+    async fn wait_for_device_event(&mut self) -> DeviceEvent {
+        self.inner.wait_for_device_event().await
+    }
+
+    /// Resets the device connected to the port.
+    ///
+    /// The USB specification requires waiting 100 ms after the connection, for the device power
+    /// to stabilize, before resetting it.
+    async fn bus_reset(&mut self) -> Result<UsbSpeed, HostError> {
+        embassy_time::Timer::after_millis(100).await;
+
+        self.inner.set_port_reset(true);
+        embassy_time::Timer::after_millis(20).await;
+        self.inner.set_port_reset(false);
+
+        let speed = self.inner.wait_port_enabled().await?;
+
+        // Reset recovery time
+        embassy_time::Timer::after_millis(10).await;
+
+        Ok(speed)
+    }
+
+    fn alloc_channel(
+        &mut self,
+        device_address: u8,
+        endpoint: &EndpointInfo,
+        speed: UsbSpeed,
+    ) -> Result<Self::Channel, ChannelAllocError> {
+        self.inner.alloc_channel(device_address, endpoint, speed)
+    }
+}
+
+#[cfg(feature = "time")]
+impl<'d, T: Instance> Drop for UsbHost<'d, T> {
+    fn drop(&mut self) {
+        self.inner.set_port_power(false);
+        T::Interrupt::disable();
+        rcc::disable::<T>();
+
+        #[cfg(stm32l4)]
+        crate::pac::PWR.cr2().modify(|w| w.set_usv(false));
+    }
+}
+
+/// Powers and clocks the core and its PHY, and returns the core ID once the core is ready.
+fn init_core<T: Instance>(phy_type: PhyType) -> u32 {
+    super::common_init::<T>(!phy_type.internal());
+
+    // Enable ULPI clock if external PHY is used
+    let _ulpien = !phy_type.internal();
+
+    #[cfg(any(stm32f2, stm32f4, stm32f7))]
+    if T::HIGH_SPEED {
+        critical_section::with(|_| {
+            let rcc = crate::pac::RCC;
+            rcc.ahb1enr().modify(|w| w.set_usb_otg_hsulpien(_ulpien));
+            rcc.ahb1lpenr().modify(|w| w.set_usb_otg_hsulpilpen(_ulpien));
+        });
+    }
+
+    #[cfg(stm32h7)]
+    critical_section::with(|_| {
+        let rcc = crate::pac::RCC;
+        if T::HIGH_SPEED {
+            rcc.ahb1enr().modify(|w| w.set_usb_otg_hs_ulpien(_ulpien));
+            rcc.ahb1lpenr().modify(|w| w.set_usb_otg_hs_ulpilpen(_ulpien));
+        } else {
+            rcc.ahb1enr().modify(|w| w.set_usb_otg_fs_ulpien(_ulpien));
+            rcc.ahb1lpenr().modify(|w| w.set_usb_otg_fs_ulpilpen(_ulpien));
+        }
+    });
+
+    #[cfg(stm32h7rs)]
+    critical_section::with(|_| {
+        let rcc = crate::pac::RCC;
+        rcc.ahb1enr().modify(|w| {
+            w.set_usbphycen(true);
+            w.set_usb_otg_hsen(true);
+        });
+        rcc.ahb1lpenr().modify(|w| {
+            w.set_usbphyclpen(true);
+            w.set_usb_otg_hslpen(true);
+        });
+    });
+
+    #[cfg(all(stm32u5, peri_usb_otg_hs))]
+    {
+        crate::pac::SYSCFG.otghsphycr().modify(|w| {
+            w.set_en(true);
+        });
+
+        critical_section::with(|_| {
+            crate::pac::RCC.ahb2enr1().modify(|w| {
+                w.set_usb_otg_hsen(true);
+                w.set_usb_otg_hs_phyen(true);
+            });
+        });
+    }
+
+    let r = T::regs();
+    let core_id = r.cid().read().0;
+    trace!("Core id {:08x}", core_id);
+
+    // Wait for AHB ready.
+    while !r.grstctl().read().ahbidl() {}
+
+    core_id
+}
+
+pub(super) trait SealedInstance {
     const HIGH_SPEED: bool;
     const FIFO_DEPTH_WORDS: u16;
     const ENDPOINT_COUNT: usize;
+    const CHANNEL_COUNT: usize;
 
     fn regs() -> Otg;
     fn state() -> &'static State<{ MAX_EP_COUNT }>;
+    fn host_state() -> &'static HostState<{ MAX_CHANNEL_COUNT }>;
+    fn suspend_state() -> &'static super::SuspendState;
 }
 
 /// USB instance trait.
@@ -439,6 +588,7 @@ foreach_interrupt!(
                 if #[cfg(stm32f1)] {
                     const FIFO_DEPTH_WORDS: u16 = 128;
                     const ENDPOINT_COUNT: usize = 8;
+                    const CHANNEL_COUNT: usize = 8;
                 } else if #[cfg(any(
                     stm32f2,
                     stm32f401,
@@ -454,6 +604,7 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 320;
                     const ENDPOINT_COUNT: usize = 4;
+                    const CHANNEL_COUNT: usize = 8;
                 } else if #[cfg(any(
                     stm32f412,
                     stm32f413,
@@ -467,15 +618,19 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 320;
                     const ENDPOINT_COUNT: usize = 6;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(stm32g0x1)] {
                     const FIFO_DEPTH_WORDS: u16 = 512;
                     const ENDPOINT_COUNT: usize = 8;
+                    const CHANNEL_COUNT: usize = 8;
                 } else if #[cfg(any(stm32h7, stm32h7rs))] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 9;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(stm32u5)] {
                     const FIFO_DEPTH_WORDS: u16 = 320;
                     const ENDPOINT_COUNT: usize = 6;
+                    const CHANNEL_COUNT: usize = 12;
                 } else {
                     compile_error!("USB_OTG_FS peripheral is not supported by this chip.");
                 }
@@ -489,6 +644,16 @@ foreach_interrupt!(
                 static STATE: State<MAX_EP_COUNT> = State::new();
                 &STATE
             }
+
+            fn host_state() -> &'static HostState<MAX_CHANNEL_COUNT> {
+                static STATE: HostState<MAX_CHANNEL_COUNT> = HostState::new();
+                &STATE
+            }
+
+            fn suspend_state() -> &'static super::SuspendState {
+                static STATE: super::SuspendState = super::SuspendState::new();
+                &STATE
+            }
         }
 
         impl Instance for crate::peripherals::USB_OTG_FS {
@@ -514,6 +679,7 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 6;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(any(
                     stm32f446,
                     stm32f469,
@@ -523,9 +689,11 @@ foreach_interrupt!(
                 ))] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 9;
+                    const CHANNEL_COUNT: usize = 12;
                 } else if #[cfg(stm32u5)] {
                     const FIFO_DEPTH_WORDS: u16 = 1024;
                     const ENDPOINT_COUNT: usize = 9;
+                    const CHANNEL_COUNT: usize = 12;
                 } else {
                     compile_error!("USB_OTG_HS peripheral is not supported by this chip.");
                 }
@@ -540,6 +708,16 @@ foreach_interrupt!(
                 static STATE: State<MAX_EP_COUNT> = State::new();
                 &STATE
             }
+
+            fn host_state() -> &'static HostState<MAX_CHANNEL_COUNT> {
+                static STATE: HostState<MAX_CHANNEL_COUNT> = HostState::new();
+                &STATE
+            }
+
+            fn suspend_state() -> &'static super::SuspendState {
+                static STATE: super::SuspendState = super::SuspendState::new();
+                &STATE
+            }
         }
 
         impl Instance for crate::peripherals::USB_OTG_HS {
@@ -2,7 +2,7 @@
 
 use core::future::poll_fn;
 use core::marker::PhantomData;
-use core::sync::atomic::{AtomicBool, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU8, Ordering};
 use core::task::Poll;
 
 use embassy_hal_internal::PeripheralType;
@@ -38,6 +38,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
                 w.set_fsusp(true);
                 w.set_lpmode(true);
             });
+            super::on_suspend::<T>(true);
 
             // Write 0 to clear.
             let mut clear = regs::Istr(!0);
@@ -51,6 +52,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
         if istr.wkup() {
             //trace!("USB IRQ: wkup");
             IRQ_RESUME.store(true, Ordering::Relaxed);
+            super::on_suspend::<T>(false);
             regs.cntr().modify(|w| {
                 w.set_fsusp(false);
                 w.set_lpmode(false);
@@ -68,6 +70,7 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
         if istr.reset() {
             //trace!("USB IRQ: reset");
             IRQ_RESET.store(true, Ordering::Relaxed);
+            super::on_suspend::<T>(false);
 
             // Write 0 to clear.
             let mut clear = regs::Istr(!0);
@@ -95,8 +98,26 @@ impl<T: Instance> interrupt::typelevel::Handler<T::Interrupt> for InterruptHandl
                 //trace!("EP {} TX", index);
                 EP_IN_WAKERS[index].wake();
             }
-            epr.set_dtog_rx(false);
-            epr.set_dtog_tx(false);
+
+            // For double-buffered bulk endpoints, hand the next buffer to the peripheral right away if
+            // it's ready, instead of waiting for the endpoint task. The SW_BUF flag lives in the DTOG bit
+            // of the opposite direction.
+            let mut toggle_sw_buf = false;
+            if is_double_buffered(epr) {
+                let packets = DBL_BUF_PACKETS[index].load(Ordering::Relaxed);
+                if epr.ctr_rx() {
+                    // Take the buffer just received, unless the other one hasn't been read yet.
+                    DBL_BUF_PACKETS[index].store(packets + 1, Ordering::Relaxed);
+                    toggle_sw_buf = packets == 0;
+                }
+                if epr.ctr_tx() {
+                    // Release the second queued packet for transmission.
+                    DBL_BUF_PACKETS[index].store(packets.saturating_sub(1), Ordering::Relaxed);
+                    toggle_sw_buf = packets == 2;
+                }
+            }
+            epr.set_dtog_rx(toggle_sw_buf && epr.ctr_tx());
+            epr.set_dtog_tx(toggle_sw_buf && epr.ctr_rx());
             epr.set_stat_rx(Stat::from_bits(0));
             epr.set_stat_tx(Stat::from_bits(0));
             epr.set_ctr_rx(!epr.ctr_rx());
@@ -130,6 +151,9 @@ static EP_OUT_WAKERS: [AtomicWaker; EP_COUNT] = [const { AtomicWaker::new() }; E
 static IRQ_RESET: AtomicBool = AtomicBool::new(false);
 static IRQ_SUSPEND: AtomicBool = AtomicBool::new(false);
 static IRQ_RESUME: AtomicBool = AtomicBool::new(false);
+/// Double-buffered bulk endpoints: number of received packets not read yet (OUT), or of packets
+/// queued for transmission (IN).
+static DBL_BUF_PACKETS: [AtomicU8; EP_COUNT] = [const { AtomicU8::new(0) }; EP_COUNT];
 
 fn convert_type(t: EndpointType) -> EpType {
     match t {
@@ -150,6 +174,11 @@ fn invariant(mut r: regs::Epr) -> regs::Epr {
     r
 }
 
+/// Returns whether the endpoint is a double-buffered bulk endpoint.
+fn is_double_buffered(r: regs::Epr) -> bool {
+    r.ep_type() == EpType::BULK && r.ep_kind()
+}
+
 fn align_len_up(len: u16) -> u16 {
     ((len as usize + USBRAM_ALIGN - 1) / USBRAM_ALIGN * USBRAM_ALIGN) as u16
 }
@@ -246,6 +275,14 @@ struct EndpointBuffer<T: Instance> {
 }
 
 impl<T: Instance> EndpointBuffer<T> {
+    fn new(addr: u16, len: u16) -> Self {
+        Self {
+            addr,
+            len,
+            _phantom: PhantomData,
+        }
+    }
+
     fn read(&mut self, buf: &mut [u8]) {
         assert!(buf.len() <= self.len as usize);
         for i in 0..(buf.len() + USBRAM_ALIGN - 1) / USBRAM_ALIGN {
@@ -277,6 +314,7 @@ struct EndpointData {
     ep_type: EndpointType, // only valid if used_in || used_out
     used_in: bool,
     used_out: bool,
+    double_buffered: bool,
 }
 
 /// USB driver.
@@ -284,6 +322,7 @@ pub struct Driver<'d, T: Instance> {
     phantom: PhantomData<&'d mut T>,
     alloc: [EndpointData; EP_COUNT],
     ep_mem_free: u16, // first free address in EP mem, in bytes.
+    double_buffered_bulk: bool,
 }
 
 impl<'d, T: Instance> Driver<'d, T> {
@@ -311,7 +350,7 @@ impl<'d, T: Instance> Driver<'d, T> {
         dp: Peri<'d, impl DpPin<T>>,
         dm: Peri<'d, impl DmPin<T>>,
     ) -> Self {
-        super::common_init::<T>();
+        super::common_init::<T>(false);
 
         let regs = T::regs();
 
@@ -344,11 +383,23 @@ impl<'d, T: Instance> Driver<'d, T> {
                 ep_type: EndpointType::Bulk,
                 used_in: false,
                 used_out: false,
+                double_buffered: false,
             }; EP_COUNT],
             ep_mem_free: EP_COUNT as u16 * 8, // for each EP, 4 regs, so 8 bytes
+            double_buffered_bulk: false,
         }
     }
 
+    /// Use double buffering for the bulk endpoints allocated after this call.
+    ///
+    /// A double-buffered endpoint receives or transmits a packet while the other one is being
+    /// processed, so the host isn't NAKed between packets and full-speed bulk transfers can reach
+    /// line rate. It takes a whole endpoint register and twice the packet memory, which means fewer
+    /// endpoints fit in the peripheral.
+    pub fn set_double_buffered_bulk(&mut self, enabled: bool) {
+        self.double_buffered_bulk = enabled;
+    }
+
     fn alloc_ep_mem(&mut self, len: u16) -> u16 {
         assert!(len as usize % USBRAM_ALIGN == 0);
         let addr = self.ep_mem_free;
@@ -373,16 +424,17 @@ impl<'d, T: Instance> Driver<'d, T> {
             D::dir()
         );
 
+        let double_buffered = ep_type == EndpointType::Bulk && self.double_buffered_bulk;
+
         let index = self.alloc.iter_mut().enumerate().find(|(i, ep)| {
             if *i == 0 && ep_type != EndpointType::Control {
                 return false; // reserved for control pipe
             }
             let used = ep.used_out || ep.used_in;
-            if used && (ep.ep_type == EndpointType::Isochronous) {
-                // Isochronous endpoints are always double-buffered.
+            if used && (ep.ep_type == EndpointType::Isochronous || ep.double_buffered || double_buffered) {
+                // Isochronous endpoints are always double-buffered, bulk endpoints optionally.
                 // Their corresponding endpoint/channel registers are forced to be unidirectional.
                 // Do not reuse this index.
-                // FIXME: Bulk endpoints can be double buffered, but are not in the current implementation.
                 return false;
             }
 
@@ -399,8 +451,11 @@ impl<'d, T: Instance> Driver<'d, T> {
         };
 
         ep.ep_type = ep_type;
+        ep.double_buffered = double_buffered;
 
-        let buf = match D::dir() {
+        // Double-buffered bulk endpoints use the TX buffer descriptor for buffer 0, and the RX
+        // buffer descriptor for buffer 1, in both directions.
+        let (buf, buf_rx) = match D::dir() {
             Direction::Out => {
                 assert!(!ep.used_out);
                 ep.used_out = true;
@@ -415,10 +470,12 @@ impl<'d, T: Instance> Driver<'d, T> {
                     btable::write_out_tx::<T>(index, addr, len_bits);
                 }
 
-                EndpointBuffer {
-                    addr,
-                    len,
-                    _phantom: PhantomData,
+                if double_buffered {
+                    let addr_tx = self.alloc_ep_mem(len);
+                    btable::write_out_tx::<T>(index, addr_tx, len_bits);
+                    (EndpointBuffer::new(addr_tx, len), Some(EndpointBuffer::new(addr, len)))
+                } else {
+                    (EndpointBuffer::new(addr, len), None)
                 }
             }
             Direction::In => {
@@ -427,14 +484,19 @@ impl<'d, T: Instance> Driver<'d, T> {
 
                 let len = align_len_up(max_packet_size);
                 let addr = self.alloc_ep_mem(len);
+                let addr_rx = match (ep_type, double_buffered) {
+                    (EndpointType::Isochronous, _) => Some(addr),
+                    (_, true) => Some(self.alloc_ep_mem(len)),
+                    _ => None,
+                };
 
                 #[cfg(not(any(usbram_32_2048, usbram_32_1024)))]
                 {
                     // ep_in_len is written when actually transmitting packets.
                     btable::write_in_tx::<T>(index, addr);
 
-                    if ep_type == EndpointType::Isochronous {
-                        btable::write_in_rx::<T>(index, addr);
+                    if let Some(addr_rx) = addr_rx {
+                        btable::write_in_rx::<T>(index, addr_rx);
                     }
                 }
 
@@ -442,16 +504,16 @@ impl<'d, T: Instance> Driver<'d, T> {
                 {
                     btable::write_in_len_tx::<T>(index, addr, 0);
 
-                    if ep_type == EndpointType::Isochronous {
-                        btable::write_in_len_rx::<T>(index, addr, 0);
+                    if let Some(addr_rx) = addr_rx {
+                        btable::write_in_len_rx::<T>(index, addr_rx, 0);
                     }
                 }
 
-                EndpointBuffer {
-                    addr,
-                    len,
-                    _phantom: PhantomData,
-                }
+                let buf_rx = match double_buffered {
+                    true => addr_rx.map(|addr| EndpointBuffer::new(addr, len)),
+                    false => None,
+                };
+                (EndpointBuffer::new(addr, len), buf_rx)
             }
         };
 
@@ -466,6 +528,7 @@ impl<'d, T: Instance> Driver<'d, T> {
                 interval_ms,
             },
             buf,
+            buf_rx,
         })
     }
 }
@@ -524,14 +587,17 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
         trace!("enabled");
 
         let mut ep_types = [EpType::BULK; EP_COUNT - 1];
+        let mut ep_double_buffered = [false; EP_COUNT - 1];
         for i in 1..EP_COUNT {
             ep_types[i - 1] = convert_type(self.alloc[i].ep_type);
+            ep_double_buffered[i - 1] = self.alloc[i].double_buffered;
         }
 
         (
             Bus {
                 phantom: PhantomData,
                 ep_types,
+                ep_double_buffered,
                 inited: false,
             },
             ControlPipe {
@@ -548,6 +614,7 @@ impl<'d, T: Instance> driver::Driver<'d> for Driver<'d, T> {
 pub struct Bus<'d, T: Instance> {
     phantom: PhantomData<&'d mut T>,
     ep_types: [EpType; EP_COUNT - 1],
+    ep_double_buffered: [bool; EP_COUNT - 1],
     inited: bool,
 }
 
@@ -588,6 +655,7 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
                     regs.epr(i).write(|w| {
                         w.set_ea(i as _);
                         w.set_ep_type(self.ep_types[i - 1]);
+                        w.set_ep_kind(self.ep_double_buffered[i - 1]); // DBL_BUF
                     })
                 }
 
@@ -623,6 +691,7 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
                         Stat::STALL => break,    // done!
                         _ => {
                             let want_stat = match stalled {
+                                false if is_double_buffered(r) => Stat::VALID,
                                 false => Stat::NAK,
                                 true => Stat::STALL,
                             };
@@ -670,13 +739,29 @@ impl<'d, T: Instance> driver::Bus for Bus<'d, T> {
         // This can race, so do a retry loop.
         let epr = T::regs().epr(ep_addr.index() as _);
         trace!("EPR before: {:04x}", epr.read().0);
+
+        let r = epr.read();
+        if enabled && is_double_buffered(r) {
+            // Start with DATA0. SW_BUF must equal DTOG_TX for IN endpoints, so nothing is sent until a
+            // packet is queued, and must differ from DTOG_RX for OUT endpoints, so buffer 0 can receive.
+            DBL_BUF_PACKETS[ep_addr.index()].store(0, Ordering::Relaxed);
+            let mut w = invariant(r);
+            w.set_dtog_rx(r.dtog_rx());
+            w.set_dtog_tx(match ep_addr.direction() {
+                Direction::In => r.dtog_tx(),
+                Direction::Out => !r.dtog_tx(),
+            });
+            epr.write_value(w);
+        }
+
         match ep_addr.direction() {
             Direction::In => {
                 loop {
                     let want_stat = match enabled {
                         false => Stat::DISABLED,
-                        true => match epr.read().ep_type() {
-                            EpType::ISO => Stat::VALID,
+                        // Double-buffered endpoints NAK by themselves while no packet is queued.
+                        true => match epr.read() {
+                            r if r.ep_type() == EpType::ISO || is_double_buffered(r) => Stat::VALID,
                             _ => Stat::NAK,
                         },
                     };
@@ -756,22 +841,33 @@ pub struct Endpoint<'d, T: Instance, D> {
     _phantom: PhantomData<(&'d mut T, D)>,
     info: EndpointInfo,
     buf: EndpointBuffer<T>,
+    /// Buffer 1 of double-buffered bulk endpoints. Isochronous endpoints use `buf` for both.
+    buf_rx: Option<EndpointBuffer<T>>,
 }
 
 impl<'d, T: Instance, D> Endpoint<'d, T, D> {
+    fn packet_buffer(&mut self, packet_buffer: PacketBuffer) -> &mut EndpointBuffer<T> {
+        match (packet_buffer, &mut self.buf_rx) {
+            (PacketBuffer::Rx, Some(buf)) => buf,
+            _ => &mut self.buf,
+        }
+    }
+
     /// Write to a double-buffered endpoint.
     ///
-    /// For double-buffered endpoints, the data buffers overlap, but we still need to write to the right counter field.
+    /// For isochronous endpoints, the data buffers overlap, but we still need to write to the right counter field.
     /// The DTOG_TX bit indicates the buffer that is currently in use by the USB peripheral, that is, the buffer in
     /// which the next transmit packet will be stored, so we need to write the counter of the OTHER buffer, which is
     /// where the last transmitted packet was stored.
     fn write_data_double_buffered(&mut self, buf: &[u8], packet_buffer: PacketBuffer) {
         let index = self.info.addr.index();
-        self.buf.write(buf);
+        let ep_buf = self.packet_buffer(packet_buffer);
+        ep_buf.write(buf);
+        let addr = ep_buf.addr;
 
         match packet_buffer {
-            PacketBuffer::Rx => btable::write_in_len_rx::<T>(index, self.buf.addr, buf.len() as _),
-            PacketBuffer::Tx => btable::write_in_len_tx::<T>(index, self.buf.addr, buf.len() as _),
+            PacketBuffer::Rx => btable::write_in_len_rx::<T>(index, addr, buf.len() as _),
+            PacketBuffer::Tx => btable::write_in_len_tx::<T>(index, addr, buf.len() as _),
         }
     }
 
@@ -782,7 +878,7 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
 
     /// Read from a double-buffered endpoint.
     ///
-    /// For double-buffered endpoints, the data buffers overlap, but we still need to read from the right counter field.
+    /// For isochronous endpoints, the data buffers overlap, but we still need to read from the right counter field.
     /// The DTOG_RX bit indicates the buffer that is currently in use by the USB peripheral, that is, the buffer in
     /// which the next received packet will be stored, so we need to read the counter of the OTHER buffer, which is
     /// where the last received packet was stored.
@@ -803,7 +899,7 @@ impl<'d, T: Instance, D> Endpoint<'d, T, D> {
         if rx_len > buf.len() {
             return Err(EndpointError::BufferOverflow);
         }
-        self.buf.read(&mut buf[..rx_len]);
+        self.packet_buffer(packet_buffer).read(&mut buf[..rx_len]);
         Ok(rx_len)
     }
 
@@ -874,6 +970,13 @@ impl<'d, T: Instance> driver::EndpointOut for Endpoint<'d, T, Out> {
                 } else {
                     Poll::Pending
                 }
+            } else if self.buf_rx.is_some() {
+                // Double-buffered endpoints stay VALID, wait for a received packet.
+                if matches!(stat, Stat::DISABLED) || DBL_BUF_PACKETS[index].load(Ordering::Relaxed) > 0 {
+                    Poll::Ready(stat)
+                } else {
+                    Poll::Pending
+                }
             } else {
                 if matches!(stat, Stat::NAK | Stat::DISABLED) {
                     Poll::Ready(stat)
@@ -910,6 +1013,28 @@ impl<'d, T: Instance> driver::EndpointOut for Endpoint<'d, T, Out> {
                 PacketBuffer::Rx
             };
             self.read_data_double_buffered(buf, packet_buffer)?
+        } else if self.buf_rx.is_some() {
+            // The oldest packet is in the buffer owned by software, selected by SW_BUF (DTOG_TX).
+            let packet_buffer = if regs.epr(index).read().dtog_tx() {
+                PacketBuffer::Rx
+            } else {
+                PacketBuffer::Tx
+            };
+            let len = self.read_data_double_buffered(buf, packet_buffer)?;
+
+            critical_section::with(|_| {
+                let packets = DBL_BUF_PACKETS[index].load(Ordering::Relaxed);
+                DBL_BUF_PACKETS[index].store(packets.saturating_sub(1), Ordering::Relaxed);
+                if packets == 2 {
+                    // The peripheral is NAKing with the other buffer full: take it, and give it the one
+                    // just read.
+                    let mut w = invariant(regs.epr(index).read());
+                    w.set_dtog_tx(true);
+                    regs.epr(index).write_value(w);
+                }
+            });
+
+            len
         } else {
             let len = self.read_data(buf)?;
 
@@ -964,6 +1089,13 @@ impl<'d, T: Instance> driver::EndpointIn for Endpoint<'d, T, In> {
                 } else {
                     Poll::Pending
                 }
+            } else if self.buf_rx.is_some() {
+                // Double-buffered endpoints stay VALID, wait for a free buffer.
+                if matches!(stat, Stat::DISABLED) || DBL_BUF_PACKETS[index].load(Ordering::Relaxed) < 2 {
+                    Poll::Ready(stat)
+                } else {
+                    Poll::Pending
+                }
             } else {
                 if matches!(stat, Stat::NAK | Stat::DISABLED) {
                     Poll::Ready(stat)
@@ -978,7 +1110,27 @@ impl<'d, T: Instance> driver::EndpointIn for Endpoint<'d, T, In> {
             return Err(EndpointError::Disabled);
         }
 
-        if self.info.ep_type != EndpointType::Isochronous {
+        if self.buf_rx.is_some() {
+            // The free buffer is the one owned by software, selected by SW_BUF (DTOG_RX).
+            let packet_buffer = if regs.epr(index).read().dtog_rx() {
+                PacketBuffer::Rx
+            } else {
+                PacketBuffer::Tx
+            };
+            self.write_data_double_buffered(buf, packet_buffer);
+
+            critical_section::with(|_| {
+                let packets = DBL_BUF_PACKETS[index].load(Ordering::Relaxed);
+                DBL_BUF_PACKETS[index].store(packets + 1, Ordering::Relaxed);
+                if packets == 0 {
+                    // The peripheral is idle, hand it the buffer. Otherwise the interrupt does it once
+                    // the packet in flight is sent.
+                    let mut w = invariant(regs.epr(index).read());
+                    w.set_dtog_rx(true);
+                    regs.epr(index).write_value(w);
+                }
+            });
+        } else if self.info.ep_type != EndpointType::Isochronous {
             self.write_data(buf);
 
             regs.epr(index).write(|w| {
@@ -1229,8 +1381,9 @@ impl<'d, T: Instance> driver::ControlPipe for ControlPipe<'d, T> {
     }
 }
 
-trait SealedInstance {
+pub(super) trait SealedInstance {
     fn regs() -> crate::pac::usb::Usb;
+    fn suspend_state() -> &'static super::SuspendState;
 }
 
 /// USB instance trait.
@@ -1251,6 +1404,11 @@ foreach_interrupt!(
             fn regs() -> crate::pac::usb::Usb {
                 crate::pac::$inst
             }
+
+            fn suspend_state() -> &'static super::SuspendState {
+                static STATE: super::SuspendState = super::SuspendState::new();
+                &STATE
+            }
         }
 
         impl Instance for crate::peripherals::$inst {
@@ -0,0 +1,77 @@
+//! Backup battery (VBAT) charging and monitoring.
+//!
+//! When VDD is present, a rechargeable battery or supercapacitor on the VBAT pin can be charged
+//! through an internal resistor. Charging must stay disabled with a non-rechargeable coin cell.
+//!
+//! The VBAT voltage is measured through a bridge divider on an internal ADC channel, enabled with
+//! the `enable_vbat()` function of the ADC driver. [`vbat_millivolts`] converts the sample to the
+//! battery voltage.
+
+/// VBAT charging resistor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ChargingResistor {
+    /// Charge through a 5 kOhm resistor.
+    R5k,
+    /// Charge through a 1.5 kOhm resistor.
+    R1k5,
+}
+
+/// Ratio of the bridge divider between VBAT and the ADC channel.
+#[cfg(any(stm32l4, stm32l5, stm32g4, stm32wb, stm32wl))]
+pub const VBAT_DIVIDER: u32 = 3;
+/// Ratio of the bridge divider between VBAT and the ADC channel.
+#[cfg(stm32h7)]
+pub const VBAT_DIVIDER: u32 = 4;
+
+// The VBE and VBRS bits are in CR4, or CR3 on H7.
+#[cfg(not(stm32h7))]
+macro_rules! cr {
+    () => {
+        crate::pac::PWR.cr4()
+    };
+}
+
+#[cfg(stm32h7)]
+macro_rules! cr {
+    () => {
+        crate::pac::PWR.cr3()
+    };
+}
+
+/// Enable charging the battery on VBAT through `resistor`.
+///
+/// Charging is automatically disabled while VDD is not present.
+pub fn enable_charging(resistor: ChargingResistor) {
+    critical_section::with(|_| {
+        cr!().modify(|w| {
+            w.set_vbrs(resistor == ChargingResistor::R1k5);
+            w.set_vbe(true);
+        })
+    })
+}
+
+/// Disable charging the battery on VBAT.
+pub fn disable_charging() {
+    critical_section::with(|_| cr!().modify(|w| w.set_vbe(false)))
+}
+
+/// Return the charging resistor if charging is enabled.
+pub fn charging() -> Option<ChargingResistor> {
+    let cr = cr!().read();
+    if !cr.vbe() {
+        None
+    } else if !cr.vbrs() {
+        Some(ChargingResistor::R5k)
+    } else {
+        Some(ChargingResistor::R1k5)
+    }
+}
+
+/// Convert an ADC sample of the VBAT channel to the battery voltage, in millivolts.
+///
+/// `vref_mv` is the ADC reference voltage and `max_sample` the largest sample at the configured
+/// resolution, for example 4095 for 12 bits.
+pub const fn vbat_millivolts(sample: u16, vref_mv: u32, max_sample: u32) -> u32 {
+    sample as u32 * vref_mv * VBAT_DIVIDER / max_sample
+}
@@ -4,7 +4,7 @@ use core::marker::PhantomData;
 use embassy_hal_internal::PeripheralType;
 use stm32_metapac::iwdg::vals::{Key, Pr};
 
-use crate::rcc::LSI_FREQ;
+use crate::rcc::lsi_freq;
 use crate::Peri;
 
 /// Independent watchdog (IWDG) driver.
@@ -16,13 +16,13 @@ pub struct IndependentWatchdog<'d, T: Instance> {
 const MAX_RL: u16 = 0xFFF;
 
 /// Calculates maximum watchdog timeout in us (RL = 0xFFF) for a given prescaler
-const fn get_timeout_us(prescaler: u16, reload_value: u16) -> u32 {
-    1_000_000 * (reload_value + 1) as u32 / (LSI_FREQ.0 / prescaler as u32)
+fn get_timeout_us(prescaler: u16, reload_value: u16) -> u32 {
+    1_000_000 * (reload_value + 1) as u32 / (lsi_freq().0 / prescaler as u32)
 }
 
 /// Calculates watchdog reload value for the given prescaler and desired timeout
-const fn reload_value(prescaler: u16, timeout_us: u32) -> u16 {
-    (timeout_us / prescaler as u32 * LSI_FREQ.0 / 1_000_000) as u16 - 1
+fn reload_value(prescaler: u16, timeout_us: u32) -> u16 {
+    (timeout_us / prescaler as u32 * lsi_freq().0 / 1_000_000) as u16 - 1
 }
 
 impl<'d, T: Instance> IndependentWatchdog<'d, T> {
@@ -1162,7 +1162,7 @@ impl<'d, T: Instance> Xspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
 
         finish_dma(T::REGS);
 
@@ -1192,7 +1192,7 @@ impl<'d, T: Instance> Xspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.blocking_wait();
+        unwrap!(transfer.blocking_wait());
 
         finish_dma(T::REGS);
 
@@ -1232,7 +1232,7 @@ impl<'d, T: Instance> Xspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.await;
+        unwrap!(transfer.await);
 
         finish_dma(T::REGS);
 
@@ -1262,7 +1262,7 @@ impl<'d, T: Instance> Xspi<'d, T, Async> {
 
         T::REGS.cr().modify(|w| w.set_dmaen(true));
 
-        transfer.await;
+        unwrap!(transfer.await);
 
         finish_dma(T::REGS);
 
@@ -0,0 +1,466 @@
+//! A grant-based byte queue, handing out contiguous slices of its buffer.
+//!
+//! It can be used concurrently by a writer and a reader, i.e. it is an "SPSC" queue.
+//!
+//! Unlike a [`Pipe`](crate::pipe::Pipe), data isn't copied in and out of the queue. The writer
+//! asks for a *grant*, a contiguous slice of free space in the buffer, fills it, and commits the
+//! number of bytes written. The reader gets a contiguous slice of committed data, and releases the
+//! number of bytes it consumed. Because the slices are contiguous, a write grant can be handed
+//! directly to a DMA transfer, for example to receive from a UART without copying:
+//!
+//! ```rust,ignore
+//! let mut buf = [0; 1024];
+//! let mut queue = BipBuffer::<NoopRawMutex>::new(&mut buf);
+//! let (mut writer, mut reader) = queue.split();
+//!
+//! // In the RX task:
+//! let grant = writer.grant(64).await;
+//! let n = uart.read_until_idle(grant).await?;
+//! writer.commit(n);
+//!
+//! // In the processing task:
+//! let data = reader.read().await;
+//! let n = parse(data);
+//! reader.release(n);
+//! ```
+//!
+//! To keep the grants contiguous, the queue works as a bipartite buffer: when there isn't enough
+//! room at the end of the buffer, the writer wraps around to the start, and the unused space at
+//! the end is skipped by the reader.
+
+use core::cell::RefCell;
+use core::future::{poll_fn, Future};
+use core::marker::PhantomData;
+use core::task::{Context, Poll};
+
+use crate::blocking_mutex::raw::RawMutex;
+use crate::blocking_mutex::Mutex;
+use crate::waitqueue::WakerRegistration;
+
+/// A grant-based byte queue.
+///
+/// See the [module documentation](self) for details.
+pub struct BipBuffer<'a, M: RawMutex> {
+    buf: BufferPtr,
+    phantom: PhantomData<&'a mut [u8]>,
+    state: Mutex<M, RefCell<State>>,
+}
+
+impl<'a, M: RawMutex> BipBuffer<'a, M> {
+    /// Create a new queue using `buf` as storage.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        let len = buf.len();
+        assert!(len != 0);
+
+        Self {
+            buf: BufferPtr(buf.as_mut_ptr()),
+            phantom: PhantomData,
+            state: Mutex::new(RefCell::new(State {
+                capacity: len,
+                read: 0,
+                write: 0,
+                watermark: len,
+                granted: 0,
+                readable: 0,
+                write_waker: WakerRegistration::new(),
+                read_waker: WakerRegistration::new(),
+            })),
+        }
+    }
+
+    /// Creates a [`Writer`] and [`Reader`] from the queue.
+    pub fn split(&mut self) -> (Writer<'_, M>, Reader<'_, M>) {
+        (Writer { queue: self }, Reader { queue: self })
+    }
+
+    /// Returns the size of the buffer.
+    pub fn capacity(&self) -> usize {
+        self.state.lock(|s| s.borrow().capacity)
+    }
+
+    /// Returns the number of committed bytes that haven't been released yet.
+    pub fn len(&self) -> usize {
+        self.state.lock(|s| s.borrow().len())
+    }
+
+    /// Returns whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[repr(transparent)]
+struct BufferPtr(*mut u8);
+
+unsafe impl Send for BufferPtr {}
+unsafe impl Sync for BufferPtr {}
+
+/// Write access to a [`BipBuffer`].
+pub struct Writer<'a, M: RawMutex> {
+    queue: &'a BipBuffer<'a, M>,
+}
+
+impl<'a, M: RawMutex> Writer<'a, M> {
+    /// Try to get a contiguous slice of at least `min` free bytes.
+    ///
+    /// The slice is as large as possible, so it may be longer than `min`. Write to it, then call
+    /// [`commit`](Self::commit) with the number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `min` is larger than the capacity of the queue.
+    pub fn try_grant(&mut self, min: usize) -> Option<&mut [u8]> {
+        self.queue.state.lock(|s| {
+            let s = &mut *s.borrow_mut();
+            s.grant(min).map(|start| self.slice(start, s.granted))
+        })
+    }
+
+    /// Attempts to asynchronously get a contiguous slice of at least `min` free bytes.
+    ///
+    /// See [`try_grant`](Self::try_grant).
+    pub fn poll_grant(&mut self, cx: &mut Context, min: usize) -> Poll<&mut [u8]> {
+        self.queue.state.lock(|s| {
+            let s = &mut *s.borrow_mut();
+            match s.grant(min) {
+                Some(start) => Poll::Ready(self.slice(start, s.granted)),
+                None => {
+                    s.write_waker.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    /// Asynchronously get a contiguous slice of at least `min` free bytes, waiting until the
+    /// reader has released enough space.
+    ///
+    /// See [`try_grant`](Self::try_grant).
+    pub fn grant(&mut self, min: usize) -> impl Future<Output = &mut [u8]> {
+        let queue = self.queue;
+        poll_fn(move |cx| {
+            queue.state.lock(|s| {
+                let s = &mut *s.borrow_mut();
+                match s.grant(min) {
+                    Some(start) => Poll::Ready(unsafe { queue.buf.slice_mut(start, s.granted) }),
+                    None => {
+                        s.write_waker.register(cx.waker());
+                        Poll::Pending
+                    }
+                }
+            })
+        })
+    }
+
+    /// Make the first `len` bytes of the last grant available to the reader.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the last grant.
+    pub fn commit(&mut self, len: usize) {
+        self.queue.state.lock(|s| s.borrow_mut().commit(len))
+    }
+
+    /// Returns the number of committed bytes that haven't been released yet.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    fn slice(&mut self, start: usize, len: usize) -> &mut [u8] {
+        unsafe { self.queue.buf.slice_mut(start, len) }
+    }
+}
+
+/// Read access to a [`BipBuffer`].
+pub struct Reader<'a, M: RawMutex> {
+    queue: &'a BipBuffer<'a, M>,
+}
+
+impl<'a, M: RawMutex> Reader<'a, M> {
+    /// Try to get a contiguous slice of committed bytes.
+    ///
+    /// Call [`release`](Self::release) with the number of bytes consumed. When the data wraps
+    /// around the end of the buffer, the slice only contains the data up to the end, and the
+    /// rest is returned once that has been released.
+    pub fn try_read(&mut self) -> Option<&[u8]> {
+        self.queue.state.lock(|s| {
+            let s = &mut *s.borrow_mut();
+            s.read()
+                .map(|start| unsafe { &*self.queue.buf.slice_mut(start, s.readable) })
+        })
+    }
+
+    /// Attempts to asynchronously get a contiguous slice of committed bytes.
+    ///
+    /// See [`try_read`](Self::try_read).
+    pub fn poll_read(&mut self, cx: &mut Context) -> Poll<&[u8]> {
+        self.queue.state.lock(|s| {
+            let s = &mut *s.borrow_mut();
+            match s.read() {
+                Some(start) => Poll::Ready(unsafe { &*self.queue.buf.slice_mut(start, s.readable) }),
+                None => {
+                    s.read_waker.register(cx.waker());
+                    Poll::Pending
+                }
+            }
+        })
+    }
+
+    /// Asynchronously get a contiguous slice of committed bytes, waiting until the writer
+    /// commits some.
+    ///
+    /// See [`try_read`](Self::try_read).
+    pub fn read(&mut self) -> impl Future<Output = &[u8]> {
+        let queue = self.queue;
+        poll_fn(move |cx| {
+            queue.state.lock(|s| {
+                let s = &mut *s.borrow_mut();
+                match s.read() {
+                    Some(start) => Poll::Ready(unsafe { &*queue.buf.slice_mut(start, s.readable) }),
+                    None => {
+                        s.read_waker.register(cx.waker());
+                        Poll::Pending
+                    }
+                }
+            })
+        })
+    }
+
+    /// Free the first `len` bytes of the last read slice, making the space available to the writer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is larger than the last read slice.
+    pub fn release(&mut self, len: usize) {
+        self.queue.state.lock(|s| s.borrow_mut().release(len))
+    }
+
+    /// Returns the number of committed bytes that haven't been released yet.
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    /// Returns whether the queue is empty.
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+}
+
+impl BufferPtr {
+    /// # Safety
+    ///
+    /// The range must be in the buffer, and not be accessed through another slice at the same time.
+    unsafe fn slice_mut<'b>(&self, start: usize, len: usize) -> &'b mut [u8] {
+        core::slice::from_raw_parts_mut(self.0.add(start), len)
+    }
+}
+
+struct State {
+    /// Size of the buffer.
+    capacity: usize,
+
+    /// Start of the committed data.
+    read: usize,
+    /// End of the committed data. When `write < read` the data has wrapped around: it continues
+    /// from `read` to `watermark`, then from 0 to `write`.
+    write: usize,
+    /// End of the data at the end of the buffer, when it has wrapped around.
+    watermark: usize,
+
+    /// Length of the last write grant, starting at `write`.
+    granted: usize,
+    /// Length of the last read slice, starting at `read`.
+    readable: usize,
+
+    write_waker: WakerRegistration,
+    read_waker: WakerRegistration,
+}
+
+impl State {
+    fn len(&self) -> usize {
+        if self.write >= self.read {
+            self.write - self.read
+        } else {
+            self.watermark - self.read + self.write
+        }
+    }
+
+    /// Grant the largest contiguous free region if it holds at least `min` bytes, returning its start.
+    fn grant(&mut self, min: usize) -> Option<usize> {
+        assert!(min <= self.capacity);
+
+        if self.read == self.write && self.readable == 0 {
+            // Empty, start over at the beginning of the buffer to get the largest grant.
+            self.read = 0;
+            self.write = 0;
+        }
+
+        let len = if self.write >= self.read {
+            let end = self.capacity - self.write;
+            // `write` must stay below `read` once wrapped, or the queue would look empty.
+            let start = self.read.saturating_sub(1);
+            if end >= min && end > 0 {
+                end
+            } else if start >= min && start > 0 {
+                self.watermark = self.write;
+                self.write = 0;
+                start
+            } else {
+                return None;
+            }
+        } else {
+            let free = self.read - self.write - 1;
+            if free >= min && free > 0 {
+                free
+            } else {
+                return None;
+            }
+        };
+
+        self.granted = len;
+        Some(self.write)
+    }
+
+    fn commit(&mut self, len: usize) {
+        assert!(len <= self.granted);
+        self.write += len;
+        self.granted -= len;
+        if len > 0 {
+            self.read_waker.wake();
+        }
+    }
+
+    /// Get the contiguous committed data, returning its start.
+    fn read(&mut self) -> Option<usize> {
+        if self.write < self.read && self.read == self.watermark {
+            // The data at the end has been read, continue at the start.
+            self.read = 0;
+        }
+
+        let end = if self.write >= self.read {
+            self.write
+        } else {
+            self.watermark
+        };
+        self.readable = end - self.read;
+        (self.readable > 0).then_some(self.read)
+    }
+
+    fn release(&mut self, len: usize) {
+        assert!(len <= self.readable);
+        self.read += len;
+        self.readable -= len;
+        if self.write < self.read && self.read == self.watermark {
+            self.read = 0;
+        }
+        if len > 0 {
+            self.write_waker.wake();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::ThreadPool;
+    use futures_timer::Delay;
+    use futures_util::task::SpawnExt;
+    use static_cell::StaticCell;
+
+    use super::*;
+    use crate::blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex};
+
+    #[test]
+    fn grant_commit_read_release() {
+        let mut buf = [0u8; 8];
+        let mut queue = BipBuffer::<NoopRawMutex>::new(&mut buf);
+        let (mut writer, mut reader) = queue.split();
+
+        assert!(reader.try_read().is_none());
+
+        let grant = writer.try_grant(3).unwrap();
+        assert_eq!(grant.len(), 8);
+        grant[..3].copy_from_slice(&[1, 2, 3]);
+        writer.commit(3);
+        assert_eq!(writer.len(), 3);
+
+        assert_eq!(reader.try_read(), Some(&[1, 2, 3][..]));
+        reader.release(2);
+        assert_eq!(reader.try_read(), Some(&[3][..]));
+        reader.release(1);
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn wrap_around() {
+        let mut buf = [0u8; 8];
+        let mut queue = BipBuffer::<NoopRawMutex>::new(&mut buf);
+        let (mut writer, mut reader) = queue.split();
+
+        writer.try_grant(6).unwrap()[..6].copy_from_slice(&[1, 2, 3, 4, 5, 6]);
+        writer.commit(6);
+        reader.try_read().unwrap();
+        reader.release(4);
+
+        // Only 2 bytes left at the end, so a grant of 3 wraps around and skips them.
+        assert!(writer.try_grant(4).is_none());
+        let grant = writer.try_grant(3).unwrap();
+        assert_eq!(grant.len(), 3);
+        grant.copy_from_slice(&[7, 8, 9]);
+        writer.commit(3);
+        assert_eq!(writer.len(), 5);
+
+        // The reader gets the data at the end first, then continues at the start.
+        assert_eq!(reader.try_read(), Some(&[5, 6][..]));
+        reader.release(2);
+        assert_eq!(reader.try_read(), Some(&[7, 8, 9][..]));
+        reader.release(3);
+        assert!(reader.try_read().is_none());
+
+        // Once empty, the whole buffer can be granted again.
+        assert_eq!(writer.try_grant(8).unwrap().len(), 8);
+    }
+
+    #[test]
+    fn partial_commit() {
+        let mut buf = [0u8; 8];
+        let mut queue = BipBuffer::<NoopRawMutex>::new(&mut buf);
+        let (mut writer, mut reader) = queue.split();
+
+        writer.try_grant(4).unwrap()[..2].copy_from_slice(&[1, 2]);
+        writer.commit(2);
+        writer.try_grant(1).unwrap()[0] = 3;
+        writer.commit(1);
+
+        assert_eq!(reader.try_read(), Some(&[1, 2, 3][..]));
+    }
+
+    #[futures_test::test]
+    async fn writer_waits_for_space() {
+        static BUF: StaticCell<[u8; 8]> = StaticCell::new();
+        static QUEUE: StaticCell<BipBuffer<'static, CriticalSectionRawMutex>> = StaticCell::new();
+        let queue = QUEUE.init(BipBuffer::new(BUF.init([0; 8])));
+        let (mut writer, mut reader) = queue.split();
+
+        writer.try_grant(8).unwrap().copy_from_slice(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        writer.commit(8);
+        assert!(writer.try_grant(1).is_none());
+
+        let executor = ThreadPool::new().unwrap();
+        executor
+            .spawn(async move {
+                Delay::new(core::time::Duration::from_millis(100)).await;
+                let data = reader.read().await;
+                assert_eq!(data.len(), 8);
+                reader.release(8);
+            })
+            .unwrap();
+
+        let grant = writer.grant(8).await;
+        assert_eq!(grant.len(), 8);
+    }
+}
@@ -0,0 +1,456 @@
+//! A broadcast channel where every receiver gets every message.
+//!
+//! Unlike [`PubSubChannel`](crate::pubsub::PubSubChannel), the number of receivers isn't fixed
+//! at compile time: any number of receivers can be created with [`Broadcast::subscribe`] or by
+//! cloning an existing receiver. Each receiver gets a clone of every message sent after it was
+//! created.
+//!
+//! What happens when the channel is full is chosen with a [`LagPolicy`].
+use core::cell::RefCell;
+use core::future::poll_fn;
+use core::task::{Context, Poll};
+
+use heapless::Deque;
+
+use crate::blocking_mutex::raw::RawMutex;
+use crate::blocking_mutex::Mutex;
+use crate::waitqueue::MultiWakerRegistration;
+
+/// Number of tasks that can wait on each side without being woken spuriously.
+const WAKERS: usize = 4;
+
+/// What to do when a message is sent while the channel is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum LagPolicy {
+    /// Drop the oldest message to make room. Receivers that hadn't read it yet get a
+    /// [`RecvError::Lagged`] error with the number of messages they missed.
+    ///
+    /// Sending never waits.
+    DropOldest,
+    /// Wait until the slowest receiver has read the oldest message.
+    ///
+    /// No message is ever lost, but a receiver that stops reading blocks all senders.
+    Backpressure,
+}
+
+/// Error returned by [`Receiver::recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum RecvError {
+    /// The receiver fell behind and the given number of messages were dropped before it could
+    /// read them. The next call returns the oldest message still in the channel.
+    Lagged(u64),
+}
+
+/// Error returned by [`Receiver::try_recv`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum TryRecvError {
+    /// There are no new messages for this receiver.
+    Empty,
+    /// The receiver fell behind and the given number of messages were dropped before it could
+    /// read them. The next call returns the oldest message still in the channel.
+    Lagged(u64),
+}
+
+struct State<T: Clone, const CAP: usize> {
+    /// The messages, with the number of receivers that have yet to read each of them.
+    queue: Deque<(T, usize), CAP>,
+    /// Id of the next message to be sent.
+    next_message_id: u64,
+    /// Number of receivers.
+    receiver_count: usize,
+    receiver_wakers: MultiWakerRegistration<WAKERS>,
+    sender_wakers: MultiWakerRegistration<WAKERS>,
+}
+
+impl<T: Clone, const CAP: usize> State<T, CAP> {
+    const fn new() -> Self {
+        Self {
+            queue: Deque::new(),
+            next_message_id: 0,
+            receiver_count: 0,
+            receiver_wakers: MultiWakerRegistration::new(),
+            sender_wakers: MultiWakerRegistration::new(),
+        }
+    }
+
+    /// Id of the oldest message in the queue.
+    fn start_id(&self) -> u64 {
+        self.next_message_id - self.queue.len() as u64
+    }
+
+    fn try_send(&mut self, message: T, policy: LagPolicy, cx: Option<&mut Context<'_>>) -> Result<(), T> {
+        if self.receiver_count == 0 {
+            // Nobody would ever read it.
+            return Ok(());
+        }
+
+        if self.queue.is_full() {
+            match policy {
+                LagPolicy::DropOldest => {
+                    self.queue.pop_front();
+                }
+                LagPolicy::Backpressure => {
+                    if let Some(cx) = cx {
+                        self.sender_wakers.register(cx.waker());
+                    }
+                    return Err(message);
+                }
+            }
+        }
+
+        // We just made sure there is space.
+        self.queue.push_back((message, self.receiver_count)).ok().unwrap();
+        self.next_message_id += 1;
+        self.receiver_wakers.wake();
+
+        Ok(())
+    }
+
+    fn try_recv(&mut self, next_message_id: &mut u64, cx: Option<&mut Context<'_>>) -> Result<T, TryRecvError> {
+        let start_id = self.start_id();
+        if *next_message_id < start_id {
+            let missed = start_id - *next_message_id;
+            *next_message_id = start_id;
+            return Err(TryRecvError::Lagged(missed));
+        }
+
+        let index = (*next_message_id - start_id) as usize;
+        if index >= self.queue.len() {
+            if let Some(cx) = cx {
+                self.receiver_wakers.register(cx.waker());
+            }
+            return Err(TryRecvError::Empty);
+        }
+        *next_message_id += 1;
+
+        // We've checked that the index is valid.
+        let item = self.queue.iter_mut().nth(index).unwrap();
+        item.1 -= 1;
+
+        if index == 0 && item.1 == 0 {
+            // Last receiver to read the oldest message, it can be moved out instead of cloned.
+            let (message, _) = self.queue.pop_front().unwrap();
+            self.sender_wakers.wake();
+            Ok(message)
+        } else {
+            Ok(item.0.clone())
+        }
+    }
+
+    fn add_receiver(&mut self, next_message_id: u64) {
+        self.receiver_count += 1;
+
+        let start_id = self.start_id();
+        let skip = next_message_id.saturating_sub(start_id) as usize;
+        self.queue.iter_mut().skip(skip).for_each(|(_, count)| *count += 1);
+    }
+
+    fn remove_receiver(&mut self, next_message_id: u64) {
+        self.receiver_count -= 1;
+
+        // Messages this receiver hasn't read no longer wait for it.
+        let start_id = self.start_id();
+        let skip = next_message_id.saturating_sub(start_id) as usize;
+        self.queue.iter_mut().skip(skip).for_each(|(_, count)| *count -= 1);
+
+        let mut wake_senders = false;
+        while let Some((_, 0)) = self.queue.front() {
+            self.queue.pop_front();
+            wake_senders = true;
+        }
+        if wake_senders {
+            self.sender_wakers.wake();
+        }
+    }
+
+    fn unread(&self, next_message_id: u64) -> usize {
+        (self.next_message_id - next_message_id.max(self.start_id())) as usize
+    }
+}
+
+/// A broadcast channel with a dynamic number of receivers.
+///
+/// Every message is cloned for each receiver, except for the last receiver to read it, which
+/// gets the message itself. Messages sent while there are no receivers are dropped.
+///
+/// ```
+/// use embassy_sync::blocking_mutex::raw::NoopRawMutex;
+/// use embassy_sync::broadcast::{Broadcast, LagPolicy, TryRecvError};
+///
+/// let channel = Broadcast::<NoopRawMutex, u32, 4>::new(LagPolicy::DropOldest);
+/// let mut rx0 = channel.subscribe();
+/// let mut rx1 = rx0.clone();
+///
+/// channel.try_send(42).unwrap();
+/// assert_eq!(rx0.try_recv(), Ok(42));
+/// assert_eq!(rx1.try_recv(), Ok(42));
+/// assert_eq!(rx1.try_recv(), Err(TryRecvError::Empty));
+/// ```
+pub struct Broadcast<M: RawMutex, T: Clone, const CAP: usize> {
+    inner: Mutex<M, RefCell<State<T, CAP>>>,
+    policy: LagPolicy,
+}
+
+impl<M: RawMutex, T: Clone, const CAP: usize> Broadcast<M, T, CAP> {
+    /// Create a new broadcast channel with the given lag policy.
+    pub const fn new(policy: LagPolicy) -> Self {
+        Self {
+            inner: Mutex::new(RefCell::new(State::new())),
+            policy,
+        }
+    }
+
+    fn lock<R>(&self, f: impl FnOnce(&mut State<T, CAP>) -> R) -> R {
+        self.inner.lock(|s| f(&mut s.borrow_mut()))
+    }
+
+    /// Create a new receiver, which receives the messages sent from now on.
+    pub fn subscribe(&self) -> Receiver<'_, M, T, CAP> {
+        let next_message_id = self.lock(|s| {
+            s.add_receiver(s.next_message_id);
+            s.next_message_id
+        });
+        Receiver {
+            channel: self,
+            next_message_id,
+        }
+    }
+
+    /// Try to send a message to all receivers.
+    ///
+    /// With [`LagPolicy::DropOldest`] this always succeeds. With [`LagPolicy::Backpressure`] the
+    /// message is returned back if the channel is full.
+    pub fn try_send(&self, message: T) -> Result<(), T> {
+        self.lock(|s| s.try_send(message, self.policy, None))
+    }
+
+    /// Send a message to all receivers.
+    ///
+    /// With [`LagPolicy::Backpressure`] this waits until there is space in the channel.
+    pub async fn send(&self, message: T) {
+        let mut message = Some(message);
+        poll_fn(|cx| {
+            let m = message.take().unwrap();
+            match self.lock(|s| s.try_send(m, self.policy, Some(cx))) {
+                Ok(()) => Poll::Ready(()),
+                Err(m) => {
+                    message = Some(m);
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    /// The lag policy of the channel.
+    pub fn policy(&self) -> LagPolicy {
+        self.policy
+    }
+
+    /// Number of receivers.
+    pub fn receiver_count(&self) -> usize {
+        self.lock(|s| s.receiver_count)
+    }
+
+    /// Returns the maximum number of messages the channel can hold.
+    pub const fn capacity(&self) -> usize {
+        CAP
+    }
+
+    /// Returns the number of messages that haven't been read by all receivers yet.
+    pub fn len(&self) -> usize {
+        self.lock(|s| s.queue.len())
+    }
+
+    /// Returns whether the channel is empty.
+    pub fn is_empty(&self) -> bool {
+        self.lock(|s| s.queue.is_empty())
+    }
+
+    /// Returns whether the channel is full.
+    pub fn is_full(&self) -> bool {
+        self.lock(|s| s.queue.is_full())
+    }
+}
+
+/// A receiver of a [`Broadcast`] channel.
+///
+/// Cloning a receiver creates a new receiver at the same position, which receives the same
+/// messages from then on.
+pub struct Receiver<'a, M: RawMutex, T: Clone, const CAP: usize> {
+    channel: &'a Broadcast<M, T, CAP>,
+    next_message_id: u64,
+}
+
+impl<'a, M: RawMutex, T: Clone, const CAP: usize> Receiver<'a, M, T, CAP> {
+    /// Receive the next message, waiting until one is sent.
+    pub async fn recv(&mut self) -> Result<T, RecvError> {
+        poll_fn(
+            |cx| match self.channel.lock(|s| s.try_recv(&mut self.next_message_id, Some(cx))) {
+                Ok(message) => Poll::Ready(Ok(message)),
+                Err(TryRecvError::Lagged(n)) => Poll::Ready(Err(RecvError::Lagged(n))),
+                Err(TryRecvError::Empty) => Poll::Pending,
+            },
+        )
+        .await
+    }
+
+    /// Try to receive the next message.
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        self.channel.lock(|s| s.try_recv(&mut self.next_message_id, None))
+    }
+
+    /// Number of messages this receiver can still read.
+    pub fn len(&self) -> usize {
+        self.channel.lock(|s| s.unread(self.next_message_id))
+    }
+
+    /// Returns whether there are no messages for this receiver.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, M: RawMutex, T: Clone, const CAP: usize> Clone for Receiver<'a, M, T, CAP> {
+    fn clone(&self) -> Self {
+        self.channel.lock(|s| s.add_receiver(self.next_message_id));
+        Self {
+            channel: self.channel,
+            next_message_id: self.next_message_id,
+        }
+    }
+}
+
+impl<'a, M: RawMutex, T: Clone, const CAP: usize> Drop for Receiver<'a, M, T, CAP> {
+    fn drop(&mut self) {
+        self.channel.lock(|s| s.remove_receiver(self.next_message_id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::future::Future;
+    use core::pin::pin;
+
+    use futures_util::task::noop_waker_ref;
+
+    use super::*;
+    use crate::blocking_mutex::raw::NoopRawMutex;
+
+    #[futures_test::test]
+    async fn all_receivers_receive() {
+        let channel = Broadcast::<NoopRawMutex, u32, 4>::new(LagPolicy::DropOldest);
+        let mut rx0 = channel.subscribe();
+        let mut rx1 = channel.subscribe();
+
+        channel.send(42).await;
+        channel.send(43).await;
+
+        assert_eq!(rx0.recv().await, Ok(42));
+        assert_eq!(rx0.recv().await, Ok(43));
+        assert_eq!(rx1.recv().await, Ok(42));
+        assert_eq!(rx1.recv().await, Ok(43));
+        assert_eq!(rx0.try_recv(), Err(TryRecvError::Empty));
+        assert!(channel.is_empty());
+    }
+
+    #[test]
+    fn no_receivers() {
+        let channel = Broadcast::<NoopRawMutex, u32, 4>::new(LagPolicy::Backpressure);
+        for i in 0..8 {
+            assert_eq!(channel.try_send(i), Ok(()));
+        }
+        assert!(channel.is_empty());
+
+        let mut rx = channel.subscribe();
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn drop_oldest_lags() {
+        let channel = Broadcast::<NoopRawMutex, u32, 4>::new(LagPolicy::DropOldest);
+        let mut rx = channel.subscribe();
+
+        for i in 0..6 {
+            assert_eq!(channel.try_send(i), Ok(()));
+        }
+
+        assert_eq!(rx.len(), 4);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Lagged(2)));
+        assert_eq!(rx.try_recv(), Ok(2));
+        assert_eq!(rx.try_recv(), Ok(3));
+        assert_eq!(rx.try_recv(), Ok(4));
+        assert_eq!(rx.try_recv(), Ok(5));
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    fn backpressure_waits_for_slowest() {
+        let channel = Broadcast::<NoopRawMutex, u32, 2>::new(LagPolicy::Backpressure);
+        let mut fast = channel.subscribe();
+        let mut slow = channel.subscribe();
+
+        assert_eq!(channel.try_send(1), Ok(()));
+        assert_eq!(channel.try_send(2), Ok(()));
+        assert_eq!(fast.try_recv(), Ok(1));
+        assert_eq!(fast.try_recv(), Ok(2));
+        assert_eq!(channel.try_send(3), Err(3));
+
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut send = pin!(channel.send(3));
+        assert!(send.as_mut().poll(&mut cx).is_pending());
+
+        assert_eq!(slow.try_recv(), Ok(1));
+        assert!(send.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(slow.try_recv(), Ok(2));
+        assert_eq!(slow.try_recv(), Ok(3));
+        assert_eq!(fast.try_recv(), Ok(3));
+    }
+
+    #[test]
+    fn dropping_receiver_frees_space() {
+        let channel = Broadcast::<NoopRawMutex, u32, 2>::new(LagPolicy::Backpressure);
+        let mut rx0 = channel.subscribe();
+        let rx1 = channel.subscribe();
+
+        assert_eq!(channel.try_send(1), Ok(()));
+        assert_eq!(channel.try_send(2), Ok(()));
+        assert_eq!(rx0.try_recv(), Ok(1));
+        assert!(channel.is_full());
+
+        drop(rx1);
+        assert_eq!(channel.receiver_count(), 1);
+        assert_eq!(channel.len(), 1);
+        assert_eq!(channel.try_send(3), Ok(()));
+    }
+
+    #[test]
+    fn cloned_receiver_starts_at_same_position() {
+        let channel = Broadcast::<NoopRawMutex, u32, 4>::new(LagPolicy::DropOldest);
+        let mut rx0 = channel.subscribe();
+
+        assert_eq!(channel.try_send(1), Ok(()));
+        assert_eq!(channel.try_send(2), Ok(()));
+        assert_eq!(rx0.try_recv(), Ok(1));
+
+        let mut rx1 = rx0.clone();
+        assert_eq!(channel.receiver_count(), 2);
+        assert_eq!(rx1.try_recv(), Ok(2));
+        assert_eq!(rx0.try_recv(), Ok(2));
+        assert!(channel.is_empty());
+    }
+
+    #[test]
+    fn non_copy_messages() {
+        let channel = Broadcast::<NoopRawMutex, heapless::String<8>, 4>::new(LagPolicy::DropOldest);
+        let mut rx0 = channel.subscribe();
+        let mut rx1 = channel.subscribe();
+
+        channel.try_send("hello".try_into().unwrap()).unwrap();
+        assert_eq!(rx0.try_recv().unwrap().as_str(), "hello");
+        assert_eq!(rx1.try_recv().unwrap().as_str(), "hello");
+    }
+}
@@ -17,6 +17,10 @@
 //! messages that it can store, and if this limit is reached, trying to send
 //! another message will result in an error being returned.
 //!
+//! A channel can be closed with [`Channel::close`], for example to make worker tasks exit. After
+//! that, no more messages can be sent, and receivers get the messages still in the queue and
+//! then an error from [`Channel::receive`] or [`Channel::try_receive`].
+//!
 
 use core::cell::RefCell;
 use core::future::Future;
@@ -73,6 +77,20 @@ where
         self.channel.poll_ready_to_send(cx)
     }
 
+    /// Close the channel.
+    ///
+    /// See [`Channel::close()`]
+    pub fn close(&self) {
+        self.channel.close()
+    }
+
+    /// Returns whether the channel is closed.
+    ///
+    /// See [`Channel::is_closed()`]
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+
     /// Returns the maximum number of elements the channel can hold.
     ///
     /// See [`Channel::capacity()`]
@@ -279,10 +297,24 @@ where
     /// Poll the channel for the next item
     ///
     /// See [`Channel::poll_receive()`]
-    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
+    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>> {
         self.channel.poll_receive(cx)
     }
 
+    /// Close the channel.
+    ///
+    /// See [`Channel::close()`]
+    pub fn close(&self) {
+        self.channel.close()
+    }
+
+    /// Returns whether the channel is closed.
+    ///
+    /// See [`Channel::is_closed()`]
+    pub fn is_closed(&self) -> bool {
+        self.channel.is_closed()
+    }
+
     /// Returns the maximum number of elements the channel can hold.
     ///
     /// See [`Channel::capacity()`]
@@ -374,7 +406,7 @@ impl<'ch, T> DynamicReceiver<'ch, T> {
     /// Poll the channel for the next item
     ///
     /// See [`Channel::poll_receive()`]
-    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
+    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>> {
         self.channel.poll_receive(cx)
     }
 }
@@ -429,7 +461,7 @@ impl<'ch, T> SendDynamicReceiver<'ch, T> {
     /// Poll the channel for the next item
     ///
     /// See [`Channel::poll_receive()`]
-    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
+    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>> {
         self.channel.poll_receive(cx)
     }
 }
@@ -450,7 +482,7 @@ where
     type Item = T;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.channel.poll_receive(cx).map(Some)
+        self.channel.poll_receive(cx).map(Result::ok)
     }
 }
 
@@ -467,9 +499,9 @@ impl<'ch, M, T, const N: usize> Future for ReceiveFuture<'ch, M, T, N>
 where
     M: RawMutex,
 {
-    type Output = T;
+    type Output = Result<T, ReceiveError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         self.channel.poll_receive(cx)
     }
 }
@@ -501,11 +533,12 @@ pub struct DynamicReceiveFuture<'ch, T> {
 }
 
 impl<'ch, T> Future for DynamicReceiveFuture<'ch, T> {
-    type Output = T;
+    type Output = Result<T, ReceiveError>;
 
-    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.channel.try_receive_with_context(Some(cx)) {
-            Ok(v) => Poll::Ready(v),
+            Ok(v) => Poll::Ready(Ok(v)),
+            Err(TryReceiveError::Closed) => Poll::Ready(Err(ReceiveError::Closed)),
             Err(TryReceiveError::Empty) => Poll::Pending,
         }
     }
@@ -531,16 +564,17 @@ impl<'ch, M, T, const N: usize> Future for SendFuture<'ch, M, T, N>
 where
     M: RawMutex,
 {
-    type Output = ();
+    type Output = Result<(), T>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.message.take() {
             Some(m) => match self.channel.try_send_with_context(m, Some(cx)) {
-                Ok(..) => Poll::Ready(()),
+                Ok(..) => Poll::Ready(Ok(())),
                 Err(TrySendError::Full(m)) => {
                     self.message = Some(m);
                     Poll::Pending
                 }
+                Err(TrySendError::Closed(m)) => Poll::Ready(Err(m)),
             },
             None => panic!("Message cannot be None"),
         }
@@ -557,16 +591,17 @@ pub struct DynamicSendFuture<'ch, T> {
 }
 
 impl<'ch, T> Future for DynamicSendFuture<'ch, T> {
-    type Output = ();
+    type Output = Result<(), T>;
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
         match self.message.take() {
             Some(m) => match self.channel.try_send_with_context(m, Some(cx)) {
-                Ok(..) => Poll::Ready(()),
+                Ok(..) => Poll::Ready(Ok(())),
                 Err(TrySendError::Full(m)) => {
                     self.message = Some(m);
                     Poll::Pending
                 }
+                Err(TrySendError::Closed(m)) => Poll::Ready(Err(m)),
             },
             None => panic!("Message cannot be None"),
         }
@@ -596,7 +631,7 @@ pub(crate) trait DynamicChannel<T> {
     fn poll_ready_to_send(&self, cx: &mut Context<'_>) -> Poll<()>;
     fn poll_ready_to_receive(&self, cx: &mut Context<'_>) -> Poll<()>;
 
-    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T>;
+    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>>;
 }
 
 /// Error returned by [`try_receive`](Channel::try_receive).
@@ -605,6 +640,16 @@ pub(crate) trait DynamicChannel<T> {
 pub enum TryReceiveError {
     /// A message could not be received because the channel is empty.
     Empty,
+    /// A message could not be received because the channel is empty and closed.
+    Closed,
+}
+
+/// Error returned by [`receive`](Channel::receive).
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum ReceiveError {
+    /// The channel is empty and closed, no more messages will be received.
+    Closed,
 }
 
 /// Error returned by [`try_send`](Channel::try_send).
@@ -614,10 +659,13 @@ pub enum TrySendError<T> {
     /// The data could not be sent on the channel because the channel is
     /// currently full and sending would require blocking.
     Full(T),
+    /// The data could not be sent on the channel because the channel is closed.
+    Closed(T),
 }
 
 struct ChannelState<T, const N: usize> {
     queue: Deque<T, N>,
+    closed: bool,
     receiver_waker: WakerRegistration,
     senders_waker: WakerRegistration,
 }
@@ -626,6 +674,7 @@ impl<T, const N: usize> ChannelState<T, N> {
     const fn new() -> Self {
         ChannelState {
             queue: Deque::new(),
+            closed: false,
             receiver_waker: WakerRegistration::new(),
             senders_waker: WakerRegistration::new(),
         }
@@ -652,6 +701,8 @@ impl<T, const N: usize> ChannelState<T, N> {
 
         if let Some(message) = self.queue.front() {
             Ok(message.clone())
+        } else if self.closed {
+            Err(TryReceiveError::Closed)
         } else {
             if let Some(cx) = cx {
                 self.receiver_waker.register(cx.waker());
@@ -667,6 +718,8 @@ impl<T, const N: usize> ChannelState<T, N> {
 
         if let Some(message) = self.queue.pop_front() {
             Ok(message)
+        } else if self.closed {
+            Err(TryReceiveError::Closed)
         } else {
             if let Some(cx) = cx {
                 self.receiver_waker.register(cx.waker());
@@ -675,23 +728,18 @@ impl<T, const N: usize> ChannelState<T, N> {
         }
     }
 
-    fn poll_receive(&mut self, cx: &mut Context<'_>) -> Poll<T> {
-        if self.queue.is_full() {
-            self.senders_waker.wake();
-        }
-
-        if let Some(message) = self.queue.pop_front() {
-            Poll::Ready(message)
-        } else {
-            self.receiver_waker.register(cx.waker());
-            Poll::Pending
+    fn poll_receive(&mut self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>> {
+        match self.try_receive_with_context(Some(cx)) {
+            Ok(message) => Poll::Ready(Ok(message)),
+            Err(TryReceiveError::Closed) => Poll::Ready(Err(ReceiveError::Closed)),
+            Err(TryReceiveError::Empty) => Poll::Pending,
         }
     }
 
     fn poll_ready_to_receive(&mut self, cx: &mut Context<'_>) -> Poll<()> {
         self.receiver_waker.register(cx.waker());
 
-        if !self.queue.is_empty() {
+        if !self.queue.is_empty() || self.closed {
             Poll::Ready(())
         } else {
             Poll::Pending
@@ -703,6 +751,10 @@ impl<T, const N: usize> ChannelState<T, N> {
     }
 
     fn try_send_with_context(&mut self, message: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
+        if self.closed {
+            return Err(TrySendError::Closed(message));
+        }
+
         match self.queue.push_back(message) {
             Ok(()) => {
                 self.receiver_waker.wake();
@@ -720,7 +772,7 @@ impl<T, const N: usize> ChannelState<T, N> {
     fn poll_ready_to_send(&mut self, cx: &mut Context<'_>) -> Poll<()> {
         self.senders_waker.register(cx.waker());
 
-        if !self.queue.is_full() {
+        if !self.queue.is_full() || self.closed {
             Poll::Ready(())
         } else {
             Poll::Pending
@@ -734,6 +786,12 @@ impl<T, const N: usize> ChannelState<T, N> {
         self.queue.clear();
     }
 
+    fn close(&mut self) {
+        self.closed = true;
+        self.receiver_waker.wake();
+        self.senders_waker.wake();
+    }
+
     fn len(&self) -> usize {
         self.queue.len()
     }
@@ -796,8 +854,8 @@ where
         self.lock(|c| c.try_peek_with_context(cx))
     }
 
-    /// Poll the channel for the next message
-    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
+    /// Poll the channel for the next message, or an error once the channel is closed and empty.
+    pub fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>> {
         self.lock(|c| c.poll_receive(cx))
     }
 
@@ -839,6 +897,9 @@ where
     ///
     /// Sending completes when the value has been pushed to the channel's queue.
     /// This doesn't mean the value has been received yet.
+    ///
+    /// If the channel is closed, or gets closed while waiting, the value is given back in the
+    /// error.
     pub fn send(&self, message: T) -> SendFuture<'_, M, T, N> {
         SendFuture {
             channel: self,
@@ -855,7 +916,7 @@ where
     ///
     /// If the channel capacity has been reached, i.e., the channel has `n`
     /// buffered values where `n` is the argument passed to [`Channel`], then an
-    /// error is returned.
+    /// error is returned. If the channel is closed, [`TrySendError::Closed`] is returned.
     pub fn try_send(&self, message: T) -> Result<(), TrySendError<T>> {
         self.lock(|c| c.try_send(message))
     }
@@ -863,7 +924,8 @@ where
     /// Receive the next value.
     ///
     /// If there are no messages in the channel's buffer, this method will
-    /// wait until a message is sent.
+    /// wait until a message is sent. Messages sent before the channel was closed are still
+    /// received, then [`ReceiveError::Closed`] is returned once the buffer is empty.
     pub fn receive(&self) -> ReceiveFuture<'_, M, T, N> {
         ReceiveFuture { channel: self }
     }
@@ -879,7 +941,7 @@ where
     /// Attempt to immediately receive a message.
     ///
     /// This method will either receive a message from the channel immediately or return an error
-    /// if the channel is empty.
+    /// if the channel is empty, or [`TryReceiveError::Closed`] if it's also closed.
     pub fn try_receive(&self) -> Result<T, TryReceiveError> {
         self.lock(|c| c.try_receive())
     }
@@ -912,6 +974,20 @@ where
         self.lock(|c| c.clear());
     }
 
+    /// Close the channel.
+    ///
+    /// Sending fails from then on, and waiting senders are woken. Receivers still get the
+    /// messages already in the queue, then [`receive`](Channel::receive) returns
+    /// [`ReceiveError::Closed`]. Closing can't be undone.
+    pub fn close(&self) {
+        self.lock(|c| c.close());
+    }
+
+    /// Returns whether the channel is closed.
+    pub fn is_closed(&self) -> bool {
+        self.lock(|c| c.closed)
+    }
+
     /// Returns the number of elements currently in the channel.
     pub fn len(&self) -> usize {
         self.lock(|c| c.len())
@@ -957,7 +1033,7 @@ where
         Channel::poll_ready_to_receive(self, cx)
     }
 
-    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
+    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>> {
         Channel::poll_receive(self, cx)
     }
 }
@@ -969,7 +1045,7 @@ where
     type Item = T;
 
     fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        self.poll_receive(cx).map(Some)
+        self.poll_receive(cx).map(Result::ok)
     }
 }
 
@@ -1080,14 +1156,14 @@ mod tests {
                 assert!(c2.try_send(1).is_ok());
             })
             .is_ok());
-        assert_eq!(c.receive().await, 1);
+        assert_eq!(c.receive().await, Ok(1));
     }
 
     #[futures_test::test]
     async fn sender_send_completes_if_capacity() {
         let c = Channel::<CriticalSectionRawMutex, u32, 1>::new();
-        c.send(1).await;
-        assert_eq!(c.receive().await, 1);
+        assert_eq!(c.send(1).await, Ok(()));
+        assert_eq!(c.receive().await, Ok(1));
     }
 
     #[futures_test::test]
@@ -1105,15 +1181,82 @@ mod tests {
         // Wish I could think of a means of determining that the async send is waiting instead.
         // However, I've used the debugger to observe that the send does indeed wait.
         Delay::new(Duration::from_millis(500)).await;
-        assert_eq!(c.receive().await, 1);
+        assert_eq!(c.receive().await, Ok(1));
         assert!(executor
-            .spawn(async move {
-                loop {
-                    c.receive().await;
+            .spawn(async move { while c.receive().await.is_ok() {} })
+            .is_ok());
+        assert_eq!(send_task_1.unwrap().await, Ok(()));
+        assert_eq!(send_task_2.unwrap().await, Ok(()));
+    }
+
+    #[test]
+    fn closing() {
+        let c = Channel::<NoopRawMutex, u32, 3>::new();
+        assert!(c.try_send(1).is_ok());
+        assert!(!c.is_closed());
+
+        c.sender().close();
+        assert!(c.is_closed());
+        assert_eq!(c.try_send(2), Err(TrySendError::Closed(2)));
+
+        // Messages sent before closing are still received.
+        assert_eq!(c.try_receive(), Ok(1));
+        assert_eq!(c.try_receive(), Err(TryReceiveError::Closed));
+    }
+
+    #[futures_test::test]
+    async fn receive_after_close() {
+        let executor = ThreadPool::new().unwrap();
+
+        static CHANNEL: StaticCell<Channel<CriticalSectionRawMutex, u32, 3>> = StaticCell::new();
+        let c = &*CHANNEL.init(Channel::new());
+        let receiver = c.receiver();
+
+        let worker = executor
+            .spawn_with_handle(async move {
+                let mut received = 0;
+                while let Ok(v) = receiver.receive().await {
+                    received += v;
                 }
+                received
             })
-            .is_ok());
-        send_task_1.unwrap().await;
-        send_task_2.unwrap().await;
+            .unwrap();
+
+        assert_eq!(c.send(1).await, Ok(()));
+        assert_eq!(c.send(2).await, Ok(()));
+        Delay::new(Duration::from_millis(100)).await;
+        c.close();
+        assert_eq!(worker.await, 3);
+    }
+
+    #[futures_test::test]
+    async fn close_wakes_senders() {
+        let executor = ThreadPool::new().unwrap();
+
+        static CHANNEL: StaticCell<Channel<CriticalSectionRawMutex, u32, 1>> = StaticCell::new();
+        let c = &*CHANNEL.init(Channel::new());
+        assert!(c.try_send(1).is_ok());
+
+        let send_task = executor.spawn_with_handle(async move { c.send(2).await }).unwrap();
+        Delay::new(Duration::from_millis(100)).await;
+        c.receiver().close();
+        // The message is given back to the sender.
+        assert_eq!(send_task.await, Err(2));
+
+        assert_eq!(c.len(), 1);
+    }
+
+    #[test]
+    fn close_completes_dynamic_receive() {
+        let c = Channel::<NoopRawMutex, u32, 1>::new();
+        let r = c.dyn_receiver();
+        let waker = futures_util::task::noop_waker();
+        let mut cx = Context::from_waker(&waker);
+
+        let mut fut = core::pin::pin!(r.receive());
+        assert!(fut.as_mut().poll(&mut cx).is_pending());
+        c.close();
+        assert_eq!(fut.poll(&mut cx), Poll::Ready(Err(ReceiveError::Closed)));
+        assert_eq!(c.dyn_sender().try_send(1), Err(TrySendError::Closed(1)));
     }
 }
@@ -0,0 +1,275 @@
+//! A group of event flags that tasks can wait on.
+use core::cell::RefCell;
+use core::future::{poll_fn, Future};
+use core::task::{Context, Poll};
+
+use crate::blocking_mutex::raw::RawMutex;
+use crate::blocking_mutex::Mutex;
+use crate::waitqueue::MultiWakerRegistration;
+
+/// A set of 32 event flags, which tasks can wait on.
+///
+/// Flags are set and cleared with [`EventGroup::set`] and [`EventGroup::clear`], and tasks wait
+/// until any or all of the flags in a mask are set. This replaces one [`Signal`](crate::signal::Signal)
+/// per event when a task must react to several events at once, for example a network task
+/// waiting for "link up" and "address configured".
+///
+/// Flags stay set until they're cleared, so any number of tasks waiting on the same flags are
+/// all woken. The `_and_clear` variants of the wait methods clear the flags they waited on
+/// before returning, so an event is only handled once: in that case, only the first task to
+/// run after the flags were set sees them.
+///
+/// Up to `N` tasks can wait on the group without being woken spuriously.
+///
+/// The wait methods panic if `mask` is zero: no flag in an empty mask can ever be set.
+///
+/// ```
+/// use futures_executor::block_on;
+/// use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+/// use embassy_sync::event_group::EventGroup;
+///
+/// const LINK_UP: u32 = 1 << 0;
+/// const CONFIGURED: u32 = 1 << 1;
+///
+/// static EVENTS: EventGroup<CriticalSectionRawMutex, 4> = EventGroup::new();
+///
+/// let f = async {
+///
+/// EVENTS.set(LINK_UP | CONFIGURED);
+/// let flags = EVENTS.wait_all(LINK_UP | CONFIGURED).await;
+/// assert_eq!(flags, LINK_UP | CONFIGURED);
+///
+/// };
+/// block_on(f);
+/// ```
+pub struct EventGroup<M: RawMutex, const N: usize> {
+    state: Mutex<M, RefCell<State<N>>>,
+}
+
+struct State<const N: usize> {
+    flags: u32,
+    wakers: MultiWakerRegistration<N>,
+}
+
+#[derive(Clone, Copy)]
+enum Condition {
+    Any,
+    All,
+}
+
+impl Condition {
+    fn check(self, flags: u32, mask: u32) -> bool {
+        match self {
+            Condition::Any => flags & mask != 0,
+            Condition::All => flags & mask == mask,
+        }
+    }
+}
+
+impl<M: RawMutex, const N: usize> EventGroup<M, N> {
+    /// Create a new `EventGroup` with all flags cleared.
+    pub const fn new() -> Self {
+        Self {
+            state: Mutex::new(RefCell::new(State {
+                flags: 0,
+                wakers: MultiWakerRegistration::new(),
+            })),
+        }
+    }
+
+    /// Set the flags in `mask`, waking the waiting tasks.
+    ///
+    /// Returns the flags after setting them.
+    pub fn set(&self, mask: u32) -> u32 {
+        self.state.lock(|s| {
+            let mut s = s.borrow_mut();
+            s.flags |= mask;
+            s.wakers.wake();
+            s.flags
+        })
+    }
+
+    /// Clear the flags in `mask`.
+    ///
+    /// Returns the flags before clearing them.
+    pub fn clear(&self, mask: u32) -> u32 {
+        self.state.lock(|s| {
+            let mut s = s.borrow_mut();
+            let flags = s.flags;
+            s.flags &= !mask;
+            flags
+        })
+    }
+
+    /// Returns the current flags.
+    pub fn get(&self) -> u32 {
+        self.state.lock(|s| s.borrow().flags)
+    }
+
+    fn poll_wait(&self, cx: Option<&mut Context<'_>>, mask: u32, condition: Condition, clear: bool) -> Poll<u32> {
+        self.state.lock(|s| {
+            let mut s = s.borrow_mut();
+            let flags = s.flags;
+            if condition.check(flags, mask) {
+                if clear {
+                    s.flags &= !mask;
+                }
+                Poll::Ready(flags)
+            } else {
+                if let Some(cx) = cx {
+                    s.wakers.register(cx.waker());
+                }
+                Poll::Pending
+            }
+        })
+    }
+
+    fn wait(&self, mask: u32, condition: Condition, clear: bool) -> impl Future<Output = u32> + '_ {
+        assert!(mask != 0, "EventGroup: can't wait on an empty mask");
+        poll_fn(move |cx| self.poll_wait(Some(cx), mask, condition, clear))
+    }
+
+    fn try_wait(&self, mask: u32, condition: Condition, clear: bool) -> Option<u32> {
+        assert!(mask != 0, "EventGroup: can't wait on an empty mask");
+        match self.poll_wait(None, mask, condition, clear) {
+            Poll::Ready(flags) => Some(flags),
+            Poll::Pending => None,
+        }
+    }
+
+    /// Wait until any of the flags in `mask` is set.
+    ///
+    /// Returns all the flags at the time the condition was met.
+    pub fn wait_any(&self, mask: u32) -> impl Future<Output = u32> + '_ {
+        self.wait(mask, Condition::Any, false)
+    }
+
+    /// Wait until all of the flags in `mask` are set.
+    ///
+    /// Returns all the flags at the time the condition was met.
+    pub fn wait_all(&self, mask: u32) -> impl Future<Output = u32> + '_ {
+        self.wait(mask, Condition::All, false)
+    }
+
+    /// Wait until any of the flags in `mask` is set, then clear the flags in `mask`.
+    ///
+    /// Returns all the flags at the time the condition was met, before clearing them.
+    pub fn wait_any_and_clear(&self, mask: u32) -> impl Future<Output = u32> + '_ {
+        self.wait(mask, Condition::Any, true)
+    }
+
+    /// Wait until all of the flags in `mask` are set, then clear the flags in `mask`.
+    ///
+    /// Returns all the flags at the time the condition was met, before clearing them.
+    pub fn wait_all_and_clear(&self, mask: u32) -> impl Future<Output = u32> + '_ {
+        self.wait(mask, Condition::All, true)
+    }
+
+    /// Returns the flags if any of the flags in `mask` is set, without waiting.
+    pub fn try_wait_any(&self, mask: u32) -> Option<u32> {
+        self.try_wait(mask, Condition::Any, false)
+    }
+
+    /// Returns the flags if all of the flags in `mask` are set, without waiting.
+    pub fn try_wait_all(&self, mask: u32) -> Option<u32> {
+        self.try_wait(mask, Condition::All, false)
+    }
+
+    /// Like [`try_wait_any`](Self::try_wait_any), but clears the flags in `mask` if the condition is met.
+    pub fn try_wait_any_and_clear(&self, mask: u32) -> Option<u32> {
+        self.try_wait(mask, Condition::Any, true)
+    }
+
+    /// Like [`try_wait_all`](Self::try_wait_all), but clears the flags in `mask` if the condition is met.
+    pub fn try_wait_all_and_clear(&self, mask: u32) -> Option<u32> {
+        self.try_wait(mask, Condition::All, true)
+    }
+}
+
+impl<M: RawMutex, const N: usize> Default for EventGroup<M, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_executor::ThreadPool;
+    use futures_timer::Delay;
+    use futures_util::task::SpawnExt;
+    use static_cell::StaticCell;
+
+    use super::*;
+    use crate::blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex};
+
+    #[test]
+    fn set_and_clear() {
+        let events = EventGroup::<NoopRawMutex, 2>::new();
+
+        assert_eq!(events.set(0b0101), 0b0101);
+        assert_eq!(events.set(0b0010), 0b0111);
+        assert_eq!(events.clear(0b0100), 0b0111);
+        assert_eq!(events.get(), 0b0011);
+    }
+
+    #[test]
+    fn any_and_all() {
+        let events = EventGroup::<NoopRawMutex, 2>::new();
+
+        events.set(0b01);
+        assert_eq!(events.try_wait_any(0b11), Some(0b01));
+        assert_eq!(events.try_wait_all(0b11), None);
+
+        events.set(0b10);
+        assert_eq!(events.try_wait_all(0b11), Some(0b11));
+        assert_eq!(events.get(), 0b11);
+    }
+
+    #[test]
+    fn auto_clear() {
+        let events = EventGroup::<NoopRawMutex, 2>::new();
+
+        events.set(0b111);
+        assert_eq!(events.try_wait_all_and_clear(0b011), Some(0b111));
+        assert_eq!(events.get(), 0b100);
+        assert_eq!(events.try_wait_any_and_clear(0b011), None);
+        assert_eq!(events.get(), 0b100);
+    }
+
+    #[test]
+    #[should_panic]
+    fn wait_on_empty_mask_panics() {
+        let events = EventGroup::<NoopRawMutex, 2>::new();
+
+        drop(events.wait_any(0));
+    }
+
+    #[test]
+    #[should_panic]
+    fn try_wait_on_empty_mask_panics() {
+        let events = EventGroup::<NoopRawMutex, 2>::new();
+
+        events.set(0b1);
+        let _ = events.try_wait_all(0);
+    }
+
+    #[futures_test::test]
+    async fn wait_all_from_other_task() {
+        static EVENTS: StaticCell<EventGroup<CriticalSectionRawMutex, 2>> = StaticCell::new();
+        let events = &*EVENTS.init(EventGroup::new());
+
+        let executor = ThreadPool::new().unwrap();
+        executor
+            .spawn(async move {
+                Delay::new(core::time::Duration::from_millis(50)).await;
+                events.set(0b01);
+                Delay::new(core::time::Duration::from_millis(50)).await;
+                events.set(0b10);
+            })
+            .unwrap();
+
+        assert_eq!(events.wait_any(0b01).await & 0b01, 0b01);
+        assert_eq!(events.wait_all_and_clear(0b11).await, 0b11);
+        assert_eq!(events.get(), 0);
+    }
+}
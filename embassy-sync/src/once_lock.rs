@@ -11,6 +11,10 @@ use core::task::Poll;
 /// reference to the value. This is useful for lazy initialization of
 /// a static value.
 ///
+/// The value can also be produced by an async function with
+/// [`get_or_init_async`](OnceLock::get_or_init_async), for example for a
+/// driver that must be configured over a bus before it can be shared.
+///
 /// **Note**: this implementation uses a busy loop to poll the value,
 /// which is not as efficient as registering a dedicated `Waker`.
 /// However, if the usecase for it is to initialize a static variable
@@ -39,6 +43,7 @@ use core::task::Poll;
 /// ```
 pub struct OnceLock<T> {
     init: AtomicBool,
+    initializing: AtomicBool,
     data: Cell<MaybeUninit<T>>,
 }
 
@@ -49,6 +54,7 @@ impl<T> OnceLock<T> {
     pub const fn new() -> Self {
         Self {
             init: AtomicBool::new(false),
+            initializing: AtomicBool::new(false),
             data: Cell::new(MaybeUninit::zeroed()),
         }
     }
@@ -111,6 +117,52 @@ impl<T> OnceLock<T> {
         unsafe { self.get_ref_unchecked() }
     }
 
+    /// Get a reference to the underlying value, initializing it with the async function `f` if it does not exist.
+    ///
+    /// `f` is run by at most one caller at a time. Concurrent callers wait until it finishes, and
+    /// then return the same value. If the future initializing the value is dropped before it
+    /// finishes, one of the waiting callers runs its own `f` instead.
+    pub async fn get_or_init_async<F, Fut>(&self, f: F) -> &T
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = T>,
+    {
+        let mut f = Some(f);
+        loop {
+            if let Some(data) = self.try_get() {
+                return data;
+            }
+
+            let claimed = critical_section::with(|_| {
+                let free = !self.init.load(Ordering::Relaxed) && !self.initializing.load(Ordering::Relaxed);
+                if free {
+                    self.initializing.store(true, Ordering::Relaxed);
+                }
+                free
+            });
+
+            if claimed {
+                // Let someone else initialize the value if this future is dropped.
+                let _guard = InitializingGuard(&self.initializing);
+                let value = unwrap!(f.take())().await;
+                // The value may have been set with `init` in the meantime, in which case it's kept.
+                let _ = self.init(value);
+                return unsafe { self.get_ref_unchecked() };
+            }
+
+            // Wait for the initialization to finish, or to be abandoned.
+            poll_fn(|cx| {
+                if self.init.load(Ordering::Relaxed) || !self.initializing.load(Ordering::Relaxed) {
+                    Poll::Ready(())
+                } else {
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            })
+            .await;
+        }
+    }
+
     /// Consume the `OnceLock`, returning the underlying value if it was initialized.
     pub fn into_inner(self) -> Option<T> {
         if self.init.load(Ordering::Relaxed) {
@@ -149,8 +201,18 @@ impl<T> OnceLock<T> {
     }
 }
 
+struct InitializingGuard<'a>(&'a AtomicBool);
+
+impl Drop for InitializingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Relaxed);
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use embassy_futures::yield_now;
+
     use super::*;
 
     #[test]
@@ -232,4 +294,40 @@ mod tests {
         assert_eq!(lock.get_or_init(|| 43), &43);
         assert_eq!(lock.is_set(), true);
     }
+
+    #[futures_test::test]
+    async fn once_lock_get_or_init_async() {
+        let lock: OnceLock<i32> = OnceLock::new();
+        let calls = Cell::new(0);
+        let init = || async {
+            calls.set(calls.get() + 1);
+            yield_now().await;
+            42
+        };
+
+        let (a, b) = futures_util::future::join(lock.get_or_init_async(init), lock.get_or_init_async(init)).await;
+        assert_eq!((a, b), (&42, &42));
+        assert_eq!(calls.get(), 1);
+
+        assert_eq!(lock.get_or_init_async(|| async { 43 }).await, &42);
+    }
+
+    #[futures_test::test]
+    async fn once_lock_get_or_init_async_cancelled() {
+        let lock: OnceLock<i32> = OnceLock::new();
+
+        // Start initializing, then drop the future before it finishes.
+        {
+            let fut = core::pin::pin!(lock.get_or_init_async(|| async {
+                yield_now().await;
+                42
+            }));
+            let waker = futures_util::task::noop_waker();
+            let mut cx = core::task::Context::from_waker(&waker);
+            assert!(fut.poll(&mut cx).is_pending());
+        }
+        assert_eq!(lock.is_set(), false);
+
+        assert_eq!(lock.get_or_init_async(|| async { 43 }).await, &43);
+    }
 }
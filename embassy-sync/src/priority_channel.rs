@@ -13,7 +13,7 @@ use heapless::BinaryHeap;
 
 use crate::blocking_mutex::raw::RawMutex;
 use crate::blocking_mutex::Mutex;
-use crate::channel::{DynamicChannel, DynamicReceiver, DynamicSender, TryReceiveError, TrySendError};
+use crate::channel::{DynamicChannel, DynamicReceiver, DynamicSender, ReceiveError, TryReceiveError, TrySendError};
 use crate::waitqueue::WakerRegistration;
 
 /// Send-only access to a [`PriorityChannel`].
@@ -312,7 +312,7 @@ where
         match self.message.take() {
             Some(m) => match self.channel.try_send_with_context(m, Some(cx)) {
                 Ok(..) => Poll::Ready(()),
-                Err(TrySendError::Full(m)) => {
+                Err(m) => {
                     self.message = Some(m);
                     Poll::Pending
                 }
@@ -416,11 +416,12 @@ where
         }
     }
 
-    fn try_send(&mut self, message: T) -> Result<(), TrySendError<T>> {
+    fn try_send(&mut self, message: T) -> Result<(), T> {
         self.try_send_with_context(message, None)
     }
 
-    fn try_send_with_context(&mut self, message: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
+    /// Returns the message back if the queue is full. A `PriorityChannel` can't be closed.
+    fn try_send_with_context(&mut self, message: T, cx: Option<&mut Context<'_>>) -> Result<(), T> {
         match self.queue.push(message) {
             Ok(()) => {
                 self.receiver_waker.wake();
@@ -430,7 +431,7 @@ where
                 if let Some(cx) = cx {
                     self.senders_waker.register(cx.waker());
                 }
-                Err(TrySendError::Full(message))
+                Err(message)
             }
         }
     }
@@ -525,7 +526,7 @@ where
         self.lock(|c| c.poll_receive(cx))
     }
 
-    fn try_send_with_context(&self, m: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
+    fn try_send_with_context(&self, m: T, cx: Option<&mut Context<'_>>) -> Result<(), T> {
         self.lock(|c| c.try_send_with_context(m, cx))
     }
 
@@ -571,7 +572,7 @@ where
     /// buffered values where `n` is the argument passed to [`PriorityChannel`], then an
     /// error is returned.
     pub fn try_send(&self, message: T) -> Result<(), TrySendError<T>> {
-        self.lock(|c| c.try_send(message))
+        self.lock(|c| c.try_send(message)).map_err(TrySendError::Full)
     }
 
     /// Receive the next value.
@@ -663,7 +664,7 @@ where
     M: RawMutex,
 {
     fn try_send_with_context(&self, m: T, cx: Option<&mut Context<'_>>) -> Result<(), TrySendError<T>> {
-        PriorityChannel::try_send_with_context(self, m, cx)
+        PriorityChannel::try_send_with_context(self, m, cx).map_err(TrySendError::Full)
     }
 
     fn try_receive_with_context(&self, cx: Option<&mut Context<'_>>) -> Result<T, TryReceiveError> {
@@ -685,8 +686,8 @@ where
         PriorityChannel::poll_ready_to_receive(self, cx)
     }
 
-    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<T> {
-        PriorityChannel::poll_receive(self, cx)
+    fn poll_receive(&self, cx: &mut Context<'_>) -> Poll<Result<T, ReceiveError>> {
+        PriorityChannel::poll_receive(self, cx).map(Ok)
     }
 }
 
@@ -724,10 +725,7 @@ mod tests {
         let _ = c.try_send(1);
         let _ = c.try_send(1);
         let _ = c.try_send(1);
-        match c.try_send(2) {
-            Err(TrySendError::Full(2)) => assert!(true),
-            _ => assert!(false),
-        }
+        assert_eq!(c.try_send(2), Err(2));
         assert_eq!(capacity(&c), 0);
     }
 
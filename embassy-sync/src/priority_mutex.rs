@@ -0,0 +1,606 @@
+//! Async mutex with priority inheritance.
+//!
+//! With several executors running at different priorities, for example an
+//! `InterruptExecutor` next to the thread-mode executor, a task of a high priority executor
+//! waiting for a [`Mutex`](crate::mutex::Mutex) held by a task of a low priority executor can
+//! be delayed for as long as medium priority executors keep the CPU busy: this is *priority
+//! inversion*.
+//!
+//! The [`PriorityMutex`] detects this, and temporarily raises the priority of the executor
+//! running the task holding the lock to the priority of the waiting task, until the lock is
+//! released. Since a task always runs in its executor's context, the whole executor is boosted.
+//! How priorities are read and changed is up to the [`PriorityControl`] implementation, for
+//! example by changing the NVIC priority of the interrupt running an `InterruptExecutor`.
+use core::cell::{RefCell, UnsafeCell};
+use core::fmt;
+use core::future::Future;
+use core::marker::PhantomData;
+use core::ops::{Deref, DerefMut};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use crate::blocking_mutex::raw::RawMutex;
+use crate::blocking_mutex::Mutex as BlockingMutex;
+use crate::mutex::TryLockError;
+use crate::waitqueue::WakerRegistration;
+
+/// Reads and changes the priority of the executors, for a [`PriorityMutex`].
+///
+/// Priorities are numbers where higher values are more urgent. Note that this is the opposite of
+/// the NVIC priorities on Cortex-M, where lower values are more urgent.
+pub trait PriorityControl {
+    /// Priority of the executor running the current task.
+    fn current() -> u8;
+
+    /// Raise the executor running at priority `holder` to priority `to`.
+    ///
+    /// This is called while a task of a higher priority executor waits for the lock held by a
+    /// task of the `holder` executor. The default implementation does nothing, which only
+    /// detects the inversions.
+    fn boost(holder: u8, to: u8) {
+        let _ = (holder, to);
+    }
+
+    /// Restore the executor boosted with [`boost`](Self::boost) to its priority `holder`.
+    fn restore(holder: u8) {
+        let _ = holder;
+    }
+
+    /// Called when a priority inversion is detected, for example to log it.
+    fn inversion(holder: u8, waiter: u8) {
+        let _ = (holder, waiter);
+    }
+}
+
+/// Priority inversion statistics of a [`PriorityMutex`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct InversionStats {
+    /// Number of times a task had to wait for the lock held by a lower priority task.
+    pub inversions: u32,
+    /// Highest priority of a task that had to wait for a lower priority task.
+    pub max_waiter_priority: u8,
+    /// Number of times the lock was taken.
+    pub locks: u32,
+}
+
+struct State {
+    locked: bool,
+    /// Priority of the executor of the task holding the lock.
+    holder: u8,
+    /// Priority the holder's executor has been raised to.
+    boosted: Option<u8>,
+    /// Highest priority of the tasks waiting for the lock.
+    waiting: Option<u8>,
+    /// Priority of the task the released lock is reserved for.
+    handoff: Option<u8>,
+    stats: InversionStats,
+    waker: WakerRegistration,
+}
+
+/// Async mutex with priority inheritance.
+///
+/// This works like [`Mutex`](crate::mutex::Mutex), but when a task waits for the lock held by a
+/// task running at a lower priority, the priority of the holder is raised with
+/// [`PriorityControl::boost`] until it releases the lock. Inversions are counted in
+/// [`PriorityMutex::stats`], and the holder can check [`PriorityMutexGuard::is_blocking_higher_priority`]
+/// to release the lock early instead.
+///
+/// When the lock is released, it goes to the highest priority task waiting for it, even if a
+/// lower priority task gets to run first.
+///
+/// If the waiting task stops waiting, for example because of a timeout, the holder stays
+/// boosted until it releases the lock.
+///
+/// Since the lock is shared by tasks of different executors, `M` should be
+/// [`CriticalSectionRawMutex`](crate::blocking_mutex::raw::CriticalSectionRawMutex).
+pub struct PriorityMutex<M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    state: BlockingMutex<M, RefCell<State>>,
+    _priority: PhantomData<P>,
+    inner: UnsafeCell<T>,
+}
+
+unsafe impl<M: RawMutex + Send, P: PriorityControl, T: ?Sized + Send> Send for PriorityMutex<M, P, T> {}
+unsafe impl<M: RawMutex + Sync, P: PriorityControl, T: ?Sized + Send> Sync for PriorityMutex<M, P, T> {}
+
+impl<M, P, T> PriorityMutex<M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+{
+    /// Create a new mutex with the given value.
+    pub const fn new(value: T) -> Self {
+        Self {
+            inner: UnsafeCell::new(value),
+            _priority: PhantomData,
+            state: BlockingMutex::new(RefCell::new(State {
+                locked: false,
+                holder: 0,
+                boosted: None,
+                waiting: None,
+                handoff: None,
+                stats: InversionStats {
+                    inversions: 0,
+                    max_waiter_priority: 0,
+                    locks: 0,
+                },
+                waker: WakerRegistration::new(),
+            })),
+        }
+    }
+}
+
+impl<M, P, T> PriorityMutex<M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    /// Lock the mutex.
+    ///
+    /// This will wait for the mutex to be unlocked if it's already locked, boosting the holder
+    /// if it runs at a lower priority than the current task.
+    pub fn lock(&self) -> impl Future<Output = PriorityMutexGuard<'_, M, P, T>> {
+        LockFuture {
+            mutex: self,
+            waiting: None,
+        }
+    }
+
+    /// Attempt to immediately lock the mutex.
+    ///
+    /// If the mutex is already locked, this will return an error instead of waiting.
+    pub fn try_lock(&self) -> Result<PriorityMutexGuard<'_, M, P, T>, TryLockError> {
+        let priority = P::current();
+        self.state.lock(|s| {
+            let mut s = unwrap!(s.try_borrow_mut());
+            if !s.available(priority) {
+                Err(TryLockError)
+            } else {
+                s.take(priority);
+                Ok(())
+            }
+        })?;
+
+        Ok(PriorityMutexGuard { mutex: self })
+    }
+
+    /// Returns the priority inversion statistics.
+    pub fn stats(&self) -> InversionStats {
+        self.state.lock(|s| s.borrow().stats)
+    }
+
+    /// Reset the priority inversion statistics.
+    pub fn reset_stats(&self) {
+        self.state.lock(|s| s.borrow_mut().stats = InversionStats::default())
+    }
+
+    /// Consumes this mutex, returning the underlying data.
+    pub fn into_inner(self) -> T
+    where
+        T: Sized,
+    {
+        self.inner.into_inner()
+    }
+
+    /// Returns a mutable reference to the underlying data.
+    ///
+    /// Since this call borrows the mutex mutably, no actual locking needs to
+    /// take place -- the mutable borrow statically guarantees no locks exist.
+    pub fn get_mut(&mut self) -> &mut T {
+        self.inner.get_mut()
+    }
+}
+
+impl State {
+    /// Whether a task of `priority` can take the lock now.
+    fn available(&self, priority: u8) -> bool {
+        !self.locked && self.handoff.is_none_or(|p| priority >= p)
+    }
+
+    fn take(&mut self, priority: u8) {
+        self.locked = true;
+        self.holder = priority;
+        self.handoff = None;
+        self.stats.locks = self.stats.locks.wrapping_add(1);
+    }
+
+    fn wait<P: PriorityControl>(&mut self, priority: u8) {
+        self.waiting = Some(self.waiting.map_or(priority, |p| p.max(priority)));
+        if !self.locked {
+            return;
+        }
+
+        let effective = self.boosted.unwrap_or(self.holder);
+        if priority > effective {
+            if self.boosted.is_none() {
+                self.stats.inversions = self.stats.inversions.wrapping_add(1);
+                P::inversion(self.holder, priority);
+            }
+            self.stats.max_waiter_priority = self.stats.max_waiter_priority.max(priority);
+            P::boost(self.holder, priority);
+            self.boosted = Some(priority);
+        }
+    }
+}
+
+struct LockFuture<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    mutex: &'a PriorityMutex<M, P, T>,
+    /// Priority the task waited at, if it had to.
+    waiting: Option<u8>,
+}
+
+impl<'a, M, P, T> Future for LockFuture<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    type Output = PriorityMutexGuard<'a, M, P, T>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let priority = P::current();
+        let ready = this.mutex.state.lock(|s| {
+            let mut s = unwrap!(s.try_borrow_mut());
+            if s.available(priority) {
+                s.take(priority);
+                true
+            } else {
+                s.waker.register(cx.waker());
+                s.wait::<P>(priority);
+                false
+            }
+        });
+
+        if ready {
+            this.waiting = None;
+            Poll::Ready(PriorityMutexGuard { mutex: this.mutex })
+        } else {
+            this.waiting = Some(priority);
+            Poll::Pending
+        }
+    }
+}
+
+impl<'a, M, P, T> Drop for LockFuture<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    fn drop(&mut self) {
+        let Some(priority) = self.waiting else {
+            return;
+        };
+
+        // Don't keep the lock reserved for a task that stopped waiting. The other waiters
+        // announce themselves again when they are polled.
+        self.mutex.state.lock(|s| {
+            let mut s = unwrap!(s.try_borrow_mut());
+            if s.waiting == Some(priority) {
+                s.waiting = None;
+            }
+            if s.handoff == Some(priority) {
+                s.handoff = None;
+                s.waker.wake();
+            }
+        })
+    }
+}
+
+impl<M, P, T> fmt::Debug for PriorityMutex<M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut d = f.debug_struct("PriorityMutex");
+        match self.try_lock() {
+            Ok(value) => {
+                d.field("inner", &&*value);
+            }
+            Err(TryLockError) => {
+                d.field("inner", &format_args!("<locked>"));
+            }
+        }
+
+        d.finish_non_exhaustive()
+    }
+}
+
+/// Async priority mutex guard.
+///
+/// Owning an instance of this type indicates having
+/// successfully locked the mutex, and grants access to the contents.
+///
+/// Dropping it unlocks the mutex, and restores the priority of the executor if it was boosted.
+#[clippy::has_significant_drop]
+#[must_use = "if unused the PriorityMutex will immediately unlock"]
+pub struct PriorityMutexGuard<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    mutex: &'a PriorityMutex<M, P, T>,
+}
+
+impl<'a, M, P, T> PriorityMutexGuard<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    /// Returns whether a higher priority task is waiting for the lock.
+    ///
+    /// Long operations can check this to release the lock early, and continue later.
+    pub fn is_blocking_higher_priority(&self) -> bool {
+        self.mutex.state.lock(|s| s.borrow().boosted.is_some())
+    }
+}
+
+impl<'a, M, P, T> Drop for PriorityMutexGuard<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    fn drop(&mut self) {
+        self.mutex.state.lock(|s| {
+            let mut s = unwrap!(s.try_borrow_mut());
+            if s.boosted.take().is_some() {
+                P::restore(s.holder);
+            }
+            s.locked = false;
+            s.handoff = s.waiting.take();
+            s.waker.wake();
+        })
+    }
+}
+
+impl<'a, M, P, T> Deref for PriorityMutexGuard<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    type Target = T;
+    fn deref(&self) -> &Self::Target {
+        // Safety: the guard represents exclusive access to the contents
+        // of the mutex, so it's OK to get it.
+        unsafe { &*(self.mutex.inner.get() as *const T) }
+    }
+}
+
+impl<'a, M, P, T> DerefMut for PriorityMutexGuard<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized,
+{
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // Safety: the guard represents exclusive access to the contents
+        // of the mutex, so it's OK to get it.
+        unsafe { &mut *(self.mutex.inner.get()) }
+    }
+}
+
+impl<'a, M, P, T> fmt::Debug for PriorityMutexGuard<'a, M, P, T>
+where
+    M: RawMutex,
+    P: PriorityControl,
+    T: ?Sized + fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&**self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::cell::Cell;
+    use core::future::Future;
+    use core::pin::pin;
+    use core::task::Context;
+
+    use futures_util::task::noop_waker_ref;
+
+    use super::*;
+    use crate::blocking_mutex::raw::NoopRawMutex;
+
+    const NOT_BOOSTED: u8 = 0xff;
+
+    // Per thread, since the tests run in parallel.
+    std::thread_local! {
+        static CURRENT: Cell<u8> = const { Cell::new(0) };
+        static BOOSTED: Cell<u8> = const { Cell::new(NOT_BOOSTED) };
+    }
+
+    fn set_current(priority: u8) {
+        CURRENT.set(priority);
+    }
+
+    fn boosted() -> u8 {
+        BOOSTED.get()
+    }
+
+    struct TestPriority;
+
+    impl PriorityControl for TestPriority {
+        fn current() -> u8 {
+            CURRENT.get()
+        }
+
+        fn boost(_holder: u8, to: u8) {
+            BOOSTED.set(to);
+        }
+
+        fn restore(_holder: u8) {
+            BOOSTED.set(NOT_BOOSTED);
+        }
+    }
+
+    #[test]
+    fn boosts_holder() {
+        let mutex = PriorityMutex::<NoopRawMutex, TestPriority, u32>::new(0);
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        // A low priority task holds the lock.
+        set_current(1);
+        let guard = mutex.try_lock().unwrap();
+
+        // A lower priority task waiting doesn't boost it.
+        set_current(0);
+        assert!(pin!(mutex.lock()).poll(&mut cx).is_pending());
+        assert!(!guard.is_blocking_higher_priority());
+        assert_eq!(boosted(), NOT_BOOSTED);
+
+        // A higher priority task waiting does.
+        set_current(3);
+        let mut high = pin!(mutex.lock());
+        assert!(high.as_mut().poll(&mut cx).is_pending());
+        assert!(guard.is_blocking_higher_priority());
+        assert_eq!(boosted(), 3);
+
+        drop(guard);
+        assert_eq!(boosted(), NOT_BOOSTED);
+        assert!(high.as_mut().poll(&mut cx).is_ready());
+
+        let stats = mutex.stats();
+        assert_eq!(stats.inversions, 1);
+        assert_eq!(stats.max_waiter_priority, 3);
+        assert_eq!(stats.locks, 2);
+    }
+
+    #[test]
+    fn hands_lock_to_highest_priority_waiter() {
+        let mutex = PriorityMutex::<NoopRawMutex, TestPriority, u32>::new(0);
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        set_current(1);
+        let guard = mutex.try_lock().unwrap();
+
+        set_current(2);
+        let mut medium = pin!(mutex.lock());
+        assert!(medium.as_mut().poll(&mut cx).is_pending());
+
+        set_current(4);
+        let mut high = pin!(mutex.lock());
+        assert!(high.as_mut().poll(&mut cx).is_pending());
+
+        set_current(3);
+        let mut other = pin!(mutex.lock());
+        assert!(other.as_mut().poll(&mut cx).is_pending());
+
+        drop(guard);
+
+        // The lower priority waiters run first, but the lock is kept for the highest one.
+        set_current(2);
+        assert!(medium.as_mut().poll(&mut cx).is_pending());
+        set_current(3);
+        assert!(other.as_mut().poll(&mut cx).is_pending());
+        assert!(mutex.try_lock().is_err());
+
+        set_current(4);
+        let guard = match high.as_mut().poll(&mut cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("highest priority waiter didn't get the lock"),
+        };
+        drop(guard);
+
+        set_current(2);
+        assert!(medium.as_mut().poll(&mut cx).is_pending());
+        set_current(3);
+        let guard = match other.as_mut().poll(&mut cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("next waiter didn't get the lock"),
+        };
+        drop(guard);
+
+        set_current(2);
+        assert!(medium.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(mutex.stats().locks, 4);
+    }
+
+    #[test]
+    fn cancelled_waiter_releases_handoff() {
+        let mutex = PriorityMutex::<NoopRawMutex, TestPriority, u32>::new(0);
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        set_current(1);
+        let guard = mutex.try_lock().unwrap();
+
+        set_current(2);
+        let mut low = pin!(mutex.lock());
+        assert!(low.as_mut().poll(&mut cx).is_pending());
+
+        {
+            set_current(3);
+            let mut high = pin!(mutex.lock());
+            assert!(high.as_mut().poll(&mut cx).is_pending());
+            drop(guard);
+        }
+
+        set_current(2);
+        assert!(low.as_mut().poll(&mut cx).is_ready());
+    }
+
+    #[test]
+    fn restores_priority_on_release() {
+        let mutex = PriorityMutex::<NoopRawMutex, TestPriority, u32>::new(0);
+        let mut cx = Context::from_waker(noop_waker_ref());
+
+        set_current(5);
+        let mut guard = mutex.try_lock().unwrap();
+        *guard = 1;
+        drop(guard);
+        assert_eq!(boosted(), NOT_BOOSTED);
+
+        set_current(6);
+        let guard = mutex.try_lock().unwrap();
+
+        // Waiters above the holder raise the boost to the highest of them.
+        set_current(7);
+        let mut first = pin!(mutex.lock());
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(boosted(), 7);
+
+        set_current(9);
+        let mut second = pin!(mutex.lock());
+        assert!(second.as_mut().poll(&mut cx).is_pending());
+        assert_eq!(boosted(), 9);
+
+        drop(guard);
+        assert_eq!(boosted(), NOT_BOOSTED);
+
+        // The new holder runs above the remaining waiter, so it isn't boosted.
+        set_current(9);
+        let guard = match second.as_mut().poll(&mut cx) {
+            Poll::Ready(guard) => guard,
+            Poll::Pending => panic!("highest priority waiter didn't get the lock"),
+        };
+        set_current(7);
+        assert!(first.as_mut().poll(&mut cx).is_pending());
+        assert!(!guard.is_blocking_higher_priority());
+        assert_eq!(boosted(), NOT_BOOSTED);
+        drop(guard);
+
+        assert!(first.as_mut().poll(&mut cx).is_ready());
+        assert_eq!(boosted(), NOT_BOOSTED);
+        assert_eq!(*mutex.try_lock().unwrap(), 1);
+    }
+}
@@ -22,7 +22,9 @@ use crate::waitqueue::MultiWakerRegistration;
 /// Typically, `Watch` instances are declared as `static`, and a [`Sender`] and [`Receiver`]
 /// (or [`DynSender`] and/or [`DynReceiver`]) are obtained where relevant. An [`AnonReceiver`]
 /// and [`DynAnonReceiver`] are also available, which do not increase the receiver count for the
-/// channel, and unwrapping is therefore not required, but it is not possible to `.await` the channel.
+/// channel, so any number of them can be created and unwrapping is not required. They can
+/// `.await` changes too, but only `N` waiting tasks can be tracked: beyond that, every waiting
+/// task is woken when another one starts waiting, and checks the value again.
 /// ```
 ///
 /// use futures_executor::block_on;
@@ -621,7 +623,10 @@ impl<'a, T: Clone, W: WatchBehavior<T> + ?Sized> Drop for Rcv<'a, T, W> {
     }
 }
 
-/// A anonymous receiver can NOT `.await` a change in the `Watch` value.
+/// An anonymous receiver, which doesn't count towards the `N` receivers of the `Watch`.
+///
+/// It can `.await` a change in the `Watch` value, but if more than `N` tasks are waiting on the
+/// `Watch` at the same time, they are woken spuriously.
 pub struct AnonRcv<'a, T: Clone, W: WatchBehavior<T> + ?Sized> {
     watch: &'a W,
     at_id: u64,
@@ -638,11 +643,29 @@ impl<'a, T: Clone, W: WatchBehavior<T> + ?Sized> AnonRcv<'a, T, W> {
         }
     }
 
+    /// Returns the current value of the `Watch` once it is initialized, marking it as seen.
+    ///
+    /// **Note**: Futures do nothing unless you `.await` or poll them.
+    pub fn get(&mut self) -> impl Future<Output = T> + '_ {
+        poll_fn(|cx| self.watch.poll_get(&mut self.at_id, cx))
+    }
+
     /// Tries to get the current value of the `Watch` without waiting, marking it as seen.
     pub fn try_get(&mut self) -> Option<T> {
         self.watch.try_get(Some(&mut self.at_id))
     }
 
+    /// Returns the value of the `Watch` if it matches the predicate function `f`,
+    /// or waits for it to match, marking it as seen.
+    ///
+    /// **Note**: Futures do nothing unless you `.await` or poll them.
+    pub async fn get_and<F>(&mut self, mut f: F) -> T
+    where
+        F: Fn(&T) -> bool,
+    {
+        poll_fn(|cx| self.watch.poll_get_and(&mut self.at_id, &mut f, cx)).await
+    }
+
     /// Tries to get the current value of the `Watch` if it matches the predicate
     /// function `f` without waiting, marking it as seen.
     pub fn try_get_and<F>(&mut self, mut f: F) -> Option<T>
@@ -652,11 +675,29 @@ impl<'a, T: Clone, W: WatchBehavior<T> + ?Sized> AnonRcv<'a, T, W> {
         self.watch.try_get_and(Some(&mut self.at_id), &mut f)
     }
 
+    /// Waits for the `Watch` to change and returns the new value, marking it as seen.
+    ///
+    /// **Note**: Futures do nothing unless you `.await` or poll them.
+    pub async fn changed(&mut self) -> T {
+        poll_fn(|cx| self.watch.poll_changed(&mut self.at_id, cx)).await
+    }
+
     /// Tries to get the new value of the watch without waiting, marking it as seen.
     pub fn try_changed(&mut self) -> Option<T> {
         self.watch.try_changed(&mut self.at_id)
     }
 
+    /// Waits for the `Watch` to change to a value which satisfies the predicate
+    /// function `f` and returns the new value, marking it as seen.
+    ///
+    /// **Note**: Futures do nothing unless you `.await` or poll them.
+    pub async fn changed_and<F>(&mut self, mut f: F) -> T
+    where
+        F: Fn(&T) -> bool,
+    {
+        poll_fn(|cx| self.watch.poll_changed_and(&mut self.at_id, &mut f, cx)).await
+    }
+
     /// Tries to get the new value of the watch which satisfies the predicate
     /// function `f` and returns the new value without waiting, marking it as seen.
     pub fn try_changed_and<F>(&mut self, mut f: F) -> Option<T>
@@ -667,7 +708,7 @@ impl<'a, T: Clone, W: WatchBehavior<T> + ?Sized> AnonRcv<'a, T, W> {
     }
 
     /// Checks if the `Watch` contains a value. If this returns true,
-    /// then awaiting [`Rcv::get`] will return immediately.
+    /// then awaiting [`AnonRcv::get`] will return immediately.
     pub fn contains_value(&self) -> bool {
         self.watch.contains_value()
     }
@@ -779,7 +820,11 @@ impl<'a, T: Clone> DerefMut for DynAnonReceiver<'a, T> {
 
 #[cfg(test)]
 mod tests {
+    use core::future::poll_fn;
+    use core::task::Poll;
+
     use futures_executor::block_on;
+    use futures_util::future::join4;
 
     use super::Watch;
     use crate::blocking_mutex::raw::CriticalSectionRawMutex;
@@ -997,6 +1042,41 @@ mod tests {
         block_on(f);
     }
 
+    #[test]
+    fn many_anon_receivers_await() {
+        let f = async {
+            static WATCH: Watch<CriticalSectionRawMutex, u8, 1> = Watch::new();
+
+            // More waiting receivers than tracked wakers
+            let mut rcv0 = WATCH.anon_receiver();
+            let mut rcv1 = WATCH.anon_receiver();
+            let mut rcv2 = WATCH.dyn_anon_receiver();
+            let snd = WATCH.sender();
+
+            let send = async {
+                // Let the receivers start waiting first
+                let mut yielded = false;
+                poll_fn(|cx| {
+                    if yielded {
+                        return Poll::Ready(());
+                    }
+                    yielded = true;
+                    cx.waker().wake_by_ref();
+                    Poll::Pending
+                })
+                .await;
+                snd.send(10);
+            };
+            let values = join4(rcv0.changed(), rcv1.changed(), rcv2.changed(), send).await;
+            assert_eq!((values.0, values.1, values.2), (10, 10, 10));
+
+            assert_eq!(rcv0.try_changed(), None);
+            assert_eq!(rcv1.get().await, 10);
+            assert_eq!(rcv2.get_and(|v| *v > 5).await, 10);
+        };
+        block_on(f);
+    }
+
     #[test]
     fn clone_senders() {
         let f = async {
@@ -3,7 +3,7 @@
 //! Time queue drivers may use this to simplify their implementation.
 
 use core::cmp::{min, Ordering};
-use core::task::Waker;
+use core::task::{RawWakerVTable, Waker};
 
 use heapless::Vec;
 
@@ -99,6 +99,182 @@ impl<const QUEUE_SIZE: usize> ConstGenericQueue<QUEUE_SIZE> {
     }
 }
 
+/// Identity of a waker, the pointers [`Waker::will_wake`] compares.
+type WakerKey = (usize, usize);
+
+fn waker_key(waker: &Waker) -> WakerKey {
+    (waker.data() as usize, waker.vtable() as *const RawWakerVTable as usize)
+}
+
+/// Position in the heap of a queued waker.
+#[derive(Debug, Clone, Copy)]
+struct Slot {
+    key: WakerKey,
+    heap: usize,
+}
+
+/// A timer queue with a pre-determined capacity, ordered by expiration time.
+///
+/// Unlike [`ConstGenericQueue`], the timers are kept in a binary min-heap, with an index of the
+/// queued wakers sorted by identity, so dequeuing expired timers and scheduling a new expiration
+/// time take `O(log n)` comparisons. It stays fast with thousands of timers.
+///
+/// When the queue is full, scheduling a new timer wakes the task with the latest expiration time
+/// early to make room.
+pub struct ConstGenericHeapQueue<const QUEUE_SIZE: usize> {
+    queue: Vec<Timer, QUEUE_SIZE>,
+    index: Vec<Slot, QUEUE_SIZE>,
+}
+
+impl<const QUEUE_SIZE: usize> ConstGenericHeapQueue<QUEUE_SIZE> {
+    /// Creates a new timer queue.
+    pub const fn new() -> Self {
+        Self {
+            queue: Vec::new(),
+            index: Vec::new(),
+        }
+    }
+
+    /// Schedules a task to run at a specific time, and returns whether any changes were made.
+    ///
+    /// If this function returns `true`, the called should find the next expiration time and set
+    /// a new alarm for that time.
+    pub fn schedule_wake(&mut self, at: u64, waker: &Waker) -> bool {
+        let key = waker_key(waker);
+        if let Ok(slot) = self.index.binary_search_by_key(&key, |slot| slot.key) {
+            let i = self.index[slot].heap;
+            if self.queue[i].at <= at {
+                return false;
+            }
+            self.queue[i].at = at;
+            return self.sift_up(i) == 0;
+        }
+
+        if self.queue.is_full() {
+            // Make room by waking the task that would be woken last early.
+            let latest = self.latest();
+            if self.queue[latest].at <= at {
+                waker.wake_by_ref();
+                return false;
+            }
+            self.remove(latest).waker.wake();
+        }
+
+        // Can't fail, there is room for at least one timer. The index has to be searched again,
+        // the removed timer may have moved the insertion point.
+        let slot = self.index.binary_search_by_key(&key, |slot| slot.key).unwrap_err();
+        let _ = self.index.insert(
+            slot,
+            Slot {
+                key,
+                heap: self.queue.len(),
+            },
+        );
+        let _ = self.queue.push(Timer {
+            waker: waker.clone(),
+            at,
+        });
+        self.sift_up(self.queue.len() - 1) == 0
+    }
+
+    /// Dequeues expired timers and returns the next alarm time.
+    pub fn next_expiration(&mut self, now: u64) -> u64 {
+        while let Some(timer) = self.queue.first() {
+            if timer.at > now {
+                return timer.at;
+            }
+            self.remove(0).waker.wake();
+        }
+
+        u64::MAX
+    }
+
+    /// Index of the timer with the latest expiration time. It's a leaf, in the second half of
+    /// the heap.
+    fn latest(&self) -> usize {
+        let leaves = self.queue.len() / 2;
+        self.queue[leaves..]
+            .iter()
+            .enumerate()
+            .max_by_key(|(_, timer)| timer.at)
+            .map_or(0, |(i, _)| leaves + i)
+    }
+
+    /// Removes the timer at `i` from the heap.
+    fn remove(&mut self, i: usize) -> Timer {
+        let last = self.queue.len() - 1;
+        self.swap(i, last);
+        let timer = self.queue.pop().unwrap();
+        let slot = self.slot(&timer.waker);
+        self.index.remove(slot);
+        if i < last {
+            let i = self.sift_up(i);
+            self.sift_down(i);
+        }
+        timer
+    }
+
+    /// Position in the index of a queued waker.
+    fn slot(&self, waker: &Waker) -> usize {
+        let key = waker_key(waker);
+        self.index.binary_search_by_key(&key, |slot| slot.key).unwrap()
+    }
+
+    /// Swaps two timers in the heap, and updates their positions in the index.
+    fn swap(&mut self, a: usize, b: usize) {
+        if a == b {
+            return;
+        }
+        self.queue.swap(a, b);
+        let slot = self.slot(&self.queue[a].waker);
+        self.index[slot].heap = a;
+        let slot = self.slot(&self.queue[b].waker);
+        self.index[slot].heap = b;
+    }
+
+    /// Moves the timer at `i` towards the root until its parent expires earlier, and returns
+    /// its new index.
+    fn sift_up(&mut self, mut i: usize) -> usize {
+        while i > 0 {
+            let parent = (i - 1) / 2;
+            if self.queue[parent].at <= self.queue[i].at {
+                break;
+            }
+            self.swap(parent, i);
+            i = parent;
+        }
+        i
+    }
+
+    /// Moves the timer at `i` towards the leaves until both its children expire later.
+    fn sift_down(&mut self, mut i: usize) {
+        let len = self.queue.len();
+        loop {
+            let left = 2 * i + 1;
+            let right = left + 1;
+            if left >= len {
+                break;
+            }
+            let child = if right < len && self.queue[right].at < self.queue[left].at {
+                right
+            } else {
+                left
+            };
+            if self.queue[i].at <= self.queue[child].at {
+                break;
+            }
+            self.swap(i, child);
+            i = child;
+        }
+    }
+}
+
+impl<const QUEUE_SIZE: usize> Default for ConstGenericHeapQueue<QUEUE_SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(feature = "generic-queue-8")]
 const QUEUE_SIZE: usize = 8;
 #[cfg(feature = "generic-queue-16")]
@@ -109,26 +285,38 @@ const QUEUE_SIZE: usize = 32;
 const QUEUE_SIZE: usize = 64;
 #[cfg(feature = "generic-queue-128")]
 const QUEUE_SIZE: usize = 128;
+#[cfg(feature = "generic-queue-256")]
+const QUEUE_SIZE: usize = 256;
+#[cfg(feature = "generic-queue-1024")]
+const QUEUE_SIZE: usize = 1024;
+#[cfg(feature = "generic-queue-4096")]
+const QUEUE_SIZE: usize = 4096;
 #[cfg(not(any(
     feature = "generic-queue-8",
     feature = "generic-queue-16",
     feature = "generic-queue-32",
     feature = "generic-queue-64",
-    feature = "generic-queue-128"
+    feature = "generic-queue-128",
+    feature = "generic-queue-256",
+    feature = "generic-queue-1024",
+    feature = "generic-queue-4096"
 )))]
 const QUEUE_SIZE: usize = 64;
 
+#[cfg(not(feature = "generic-queue-heap"))]
+type Inner = ConstGenericQueue<QUEUE_SIZE>;
+#[cfg(feature = "generic-queue-heap")]
+type Inner = ConstGenericHeapQueue<QUEUE_SIZE>;
+
 /// A timer queue with a pre-determined capacity.
 pub struct Queue {
-    queue: ConstGenericQueue<QUEUE_SIZE>,
+    queue: Inner,
 }
 
 impl Queue {
     /// Creates a new timer queue.
     pub const fn new() -> Self {
-        Self {
-            queue: ConstGenericQueue::new(),
-        }
+        Self { queue: Inner::new() }
     }
 
     /// Schedules a task to run at a specific time, and returns whether any changes were made.
@@ -144,3 +332,141 @@ impl Queue {
         self.queue.next_expiration(now)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use core::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::Wake;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct Flag {
+        wakes: AtomicUsize,
+    }
+
+    impl Wake for Flag {
+        fn wake(self: Arc<Self>) {
+            self.wakes.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn wakers<const N: usize>() -> ([Arc<Flag>; N], [Waker; N]) {
+        let flags: [Arc<Flag>; N] = core::array::from_fn(|_| Arc::default());
+        let wakers = core::array::from_fn(|i| Waker::from(flags[i].clone()));
+        (flags, wakers)
+    }
+
+    fn wakes(flag: &Flag) -> usize {
+        flag.wakes.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn heap_queue_wakes_in_order() {
+        let (flags, wakers) = wakers::<8>();
+        let mut queue = ConstGenericHeapQueue::<8>::new();
+
+        let times = [50, 20, 80, 10, 70, 30, 60, 40];
+        for (waker, at) in wakers.iter().zip(times) {
+            queue.schedule_wake(at, waker);
+        }
+
+        let mut sorted = times;
+        sorted.sort_unstable();
+        for (i, &at) in sorted.iter().enumerate() {
+            assert_eq!(queue.next_expiration(at - 1), at);
+            assert_eq!(
+                queue.next_expiration(at),
+                sorted.get(i + 1).copied().unwrap_or(u64::MAX)
+            );
+            let task = times.iter().position(|&t| t == at).unwrap();
+            assert_eq!(wakes(&flags[task]), 1);
+        }
+        assert!(flags.iter().all(|flag| wakes(flag) == 1));
+    }
+
+    #[test]
+    fn heap_queue_returns_whether_next_expiration_changed() {
+        let (_flags, wakers) = wakers::<3>();
+        let mut queue = ConstGenericHeapQueue::<3>::new();
+
+        assert!(queue.schedule_wake(20, &wakers[0]));
+        assert!(!queue.schedule_wake(30, &wakers[1]));
+        assert!(queue.schedule_wake(10, &wakers[2]));
+        assert_eq!(queue.next_expiration(0), 10);
+    }
+
+    #[test]
+    fn heap_queue_deduplicates_wakers() {
+        let (flags, wakers) = wakers::<2>();
+        let mut queue = ConstGenericHeapQueue::<2>::new();
+
+        assert!(queue.schedule_wake(30, &wakers[0]));
+        assert!(!queue.schedule_wake(40, &wakers[0]));
+        assert!(!queue.schedule_wake(50, &wakers[1]));
+        // Moving a waker earlier updates its entry in place.
+        assert!(queue.schedule_wake(10, &wakers[1]));
+        assert_eq!(queue.queue.len(), 2);
+
+        assert_eq!(queue.next_expiration(10), 30);
+        assert_eq!(wakes(&flags[1]), 1);
+        assert_eq!(queue.next_expiration(30), u64::MAX);
+        assert_eq!(wakes(&flags[0]), 1);
+        assert_eq!(wakes(&flags[1]), 1);
+    }
+
+    #[test]
+    fn heap_queue_wakes_a_task_early_when_full() {
+        let (flags, wakers) = wakers::<3>();
+        let mut queue = ConstGenericHeapQueue::<2>::new();
+
+        queue.schedule_wake(10, &wakers[0]);
+        queue.schedule_wake(20, &wakers[1]);
+        assert!(!queue.schedule_wake(30, &wakers[2]));
+        assert_eq!(queue.queue.len(), 2);
+        assert_eq!(flags.iter().map(|flag| wakes(flag)).sum::<usize>(), 1);
+
+        // The evicted task was woken, and the others are still woken in order.
+        assert_eq!(queue.next_expiration(100), u64::MAX);
+        assert!(flags.iter().all(|flag| wakes(flag) == 1));
+    }
+
+    #[test]
+    fn heap_queue_evicts_latest_timer_when_full() {
+        let (flags, wakers) = wakers::<6>();
+        let mut queue = ConstGenericHeapQueue::<4>::new();
+
+        for (waker, at) in wakers.iter().zip([10, 40, 20, 30]) {
+            queue.schedule_wake(at, waker);
+        }
+
+        // The timers expiring last are evicted first.
+        assert!(!queue.schedule_wake(25, &wakers[4]));
+        assert_eq!(
+            flags.iter().map(|flag| wakes(flag)).collect::<std::vec::Vec<_>>(),
+            [0, 1, 0, 0, 0, 0]
+        );
+        assert!(queue.schedule_wake(5, &wakers[5]));
+        assert_eq!(
+            flags.iter().map(|flag| wakes(flag)).collect::<std::vec::Vec<_>>(),
+            [0, 1, 0, 1, 0, 0]
+        );
+
+        // A new timer expiring after all the queued ones is woken right away.
+        let (late, late_waker) = self::wakers::<1>();
+        assert!(!queue.schedule_wake(50, &late_waker[0]));
+        assert_eq!(wakes(&late[0]), 1);
+
+        // The remaining timers are still woken in order, and can still be found by their waker.
+        assert!(!queue.schedule_wake(30, &wakers[0]));
+        assert_eq!(queue.next_expiration(5), 10);
+        assert_eq!(wakes(&flags[5]), 1);
+        assert_eq!(queue.next_expiration(10), 20);
+        assert_eq!(queue.next_expiration(20), 25);
+        assert_eq!(queue.next_expiration(25), u64::MAX);
+        assert!(flags.iter().all(|flag| wakes(flag) == 1));
+    }
+}
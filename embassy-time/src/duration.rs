@@ -2,7 +2,7 @@ use core::fmt;
 use core::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Sub, SubAssign};
 
 use super::{GCD_1K, GCD_1M, TICK_HZ};
-use crate::GCD_1G;
+use crate::{ticks_to_units, GCD_1G};
 
 #[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -29,12 +29,17 @@ impl Duration {
 
     /// Convert the `Duration` to milliseconds, rounding down.
     pub const fn as_millis(&self) -> u64 {
-        self.ticks * (1000 / GCD_1K) / (TICK_HZ / GCD_1K)
+        ticks_to_units(self.ticks, 1000, GCD_1K)
     }
 
     /// Convert the `Duration` to microseconds, rounding down.
     pub const fn as_micros(&self) -> u64 {
-        self.ticks * (1_000_000 / GCD_1M) / (TICK_HZ / GCD_1M)
+        ticks_to_units(self.ticks, 1_000_000, GCD_1M)
+    }
+
+    /// Convert the `Duration` to nanoseconds, rounding down.
+    pub const fn as_nanos(&self) -> u64 {
+        ticks_to_units(self.ticks, 1_000_000_000, GCD_1G)
     }
 
     /// Creates a duration from the specified number of clock ticks
@@ -281,15 +286,24 @@ const fn div_ceil(num: u64, den: u64) -> u64 {
 impl TryFrom<core::time::Duration> for Duration {
     type Error = <u64 as TryFrom<u128>>::Error;
 
-    /// Converts using [`Duration::from_micros`]. Fails if value can not be represented as u64.
+    /// Converts using [`Duration::from_micros`], or [`Duration::from_nanos`] with tick rates
+    /// above 1 MHz. Fails if value can not be represented as u64.
     fn try_from(value: core::time::Duration) -> Result<Self, Self::Error> {
-        Ok(Self::from_micros(value.as_micros().try_into()?))
+        if TICK_HZ > 1_000_000 {
+            Ok(Self::from_nanos(value.as_nanos().try_into()?))
+        } else {
+            Ok(Self::from_micros(value.as_micros().try_into()?))
+        }
     }
 }
 
 impl From<Duration> for core::time::Duration {
-    /// Converts using [`Duration::as_micros`].
+    /// Converts using [`Duration::as_micros`], or [`Duration::as_nanos`] with tick rates above 1 MHz.
     fn from(value: Duration) -> Self {
-        core::time::Duration::from_micros(value.as_micros())
+        if TICK_HZ > 1_000_000 {
+            core::time::Duration::from_nanos(value.as_nanos())
+        } else {
+            core::time::Duration::from_micros(value.as_micros())
+        }
     }
 }
@@ -2,6 +2,7 @@ use core::fmt;
 use core::ops::{Add, AddAssign, Sub, SubAssign};
 
 use super::{Duration, GCD_1K, GCD_1M, TICK_HZ};
+use crate::{ticks_to_units, GCD_1G};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -29,6 +30,13 @@ impl Instant {
         Self { ticks }
     }
 
+    /// Create an Instant from a nanosecond count since system boot.
+    pub const fn from_nanos(nanos: u64) -> Self {
+        Self {
+            ticks: nanos * (TICK_HZ / GCD_1G) / (1_000_000_000 / GCD_1G),
+        }
+    }
+
     /// Create an Instant from a microsecond count since system boot.
     pub const fn from_micros(micros: u64) -> Self {
         Self {
@@ -50,6 +58,17 @@ impl Instant {
         }
     }
 
+    /// Try to create an Instant from a nanosecond count since system boot.
+    /// Fails if the number of nanoseconds is too large.
+    pub const fn try_from_nanos(nanos: u64) -> Option<Self> {
+        let Some(value) = nanos.checked_mul(TICK_HZ / GCD_1G) else {
+            return None;
+        };
+        Some(Self {
+            ticks: value / (1_000_000_000 / GCD_1G),
+        })
+    }
+
     /// Try to create an Instant from a microsecond count since system boot.
     /// Fails if the number of microseconds is too large.
     pub const fn try_from_micros(micros: u64) -> Option<Self> {
@@ -93,12 +112,17 @@ impl Instant {
 
     /// Milliseconds since system boot.
     pub const fn as_millis(&self) -> u64 {
-        self.ticks * (1000 / GCD_1K) / (TICK_HZ / GCD_1K)
+        ticks_to_units(self.ticks, 1000, GCD_1K)
     }
 
     /// Microseconds since system boot.
     pub const fn as_micros(&self) -> u64 {
-        self.ticks * (1_000_000 / GCD_1M) / (TICK_HZ / GCD_1M)
+        ticks_to_units(self.ticks, 1_000_000, GCD_1M)
+    }
+
+    /// Nanoseconds since system boot.
+    pub const fn as_nanos(&self) -> u64 {
+        ticks_to_units(self.ticks, 1_000_000_000, GCD_1G)
     }
 
     /// Duration between this Instant and another Instant
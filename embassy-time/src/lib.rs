@@ -14,6 +14,7 @@ mod delay;
 mod duration;
 mod instant;
 mod timer;
+mod utc;
 
 #[cfg(feature = "mock-driver")]
 mod driver_mock;
@@ -30,7 +31,8 @@ pub use delay::{block_for, Delay};
 pub use duration::Duration;
 pub use embassy_time_driver::TICK_HZ;
 pub use instant::Instant;
-pub use timer::{with_deadline, with_timeout, Ticker, TimeoutError, Timer, WithTimeout};
+pub use timer::{with_deadline, with_timeout, MissedTickBehavior, Ticker, TimeoutError, Timer, WithTimeout};
+pub use utc::UtcTime;
 
 const fn gcd(a: u64, b: u64) -> u64 {
     if b == 0 {
@@ -44,6 +46,16 @@ pub(crate) const GCD_1K: u64 = gcd(TICK_HZ, 1_000);
 pub(crate) const GCD_1M: u64 = gcd(TICK_HZ, 1_000_000);
 pub(crate) const GCD_1G: u64 = gcd(TICK_HZ, 1_000_000_000);
 
+/// Convert a tick count to `units_per_sec` units, rounding down. `gcd` must be `gcd(TICK_HZ, units_per_sec)`.
+///
+/// Whole seconds are converted separately, so the intermediate product can't overflow
+/// even for high tick rates or units finer than a tick.
+pub(crate) const fn ticks_to_units(ticks: u64, units_per_sec: u64, gcd: u64) -> u64 {
+    let secs = ticks / TICK_HZ;
+    let rem = ticks % TICK_HZ;
+    secs * units_per_sec + rem * (units_per_sec / gcd) / (TICK_HZ / gcd)
+}
+
 #[cfg(feature = "defmt-timestamp-uptime-s")]
 defmt::timestamp! {"{=u64}", Instant::now().as_secs() }
 
@@ -224,37 +224,93 @@ impl Future for Timer {
 /// }
 /// ```
 ///
+/// ## Missed ticks
+/// Ticks are always scheduled from the original phase, so they don't drift even when some are
+/// yielded late. If the ticker is polled after more than one tick has elapsed, the behavior is
+/// selected with [`MissedTickBehavior`]: by default all missed ticks are yielded right away.
+/// [`Ticker::lag`] and [`Ticker::missed_ticks`] report how far behind the ticker got.
+///
 /// ## Cancel safety
 /// It is safe to cancel waiting for the next tick,
 /// meaning no tick is lost if the Future is dropped.
 pub struct Ticker {
     expires_at: Instant,
     duration: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+    lag: Duration,
+    missed_ticks: u64,
+}
+
+/// Behavior of a [`Ticker`] when ticks were missed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum MissedTickBehavior {
+    /// Yield all missed ticks right away, one after the other, until the ticker has caught up.
+    #[default]
+    Burst,
+    /// Yield a single tick right away and drop the other missed ticks. The following ticks stay
+    /// aligned to the original phase. Dropped ticks are counted in [`Ticker::missed_ticks`].
+    Skip,
 }
 
 impl Ticker {
     /// Creates a new ticker that ticks at the specified duration interval.
     pub fn every(duration: Duration) -> Self {
         let expires_at = Instant::now() + duration;
-        Self { expires_at, duration }
+        Self {
+            expires_at,
+            duration,
+            missed_tick_behavior: MissedTickBehavior::Burst,
+            lag: Duration::MIN,
+            missed_ticks: 0,
+        }
+    }
+
+    /// Sets the behavior when ticks are missed.
+    pub fn with_missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.missed_tick_behavior = behavior;
+        self
+    }
+
+    /// Sets the behavior when ticks are missed.
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    /// Returns the behavior when ticks are missed.
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// How late the last tick was yielded, compared to its scheduled time.
+    pub fn lag(&self) -> Duration {
+        self.lag
+    }
+
+    /// Number of ticks dropped with [`MissedTickBehavior::Skip`] since the ticker was created or
+    /// reset.
+    pub fn missed_ticks(&self) -> u64 {
+        self.missed_ticks
     }
 
     /// Resets the ticker back to its original state.
     /// This causes the ticker to go back to zero, even if the current tick isn't over yet.
     pub fn reset(&mut self) {
-        self.expires_at = Instant::now() + self.duration;
+        self.reset_at(Instant::now());
     }
 
     /// Reset the ticker at the deadline.
     /// If the deadline is in the past, the ticker will fire instantly.
     pub fn reset_at(&mut self, deadline: Instant) {
         self.expires_at = deadline + self.duration;
+        self.lag = Duration::MIN;
+        self.missed_ticks = 0;
     }
 
     /// Resets the ticker, after the specified duration has passed.
     /// If the specified duration is zero, the next tick will be after the duration of the ticker.
     pub fn reset_after(&mut self, after: Duration) {
-        self.expires_at = Instant::now() + after + self.duration;
+        self.reset_at(Instant::now() + after);
     }
 
     /// Waits for the next tick.
@@ -262,16 +318,25 @@ impl Ticker {
     /// ## Cancel safety
     /// The produced Future is cancel safe, meaning no tick is lost if the Future is dropped.
     pub fn next(&mut self) -> impl Future<Output = ()> + Send + Sync + '_ {
-        poll_fn(|cx| {
-            if self.expires_at <= Instant::now() {
-                let dur = self.duration;
-                self.expires_at += dur;
-                Poll::Ready(())
-            } else {
-                embassy_time_driver::schedule_wake(self.expires_at.as_ticks(), cx.waker());
-                Poll::Pending
+        poll_fn(|cx| self.poll_tick(cx))
+    }
+
+    fn poll_tick(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let now = Instant::now();
+        if self.expires_at <= now {
+            self.lag = now - self.expires_at;
+            let mut ticks = 1;
+            if self.missed_tick_behavior == MissedTickBehavior::Skip && self.duration.as_ticks() > 0 {
+                let missed = self.lag.as_ticks() / self.duration.as_ticks();
+                self.missed_ticks += missed;
+                ticks += missed;
             }
-        })
+            self.expires_at += Duration::from_ticks(self.duration.as_ticks() * ticks);
+            Poll::Ready(())
+        } else {
+            embassy_time_driver::schedule_wake(self.expires_at.as_ticks(), cx.waker());
+            Poll::Pending
+        }
     }
 }
 
@@ -280,14 +345,7 @@ impl Unpin for Ticker {}
 impl Stream for Ticker {
     type Item = ();
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        if self.expires_at <= Instant::now() {
-            let dur = self.duration;
-            self.expires_at += dur;
-            Poll::Ready(Some(()))
-        } else {
-            embassy_time_driver::schedule_wake(self.expires_at.as_ticks(), cx.waker());
-            Poll::Pending
-        }
+        self.poll_tick(cx).map(Some)
     }
 }
 
@@ -297,3 +355,128 @@ impl FusedStream for Ticker {
         false
     }
 }
+
+#[cfg(all(test, feature = "mock-driver"))]
+mod tests {
+    use core::task::Waker;
+
+    use serial_test::serial;
+
+    use super::*;
+    use crate::MockDriver;
+
+    fn setup() {
+        MockDriver::get().reset();
+    }
+
+    fn poll(ticker: &mut Ticker) -> bool {
+        let mut cx = Context::from_waker(Waker::noop());
+        ticker.poll_tick(&mut cx).is_ready()
+    }
+
+    #[test]
+    #[serial]
+    fn ticker_ticks_every_period() {
+        setup();
+        let driver = MockDriver::get();
+
+        let mut ticker = Ticker::every(Duration::from_secs(1));
+        assert!(!poll(&mut ticker));
+        driver.advance(Duration::from_secs(1));
+        assert!(poll(&mut ticker));
+        assert!(!poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_ticks(0));
+    }
+
+    #[test]
+    #[serial]
+    fn ticker_bursts_missed_ticks() {
+        setup();
+        let driver = MockDriver::get();
+
+        let mut ticker = Ticker::every(Duration::from_secs(1));
+        driver.advance(Duration::from_millis(3500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_millis(2500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_millis(1500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_millis(500));
+        assert!(!poll(&mut ticker));
+        assert_eq!(ticker.missed_ticks(), 0);
+
+        // The next tick keeps the original phase.
+        driver.advance(Duration::from_millis(500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_ticks(0));
+    }
+
+    #[test]
+    #[serial]
+    fn ticker_skips_missed_ticks() {
+        setup();
+        let driver = MockDriver::get();
+
+        let mut ticker = Ticker::every(Duration::from_secs(1)).with_missed_tick_behavior(MissedTickBehavior::Skip);
+        assert_eq!(ticker.missed_tick_behavior(), MissedTickBehavior::Skip);
+        driver.advance(Duration::from_millis(3500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_millis(2500));
+        assert_eq!(ticker.missed_ticks(), 2);
+        assert!(!poll(&mut ticker));
+
+        // The next tick keeps the original phase.
+        driver.advance(Duration::from_millis(500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_ticks(0));
+        assert_eq!(ticker.missed_ticks(), 2);
+    }
+
+    #[test]
+    #[serial]
+    fn ticker_reset_clears_lag() {
+        setup();
+        let driver = MockDriver::get();
+
+        let mut ticker = Ticker::every(Duration::from_secs(1)).with_missed_tick_behavior(MissedTickBehavior::Skip);
+        driver.advance(Duration::from_millis(3500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.missed_ticks(), 2);
+
+        ticker.reset();
+        assert_eq!(ticker.lag(), Duration::from_ticks(0));
+        assert_eq!(ticker.missed_ticks(), 0);
+        assert!(!poll(&mut ticker));
+        driver.advance(Duration::from_secs(1));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_ticks(0));
+    }
+
+    #[test]
+    #[serial]
+    fn ticker_reset_at_and_after_clear_lag() {
+        setup();
+        let driver = MockDriver::get();
+
+        let mut ticker = Ticker::every(Duration::from_secs(1)).with_missed_tick_behavior(MissedTickBehavior::Skip);
+        driver.advance(Duration::from_millis(2500));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_millis(1500));
+
+        ticker.reset_after(Duration::from_secs(1));
+        assert_eq!(ticker.lag(), Duration::from_ticks(0));
+        assert_eq!(ticker.missed_ticks(), 0);
+        driver.advance(Duration::from_millis(1999));
+        assert!(!poll(&mut ticker));
+        driver.advance(Duration::from_millis(1));
+        assert!(poll(&mut ticker));
+
+        driver.advance(Duration::from_secs(5));
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.missed_ticks(), 4);
+        ticker.reset_at(Instant::now() - Duration::from_secs(1));
+        assert_eq!(ticker.missed_ticks(), 0);
+        assert!(poll(&mut ticker));
+        assert_eq!(ticker.lag(), Duration::from_ticks(0));
+    }
+}
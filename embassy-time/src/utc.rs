@@ -0,0 +1,360 @@
+use core::cell::RefCell;
+use core::future::{poll_fn, Future};
+use core::ops::{Add, AddAssign, Sub, SubAssign};
+use core::task::{Poll, Waker};
+
+use critical_section::Mutex as CsMutex;
+
+use crate::{Duration, Instant};
+
+/// Bindings closer together than this don't update the drift estimate.
+const MIN_DRIFT_INTERVAL_US: i64 = 60 * 1_000_000;
+/// Larger differences between the local clock and the wall clock are treated as a step change.
+const MAX_DRIFT_PPB: i64 = 1_000_000;
+/// Number of tasks that can wait for an update without being woken spuriously.
+const MAX_WAITERS: usize = 4;
+
+/// A point in wall-clock time, as microseconds since the Unix epoch (1970-01-01 00:00:00 UTC).
+///
+/// The wall clock is kept by binding a known time, for example read from an RTC or received from
+/// a network time server, to an [`Instant`] with [`UtcTime::set`]. After that [`UtcTime::now`]
+/// extrapolates from the binding using the local clock.
+///
+/// When the clock is set again, the difference between the elapsed wall-clock time and the
+/// elapsed local time is used to estimate the drift of the local clock, which is corrected for
+/// until the next binding.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct UtcTime {
+    micros: u64,
+}
+
+#[derive(Clone, Copy)]
+struct Binding {
+    instant: Instant,
+    time: UtcTime,
+    drift_ppb: i64,
+}
+
+impl Binding {
+    fn time_at(&self, instant: Instant) -> UtcTime {
+        let elapsed = instant.as_micros() as i64 - self.instant.as_micros() as i64;
+        let correction = (elapsed as i128 * self.drift_ppb as i128 / 1_000_000_000) as i64;
+        UtcTime {
+            micros: (self.time.micros as i64 + elapsed + correction).max(0) as u64,
+        }
+    }
+}
+
+struct State {
+    binding: Option<Binding>,
+    generation: u32,
+    wakers: [Option<Waker>; MAX_WAITERS],
+}
+
+static STATE: CsMutex<RefCell<State>> = CsMutex::new(RefCell::new(State {
+    binding: None,
+    generation: 0,
+    wakers: [const { None }; MAX_WAITERS],
+}));
+
+impl UtcTime {
+    /// The Unix epoch, 1970-01-01 00:00:00 UTC.
+    pub const UNIX_EPOCH: UtcTime = UtcTime { micros: 0 };
+
+    /// Create a `UtcTime` from microseconds since the Unix epoch.
+    pub const fn from_unix_micros(micros: u64) -> Self {
+        Self { micros }
+    }
+
+    /// Create a `UtcTime` from milliseconds since the Unix epoch.
+    pub const fn from_unix_millis(millis: u64) -> Self {
+        Self { micros: millis * 1000 }
+    }
+
+    /// Create a `UtcTime` from seconds since the Unix epoch.
+    pub const fn from_unix_secs(secs: u64) -> Self {
+        Self {
+            micros: secs * 1_000_000,
+        }
+    }
+
+    /// Microseconds since the Unix epoch.
+    pub const fn as_unix_micros(&self) -> u64 {
+        self.micros
+    }
+
+    /// Milliseconds since the Unix epoch, rounding down.
+    pub const fn as_unix_millis(&self) -> u64 {
+        self.micros / 1000
+    }
+
+    /// Seconds since the Unix epoch, rounding down.
+    pub const fn as_unix_secs(&self) -> u64 {
+        self.micros / 1_000_000
+    }
+
+    /// Fractional part of the second, in microseconds.
+    pub const fn subsec_micros(&self) -> u32 {
+        (self.micros % 1_000_000) as u32
+    }
+
+    /// Returns the current wall-clock time, or `None` if the clock hasn't been set.
+    pub fn now() -> Option<Self> {
+        Self::at(Instant::now())
+    }
+
+    /// Returns the wall-clock time at the given instant, or `None` if the clock hasn't been set.
+    pub fn at(instant: Instant) -> Option<Self> {
+        critical_section::with(|cs| STATE.borrow_ref(cs).binding.map(|b| b.time_at(instant)))
+    }
+
+    /// Set the wall clock, binding `time` to the given instant.
+    ///
+    /// `at` should be the instant the time was sampled at, for example when a time server reply
+    /// was received. Wakes the tasks waiting in [`UtcTime::wait_for_update`].
+    pub fn set(time: UtcTime, at: Instant) {
+        critical_section::with(|cs| {
+            let mut state = STATE.borrow_ref_mut(cs);
+
+            let mut drift_ppb = 0;
+            if let Some(prev) = state.binding {
+                drift_ppb = prev.drift_ppb;
+                let local = at.as_micros() as i64 - prev.instant.as_micros() as i64;
+                if local >= MIN_DRIFT_INTERVAL_US {
+                    let wall = time.micros as i64 - prev.time.micros as i64;
+                    let ppb = ((wall - local) as i128 * 1_000_000_000 / local as i128) as i64;
+                    // Larger differences mean the clock was stepped, not that the local clock drifts.
+                    drift_ppb = if ppb.abs() <= MAX_DRIFT_PPB { ppb } else { 0 };
+                }
+            }
+
+            state.binding = Some(Binding {
+                instant: at,
+                time,
+                drift_ppb,
+            });
+            state.generation = state.generation.wrapping_add(1);
+            for waker in state.wakers.iter_mut().filter_map(Option::take) {
+                waker.wake();
+            }
+        })
+    }
+
+    /// Estimated drift of the local clock relative to the wall clock, in parts per billion.
+    ///
+    /// Positive when the local clock runs slow. Zero until the clock has been set twice at least
+    /// a minute apart.
+    pub fn drift_ppb() -> i64 {
+        critical_section::with(|cs| STATE.borrow_ref(cs).binding.map_or(0, |b| b.drift_ppb))
+    }
+
+    /// Wait until the wall clock is set again, and return the time it was set to.
+    pub fn wait_for_update() -> impl Future<Output = UtcTime> {
+        let mut generation = None;
+        poll_fn(move |cx| {
+            critical_section::with(|cs| {
+                let mut state = STATE.borrow_ref_mut(cs);
+                let start = *generation.get_or_insert(state.generation);
+                if state.generation != start {
+                    if let Some(binding) = state.binding {
+                        return Poll::Ready(binding.time);
+                    }
+                }
+
+                let waker = cx.waker();
+                if state.wakers.iter().flatten().any(|w| w.will_wake(waker)) {
+                    return Poll::Pending;
+                }
+                // When all slots are taken, wake the other waiters so they register again.
+                if state.wakers.iter().all(Option::is_some) {
+                    for w in state.wakers.iter_mut().filter_map(Option::take) {
+                        w.wake();
+                    }
+                }
+                if let Some(slot) = state.wakers.iter_mut().find(|w| w.is_none()) {
+                    *slot = Some(waker.clone());
+                }
+                Poll::Pending
+            })
+        })
+    }
+
+    /// Adds a Duration to self, returning a new `UtcTime` or None in the event of an overflow.
+    pub fn checked_add(&self, duration: Duration) -> Option<UtcTime> {
+        self.micros
+            .checked_add(duration.as_micros())
+            .map(|micros| UtcTime { micros })
+    }
+
+    /// Subtracts a Duration from self, returning a new `UtcTime` or None in the event of an overflow.
+    pub fn checked_sub(&self, duration: Duration) -> Option<UtcTime> {
+        self.micros
+            .checked_sub(duration.as_micros())
+            .map(|micros| UtcTime { micros })
+    }
+}
+
+impl Add<Duration> for UtcTime {
+    type Output = UtcTime;
+
+    fn add(self, other: Duration) -> UtcTime {
+        self.checked_add(other)
+            .expect("overflow when adding duration to UtcTime")
+    }
+}
+
+impl AddAssign<Duration> for UtcTime {
+    fn add_assign(&mut self, other: Duration) {
+        *self = *self + other;
+    }
+}
+
+impl Sub<Duration> for UtcTime {
+    type Output = UtcTime;
+
+    fn sub(self, other: Duration) -> UtcTime {
+        self.checked_sub(other)
+            .expect("overflow when subtracting duration from UtcTime")
+    }
+}
+
+impl SubAssign<Duration> for UtcTime {
+    fn sub_assign(&mut self, other: Duration) {
+        *self = *self - other;
+    }
+}
+
+impl Sub<UtcTime> for UtcTime {
+    type Output = Duration;
+
+    fn sub(self, other: UtcTime) -> Duration {
+        Duration::from_micros(unwrap!(self.micros.checked_sub(other.micros)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::pin::pin;
+    use core::task::Context;
+
+    use serial_test::serial;
+
+    use super::*;
+
+    /// 2024-01-01 00:00:00 UTC
+    const TIME: UtcTime = UtcTime::from_unix_secs(1_704_067_200);
+
+    fn setup() {
+        critical_section::with(|cs| {
+            let mut state = STATE.borrow_ref_mut(cs);
+            state.binding = None;
+            state.wakers = [const { None }; MAX_WAITERS];
+        });
+    }
+
+    #[test]
+    fn conversions() {
+        let t = UtcTime::from_unix_micros(1_704_067_200_123_456);
+        assert_eq!(t.as_unix_secs(), 1_704_067_200);
+        assert_eq!(t.as_unix_millis(), 1_704_067_200_123);
+        assert_eq!(t.subsec_micros(), 123_456);
+        assert_eq!(UtcTime::from_unix_millis(1_704_067_200_000), TIME);
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(TIME + Duration::from_secs(1), UtcTime::from_unix_secs(1_704_067_201));
+        assert_eq!(TIME - Duration::from_secs(1), UtcTime::from_unix_secs(1_704_067_199));
+        assert_eq!((TIME + Duration::from_millis(1500)) - TIME, Duration::from_millis(1500));
+
+        let mut t = TIME;
+        t += Duration::from_secs(2);
+        t -= Duration::from_secs(1);
+        assert_eq!(t, UtcTime::from_unix_secs(1_704_067_201));
+    }
+
+    #[test]
+    fn checked_arithmetic_does_not_wrap() {
+        let max = UtcTime::from_unix_micros(u64::MAX);
+        assert_eq!(max.checked_add(Duration::from_micros(1)), None);
+        assert_eq!(max.checked_add(Duration::from_ticks(0)), Some(max));
+        assert_eq!(UtcTime::UNIX_EPOCH.checked_sub(Duration::from_micros(1)), None);
+        assert_eq!(
+            UtcTime::from_unix_micros(1).checked_sub(Duration::from_micros(1)),
+            Some(UtcTime::UNIX_EPOCH)
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn add_overflow_panics() {
+        let _ = UtcTime::from_unix_micros(u64::MAX) + Duration::from_micros(1);
+    }
+
+    #[test]
+    #[should_panic]
+    fn sub_before_epoch_panics() {
+        let _ = UtcTime::UNIX_EPOCH - Duration::from_micros(1);
+    }
+
+    #[test]
+    #[serial]
+    fn set_and_get() {
+        setup();
+
+        assert_eq!(UtcTime::at(Instant::from_secs(10)), None);
+
+        UtcTime::set(TIME, Instant::from_secs(10));
+        assert_eq!(UtcTime::at(Instant::from_secs(10)), Some(TIME));
+        assert_eq!(UtcTime::at(Instant::from_secs(15)), Some(TIME + Duration::from_secs(5)));
+        assert_eq!(UtcTime::at(Instant::from_secs(5)), Some(TIME - Duration::from_secs(5)));
+        assert_eq!(UtcTime::drift_ppb(), 0);
+    }
+
+    #[test]
+    #[serial]
+    fn time_before_epoch_saturates() {
+        setup();
+
+        UtcTime::set(UtcTime::from_unix_secs(1), Instant::from_secs(10));
+        assert_eq!(UtcTime::at(Instant::from_secs(5)), Some(UtcTime::UNIX_EPOCH));
+    }
+
+    #[test]
+    #[serial]
+    fn drift_is_estimated_and_corrected() {
+        setup();
+
+        UtcTime::set(TIME, Instant::from_secs(0));
+        // The wall clock advanced 100 ms more than the local clock in 100 s: 1000 ppm slow.
+        let time = TIME + Duration::from_millis(100_100);
+        UtcTime::set(time, Instant::from_secs(100));
+        assert_eq!(UtcTime::drift_ppb(), 1_000_000);
+        assert_eq!(
+            UtcTime::at(Instant::from_secs(200)),
+            Some(time + Duration::from_millis(100_100))
+        );
+
+        // Bindings less than a minute apart keep the previous estimate.
+        let time = time + Duration::from_secs(10);
+        UtcTime::set(time, Instant::from_secs(110));
+        assert_eq!(UtcTime::drift_ppb(), 1_000_000);
+
+        // A step change of the wall clock is not drift.
+        UtcTime::set(time + Duration::from_secs(3600), Instant::from_secs(200));
+        assert_eq!(UtcTime::drift_ppb(), 0);
+    }
+
+    #[test]
+    #[serial]
+    fn wait_for_update() {
+        setup();
+        let mut cx = Context::from_waker(Waker::noop());
+
+        let mut update = pin!(UtcTime::wait_for_update());
+        assert_eq!(update.as_mut().poll(&mut cx), Poll::Pending);
+        UtcTime::set(TIME, Instant::from_secs(10));
+        assert_eq!(update.as_mut().poll(&mut cx), Poll::Ready(TIME));
+    }
+}
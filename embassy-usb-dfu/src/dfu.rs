@@ -2,7 +2,7 @@ use embassy_boot::{AlignedBuffer, BlockingFirmwareUpdater, FirmwareUpdaterError}
 use embassy_usb::control::{InResponse, OutResponse, Recipient, RequestType};
 use embassy_usb::driver::Driver;
 use embassy_usb::{Builder, FunctionBuilder, Handler};
-use embedded_storage::nor_flash::{NorFlash, NorFlashErrorKind};
+use embedded_storage::nor_flash::{ErrorType, NorFlash, NorFlashErrorKind, ReadNorFlash};
 
 use crate::consts::{
     DfuAttributes, Request, State, Status, APPN_SPEC_SUBCLASS_DFU, DESC_DFU_FUNCTIONAL, DFU_PROTOCOL_DFU,
@@ -11,12 +11,14 @@ use crate::consts::{
 use crate::Reset;
 
 /// Internal state for USB DFU
-pub struct Control<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> {
+pub struct Control<'d, DFU: NorFlash, STATE: NorFlash, ACTIVE: ReadNorFlash, RST: Reset, const BLOCK_SIZE: usize> {
     updater: BlockingFirmwareUpdater<'d, DFU, STATE>,
+    active: Option<ACTIVE>,
     attrs: DfuAttributes,
     state: State,
     status: Status,
     offset: usize,
+    manifested: bool,
     buf: AlignedBuffer<BLOCK_SIZE>,
     reset: RST,
 
@@ -24,20 +26,48 @@ pub struct Control<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_S
     public_key: &'static [u8; 32],
 }
 
-impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Control<'d, DFU, STATE, RST, BLOCK_SIZE> {
+/// Flash type of a [`Control`] that doesn't support DFU_UPLOAD.
+pub enum NoUpload {}
+
+impl ErrorType for NoUpload {
+    type Error = NorFlashErrorKind;
+}
+
+impl ReadNorFlash for NoUpload {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, _offset: u32, _bytes: &mut [u8]) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    fn capacity(&self) -> usize {
+        match *self {}
+    }
+}
+
+impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize>
+    Control<'d, DFU, STATE, NoUpload, RST, BLOCK_SIZE>
+{
     /// Create a new DFU instance to handle DFU transfers.
+    ///
+    /// `CAN_UPLOAD` is ignored, use [`Control::with_upload`] to let the host read back the firmware.
     pub fn new(
         updater: BlockingFirmwareUpdater<'d, DFU, STATE>,
         attrs: DfuAttributes,
         reset: RST,
         #[cfg(feature = "_verify")] public_key: &'static [u8; 32],
     ) -> Self {
+        let mut attrs = attrs;
+        attrs.remove(DfuAttributes::CAN_UPLOAD);
+
         Self {
             updater,
+            active: None,
             attrs,
             state: State::DfuIdle,
             status: Status::Ok,
             offset: 0,
+            manifested: false,
             buf: AlignedBuffer([0; BLOCK_SIZE]),
             reset,
 
@@ -46,11 +76,60 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Co
         }
     }
 
+    /// Serve DFU_UPLOAD requests by reading back the firmware from the `active` partition.
+    ///
+    /// This sets `CAN_UPLOAD` in the DFU attributes.
+    pub fn with_upload<ACTIVE: ReadNorFlash>(self, active: ACTIVE) -> Control<'d, DFU, STATE, ACTIVE, RST, BLOCK_SIZE> {
+        let mut attrs = self.attrs;
+        attrs.insert(DfuAttributes::CAN_UPLOAD);
+
+        Control {
+            updater: self.updater,
+            active: Some(active),
+            attrs,
+            state: self.state,
+            status: self.status,
+            offset: self.offset,
+            manifested: self.manifested,
+            buf: self.buf,
+            reset: self.reset,
+
+            #[cfg(feature = "_verify")]
+            public_key: self.public_key,
+        }
+    }
+}
+
+impl<'d, DFU: NorFlash, STATE: NorFlash, ACTIVE: ReadNorFlash, RST: Reset, const BLOCK_SIZE: usize>
+    Control<'d, DFU, STATE, ACTIVE, RST, BLOCK_SIZE>
+{
     fn reset_state(&mut self) {
         self.offset = 0;
         self.state = State::DfuIdle;
         self.status = Status::Ok;
     }
+
+    /// Advance the manifestation phase on DFU_GETSTATUS.
+    ///
+    /// The host is told about the `Manifest` state first, then the device either goes back to
+    /// `DfuIdle` when it is manifestation tolerant, or waits for a USB reset to boot the new firmware.
+    fn manifest(&mut self) {
+        match self.state {
+            State::ManifestSync => self.state = State::Manifest,
+            State::Manifest if self.attrs.contains(DfuAttributes::MANIFESTATION_TOLERANT) => {
+                self.state = State::DfuIdle
+            }
+            State::Manifest if self.attrs.contains(DfuAttributes::WILL_DETACH) => {
+                info!("Manifestation complete, resetting");
+                self.reset.sys_reset()
+            }
+            State::Manifest => {
+                info!("Manifestation complete, awaiting USB reset");
+                self.state = State::ManifestWaitReset
+            }
+            _ => {}
+        }
+    }
 }
 
 impl From<FirmwareUpdaterError> for Status {
@@ -67,9 +146,16 @@ impl From<FirmwareUpdaterError> for Status {
     }
 }
 
-impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Handler
-    for Control<'d, DFU, STATE, RST, BLOCK_SIZE>
+impl<'d, DFU: NorFlash, STATE: NorFlash, ACTIVE: ReadNorFlash, RST: Reset, const BLOCK_SIZE: usize> Handler
+    for Control<'d, DFU, STATE, ACTIVE, RST, BLOCK_SIZE>
 {
+    fn reset(&mut self) {
+        if self.manifested {
+            info!("USB reset after update, resetting");
+            self.reset.sys_reset()
+        }
+    }
+
     fn control_out(
         &mut self,
         req: embassy_usb::control::Request,
@@ -108,9 +194,15 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Ha
 
                 debug!("Copying {} bytes to buffer", data.len());
                 self.buf.as_mut()[..data.len()].copy_from_slice(data);
+                // Don't write leftovers of the previous block when the last one is short.
+                self.buf.as_mut()[data.len()..].fill(0);
 
                 let final_transfer = req.length == 0;
-                if final_transfer {
+                if final_transfer && self.offset == 0 {
+                    error!("Received final transfer without any firmware");
+                    self.state = State::Error;
+                    self.status = Status::ErrNotDone;
+                } else if final_transfer {
                     debug!("Receiving final transfer");
 
                     #[cfg(feature = "_verify")]
@@ -133,6 +225,7 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Ha
                         Ok(_) => {
                             self.status = Status::Ok;
                             self.state = State::ManifestSync;
+                            self.manifested = true;
                             info!("Update complete");
                         }
                         Err(e) => {
@@ -182,7 +275,7 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Ha
             Ok(Request::GetStatus) => {
                 match self.state {
                     State::DlSync => self.state = State::Download,
-                    State::ManifestSync => self.reset.sys_reset(),
+                    State::ManifestSync | State::Manifest => self.manifest(),
                     _ => {}
                 }
 
@@ -195,8 +288,43 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Ha
                 Some(InResponse::Accepted(&buf[0..1]))
             }
             Ok(Request::Upload) if self.attrs.contains(DfuAttributes::CAN_UPLOAD) => {
-                //TODO: FirmwareUpdater provides a way of reading the active partition so we could in theory add functionality to upload firmware.
-                Some(InResponse::Rejected)
+                let active = self.active.as_mut()?;
+                match self.state {
+                    State::DfuIdle => {
+                        info!("Upload starting");
+                        self.state = State::UploadIdle;
+                        self.offset = 0;
+                    }
+                    State::UploadIdle => {}
+                    _ => {
+                        error!("Unexpected UPLOAD in state {}", self.state as u8);
+                        self.status = Status::ErrUnknown;
+                        self.state = State::Error;
+                        return Some(InResponse::Rejected);
+                    }
+                }
+
+                // The active partition is read back until its end, a short block tells the host that
+                // the upload is complete.
+                let requested = (req.length as usize).min(buf.len()).min(BLOCK_SIZE);
+                let len = requested.min(active.capacity().saturating_sub(self.offset));
+                match active.read(self.offset as u32, &mut buf[..len]) {
+                    Ok(()) if len < requested => {
+                        info!("Upload complete");
+                        self.reset_state();
+                        Some(InResponse::Accepted(&buf[..len]))
+                    }
+                    Ok(()) => {
+                        self.offset += len;
+                        Some(InResponse::Accepted(&buf[..len]))
+                    }
+                    Err(_) => {
+                        error!("Error reading firmware at {}", self.offset);
+                        self.state = State::Error;
+                        self.status = Status::ErrUnknown;
+                        Some(InResponse::Rejected)
+                    }
+                }
             }
             _ => None,
         }
@@ -209,10 +337,20 @@ impl<'d, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize> Ha
 /// The handler is responsive to DFU GetState, GetStatus, Abort, and ClrStatus commands, as well as Download if configured by the user.
 ///
 /// Once the host has initiated a DFU download operation, the chunks sent by the host will be written to the DFU partition.
-/// Once the final sync in the manifestation phase has been received, the handler will trigger a system reset to swap the new firmware.
-pub fn usb_dfu<'d, D: Driver<'d>, DFU: NorFlash, STATE: NorFlash, RST: Reset, const BLOCK_SIZE: usize>(
+/// Once the download is complete, the update is marked in the bootloader state and the manifestation phase is reported to the host.
+/// The handler then triggers a system reset to swap the new firmware on the next USB reset, or right away when `WILL_DETACH` is set.
+/// When the Control is created [`with_upload`](Control::with_upload), the host can read back the active partition.
+pub fn usb_dfu<
+    'd,
+    D: Driver<'d>,
+    DFU: NorFlash,
+    STATE: NorFlash,
+    ACTIVE: ReadNorFlash,
+    RST: Reset,
+    const BLOCK_SIZE: usize,
+>(
     builder: &mut Builder<'d, D>,
-    handler: &'d mut Control<'d, DFU, STATE, RST, BLOCK_SIZE>,
+    handler: &'d mut Control<'d, DFU, STATE, ACTIVE, RST, BLOCK_SIZE>,
     func_modifier: impl Fn(&mut FunctionBuilder<'_, 'd, D>),
 ) {
     let mut func = builder.function(USB_CLASS_APPN_SPEC, APPN_SPEC_SUBCLASS_DFU, DFU_PROTOCOL_DFU);
@@ -0,0 +1,156 @@
+//! USB host driver traits.
+//!
+//! A host driver manages the root port and a set of channels. Each channel is bound to one
+//! endpoint of one device and performs transfers on it.
+
+use crate::{Direction, EndpointInfo};
+
+/// Speed of a device attached to a host port.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Speed {
+    /// Low speed (1.5 Mbit/s).
+    Low,
+    /// Full speed (12 Mbit/s).
+    Full,
+    /// High speed (480 Mbit/s).
+    High,
+}
+
+/// Event on the root port of a host.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum DeviceEvent {
+    /// A device has been connected. It must be reset with [`UsbHostDriver::bus_reset`] before it
+    /// can be used.
+    Connected,
+    /// The device has been disconnected.
+    Disconnected,
+}
+
+/// Errors returned by host transfers.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum HostError {
+    /// The device answered with a STALL handshake.
+    Stall,
+    /// The device has been disconnected.
+    Disconnected,
+    /// The transaction failed: CRC error, timeout, babble or data toggle mismatch.
+    TransactionError,
+    /// The device sent more data than fits in the buffer.
+    BufferOverflow,
+}
+
+/// Allocating a channel failed.
+///
+/// This can be due to running out of channels, or because the hardware doesn't support the
+/// requested endpoint type.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct ChannelAllocError;
+
+/// SETUP packet of a control transfer.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SetupPacket {
+    /// `bmRequestType` field.
+    pub request_type: u8,
+    /// `bRequest` field.
+    pub request: u8,
+    /// `wValue` field.
+    pub value: u16,
+    /// `wIndex` field.
+    pub index: u16,
+    /// `wLength` field.
+    pub length: u16,
+}
+
+impl SetupPacket {
+    /// Direction of the data stage, taken from `bmRequestType`.
+    pub fn direction(&self) -> Direction {
+        if self.request_type & 0x80 != 0 {
+            Direction::In
+        } else {
+            Direction::Out
+        }
+    }
+
+    /// Serializes the packet as sent on the bus.
+    pub fn to_bytes(&self) -> [u8; 8] {
+        let value = self.value.to_le_bytes();
+        let index = self.index.to_le_bytes();
+        let length = self.length.to_le_bytes();
+        [
+            self.request_type,
+            self.request,
+            value[0],
+            value[1],
+            index[0],
+            index[1],
+            length[0],
+            length[1],
+        ]
+    }
+}
+
+/// Main USB host driver trait.
+///
+/// Implement this to add host support for a new hardware platform.
+pub trait UsbHostDriver {
+    /// Type of the channels for this driver.
+    type Channel: UsbChannel;
+
+    /// Waits for a device to be connected to or disconnected from the root port.
+    async fn wait_for_device_event(&mut self) -> DeviceEvent;
+
+    /// Resets the device connected to the root port and enables the port.
+    ///
+    /// Returns the speed of the device.
+    async fn bus_reset(&mut self) -> Result<Speed, HostError>;
+
+    /// Allocates a channel for an endpoint of a device.
+    ///
+    /// `device_address` is 0 until the device has been assigned an address with `SET_ADDRESS`,
+    /// see [`UsbChannel::set_device_address`]. Only the index, type, maximum packet size and
+    /// interval of `endpoint` are used, the direction of control channels is ignored.
+    fn alloc_channel(
+        &mut self,
+        device_address: u8,
+        endpoint: &EndpointInfo,
+        speed: Speed,
+    ) -> Result<Self::Channel, ChannelAllocError>;
+}
+
+/// Channel to an endpoint of a device.
+///
+/// The channel is released when dropped.
+pub trait UsbChannel {
+    /// Get the endpoint information.
+    fn info(&self) -> &EndpointInfo;
+
+    /// Changes the device address the channel talks to.
+    ///
+    /// This is used after the device has accepted a `SET_ADDRESS` request.
+    fn set_device_address(&mut self, address: u8);
+
+    /// Performs a control transfer with an IN data stage, or no data stage.
+    ///
+    /// Returns the number of bytes received.
+    async fn control_in(&mut self, setup: &SetupPacket, buf: &mut [u8]) -> Result<usize, HostError>;
+
+    /// Performs a control transfer with an OUT data stage, or no data stage.
+    async fn control_out(&mut self, setup: &SetupPacket, buf: &[u8]) -> Result<(), HostError>;
+
+    /// Reads from an IN endpoint.
+    ///
+    /// Packets are read until `buf` is full or a short packet is received. Returns the number of
+    /// bytes received.
+    async fn request_in(&mut self, buf: &mut [u8]) -> Result<usize, HostError>;
+
+    /// Writes to an OUT endpoint.
+    ///
+    /// `buf` is split in packets of the maximum packet size. A zero-length packet is sent if
+    /// `buf` is empty.
+    async fn request_out(&mut self, buf: &[u8]) -> Result<(), HostError>;
+}
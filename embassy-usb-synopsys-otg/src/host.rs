@@ -0,0 +1,910 @@
+//! USB host mode.
+//!
+//! The core is used in slave mode: every transaction is a single packet, handled by one channel.
+//! Data is copied from and to the FIFOs by the CPU.
+
+use core::cell::UnsafeCell;
+use core::future::poll_fn;
+use core::sync::atomic::{AtomicBool, AtomicU16, AtomicU32, Ordering};
+use core::task::Poll;
+
+use embassy_hal_internal::drop::OnDrop;
+use embassy_sync::waitqueue::AtomicWaker;
+use embassy_usb_driver::host::{ChannelAllocError, DeviceEvent, HostError, SetupPacket, Speed, UsbChannel};
+use embassy_usb_driver::{Direction, EndpointInfo, EndpointType};
+
+use crate::otg_v1::{regs, vals, Otg};
+use crate::{to_eptyp, PhyType};
+
+/// Maximum number of host channels supported by the core.
+pub const MAX_CHANNEL_COUNT: usize = 12;
+
+/// Indicates that no IN packet has been received.
+const RX_EMPTY: u16 = u16::MAX;
+/// Indicates that the received IN packet did not fit in the buffer.
+const RX_OVERFLOW: u16 = u16::MAX - 1;
+
+/// The SETUP PID shares its encoding with MDATA in HCTSIZ.
+const PID_SETUP: vals::Dpid = vals::Dpid::MDATA;
+
+/// HCINT bits that end a transaction: XFRC, STALL, NAK, TXERR, BBERR, FRMOR and DTERR.
+const HCINT_DONE_MASK: u32 = 1 << 0 | 1 << 3 | 1 << 4 | 1 << 7 | 1 << 8 | 1 << 9 | 1 << 10;
+
+/// Number of times a transaction is retried after a transmission error.
+const MAX_ERROR_RETRIES: u8 = 3;
+
+struct ChannelState {
+    waker: AtomicWaker,
+    /// HCINT bits collected by the interrupt handler since the transaction started.
+    ints: AtomicU32,
+    /// Buffer for the IN packet being received.
+    buffer: UnsafeCell<*mut u8>,
+    buffer_len: AtomicU16,
+    /// Number of bytes received, [RX_EMPTY] or [RX_OVERFLOW].
+    received: AtomicU16,
+}
+
+// SAFETY: The buffer is only set by the channel owning this state before enabling the channel, and only
+// written by the interrupt while the channel is enabled. Access is synchronized through the received atomic.
+unsafe impl Send for ChannelState {}
+unsafe impl Sync for ChannelState {}
+
+struct PortState {
+    waker: AtomicWaker,
+    connected: AtomicBool,
+    /// Set when the connection state changed and has not been reported yet.
+    changed: AtomicBool,
+    /// Bitmask of allocated channels.
+    allocated: AtomicU16,
+}
+
+/// USB OTG host driver state.
+pub struct HostState<const CH_COUNT: usize> {
+    port: PortState,
+    channels: [ChannelState; CH_COUNT],
+}
+
+impl<const CH_COUNT: usize> HostState<CH_COUNT> {
+    /// Create a new State.
+    pub const fn new() -> Self {
+        Self {
+            port: PortState {
+                waker: AtomicWaker::new(),
+                connected: AtomicBool::new(false),
+                changed: AtomicBool::new(false),
+                allocated: AtomicU16::new(0),
+            },
+            channels: [const {
+                ChannelState {
+                    waker: AtomicWaker::new(),
+                    ints: AtomicU32::new(0),
+                    buffer: UnsafeCell::new(0 as _),
+                    buffer_len: AtomicU16::new(0),
+                    received: AtomicU16::new(RX_EMPTY),
+                }
+            }; CH_COUNT],
+        }
+    }
+}
+
+/// Reads HPRT with the write-1-to-clear bits masked, so that the value can be written back.
+fn hprt_rw(r: Otg) -> regs::Hprt {
+    let mut hprt = r.hprt().read();
+    hprt.set_pena(false);
+    hprt.set_pcdet(false);
+    hprt.set_penchng(false);
+    hprt.set_pocchng(false);
+    hprt
+}
+
+/// Requests the channel to be halted if it is enabled.
+fn disable_channel(r: Otg, index: usize) {
+    if r.hcchar(index).read().chena() {
+        r.hcchar(index).modify(|w| {
+            w.set_chdis(true);
+            w.set_chena(true);
+        });
+    }
+}
+
+/// Handle host mode interrupts.
+pub unsafe fn on_host_interrupt<const CH_COUNT: usize>(r: Otg, state: &HostState<CH_COUNT>, channel_count: usize) {
+    trace!("host irq");
+
+    let ints = r.gintsts().read();
+
+    if ints.hprtint() {
+        let hprt = r.hprt().read();
+        let mut clear = hprt_rw(r);
+        if hprt.pcdet() {
+            clear.set_pcdet(true);
+            state.port.connected.store(true, Ordering::Relaxed);
+            state.port.changed.store(true, Ordering::Release);
+        }
+        if hprt.penchng() {
+            clear.set_penchng(true);
+        }
+        if hprt.pocchng() {
+            error!("port overcurrent");
+            clear.set_pocchng(true);
+        }
+        r.hprt().write_value(clear);
+        state.port.waker.wake();
+    }
+
+    if ints.discint() {
+        r.gintsts().write(|w| w.set_discint(true));
+        state.port.connected.store(false, Ordering::Relaxed);
+        state.port.changed.store(true, Ordering::Release);
+        state.port.waker.wake();
+        for ch in &state.channels[..channel_count] {
+            ch.waker.wake();
+        }
+    }
+
+    // The TX FIFO empty and SOF interrupts are only unmasked while channels wait for room in a
+    // FIFO or for the next frame. They stay asserted, so mask them again before waking the channels.
+    let msk = r.gintmsk().read();
+    let nptxfe = ints.nptxfe() && msk.nptxfem();
+    let ptxfe = ints.ptxfe() && msk.ptxfem();
+    let sof = ints.sof() && msk.sofm();
+    if nptxfe || ptxfe || sof {
+        if sof {
+            r.gintsts().write(|w| w.set_sof(true));
+        }
+        r.gintmsk().modify(|w| {
+            w.set_nptxfem(w.nptxfem() && !nptxfe);
+            w.set_ptxfem(w.ptxfem() && !ptxfe);
+            w.set_sofm(w.sofm() && !sof);
+        });
+        for ch in &state.channels[..channel_count] {
+            ch.waker.wake();
+        }
+    }
+
+    // Handle RX
+    while r.gintsts().read().rxflvl() {
+        let status = r.grxstsp().read();
+        let ch_num = status.epnum() as usize;
+        let len = status.bcnt() as usize;
+
+        assert!(ch_num < channel_count);
+
+        match status.pktstsh() {
+            vals::Pktstsh::IN_DATA_RX => {
+                trace!("IN_DATA_RX ch={} len={}", ch_num, len);
+                let ch = &state.channels[ch_num];
+
+                if len <= ch.buffer_len.load(Ordering::Acquire) as usize {
+                    // SAFETY: the buffer is valid for `buffer_len` bytes while the channel is enabled.
+                    let buf = unsafe { core::slice::from_raw_parts_mut(*ch.buffer.get(), len) };
+                    for chunk in buf.chunks_mut(4) {
+                        // RX FIFO is shared so always read from fifo(0)
+                        let data = r.fifo(0).read().0;
+                        chunk.copy_from_slice(&data.to_ne_bytes()[0..chunk.len()]);
+                    }
+                    ch.received.store(len as u16, Ordering::Release);
+                } else {
+                    // discard FIFO data
+                    for _ in 0..len.div_ceil(4) {
+                        r.fifo(0).read().data();
+                    }
+                    ch.received.store(RX_OVERFLOW, Ordering::Release);
+                }
+            }
+            x => trace!("PKTSTS: {}", x.to_bits()),
+        }
+    }
+
+    if ints.hcint() {
+        let mut ch_mask = r.haint().read().haint();
+        let mut ch_num = 0;
+
+        while ch_mask != 0 {
+            if ch_mask & 1 != 0 {
+                let hcint = r.hcint(ch_num).read();
+                // clear all
+                r.hcint(ch_num).write_value(hcint);
+
+                let ch = &state.channels[ch_num];
+                let ints = ch.ints.load(Ordering::Relaxed);
+                ch.ints.store(ints | hcint.0, Ordering::Release);
+                ch.waker.wake();
+                trace!("ch={} irq val={:08x}", ch_num, hcint.0);
+            }
+
+            ch_mask >>= 1;
+            ch_num += 1;
+        }
+    }
+}
+
+/// USB OTG host driver.
+pub struct Host<'d, const CH_COUNT: usize> {
+    regs: Otg,
+    state: &'d HostState<CH_COUNT>,
+    phy_type: PhyType,
+    fifo_depth_words: u16,
+    channel_count: usize,
+}
+
+impl<'d, const CH_COUNT: usize> Host<'d, CH_COUNT> {
+    /// Creates the host driver.
+    ///
+    /// The core must have been reset and clocked. Call [`Host::configure_as_host`], one of the
+    /// `config_*` functions matching the core, then [`Host::init`].
+    pub fn new(
+        regs: Otg,
+        state: &'d HostState<CH_COUNT>,
+        phy_type: PhyType,
+        fifo_depth_words: u16,
+        channel_count: usize,
+    ) -> Self {
+        assert!(channel_count <= CH_COUNT && channel_count <= MAX_CHANNEL_COUNT);
+        Self {
+            regs,
+            state,
+            phy_type,
+            fifo_depth_words,
+            channel_count,
+        }
+    }
+
+    /// Returns the PHY type.
+    pub fn phy_type(&self) -> PhyType {
+        self.phy_type
+    }
+
+    /// Configures the core as a host.
+    pub fn configure_as_host(&mut self) {
+        let r = self.regs;
+        let phy_type = self.phy_type;
+        r.gusbcfg().write(|w| {
+            // Force host mode
+            w.set_fhmod(true);
+            // Enable internal full-speed PHY
+            w.set_physel(phy_type.internal() && !phy_type.high_speed());
+        });
+
+        // Wait for the core to switch to host mode
+        while !r.gintsts().read().cmod() {}
+    }
+
+    /// Applies configuration specific to
+    /// Core ID 0x0000_1100 and 0x0000_1200
+    pub fn config_v1(&mut self) {
+        let r = self.regs;
+        r.gccfg_v1().modify(|w| {
+            // Enable internal full-speed PHY, logic is inverted
+            w.set_pwrdwn(self.phy_type.internal());
+            // VBUS is driven by the application
+            w.set_novbussens(true);
+            w.set_vbusasen(false);
+            w.set_vbusbsen(false);
+            w.set_sofouten(false);
+        });
+    }
+
+    /// Applies configuration specific to
+    /// Core ID 0x0000_2000, 0x0000_2100, 0x0000_2300, 0x0000_3000 and 0x0000_3100
+    pub fn config_v2v3(&mut self) {
+        let r = self.regs;
+        let phy_type = self.phy_type;
+        r.gccfg_v2().modify(|w| {
+            // Enable internal full-speed PHY, logic is inverted
+            w.set_pwrdwn(phy_type.internal() && !phy_type.high_speed());
+            w.set_phyhsen(phy_type.internal() && phy_type.high_speed());
+            // VBUS is driven by the application
+            w.set_vbden(false);
+        });
+    }
+
+    /// Applies configuration specific to
+    /// Core ID 0x0000_5000
+    pub fn config_v5(&mut self) {
+        self.regs.gccfg_v3().modify(|w| {
+            // VBUS is driven by the application
+            w.set_vbden(false);
+        });
+    }
+
+    /// Sets up the FIFOs and interrupts, and powers the port.
+    pub fn init(&mut self) {
+        let r = self.regs;
+
+        // Restart the PHY clock
+        r.pcgcctl().write(|_| {});
+
+        r.hcfg().write(|w| {
+            // 48 MHz PHY clock, switched to 6 MHz for low-speed devices on full-speed PHYs.
+            w.set_fslspcs(1);
+            w.set_fslss(!self.phy_type.high_speed());
+        });
+
+        // ERRATA NOTE: Don't interrupt FIFOs being written to.
+        critical_section::with(|_| {
+            let rx_fifo_words = self.fifo_depth_words / 2;
+            let np_tx_fifo_words = self.fifo_depth_words / 4;
+            let p_tx_fifo_words = self.fifo_depth_words - rx_fifo_words - np_tx_fifo_words;
+            trace!(
+                "configuring fifos rx={} non-periodic tx={} periodic tx={}",
+                rx_fifo_words,
+                np_tx_fifo_words,
+                p_tx_fifo_words
+            );
+
+            r.grxfsiz().write(|w| w.set_rxfd(rx_fifo_words));
+            r.hnptxfsiz().write(|w| {
+                w.set_sa(rx_fifo_words);
+                w.set_fd(np_tx_fifo_words);
+            });
+            r.hptxfsiz().write(|w| {
+                w.set_sa(rx_fifo_words + np_tx_fifo_words);
+                w.set_fd(p_tx_fifo_words);
+            });
+
+            // Flush fifos
+            r.grstctl().write(|w| {
+                w.set_rxfflsh(true);
+                w.set_txfflsh(true);
+                w.set_txfnum(0x10);
+            });
+        });
+
+        loop {
+            let x = r.grstctl().read();
+            if !x.rxfflsh() && !x.txfflsh() {
+                break;
+            }
+        }
+
+        // Clear and mask all channel interrupts, they are unmasked on allocation
+        for i in 0..self.channel_count {
+            r.hcint(i).write_value(regs::Hcint(0xFFFF_FFFF));
+            r.hcintmsk(i).write_value(regs::Hcintmsk(0));
+        }
+        r.haintmsk().write(|w| w.set_haintm(0));
+
+        // Unmask and clear core interrupts
+        r.gintsts().write_value(regs::Gintsts(0xFFFF_FFFF));
+        r.gintmsk().write(|w| {
+            w.set_prtim(true);
+            w.set_hcim(true);
+            w.set_rxflvlm(true);
+            w.set_discint(true);
+        });
+
+        // Unmask global interrupt
+        r.gahbcfg().write(|w| w.set_gint(true));
+
+        self.set_port_power(true);
+    }
+
+    /// Turns the port power on or off.
+    ///
+    /// This drives the `PPWR` bit, VBUS itself is usually switched by a GPIO controlled by the
+    /// application.
+    pub fn set_port_power(&self, enabled: bool) {
+        let mut hprt = hprt_rw(self.regs);
+        hprt.set_ppwr(enabled);
+        self.regs.hprt().write_value(hprt);
+    }
+
+    /// Starts or ends a reset of the port.
+    ///
+    /// The reset must be held for at least 10 ms, then [`Host::wait_port_enabled`] must be called.
+    pub fn set_port_reset(&self, reset: bool) {
+        let mut hprt = hprt_rw(self.regs);
+        hprt.set_prst(reset);
+        self.regs.hprt().write_value(hprt);
+    }
+
+    /// Waits for the port to be enabled after a reset, and returns the speed of the device.
+    pub async fn wait_port_enabled(&self) -> Result<Speed, HostError> {
+        let r = self.regs;
+        let speed = poll_fn(|cx| {
+            self.state.port.waker.register(cx.waker());
+
+            if !self.state.port.connected.load(Ordering::Relaxed) {
+                return Poll::Ready(Err(HostError::Disconnected));
+            }
+
+            let hprt = r.hprt().read();
+            if !hprt.pena() {
+                return Poll::Pending;
+            }
+
+            Poll::Ready(Ok(match hprt.pspd() {
+                0 => Speed::High,
+                1 => Speed::Full,
+                _ => Speed::Low,
+            }))
+        })
+        .await?;
+
+        trace!("port enabled, speed={:?}", speed);
+
+        // Select the PHY clock and frame interval matching the device speed
+        if self.phy_type.internal() && !self.phy_type.high_speed() {
+            let low_speed = speed == Speed::Low;
+            r.hcfg().modify(|w| w.set_fslspcs(if low_speed { 2 } else { 1 }));
+            r.hfir().write(|w| w.set_frivl(if low_speed { 6_000 } else { 48_000 }));
+        } else if speed != Speed::High {
+            r.hfir().write(|w| w.set_frivl(60_000));
+        }
+
+        Ok(speed)
+    }
+
+    /// Waits for a device to be connected or disconnected.
+    pub async fn wait_for_device_event(&self) -> DeviceEvent {
+        poll_fn(|cx| {
+            self.state.port.waker.register(cx.waker());
+
+            if !self.state.port.changed.load(Ordering::Acquire) {
+                return Poll::Pending;
+            }
+            self.state.port.changed.store(false, Ordering::Relaxed);
+
+            if self.state.port.connected.load(Ordering::Relaxed) {
+                Poll::Ready(DeviceEvent::Connected)
+            } else {
+                Poll::Ready(DeviceEvent::Disconnected)
+            }
+        })
+        .await
+    }
+
+    /// Allocates a channel for an endpoint of a device.
+    pub fn alloc_channel(
+        &mut self,
+        device_address: u8,
+        endpoint: &EndpointInfo,
+        speed: Speed,
+    ) -> Result<Channel<'d>, ChannelAllocError> {
+        trace!(
+            "allocating channel addr={} ep={:?} type={:?} mps={}",
+            device_address,
+            endpoint.addr,
+            endpoint.ep_type,
+            endpoint.max_packet_size
+        );
+
+        if endpoint.ep_type == EndpointType::Isochronous {
+            error!("Isochronous channels are not supported");
+            return Err(ChannelAllocError);
+        }
+
+        let r = self.regs;
+        let port = &self.state.port;
+        let index = critical_section::with(|_| {
+            let allocated = port.allocated.load(Ordering::Relaxed);
+            let index = (0..self.channel_count).find(|i| allocated & (1 << i) == 0)?;
+            port.allocated.store(allocated | 1 << index, Ordering::Relaxed);
+            r.haintmsk().modify(|w| w.set_haintm(w.haintm() | 1 << index));
+            Some(index)
+        })
+        .ok_or_else(|| {
+            error!("No free channels available");
+            ChannelAllocError
+        })?;
+
+        trace!("  index={}", index);
+
+        Ok(Channel {
+            regs: r,
+            index,
+            state: &self.state.channels[index],
+            port,
+            info: *endpoint,
+            device_address,
+            low_speed: speed == Speed::Low,
+            data_toggle: false,
+        })
+    }
+}
+
+/// USB host channel.
+pub struct Channel<'d> {
+    regs: Otg,
+    index: usize,
+    state: &'d ChannelState,
+    port: &'d PortState,
+    info: EndpointInfo,
+    device_address: u8,
+    low_speed: bool,
+    /// Next data PID of bulk and interrupt transfers, `true` for DATA1.
+    data_toggle: bool,
+}
+
+impl<'d> Channel<'d> {
+    fn check_connected(&self) -> Result<(), HostError> {
+        if self.port.connected.load(Ordering::Relaxed) {
+            Ok(())
+        } else {
+            Err(HostError::Disconnected)
+        }
+    }
+
+    /// Programs and enables the channel for a single-packet transaction.
+    fn start(&mut self, dir: Direction, pid: vals::Dpid, len: usize) {
+        let r = self.regs;
+        let index = self.index;
+
+        self.state.ints.store(0, Ordering::Relaxed);
+
+        r.hcintmsk(index).write(|w| {
+            w.set_xfrcm(true);
+            w.set_chhm(true);
+            w.set_stallm(true);
+            w.set_nakm(true);
+            w.set_txerrm(true);
+            w.set_bberrm(true);
+            w.set_frmorm(true);
+            w.set_dterrm(true);
+        });
+
+        r.hctsiz(index).write(|w| {
+            w.set_xfrsiz(len as _);
+            w.set_pktcnt(1);
+            w.set_dpid(pid.to_bits());
+        });
+
+        // Periodic transactions are scheduled in the next frame.
+        let frame_is_odd = r.hfnum().read().frnum() & 0x01 == 1;
+
+        r.hcchar(index).write(|w| {
+            w.set_mpsiz(self.info.max_packet_size);
+            w.set_epnum(self.info.addr.index() as u8);
+            w.set_epdir(dir == Direction::In);
+            w.set_lsdev(self.low_speed);
+            w.set_eptyp(to_eptyp(self.info.ep_type));
+            w.set_mcnt(1);
+            w.set_dad(self.device_address);
+            w.set_oddfrm(!frame_is_odd);
+            w.set_chena(true);
+        });
+    }
+
+    /// Waits for the end of the current transaction and returns the channel interrupts.
+    async fn wait_done(&mut self) -> Result<regs::Hcint, HostError> {
+        poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            if let Err(e) = self.check_connected() {
+                return Poll::Ready(Err(e));
+            }
+
+            let ints = self.state.ints.load(Ordering::Acquire);
+            if ints & HCINT_DONE_MASK != 0 {
+                Poll::Ready(Ok(regs::Hcint(ints)))
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Halts the channel if it is still enabled, and waits for it to be halted.
+    async fn halt(&mut self) {
+        let r = self.regs;
+        let index = self.index;
+
+        if !r.hcchar(index).read().chena() {
+            return;
+        }
+
+        r.hcchar(index).modify(|w| {
+            w.set_chdis(true);
+            w.set_chena(true);
+        });
+
+        poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            if !r.hcchar(index).read().chena() || self.check_connected().is_err() {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Waits for room for `len` bytes in the transmit FIFO used by this channel.
+    async fn wait_tx_fifo(&mut self, len: usize) -> Result<(), HostError> {
+        let r = self.regs;
+        let size_words = len.div_ceil(4);
+        let periodic = self.info.ep_type == EndpointType::Interrupt;
+
+        poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            if let Err(e) = self.check_connected() {
+                return Poll::Ready(Err(e));
+            }
+
+            let (space_words, queue_space) = if periodic {
+                let sts = r.hptxsts().read();
+                (sts.ptxfsavl() as usize, sts.ptxqsav())
+            } else {
+                let sts = r.hnptxsts().read();
+                (sts.nptxfsav() as usize, sts.nptqxsav())
+            };
+
+            if space_words >= size_words && queue_space > 0 {
+                Poll::Ready(Ok(()))
+            } else {
+                // The FIFO is shared with other channels, wait for it to be drained.
+                critical_section::with(|_| {
+                    r.gintmsk().modify(|w| {
+                        if periodic {
+                            w.set_ptxfem(true);
+                        } else {
+                            w.set_nptxfem(true);
+                        }
+                    })
+                });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Waits for the start of the next frame, before retrying a NAKed transaction.
+    async fn wait_next_frame(&mut self) -> Result<(), HostError> {
+        let r = self.regs;
+        let frame = r.hfnum().read().frnum();
+
+        poll_fn(|cx| {
+            self.state.waker.register(cx.waker());
+
+            if let Err(e) = self.check_connected() {
+                return Poll::Ready(Err(e));
+            }
+
+            if r.hfnum().read().frnum() != frame {
+                Poll::Ready(Ok(()))
+            } else {
+                critical_section::with(|_| r.gintmsk().modify(|w| w.set_sofm(true)));
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Sends a single OUT or SETUP packet, retrying on NAK.
+    async fn packet_out(&mut self, pid: vals::Dpid, data: &[u8]) -> Result<(), HostError> {
+        trace!(
+            "packet_out ch={} pid={} data={:?}",
+            self.index,
+            pid.to_bits(),
+            crate::fmt::Bytes(data)
+        );
+
+        let r = self.regs;
+        let index = self.index;
+        // If the transaction is dropped, don't leave the channel enabled.
+        let _halt = OnDrop::new(move || critical_section::with(|_| disable_channel(r, index)));
+
+        let mut errors = 0;
+        loop {
+            self.wait_tx_fifo(data.len()).await?;
+
+            // ERRATA: Transmit data FIFO is corrupted when a write sequence to the FIFO is interrupted with
+            // accesses to certain OTG_FS registers.
+            //
+            // Prevent the interrupt (which might poke FIFOs) from executing while copying data to FIFOs.
+            critical_section::with(|_| {
+                self.start(Direction::Out, pid, data.len());
+
+                for chunk in data.chunks(4) {
+                    let mut tmp = [0u8; 4];
+                    tmp[0..chunk.len()].copy_from_slice(chunk);
+                    self.regs
+                        .fifo(self.index)
+                        .write_value(regs::Fifo(u32::from_ne_bytes(tmp)));
+                }
+            });
+
+            let ints = self.wait_done().await?;
+            self.halt().await;
+
+            match self.transaction_result(ints, &mut errors) {
+                Some(result) => return result,
+                None if ints.nak() => self.wait_next_frame().await?,
+                None => continue,
+            }
+        }
+    }
+
+    /// Receives a single IN packet into `buf`, retrying on NAK.
+    async fn packet_in(&mut self, pid: vals::Dpid, buf: &mut [u8]) -> Result<usize, HostError> {
+        trace!("packet_in ch={} pid={} len={}", self.index, pid.to_bits(), buf.len());
+
+        let r = self.regs;
+        let index = self.index;
+        let state = self.state;
+        // If the transaction is dropped, the channel is halted and the interrupt handler discards
+        // the data instead of writing to `buf`.
+        let _detach = OnDrop::new(move || {
+            critical_section::with(|_| {
+                state.buffer_len.store(0, Ordering::Release);
+                disable_channel(r, index);
+            })
+        });
+
+        let mut errors = 0;
+        loop {
+            critical_section::with(|_| {
+                // SAFETY: the buffer outlives the transaction, the channel is halted and the buffer
+                // detached before returning or when the future is dropped.
+                unsafe { *self.state.buffer.get() = buf.as_mut_ptr() };
+                self.state.buffer_len.store(buf.len() as u16, Ordering::Relaxed);
+                self.state.received.store(RX_EMPTY, Ordering::Release);
+
+                // IN transfer sizes must be a multiple of the maximum packet size.
+                self.start(Direction::In, pid, self.info.max_packet_size as usize);
+            });
+
+            let ints = self.wait_done().await;
+            self.halt().await;
+            self.state.buffer_len.store(0, Ordering::Release);
+            let ints = ints?;
+
+            match self.transaction_result(ints, &mut errors) {
+                Some(Ok(())) => {
+                    return match self.state.received.load(Ordering::Acquire) {
+                        RX_OVERFLOW => Err(HostError::BufferOverflow),
+                        RX_EMPTY => Ok(0),
+                        len => Ok(len as usize),
+                    }
+                }
+                Some(Err(e)) => return Err(e),
+                None if ints.nak() => self.wait_next_frame().await?,
+                None => continue,
+            }
+        }
+    }
+
+    /// Decodes the channel interrupts of a finished transaction.
+    ///
+    /// Returns `None` if the transaction must be retried.
+    fn transaction_result(&self, ints: regs::Hcint, errors: &mut u8) -> Option<Result<(), HostError>> {
+        if ints.xfrc() {
+            Some(Ok(()))
+        } else if ints.stall() {
+            trace!("ch={} stall", self.index);
+            Some(Err(HostError::Stall))
+        } else if ints.nak() || ints.frmor() {
+            None
+        } else {
+            trace!("ch={} error ints={:08x}", self.index, ints.0);
+            *errors += 1;
+            if *errors < MAX_ERROR_RETRIES {
+                None
+            } else {
+                Some(Err(HostError::TransactionError))
+            }
+        }
+    }
+
+    fn next_data_pid(&mut self) -> vals::Dpid {
+        let pid = if self.data_toggle {
+            vals::Dpid::DATA1
+        } else {
+            vals::Dpid::DATA0
+        };
+        self.data_toggle = !self.data_toggle;
+        pid
+    }
+}
+
+impl<'d> UsbChannel for Channel<'d> {
+    fn info(&self) -> &EndpointInfo {
+        &self.info
+    }
+
+    fn set_device_address(&mut self, address: u8) {
+        self.device_address = address;
+    }
+
+    async fn control_in(&mut self, setup: &SetupPacket, buf: &mut [u8]) -> Result<usize, HostError> {
+        self.packet_out(PID_SETUP, &setup.to_bytes()).await?;
+
+        // Data stage, starting with DATA1
+        let mps = self.info.max_packet_size as usize;
+        let len = buf.len().min(setup.length as usize);
+        let mut offset = 0;
+        let mut toggle = true;
+        while offset < len {
+            let pid = if toggle { vals::Dpid::DATA1 } else { vals::Dpid::DATA0 };
+            let end = (offset + mps).min(len);
+            let n = self.packet_in(pid, &mut buf[offset..end]).await?;
+            toggle = !toggle;
+            offset += n;
+            if n < mps {
+                break;
+            }
+        }
+
+        // Status stage, in the opposite direction of the data stage, or IN without a data stage
+        if setup.length == 0 {
+            self.packet_in(vals::Dpid::DATA1, &mut []).await?;
+        } else {
+            self.packet_out(vals::Dpid::DATA1, &[]).await?;
+        }
+
+        Ok(offset)
+    }
+
+    async fn control_out(&mut self, setup: &SetupPacket, buf: &[u8]) -> Result<(), HostError> {
+        self.packet_out(PID_SETUP, &setup.to_bytes()).await?;
+
+        // Data stage, starting with DATA1
+        let len = buf.len().min(setup.length as usize);
+        let mut toggle = true;
+        for chunk in buf[..len].chunks(self.info.max_packet_size as usize) {
+            let pid = if toggle { vals::Dpid::DATA1 } else { vals::Dpid::DATA0 };
+            self.packet_out(pid, chunk).await?;
+            toggle = !toggle;
+        }
+
+        // Status stage
+        self.packet_in(vals::Dpid::DATA1, &mut []).await?;
+
+        Ok(())
+    }
+
+    async fn request_in(&mut self, buf: &mut [u8]) -> Result<usize, HostError> {
+        let mps = self.info.max_packet_size as usize;
+        let mut offset = 0;
+        loop {
+            let end = (offset + mps).min(buf.len());
+            let pid = self.next_data_pid();
+            let n = match self.packet_in(pid, &mut buf[offset..end]).await {
+                Ok(n) => n,
+                Err(e) => {
+                    // The toggle only advances on successful transactions.
+                    self.data_toggle = !self.data_toggle;
+                    return Err(e);
+                }
+            };
+            offset += n;
+            if n < mps || offset == buf.len() {
+                return Ok(offset);
+            }
+        }
+    }
+
+    async fn request_out(&mut self, buf: &[u8]) -> Result<(), HostError> {
+        let mut chunks = buf.chunks(self.info.max_packet_size as usize);
+        let mut chunk = chunks.next().unwrap_or(&[]);
+        loop {
+            let pid = self.next_data_pid();
+            if let Err(e) = self.packet_out(pid, chunk).await {
+                // The toggle only advances on successful transactions.
+                self.data_toggle = !self.data_toggle;
+                return Err(e);
+            }
+            match chunks.next() {
+                Some(next) => chunk = next,
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+impl<'d> Drop for Channel<'d> {
+    fn drop(&mut self) {
+        let r = self.regs;
+        let index = self.index;
+        critical_section::with(|_| {
+            disable_channel(r, index);
+            r.hcintmsk(index).write_value(regs::Hcintmsk(0));
+            r.haintmsk().modify(|w| w.set_haintm(w.haintm() & !(1 << index)));
+            let allocated = self.port.allocated.load(Ordering::Relaxed);
+            self.port.allocated.store(allocated & !(1 << index), Ordering::Relaxed);
+        });
+    }
+}
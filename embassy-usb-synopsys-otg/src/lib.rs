@@ -20,6 +20,7 @@ use embassy_usb_driver::{
 
 use crate::fmt::Bytes;
 
+pub mod host;
 pub mod otg_v1;
 
 use otg_v1::{regs, vals, Otg};
@@ -35,6 +36,8 @@ pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_
             w.set_iepint(true);
             w.set_oepint(true);
             w.set_rxflvlm(true);
+            w.set_iisoixfrm(true);
+            w.set_ipxfrm_iisooxfrm(true);
         });
         state.bus_waker.wake();
     }
@@ -70,11 +73,22 @@ pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_
             vals::Pktstsd::OUT_DATA_RX => {
                 trace!("OUT_DATA_RX ep={} len={}", ep_num, len);
 
-                if state.ep_states[ep_num].out_size.load(Ordering::Acquire) == EP_OUT_BUFFER_EMPTY {
+                let ep_state = &state.ep_states[ep_num];
+                // Double-buffered endpoints store the packet in the second buffer if the first one wasn't read yet.
+                let slot = if ep_state.out_size.load(Ordering::Acquire) == EP_OUT_BUFFER_EMPTY {
+                    Some((&ep_state.out_buffer, &ep_state.out_size))
+                } else if ep_state.is_out_double_buffered()
+                    && ep_state.out_size_next.load(Ordering::Acquire) == EP_OUT_BUFFER_EMPTY
+                {
+                    Some((&ep_state.out_buffer_next, &ep_state.out_size_next))
+                } else {
+                    None
+                };
+
+                if let Some((out_buffer, out_size)) = slot {
                     // SAFETY: Buffer size is allocated to be equal to endpoint's maximum packet size
                     // We trust the peripheral to not exceed its configured MPSIZ
-                    let buf =
-                        unsafe { core::slice::from_raw_parts_mut(*state.ep_states[ep_num].out_buffer.get(), len) };
+                    let buf = unsafe { core::slice::from_raw_parts_mut(*out_buffer.get(), len) };
 
                     for chunk in buf.chunks_mut(4) {
                         // RX FIFO is shared so always read from fifo(0)
@@ -82,8 +96,8 @@ pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_
                         chunk.copy_from_slice(&data.to_ne_bytes()[0..chunk.len()]);
                     }
 
-                    state.ep_states[ep_num].out_size.store(len as u16, Ordering::Release);
-                    state.ep_states[ep_num].out_waker.wake();
+                    out_size.store(len as u16, Ordering::Release);
+                    ep_state.out_waker.wake();
                 } else {
                     error!("ep_out buffer overflow index={}", ep_num);
 
@@ -96,6 +110,20 @@ pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_
             }
             vals::Pktstsd::OUT_DATA_DONE => {
                 trace!("OUT_DATA_DONE ep={}", ep_num);
+
+                // Double-buffered endpoints receive the next packet right away while the second buffer is
+                // free. Otherwise `read` re-arms the endpoint once it has consumed a buffer.
+                let ep_state = &state.ep_states[ep_num];
+                if ep_state.is_out_double_buffered()
+                    && ep_state.out_size_next.load(Ordering::Acquire) == EP_OUT_BUFFER_EMPTY
+                {
+                    let max_packet_size = r.doepctl(ep_num).read().mpsiz();
+                    r.doeptsiz(ep_num).modify(|w| {
+                        w.set_xfrsiz(max_packet_size as _);
+                        w.set_pktcnt(1);
+                    });
+                    r.doepctl(ep_num).modify(|w| w.set_cnak(true));
+                }
             }
             vals::Pktstsd::SETUP_DATA_DONE => {
                 trace!("SETUP_DATA_DONE ep={}", ep_num);
@@ -117,6 +145,18 @@ pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_
                 // clear all
                 r.diepint(ep_num).write_value(ep_ints);
 
+                // Start the packet queued by a double-buffered endpoint while the previous one was being sent.
+                if ep_ints.xfrc() {
+                    let ep_state = &state.ep_states[ep_num];
+                    let len = ep_state.in_size.load(Ordering::Acquire);
+                    if len != EP_IN_BUFFER_EMPTY {
+                        // SAFETY: exclusive access ensured by `in_size` atomic variable
+                        let data = unsafe { core::slice::from_raw_parts(*ep_state.in_buffer.get(), len as usize) };
+                        start_in_transfer(r, ep_num, EndpointType::Bulk, data);
+                        ep_state.in_size.store(EP_IN_BUFFER_EMPTY, Ordering::Release);
+                    }
+                }
+
                 // TXFE is cleared in DIEPEMPMSK
                 if ep_ints.txfe() {
                     critical_section::with(|_| {
@@ -159,6 +199,47 @@ pub unsafe fn on_interrupt<const MAX_EP_COUNT: usize>(r: Otg, state: &State<MAX_
             ep_num += 1;
         }
     }
+
+    // Incomplete isochronous IN transfer: the packet queued for the previous frame was not sent.
+    if ints.iisoixfr() {
+        trace!("iisoixfr");
+        r.gintsts().write(|w| w.set_iisoixfr(true)); // clear
+
+        for ep_num in 1..ep_count {
+            let diepctl = r.diepctl(ep_num).read();
+            if diepctl.eptyp() == vals::Eptyp::ISOCHRONOUS && diepctl.epena() {
+                // Drop the stale packet so that the next `write` can queue data for the upcoming frame.
+                // The endpoint is disabled asynchronously, `write` flushes its FIFO once it is.
+                r.diepctl(ep_num).modify(|w| {
+                    w.set_snak(true);
+                    w.set_epdis(true);
+                });
+                state.ep_states[ep_num].in_flush.store(true, Ordering::Release);
+                state.ep_states[ep_num].in_waker.wake();
+            }
+        }
+    }
+
+    // Incomplete isochronous OUT transfer: no packet was received in the frame the endpoint was armed for.
+    if ints.ipxfr_incompisoout() {
+        trace!("incompisoout");
+        r.gintsts().write(|w| w.set_ipxfr_incompisoout(true)); // clear
+
+        let frame_is_odd = r.dsts().read().fnsof() & 0x01 == 1;
+        for ep_num in 1..ep_count {
+            let doepctl = r.doepctl(ep_num).read();
+            if doepctl.eptyp() == vals::Eptyp::ISOCHRONOUS && doepctl.epena() && doepctl.eonum_dpid() == frame_is_odd {
+                // Re-arm the endpoint for the next frame.
+                r.doepctl(ep_num).modify(|w| {
+                    if frame_is_odd {
+                        w.set_sd0pid_sevnfrm(true);
+                    } else {
+                        w.set_sd1pid_soddfrm(true);
+                    }
+                });
+            }
+        }
+    }
 }
 
 /// USB PHY type
@@ -208,6 +289,9 @@ impl PhyType {
 /// Indicates that [State::ep_out_buffers] is empty.
 const EP_OUT_BUFFER_EMPTY: u16 = u16::MAX;
 
+/// Indicates that [EpState::in_buffer] is empty.
+const EP_IN_BUFFER_EMPTY: u16 = u16::MAX;
+
 struct EpState {
     in_waker: AtomicWaker,
     out_waker: AtomicWaker,
@@ -215,6 +299,29 @@ struct EpState {
     /// Buffers are ready when associated [State::ep_out_size] != [EP_OUT_BUFFER_EMPTY].
     out_buffer: UnsafeCell<*mut u8>,
     out_size: AtomicU16,
+    /// Second buffer of double-buffered OUT endpoints, null otherwise. Holds the packet received after
+    /// the one in `out_buffer`.
+    out_buffer_next: UnsafeCell<*mut u8>,
+    out_size_next: AtomicU16,
+    /// Packet queued by double-buffered IN endpoints while the previous one is being sent, null
+    /// otherwise. Ready when `in_size` != [EP_IN_BUFFER_EMPTY].
+    in_buffer: UnsafeCell<*mut u8>,
+    in_size: AtomicU16,
+    /// Set when an incomplete isochronous IN transfer was cancelled, and the TX FIFO must be
+    /// flushed once the endpoint is disabled.
+    in_flush: AtomicBool,
+}
+
+impl EpState {
+    fn is_out_double_buffered(&self) -> bool {
+        // SAFETY: only written during endpoint allocation
+        unsafe { !(*self.out_buffer_next.get()).is_null() }
+    }
+
+    fn is_in_double_buffered(&self) -> bool {
+        // SAFETY: only written during endpoint allocation
+        unsafe { !(*self.in_buffer.get()).is_null() }
+    }
 }
 
 // SAFETY: The EndpointAllocator ensures that the buffer points to valid memory exclusive for each endpoint and is
@@ -253,6 +360,11 @@ impl<const EP_COUNT: usize> State<EP_COUNT> {
                     out_waker: AtomicWaker::new(),
                     out_buffer: UnsafeCell::new(0 as _),
                     out_size: AtomicU16::new(EP_OUT_BUFFER_EMPTY),
+                    out_buffer_next: UnsafeCell::new(0 as _),
+                    out_size_next: AtomicU16::new(EP_OUT_BUFFER_EMPTY),
+                    in_buffer: UnsafeCell::new(0 as _),
+                    in_size: AtomicU16::new(EP_IN_BUFFER_EMPTY),
+                    in_flush: AtomicBool::new(false),
                 }
             }; EP_COUNT],
             bus_waker: AtomicWaker::new(),
@@ -294,6 +406,23 @@ pub struct Config {
     /// enumerates in FS mode. Some USB Link IP like those in the STM32H7 series support adding this delay to work with
     /// the affected PHYs.
     pub xcvrdly: bool,
+
+    /// Stop the PHY clock while the bus is suspended.
+    ///
+    /// This lowers the power consumption in suspend, which matters most with external ULPI PHYs. The
+    /// clock is restarted when the host resumes or resets the bus.
+    pub phy_low_power: bool,
+
+    /// Use double buffering for bulk endpoints.
+    ///
+    /// A double-buffered OUT endpoint receives the next packet while the application hasn't read the
+    /// previous one yet, and a double-buffered IN endpoint queues the next packet while the previous
+    /// one is being sent. The host then doesn't get NAKed between packets, so bulk transfers can reach
+    /// line rate.
+    ///
+    /// Bulk endpoints then use twice their max packet size of `ep_out_buffer` for OUT, and their max
+    /// packet size for IN.
+    pub double_buffered_bulk: bool,
 }
 
 impl Default for Config {
@@ -301,6 +430,8 @@ impl Default for Config {
         Self {
             vbus_detection: false,
             xcvrdly: false,
+            phy_low_power: false,
+            double_buffered_bulk: false,
         }
     }
 }
@@ -321,7 +452,8 @@ impl<'d, const MAX_EP_COUNT: usize> Driver<'d, MAX_EP_COUNT> {
     /// # Arguments
     ///
     /// * `ep_out_buffer` - An internal buffer used to temporarily store received packets.
-    /// Must be large enough to fit all OUT endpoint max packet sizes.
+    /// Must be large enough to fit all OUT endpoint max packet sizes, and the extra buffers
+    /// of double-buffered bulk endpoints (see [`Config::double_buffered_bulk`]).
     /// Endpoint allocation will fail if it is too small.
     /// * `instance` - The USB OTG peripheral instance and its configuration.
     /// * `config` - The USB driver configuration.
@@ -356,13 +488,19 @@ impl<'d, const MAX_EP_COUNT: usize> Driver<'d, MAX_EP_COUNT> {
             D::dir()
         );
 
-        if D::dir() == Direction::Out {
-            if self.ep_out_buffer_offset + max_packet_size as usize > self.ep_out_buffer.len() {
-                error!("Not enough endpoint out buffer capacity");
-                return Err(EndpointAllocError);
-            }
+        let double_buffered = ep_type == EndpointType::Bulk && self.config.double_buffered_bulk;
+        let buffer_size = match (D::dir(), double_buffered) {
+            (Direction::Out, false) => max_packet_size as usize,
+            (Direction::Out, true) => 2 * max_packet_size as usize,
+            (Direction::In, false) => 0,
+            (Direction::In, true) => max_packet_size as usize,
         };
 
+        if self.ep_out_buffer_offset + buffer_size > self.ep_out_buffer.len() {
+            error!("Not enough endpoint out buffer capacity");
+            return Err(EndpointAllocError);
+        }
+
         let fifo_size_words = match D::dir() {
             Direction::Out => (max_packet_size + 3) / 4,
             // INEPTXFD requires minimum size of 16 words
@@ -407,13 +545,21 @@ impl<'d, const MAX_EP_COUNT: usize> Driver<'d, MAX_EP_COUNT> {
         trace!("  index={}", index);
 
         let state = &self.instance.state.ep_states[index];
-        if D::dir() == Direction::Out {
-            // Buffer capacity check was done above, now allocation cannot fail
-            unsafe {
-                *state.out_buffer.get() = self.ep_out_buffer.as_mut_ptr().offset(self.ep_out_buffer_offset as _);
-            }
-            self.ep_out_buffer_offset += max_packet_size as usize;
+        // Buffer capacity check was done above, now allocation cannot fail
+        let buffer = unsafe { self.ep_out_buffer.as_mut_ptr().offset(self.ep_out_buffer_offset as _) };
+        match (D::dir(), double_buffered) {
+            (Direction::Out, false) => unsafe {
+                *state.out_buffer.get() = buffer;
+                *state.out_buffer_next.get() = core::ptr::null_mut();
+            },
+            (Direction::Out, true) => unsafe {
+                *state.out_buffer.get() = buffer;
+                *state.out_buffer_next.get() = buffer.offset(max_packet_size as _);
+            },
+            (Direction::In, false) => unsafe { *state.in_buffer.get() = core::ptr::null_mut() },
+            (Direction::In, true) => unsafe { *state.in_buffer.get() = buffer },
         }
+        self.ep_out_buffer_offset += buffer_size;
 
         Ok(Endpoint {
             _phantom: PhantomData,
@@ -507,6 +653,8 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
             w.set_rxflvlm(true);
             w.set_srqim(true);
             w.set_otgint(true);
+            w.set_iisoixfrm(true);
+            w.set_ipxfrm_iisooxfrm(true);
         });
     }
 
@@ -524,9 +672,19 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
             w.set_fdmod(true);
             // Enable internal full-speed PHY
             w.set_physel(phy_type.internal() && !phy_type.high_speed());
+            if !phy_type.internal() {
+                // Let the ULPI PHY resume by itself when the host signals resume while suspended.
+                w.set_ulpiar(true);
+            }
         });
     }
 
+    fn set_phy_clock_stopped(&mut self, stopped: bool) {
+        if self.config.phy_low_power {
+            self.instance.regs.pcgcctl().modify(|w| w.set_stppclk(stopped));
+        }
+    }
+
     /// Applies configuration specific to
     /// Core ID 0x0000_1100 and 0x0000_1200
     pub fn config_v1(&mut self) {
@@ -614,6 +772,8 @@ impl<'d, const MAX_EP_COUNT: usize> Bus<'d, MAX_EP_COUNT> {
         // Unmask transfer complete EP interrupt
         r.diepmsk().write(|w| {
             w.set_xfrcm(true);
+            // Wakes `write` once a cancelled isochronous transfer is disabled.
+            w.set_epdm(true);
         });
 
         // Unmask SETUP received EP interrupt
@@ -802,6 +962,7 @@ impl<'d, const MAX_EP_COUNT: usize> embassy_usb_driver::Bus for Bus<'d, MAX_EP_C
             if ints.usbrst() {
                 trace!("reset");
 
+                self.set_phy_clock_stopped(false);
                 self.init_fifo();
                 self.configure_endpoints();
 
@@ -833,12 +994,14 @@ impl<'d, const MAX_EP_COUNT: usize> embassy_usb_driver::Bus for Bus<'d, MAX_EP_C
             if ints.usbsusp() {
                 trace!("suspend");
                 regs.gintsts().write(|w| w.set_usbsusp(true)); // clear
+                self.set_phy_clock_stopped(true);
                 self.restore_irqs();
                 return Poll::Ready(Event::Suspend);
             }
 
             if ints.wkupint() {
                 trace!("resume");
+                self.set_phy_clock_stopped(false);
                 regs.gintsts().write(|w| w.set_wkupint(true)); // clear
                 self.restore_irqs();
                 return Poll::Ready(Event::Resume);
@@ -951,7 +1114,12 @@ impl<'d, const MAX_EP_COUNT: usize> embassy_usb_driver::Bus for Bus<'d, MAX_EP_C
                     regs.diepctl(ep_addr.index()).modify(|w| {
                         w.set_usbaep(enabled);
                         w.set_cnak(enabled); // clear NAK that might've been set by SNAK above.
-                    })
+                    });
+
+                    // Drop the packet queued by a double-buffered endpoint.
+                    state.ep_states[ep_addr.index()]
+                        .in_size
+                        .store(EP_IN_BUFFER_EMPTY, Ordering::Release);
                 });
 
                 // Wake `Endpoint::wait_enabled()`
@@ -1076,10 +1244,32 @@ impl<'d> embassy_usb_driver::EndpointOut for Endpoint<'d, Out> {
                 let data = unsafe { core::slice::from_raw_parts(*self.state.out_buffer.get(), len as usize) };
                 buf[..len as usize].copy_from_slice(data);
 
-                // Release buffer
-                self.state.out_size.store(EP_OUT_BUFFER_EMPTY, Ordering::Release);
-
                 critical_section::with(|_| {
+                    // Release buffer
+                    if self.state.is_out_double_buffered() {
+                        let next_len = self.state.out_size_next.load(Ordering::Acquire);
+                        if next_len == EP_OUT_BUFFER_EMPTY {
+                            // The interrupt already re-armed the endpoint after this packet.
+                            self.state.out_size.store(EP_OUT_BUFFER_EMPTY, Ordering::Release);
+                            return;
+                        }
+
+                        // Move up the packet received meanwhile. Both buffers were full, so the endpoint is
+                        // NAKing and must be re-armed below.
+                        // SAFETY: exclusive access ensured by the `out_size` atomic variables
+                        unsafe {
+                            core::ptr::copy_nonoverlapping(
+                                *self.state.out_buffer_next.get(),
+                                *self.state.out_buffer.get(),
+                                next_len as usize,
+                            );
+                        }
+                        self.state.out_size.store(next_len, Ordering::Release);
+                        self.state.out_size_next.store(EP_OUT_BUFFER_EMPTY, Ordering::Release);
+                    } else {
+                        self.state.out_size.store(EP_OUT_BUFFER_EMPTY, Ordering::Release);
+                    }
+
                     // Receive 1 packet
                     self.regs.doeptsiz(index).modify(|w| {
                         w.set_xfrsiz(self.info.max_packet_size as _);
@@ -1129,6 +1319,21 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
         poll_fn(|cx| {
             self.state.in_waker.register(cx.waker());
 
+            if self.state.in_flush.load(Ordering::Acquire) {
+                if self.regs.diepctl(index).read().epena() {
+                    trace!("write ep={:?} wait for prev: cancelling", self.info.addr);
+                    return Poll::Pending;
+                }
+
+                // The cancelled packet is still in the FIFO.
+                self.regs.grstctl().write(|w| {
+                    w.set_txfnum(index as _);
+                    w.set_txfflsh(true);
+                });
+                while self.regs.grstctl().read().txfflsh() {}
+                self.state.in_flush.store(false, Ordering::Release);
+            }
+
             let diepctl = self.regs.diepctl(index).read();
             let dtxfsts = self.regs.dtxfsts(index).read();
             trace!(
@@ -1143,6 +1348,11 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
             } else if !diepctl.epena() {
                 trace!("write ep={:?} wait for prev: ready", self.info.addr);
                 Poll::Ready(Ok(()))
+            } else if self.state.is_in_double_buffered()
+                && self.state.in_size.load(Ordering::Acquire) == EP_IN_BUFFER_EMPTY
+            {
+                trace!("write ep={:?} wait for prev: queue", self.info.addr);
+                Poll::Ready(Ok(()))
             } else {
                 trace!("write ep={:?} wait for prev: pending", self.info.addr);
                 Poll::Pending
@@ -1150,7 +1360,7 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
         })
         .await?;
 
-        if buf.len() > 0 {
+        if buf.len() > 0 && !self.regs.diepctl(index).read().epena() {
             poll_fn(|cx| {
                 self.state.in_waker.register(cx.waker());
 
@@ -1181,39 +1391,15 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
         //
         // Prevent the interrupt (which might poke FIFOs) from executing while copying data to FIFOs.
         critical_section::with(|_| {
-            // Setup transfer size
-            self.regs.dieptsiz(index).write(|w| {
-                w.set_mcnt(1);
-                w.set_pktcnt(1);
-                w.set_xfrsiz(buf.len() as _);
-            });
-
-            if self.info.ep_type == EndpointType::Isochronous {
-                // Isochronous endpoints must set the correct even/odd frame bit to
-                // correspond with the next frame's number.
-                let frame_number = self.regs.dsts().read().fnsof();
-                let frame_is_odd = frame_number & 0x01 == 1;
-
-                self.regs.diepctl(index).modify(|r| {
-                    if frame_is_odd {
-                        r.set_sd0pid_sevnfrm(true);
-                    } else {
-                        r.set_sd1pid_soddfrm(true);
-                    }
-                });
-            }
-
-            // Enable endpoint
-            self.regs.diepctl(index).modify(|w| {
-                w.set_cnak(true);
-                w.set_epena(true);
-            });
-
-            // Write data to FIFO
-            for chunk in buf.chunks(4) {
-                let mut tmp = [0u8; 4];
-                tmp[0..chunk.len()].copy_from_slice(chunk);
-                self.regs.fifo(index).write_value(regs::Fifo(u32::from_ne_bytes(tmp)));
+            if self.regs.diepctl(index).read().epena() {
+                // A double-buffered endpoint is still sending the previous packet. Queue this one, the
+                // interrupt starts it when the transfer completes.
+                // SAFETY: exclusive access ensured by `in_size` atomic variable
+                let data = unsafe { core::slice::from_raw_parts_mut(*self.state.in_buffer.get(), buf.len()) };
+                data.copy_from_slice(buf);
+                self.state.in_size.store(buf.len() as u16, Ordering::Release);
+            } else {
+                start_in_transfer(self.regs, index, self.info.ep_type, buf);
             }
         });
 
@@ -1223,6 +1409,46 @@ impl<'d> embassy_usb_driver::EndpointIn for Endpoint<'d, In> {
     }
 }
 
+/// Sets up a 1 packet transfer on IN endpoint `index`, and writes `data` to its FIFO.
+///
+/// Must not be interrupted by the interrupt handler, see the FIFO errata in [`Endpoint::write`].
+fn start_in_transfer(r: Otg, index: usize, ep_type: EndpointType, data: &[u8]) {
+    // Setup transfer size
+    r.dieptsiz(index).write(|w| {
+        w.set_mcnt(1);
+        w.set_pktcnt(1);
+        w.set_xfrsiz(data.len() as _);
+    });
+
+    if ep_type == EndpointType::Isochronous {
+        // Isochronous endpoints must set the correct even/odd frame bit to
+        // correspond with the next frame's number.
+        let frame_number = r.dsts().read().fnsof();
+        let frame_is_odd = frame_number & 0x01 == 1;
+
+        r.diepctl(index).modify(|w| {
+            if frame_is_odd {
+                w.set_sd0pid_sevnfrm(true);
+            } else {
+                w.set_sd1pid_soddfrm(true);
+            }
+        });
+    }
+
+    // Enable endpoint
+    r.diepctl(index).modify(|w| {
+        w.set_cnak(true);
+        w.set_epena(true);
+    });
+
+    // Write data to FIFO
+    for chunk in data.chunks(4) {
+        let mut tmp = [0u8; 4];
+        tmp[0..chunk.len()].copy_from_slice(chunk);
+        r.fifo(index).write_value(regs::Fifo(u32::from_ne_bytes(tmp)));
+    }
+}
+
 /// USB control pipe.
 pub struct ControlPipe<'d> {
     max_packet_size: u16,
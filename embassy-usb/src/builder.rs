@@ -5,7 +5,7 @@ use crate::descriptor::{BosWriter, DescriptorWriter, SynchronizationType, UsageT
 use crate::driver::{Driver, Endpoint, EndpointInfo, EndpointType};
 use crate::msos::{DeviceLevelDescriptor, FunctionLevelDescriptor, MsOsDescriptorWriter};
 use crate::types::{InterfaceNumber, StringIndex};
-use crate::{Handler, Interface, UsbDevice, MAX_INTERFACE_COUNT, STRING_INDEX_CUSTOM_START};
+use crate::{Handler, Interface, StallSignal, UsbDevice, MAX_INTERFACE_COUNT, STRING_INDEX_CUSTOM_START};
 
 #[derive(Debug, Copy, Clone)]
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -77,6 +77,8 @@ pub struct Config<'a> {
 
     /// Serial number string descriptor.
     ///
+    /// See also: `serial_number_from_id`
+    ///
     /// Default: (none)
     pub serial_number: Option<&'a str>,
 
@@ -140,12 +142,33 @@ impl<'a> Config<'a> {
             max_power: 100,
         }
     }
+
+    /// Set the serial number string descriptor from a unique device ID.
+    ///
+    /// The ID is encoded into uppercase hexadecimal digits stored in `buf`, which must be at least
+    /// twice as long as `id`. For example, a 96-bit ID gives a 24 character serial number.
+    ///
+    /// This gives every device its own serial number, without having to program one in.
+    pub fn serial_number_from_id(&mut self, id: &[u8], buf: &'a mut [u8]) {
+        const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+        assert!(buf.len() >= id.len() * 2, "serial number buffer too small");
+        let buf = &mut buf[..id.len() * 2];
+        for (chunk, v) in buf.chunks_exact_mut(2).zip(id) {
+            chunk[0] = HEX[(v >> 4) as usize];
+            chunk[1] = HEX[(v & 0x0f) as usize];
+        }
+
+        // Safety: `buf` only contains ASCII hexadecimal digits.
+        self.serial_number = Some(unsafe { core::str::from_utf8_unchecked(buf) });
+    }
 }
 
 /// [`UsbDevice`] builder.
 pub struct Builder<'d, D: Driver<'d>> {
     config: Config<'d>,
     handlers: Vec<&'d mut dyn Handler, MAX_HANDLER_COUNT>,
+    stall_signals: Vec<&'d StallSignal, MAX_HANDLER_COUNT>,
     interfaces: Vec<Interface, MAX_INTERFACE_COUNT>,
     control_buf: &'d mut [u8],
 
@@ -200,6 +223,7 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
             config,
             interfaces: Vec::new(),
             handlers: Vec::new(),
+            stall_signals: Vec::new(),
             control_buf,
             next_string_index: STRING_INDEX_CUSTOM_START,
 
@@ -227,6 +251,7 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
             self.driver,
             self.config,
             self.handlers,
+            self.stall_signals,
             self.config_descriptor.into_buf(),
             self.bos_descriptor.writer.into_buf(),
             msos_descriptor,
@@ -278,6 +303,15 @@ impl<'d, D: Driver<'d>> Builder<'d, D> {
         );
     }
 
+    /// Add a signal through which a class requests endpoints to be stalled.
+    pub(crate) fn stall_signal(&mut self, signal: &'d StallSignal) {
+        assert!(
+            self.stall_signals.push(signal).is_ok(),
+            "embassy-usb: stall signal list full. Increase the `max_handler_count` compile-time setting. Current value: {}",
+            MAX_HANDLER_COUNT
+        );
+    }
+
     /// Allocates a new string index.
     pub fn string(&mut self) -> StringIndex {
         let index = self.next_string_index;
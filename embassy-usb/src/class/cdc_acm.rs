@@ -33,6 +33,8 @@ const REQ_SET_LINE_CODING: u8 = 0x20;
 const REQ_GET_LINE_CODING: u8 = 0x21;
 const REQ_SET_CONTROL_LINE_STATE: u8 = 0x22;
 
+const NOTIFICATION_SERIAL_STATE: u8 = 0x20;
+
 /// Internal state for CDC-ACM
 pub struct State<'a> {
     control: MaybeUninit<Control<'a>>,
@@ -68,7 +70,8 @@ impl<'a> State<'a> {
 ///   can be sent if there is no other data to send. This is because USB bulk transactions must be
 ///   terminated with a short packet, even if the bulk endpoint is used for stream-like data.
 pub struct CdcAcmClass<'d, D: Driver<'d>> {
-    _comm_ep: D::EndpointIn,
+    comm_ep: D::EndpointIn,
+    comm_if: InterfaceNumber,
     _data_if: InterfaceNumber,
     read_ep: D::EndpointOut,
     write_ep: D::EndpointIn,
@@ -274,7 +277,8 @@ impl<'d, D: Driver<'d>> CdcAcmClass<'d, D> {
         let control_shared = &state.shared;
 
         CdcAcmClass {
-            _comm_ep: comm_ep,
+            comm_ep,
+            comm_if,
             _data_if: data_if,
             read_ep,
             write_ep,
@@ -319,6 +323,13 @@ impl<'d, D: Driver<'d>> CdcAcmClass<'d, D> {
         self.read_ep.wait_enabled().await;
     }
 
+    /// Sends the state of the serial input lines (DCD, DSR, RI) to the host.
+    ///
+    /// This should be called whenever one of the lines changes.
+    pub async fn set_serial_state(&mut self, state: SerialState) -> Result<(), EndpointError> {
+        send_serial_state(&mut self.comm_ep, self.comm_if, state).await
+    }
+
     /// Split the class into a sender and receiver.
     ///
     /// This allows concurrently sending and receiving packets from separate tasks.
@@ -352,6 +363,54 @@ impl<'d, D: Driver<'d>> CdcAcmClass<'d, D> {
             ControlChanged { control: self.control },
         )
     }
+
+    /// Split the class into sender, receiver and notifier
+    ///
+    /// Allows concurrently sending and receiving packets whilst monitoring for control changes
+    /// and driving the serial state lines, e.g. for a USB to UART bridge.
+    pub fn split_with_notifier(self) -> (Sender<'d, D>, Receiver<'d, D>, Notifier<'d, D>) {
+        (
+            Sender {
+                write_ep: self.write_ep,
+                control: self.control,
+            },
+            Receiver {
+                read_ep: self.read_ep,
+                control: self.control,
+            },
+            Notifier {
+                comm_ep: self.comm_ep,
+                comm_if: self.comm_if,
+                control: self.control,
+            },
+        )
+    }
+}
+
+async fn send_serial_state<E: EndpointIn>(
+    ep: &mut E,
+    comm_if: InterfaceNumber,
+    state: SerialState,
+) -> Result<(), EndpointError> {
+    let bitmap = state.bits().to_le_bytes();
+    let notification = [
+        0xA1,                      // bmRequestType
+        NOTIFICATION_SERIAL_STATE, // bNotification
+        0x00,                      // wValue
+        0x00,                      // |
+        comm_if.into(),            // wIndex = interface
+        0x00,                      // |
+        0x02,                      // wLength
+        0x00,                      // |
+        bitmap[0],                 // UART state bitmap
+        bitmap[1],                 // |
+    ];
+
+    // The notification may not fit in a single packet, the last one is always short.
+    for chunk in notification.chunks(ep.info().max_packet_size as usize) {
+        ep.write(chunk).await?;
+    }
+    Ok(())
 }
 
 /// CDC ACM Control status change monitor
@@ -366,6 +425,68 @@ impl<'d> ControlChanged<'d> {
     pub async fn control_changed(&self) {
         self.control.changed().await;
     }
+
+    /// Gets the current line coding. The line coding contains information that's mainly relevant
+    /// for USB to UART serial port emulators, and can be ignored if not relevant.
+    pub fn line_coding(&self) -> LineCoding {
+        self.control.line_coding.lock(Cell::get)
+    }
+
+    /// Gets the DTR (data terminal ready) state
+    pub fn dtr(&self) -> bool {
+        self.control.dtr.load(Ordering::Relaxed)
+    }
+
+    /// Gets the RTS (request to send) state
+    pub fn rts(&self) -> bool {
+        self.control.rts.load(Ordering::Relaxed)
+    }
+}
+
+/// CDC ACM control change monitor and serial state notifier
+///
+/// You can obtain a `Notifier` with [`CdcAcmClass::split_with_notifier`]
+pub struct Notifier<'d, D: Driver<'d>> {
+    comm_ep: D::EndpointIn,
+    comm_if: InterfaceNumber,
+    control: &'d ControlShared,
+}
+
+impl<'d, D: Driver<'d>> Notifier<'d, D> {
+    /// Return a future for when the control settings change
+    ///
+    /// This is the case when the host sets the line coding, or changes DTR or RTS.
+    pub async fn control_changed(&self) {
+        self.control.changed().await;
+    }
+
+    /// Gets the current line coding. The line coding contains information that's mainly relevant
+    /// for USB to UART serial port emulators, and can be ignored if not relevant.
+    pub fn line_coding(&self) -> LineCoding {
+        self.control.line_coding.lock(Cell::get)
+    }
+
+    /// Gets the DTR (data terminal ready) state
+    pub fn dtr(&self) -> bool {
+        self.control.dtr.load(Ordering::Relaxed)
+    }
+
+    /// Gets the RTS (request to send) state
+    pub fn rts(&self) -> bool {
+        self.control.rts.load(Ordering::Relaxed)
+    }
+
+    /// Sends the state of the serial input lines (DCD, DSR, RI) to the host.
+    ///
+    /// This should be called whenever one of the lines changes.
+    pub async fn set_serial_state(&mut self, state: SerialState) -> Result<(), EndpointError> {
+        send_serial_state(&mut self.comm_ep, self.comm_if, state).await
+    }
+
+    /// Waits for the USB host to enable this interface
+    pub async fn wait_connection(&mut self) {
+        self.comm_ep.wait_enabled().await;
+    }
 }
 
 /// CDC ACM class packet sender.
@@ -637,6 +758,25 @@ impl LineCoding {
     }
 }
 
+/// State of the serial input lines, sent to the host in a SERIAL_STATE notification.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct SerialState {
+    /// DCD (data carrier detect) state
+    pub dcd: bool,
+    /// DSR (data set ready) state
+    pub dsr: bool,
+    /// RI (ring indicator) state
+    pub ri: bool,
+}
+
+impl SerialState {
+    fn bits(&self) -> u16 {
+        // D0: bRxCarrier (DCD), D1: bTxCarrier (DSR), D3: bRingSignal
+        (self.dcd as u16) | (self.dsr as u16) << 1 | (self.ri as u16) << 3
+    }
+}
+
 impl Default for LineCoding {
     fn default() -> Self {
         LineCoding {
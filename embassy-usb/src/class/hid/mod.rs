@@ -2,7 +2,7 @@
 
 use core::mem::MaybeUninit;
 use core::ops::Range;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
 
 #[cfg(feature = "usbd-hid")]
 use ssmarshal::serialize;
@@ -14,9 +14,9 @@ use crate::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut};
 use crate::types::InterfaceNumber;
 use crate::{Builder, Handler};
 
+pub mod report_descriptor;
+
 const USB_CLASS_HID: u8 = 0x03;
-const USB_SUBCLASS_NONE: u8 = 0x00;
-const USB_PROTOCOL_NONE: u8 = 0x00;
 
 // HID
 const HID_DESC_DESCTYPE_HID: u8 = 0x21;
@@ -48,6 +48,58 @@ pub struct Config<'d> {
 
     /// Max packet size for both the IN and OUT endpoints.
     pub max_packet_size: u16,
+
+    /// Defines if the device supports the boot protocol, used by BIOSes before the OS loads
+    /// full HID drivers.
+    pub hid_subclass: HidSubclass,
+
+    /// Boot protocol of the device, used with [`HidSubclass::Boot`].
+    pub hid_boot_protocol: HidBootProtocol,
+}
+
+/// HID interface subclass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum HidSubclass {
+    /// No subclass, the device only supports the report protocol.
+    No = 0x00,
+    /// Boot interface subclass, the device supports the boot protocol.
+    Boot = 0x01,
+}
+
+/// HID boot protocol of the interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum HidBootProtocol {
+    /// No boot protocol.
+    None = 0x00,
+    /// Boot keyboard, with the 8 byte boot keyboard input report.
+    Keyboard = 0x01,
+    /// Boot mouse, with the 3 byte boot mouse input report.
+    Mouse = 0x02,
+}
+
+/// HID protocol selected by the host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum HidProtocolMode {
+    /// Boot protocol, reports must use the boot format and don't carry report IDs.
+    Boot = 0,
+    /// Report protocol, reports follow the report descriptor.
+    Report = 1,
+}
+
+impl From<u8> for HidProtocolMode {
+    fn from(mode: u8) -> Self {
+        if mode == HidProtocolMode::Boot as u8 {
+            HidProtocolMode::Boot
+        } else {
+            HidProtocolMode::Report
+        }
+    }
 }
 
 /// Report ID
@@ -77,6 +129,7 @@ impl ReportId {
 pub struct State<'d> {
     control: MaybeUninit<Control<'d>>,
     out_report_offset: AtomicUsize,
+    protocol_mode: AtomicU8,
 }
 
 impl<'d> Default for State<'d> {
@@ -91,6 +144,7 @@ impl<'d> State<'d> {
         State {
             control: MaybeUninit::uninit(),
             out_report_offset: AtomicUsize::new(0),
+            protocol_mode: AtomicU8::new(HidProtocolMode::Report as u8),
         }
     }
 }
@@ -106,13 +160,15 @@ fn build<'d, D: Driver<'d>>(
     state: &'d mut State<'d>,
     config: Config<'d>,
     with_out_endpoint: bool,
-) -> (Option<D::EndpointOut>, D::EndpointIn, &'d AtomicUsize) {
+) -> (Option<D::EndpointOut>, D::EndpointIn, &'d AtomicUsize, &'d AtomicU8) {
     let len = config.report_descriptor.len();
+    let subclass = config.hid_subclass as u8;
+    let protocol = config.hid_boot_protocol as u8;
 
-    let mut func = builder.function(USB_CLASS_HID, USB_SUBCLASS_NONE, USB_PROTOCOL_NONE);
+    let mut func = builder.function(USB_CLASS_HID, subclass, protocol);
     let mut iface = func.interface();
     let if_num = iface.interface_number();
-    let mut alt = iface.alt_setting(USB_CLASS_HID, USB_SUBCLASS_NONE, USB_PROTOCOL_NONE, None);
+    let mut alt = iface.alt_setting(USB_CLASS_HID, subclass, protocol, None);
 
     // HID descriptor
     alt.descriptor(
@@ -147,10 +203,12 @@ fn build<'d, D: Driver<'d>>(
         config.report_descriptor,
         config.request_handler,
         &state.out_report_offset,
+        &state.protocol_mode,
+        config.hid_subclass,
     ));
     builder.handler(control);
 
-    (ep_out, ep_in, &state.out_report_offset)
+    (ep_out, ep_in, &state.out_report_offset, &state.protocol_mode)
 }
 
 impl<'d, D: Driver<'d>, const READ_N: usize, const WRITE_N: usize> HidReaderWriter<'d, D, READ_N, WRITE_N> {
@@ -160,14 +218,14 @@ impl<'d, D: Driver<'d>, const READ_N: usize, const WRITE_N: usize> HidReaderWrit
     /// HID reports, consider using [`HidWriter::new`] instead, which allocates an IN endpoint only.
     ///
     pub fn new(builder: &mut Builder<'d, D>, state: &'d mut State<'d>, config: Config<'d>) -> Self {
-        let (ep_out, ep_in, offset) = build(builder, state, config, true);
+        let (ep_out, ep_in, offset, protocol_mode) = build(builder, state, config, true);
 
         Self {
             reader: HidReader {
                 ep_out: ep_out.unwrap(),
                 offset,
             },
-            writer: HidWriter { ep_in },
+            writer: HidWriter { ep_in, protocol_mode },
         }
     }
 
@@ -193,6 +251,18 @@ impl<'d, D: Driver<'d>, const READ_N: usize, const WRITE_N: usize> HidReaderWrit
         self.writer.write(report).await
     }
 
+    /// Writes `report` with the report ID `id` to its interrupt endpoint.
+    ///
+    /// See [`HidWriter::write_report`].
+    pub async fn write_report(&mut self, id: u8, report: &[u8]) -> Result<(), EndpointError> {
+        self.writer.write_report(id, report).await
+    }
+
+    /// Gets the protocol currently selected by the host.
+    pub fn protocol_mode(&self) -> HidProtocolMode {
+        self.writer.protocol_mode()
+    }
+
     /// Reads an output report from the Interrupt Out pipe.
     ///
     /// See [`HidReader::read`].
@@ -206,6 +276,7 @@ impl<'d, D: Driver<'d>, const READ_N: usize, const WRITE_N: usize> HidReaderWrit
 /// You can obtain a `HidWriter` using [`HidReaderWriter::split`].
 pub struct HidWriter<'d, D: Driver<'d>, const N: usize> {
     ep_in: D::EndpointIn,
+    protocol_mode: &'d AtomicU8,
 }
 
 /// USB HID reader.
@@ -249,11 +320,18 @@ impl<'d, D: Driver<'d>, const N: usize> HidWriter<'d, D, N> {
     /// of CPU on the device & bandwidth on the bus. A value of 10 is reasonable for
     /// high performance uses, and a value of 255 is good for best-effort usecases.
     pub fn new(builder: &mut Builder<'d, D>, state: &'d mut State<'d>, config: Config<'d>) -> Self {
-        let (ep_out, ep_in, _offset) = build(builder, state, config, false);
+        let (ep_out, ep_in, _offset, protocol_mode) = build(builder, state, config, false);
 
         assert!(ep_out.is_none());
 
-        Self { ep_in }
+        Self { ep_in, protocol_mode }
+    }
+
+    /// Gets the protocol currently selected by the host.
+    ///
+    /// In [`HidProtocolMode::Boot`], only the boot report must be sent, without report ID.
+    pub fn protocol_mode(&self) -> HidProtocolMode {
+        self.protocol_mode.load(Ordering::Relaxed).into()
     }
 
     /// Waits for the interrupt in endpoint to be enabled.
@@ -287,6 +365,20 @@ impl<'d, D: Driver<'d>, const N: usize> HidWriter<'d, D, N> {
 
         Ok(())
     }
+
+    /// Writes `report` with the report ID `id` to its interrupt endpoint.
+    ///
+    /// This is for report descriptors declaring several reports, e.g. a keyboard, consumer
+    /// control and mouse in one interface. `report` doesn't include the ID, and its length plus
+    /// one must not exceed `N`.
+    pub async fn write_report(&mut self, id: u8, report: &[u8]) -> Result<(), EndpointError> {
+        assert!(report.len() < N);
+
+        let mut buf: [u8; N] = [0; N];
+        buf[0] = id;
+        buf[1..=report.len()].copy_from_slice(report);
+        self.write(&buf[..=report.len()]).await
+    }
 }
 
 impl<'d, D: Driver<'d>, const N: usize> HidReader<'d, D, N> {
@@ -406,6 +498,13 @@ pub trait RequestHandler {
     fn set_idle_ms(&mut self, id: Option<ReportId>, duration_ms: u32) {
         let _ = (id, duration_ms);
     }
+
+    /// Called when the host selects the boot or report protocol.
+    ///
+    /// The boot protocol can only be selected if the interface uses [`HidSubclass::Boot`].
+    fn set_protocol(&mut self, mode: HidProtocolMode) {
+        let _ = mode;
+    }
 }
 
 struct Control<'d> {
@@ -413,6 +512,8 @@ struct Control<'d> {
     report_descriptor: &'d [u8],
     request_handler: Option<&'d mut dyn RequestHandler>,
     out_report_offset: &'d AtomicUsize,
+    protocol_mode: &'d AtomicU8,
+    hid_subclass: HidSubclass,
     hid_descriptor: [u8; 9],
 }
 
@@ -422,12 +523,16 @@ impl<'d> Control<'d> {
         report_descriptor: &'d [u8],
         request_handler: Option<&'d mut dyn RequestHandler>,
         out_report_offset: &'d AtomicUsize,
+        protocol_mode: &'d AtomicU8,
+        hid_subclass: HidSubclass,
     ) -> Self {
         Control {
             if_num,
             report_descriptor,
             request_handler,
             out_report_offset,
+            protocol_mode,
+            hid_subclass,
             hid_descriptor: [
                 // Length of buf inclusive of size prefix
                 9,
@@ -453,6 +558,9 @@ impl<'d> Control<'d> {
 impl<'d> Handler for Control<'d> {
     fn reset(&mut self) {
         self.out_report_offset.store(0, Ordering::Release);
+        // Devices start in the report protocol after a reset.
+        self.protocol_mode
+            .store(HidProtocolMode::Report as u8, Ordering::Relaxed);
     }
 
     fn control_out(&mut self, req: Request, data: &[u8]) -> Option<OutResponse> {
@@ -482,12 +590,19 @@ impl<'d> Handler for Control<'d> {
                 _ => Some(OutResponse::Rejected),
             },
             HID_REQ_SET_PROTOCOL => {
-                if req.value == 1 {
-                    Some(OutResponse::Accepted)
-                } else {
-                    warn!("HID Boot Protocol is unsupported.");
-                    Some(OutResponse::Rejected) // UNSUPPORTED: Boot Protocol
+                let mode = match req.value {
+                    0 if self.hid_subclass == HidSubclass::Boot => HidProtocolMode::Boot,
+                    1 => HidProtocolMode::Report,
+                    _ => {
+                        warn!("HID protocol {} is unsupported.", req.value);
+                        return Some(OutResponse::Rejected);
+                    }
+                };
+                self.protocol_mode.store(mode as u8, Ordering::Relaxed);
+                if let Some(handler) = self.request_handler.as_mut() {
+                    handler.set_protocol(mode);
                 }
+                Some(OutResponse::Accepted)
             }
             _ => Some(OutResponse::Rejected),
         }
@@ -539,8 +654,7 @@ impl<'d> Handler for Control<'d> {
                         }
                     }
                     HID_REQ_GET_PROTOCOL => {
-                        // UNSUPPORTED: Boot Protocol
-                        buf[0] = 1;
+                        buf[0] = self.protocol_mode.load(Ordering::Relaxed);
                         Some(InResponse::Accepted(&buf[0..1]))
                     }
                     _ => Some(InResponse::Rejected),
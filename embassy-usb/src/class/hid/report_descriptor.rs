@@ -0,0 +1,337 @@
+//! HID report descriptor builder.
+//!
+//! Report descriptors are a list of short items, described in the [HID specification], section
+//! 6.2.2. [`ReportDescriptorBuilder`] writes them into a buffer from typed calls, and picks the
+//! smallest encoding for each value.
+//!
+//! Several reports can share one interface by giving each of them a report ID. The device then
+//! sends the ID as first byte of each input report, see [`HidWriter::write_report`](super::HidWriter::write_report).
+//!
+//! ```ignore
+//! let mut buf = [0; 128];
+//! let mut desc = ReportDescriptorBuilder::new(&mut buf);
+//! // Mouse buttons, report ID 1
+//! desc.usage_page(usage_page::GENERIC_DESKTOP)
+//!     .usage(0x02) // Mouse
+//!     .collection(Collection::Application)
+//!     .report_id(1)
+//!     .usage_page(usage_page::BUTTON)
+//!     .usage_minimum(1)
+//!     .usage_maximum(3)
+//!     .logical_minimum(0)
+//!     .logical_maximum(1)
+//!     .report_size(1)
+//!     .report_count(3)
+//!     .input(MainItem::DATA | MainItem::VARIABLE | MainItem::ABSOLUTE)
+//!     .report_size(5)
+//!     .report_count(1)
+//!     .input(MainItem::CONSTANT)
+//!     .end_collection();
+//! // Consumer control, report ID 2
+//! desc.usage_page(usage_page::CONSUMER)
+//!     .usage(0x01) // Consumer Control
+//!     .collection(Collection::Application)
+//!     .report_id(2)
+//!     .logical_minimum(0)
+//!     .logical_maximum(0x3ff)
+//!     .usage_minimum(0)
+//!     .usage_maximum(0x3ff)
+//!     .report_size(16)
+//!     .report_count(1)
+//!     .input(MainItem::DATA | MainItem::ARRAY | MainItem::ABSOLUTE)
+//!     .end_collection();
+//! let report_descriptor = desc.build();
+//! ```
+//!
+//! [HID specification]: https://www.usb.org/sites/default/files/hid1_11.pdf
+
+use core::ops::BitOr;
+
+/// Common usage pages, from the HID Usage Tables.
+pub mod usage_page {
+    /// Generic Desktop Controls (mouse, keyboard, joystick...).
+    pub const GENERIC_DESKTOP: u16 = 0x01;
+    /// Simulation Controls.
+    pub const SIMULATION: u16 = 0x02;
+    /// Game Controls.
+    pub const GAME: u16 = 0x05;
+    /// Keyboard/Keypad.
+    pub const KEYBOARD: u16 = 0x07;
+    /// LEDs.
+    pub const LED: u16 = 0x08;
+    /// Buttons.
+    pub const BUTTON: u16 = 0x09;
+    /// Consumer devices (media keys...).
+    pub const CONSUMER: u16 = 0x0C;
+    /// Digitizers.
+    pub const DIGITIZER: u16 = 0x0D;
+    /// First vendor-defined usage page.
+    pub const VENDOR_DEFINED: u16 = 0xFF00;
+}
+
+/// Flags of Input, Output and Feature main items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct MainItem(u16);
+
+impl MainItem {
+    /// The item is data (default).
+    pub const DATA: Self = Self(0);
+    /// The item is constant, e.g. padding.
+    pub const CONSTANT: Self = Self(1 << 0);
+    /// The item is an array of selectors (default).
+    pub const ARRAY: Self = Self(0);
+    /// Each field is a separate variable.
+    pub const VARIABLE: Self = Self(1 << 1);
+    /// Values are absolute (default).
+    pub const ABSOLUTE: Self = Self(0);
+    /// Values are relative to the previous report.
+    pub const RELATIVE: Self = Self(1 << 2);
+    /// Values roll over at the logical extremes.
+    pub const WRAP: Self = Self(1 << 3);
+    /// Values are not linear with the measured data.
+    pub const NON_LINEAR: Self = Self(1 << 4);
+    /// The control has no preferred state.
+    pub const NO_PREFERRED: Self = Self(1 << 5);
+    /// The control has a null state, outside of the logical range.
+    pub const NULL_STATE: Self = Self(1 << 6);
+    /// The value can be changed by the device (Output and Feature only).
+    pub const VOLATILE: Self = Self(1 << 7);
+    /// The field is a fixed-size stream of bytes.
+    pub const BUFFERED_BYTES: Self = Self(1 << 8);
+
+    /// Get the raw flags.
+    pub const fn bits(&self) -> u16 {
+        self.0
+    }
+}
+
+impl BitOr for MainItem {
+    type Output = Self;
+
+    fn bitor(self, rhs: Self) -> Self {
+        Self(self.0 | rhs.0)
+    }
+}
+
+/// Collection type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[repr(u8)]
+pub enum Collection {
+    /// Group of axes.
+    Physical = 0x00,
+    /// Top level collection of a device, e.g. a mouse or a keyboard.
+    Application = 0x01,
+    /// Group of related data.
+    Logical = 0x02,
+    /// Report.
+    Report = 0x03,
+    /// Named array.
+    NamedArray = 0x04,
+    /// Usage switch.
+    UsageSwitch = 0x05,
+    /// Usage modifier.
+    UsageModifier = 0x06,
+}
+
+// Item types
+const TYPE_MAIN: u8 = 0;
+const TYPE_GLOBAL: u8 = 1;
+const TYPE_LOCAL: u8 = 2;
+
+// Main item tags
+const TAG_INPUT: u8 = 0x8;
+const TAG_OUTPUT: u8 = 0x9;
+const TAG_COLLECTION: u8 = 0xA;
+const TAG_FEATURE: u8 = 0xB;
+const TAG_END_COLLECTION: u8 = 0xC;
+
+// Global item tags
+const TAG_USAGE_PAGE: u8 = 0x0;
+const TAG_LOGICAL_MINIMUM: u8 = 0x1;
+const TAG_LOGICAL_MAXIMUM: u8 = 0x2;
+const TAG_PHYSICAL_MINIMUM: u8 = 0x3;
+const TAG_PHYSICAL_MAXIMUM: u8 = 0x4;
+const TAG_UNIT_EXPONENT: u8 = 0x5;
+const TAG_UNIT: u8 = 0x6;
+const TAG_REPORT_SIZE: u8 = 0x7;
+const TAG_REPORT_ID: u8 = 0x8;
+const TAG_REPORT_COUNT: u8 = 0x9;
+
+// Local item tags
+const TAG_USAGE: u8 = 0x0;
+const TAG_USAGE_MINIMUM: u8 = 0x1;
+const TAG_USAGE_MAXIMUM: u8 = 0x2;
+
+/// Writes a HID report descriptor into a buffer.
+///
+/// Panics if the buffer is too small.
+pub struct ReportDescriptorBuilder<'a> {
+    buf: &'a mut [u8],
+    position: usize,
+    depth: usize,
+}
+
+impl<'a> ReportDescriptorBuilder<'a> {
+    /// Create a new builder writing into `buf`.
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self {
+            buf,
+            position: 0,
+            depth: 0,
+        }
+    }
+
+    /// Set the usage page of the following usages.
+    pub fn usage_page(&mut self, page: u16) -> &mut Self {
+        self.unsigned(TYPE_GLOBAL, TAG_USAGE_PAGE, page.into())
+    }
+
+    /// Add a usage to the next main item or collection.
+    pub fn usage(&mut self, usage: u16) -> &mut Self {
+        self.unsigned(TYPE_LOCAL, TAG_USAGE, usage.into())
+    }
+
+    /// Set the first usage of a usage range.
+    pub fn usage_minimum(&mut self, usage: u16) -> &mut Self {
+        self.unsigned(TYPE_LOCAL, TAG_USAGE_MINIMUM, usage.into())
+    }
+
+    /// Set the last usage of a usage range.
+    pub fn usage_maximum(&mut self, usage: u16) -> &mut Self {
+        self.unsigned(TYPE_LOCAL, TAG_USAGE_MAXIMUM, usage.into())
+    }
+
+    /// Set the smallest value the following fields report.
+    pub fn logical_minimum(&mut self, value: i32) -> &mut Self {
+        self.signed(TYPE_GLOBAL, TAG_LOGICAL_MINIMUM, value)
+    }
+
+    /// Set the largest value the following fields report.
+    pub fn logical_maximum(&mut self, value: i32) -> &mut Self {
+        self.signed(TYPE_GLOBAL, TAG_LOGICAL_MAXIMUM, value)
+    }
+
+    /// Set the physical value of the logical minimum.
+    pub fn physical_minimum(&mut self, value: i32) -> &mut Self {
+        self.signed(TYPE_GLOBAL, TAG_PHYSICAL_MINIMUM, value)
+    }
+
+    /// Set the physical value of the logical maximum.
+    pub fn physical_maximum(&mut self, value: i32) -> &mut Self {
+        self.signed(TYPE_GLOBAL, TAG_PHYSICAL_MAXIMUM, value)
+    }
+
+    /// Set the base 10 exponent of the physical unit.
+    pub fn unit_exponent(&mut self, exponent: i8) -> &mut Self {
+        self.signed(TYPE_GLOBAL, TAG_UNIT_EXPONENT, exponent.into())
+    }
+
+    /// Set the physical unit, encoded as described in the HID specification.
+    pub fn unit(&mut self, unit: u32) -> &mut Self {
+        self.unsigned(TYPE_GLOBAL, TAG_UNIT, unit)
+    }
+
+    /// Set the size of the following fields, in bits.
+    pub fn report_size(&mut self, bits: u32) -> &mut Self {
+        self.unsigned(TYPE_GLOBAL, TAG_REPORT_SIZE, bits)
+    }
+
+    /// Set the number of the following fields.
+    pub fn report_count(&mut self, count: u32) -> &mut Self {
+        self.unsigned(TYPE_GLOBAL, TAG_REPORT_COUNT, count)
+    }
+
+    /// Set the report ID of the following items.
+    ///
+    /// Report ID 0 is reserved.
+    pub fn report_id(&mut self, id: u8) -> &mut Self {
+        assert!(id != 0, "report ID 0 is reserved");
+        self.unsigned(TYPE_GLOBAL, TAG_REPORT_ID, id.into())
+    }
+
+    /// Add input fields, sent from the device to the host.
+    pub fn input(&mut self, flags: MainItem) -> &mut Self {
+        self.main(TAG_INPUT, flags.bits())
+    }
+
+    /// Add output fields, sent from the host to the device.
+    pub fn output(&mut self, flags: MainItem) -> &mut Self {
+        self.main(TAG_OUTPUT, flags.bits())
+    }
+
+    /// Add feature fields, read and written by the host with control requests.
+    pub fn feature(&mut self, flags: MainItem) -> &mut Self {
+        self.main(TAG_FEATURE, flags.bits())
+    }
+
+    /// Start a collection, which must be closed with [`Self::end_collection`].
+    pub fn collection(&mut self, collection: Collection) -> &mut Self {
+        self.depth += 1;
+        self.main(TAG_COLLECTION, collection as u8 as u16)
+    }
+
+    /// End the innermost collection.
+    pub fn end_collection(&mut self) -> &mut Self {
+        assert!(self.depth > 0, "end_collection without collection");
+        self.depth -= 1;
+        self.item(TYPE_MAIN, TAG_END_COLLECTION, &[])
+    }
+
+    /// Finish the report descriptor and get its bytes.
+    ///
+    /// Panics if a collection is still open.
+    pub fn build(self) -> &'a [u8] {
+        assert!(self.depth == 0, "unterminated collection");
+        &self.buf[..self.position]
+    }
+
+    fn main(&mut self, tag: u8, value: u16) -> &mut Self {
+        // Main items conventionally always carry their data, even when zero.
+        let bytes = value.to_le_bytes();
+        let len = if value > 0xFF { 2 } else { 1 };
+        self.item(TYPE_MAIN, tag, &bytes[..len])
+    }
+
+    fn unsigned(&mut self, kind: u8, tag: u8, value: u32) -> &mut Self {
+        let bytes = value.to_le_bytes();
+        let len = match value {
+            0 => 0,
+            0x01..=0xFF => 1,
+            0x100..=0xFFFF => 2,
+            _ => 4,
+        };
+        self.item(kind, tag, &bytes[..len])
+    }
+
+    fn signed(&mut self, kind: u8, tag: u8, value: i32) -> &mut Self {
+        let bytes = value.to_le_bytes();
+        let len = if value == 0 {
+            0
+        } else if i8::try_from(value).is_ok() {
+            1
+        } else if i16::try_from(value).is_ok() {
+            2
+        } else {
+            4
+        };
+        self.item(kind, tag, &bytes[..len])
+    }
+
+    fn item(&mut self, kind: u8, tag: u8, data: &[u8]) -> &mut Self {
+        let size = match data.len() {
+            0 => 0,
+            1 => 1,
+            2 => 2,
+            _ => 3,
+        };
+        let end = self.position + 1 + data.len();
+        assert!(end <= self.buf.len(), "report descriptor buffer full");
+
+        self.buf[self.position] = tag << 4 | kind << 2 | size;
+        self.buf[self.position + 1..end].copy_from_slice(data);
+        self.position = end;
+        self
+    }
+}
@@ -0,0 +1,547 @@
+//! Mass Storage Class implementation, aka USB drive.
+//!
+//! The class implements the Bulk-Only Transport (BOT) with the SCSI transparent command set, and
+//! exposes a single logical unit backed by a [`BlockDevice`], like an SD card or a region of
+//! internal flash.
+//!
+//! When a command fails or the host asked for more data than the device has, the data stage is
+//! terminated with a short packet and the failure is reported in the status, instead of stalling
+//! the bulk endpoints. All major operating systems handle this fine. An invalid command block
+//! wrapper does stall both bulk endpoints, until the host performs a Reset Recovery.
+
+use core::cell::RefCell;
+use core::future::{poll_fn, Future};
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::task::Poll;
+
+use embassy_futures::select::{select, Either};
+use embassy_sync::waitqueue::WakerRegistration;
+
+use self::scsi::Sense;
+use crate::control::{InResponse, OutResponse, Recipient, Request, RequestType};
+use crate::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut};
+use crate::types::InterfaceNumber;
+use crate::{Builder, Handler, StallSignal};
+
+mod scsi;
+
+/// This should be used as `device_class` when building the `UsbDevice`.
+///
+/// The class is actually declared in the interface descriptor, so `0x00` works as well.
+pub const USB_CLASS_MSC: u8 = 0x08;
+
+const MSC_SUBCLASS_SCSI: u8 = 0x06;
+const MSC_PROTOCOL_BBB: u8 = 0x50;
+
+const REQ_GET_MAX_LUN: u8 = 0xFE;
+const REQ_BULK_ONLY_RESET: u8 = 0xFF;
+
+const CBW_SIGNATURE: u32 = 0x4342_5355;
+const CBW_LEN: usize = 31;
+const CSW_SIGNATURE: u32 = 0x5342_5355;
+const CSW_LEN: usize = 13;
+
+/// A block device that can be exposed over USB.
+///
+/// Blocks are addressed by their logical block address (LBA), starting at 0.
+#[allow(async_fn_in_trait)]
+pub trait BlockDevice {
+    /// Error type.
+    type Error;
+
+    /// Size of a block in bytes.
+    ///
+    /// This is typically 512. It must be a multiple of the maximum packet size of the class.
+    fn block_size(&self) -> usize;
+
+    /// Number of blocks of the device.
+    fn block_count(&self) -> u32;
+
+    /// Whether the host must be prevented from writing to the device.
+    fn is_write_protected(&self) -> bool {
+        false
+    }
+
+    /// Reads consecutive blocks starting at `lba`.
+    ///
+    /// The length of `buf` is a multiple of the block size.
+    async fn read(&mut self, lba: u32, buf: &mut [u8]) -> Result<(), Self::Error>;
+
+    /// Writes consecutive blocks starting at `lba`.
+    ///
+    /// The length of `data` is a multiple of the block size.
+    async fn write(&mut self, lba: u32, data: &[u8]) -> Result<(), Self::Error>;
+
+    /// Writes back any data cached by the device.
+    ///
+    /// This is called when the host synchronizes the cache, typically before the drive is ejected.
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+/// Configuration for the mass storage class.
+#[derive(Copy, Clone, Debug)]
+#[non_exhaustive]
+pub struct Config<'a> {
+    /// Vendor identification reported to the host, up to 8 ASCII characters.
+    pub vendor: &'a str,
+    /// Product identification reported to the host, up to 16 ASCII characters.
+    pub product: &'a str,
+    /// Product revision reported to the host, up to 4 ASCII characters.
+    pub revision: &'a str,
+    /// Whether the medium is reported as removable.
+    ///
+    /// Operating systems are more willing to mount removable drives automatically.
+    pub removable: bool,
+}
+
+impl<'a> Default for Config<'a> {
+    fn default() -> Self {
+        Self {
+            vendor: "Embassy",
+            product: "USB Drive",
+            revision: "1.0",
+            removable: true,
+        }
+    }
+}
+
+/// Internal state for the mass storage class.
+pub struct State<'a> {
+    control: MaybeUninit<Control<'a>>,
+    shared: ControlShared,
+}
+
+impl<'a> Default for State<'a> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> State<'a> {
+    /// Create a new `State`.
+    pub const fn new() -> Self {
+        Self {
+            control: MaybeUninit::uninit(),
+            shared: ControlShared::new(),
+        }
+    }
+}
+
+struct Control<'a> {
+    iface: InterfaceNumber,
+    shared: &'a ControlShared,
+}
+
+/// Shared data between Control and MscClass
+struct ControlShared {
+    reset: AtomicBool,
+    waker: RefCell<WakerRegistration>,
+    stall: StallSignal,
+}
+
+impl ControlShared {
+    const fn new() -> Self {
+        Self {
+            reset: AtomicBool::new(false),
+            waker: RefCell::new(WakerRegistration::new()),
+            stall: StallSignal::new(),
+        }
+    }
+
+    fn request_reset(&self) {
+        self.reset.store(true, Ordering::Relaxed);
+        self.waker.borrow_mut().wake();
+    }
+
+    fn wait_reset(&self) -> impl Future<Output = ()> + '_ {
+        poll_fn(|cx| {
+            if self.reset.swap(false, Ordering::Relaxed) {
+                Poll::Ready(())
+            } else {
+                self.waker.borrow_mut().register(cx.waker());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+impl<'d> Handler for Control<'d> {
+    fn reset(&mut self) {
+        self.shared.request_reset();
+    }
+
+    fn control_out(&mut self, req: Request, _data: &[u8]) -> Option<OutResponse> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.iface.0 as u16)
+        {
+            return None;
+        }
+
+        match req.request {
+            REQ_BULK_ONLY_RESET => {
+                debug!("Bulk-only mass storage reset");
+                self.shared.request_reset();
+                Some(OutResponse::Accepted)
+            }
+            _ => Some(OutResponse::Rejected),
+        }
+    }
+
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        if (req.request_type, req.recipient, req.index)
+            != (RequestType::Class, Recipient::Interface, self.iface.0 as u16)
+        {
+            return None;
+        }
+
+        match req.request {
+            REQ_GET_MAX_LUN => {
+                // Single logical unit.
+                buf[0] = 0;
+                Some(InResponse::Accepted(&buf[..1]))
+            }
+            _ => Some(InResponse::Rejected),
+        }
+    }
+}
+
+/// Command block wrapper, sent by the host to start a command.
+struct CommandBlock {
+    tag: u32,
+    data_len: u32,
+    data_in: bool,
+    cb: [u8; 16],
+}
+
+impl CommandBlock {
+    fn parse(buf: &[u8]) -> Option<Self> {
+        if buf.len() != CBW_LEN || u32::from_le_bytes(buf[0..4].try_into().unwrap()) != CBW_SIGNATURE {
+            return None;
+        }
+
+        let cb_len = buf[14] as usize;
+        if !(1..=16).contains(&cb_len) {
+            return None;
+        }
+
+        let mut cb = [0; 16];
+        cb[..cb_len].copy_from_slice(&buf[15..15 + cb_len]);
+
+        Some(Self {
+            tag: u32::from_le_bytes(buf[4..8].try_into().unwrap()),
+            data_len: u32::from_le_bytes(buf[8..12].try_into().unwrap()),
+            data_in: buf[12] & 0x80 != 0,
+            cb,
+        })
+    }
+}
+
+/// Status reported in the command status wrapper.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+enum Status {
+    Passed = 0,
+    Failed = 1,
+    PhaseError = 2,
+}
+
+/// Mass storage class, with a single logical unit.
+pub struct MscClass<'d, D: Driver<'d>> {
+    read_ep: D::EndpointOut,
+    write_ep: D::EndpointIn,
+    shared: &'d ControlShared,
+    inquiry: [u8; scsi::INQUIRY_LEN],
+    sense: Sense,
+    /// Number of bytes transferred in the data stage of the current command.
+    transferred: u32,
+    /// Whether the host ended the OUT data stage of the current command with a short packet.
+    out_ended: bool,
+}
+
+impl<'d, D: Driver<'d>> MscClass<'d, D> {
+    /// Creates a new MscClass with the provided UsbBus and `max_packet_size` in bytes. For
+    /// full-speed devices, `max_packet_size` has to be 64, for high-speed devices 512.
+    pub fn new(builder: &mut Builder<'d, D>, state: &'d mut State<'d>, config: Config, max_packet_size: u16) -> Self {
+        let mut func = builder.function(USB_CLASS_MSC, MSC_SUBCLASS_SCSI, MSC_PROTOCOL_BBB);
+        let mut iface = func.interface();
+        let iface_num = iface.interface_number();
+        let mut alt = iface.alt_setting(USB_CLASS_MSC, MSC_SUBCLASS_SCSI, MSC_PROTOCOL_BBB, None);
+        let read_ep = alt.endpoint_bulk_out(max_packet_size);
+        let write_ep = alt.endpoint_bulk_in(max_packet_size);
+        drop(func);
+
+        let control = state.control.write(Control {
+            iface: iface_num,
+            shared: &state.shared,
+        });
+        builder.handler(control);
+        builder.stall_signal(&state.shared.stall);
+
+        MscClass {
+            read_ep,
+            write_ep,
+            shared: &state.shared,
+            inquiry: scsi::inquiry_data(config.vendor, config.product, config.revision, config.removable),
+            sense: Sense::NO_SENSE,
+            transferred: 0,
+            out_ended: false,
+        }
+    }
+
+    /// Gets the maximum packet size in bytes.
+    pub fn max_packet_size(&self) -> u16 {
+        // The size is the same for both endpoints.
+        self.read_ep.info().max_packet_size
+    }
+
+    /// Serves commands from the host, forever.
+    ///
+    /// `buf` is used for the data stage of READ and WRITE commands. It must hold at least one
+    /// block, larger buffers allow transferring several blocks per call to the block device.
+    pub async fn run<B: BlockDevice>(&mut self, device: &mut B, buf: &mut [u8]) -> ! {
+        let block_size = device.block_size();
+        let mps = self.max_packet_size() as usize;
+        assert!(
+            block_size % mps == 0,
+            "block size must be a multiple of the max packet size"
+        );
+        assert!(buf.len() >= block_size, "buffer must hold at least one block");
+
+        loop {
+            self.read_ep.wait_enabled().await;
+            debug!("mass storage enabled");
+            self.shared.reset.store(false, Ordering::Relaxed);
+            self.sense = Sense::NO_SENSE;
+
+            loop {
+                let shared = self.shared;
+                match select(self.process_command(device, buf), shared.wait_reset()).await {
+                    Either::First(Ok(())) => {}
+                    Either::First(Err(EndpointError::Disabled)) => break,
+                    Either::First(Err(EndpointError::BufferOverflow)) => warn!("mass storage: buffer overflow"),
+                    Either::Second(()) => self.sense = Sense::NO_SENSE,
+                }
+            }
+
+            debug!("mass storage disabled");
+        }
+    }
+
+    async fn process_command<B: BlockDevice>(&mut self, device: &mut B, buf: &mut [u8]) -> Result<(), EndpointError> {
+        let mps = self.max_packet_size() as usize;
+        let n = self.read_ep.read(&mut buf[..mps]).await?;
+        let Some(cbw) = CommandBlock::parse(&buf[..n]) else {
+            // BOT 6.6.1: stall both bulk endpoints until Reset Recovery, which cancels this future
+            // from `run`. Should the host clear the halt without a reset, stall them again.
+            warn!("mass storage: invalid command block wrapper");
+            loop {
+                self.shared.stall.stall(self.read_ep.info().addr);
+                self.shared.stall.stall(self.write_ep.info().addr);
+                self.read_ep.read(&mut buf[..mps]).await?;
+            }
+        };
+
+        self.transferred = 0;
+        self.out_ended = false;
+        let status = self.execute(device, buf, &cbw).await?;
+
+        // Terminate the data stage if less data than announced by the host was transferred.
+        if self.transferred < cbw.data_len {
+            if cbw.data_in {
+                if self.transferred as usize % mps == 0 {
+                    self.write_ep.write(&[]).await?;
+                }
+            } else {
+                self.drain(buf, cbw.data_len).await?;
+            }
+        }
+
+        let mut csw = [0; CSW_LEN];
+        csw[0..4].copy_from_slice(&CSW_SIGNATURE.to_le_bytes());
+        csw[4..8].copy_from_slice(&cbw.tag.to_le_bytes());
+        csw[8..12].copy_from_slice(&cbw.data_len.saturating_sub(self.transferred).to_le_bytes());
+        csw[12] = status as u8;
+        self.write_ep.write(&csw).await
+    }
+
+    async fn execute<B: BlockDevice>(
+        &mut self,
+        device: &mut B,
+        buf: &mut [u8],
+        cbw: &CommandBlock,
+    ) -> Result<Status, EndpointError> {
+        let cb = &cbw.cb;
+        trace!("mass storage: command {:02x}", cb[0]);
+
+        match cb[0] {
+            scsi::TEST_UNIT_READY | scsi::PREVENT_ALLOW_MEDIUM_REMOVAL | scsi::START_STOP_UNIT => Ok(Status::Passed),
+            scsi::REQUEST_SENSE => {
+                let sense = self.sense.to_bytes();
+                self.sense = Sense::NO_SENSE;
+                self.respond(cbw, &sense[..sense.len().min(cb[4] as usize)]).await
+            }
+            scsi::INQUIRY => {
+                if cb[1] & 0x01 != 0 {
+                    // Vital product data pages are not supported.
+                    return Ok(self.fail(Sense::INVALID_FIELD_IN_CDB));
+                }
+                let len = u16::from_be_bytes([cb[3], cb[4]]) as usize;
+                let inquiry = self.inquiry;
+                self.respond(cbw, &inquiry[..inquiry.len().min(len)]).await
+            }
+            scsi::MODE_SENSE_6 => {
+                let wp = if device.is_write_protected() { 0x80 } else { 0x00 };
+                let data = [3, 0, wp, 0];
+                self.respond(cbw, &data[..data.len().min(cb[4] as usize)]).await
+            }
+            scsi::MODE_SENSE_10 => {
+                let wp = if device.is_write_protected() { 0x80 } else { 0x00 };
+                let data = [0, 6, 0, wp, 0, 0, 0, 0];
+                let len = u16::from_be_bytes([cb[7], cb[8]]) as usize;
+                self.respond(cbw, &data[..data.len().min(len)]).await
+            }
+            scsi::READ_CAPACITY_10 => {
+                let mut data = [0; 8];
+                data[0..4].copy_from_slice(&device.block_count().saturating_sub(1).to_be_bytes());
+                data[4..8].copy_from_slice(&(device.block_size() as u32).to_be_bytes());
+                self.respond(cbw, &data).await
+            }
+            scsi::READ_FORMAT_CAPACITIES => {
+                let mut data = [0; 12];
+                data[3] = 8; // capacity list length
+                data[4..8].copy_from_slice(&device.block_count().to_be_bytes());
+                data[8] = 0x02; // formatted media
+                data[9..12].copy_from_slice(&(device.block_size() as u32).to_be_bytes()[1..]);
+                let len = u16::from_be_bytes([cb[7], cb[8]]) as usize;
+                self.respond(cbw, &data[..data.len().min(len)]).await
+            }
+            scsi::READ_10 => self.read_blocks(device, buf, cbw).await,
+            scsi::WRITE_10 => self.write_blocks(device, buf, cbw).await,
+            scsi::VERIFY_10 => {
+                let (lba, blocks) = scsi::parse_rw10(cb);
+                if !in_range(device, lba, blocks) {
+                    return Ok(self.fail(Sense::LBA_OUT_OF_RANGE));
+                }
+                Ok(Status::Passed)
+            }
+            scsi::SYNCHRONIZE_CACHE_10 => match device.flush().await {
+                Ok(()) => Ok(Status::Passed),
+                Err(_) => Ok(self.fail(Sense::WRITE_ERROR)),
+            },
+            _ => {
+                debug!("mass storage: unsupported command {:02x}", cb[0]);
+                Ok(self.fail(Sense::INVALID_COMMAND))
+            }
+        }
+    }
+
+    fn fail(&mut self, sense: Sense) -> Status {
+        self.sense = sense;
+        Status::Failed
+    }
+
+    /// Sends the response of a command with a small IN data stage.
+    async fn respond(&mut self, cbw: &CommandBlock, data: &[u8]) -> Result<Status, EndpointError> {
+        if cbw.data_len > 0 && !cbw.data_in {
+            return Ok(Status::PhaseError);
+        }
+
+        let data = &data[..data.len().min(cbw.data_len as usize)];
+        for chunk in data.chunks(self.max_packet_size() as usize) {
+            self.write_ep.write(chunk).await?;
+            self.transferred += chunk.len() as u32;
+        }
+        Ok(Status::Passed)
+    }
+
+    async fn read_blocks<B: BlockDevice>(
+        &mut self,
+        device: &mut B,
+        buf: &mut [u8],
+        cbw: &CommandBlock,
+    ) -> Result<Status, EndpointError> {
+        let block_size = device.block_size();
+        let (mut lba, mut blocks) = scsi::parse_rw10(&cbw.cb);
+        if (blocks > 0 && !cbw.data_in) || cbw.data_len < blocks * block_size as u32 {
+            return Ok(Status::PhaseError);
+        }
+        if !in_range(device, lba, blocks) {
+            return Ok(self.fail(Sense::LBA_OUT_OF_RANGE));
+        }
+
+        let max_blocks = (buf.len() / block_size) as u32;
+        while blocks > 0 {
+            let n = blocks.min(max_blocks);
+            let data = &mut buf[..n as usize * block_size];
+            if device.read(lba, data).await.is_err() {
+                return Ok(self.fail(Sense::UNRECOVERED_READ_ERROR));
+            }
+            for chunk in data.chunks(self.max_packet_size() as usize) {
+                self.write_ep.write(chunk).await?;
+                self.transferred += chunk.len() as u32;
+            }
+            lba += n;
+            blocks -= n;
+        }
+        Ok(Status::Passed)
+    }
+
+    async fn write_blocks<B: BlockDevice>(
+        &mut self,
+        device: &mut B,
+        buf: &mut [u8],
+        cbw: &CommandBlock,
+    ) -> Result<Status, EndpointError> {
+        let block_size = device.block_size();
+        let mps = self.max_packet_size() as usize;
+        let (mut lba, mut blocks) = scsi::parse_rw10(&cbw.cb);
+        if (blocks > 0 && cbw.data_in) || cbw.data_len < blocks * block_size as u32 {
+            return Ok(Status::PhaseError);
+        }
+        if !in_range(device, lba, blocks) {
+            return Ok(self.fail(Sense::LBA_OUT_OF_RANGE));
+        }
+        if device.is_write_protected() {
+            return Ok(self.fail(Sense::WRITE_PROTECTED));
+        }
+
+        let max_blocks = (buf.len() / block_size) as u32;
+        while blocks > 0 {
+            let n = blocks.min(max_blocks);
+            let data = &mut buf[..n as usize * block_size];
+            for chunk in data.chunks_mut(mps) {
+                let len = self.read_ep.read(chunk).await?;
+                self.transferred += len as u32;
+                if len < chunk.len() {
+                    // Short packet, the host sent less data than the command requires.
+                    self.out_ended = true;
+                    return Ok(Status::PhaseError);
+                }
+            }
+            if device.write(lba, data).await.is_err() {
+                return Ok(self.fail(Sense::WRITE_ERROR));
+            }
+            lba += n;
+            blocks -= n;
+        }
+        Ok(Status::Passed)
+    }
+
+    /// Reads and discards the rest of an OUT data stage.
+    async fn drain(&mut self, buf: &mut [u8], data_len: u32) -> Result<(), EndpointError> {
+        let mps = self.max_packet_size() as usize;
+        while !self.out_ended && self.transferred < data_len {
+            let len = self.read_ep.read(&mut buf[..mps]).await?;
+            self.transferred += len as u32;
+            self.out_ended = len < mps;
+        }
+        Ok(())
+    }
+}
+
+fn in_range<B: BlockDevice>(device: &B, lba: u32, blocks: u32) -> bool {
+    lba.checked_add(blocks).is_some_and(|end| end <= device.block_count())
+}
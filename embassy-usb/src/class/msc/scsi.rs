@@ -0,0 +1,82 @@
+//! SCSI transparent command set, as used by USB mass storage devices.
+
+pub const TEST_UNIT_READY: u8 = 0x00;
+pub const REQUEST_SENSE: u8 = 0x03;
+pub const INQUIRY: u8 = 0x12;
+pub const MODE_SENSE_6: u8 = 0x1A;
+pub const START_STOP_UNIT: u8 = 0x1B;
+pub const PREVENT_ALLOW_MEDIUM_REMOVAL: u8 = 0x1E;
+pub const READ_FORMAT_CAPACITIES: u8 = 0x23;
+pub const READ_CAPACITY_10: u8 = 0x25;
+pub const READ_10: u8 = 0x28;
+pub const WRITE_10: u8 = 0x2A;
+pub const VERIFY_10: u8 = 0x2F;
+pub const SYNCHRONIZE_CACHE_10: u8 = 0x35;
+pub const MODE_SENSE_10: u8 = 0x5A;
+
+/// Length of the standard INQUIRY data.
+pub const INQUIRY_LEN: usize = 36;
+
+/// Sense data reported by REQUEST SENSE after a failed command.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct Sense {
+    pub key: u8,
+    pub asc: u8,
+    pub ascq: u8,
+}
+
+impl Sense {
+    pub const NO_SENSE: Self = Self::new(0x00, 0x00, 0x00);
+    pub const UNRECOVERED_READ_ERROR: Self = Self::new(0x03, 0x11, 0x00);
+    pub const WRITE_ERROR: Self = Self::new(0x03, 0x0C, 0x00);
+    pub const INVALID_COMMAND: Self = Self::new(0x05, 0x20, 0x00);
+    pub const LBA_OUT_OF_RANGE: Self = Self::new(0x05, 0x21, 0x00);
+    pub const INVALID_FIELD_IN_CDB: Self = Self::new(0x05, 0x24, 0x00);
+    pub const WRITE_PROTECTED: Self = Self::new(0x07, 0x27, 0x00);
+
+    const fn new(key: u8, asc: u8, ascq: u8) -> Self {
+        Self { key, asc, ascq }
+    }
+
+    /// Fixed format sense data.
+    pub fn to_bytes(self) -> [u8; 18] {
+        let mut buf = [0; 18];
+        buf[0] = 0x70; // current error, fixed format
+        buf[2] = self.key;
+        buf[7] = 10; // additional sense length
+        buf[12] = self.asc;
+        buf[13] = self.ascq;
+        buf
+    }
+}
+
+/// Builds the standard INQUIRY data for a direct access block device.
+///
+/// The identification strings are truncated or padded with spaces to their fixed length.
+pub fn inquiry_data(vendor: &str, product: &str, revision: &str, removable: bool) -> [u8; INQUIRY_LEN] {
+    fn copy_padded(dst: &mut [u8], src: &str) {
+        dst.fill(b' ');
+        let n = src.len().min(dst.len());
+        dst[..n].copy_from_slice(&src.as_bytes()[..n]);
+    }
+
+    let mut buf = [0; INQUIRY_LEN];
+    buf[0] = 0x00; // direct access block device
+    buf[1] = if removable { 0x80 } else { 0x00 };
+    buf[2] = 0x04; // SPC-2
+    buf[3] = 0x02; // response data format
+    buf[4] = (INQUIRY_LEN - 5) as u8; // additional length
+    copy_padded(&mut buf[8..16], vendor);
+    copy_padded(&mut buf[16..32], product);
+    copy_padded(&mut buf[32..36], revision);
+    buf
+}
+
+/// Parses the logical block address and the number of blocks of a READ(10), WRITE(10) or
+/// VERIFY(10) command.
+pub fn parse_rw10(cb: &[u8]) -> (u32, u32) {
+    let lba = u32::from_be_bytes([cb[2], cb[3], cb[4], cb[5]]);
+    let blocks = u16::from_be_bytes([cb[7], cb[8]]);
+    (lba, blocks as u32)
+}
@@ -6,7 +6,7 @@
 pub mod speaker;
 
 mod class_codes;
-mod terminal_type;
+pub(super) mod terminal_type;
 
 /// The maximum supported audio channel index (corresponds to `Top`).
 /// FIXME: Use `core::mem::variant_count(...)` when stabilized.
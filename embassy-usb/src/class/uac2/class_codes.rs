@@ -0,0 +1,118 @@
+//! Audio Device Class Codes as defined in Universal Serial Bus Device Class
+//! Definition for Audio Devices, Release 2.0, Appendix A and Universal Serial
+//! Bus Device Class Definition for Audio Data Formats, Release 2.0, Appendix
+//! A.1 and A.2
+#![allow(dead_code)]
+
+/// The current version of the ADC specification (2.0)
+pub const ADC_VERSION: u16 = 0x0200;
+
+/// Audio Interface Class Code
+pub const USB_AUDIO_CLASS: u8 = 0x01;
+
+// Audio Function Subclass Codes
+pub const FUNCTION_SUBCLASS_UNDEFINED: u8 = 0x00;
+
+// Audio Function Protocol Codes
+pub const FUNCTION_PROTOCOL_UNDEFINED: u8 = 0x00;
+pub const AF_VERSION_02_00: u8 = 0x20;
+
+// Audio Interface Subclass Codes
+pub const USB_AUDIOCONTROL_SUBCLASS: u8 = 0x01;
+pub const USB_AUDIOSTREAMING_SUBCLASS: u8 = 0x02;
+pub const USB_MIDISTREAMING_SUBCLASS: u8 = 0x03;
+
+// Audio Interface Protocol Codes
+pub const IP_VERSION_02_00: u8 = 0x20;
+
+// Audio Function Category Codes
+pub const FUNCTION_CATEGORY_UNDEFINED: u8 = 0x00;
+pub const DESKTOP_SPEAKER: u8 = 0x01;
+pub const HOME_THEATER: u8 = 0x02;
+pub const MICROPHONE: u8 = 0x03;
+pub const HEADSET: u8 = 0x04;
+pub const TELEPHONE: u8 = 0x05;
+pub const CONVERTER: u8 = 0x06;
+pub const VOICE_SOUND_RECORDER: u8 = 0x07;
+pub const IO_BOX: u8 = 0x08;
+pub const MUSICAL_INSTRUMENT: u8 = 0x09;
+pub const PRO_AUDIO: u8 = 0x0A;
+pub const AUDIO_VIDEO: u8 = 0x0B;
+pub const CONTROL_PANEL: u8 = 0x0C;
+pub const OTHER: u8 = 0xFF;
+
+// Audio Class-Specific Descriptor Types
+pub const CS_UNDEFINED: u8 = 0x20;
+pub const CS_DEVICE: u8 = 0x21;
+pub const CS_CONFIGURATION: u8 = 0x22;
+pub const CS_STRING: u8 = 0x23;
+pub const CS_INTERFACE: u8 = 0x24;
+pub const CS_ENDPOINT: u8 = 0x25;
+
+// Audio Class-Specific AC Interface Descriptor Subtypes
+pub const AC_DESCRIPTOR_UNDEFINED: u8 = 0x00;
+pub const HEADER: u8 = 0x01;
+pub const INPUT_TERMINAL: u8 = 0x02;
+pub const OUTPUT_TERMINAL: u8 = 0x03;
+pub const MIXER_UNIT: u8 = 0x04;
+pub const SELECTOR_UNIT: u8 = 0x05;
+pub const FEATURE_UNIT: u8 = 0x06;
+pub const EFFECT_UNIT: u8 = 0x07;
+pub const PROCESSING_UNIT: u8 = 0x08;
+pub const EXTENSION_UNIT: u8 = 0x09;
+pub const CLOCK_SOURCE: u8 = 0x0A;
+pub const CLOCK_SELECTOR: u8 = 0x0B;
+pub const CLOCK_MULTIPLIER: u8 = 0x0C;
+pub const SAMPLE_RATE_CONVERTER: u8 = 0x0D;
+
+// Audio Class-Specific AS Interface Descriptor Subtypes
+pub const AS_DESCRIPTOR_UNDEFINED: u8 = 0x00;
+pub const AS_GENERAL: u8 = 0x01;
+pub const FORMAT_TYPE: u8 = 0x02;
+pub const ENCODER: u8 = 0x03;
+pub const DECODER: u8 = 0x04;
+
+// Audio Class-Specific Endpoint Descriptor Subtypes
+pub const DESCRIPTOR_UNDEFINED: u8 = 0x00;
+pub const EP_GENERAL: u8 = 0x01;
+
+// Audio Class-Specific Request Codes
+pub const REQUEST_CODE_UNDEFINED: u8 = 0x00;
+pub const CUR: u8 = 0x01;
+pub const RANGE: u8 = 0x02;
+pub const MEM: u8 = 0x03;
+
+// Clock Source Control Selectors
+pub const CS_CONTROL_UNDEFINED: u8 = 0x00;
+pub const CS_SAM_FREQ_CONTROL: u8 = 0x01;
+pub const CS_CLOCK_VALID_CONTROL: u8 = 0x02;
+
+// Clock Selector Control Selectors
+pub const CX_CONTROL_UNDEFINED: u8 = 0x00;
+pub const CX_CLOCK_SELECTOR_CONTROL: u8 = 0x01;
+
+// Feature Unit Control Selectors
+pub const FU_CONTROL_UNDEFINED: u8 = 0x00;
+pub const FU_MUTE_CONTROL: u8 = 0x01;
+pub const FU_VOLUME_CONTROL: u8 = 0x02;
+
+// Clock Source bmAttributes
+pub const CLOCK_TYPE_EXTERNAL: u8 = 0x00;
+pub const CLOCK_TYPE_INTERNAL_FIXED: u8 = 0x01;
+pub const CLOCK_TYPE_INTERNAL_VARIABLE: u8 = 0x02;
+pub const CLOCK_TYPE_INTERNAL_PROGRAMMABLE: u8 = 0x03;
+
+// Control capabilities, two bits per control in bmControls
+pub const CONTROL_READ_ONLY: u8 = 0b01;
+pub const CONTROL_READ_WRITE: u8 = 0b11;
+
+// Format Type Codes
+pub const FORMAT_TYPE_UNDEFINED: u8 = 0x00;
+pub const FORMAT_TYPE_I: u8 = 0x01;
+
+// Audio Data Format Type I Bit Allocations
+pub const PCM: u32 = 0x0000_0001;
+pub const PCM8: u32 = 0x0000_0002;
+pub const IEEE_FLOAT: u32 = 0x0000_0004;
+pub const ALAW: u32 = 0x0000_0008;
+pub const MULAW: u32 = 0x0000_0010;
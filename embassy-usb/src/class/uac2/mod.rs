@@ -0,0 +1,30 @@
+//! USB Audio Class 2.0 implementations for different applications.
+//!
+//! Contains:
+//! - The `speaker` class with a single audio streaming interface (host to device)
+//!
+//! Compared to [UAC 1.0](super::uac1), UAC 2.0 supports high-speed operation, sample rates are
+//! controlled through clock entities, and hosts expect 32 bit feedback values in 16.16 format on
+//! high-speed.
+
+pub mod speaker;
+
+mod class_codes;
+
+pub use super::uac1::{Channel, SampleWidth};
+
+/// The maximum supported audio channel index (corresponds to `Top`).
+const MAX_AUDIO_CHANNEL_INDEX: usize = 12;
+
+/// The maximum number of supported audio channels.
+///
+/// Includes all twelve channels from `Channel`, plus the Master channel.
+const MAX_AUDIO_CHANNEL_COUNT: usize = MAX_AUDIO_CHANNEL_INDEX + 1;
+
+/// Get the `bmChannelConfig` bit of a channel [UAC2 Audio Data Formats 4.1].
+///
+/// The spatial locations of UAC 2.0 start with the same twelve positions as UAC 1.0, in the same
+/// order as `Channel`.
+fn channel_config_bit(channel: Channel) -> u32 {
+    1 << (channel as u32)
+}
@@ -0,0 +1,746 @@
+//! USB Audio Class 2.0 - Speaker device
+//!
+//! Provides a class with a single audio streaming interface (host to device),
+//! that advertises itself as a speaker. Includes explicit sample rate feedback for asynchronous
+//! playback.
+//!
+//! Various aspects of the audio stream can be configured, for example:
+//! - sample rate
+//! - sample resolution
+//! - audio channel count and assignment
+//!
+//! The sample rate is controlled through a programmable clock source, behind a clock selector.
+//! The class provides volume and mute controls for each channel.
+
+use core::cell::{Cell, RefCell};
+use core::future::{poll_fn, Future};
+use core::marker::PhantomData;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::task::Poll;
+
+use embassy_sync::blocking_mutex::CriticalSectionMutex;
+use embassy_sync::waitqueue::WakerRegistration;
+use heapless::Vec;
+
+use super::class_codes::*;
+use super::{channel_config_bit, Channel, SampleWidth, MAX_AUDIO_CHANNEL_COUNT, MAX_AUDIO_CHANNEL_INDEX};
+use crate::class::uac1::terminal_type::TerminalType;
+use crate::control::{self, InResponse, OutResponse, Recipient, Request, RequestType};
+use crate::descriptor::{SynchronizationType, UsageType};
+use crate::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut, EndpointType};
+use crate::types::InterfaceNumber;
+use crate::{Builder, Handler};
+
+/// Arbitrary unique identifier for the clock source.
+const CLOCK_SOURCE_ID: u8 = 0x01;
+
+/// Arbitrary unique identifier for the clock selector.
+const CLOCK_SELECTOR_ID: u8 = 0x02;
+
+/// Arbitrary unique identifier for the input terminal.
+const INPUT_TERMINAL_ID: u8 = 0x03;
+
+/// Arbitrary unique identifier for the feature unit.
+const FEATURE_UNIT_ID: u8 = 0x04;
+
+/// Arbitrary unique identifier for the output terminal.
+const OUTPUT_TERMINAL_ID: u8 = 0x05;
+
+// Volume settings go from -25600 to 0, in steps of 256.
+// Therefore, the volume settings are 8q8 values in units of dB.
+const VOLUME_STEPS_PER_DB: i16 = 256;
+const MIN_VOLUME_DB: i16 = -100;
+const MAX_VOLUME_DB: i16 = 0;
+
+// Maximum number of supported discrete sample rates.
+const MAX_SAMPLE_RATE_COUNT: usize = 10;
+
+/// The volume of an audio channel.
+#[derive(Debug, Clone, Copy)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum Volume {
+    /// The channel is muted.
+    Muted,
+    /// The channel volume in dB. Ranges from `MIN_VOLUME_DB` (quietest) to `MAX_VOLUME_DB` (loudest).
+    DeciBel(f32),
+}
+
+/// Internal state for the USB Audio Class.
+pub struct State<'d> {
+    control: Option<Control<'d>>,
+    shared: SharedControl<'d>,
+}
+
+impl<'d> Default for State<'d> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'d> State<'d> {
+    /// Create a new `State`.
+    pub fn new() -> Self {
+        Self {
+            control: None,
+            shared: SharedControl::default(),
+        }
+    }
+}
+
+/// Implementation of the USB audio class 2.0.
+pub struct Speaker<'d, D: Driver<'d>> {
+    phantom: PhantomData<&'d D>,
+}
+
+impl<'d, D: Driver<'d>> Speaker<'d, D> {
+    /// Creates a new [`Speaker`] device, split into a stream, feedback, and a control change notifier.
+    ///
+    /// The packet size should be chosen, based on the expected transfer size of samples per (micro)frame.
+    /// For example, a stereo stream at 32 bit resolution and 48 kHz sample rate yields packets of 384 byte for
+    /// full-speed USB (1 ms frame interval) or 48 byte for high-speed USB (125 us microframe interval).
+    /// As the host adjusts the packet size to the feedback, the `max_packet_size` should be increased (e.g. to double).
+    ///
+    /// # Arguments
+    ///
+    /// * `builder` - The builder for the class.
+    /// * `state` - The internal state of the class.
+    /// * `max_packet_size` - The maximum packet size per (micro)frame.
+    /// * `resolution` - The audio sample resolution.
+    /// * `sample_rates_hz` - The supported sample rates in Hz (up to 10). The first entry is the initial rate.
+    /// * `channels` - The advertised audio channels (up to 12). Entries must be unique, or this function panics.
+    pub fn new(
+        builder: &mut Builder<'d, D>,
+        state: &'d mut State<'d>,
+        max_packet_size: u16,
+        resolution: SampleWidth,
+        sample_rates_hz: &'d [u32],
+        channels: &'d [Channel],
+    ) -> (Stream<'d, D>, Feedback<'d, D>, ControlMonitor<'d>) {
+        assert!(!sample_rates_hz.is_empty() && sample_rates_hz.len() <= MAX_SAMPLE_RATE_COUNT);
+
+        let mut func = builder.function(USB_AUDIO_CLASS, FUNCTION_SUBCLASS_UNDEFINED, AF_VERSION_02_00);
+
+        // Audio control interface (mandatory) [UAC2 4.7]
+        let mut interface = func.interface();
+        let control_interface = interface.interface_number();
+        let mut alt = interface.alt_setting(USB_AUDIO_CLASS, USB_AUDIOCONTROL_SUBCLASS, IP_VERSION_02_00, None);
+
+        // Clock topology:
+        // Clock source (programmable) -> Clock selector -> terminals
+        //
+        // Terminal topology:
+        // Input terminal (receives audio stream) -> Feature Unit (mute and volume) -> Output terminal (e.g. towards speaker)
+
+        // ======================================
+        // Clock Source Descriptor [UAC2 4.7.2.1]
+        let clock_source_descriptor = [
+            CLOCK_SOURCE,                                  // bDescriptorSubtype
+            CLOCK_SOURCE_ID,                               // bClockID
+            CLOCK_TYPE_INTERNAL_PROGRAMMABLE,              // bmAttributes
+            CONTROL_READ_WRITE | (CONTROL_READ_ONLY << 2), // bmControls (frequency, validity)
+            0x00,                                          // bAssocTerminal (none)
+            0x00,                                          // iClockSource (none)
+        ];
+
+        // ========================================
+        // Clock Selector Descriptor [UAC2 4.7.2.2]
+        let clock_selector_descriptor = [
+            CLOCK_SELECTOR,     // bDescriptorSubtype
+            CLOCK_SELECTOR_ID,  // bClockID
+            0x01,               // bNrInPins
+            CLOCK_SOURCE_ID,    // baCSourceID
+            CONTROL_READ_WRITE, // bmControls (selector)
+            0x00,               // iClockSelector (none)
+        ];
+
+        // =======================================
+        // Input Terminal Descriptor [UAC2 4.7.2.4]
+        // Audio input
+        let terminal_type: u16 = TerminalType::UsbStreaming.into();
+
+        // Assemble channel configuration field
+        let mut channel_config: u32 = 0;
+        for channel in channels {
+            let channel = channel_config_bit(*channel);
+
+            if channel_config & channel != 0 {
+                panic!("Invalid channel config, duplicate channel {}.", channel);
+            }
+            channel_config |= channel;
+        }
+        let channel_config = channel_config.to_le_bytes();
+
+        let input_terminal_descriptor = [
+            INPUT_TERMINAL,    // bDescriptorSubtype
+            INPUT_TERMINAL_ID, // bTerminalID
+            terminal_type as u8,
+            (terminal_type >> 8) as u8, // wTerminalType
+            0x00,                       // bAssocTerminal (none)
+            CLOCK_SELECTOR_ID,          // bCSourceID
+            channels.len() as u8,       // bNrChannels
+            channel_config[0],
+            channel_config[1],
+            channel_config[2],
+            channel_config[3], // bmChannelConfig
+            0x00,              // iChannelNames (none)
+            0x00,
+            0x00, // bmControls (none)
+            0x00, // iTerminal (none)
+        ];
+
+        // ========================================
+        // Output Terminal Descriptor [UAC2 4.7.2.5]
+        // Speaker output
+        let terminal_type: u16 = TerminalType::OutSpeaker.into();
+        let output_terminal_descriptor = [
+            OUTPUT_TERMINAL,    // bDescriptorSubtype
+            OUTPUT_TERMINAL_ID, // bTerminalID
+            terminal_type as u8,
+            (terminal_type >> 8) as u8, // wTerminalType
+            0x00,                       // bAssocTerminal (none)
+            FEATURE_UNIT_ID,            // bSourceID (the feature unit)
+            CLOCK_SELECTOR_ID,          // bCSourceID
+            0x00,
+            0x00, // bmControls (none)
+            0x00, // iTerminal (none)
+        ];
+
+        // ======================================
+        // Feature Unit Descriptor [UAC2 4.7.2.8]
+        // Mute and volume control
+        let controls = (CONTROL_READ_WRITE | (CONTROL_READ_WRITE << 2)) as u32;
+
+        const FEATURE_UNIT_DESCRIPTOR_SIZE: usize = 3;
+        let mut feature_unit_descriptor: Vec<u8, { FEATURE_UNIT_DESCRIPTOR_SIZE + 4 * MAX_AUDIO_CHANNEL_COUNT + 1 }> =
+            Vec::from_slice(&[
+                FEATURE_UNIT,      // bDescriptorSubtype (Feature Unit)
+                FEATURE_UNIT_ID,   // bUnitID
+                INPUT_TERMINAL_ID, // bSourceID
+            ])
+            .unwrap();
+
+        // Master controls (disabled, use only per-channel control)
+        feature_unit_descriptor.extend_from_slice(&[0; 4]).unwrap();
+
+        // Add per-channel controls
+        for _channel in channels {
+            feature_unit_descriptor
+                .extend_from_slice(&controls.to_le_bytes())
+                .unwrap();
+        }
+        feature_unit_descriptor.push(0x00).unwrap(); // iFeature (none)
+
+        // ==================================================
+        // Class-specific AC Interface Descriptor [UAC2 4.7.2]
+        const DESCRIPTOR_HEADER_SIZE: usize = 2;
+        const INTERFACE_DESCRIPTOR_SIZE: usize = 7;
+
+        let mut total_descriptor_length = 0;
+
+        for size in [
+            INTERFACE_DESCRIPTOR_SIZE,
+            clock_source_descriptor.len(),
+            clock_selector_descriptor.len(),
+            input_terminal_descriptor.len(),
+            feature_unit_descriptor.len(),
+            output_terminal_descriptor.len(),
+        ] {
+            total_descriptor_length += size + DESCRIPTOR_HEADER_SIZE;
+        }
+
+        let interface_descriptor: [u8; INTERFACE_DESCRIPTOR_SIZE] = [
+            HEADER, // bDescriptorSubtype (Header)
+            ADC_VERSION as u8,
+            (ADC_VERSION >> 8) as u8, // bcdADC
+            DESKTOP_SPEAKER,          // bCategory
+            total_descriptor_length as u8,
+            (total_descriptor_length >> 8) as u8, // wTotalLength
+            0x00,                                 // bmControls (none)
+        ];
+
+        alt.descriptor(CS_INTERFACE, &interface_descriptor);
+        alt.descriptor(CS_INTERFACE, &clock_source_descriptor);
+        alt.descriptor(CS_INTERFACE, &clock_selector_descriptor);
+        alt.descriptor(CS_INTERFACE, &input_terminal_descriptor);
+        alt.descriptor(CS_INTERFACE, &feature_unit_descriptor);
+        alt.descriptor(CS_INTERFACE, &output_terminal_descriptor);
+
+        // =====================================================
+        // Audio streaming interface, zero-bandwidth [UAC2 4.9.1]
+        let mut interface = func.interface();
+        let alt = interface.alt_setting(USB_AUDIO_CLASS, USB_AUDIOSTREAMING_SUBCLASS, IP_VERSION_02_00, None);
+        drop(alt);
+
+        // ==================================================
+        // Audio streaming interface, operational [UAC2 4.9.1]
+        let mut alt = interface.alt_setting(USB_AUDIO_CLASS, USB_AUDIOSTREAMING_SUBCLASS, IP_VERSION_02_00, None);
+
+        let formats = PCM.to_le_bytes();
+        alt.descriptor(
+            CS_INTERFACE,
+            &[
+                AS_GENERAL,        // bDescriptorSubtype
+                INPUT_TERMINAL_ID, // bTerminalLink
+                0x00,              // bmControls (none)
+                FORMAT_TYPE_I,     // bFormatType
+                formats[0],
+                formats[1],
+                formats[2],
+                formats[3],           // bmFormats (PCM)
+                channels.len() as u8, // bNrChannels
+                channel_config[0],
+                channel_config[1],
+                channel_config[2],
+                channel_config[3], // bmChannelConfig
+                0x00,              // iChannelNames (none)
+            ],
+        );
+
+        // Format type I descriptor [UAC2 Audio Data Formats 2.3.1.6]
+        alt.descriptor(
+            CS_INTERFACE,
+            &[
+                FORMAT_TYPE,               // bDescriptorSubtype
+                FORMAT_TYPE_I,             // bFormatType
+                resolution as u8,          // bSubslotSize
+                resolution.in_bit() as u8, // bBitResolution
+            ],
+        );
+
+        let streaming_endpoint = alt.alloc_endpoint_out(EndpointType::Isochronous, max_packet_size, 1);
+        let feedback_endpoint = alt.alloc_endpoint_in(
+            EndpointType::Isochronous,
+            4, // Feedback packets are 24 bit (10.14 format) on full-speed, 32 bit (16.16 format) on high-speed.
+            1,
+        );
+
+        // UAC 2.0 uses the standard 7 byte endpoint descriptors. The feedback endpoint is the
+        // IN endpoint with the same number in the same alternate setting, no bSynchAddress needed.
+        alt.endpoint_descriptor(
+            streaming_endpoint.info(),
+            SynchronizationType::Asynchronous,
+            UsageType::DataEndpoint,
+            &[],
+        );
+
+        alt.descriptor(
+            CS_ENDPOINT,
+            &[
+                EP_GENERAL, // bDescriptorSubtype (General)
+                0x00,       // bmAttributes
+                0x00,       // bmControls (none)
+                0x00,       // bLockDelayUnits (undefined)
+                0x00, 0x00, // wLockDelay (0)
+            ],
+        );
+
+        alt.endpoint_descriptor(
+            feedback_endpoint.info(),
+            SynchronizationType::NoSynchronization,
+            UsageType::FeedbackEndpoint,
+            &[],
+        );
+
+        // Free up the builder.
+        drop(func);
+
+        // Store channel and sample rate information
+        state.shared.channels = channels;
+        state.shared.sample_rates_hz = sample_rates_hz;
+        state.shared.sample_rate_hz.store(sample_rates_hz[0], Ordering::Relaxed);
+
+        state.control = Some(Control {
+            shared: &state.shared,
+            control_interface_number: control_interface,
+        });
+
+        builder.handler(state.control.as_mut().unwrap());
+
+        let control = &state.shared;
+
+        (
+            Stream { streaming_endpoint },
+            Feedback { feedback_endpoint },
+            ControlMonitor { shared: control },
+        )
+    }
+}
+
+/// Audio settings for the feature unit.
+///
+/// Contains volume and mute control.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub struct AudioSettings {
+    /// Channel mute states.
+    muted: [bool; MAX_AUDIO_CHANNEL_COUNT],
+    /// Channel volume levels in 8.8 format (in dB).
+    volume_8q8_db: [i16; MAX_AUDIO_CHANNEL_COUNT],
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        AudioSettings {
+            muted: [false; MAX_AUDIO_CHANNEL_COUNT],
+            volume_8q8_db: [MAX_VOLUME_DB * VOLUME_STEPS_PER_DB; MAX_AUDIO_CHANNEL_COUNT],
+        }
+    }
+}
+
+struct Control<'d> {
+    control_interface_number: InterfaceNumber,
+    shared: &'d SharedControl<'d>,
+}
+
+/// Shared data between [`Control`] and the [`Speaker`] class.
+struct SharedControl<'d> {
+    /// The collection of audio settings (volumes, mute states).
+    audio_settings: CriticalSectionMutex<Cell<AudioSettings>>,
+
+    /// Channel assignments.
+    channels: &'d [Channel],
+
+    /// Supported sample rates in Hz.
+    sample_rates_hz: &'d [u32],
+
+    /// The audio sample rate in Hz.
+    sample_rate_hz: AtomicU32,
+
+    // Notification mechanism.
+    waker: RefCell<WakerRegistration>,
+    changed: AtomicBool,
+}
+
+impl<'d> Default for SharedControl<'d> {
+    fn default() -> Self {
+        SharedControl {
+            audio_settings: CriticalSectionMutex::new(Cell::new(AudioSettings::default())),
+            channels: &[],
+            sample_rates_hz: &[],
+            sample_rate_hz: AtomicU32::new(0),
+            waker: RefCell::new(WakerRegistration::new()),
+            changed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl<'d> SharedControl<'d> {
+    fn changed(&self) -> impl Future<Output = ()> + '_ {
+        poll_fn(|context| {
+            if self.changed.load(Ordering::Relaxed) {
+                self.changed.store(false, Ordering::Relaxed);
+                Poll::Ready(())
+            } else {
+                self.waker.borrow_mut().register(context.waker());
+                Poll::Pending
+            }
+        })
+    }
+}
+
+/// Used for reading audio frames.
+pub struct Stream<'d, D: Driver<'d>> {
+    streaming_endpoint: D::EndpointOut,
+}
+
+impl<'d, D: Driver<'d>> Stream<'d, D> {
+    /// Reads a single packet from the OUT endpoint
+    pub async fn read_packet(&mut self, data: &mut [u8]) -> Result<usize, EndpointError> {
+        self.streaming_endpoint.read(data).await
+    }
+
+    /// Waits for the USB host to enable this interface
+    pub async fn wait_connection(&mut self) {
+        self.streaming_endpoint.wait_enabled().await;
+    }
+}
+
+/// Used for writing sample rate information over the feedback endpoint.
+pub struct Feedback<'d, D: Driver<'d>> {
+    feedback_endpoint: D::EndpointIn,
+}
+
+impl<'d, D: Driver<'d>> Feedback<'d, D> {
+    /// Writes a single packet into the IN endpoint.
+    pub async fn write_packet(&mut self, data: &[u8]) -> Result<(), EndpointError> {
+        self.feedback_endpoint.write(data).await
+    }
+
+    /// Writes a full-speed feedback value, in samples per frame in 10.14 format.
+    pub async fn write_full_speed(&mut self, samples_per_frame_10q14: u32) -> Result<(), EndpointError> {
+        self.feedback_endpoint
+            .write(&samples_per_frame_10q14.to_le_bytes()[..3])
+            .await
+    }
+
+    /// Writes a high-speed feedback value, in samples per microframe in 16.16 format.
+    pub async fn write_high_speed(&mut self, samples_per_microframe_16q16: u32) -> Result<(), EndpointError> {
+        self.feedback_endpoint
+            .write(&samples_per_microframe_16q16.to_le_bytes())
+            .await
+    }
+
+    /// Waits for the USB host to enable this interface.
+    pub async fn wait_connection(&mut self) {
+        self.feedback_endpoint.wait_enabled().await;
+    }
+}
+
+/// Control status change monitor
+///
+/// Await [`ControlMonitor::changed`] for being notified of configuration changes. Afterwards, the updated
+/// configuration settings can be read with [`ControlMonitor::volume`] and [`ControlMonitor::sample_rate_hz`].
+pub struct ControlMonitor<'d> {
+    shared: &'d SharedControl<'d>,
+}
+
+impl<'d> ControlMonitor<'d> {
+    fn audio_settings(&self) -> AudioSettings {
+        self.shared.audio_settings.lock(|x| x.get())
+    }
+
+    fn get_logical_channel(&self, search_channel: Channel) -> Option<usize> {
+        let index = self.shared.channels.iter().position(|&c| c == search_channel)?;
+
+        // The logical channels start at one (zero is the master channel).
+        Some(index + 1)
+    }
+
+    /// Get the volume of a selected channel.
+    pub fn volume(&self, channel: Channel) -> Option<Volume> {
+        let channel_index = self.get_logical_channel(channel)?;
+
+        if self.audio_settings().muted[channel_index] {
+            return Some(Volume::Muted);
+        }
+
+        Some(Volume::DeciBel(
+            (self.audio_settings().volume_8q8_db[channel_index] as f32) / 256.0f32,
+        ))
+    }
+
+    /// Get the sample rate of the clock source in Hz.
+    pub fn sample_rate_hz(&self) -> u32 {
+        self.shared.sample_rate_hz.load(Ordering::Relaxed)
+    }
+
+    /// Return a future for when the control settings change.
+    pub async fn changed(&self) {
+        self.shared.changed().await;
+    }
+}
+
+impl<'d> Control<'d> {
+    fn changed(&mut self) {
+        self.shared.changed.store(true, Ordering::Relaxed);
+        self.shared.waker.borrow_mut().wake();
+    }
+
+    fn clock_source_set_request(&mut self, control_selector: u8, data: &[u8]) -> OutResponse {
+        if control_selector != CS_SAM_FREQ_CONTROL || data.len() < 4 {
+            debug!("Unsupported clock source set request for control {}", control_selector);
+            return OutResponse::Rejected;
+        }
+
+        let sample_rate_hz = u32::from_le_bytes(data[..4].try_into().unwrap());
+        if !self.shared.sample_rates_hz.contains(&sample_rate_hz) {
+            debug!("Unsupported sample rate {} Hz", sample_rate_hz);
+            return OutResponse::Rejected;
+        }
+
+        self.shared.sample_rate_hz.store(sample_rate_hz, Ordering::Relaxed);
+        debug!("Set sample rate to {} Hz", sample_rate_hz);
+
+        self.changed();
+        OutResponse::Accepted
+    }
+
+    fn feature_unit_set_request(&mut self, control_selector: u8, channel_index: u8, data: &[u8]) -> OutResponse {
+        if channel_index as usize > MAX_AUDIO_CHANNEL_INDEX {
+            debug!("Unsupported feature unit set request for channel {}", channel_index);
+            return OutResponse::Rejected;
+        }
+
+        let mut audio_settings = self.shared.audio_settings.lock(|x| x.get());
+        match control_selector {
+            FU_MUTE_CONTROL if !data.is_empty() => {
+                let mute_state = data[0] != 0;
+                audio_settings.muted[channel_index as usize] = mute_state;
+                debug!("Set channel {} mute state: {}", channel_index, mute_state);
+            }
+            FU_VOLUME_CONTROL if data.len() >= 2 => {
+                let volume = i16::from_le_bytes([data[0], data[1]]);
+                audio_settings.volume_8q8_db[channel_index as usize] = volume;
+                debug!("Set channel {} volume: {}", channel_index, volume);
+            }
+            _ => return OutResponse::Rejected,
+        }
+
+        // Store updated settings
+        self.shared.audio_settings.lock(|x| x.set(audio_settings));
+
+        self.changed();
+        OutResponse::Accepted
+    }
+
+    fn interface_set_request(&mut self, req: control::Request, data: &[u8]) -> Option<OutResponse> {
+        let interface_number = req.index as u8;
+        let entity_index = (req.index >> 8) as u8;
+        let channel_index = req.value as u8;
+        let control_selector = (req.value >> 8) as u8;
+
+        if interface_number != self.control_interface_number.into() {
+            debug!("Unhandled interface set request for interface {}", interface_number);
+            return None;
+        }
+
+        if req.request != CUR {
+            debug!("Unsupported interface set request type {}", req.request);
+            return Some(OutResponse::Rejected);
+        }
+
+        let response = match entity_index {
+            CLOCK_SOURCE_ID => self.clock_source_set_request(control_selector, data),
+            CLOCK_SELECTOR_ID if control_selector == CX_CLOCK_SELECTOR_CONTROL && data.first() == Some(&1) => {
+                // There is only one clock source to select.
+                OutResponse::Accepted
+            }
+            FEATURE_UNIT_ID => self.feature_unit_set_request(control_selector, channel_index, data),
+            _ => {
+                debug!("Unsupported interface set request for entity {}", entity_index);
+                OutResponse::Rejected
+            }
+        };
+
+        Some(response)
+    }
+
+    fn clock_source_get_request<'r>(&'r mut self, req: Request, buf: &'r mut [u8]) -> InResponse<'r> {
+        let control_selector = (req.value >> 8) as u8;
+
+        match (req.request, control_selector) {
+            (CUR, CS_SAM_FREQ_CONTROL) => {
+                let sample_rate_hz = self.shared.sample_rate_hz.load(Ordering::Relaxed);
+                buf[..4].copy_from_slice(&sample_rate_hz.to_le_bytes());
+                InResponse::Accepted(&buf[..4])
+            }
+            (RANGE, CS_SAM_FREQ_CONTROL) => {
+                // Layout 3 parameter block [UAC2 5.2.3.3], one subrange per discrete sample rate.
+                let rates = self.shared.sample_rates_hz;
+                let rates = &rates[..rates.len().min((buf.len() - 2) / 12)];
+                buf[..2].copy_from_slice(&(rates.len() as u16).to_le_bytes());
+                for (i, rate) in rates.iter().enumerate() {
+                    let subrange = &mut buf[2 + 12 * i..][..12];
+                    subrange[0..4].copy_from_slice(&rate.to_le_bytes()); // dMIN
+                    subrange[4..8].copy_from_slice(&rate.to_le_bytes()); // dMAX
+                    subrange[8..12].copy_from_slice(&0u32.to_le_bytes()); // dRES
+                }
+                InResponse::Accepted(&buf[..2 + 12 * rates.len()])
+            }
+            (CUR, CS_CLOCK_VALID_CONTROL) => {
+                buf[0] = 1; // The internal clock is always valid.
+                InResponse::Accepted(&buf[..1])
+            }
+            _ => InResponse::Rejected,
+        }
+    }
+
+    fn feature_unit_get_request<'r>(&'r mut self, req: Request, buf: &'r mut [u8]) -> InResponse<'r> {
+        let channel_index = req.value as u8;
+        let control_selector = (req.value >> 8) as u8;
+
+        if channel_index as usize > MAX_AUDIO_CHANNEL_INDEX {
+            return InResponse::Rejected;
+        }
+
+        let audio_settings = self.shared.audio_settings.lock(|x| x.get());
+
+        match (req.request, control_selector) {
+            (CUR, FU_MUTE_CONTROL) => {
+                let mute_state = audio_settings.muted[channel_index as usize];
+                buf[0] = mute_state.into();
+                debug!("Got channel {} mute state: {}.", channel_index, mute_state);
+                InResponse::Accepted(&buf[..1])
+            }
+            (CUR, FU_VOLUME_CONTROL) => {
+                let volume = audio_settings.volume_8q8_db[channel_index as usize];
+                buf[..2].copy_from_slice(&volume.to_le_bytes());
+                debug!("Got channel {} volume: {}.", channel_index, volume);
+                InResponse::Accepted(&buf[..2])
+            }
+            (RANGE, FU_VOLUME_CONTROL) => {
+                // Layout 2 parameter block [UAC2 5.2.3.2], a single subrange.
+                buf[0..2].copy_from_slice(&1u16.to_le_bytes()); // wNumSubRanges
+                buf[2..4].copy_from_slice(&(MIN_VOLUME_DB * VOLUME_STEPS_PER_DB).to_le_bytes()); // wMIN
+                buf[4..6].copy_from_slice(&(MAX_VOLUME_DB * VOLUME_STEPS_PER_DB).to_le_bytes()); // wMAX
+                buf[6..8].copy_from_slice(&VOLUME_STEPS_PER_DB.to_le_bytes()); // wRES
+                InResponse::Accepted(&buf[..8])
+            }
+            _ => InResponse::Rejected,
+        }
+    }
+
+    fn interface_get_request<'r>(&'r mut self, req: Request, buf: &'r mut [u8]) -> Option<InResponse<'r>> {
+        let interface_number = req.index as u8;
+        let entity_index = (req.index >> 8) as u8;
+        let control_selector = (req.value >> 8) as u8;
+
+        if interface_number != self.control_interface_number.into() {
+            debug!("Unhandled interface get request for interface {}.", interface_number);
+            return None;
+        }
+
+        let response = match entity_index {
+            CLOCK_SOURCE_ID => self.clock_source_get_request(req, buf),
+            CLOCK_SELECTOR_ID if req.request == CUR && control_selector == CX_CLOCK_SELECTOR_CONTROL => {
+                buf[0] = 1; // The only input pin.
+                InResponse::Accepted(&buf[..1])
+            }
+            FEATURE_UNIT_ID => self.feature_unit_get_request(req, buf),
+            _ => {
+                debug!("Unsupported interface get request for entity {}.", entity_index);
+                InResponse::Rejected
+            }
+        };
+
+        Some(response)
+    }
+}
+
+impl<'d> Handler for Control<'d> {
+    /// Called when a "set alternate setting" control request is done on the interface.
+    fn set_alternate_setting(&mut self, iface: InterfaceNumber, alternate_setting: u8) {
+        debug!(
+            "USB set interface number {} to alt setting {}.",
+            iface, alternate_setting
+        );
+    }
+
+    /// Called after a USB reset after the bus reset sequence is complete.
+    fn reset(&mut self) {
+        let shared = self.shared;
+        shared.audio_settings.lock(|x| x.set(AudioSettings::default()));
+        if let Some(rate) = shared.sample_rates_hz.first() {
+            shared.sample_rate_hz.store(*rate, Ordering::Relaxed);
+        }
+
+        shared.changed.store(true, Ordering::Relaxed);
+        shared.waker.borrow_mut().wake();
+    }
+
+    // Handle control set requests.
+    fn control_out(&mut self, req: control::Request, data: &[u8]) -> Option<OutResponse> {
+        match (req.request_type, req.recipient) {
+            (RequestType::Class, Recipient::Interface) => self.interface_set_request(req, data),
+            _ => None,
+        }
+    }
+
+    // Handle control get requests.
+    fn control_in<'a>(&'a mut self, req: Request, buf: &'a mut [u8]) -> Option<InResponse<'a>> {
+        match (req.request_type, req.recipient) {
+            (RequestType::Class, Recipient::Interface) => self.interface_get_request(req, buf),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,157 @@
+//! Vendor-specific class with WinUSB support.
+//!
+//! This is a plain pair of bulk endpoints on a vendor-specific (`0xFF`) interface, for devices that
+//! talk a custom protocol through libusb, WinUSB or similar.
+//!
+//! The class adds a Microsoft OS 2.0 descriptor set with the `WINUSB` compatible ID and the
+//! device interface GUIDs, which is announced to the host through a BOS platform capability and
+//! served with a vendor request. Windows 8.1 and later then bind the WinUSB driver automatically,
+//! without an INF file or tools like Zadig.
+//!
+//! Windows reads the BOS descriptor only if `bcd_usb` is at least 2.1, which is the default. The
+//! `msos_descriptor_buf` passed to [`Builder::new`] must be large enough for the descriptor set.
+
+use core::mem::MaybeUninit;
+
+use crate::driver::{Driver, Endpoint, EndpointError, EndpointIn, EndpointOut};
+use crate::types::StringIndex;
+use crate::{msos, Builder, Handler};
+
+const USB_CLASS_VENDOR: u8 = 0xFF;
+
+/// Configuration for the vendor class.
+pub struct Config<'d> {
+    /// Name of the interface, shown by the host.
+    pub interface_name: Option<&'d str>,
+
+    /// Device interface GUIDs applications use to find the device with WinUSB, in registry format,
+    /// e.g. `"{EAA9A5DC-30BA-44BC-9232-606CDC875321}"`.
+    ///
+    /// Generate your own GUID, don't reuse the one from the examples.
+    pub device_interface_guids: &'d [&'d str],
+
+    /// Vendor request code used by the host to read the MS OS 2.0 descriptor set.
+    ///
+    /// Only used if the descriptor set header was not already added with
+    /// [`Builder::msos_descriptor`].
+    pub vendor_code: u8,
+
+    /// Max packet size of the bulk endpoints.
+    pub max_packet_size: u16,
+}
+
+/// Internal state for the vendor class.
+pub struct State<'d> {
+    control: MaybeUninit<Control<'d>>,
+}
+
+impl<'d> Default for State<'d> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'d> State<'d> {
+    /// Create a new `State`.
+    pub const fn new() -> Self {
+        Self {
+            control: MaybeUninit::uninit(),
+        }
+    }
+}
+
+struct Control<'d> {
+    iface_string: StringIndex,
+    interface_name: Option<&'d str>,
+}
+
+impl<'d> Handler for Control<'d> {
+    fn get_string(&mut self, index: StringIndex, _lang_id: u16) -> Option<&str> {
+        if index == self.iface_string {
+            self.interface_name
+        } else {
+            None
+        }
+    }
+}
+
+/// Vendor-specific USB class with bulk IN and OUT endpoints, bound to WinUSB on Windows.
+pub struct VendorClass<'d, D: Driver<'d>> {
+    read_ep: D::EndpointOut,
+    write_ep: D::EndpointIn,
+    max_packet_size: u16,
+}
+
+impl<'d, D: Driver<'d>> VendorClass<'d, D> {
+    /// Creates a new `VendorClass` with the provided configuration.
+    ///
+    /// If the MS OS 2.0 descriptor set header was not added yet, it is added for Windows 8.1 and
+    /// later with `config.vendor_code`.
+    pub fn new(builder: &mut Builder<'d, D>, state: &'d mut State<'d>, config: Config<'d>) -> Self {
+        if builder.msos_writer().is_empty() {
+            builder.msos_descriptor(msos::windows_version::WIN8_1, config.vendor_code);
+        }
+
+        let iface_string = builder.string();
+        let mut function = builder.function(USB_CLASS_VENDOR, 0, 0);
+        function.msos_feature(msos::CompatibleIdFeatureDescriptor::new("WINUSB", ""));
+        function.msos_feature(msos::RegistryPropertyFeatureDescriptor::new(
+            "DeviceInterfaceGUIDs",
+            msos::PropertyData::RegMultiSz(config.device_interface_guids),
+        ));
+        let mut interface = function.interface();
+        let iface_name = config.interface_name.map(|_| iface_string);
+        let mut alt = interface.alt_setting(USB_CLASS_VENDOR, 0, 0, iface_name);
+        let read_ep = alt.endpoint_bulk_out(config.max_packet_size);
+        let write_ep = alt.endpoint_bulk_in(config.max_packet_size);
+        drop(function);
+
+        builder.handler(state.control.write(Control {
+            iface_string,
+            interface_name: config.interface_name,
+        }));
+
+        VendorClass {
+            read_ep,
+            write_ep,
+            max_packet_size: config.max_packet_size,
+        }
+    }
+
+    /// Gets the maximum packet size in bytes.
+    pub fn max_packet_size(&self) -> u16 {
+        self.max_packet_size
+    }
+
+    /// Waits for the USB host to enable this interface
+    pub async fn wait_connection(&mut self) {
+        self.read_ep.wait_enabled().await;
+    }
+
+    /// Write a transfer to the host, terminated by a short packet.
+    pub async fn write_packet(&mut self, data: &[u8]) -> Result<(), EndpointError> {
+        for chunk in data.chunks(self.max_packet_size as usize) {
+            self.write_ep.write(chunk).await?;
+        }
+        if data.len() % self.max_packet_size as usize == 0 {
+            self.write_ep.write(&[]).await?;
+        }
+        Ok(())
+    }
+
+    /// Read a transfer from the host, until a short packet is received.
+    ///
+    /// `data` must be large enough for the whole transfer, rounded up to a multiple of the
+    /// max packet size.
+    pub async fn read_packet(&mut self, data: &mut [u8]) -> Result<usize, EndpointError> {
+        let mut n = 0;
+
+        loop {
+            let i = self.read_ep.read(&mut data[n..]).await?;
+            n += i;
+            if i < self.max_packet_size as usize {
+                return Ok(n);
+            }
+        }
+    }
+}
@@ -0,0 +1,108 @@
+//! Async notifications of USB bus state changes.
+//!
+//! [`BusEvents`] lets a task other than the one running [`UsbDevice`](crate::UsbDevice) react to
+//! the bus being reset, suspended and resumed, for example to enter a low-power mode while the
+//! host has suspended the bus.
+
+use core::mem::MaybeUninit;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::signal::Signal;
+
+use crate::driver::Driver;
+use crate::{Builder, Handler};
+
+/// A USB bus state change.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+pub enum BusEvent {
+    /// The host has reset the bus.
+    Reset,
+    /// The host has suspended the bus.
+    ///
+    /// The device must draw less than 2.5 mA from the bus within 10 ms, unless it is self-powered.
+    Suspend,
+    /// The bus has resumed from the suspend state, either by the host or by a remote wakeup.
+    Resume,
+}
+
+/// Internal state for [`BusEvents`].
+pub struct State<'d> {
+    handler: MaybeUninit<EventHandler<'d>>,
+    shared: Shared,
+}
+
+impl<'d> Default for State<'d> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'d> State<'d> {
+    /// Create a new `State`.
+    pub const fn new() -> Self {
+        Self {
+            handler: MaybeUninit::uninit(),
+            shared: Shared {
+                signal: Signal::new(),
+                suspended: AtomicBool::new(false),
+            },
+        }
+    }
+}
+
+struct Shared {
+    signal: Signal<CriticalSectionRawMutex, BusEvent>,
+    suspended: AtomicBool,
+}
+
+struct EventHandler<'d> {
+    shared: &'d Shared,
+}
+
+impl<'d> EventHandler<'d> {
+    fn notify(&mut self, event: BusEvent) {
+        self.shared
+            .suspended
+            .store(event == BusEvent::Suspend, Ordering::Relaxed);
+        self.shared.signal.signal(event);
+    }
+}
+
+impl<'d> Handler for EventHandler<'d> {
+    fn reset(&mut self) {
+        self.notify(BusEvent::Reset);
+    }
+
+    fn suspended(&mut self, suspended: bool) {
+        self.notify(if suspended { BusEvent::Suspend } else { BusEvent::Resume });
+    }
+}
+
+/// Receiver of USB bus state changes.
+pub struct BusEvents<'d> {
+    shared: &'d Shared,
+}
+
+impl<'d> BusEvents<'d> {
+    /// Create a new `BusEvents`, registering its handler with the builder.
+    pub fn new<D: Driver<'d>>(builder: &mut Builder<'d, D>, state: &'d mut State<'d>) -> Self {
+        let shared = &state.shared;
+        builder.handler(state.handler.write(EventHandler { shared }));
+        Self { shared }
+    }
+
+    /// Wait for the next bus state change.
+    ///
+    /// Only the latest event is kept, if several events happen before this is called only the
+    /// last one is returned. Use [`BusEvents::is_suspended`] to get the current state.
+    pub async fn wait(&self) -> BusEvent {
+        self.shared.signal.wait().await
+    }
+
+    /// Returns whether the bus is currently suspended.
+    pub fn is_suspended(&self) -> bool {
+        self.shared.suspended.load(Ordering::Relaxed)
+    }
+}
@@ -12,6 +12,7 @@ pub mod class;
 pub mod control;
 pub mod descriptor;
 mod descriptor_reader;
+pub mod events;
 pub mod msos;
 pub mod types;
 
@@ -20,7 +21,12 @@ mod config {
     include!(concat!(env!("OUT_DIR"), "/config.rs"));
 }
 
-use embassy_futures::select::{select, Either};
+use core::cell::{Cell, RefCell};
+use core::future::{poll_fn, Future};
+use core::task::Poll;
+
+use embassy_futures::select::{select3, Either3};
+use embassy_sync::waitqueue::WakerRegistration;
 use heapless::Vec;
 
 pub use crate::builder::{Builder, Config, FunctionBuilder, InterfaceAltBuilder, InterfaceBuilder, UsbVersion};
@@ -178,6 +184,41 @@ pub struct UsbBufferReport {
     pub control_buffer_size: usize,
 }
 
+/// Endpoint stall requests from a class, applied to the bus by [`UsbDevice::run()`].
+///
+/// Classes only own their endpoints, while setting the STALL condition is done through the bus.
+pub(crate) struct StallSignal {
+    /// Bit `n` is OUT endpoint `n`, bit `16 + n` is IN endpoint `n`.
+    pending: Cell<u32>,
+    waker: RefCell<WakerRegistration>,
+}
+
+impl StallSignal {
+    pub(crate) const fn new() -> Self {
+        Self {
+            pending: Cell::new(0),
+            waker: RefCell::new(WakerRegistration::new()),
+        }
+    }
+
+    /// Requests the STALL condition to be set on `ep_addr`.
+    ///
+    /// The condition is cleared by the host with a CLEAR_FEATURE(ENDPOINT_HALT) request.
+    pub(crate) fn stall(&self, ep_addr: EndpointAddress) {
+        self.pending.set(self.pending.get() | Self::bit(ep_addr));
+        self.waker.borrow_mut().wake();
+    }
+
+    fn bit(ep_addr: EndpointAddress) -> u32 {
+        let shift = if ep_addr.is_in() { 16 } else { 0 };
+        1 << (ep_addr.index() + shift)
+    }
+
+    fn take(&self) -> u32 {
+        self.pending.replace(0)
+    }
+}
+
 /// Main struct for the USB device stack.
 pub struct UsbDevice<'d, D: Driver<'d>> {
     control_buf: &'d mut [u8],
@@ -209,6 +250,7 @@ struct Inner<'d, D: Driver<'d>> {
 
     interfaces: Vec<Interface, MAX_INTERFACE_COUNT>,
     handlers: Vec<&'d mut dyn Handler, MAX_HANDLER_COUNT>,
+    stall_signals: Vec<&'d StallSignal, MAX_HANDLER_COUNT>,
 }
 
 impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
@@ -216,6 +258,7 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
         driver: D,
         config: Config<'d>,
         handlers: Vec<&'d mut dyn Handler, MAX_HANDLER_COUNT>,
+        stall_signals: Vec<&'d StallSignal, MAX_HANDLER_COUNT>,
         config_descriptor: &'d [u8],
         bos_descriptor: &'d [u8],
         msos_descriptor: crate::msos::MsOsDescriptorSet<'d>,
@@ -248,6 +291,7 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
                 set_address_pending: false,
                 interfaces,
                 handlers,
+                stall_signals,
             },
         }
     }
@@ -287,9 +331,11 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
         while !self.inner.suspended {
             let control_fut = self.control.setup();
             let bus_fut = self.inner.bus.poll();
-            match select(bus_fut, control_fut).await {
-                Either::First(evt) => self.inner.handle_bus_event(evt).await,
-                Either::Second(req) => self.handle_control(req).await,
+            let stall_fut = wait_stall_requested(&self.inner.stall_signals);
+            match select3(bus_fut, control_fut, stall_fut).await {
+                Either3::First(evt) => self.inner.handle_bus_event(evt).await,
+                Either3::Second(req) => self.handle_control(req).await,
+                Either3::Third(()) => self.inner.apply_stall_requests(),
             }
         }
     }
@@ -440,7 +486,38 @@ impl<'d, D: Driver<'d>> UsbDevice<'d, D> {
     }
 }
 
+fn wait_stall_requested<'a>(signals: &'a [&'a StallSignal]) -> impl Future<Output = ()> + 'a {
+    poll_fn(move |cx| {
+        let mut requested = false;
+        for signal in signals {
+            requested |= signal.pending.get() != 0;
+            signal.waker.borrow_mut().register(cx.waker());
+        }
+        if requested {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    })
+}
+
 impl<'d, D: Driver<'d>> Inner<'d, D> {
+    fn apply_stall_requests(&mut self) {
+        for signal in &self.stall_signals {
+            let pending = signal.take();
+            for i in 0..16 {
+                if pending & (1 << i) != 0 {
+                    self.bus
+                        .endpoint_set_stalled(EndpointAddress::from_parts(i, Direction::Out), true);
+                }
+                if pending & (1 << (16 + i)) != 0 {
+                    self.bus
+                        .endpoint_set_stalled(EndpointAddress::from_parts(i, Direction::In), true);
+                }
+            }
+        }
+    }
+
     async fn handle_bus_event(&mut self, evt: Event) {
         match evt {
             Event::Reset => {
@@ -11,6 +11,7 @@ use embassy_time::Timer;
 use static_cell::StaticCell;
 use {defmt_rtt as _, panic_probe as _};
 
+#[derive(defmt::Format)]
 enum LedState {
     On,
     Off,
@@ -21,9 +22,9 @@ static CHANNEL: StaticCell<Channel<NoopRawMutex, LedState, 1>> = StaticCell::new
 #[embassy_executor::task]
 async fn send_task(sender: Sender<'static, NoopRawMutex, LedState, 1>) {
     loop {
-        sender.send(LedState::On).await;
+        unwrap!(sender.send(LedState::On).await);
         Timer::after_secs(1).await;
-        sender.send(LedState::Off).await;
+        unwrap!(sender.send(LedState::Off).await);
         Timer::after_secs(1).await;
     }
 }
@@ -33,7 +34,7 @@ async fn recv_task(led: Peri<'static, AnyPin>, receiver: Receiver<'static, NoopR
     let mut led = Output::new(led, Level::Low, OutputDrive::Standard);
 
     loop {
-        match receiver.receive().await {
+        match unwrap!(receiver.receive().await) {
             LedState::On => led.set_low(),
             LedState::Off => led.set_high(),
         }
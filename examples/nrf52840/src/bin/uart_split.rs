@@ -45,7 +45,7 @@ async fn main(spawner: Spawner) {
     // back out the buffer we receive from the read
     // task.
     loop {
-        let buf = CHANNEL.receive().await;
+        let buf = unwrap!(CHANNEL.receive().await);
         info!("writing...");
         unwrap!(tx.write(&buf).await);
     }
@@ -57,6 +57,6 @@ async fn reader(mut rx: UarteRx<'static, UARTE0>) {
     loop {
         info!("reading...");
         unwrap!(rx.read(&mut buf).await);
-        CHANNEL.send(buf).await;
+        unwrap!(CHANNEL.send(buf).await);
     }
 }
@@ -71,7 +71,7 @@ async fn main(spawner: Spawner) {
 async fn processing(avg: &'static Cell<u32>) {
     let mut buffer: heapless::HistoryBuffer<u16, 100> = Default::default();
     loop {
-        let val = ADC_VALUES.receive().await;
+        let val = unwrap!(ADC_VALUES.receive().await);
         buffer.write(val);
         let sum: u32 = buffer.iter().map(|x| *x as u32).sum();
         avg.set(sum / buffer.len() as u32);
@@ -48,6 +48,7 @@ bind_interrupts!(struct Irqs {
 });
 
 /// Events that worker tasks send to the orchestrator
+#[derive(Format)]
 enum Events {
     UsbPowered(bool),      // USB connection state changed
     VsysVoltage(f32),      // New voltage reading
@@ -145,7 +146,7 @@ async fn orchestrate(spawner: Spawner) {
 
     loop {
         // Do nothing until we receive any event
-        let event = receiver.receive().await;
+        let event = unwrap!(receiver.receive().await);
 
         // Scope in which we want to lock the system state. As an alternative we could also call `drop` on the state
         {
@@ -190,7 +191,7 @@ async fn orchestrate(spawner: Spawner) {
                 max if max == state.maximum_times_we_want_first_random_seed => {
                     info!("Stopping the first random signal task");
                     STOP_FIRST_RANDOM_SIGNAL.signal(Commands::Stop);
-                    EVENT_CHANNEL.sender().send(Events::ResetFirstRandomSeed).await;
+                    unwrap!(EVENT_CHANNEL.sender().send(Events::ResetFirstRandomSeed).await);
                 }
                 0 => {
                     let respawn_first_random_seed_task = !state.first_random_seed_task_running;
@@ -249,7 +250,7 @@ async fn random_30s(_spawner: Spawner) {
             Either::First(_) => {
                 info!("30s are up, generating random number");
                 let random_number = rng.next_u32();
-                sender.send(Events::FirstRandomSeed(random_number)).await;
+                unwrap!(sender.send(Events::FirstRandomSeed(random_number)).await);
             }
             Either::Second(_) => {
                 info!("Received signal to stop, goodbye!");
@@ -272,7 +273,7 @@ async fn random_60s(_spawner: Spawner) {
     loop {
         Timer::after(Duration::from_secs(60)).await;
         let random_number = rng.next_u32();
-        sender.send(Events::SecondRandomSeed(random_number)).await;
+        unwrap!(sender.send(Events::SecondRandomSeed(random_number)).await);
     }
 }
 
@@ -285,7 +286,7 @@ async fn random_90s(_spawner: Spawner) {
     loop {
         Timer::after(Duration::from_secs(90)).await;
         let random_number = rng.next_u32();
-        sender.send(Events::ThirdRandomSeed(random_number)).await;
+        unwrap!(sender.send(Events::ThirdRandomSeed(random_number)).await);
     }
 }
 
@@ -296,7 +297,7 @@ pub async fn usb_power(_spawner: Spawner, r: Vbus) {
     let sender = EVENT_CHANNEL.sender();
 
     loop {
-        sender.send(Events::UsbPowered(vbus_in.is_high())).await;
+        unwrap!(sender.send(Events::UsbPowered(vbus_in.is_high())).await);
         vbus_in.wait_for_any_edge().await;
     }
 }
@@ -313,6 +314,6 @@ pub async fn vsys_voltage(_spawner: Spawner, r: Vsys) {
         Timer::after(Duration::from_secs(30)).await;
         let adc_value = adc.read(&mut channel).await.unwrap();
         let voltage = (adc_value as f32) * 3.3 * 3.0 / 4096.0;
-        sender.send(Events::VsysVoltage(voltage)).await;
+        unwrap!(sender.send(Events::VsysVoltage(voltage)).await);
     }
 }
@@ -20,6 +20,7 @@ static EXECUTOR0: StaticCell<Executor> = StaticCell::new();
 static EXECUTOR1: StaticCell<Executor> = StaticCell::new();
 static CHANNEL: Channel<CriticalSectionRawMutex, LedState, 1> = Channel::new();
 
+#[derive(Format)]
 enum LedState {
     On,
     Off,
@@ -47,9 +48,9 @@ fn main() -> ! {
 async fn core0_task() {
     info!("Hello from core 0");
     loop {
-        CHANNEL.send(LedState::On).await;
+        unwrap!(CHANNEL.send(LedState::On).await);
         Timer::after_millis(100).await;
-        CHANNEL.send(LedState::Off).await;
+        unwrap!(CHANNEL.send(LedState::Off).await);
         Timer::after_millis(400).await;
     }
 }
@@ -58,7 +59,7 @@ async fn core0_task() {
 async fn core1_task(mut led: Output<'static>) {
     info!("Hello from core 1");
     loop {
-        match CHANNEL.receive().await {
+        match unwrap!(CHANNEL.receive().await) {
             LedState::On => led.set_high(),
             LedState::Off => led.set_low(),
         }
@@ -49,11 +49,11 @@ impl<'a> Leds<'a> {
         self.leds[self.current_led].set_high();
         if let Ok(new_message) = with_timeout(Duration::from_millis(500), CHANNEL.receive()).await {
             self.leds[self.current_led].set_low();
-            self.process_event(new_message).await;
+            self.process_event(unwrap!(new_message)).await;
         } else {
             self.leds[self.current_led].set_low();
             if let Ok(new_message) = with_timeout(Duration::from_millis(200), CHANNEL.receive()).await {
-                self.process_event(new_message).await;
+                self.process_event(unwrap!(new_message)).await;
             }
         }
     }
@@ -136,17 +136,17 @@ async fn button_waiter(mut button: ExtiInput<'static>) {
             .is_err()
         {
             info!("Hold");
-            CHANNEL.send(ButtonEvent::Hold).await;
+            unwrap!(CHANNEL.send(ButtonEvent::Hold).await);
             button.wait_for_falling_edge().await;
         } else if with_timeout(Duration::from_millis(DOUBLE_CLICK_DELAY), button.wait_for_rising_edge())
             .await
             .is_err()
         {
             info!("Single click");
-            CHANNEL.send(ButtonEvent::SingleClick).await;
+            unwrap!(CHANNEL.send(ButtonEvent::SingleClick).await);
         } else {
             info!("Double click");
-            CHANNEL.send(ButtonEvent::DoubleClick).await;
+            unwrap!(CHANNEL.send(ButtonEvent::DoubleClick).await);
             button.wait_for_falling_edge().await;
         }
         button.wait_for_rising_edge().await;
@@ -64,7 +64,7 @@ async fn main(_spawner: Spawner) {
     let mut config = embassy_usb::Config::new(0xc0de, 0xcafe);
     config.manufacturer = Some("Embassy");
     config.product = Some("USB-serial example");
-    config.serial_number = Some("12345678");
+    config.serial_number = Some(usb::serial_number());
 
     // Create embassy-usb DeviceBuilder using the driver and config.
     // It needs some buffers for building the descriptors.
@@ -30,7 +30,7 @@ async fn main(spawner: Spawner) -> ! {
     unwrap!(spawner.spawn(reader(rx)));
 
     loop {
-        let buf = CHANNEL.receive().await;
+        let buf = unwrap!(CHANNEL.receive().await);
         info!("writing...");
         unwrap!(tx.write(&buf).await);
     }
@@ -42,6 +42,6 @@ async fn reader(mut rx: UartRx<'static, Async>) {
     loop {
         info!("reading...");
         unwrap!(rx.read(&mut buf).await);
-        CHANNEL.send(buf).await;
+        unwrap!(CHANNEL.send(buf).await);
     }
 }
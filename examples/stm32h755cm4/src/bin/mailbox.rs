@@ -0,0 +1,79 @@
+//! Counterpart of `stm32h755cm7/src/bin/mailbox.rs`, blinks the LED as asked by the Cortex-M7, and
+//! spawns the tasks it requests.
+
+#![no_std]
+#![no_main]
+
+use core::mem::MaybeUninit;
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_futures::select::{select, Either};
+use embassy_stm32::gpio::{Level, Output, Speed};
+use embassy_stm32::hsem::{self, HardwareSemaphore, Mailbox, RemoteSpawner};
+use embassy_stm32::{bind_interrupts, peripherals, SharedData};
+use embassy_time::{Duration, Ticker, Timer};
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    HSEM2 => hsem::InterruptHandler<peripherals::HSEM>;
+});
+
+#[unsafe(link_section = ".ram_d3.shared_data")]
+static SHARED_DATA: MaybeUninit<SharedData> = MaybeUninit::uninit();
+#[unsafe(link_section = ".ram_d3.to_cm4")]
+static TO_CM4: MaybeUninit<Mailbox<u32, 4>> = MaybeUninit::uninit();
+#[unsafe(link_section = ".ram_d3.to_cm7")]
+static TO_CM7: MaybeUninit<Mailbox<u32, 4>> = MaybeUninit::uninit();
+#[unsafe(link_section = ".ram_d3.cm4_spawner")]
+static CM4_SPAWNER: MaybeUninit<RemoteSpawner<u32, 2>> = MaybeUninit::uninit();
+
+#[embassy_executor::task]
+async fn heartbeat(period_secs: u32) {
+    loop {
+        info!("Cortex-M4 alive");
+        Timer::after_secs(period_secs as u64).await;
+    }
+}
+
+#[embassy_executor::task]
+async fn spawn_requests(spawner: Spawner, requests: &'static RemoteSpawner<u32, 2>) {
+    requests
+        .run(|period_secs| {
+            if spawner.spawn(heartbeat(period_secs)).is_err() {
+                warn!("heartbeat already running");
+            }
+        })
+        .await
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    let p = embassy_stm32::init_secondary(&SHARED_DATA);
+    let _hsem = HardwareSemaphore::new_with_interrupt(p.HSEM, Irqs);
+    let from_cm7 = Mailbox::get(&TO_CM4);
+    let to_cm7 = Mailbox::get(&TO_CM7);
+    let requests = RemoteSpawner::get(&CM4_SPAWNER);
+    info!("Hello World!");
+
+    unwrap!(spawner.spawn(spawn_requests(spawner, requests)));
+
+    let mut led = Output::new(p.PE1, Level::High, Speed::Low);
+    let mut ticker = Ticker::every(Duration::from_millis(500));
+    let mut count = 0;
+
+    loop {
+        match select(from_cm7.receive(), ticker.next()).await {
+            Either::First(period_ms) => {
+                info!("blinking every {} ms", period_ms);
+                ticker = Ticker::every(Duration::from_millis(period_ms as u64));
+            }
+            Either::Second(()) => {
+                led.toggle();
+                count += 1;
+                // Drop the report rather than waiting if the Cortex-M7 falls behind.
+                let _ = to_cm7.try_send(count);
+            }
+        }
+    }
+}
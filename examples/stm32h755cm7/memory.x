@@ -10,6 +10,9 @@ SECTIONS
     .ram_d3 :
     {
         *(.ram_d3.shared_data)
+        *(.ram_d3.to_cm4)
+        *(.ram_d3.to_cm7)
+        *(.ram_d3.cm4_spawner)
         *(.ram_d3)
     } > RAM_D3
 }
\ No newline at end of file
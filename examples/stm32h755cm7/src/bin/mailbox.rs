@@ -0,0 +1,84 @@
+//! Passes messages between the cores with hardware semaphore backed mailboxes.
+//!
+//! Flash `stm32h755cm4/src/bin/mailbox.rs` to the Cortex-M4 as well. The Cortex-M7 tells the
+//! Cortex-M4 how fast to blink its LED, and the Cortex-M4 reports each blink back. The Cortex-M7
+//! also has the Cortex-M4 spawn a heartbeat task.
+
+#![no_std]
+#![no_main]
+
+use core::mem::MaybeUninit;
+
+use defmt::*;
+use embassy_executor::Spawner;
+use embassy_stm32::hsem::{self, HardwareSemaphore, Mailbox, RemoteSpawner};
+use embassy_stm32::{bind_interrupts, peripherals, SharedData};
+use embassy_time::Timer;
+use {defmt_rtt as _, panic_probe as _};
+
+bind_interrupts!(struct Irqs {
+    HSEM1 => hsem::InterruptHandler<peripherals::HSEM>;
+});
+
+#[unsafe(link_section = ".ram_d3.shared_data")]
+static SHARED_DATA: MaybeUninit<SharedData> = MaybeUninit::uninit();
+#[unsafe(link_section = ".ram_d3.to_cm4")]
+static TO_CM4: MaybeUninit<Mailbox<u32, 4>> = MaybeUninit::uninit();
+#[unsafe(link_section = ".ram_d3.to_cm7")]
+static TO_CM7: MaybeUninit<Mailbox<u32, 4>> = MaybeUninit::uninit();
+#[unsafe(link_section = ".ram_d3.cm4_spawner")]
+static CM4_SPAWNER: MaybeUninit<RemoteSpawner<u32, 2>> = MaybeUninit::uninit();
+
+#[embassy_executor::task]
+async fn blink_reports(from_cm4: &'static Mailbox<u32, 4>) {
+    loop {
+        let count = from_cm4.receive().await;
+        info!("Cortex-M4 blinked {} times", count);
+    }
+}
+
+#[embassy_executor::main]
+async fn main(spawner: Spawner) {
+    // The mailboxes must be ready before the Cortex-M4 finishes its init.
+    let to_cm4 = Mailbox::init(&TO_CM4, 1);
+    let to_cm7 = Mailbox::init(&TO_CM7, 2);
+    let cm4 = RemoteSpawner::init(&CM4_SPAWNER, 3);
+
+    let mut config = embassy_stm32::Config::default();
+    {
+        use embassy_stm32::rcc::*;
+        config.rcc.hsi = Some(HSIPrescaler::DIV1);
+        config.rcc.csi = true;
+        config.rcc.pll1 = Some(Pll {
+            source: PllSource::HSI,
+            prediv: PllPreDiv::DIV4,
+            mul: PllMul::MUL50,
+            divp: Some(PllDiv::DIV2),
+            divq: Some(PllDiv::DIV8), // 100mhz
+            divr: None,
+        });
+        config.rcc.sys = Sysclk::PLL1_P; // 400 Mhz
+        config.rcc.ahb_pre = AHBPrescaler::DIV2; // 200 Mhz
+        config.rcc.apb1_pre = APBPrescaler::DIV2; // 100 Mhz
+        config.rcc.apb2_pre = APBPrescaler::DIV2; // 100 Mhz
+        config.rcc.apb3_pre = APBPrescaler::DIV2; // 100 Mhz
+        config.rcc.apb4_pre = APBPrescaler::DIV2; // 100 Mhz
+        config.rcc.voltage_scale = VoltageScale::Scale1;
+        config.rcc.supply_config = SupplyConfig::DirectSMPS;
+    }
+    let p = embassy_stm32::init_primary(config, &SHARED_DATA);
+    let _hsem = HardwareSemaphore::new_with_interrupt(p.HSEM, Irqs);
+    info!("Hello World!");
+
+    unwrap!(spawner.spawn(blink_reports(to_cm7)));
+    // Have the Cortex-M4 spawn its heartbeat task, logging every 2 seconds.
+    cm4.spawn(2).await;
+
+    let mut period_ms = 100;
+    loop {
+        info!("asking the Cortex-M4 to blink every {} ms", period_ms);
+        to_cm4.send(period_ms).await;
+        Timer::after_secs(5).await;
+        period_ms = if period_ms >= 800 { 100 } else { period_ms * 2 };
+    }
+}